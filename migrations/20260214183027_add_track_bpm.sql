@@ -0,0 +1,2 @@
+ALTER TABLE track ADD COLUMN bpm INTEGER;
+ALTER TABLE track ADD COLUMN bpm_estimated INTEGER DEFAULT 0 NOT NULL;
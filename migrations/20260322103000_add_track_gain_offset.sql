@@ -0,0 +1 @@
+ALTER TABLE track ADD COLUMN gain_offset REAL DEFAULT 0 NOT NULL;
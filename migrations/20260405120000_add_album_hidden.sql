@@ -0,0 +1 @@
+ALTER TABLE album ADD COLUMN hidden INTEGER DEFAULT 0 NOT NULL;
@@ -0,0 +1,15 @@
+-- how the scanner decided which files belong to the album:
+-- 0: album title and MusicBrainz release ID, 1: album title only, 2: grouped by hand
+ALTER TABLE album ADD COLUMN match_basis INTEGER DEFAULT 1 NOT NULL;
+
+UPDATE album SET match_basis = 0 WHERE mbid != 'none';
+
+-- set on tracks that were moved to another album by hand, so that rescans leave them there
+ALTER TABLE track ADD COLUMN album_locked INTEGER DEFAULT 0 NOT NULL;
+
+-- albums split off by hand keep the title, artist and release ID of the album they came from, so
+-- they're left out of the index the scanner uses to find albums
+DROP INDEX album_title_artist_mbid;
+
+CREATE UNIQUE INDEX IF NOT EXISTS album_title_artist_mbid ON album (title, artist_id, mbid)
+    WHERE match_basis != 2;
@@ -0,0 +1,29 @@
+-- the liked songs playlist used to be assumed to have ID 1. from now on, it's the only playlist
+-- with type 1 (system)
+
+-- playlist 1 is only taken to be the liked songs playlist if it still has the built-in name
+UPDATE playlist SET type = 1
+    WHERE id = 1 AND name = 'Liked Songs'
+    AND NOT EXISTS (SELECT 1 FROM playlist WHERE type = 1);
+
+UPDATE playlist SET type = 0
+    WHERE type = 1 AND id != (SELECT MIN(id) FROM playlist WHERE type = 1);
+
+INSERT INTO playlist (name, type)
+    SELECT CASE
+            WHEN EXISTS (SELECT 1 FROM playlist WHERE name = 'Liked Songs') THEN 'Liked Songs (2)'
+            ELSE 'Liked Songs'
+        END, 1
+    WHERE NOT EXISTS (SELECT 1 FROM playlist WHERE type = 1);
+
+CREATE UNIQUE INDEX IF NOT EXISTS playlist_single_system ON playlist (type) WHERE type = 1;
+
+-- checks that should be shown to the user once, the next time the app is started
+CREATE TABLE IF NOT EXISTS pending_check (
+    name TEXT PRIMARY KEY NOT NULL
+);
+
+-- if playlist 1 is a regular playlist, liked songs have been added to it, so the user gets to
+-- pick which playlist holds their liked songs
+INSERT INTO pending_check (name)
+    SELECT 'liked_playlist' WHERE EXISTS (SELECT 1 FROM playlist WHERE id = 1 AND type = 0);
@@ -0,0 +1,11 @@
+ALTER TABLE track ADD COLUMN comment TEXT;
+ALTER TABLE track ADD COLUMN comment_edited INTEGER DEFAULT 0 NOT NULL;
+
+-- comments used to only be kept with the other extended tags
+UPDATE track SET comment = (
+    SELECT json_extract(tag.value, '$[1]')
+    FROM json_each(track.extra_tags) AS tag
+    WHERE json_extract(tag.value, '$[0]') = 'COMMENT'
+    LIMIT 1
+)
+WHERE extra_tags IS NOT NULL AND json_valid(extra_tags);
@@ -0,0 +1,3 @@
+-- a hash of the album art as read from the file, before it was resized, so that changes to the
+-- art can be noticed on rescans without loading the stored image
+ALTER TABLE album ADD COLUMN image_hash BLOB;
@@ -0,0 +1,3 @@
+-- the position of the track in its album when sorted by file name, for albums whose files aren't
+-- tagged with track numbers. filled in by the scanner
+ALTER TABLE track ADD COLUMN fallback_order INTEGER;
@@ -0,0 +1,4 @@
+-- where the album's art came from:
+-- 0: unknown, 1: embedded in the files, 2: image in the album's folder, 3: override image,
+-- 4: downloaded, 5: chosen by hand
+ALTER TABLE album ADD COLUMN art_source INTEGER DEFAULT 0 NOT NULL;
@@ -0,0 +1,6 @@
+-- the loudness of tracks without ReplayGain tags, measured by the scanner when loudness analysis
+-- is enabled. loudness_analyzed is set once a track has been looked at, even if it turned out to
+-- have ReplayGain tags or couldn't be measured, so that it isn't looked at again
+ALTER TABLE track ADD COLUMN loudness REAL;
+ALTER TABLE track ADD COLUMN loudness_peak REAL;
+ALTER TABLE track ADD COLUMN loudness_analyzed INTEGER DEFAULT 0 NOT NULL;
@@ -0,0 +1,3 @@
+-- the exact length of each track, in milliseconds. duration keeps the length in whole seconds,
+-- for tracks that haven't been scanned since this was added (where duration_ms is NULL)
+ALTER TABLE track ADD COLUMN duration_ms INTEGER;
@@ -0,0 +1,3 @@
+-- whether the track's embedded album art was skipped while scanning because it was too large
+-- (see MAX_EMBEDDED_ART_SIZE in src/library/scan.rs)
+ALTER TABLE track ADD COLUMN art_oversized INTEGER DEFAULT 0 NOT NULL;
@@ -0,0 +1,3 @@
+-- whether the track's codec is lossless (like FLAC) or lossy (like MP3), or NULL if it's unknown or
+-- the track hasn't been scanned since this was added
+ALTER TABLE track ADD COLUMN lossless INTEGER;
@@ -0,0 +1,9 @@
+-- when a track was last added to or removed from the playlist, used to list recently used
+-- playlists first; playlists that haven't changed since this was added use their newest item (or
+-- when they were created, if they're empty)
+ALTER TABLE playlist ADD COLUMN last_modified DATETIME;
+
+UPDATE playlist SET last_modified = COALESCE(
+    (SELECT MAX(created_at) FROM playlist_item WHERE playlist_item.playlist_id = playlist.id),
+    created_at
+);
@@ -0,0 +1,4 @@
+-- when the album was first released, for reissues and remasters that are tagged with it. Like the
+-- release date, only one of these is filled in: the year is used when the tag isn't a full date
+ALTER TABLE album ADD COLUMN original_date DATETIME;
+ALTER TABLE album ADD COLUMN original_year INTEGER;
@@ -0,0 +1 @@
+DELETE FROM pending_check WHERE name = $1;
@@ -0,0 +1,2 @@
+DELETE FROM album
+    WHERE id = $1 AND NOT EXISTS (SELECT 1 FROM track WHERE album_id = $1);
@@ -5,10 +5,13 @@ SELECT
     artist_id,
     release_date,
     release_year,
+    original_date,
+    original_year,
     created_at,
     label,
     catalog_number,
     isrc,
-    vinyl_numbering
+    vinyl_numbering,
+    hidden
 FROM album
 WHERE id = $1;
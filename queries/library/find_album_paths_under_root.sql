@@ -0,0 +1,2 @@
+SELECT album_id, disc_num, path FROM album_path
+WHERE substr(path, 1, length($1)) = $1;
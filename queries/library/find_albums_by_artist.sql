@@ -0,0 +1,24 @@
+SELECT
+    p.id,
+    p.title,
+    a.name AS artist_name,
+    -- the year the album first came out if $3 is true and it's known, otherwise the year of the
+    -- release, like album_query::year
+    COALESCE(
+        IIF($3, COALESCE(CAST(strftime('%Y', p.original_date) AS INTEGER), p.original_year), NULL),
+        CAST(strftime('%Y', p.release_date) AS INTEGER),
+        p.release_year,
+        CAST(strftime('%Y', p.original_date) AS INTEGER),
+        p.original_year
+    ) AS year
+FROM
+    album p
+    JOIN artist a ON p.artist_id = a.id
+WHERE
+    p.artist_id = $1
+    AND p.id != $2
+    AND NOT p.hidden
+ORDER BY
+    year IS NULL,
+    year ASC,
+    p.title_sortable COLLATE NOCASE ASC;
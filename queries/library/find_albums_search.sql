@@ -1,6 +1,13 @@
 SELECT
     p.id,
     p.title,
-    a.name
+    a.name,
+    (
+        SELECT group_concat(t.comment, ' ')
+        FROM track t
+        WHERE t.album_id = p.id AND t.comment IS NOT NULL
+    ) AS comments
 FROM
-    album p JOIN artist a ON p.artist_id = a.id;
+    album p JOIN artist a ON p.artist_id = a.id
+WHERE
+    NOT p.hidden;
@@ -1,7 +0,0 @@
-SELECT
-    id,
-    title_sortable
-FROM
-    album
-ORDER BY
-    title_sortable COLLATE NOCASE DESC;
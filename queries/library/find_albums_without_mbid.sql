@@ -0,0 +1,15 @@
+SELECT
+    p.id,
+    p.title,
+    a.name AS artist_name,
+    COALESCE(CAST(strftime('%Y', p.release_date) AS INTEGER), p.release_year) AS year,
+    (SELECT path FROM album_path WHERE album_id = p.id ORDER BY disc_num LIMIT 1) AS folder
+FROM
+    album p
+    JOIN artist a ON p.artist_id = a.id
+WHERE
+    p.mbid = 'none'
+    AND NOT p.hidden
+ORDER BY
+    a.name_sortable COLLATE NOCASE ASC,
+    p.title_sortable COLLATE NOCASE ASC;
@@ -0,0 +1,2 @@
+SELECT id, location, folder FROM track
+WHERE substr(location, 1, length($1)) = $1;
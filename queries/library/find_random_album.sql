@@ -0,0 +1,4 @@
+SELECT id FROM album
+WHERE id != $1 AND NOT hidden
+ORDER BY RANDOM()
+LIMIT 1;
@@ -0,0 +1,2 @@
+SELECT gain_offset FROM track
+WHERE location = $1;
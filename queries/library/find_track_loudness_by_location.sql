@@ -0,0 +1,2 @@
+SELECT loudness, loudness_peak FROM track
+WHERE location = $1 AND loudness IS NOT NULL AND loudness_peak IS NOT NULL;
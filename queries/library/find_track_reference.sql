@@ -0,0 +1,6 @@
+SELECT NULLIF(a.mbid, 'none') AS album_mbid, t.disc_number, t.track_number, ar.name AS artist,
+       a.title AS album, t.title
+    FROM track t
+    LEFT JOIN album a ON a.id = t.album_id
+    LEFT JOIN artist ar ON ar.id = a.artist_id
+    WHERE t.id = $1;
@@ -6,7 +6,11 @@ SELECT
 FROM
     track t
     LEFT JOIN album al ON t.album_id = al.id
+WHERE
+    NOT COALESCE(al.hidden, FALSE)
 ORDER BY
     al.title_sortable COLLATE NOCASE DESC,
     t.disc_number ASC,
-    t.track_number ASC;
+    t.track_number IS NULL,
+    t.track_number ASC,
+    t.fallback_order ASC;
@@ -7,8 +7,12 @@ FROM
     track t
     LEFT JOIN album al ON t.album_id = al.id
     LEFT JOIN artist ar ON al.artist_id = ar.id
+WHERE
+    NOT COALESCE(al.hidden, FALSE)
 ORDER BY
     ar.name_sortable COLLATE NOCASE ASC,
     al.title_sortable COLLATE NOCASE ASC,
     t.disc_number ASC,
-    t.track_number ASC;
+    t.track_number IS NULL,
+    t.track_number ASC,
+    t.fallback_order ASC;
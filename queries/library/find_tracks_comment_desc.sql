@@ -0,0 +1,14 @@
+SELECT
+    t.id,
+    t.title_sortable,
+    t.album_id,
+    t.location
+FROM
+    track t
+    LEFT JOIN album al ON t.album_id = al.id
+WHERE
+    NOT COALESCE(al.hidden, FALSE)
+ORDER BY
+    t.comment IS NULL,
+    t.comment COLLATE NOCASE DESC,
+    t.title_sortable COLLATE NOCASE ASC;
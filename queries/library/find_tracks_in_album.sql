@@ -1,3 +1,4 @@
 SELECT * FROM track
 WHERE album_id = $1
-ORDER BY disc_number ASC, track_number ASC;
+-- tracks without a track number come after the rest, in the order of their file names
+ORDER BY disc_number ASC, track_number IS NULL, track_number ASC, fallback_order ASC;
@@ -5,5 +5,8 @@ SELECT
     t.location
 FROM
     track t
+    LEFT JOIN album al ON t.album_id = al.id
+WHERE
+    NOT COALESCE(al.hidden, FALSE)
 ORDER BY
-    t.duration DESC;
+    COALESCE(t.duration_ms, t.duration * 1000) DESC;
@@ -5,6 +5,9 @@ SELECT
     t.location
 FROM
     track t
+    LEFT JOIN album al ON t.album_id = al.id
+WHERE
+    NOT COALESCE(al.hidden, FALSE)
 ORDER BY
     t.disc_number DESC,
     t.track_number DESC;
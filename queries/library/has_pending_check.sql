@@ -0,0 +1 @@
+SELECT EXISTS (SELECT 1 FROM pending_check WHERE name = $1);
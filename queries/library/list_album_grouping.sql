@@ -0,0 +1,4 @@
+SELECT id, title, track_number, disc_number, folder, artist_names, location, album_locked
+    FROM track
+    WHERE album_id = $1
+    ORDER BY folder, disc_number, track_number, title_sortable;
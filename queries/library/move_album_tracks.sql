@@ -0,0 +1,2 @@
+UPDATE track SET album_id = $1, album_locked = 1
+    WHERE album_id = $2;
@@ -0,0 +1,2 @@
+UPDATE track SET album_id = $1, album_locked = 1
+    WHERE id = $2 AND album_id = $3;
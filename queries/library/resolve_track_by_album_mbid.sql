@@ -0,0 +1,14 @@
+/*
+ Finds a track by its release's MusicBrainz ID and its position on the release.
+ Parameters:
+    1. The MusicBrainz ID of the release.
+    2. The disc number.
+    3. The track number.
+ */
+
+SELECT t.id, t.album_id, t.location FROM track t
+    JOIN album a ON a.id = t.album_id
+    WHERE a.mbid = $1
+        AND t.disc_number IS $2
+        AND t.track_number IS $3
+    LIMIT 1;
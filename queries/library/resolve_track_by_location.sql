@@ -0,0 +1,2 @@
+SELECT id, album_id, location FROM track
+WHERE location = $1;
@@ -0,0 +1,19 @@
+/*
+ Finds a track by its title, album and album artist. If there's more than one match (for example,
+ the same track on two discs), the one in the same position is preferred.
+ Parameters:
+    1. The track title.
+    2. The album title.
+    3. The album artist's name.
+    4. The disc number.
+    5. The track number.
+ */
+
+SELECT t.id, t.album_id, t.location FROM track t
+    JOIN album a ON a.id = t.album_id
+    JOIN artist ar ON ar.id = a.artist_id
+    WHERE t.title = $1 COLLATE NOCASE
+        AND a.title = $2 COLLATE NOCASE
+        AND ar.name = $3 COLLATE NOCASE
+    ORDER BY (t.disc_number IS $4 AND t.track_number IS $5) DESC
+    LIMIT 1;
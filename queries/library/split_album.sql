@@ -0,0 +1,5 @@
+INSERT INTO album (title, title_sortable, artist_id, image, thumb, release_date, release_year, label, catalog_number, isrc, mbid, vinyl_numbering, hidden, match_basis, image_hash, art_source, original_date, original_year)
+    SELECT title, title_sortable, artist_id, image, thumb, release_date, release_year, label, catalog_number, isrc, mbid, vinyl_numbering, hidden, 2, image_hash, art_source, original_date, original_year
+    FROM album
+    WHERE id = $1
+    RETURNING id;
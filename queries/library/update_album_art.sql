@@ -0,0 +1,3 @@
+-- the hash is of the original image, which isn't known here, so it's cleared
+UPDATE album SET image = $2, thumb = $3, image_hash = NULL, art_source = $4
+    WHERE id = $1;
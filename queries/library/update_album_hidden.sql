@@ -0,0 +1,2 @@
+UPDATE album SET hidden = $2
+    WHERE id = $1;
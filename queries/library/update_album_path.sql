@@ -0,0 +1,2 @@
+UPDATE album_path SET path = $3
+WHERE album_id = $1 AND disc_num = $2;
@@ -0,0 +1,2 @@
+UPDATE track SET bpm = $2, bpm_estimated = 0
+    WHERE id = $1;
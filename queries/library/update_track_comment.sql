@@ -0,0 +1,2 @@
+UPDATE track SET comment = $2, comment_edited = 1
+    WHERE id = $1;
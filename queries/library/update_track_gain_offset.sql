@@ -0,0 +1,2 @@
+UPDATE track SET gain_offset = $2
+    WHERE id = $1;
@@ -0,0 +1,2 @@
+UPDATE track SET location = $2, folder = $3
+WHERE id = $1;
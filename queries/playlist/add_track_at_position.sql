@@ -0,0 +1,2 @@
+INSERT INTO playlist_item (playlist_id, track_id, position)
+	VALUES($1, $2, $3)
@@ -0,0 +1 @@
+UPDATE playlist SET type = 0 WHERE type = 1;
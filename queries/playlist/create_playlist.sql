@@ -1,2 +1,2 @@
-INSERT INTO playlist (name)
-    VALUES($1);
+INSERT INTO playlist (name, last_modified)
+    VALUES($1, CURRENT_TIMESTAMP);
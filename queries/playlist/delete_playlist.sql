@@ -1,2 +1,2 @@
-DELETE FROM playlist_item WHERE playlist_id = $1;
-DELETE FROM playlist WHERE id = $1;
+DELETE FROM playlist_item WHERE playlist_id = $1 AND playlist_id IN (SELECT id FROM playlist WHERE type = 0);
+DELETE FROM playlist WHERE id = $1 AND type = 0;
@@ -0,0 +1 @@
+SELECT COALESCE(MAX(position), 0) FROM playlist_item;
@@ -0,0 +1 @@
+SELECT id FROM playlist WHERE type = 1;
@@ -0,0 +1,2 @@
+SELECT name FROM playlist
+    WHERE $1 IS NULL OR id != $1;
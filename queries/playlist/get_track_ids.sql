@@ -0,0 +1 @@
+SELECT track_id FROM playlist_item WHERE playlist_id = $1;
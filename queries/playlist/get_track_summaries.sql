@@ -0,0 +1,9 @@
+SELECT pl.id as item_id, t.title_sortable,
+    COALESCE(t.artist_names, ar.name) as artist_name,
+    al.title as album_title, al.title_sortable as album_title_sortable,
+    COALESCE(t.duration_ms, t.duration * 1000) as duration, t.bpm, pl.created_at as added
+    FROM playlist_item as pl
+    JOIN track t on pl.track_id = t.id
+    LEFT JOIN album al on t.album_id = al.id
+    LEFT JOIN artist ar on al.artist_id = ar.id
+    WHERE pl.playlist_id = $1;
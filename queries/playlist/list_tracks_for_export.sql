@@ -1,4 +1,4 @@
-SELECT t.location AS location, t.duration AS duration, t.artist_names AS track_artist_names, art.name AS artist_name, t.title as track_title, a.title AS album_title
+SELECT t.location AS location, COALESCE((t.duration_ms + 500) / 1000, t.duration) AS duration, t.artist_names AS track_artist_names, art.name AS artist_name, t.title as track_title, a.title AS album_title
     FROM playlist_item AS pl
     JOIN track t ON pl.track_id = t.id
     JOIN album a ON t.album_id = a.id
@@ -0,0 +1 @@
+UPDATE playlist SET type = 1 WHERE id = $1;
@@ -0,0 +1,2 @@
+UPDATE playlist SET name = $1
+    WHERE id = $2 AND type = 0;
@@ -0,0 +1 @@
+UPDATE playlist SET last_modified = CURRENT_TIMESTAMP WHERE id = $1;
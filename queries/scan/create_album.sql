@@ -1,16 +1,21 @@
-INSERT INTO album (title, title_sortable, artist_id, image, thumb, release_date, release_year, label, catalog_number, isrc, mbid, vinyl_numbering)
-    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
-    ON CONFLICT (title, artist_id, mbid) DO UPDATE SET
+INSERT INTO album (title, title_sortable, artist_id, image, thumb, release_date, release_year, label, catalog_number, isrc, mbid, vinyl_numbering, match_basis, image_hash, art_source, original_date, original_year)
+    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17)
+    ON CONFLICT (title, artist_id, mbid) WHERE match_basis != 2 DO UPDATE SET
         title = EXCLUDED.title,
         title_sortable = EXCLUDED.title_sortable,
         artist_id = EXCLUDED.artist_id,
         image = EXCLUDED.image,
         thumb = EXCLUDED.thumb,
+        image_hash = EXCLUDED.image_hash,
+        art_source = EXCLUDED.art_source,
         release_date = EXCLUDED.release_date,
         release_year = EXCLUDED.release_year,
+        original_date = EXCLUDED.original_date,
+        original_year = EXCLUDED.original_year,
         label = EXCLUDED.label,
         catalog_number = EXCLUDED.catalog_number,
         isrc = EXCLUDED.isrc,
         mbid = EXCLUDED.mbid,
+        -- hidden is set by the user, so it's left as is
         vinyl_numbering = vinyl_numbering OR EXCLUDED.vinyl_numbering
     RETURNING id;
@@ -1,5 +1,5 @@
-INSERT INTO track (title, title_sortable, album_id, track_number, disc_number, duration, location, genres, artist_names, folder)
-    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+INSERT INTO track (title, title_sortable, album_id, track_number, disc_number, duration, location, genres, artist_names, folder, bpm, bpm_estimated, extra_tags, recording_mbid, comment, duration_ms, art_oversized, lossless)
+    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18)
     ON CONFLICT (location) DO UPDATE SET
         title = EXCLUDED.title,
         title_sortable = EXCLUDED.title_sortable,
@@ -7,8 +7,21 @@ INSERT INTO track (title, title_sortable, album_id, track_number, disc_number, d
         track_number = EXCLUDED.track_number,
         disc_number = EXCLUDED.disc_number,
         duration = EXCLUDED.duration,
+        duration_ms = EXCLUDED.duration_ms,
         location = EXCLUDED.location,
         genres = EXCLUDED.genres,
         artist_names = EXCLUDED.artist_names,
-        folder = EXCLUDED.folder
+        folder = EXCLUDED.folder,
+        extra_tags = EXCLUDED.extra_tags,
+        recording_mbid = EXCLUDED.recording_mbid,
+        art_oversized = EXCLUDED.art_oversized,
+        lossless = EXCLUDED.lossless,
+        -- comments written in the app replace the file's
+        comment = CASE WHEN track.comment_edited THEN track.comment ELSE EXCLUDED.comment END,
+        -- keep manually entered or previously estimated values if the file has no BPM
+        bpm = COALESCE(EXCLUDED.bpm, track.bpm),
+        bpm_estimated = CASE WHEN EXCLUDED.bpm IS NULL THEN track.bpm_estimated ELSE EXCLUDED.bpm_estimated END,
+        -- files that were only retagged keep their measured loudness, but a different length means
+        -- the audio has changed
+        loudness_analyzed = CASE WHEN track.duration = EXCLUDED.duration THEN track.loudness_analyzed ELSE 0 END
     RETURNING id;
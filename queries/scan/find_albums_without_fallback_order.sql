@@ -0,0 +1 @@
+SELECT DISTINCT album_id FROM track WHERE fallback_order IS NULL AND album_id IS NOT NULL;
@@ -0,0 +1 @@
+SELECT id, location FROM track WHERE loudness_analyzed = 0 ORDER BY id;
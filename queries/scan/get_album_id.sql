@@ -1 +1 @@
-SELECT id FROM album WHERE title = $1 AND mbid = $2;
+SELECT id FROM album WHERE title = $1 AND mbid = $2 AND match_basis != 2;
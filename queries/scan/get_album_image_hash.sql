@@ -0,0 +1 @@
+SELECT image_hash FROM album WHERE id = $1;
@@ -0,0 +1 @@
+SELECT id, location FROM track WHERE album_id = $1;
@@ -0,0 +1 @@
+UPDATE album SET image = $2, thumb = $3, image_hash = $4, art_source = $5 WHERE id = $1;
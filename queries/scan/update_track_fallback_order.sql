@@ -0,0 +1 @@
+UPDATE track SET fallback_order = $2 WHERE id = $1;
@@ -0,0 +1 @@
+UPDATE track SET loudness = $1, loudness_peak = $2, loudness_analyzed = 1 WHERE id = $3;
@@ -1 +1 @@
-SELECT COUNT(*) as track_count, SUM(duration) as total_duration FROM track;
+SELECT COUNT(*) as track_count, SUM(COALESCE(duration_ms, duration * 1000)) as total_duration FROM track;
@@ -0,0 +1,187 @@
+//! Crash reports, written to the data directory when Hummingbird panics.
+//!
+//! Reports stay on the user's computer: nothing is sent anywhere. The next time Hummingbird
+//! starts, the user is told that it closed unexpectedly, and can open the folder the reports are
+//! in or copy the latest one, to share it if they want to.
+
+use std::{
+    backtrace::Backtrace,
+    fs,
+    panic::PanicHookInfo,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use chrono::Local;
+
+use crate::{VERSION_STRING, paths::DataPaths};
+
+/// How many crash reports are kept. The oldest ones are deleted once there are more than this.
+const MAX_CRASH_REPORTS: usize = 10;
+
+/// The file that holds the name of a crash report the user hasn't been told about yet.
+const UNSEEN_MARKER: &str = "unseen";
+
+/// The audio backend playback is going through, included in crash reports since many of the
+/// playback thread's panics are down to the audio device.
+static AUDIO_BACKEND: Mutex<Option<String>> = Mutex::new(None);
+
+/// The folder crash reports are kept in.
+pub fn crash_dir() -> PathBuf {
+    DataPaths::get().data_dir().join("crashes")
+}
+
+/// Records which audio backend playback is going through, for crash reports.
+pub fn set_audio_backend(backend: String) {
+    if let Ok(mut current) = AUDIO_BACKEND.lock() {
+        *current = Some(backend);
+    }
+}
+
+/// Installs a panic hook that writes a crash report before the usual panic message is printed.
+/// Panics on every thread go through the hook, so reports are written for the playback and scan
+/// threads as well as the main thread.
+pub fn install_panic_hook() {
+    let default_hook = std::panic::take_hook();
+
+    std::panic::set_hook(Box::new(move |info| {
+        let report = format_report(info, &Backtrace::force_capture());
+
+        match write_report(&crash_dir(), &report) {
+            Ok(path) => eprintln!("Crash report written to {}", path.display()),
+            Err(err) => eprintln!("Couldn't write a crash report: {err}"),
+        }
+
+        default_hook(info);
+    }));
+}
+
+/// Describes a panic, along with what was going on when it happened.
+fn format_report(info: &PanicHookInfo, backtrace: &Backtrace) -> String {
+    let payload = info.payload();
+    let message = payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("(no message)");
+    let location = info.location().map_or_else(
+        || "an unknown location".to_string(),
+        |location| location.to_string(),
+    );
+
+    let thread = std::thread::current();
+    // the lock may be held by the thread that panicked, in which case it's left out
+    let backend = AUDIO_BACKEND
+        .try_lock()
+        .ok()
+        .and_then(|backend| backend.clone())
+        .unwrap_or_else(|| "unknown".to_string());
+
+    format!(
+        "Hummingbird closed unexpectedly.\n\
+        \n\
+        Version: {VERSION_STRING}\n\
+        OS: {} ({})\n\
+        Thread: {}\n\
+        Audio backend: {backend}\n\
+        Time: {}\n\
+        \n\
+        Panicked at {location}:\n\
+        {message}\n\
+        \n\
+        Backtrace:\n\
+        {backtrace}\n",
+        std::env::consts::OS,
+        std::env::consts::ARCH,
+        thread.name().unwrap_or("unnamed"),
+        Local::now().to_rfc3339(),
+    )
+}
+
+/// Writes a crash report to the given folder, and marks it as one the user hasn't been told
+/// about yet. Only the newest [MAX_CRASH_REPORTS] reports are kept. Returns where the report was
+/// written.
+pub fn write_report(dir: &Path, report: &str) -> std::io::Result<PathBuf> {
+    fs::create_dir_all(dir)?;
+
+    let stamp = Local::now().format("%Y%m%d-%H%M%S%.3f");
+    let name = format!("crash-{stamp}.txt");
+    let path = dir.join(&name);
+
+    fs::write(&path, report)?;
+    fs::write(dir.join(UNSEEN_MARKER), &name)?;
+
+    // the timestamps in the names sort the reports from oldest to newest
+    let mut reports = list_reports(dir)?;
+    if reports.len() > MAX_CRASH_REPORTS {
+        for old in reports.drain(..reports.len() - MAX_CRASH_REPORTS) {
+            fs::remove_file(old).ok();
+        }
+    }
+
+    Ok(path)
+}
+
+fn list_reports(dir: &Path) -> std::io::Result<Vec<PathBuf>> {
+    let mut reports: Vec<PathBuf> = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with("crash-") && name.ends_with(".txt"))
+        })
+        .collect();
+
+    reports.sort();
+    Ok(reports)
+}
+
+/// Returns the crash report written since the user was last told about one, if there is one, and
+/// forgets about it so that it's only shown once.
+pub fn take_unseen_report() -> Option<PathBuf> {
+    take_unseen_report_in(&crash_dir())
+}
+
+fn take_unseen_report_in(dir: &Path) -> Option<PathBuf> {
+    let marker = dir.join(UNSEEN_MARKER);
+    let name = fs::read_to_string(&marker).ok()?;
+    fs::remove_file(&marker).ok();
+
+    Some(dir.join(name.trim())).filter(|path| path.is_file())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn writes_reports_and_shows_them_once() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let path = write_report(dir.path(), "report").unwrap();
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "report");
+        assert_eq!(take_unseen_report_in(dir.path()), Some(path));
+        assert_eq!(take_unseen_report_in(dir.path()), None);
+    }
+
+    #[test]
+    fn keeps_only_the_newest_reports() {
+        let dir = tempfile::tempdir().unwrap();
+        for index in 0..MAX_CRASH_REPORTS + 2 {
+            let name = format!("crash-20200101-000000.{index:03}.txt");
+            fs::write(dir.path().join(name), "old report").unwrap();
+        }
+        fs::write(dir.path().join("notes.txt"), "not a report").unwrap();
+
+        let path = write_report(dir.path(), "report").unwrap();
+
+        let reports = list_reports(dir.path()).unwrap();
+        assert_eq!(reports.len(), MAX_CRASH_REPORTS);
+        assert_eq!(reports.last(), Some(&path));
+        assert!(!dir.path().join("crash-20200101-000000.002.txt").exists());
+        assert!(dir.path().join("crash-20200101-000000.003.txt").exists());
+        assert!(dir.path().join("notes.txt").exists());
+    }
+}
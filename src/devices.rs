@@ -1,6 +1,9 @@
 pub mod builtin;
+pub mod category;
+pub mod dither;
 pub mod errors;
 pub mod format;
+pub mod mixer;
 pub mod resample;
 pub mod traits;
 pub mod util;
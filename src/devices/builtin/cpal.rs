@@ -1,12 +1,23 @@
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::Duration,
+};
+
 use crate::{
     devices::{
         errors::{
             CloseError, FindError, InfoError, InitializationError, ListError, OpenError,
             ResetError, StateError, SubmissionError,
         },
-        format::{BufferSize, ChannelSpec, FormatInfo, SampleFormat, SupportedFormat},
-        traits::{Device, DeviceProvider, OutputStream},
-        util::{Scale, interleave},
+        format::{
+            BufferSize, ChannelSpec, FormatInfo, SampleFormat, SupportedFormat,
+            check_format_supported,
+        },
+        traits::{Device, DeviceInfo, DeviceProvider, OutputStream},
+        util::{Scale, UnderrunCounter, interleave},
     },
     media::playback::{GetInnerSamples, Mute, PlaybackFrame},
     util::make_unknown_error,
@@ -17,6 +28,10 @@ use cpal::{
 };
 use rb::{Producer, RB, RbConsumer, RbProducer, SpscRb};
 
+/// How long submit_frame waits for room in the buffer before giving up on the rest of the frame.
+/// The buffer only fills up for this long if the device has stopped taking samples.
+const SUBMIT_TIMEOUT: Duration = Duration::from_millis(500);
+
 pub struct CpalProvider {
     host: Host,
 }
@@ -57,6 +72,21 @@ impl DeviceProvider for CpalProvider {
             .ok_or(FindError::DeviceDoesNotExist)
             .map(|dev| Box::new(CpalDevice::from(dev)) as Box<dyn Device>)
     }
+
+    fn list_devices(&mut self) -> Result<Vec<DeviceInfo>, ListError> {
+        // all devices includes inputs like microphones, which can't be played through
+        Ok(self
+            .host
+            .output_devices()?
+            .filter_map(|dev| {
+                let name = dev.name().ok()?;
+                Some(DeviceInfo {
+                    uid: name.clone(),
+                    name,
+                })
+            })
+            .collect())
+    }
 }
 
 struct CpalDevice {
@@ -105,15 +135,21 @@ fn create_stream_internal<
     device: &cpal::Device,
     config: &cpal::StreamConfig,
     buffer_size: usize,
+    consumed: Arc<AtomicU64>,
+    underruns: Arc<AtomicU64>,
 ) -> Result<(cpal::Stream, Producer<T>), OpenError> {
     let rb: SpscRb<T> = SpscRb::new(buffer_size);
     let cons = rb.consumer();
     let prod = rb.producer();
+    let channels = usize::from(config.channels.max(1));
+    let underruns = UnderrunCounter::new(underruns);
 
     let stream = device.build_output_stream(
         config,
         move |data: &mut [T], _: &cpal::OutputCallbackInfo| {
             let written = cons.read(data).unwrap_or(0);
+            consumed.fetch_add((written / channels) as u64, Ordering::Relaxed);
+            underruns.record(written, data.len());
 
             data[written..].iter_mut().for_each(|v| *v = T::muted())
         },
@@ -140,14 +176,19 @@ impl CpalDevice {
         let config =
             cpal_config_from_info(&format).map_err(|_| OpenError::InvalidConfigProvider)?;
 
-        let channels = match format.channels {
-            ChannelSpec::Count(v) => v,
-            _ => panic!("non cpal device"),
-        };
+        let channels = format.channels.count();
 
         let buffer_size = ((200 * config.sample_rate.0 as usize) / 1000) * channels as usize;
 
-        let (stream, prod) = create_stream_internal::<T>(&self.device, &config, buffer_size)?;
+        let consumed = Arc::new(AtomicU64::new(0));
+        let underruns = Arc::new(AtomicU64::new(0));
+        let (stream, prod) = create_stream_internal::<T>(
+            &self.device,
+            &config,
+            buffer_size,
+            consumed.clone(),
+            underruns.clone(),
+        )?;
 
         Ok(Box::new(CpalStream {
             ring_buf: prod,
@@ -157,6 +198,8 @@ impl CpalDevice {
             buffer_size,
             device: self.device.clone(),
             volume: 1.0,
+            consumed,
+            underruns,
         }))
     }
 }
@@ -166,6 +209,18 @@ impl Device for CpalDevice {
         if format.originating_provider != "cpal" {
             Err(OpenError::InvalidConfigProvider)
         } else {
+            // cpal doesn't say why a stream couldn't be built, so check the format first
+            if let Ok(supported) = self.get_supported_formats() {
+                check_format_supported(&format, &supported)?;
+            }
+
+            // cpal can't tell which position each channel is played at, so the usual layout for
+            // the number of channels is assumed
+            let format = FormatInfo {
+                channels: ChannelSpec::Count(format.channels.count()),
+                ..format
+            };
+
             match format.sample_type {
                 SampleFormat::Signed8 => self.create_stream::<i8>(format),
                 SampleFormat::Signed16 => self.create_stream::<i16>(format),
@@ -175,7 +230,7 @@ impl Device for CpalDevice {
                 SampleFormat::Unsigned32 => self.create_stream::<u32>(format),
                 SampleFormat::Float32 => self.create_stream::<f32>(format),
                 SampleFormat::Float64 => self.create_stream::<f64>(format),
-                _ => Err(OpenError::InvalidSampleFormat),
+                _ => Err(OpenError::UnsupportedSampleType(format.sample_type)),
             }
         }
     }
@@ -240,6 +295,10 @@ where
     pub format: FormatInfo,
     pub buffer_size: usize,
     pub volume: f64,
+    /// The number of frames the device callback has taken from the buffer. Kept across resets.
+    pub consumed: Arc<AtomicU64>,
+    /// The number of times the device callback found the buffer short. Kept across resets.
+    pub underruns: Arc<AtomicU64>,
 }
 
 impl<T> OutputStream for CpalStream<T>
@@ -258,7 +317,9 @@ where
         let interleaved = interleave(samples);
         let mut slice: &[T] = &interleaved;
 
-        while let Some(written) = self.ring_buf.write_blocking(slice) {
+        // if the device stops taking samples, the rest of the frame is dropped rather than waiting
+        // forever, so that the playback thread can notice (see frames_consumed)
+        while let Ok(Some(written)) = self.ring_buf.write_blocking_timeout(slice, SUBMIT_TIMEOUT) {
             slice = &slice[written..];
         }
 
@@ -286,8 +347,13 @@ where
     }
 
     fn reset(&mut self) -> Result<(), ResetError> {
-        let (stream, prod) =
-            create_stream_internal::<T>(&self.device, &self.config, self.buffer_size)?;
+        let (stream, prod) = create_stream_internal::<T>(
+            &self.device,
+            &self.config,
+            self.buffer_size,
+            self.consumed.clone(),
+            self.underruns.clone(),
+        )?;
 
         self.stream = stream;
         self.ring_buf = prod;
@@ -299,6 +365,18 @@ where
         self.volume = volume;
         Ok(())
     }
+
+    fn frames_consumed(&self) -> Option<u64> {
+        Some(self.consumed.load(Ordering::Relaxed))
+    }
+
+    fn underruns(&self) -> Option<u64> {
+        Some(self.underruns.load(Ordering::Relaxed))
+    }
+
+    fn buffer_frames(&self) -> Option<usize> {
+        Some(self.buffer_size / usize::from(self.config.channels.max(1)))
+    }
 }
 
 make_unknown_error!(OpenError, ResetError);
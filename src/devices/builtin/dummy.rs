@@ -6,7 +6,9 @@ use crate::devices::{
     errors::{
         CloseError, FindError, InfoError, InitializationError, ListError, OpenError, StateError,
     },
-    format::{BufferSize, ChannelSpec, FormatInfo, SampleFormat, SupportedFormat},
+    format::{
+        BufferSize, ChannelSpec, FormatInfo, SampleFormat, SupportedFormat, check_format_supported,
+    },
     traits::{Device, DeviceProvider, OutputStream},
 };
 
@@ -22,6 +24,12 @@ use crate::devices::{
 /// - `HB_DUMMY_CHANNELS`: The number of channels of the dummy device. Defaults to 2.
 /// - `HB_DUMMY_BUFFER_SIZE`: The buffer size of the dummy device. Defaults to 4096.
 ///   - This doesn't actually do anything: no samples are ever loaded into a buffer.
+/// - `HB_DUMMY_STRICT`: If set, the dummy device will refuse to open with any format other than
+///   the one configured above, like a real device would. This is useful for testing the format
+///   fallbacks used when opening a device.
+/// - `HB_DUMMY_STALL_AFTER`: If set, each stream stops consuming samples after this many frames
+///   have been submitted to it, while still accepting more, like a device that died without
+///   reporting an error. This is useful for testing the playback thread's output watchdog.
 pub struct DummyDeviceProvider {}
 
 impl DummyDeviceProvider {
@@ -91,6 +99,16 @@ impl DummyDevice {
             .unwrap_or(2)
     }
 
+    pub fn is_strict() -> bool {
+        env::var_os("HB_DUMMY_STRICT").is_some()
+    }
+
+    pub fn get_stall_after() -> Option<u64> {
+        env::var("HB_DUMMY_STALL_AFTER")
+            .ok()
+            .and_then(|s| s.parse().ok())
+    }
+
     pub fn get_buffer_size() -> u32 {
         env::var("HB_DUMMY_BUFFER_SIZE")
             .ok()
@@ -101,7 +119,18 @@ impl DummyDevice {
 
 impl Device for DummyDevice {
     fn open_device(&mut self, format: FormatInfo) -> Result<Box<dyn OutputStream>, OpenError> {
-        let device = DummyStream { format };
+        if DummyDevice::is_strict() {
+            let supported = self
+                .get_supported_formats()
+                .map_err(|err| OpenError::Unknown(err.to_string()))?;
+            check_format_supported(&format, &supported)?;
+        }
+
+        let device = DummyStream {
+            format,
+            consumed: 0,
+            stall_after: DummyDevice::get_stall_after(),
+        };
         Ok(Box::new(device) as Box<dyn OutputStream>)
     }
 
@@ -143,6 +172,10 @@ impl Device for DummyDevice {
 
 pub struct DummyStream {
     pub format: FormatInfo,
+    /// The number of frames that have been "played".
+    pub consumed: u64,
+    /// The number of frames after which the stream stops consuming (see `HB_DUMMY_STALL_AFTER`).
+    pub stall_after: Option<u64>,
 }
 
 impl OutputStream for DummyStream {
@@ -151,6 +184,13 @@ impl OutputStream for DummyStream {
         frame: crate::media::playback::PlaybackFrame,
     ) -> Result<(), crate::devices::errors::SubmissionError> {
         debug!("Frame received! Sample rate: {}", frame.rate);
+
+        let consumed = self.consumed + frame.samples.frames() as u64;
+        self.consumed = match self.stall_after {
+            Some(stall_after) => consumed.min(stall_after),
+            None => consumed,
+        };
+
         Ok(())
     }
 
@@ -186,4 +226,8 @@ impl OutputStream for DummyStream {
         debug!("Volume set to {}.", volume);
         Ok(())
     }
+
+    fn frames_consumed(&self) -> Option<u64> {
+        Some(self.consumed)
+    }
 }
@@ -1,4 +1,11 @@
-use std::slice::from_raw_parts_mut;
+use std::{
+    slice::from_raw_parts_mut,
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::Duration,
+};
 
 use rb::{Producer, RB, RbConsumer, RbProducer, SpscRb};
 use tracing::error;
@@ -11,6 +18,7 @@ use windows::{
             FrameInputNodeQuantumStartedEventArgs,
         },
         AudioBufferAccessMode, AudioFrame,
+        Devices::{AudioDeviceRole, MediaDevice},
         Render::AudioRenderCategory,
     },
     Win32::System::WinRT::IMemoryBufferByteAccess,
@@ -25,12 +33,16 @@ use crate::{
         },
         format::{BufferSize, ChannelSpec, FormatInfo, SampleFormat, SupportedFormat},
         traits::{Device, DeviceProvider, OutputStream},
-        util::{Packed, interleave},
+        util::{Packed, UnderrunCounter, interleave},
     },
     media::playback::{GetInnerSamples, PlaybackFrame},
     util::make_unknown_error,
 };
 
+/// How long submit_frame waits for room in the buffer before giving up on the rest of the frame.
+/// The buffer only fills up for this long if the graph has stopped asking for samples.
+const SUBMIT_TIMEOUT: Duration = Duration::from_millis(500);
+
 /// Windows Audio Graph backend
 ///
 /// Audio Graph is the most managed of the Windows backends: you can throw nearly any stream at
@@ -71,6 +83,13 @@ impl DeviceProvider for AudioGraphProvider {
             .ok_or(FindError::DeviceDoesNotExist)
             .map(|device| Box::new(AudioGraphDevice::from(device)) as Box<dyn Device>)
     }
+
+    fn get_default_device_uid(&mut self) -> Option<String> {
+        // the default device's graph doesn't know which device it's playing on, and creating one
+        // every time this is checked would be slow, so the system is asked instead
+        let id = MediaDevice::GetDefaultAudioRenderId(AudioDeviceRole::Default).ok()?;
+        (!id.is_empty()).then(|| id.to_string())
+    }
 }
 
 pub struct AudioGraphDevice {
@@ -154,6 +173,12 @@ impl From<DeviceInformation> for AudioGraphDevice {
 
 impl Device for AudioGraphDevice {
     fn open_device(&mut self, format: FormatInfo) -> Result<Box<dyn OutputStream>, OpenError> {
+        // AudioGraph only takes a number of channels, and picks the positions itself
+        let format = FormatInfo {
+            channels: ChannelSpec::Count(format.channels.count()),
+            ..format
+        };
+
         self.graph.Start()?;
         self.device_out.Start()?;
 
@@ -161,7 +186,7 @@ impl Device for AudioGraphDevice {
 
         properties
             .SetChannelCount(format.channels.count() as u32)
-            .map_err(|_| OpenError::InvalidSampleFormat)?;
+            .map_err(|_| OpenError::UnsupportedChannelCount(format.channels.count()))?;
 
         let input_node = self.graph.CreateFrameInputNodeWithFormat(&properties)?;
 
@@ -180,6 +205,10 @@ impl Device for AudioGraphDevice {
         let rb: SpscRb<u8> = SpscRb::new(rb_size);
         let cons = rb.consumer();
         let prod = rb.producer();
+        let consumed = Arc::new(AtomicU64::new(0));
+        let handler_consumed = consumed.clone();
+        let underruns = Arc::new(AtomicU64::new(0));
+        let handler_underruns = UnderrunCounter::new(underruns.clone());
 
         let handler =
             TypedEventHandler::<AudioFrameInputNode, FrameInputNodeQuantumStartedEventArgs>::new(
@@ -222,6 +251,9 @@ impl Device for AudioGraphDevice {
                         }
 
                         let read = cons.read(slice).unwrap_or(0);
+                        let frame_bytes = size_of::<f32>() * channel_count.max(1) as usize;
+                        handler_consumed.fetch_add((read / frame_bytes) as u64, Ordering::Relaxed);
+                        handler_underruns.record(read, slice.len());
                         // should be fine? IEEE says that 0.0 is 0x00000000...
                         slice[read..].iter_mut().for_each(|v| *v = 0);
 
@@ -246,10 +278,15 @@ impl Device for AudioGraphDevice {
 
         input_node.QuantumStarted(&handler)?;
 
+        let buffer_frames = rb_size / (size_of::<f32>() * format.channels.count().max(1) as usize);
+
         let stream = AudioGraphStream {
             node: input_node,
             producer: prod,
             format,
+            consumed,
+            underruns,
+            buffer_frames,
         };
 
         Ok(Box::new(stream) as Box<dyn OutputStream>)
@@ -312,6 +349,12 @@ pub struct AudioGraphStream {
     pub node: AudioFrameInputNode,
     pub producer: Producer<u8>,
     pub format: FormatInfo,
+    /// The number of frames the graph has taken from the buffer.
+    pub consumed: Arc<AtomicU64>,
+    /// The number of times the graph found the buffer short.
+    pub underruns: Arc<AtomicU64>,
+    /// How many frames the buffer holds.
+    pub buffer_frames: usize,
 }
 
 impl OutputStream for AudioGraphStream {
@@ -322,7 +365,9 @@ impl OutputStream for AudioGraphStream {
         let packed = interleave(samples).pack();
         let mut slice: &[u8] = &packed;
 
-        while let Some(written) = self.producer.write_blocking(slice) {
+        // if the graph stops asking for samples, the rest of the frame is dropped rather than
+        // waiting forever, so that the playback thread can notice (see frames_consumed)
+        while let Ok(Some(written)) = self.producer.write_blocking_timeout(slice, SUBMIT_TIMEOUT) {
             slice = &slice[written..];
         }
 
@@ -356,6 +401,18 @@ impl OutputStream for AudioGraphStream {
     fn set_volume(&mut self, volume: f64) -> Result<(), StateError> {
         self.node.SetOutgoingGain(volume).map_err(|e| e.into())
     }
+
+    fn frames_consumed(&self) -> Option<u64> {
+        Some(self.consumed.load(Ordering::Relaxed))
+    }
+
+    fn underruns(&self) -> Option<u64> {
+        Some(self.underruns.load(Ordering::Relaxed))
+    }
+
+    fn buffer_frames(&self) -> Option<usize> {
+        Some(self.buffer_frames)
+    }
 }
 
 make_unknown_error!(windows_result::Error, StateError);
@@ -0,0 +1,141 @@
+/// The kind of output a device is, as far as pausing when it's removed is concerned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeviceCategory {
+    /// Headphones, headsets and earbuds. Audio moving to the speakers when these are unplugged
+    /// would be heard by everyone in the room.
+    Headphones,
+    /// Speakers, line outputs and other devices meant to be heard by the whole room.
+    Speakers,
+    /// Devices that couldn't be classified.
+    Unknown,
+}
+
+/// Words that only show up in the names of devices worn by the listener.
+const HEADPHONE_WORDS: &[&str] = &[
+    "headphone",
+    "headset",
+    "earphone",
+    "earbud",
+    "earpiece",
+    "airpods",
+    "buds",
+    "hands-free",
+    "handsfree",
+];
+
+/// Words that only show up in the names of devices that play out loud.
+const SPEAKER_WORDS: &[&str] = &[
+    "speaker",
+    "line out",
+    "lineout",
+    "line-out",
+    "hdmi",
+    "displayport",
+    "spdif",
+    "s/pdif",
+    "optical",
+    "soundbar",
+    "tv",
+];
+
+/// Guesses what kind of device this is from its name. Most providers don't report a device's
+/// form factor, but the names given to devices by drivers and operating systems usually say
+/// whether they're headphones ("Headphones (Realtek Audio)", "WH-1000XM4 Hands-Free") or speakers
+/// ("Speakers (USB Audio)", "HDA Intel PCH, HDMI 0").
+///
+/// Headphone words are checked first, since headsets are often named after the speaker in them
+/// ("Headset Earphone", "Speaker/Headphone").
+pub fn classify_device_name(name: &str) -> DeviceCategory {
+    let name = name.to_lowercase();
+    let has_word = |words: &[&str]| {
+        words.iter().any(|word| {
+            name.match_indices(word).any(|(start, _)| {
+                // "tv" and "buds" would otherwise match the middle of other words
+                let before = name[..start].chars().next_back();
+                let after = name[start + word.len()..].chars().next();
+                !before.is_some_and(char::is_alphanumeric)
+                    && !after.is_some_and(|c| c.is_alphabetic() && c != 's')
+            })
+        })
+    };
+
+    if has_word(HEADPHONE_WORDS) {
+        DeviceCategory::Headphones
+    } else if has_word(SPEAKER_WORDS) {
+        DeviceCategory::Speakers
+    } else {
+        DeviceCategory::Unknown
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_classified(names: &[&str], category: DeviceCategory) {
+        for name in names {
+            assert_eq!(classify_device_name(name), category, "{name}");
+        }
+    }
+
+    #[test]
+    fn classifies_headphones() {
+        let names = [
+            "Headphones (Realtek(R) Audio)",
+            "WH-1000XM4 Hands-Free",
+            "Galaxy Buds2 Pro",
+            "AirPods Pro",
+            "Headset Earphone (USB Audio)",
+        ];
+        assert_classified(&names, DeviceCategory::Headphones);
+    }
+
+    #[test]
+    fn classifies_speakers() {
+        let names = [
+            "Speakers (USB Audio)",
+            "Line Out (Realtek Audio)",
+            "Living Room TV",
+            "Optical Out",
+        ];
+        assert_classified(&names, DeviceCategory::Speakers);
+    }
+
+    #[test]
+    fn classifies_display_outputs_as_speakers() {
+        let names = ["HDA Intel PCH, HDMI 0", "DELL U2720Q (DisplayPort)"];
+        assert_classified(&names, DeviceCategory::Speakers);
+    }
+
+    #[test]
+    fn prefers_headphones_over_speakers() {
+        assert_classified(
+            &["Speaker/Headphone (Realtek Audio)"],
+            DeviceCategory::Headphones,
+        );
+    }
+
+    #[test]
+    fn classifies_bluetooth_devices_by_what_they_are() {
+        assert_classified(
+            &["Sony WH-1000XM4 Headphones (Bluetooth)"],
+            DeviceCategory::Headphones,
+        );
+        assert_classified(&["JBL Flip 5 Bluetooth Speaker"], DeviceCategory::Speakers);
+        // a bluetooth device that doesn't say what it is could be either
+        assert_classified(&["JBL Flip 5"], DeviceCategory::Unknown);
+    }
+
+    #[test]
+    fn leaves_other_devices_unclassified() {
+        // "tv" and "buds" in the middle of other words don't count
+        let names = [
+            "default",
+            "pipewire",
+            "USB Audio Device",
+            "Stvdio Monitor",
+            "Budsmith DAC",
+        ];
+        assert_classified(&names, DeviceCategory::Unknown);
+    }
+}
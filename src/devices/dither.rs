@@ -0,0 +1,218 @@
+use intx::{I24, U24};
+use rand::{Rng, SeedableRng, rng, rngs::SmallRng};
+
+use crate::{media::playback::Samples, settings::playback::DitherMode};
+
+use super::{format::SampleFormat, resample::convert_samples};
+
+/// How many bits of precision samples in the given format have, or None for floating point
+/// formats, which samples are never dithered to. 32-bit floats are counted as having 24 bits,
+/// the size of their mantissa.
+fn precision(format: SampleFormat) -> Option<u32> {
+    match format {
+        SampleFormat::Float64 | SampleFormat::Float32 | SampleFormat::Dsd => None,
+        SampleFormat::Signed32 | SampleFormat::Unsigned32 => Some(32),
+        SampleFormat::Signed24
+        | SampleFormat::Unsigned24
+        | SampleFormat::Signed24Packed
+        | SampleFormat::Unsigned24Packed => Some(24),
+        SampleFormat::Signed16 | SampleFormat::Unsigned16 => Some(16),
+        SampleFormat::Signed8 | SampleFormat::Unsigned8 => Some(8),
+    }
+}
+
+/// How many bits of precision the given samples have.
+fn source_precision(samples: &Samples) -> u32 {
+    match samples {
+        Samples::Float64(_) => 53,
+        Samples::Float32(_) => 24,
+        Samples::Signed32(_) | Samples::Unsigned32(_) => 32,
+        Samples::Signed24(_) | Samples::Unsigned24(_) => 24,
+        Samples::Signed16(_) | Samples::Unsigned16(_) => 16,
+        Samples::Signed8(_) | Samples::Unsigned8(_) => 8,
+        Samples::Dsd(_) => 1,
+    }
+}
+
+/// Adds TPDF (triangular) dither to samples as they're converted to a format with fewer bits,
+/// which turns the distortion that truncating them would cause in quiet passages into a constant,
+/// very quiet hiss. With noise shaping, the quantization error of each sample is also subtracted
+/// from the next one, which moves most of that hiss up to frequencies that are harder to hear.
+pub struct Dither {
+    mode: DitherMode,
+    rng: SmallRng,
+    /// The quantization error of the last sample of each channel, for noise shaping.
+    errors: Vec<f64>,
+}
+
+impl Dither {
+    pub fn new(mode: DitherMode) -> Self {
+        Dither {
+            mode,
+            rng: SmallRng::from_rng(&mut rng()),
+            errors: Vec::new(),
+        }
+    }
+
+    /// Whether converting the given samples to `target` loses bits, and dither should be added.
+    /// Conversions to floating point formats or to formats with as many bits or more never are.
+    pub fn needed(&self, samples: &Samples, target: SampleFormat) -> bool {
+        self.mode != DitherMode::Off
+            && precision(target).is_some_and(|bits| bits < source_precision(samples))
+    }
+
+    /// Converts the samples to `target`, adding dither. Should only be called if
+    /// [Dither::needed] returned true.
+    pub fn apply(&mut self, samples: Samples, target: SampleFormat) -> Samples {
+        let bits = precision(target).expect("dither is only added to integer formats");
+        let channels: Vec<Vec<f64>> = convert_samples(samples);
+        let quantized = self.quantize(channels, bits);
+
+        // the unsigned formats are the signed ones shifted up by their maximum, as in
+        // resample::SampleFrom
+        let max = (1_i64 << (bits - 1)) - 1;
+
+        match target {
+            SampleFormat::Signed32 => Samples::Signed32(cast(quantized, |v| v as i32)),
+            SampleFormat::Unsigned32 => Samples::Unsigned32(cast(quantized, |v| (v + max) as u32)),
+            SampleFormat::Signed24 | SampleFormat::Signed24Packed => {
+                Samples::Signed24(cast(quantized, |v| {
+                    I24::try_from(v as i32).expect("out of I24 bounds")
+                }))
+            }
+            SampleFormat::Unsigned24 | SampleFormat::Unsigned24Packed => {
+                Samples::Unsigned24(cast(quantized, |v| {
+                    U24::try_from((v + max) as u32).expect("out of U24 bounds")
+                }))
+            }
+            SampleFormat::Signed16 => Samples::Signed16(cast(quantized, |v| v as i16)),
+            SampleFormat::Unsigned16 => Samples::Unsigned16(cast(quantized, |v| (v + max) as u16)),
+            SampleFormat::Signed8 => Samples::Signed8(cast(quantized, |v| v as i8)),
+            SampleFormat::Unsigned8 => Samples::Unsigned8(cast(quantized, |v| (v + max) as u8)),
+            SampleFormat::Float64 | SampleFormat::Float32 | SampleFormat::Dsd => {
+                unreachable!("dither is only added to integer formats")
+            }
+        }
+    }
+
+    /// Rounds each sample (from -1.0 to 1.0) to a signed integer with the given number of bits,
+    /// after adding dither of up to one step either way.
+    fn quantize(&mut self, channels: Vec<Vec<f64>>, bits: u32) -> Vec<Vec<i64>> {
+        let max = ((1_i64 << (bits - 1)) - 1) as f64;
+        let shaping = self.mode == DitherMode::NoiseShaped;
+        if self.errors.len() < channels.len() {
+            self.errors.resize(channels.len(), 0.0);
+        }
+
+        channels
+            .into_iter()
+            .zip(self.errors.iter_mut())
+            .map(|(channel, error)| {
+                channel
+                    .into_iter()
+                    .map(|sample| {
+                        let wanted = sample * max - if shaping { *error } else { 0.0 };
+                        // the difference of two uniform values has a triangular distribution
+                        let dither = self.rng.random::<f64>() - self.rng.random::<f64>();
+                        let quantized = (wanted + dither).round().clamp(-max, max);
+
+                        *error = quantized - wanted;
+                        quantized as i64
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+fn cast<T>(channels: Vec<Vec<i64>>, f: impl Fn(i64) -> T) -> Vec<Vec<T>> {
+    channels
+        .into_iter()
+        .map(|channel| channel.into_iter().map(&f).collect())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// One channel of samples that convert to the given 16-bit values exactly.
+    fn samples(values: &[i16]) -> Samples {
+        let max = f64::from(i16::MAX);
+        Samples::Float64(vec![values.iter().map(|v| f64::from(*v) / max).collect()])
+    }
+
+    /// The samples converted to 16 bits without dither, which truncates them.
+    fn truncated(values: &[i16]) -> Vec<i16> {
+        convert_samples::<i16>(samples(values)).concat()
+    }
+
+    fn dithered(mode: DitherMode, values: &[i16]) -> Vec<i16> {
+        let mut dither = Dither::new(mode);
+
+        match dither.apply(samples(values), SampleFormat::Signed16) {
+            Samples::Signed16(channels) => channels.concat(),
+            _ => panic!("expected 16-bit samples"),
+        }
+    }
+
+    fn steps_apart(a: i16, b: i16) -> i32 {
+        (i32::from(a) - i32::from(b)).abs()
+    }
+
+    #[test]
+    fn stays_within_one_step_of_the_truncated_value() {
+        let values: Vec<i16> = (-1000..1000)
+            .map(|v| v * 31)
+            .chain([i16::MAX, -i16::MAX])
+            .collect();
+
+        let dithered = dithered(DitherMode::Tpdf, &values);
+
+        for (truncated, dithered) in truncated(&values).into_iter().zip(dithered) {
+            assert!(
+                steps_apart(truncated, dithered) <= 1,
+                "{truncated} was dithered to {dithered}"
+            );
+        }
+    }
+
+    #[test]
+    fn adds_a_quiet_hiss_to_silence() {
+        let dithered = dithered(DitherMode::Tpdf, &[0; 1000]);
+
+        assert!(dithered.iter().all(|v| (-1..=1).contains(v)));
+        assert!(dithered.iter().any(|v| *v != 0));
+    }
+
+    #[test]
+    fn noise_shaping_keeps_the_level() {
+        let values = [1000; 1000];
+
+        let dithered = dithered(DitherMode::NoiseShaped, &values);
+
+        // each sample makes up for the error of the one before it, so the errors cancel out
+        let total: i32 = values
+            .iter()
+            .zip(dithered)
+            .map(|(value, dithered)| i32::from(dithered) - i32::from(*value))
+            .sum();
+        assert!(total.abs() <= 2, "the samples drifted by {total} in total");
+    }
+
+    #[test]
+    fn only_dithers_when_bits_are_lost() {
+        let tpdf = Dither::new(DitherMode::Tpdf);
+        let off = Dither::new(DitherMode::Off);
+        let signed_24 = Samples::Signed24(Vec::new());
+        let float_32 = Samples::Float32(Vec::new());
+
+        assert!(tpdf.needed(&signed_24, SampleFormat::Signed16));
+        assert!(tpdf.needed(&float_32, SampleFormat::Unsigned16));
+        assert!(!tpdf.needed(&signed_24, SampleFormat::Signed24));
+        assert!(!tpdf.needed(&signed_24, SampleFormat::Signed32));
+        assert!(!tpdf.needed(&float_32, SampleFormat::Signed24));
+        assert!(!tpdf.needed(&signed_24, SampleFormat::Float32));
+        assert!(!off.needed(&signed_24, SampleFormat::Signed16));
+    }
+}
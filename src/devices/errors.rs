@@ -2,6 +2,8 @@
 
 use thiserror::Error;
 
+use super::format::SampleFormat;
+
 #[derive(PartialEq, Eq, Debug, Clone, Error)]
 pub enum InitializationError {
     #[error("Unknown device provider error: `{0}`")]
@@ -42,14 +44,24 @@ pub enum InfoError {
     Unknown(String),
 }
 
+#[derive(PartialEq, Eq, Debug, Clone, Error)]
+pub enum MixError {
+    #[error("Playing {0} channels on a device with {1} channels needs downmixing, which is off")]
+    DownmixRefused(u16, u16),
+}
+
 #[derive(PartialEq, Eq, Debug, Clone, Error)]
 pub enum OpenError {
     #[error(
         "The supplied sample format is from a different device provider than the requested device"
     )]
     InvalidConfigProvider,
-    #[error("The supplied sample format is not supported by the device")]
-    InvalidSampleFormat,
+    #[error("The device does not support the `{0:?}` sample format")]
+    UnsupportedSampleType(SampleFormat),
+    #[error("The device does not support {0} channels")]
+    UnsupportedChannelCount(u16),
+    #[error("The device does not support a sample rate of {0} Hz")]
+    UnsupportedSampleRate(u32),
     #[error("Unknown device error: `{0}`")]
     Unknown(String),
 }
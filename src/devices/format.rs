@@ -1,5 +1,7 @@
 use bitflags::bitflags;
 
+use super::errors::OpenError;
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum SampleFormat {
     Float64,
@@ -17,6 +19,9 @@ pub enum SampleFormat {
     Dsd,
 }
 
+/// The channels of a track or device. Where the position of each channel is known, it's given as
+/// a bitmask, and the samples of each channel are in the order of their bits (the same order
+/// WAVEFORMATEXTENSIBLE and Symphonia use). Otherwise, only the number of channels is known.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ChannelSpec {
     Bitmask(Channels),
@@ -30,6 +35,15 @@ impl ChannelSpec {
             ChannelSpec::Count(count) => count,
         }
     }
+
+    /// Returns the position of each channel. When only the number of channels is known, the usual
+    /// layout for that number is assumed, if there is one.
+    pub fn layout(self) -> Option<Channels> {
+        match self {
+            ChannelSpec::Bitmask(channels) => Some(channels),
+            ChannelSpec::Count(count) => Layout::for_count(count).map(Layout::channels),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -93,11 +107,25 @@ pub enum Layout {
     Mono,
     Stereo,
     TwoOne,
+    Quad,
     FiveOne,
     SevenOne,
 }
 
 impl Layout {
+    /// Returns the layout usually meant by the given number of channels, if there is one.
+    pub fn for_count(count: u16) -> Option<Layout> {
+        match count {
+            1 => Some(Layout::Mono),
+            2 => Some(Layout::Stereo),
+            3 => Some(Layout::TwoOne),
+            4 => Some(Layout::Quad),
+            6 => Some(Layout::FiveOne),
+            8 => Some(Layout::SevenOne),
+            _ => None,
+        }
+    }
+
     pub fn channels(self) -> Channels {
         match self {
             Layout::Mono => Channels::FRONT_LEFT,
@@ -105,9 +133,16 @@ impl Layout {
             Layout::TwoOne => {
                 Channels::FRONT_LEFT | Channels::FRONT_RIGHT | Channels::LOW_FREQUENCY
             }
+            Layout::Quad => {
+                Channels::FRONT_LEFT
+                    | Channels::FRONT_RIGHT
+                    | Channels::BACK_LEFT
+                    | Channels::BACK_RIGHT
+            }
             Layout::FiveOne => {
                 Channels::FRONT_LEFT
                     | Channels::FRONT_RIGHT
+                    | Channels::FRONT_CENTER
                     | Channels::BACK_LEFT
                     | Channels::BACK_RIGHT
                     | Channels::LOW_FREQUENCY
@@ -115,6 +150,7 @@ impl Layout {
             Layout::SevenOne => {
                 Channels::FRONT_LEFT
                     | Channels::FRONT_RIGHT
+                    | Channels::FRONT_CENTER
                     | Channels::SIDE_LEFT
                     | Channels::SIDE_RIGHT
                     | Channels::BACK_LEFT
@@ -124,3 +160,238 @@ impl Layout {
         }
     }
 }
+
+/// Checks a format against the formats a device supports. If none of them match, the error says
+/// which part of the format was rejected: the sample type is checked first, then the channel
+/// count, then the sample rate.
+pub fn check_format_supported(
+    format: &FormatInfo,
+    supported: &[SupportedFormat],
+) -> Result<(), OpenError> {
+    let matching: Vec<_> = supported
+        .iter()
+        .filter(|v| v.sample_type == format.sample_type)
+        .collect();
+
+    if matching.is_empty() {
+        return Err(OpenError::UnsupportedSampleType(format.sample_type));
+    }
+
+    let matching: Vec<_> = matching
+        .into_iter()
+        .filter(|v| v.channels.count() == format.channels.count())
+        .collect();
+
+    if matching.is_empty() {
+        return Err(OpenError::UnsupportedChannelCount(format.channels.count()));
+    }
+
+    if !matching.iter().any(|v| {
+        let (min, max) = v.sample_rates;
+        (min..=max).contains(&format.sample_rate)
+    }) {
+        return Err(OpenError::UnsupportedSampleRate(format.sample_rate));
+    }
+
+    Ok(())
+}
+
+/// Returns the sample types that hold samples of the given bit depth exactly, best first. Tracks
+/// without a bit depth (`None`) are decoded to floating point samples.
+fn exact_sample_types(bits_per_sample: Option<u32>) -> &'static [SampleFormat] {
+    match bits_per_sample {
+        None => &[SampleFormat::Float32],
+        Some(8) => &[SampleFormat::Unsigned8, SampleFormat::Signed8],
+        Some(16) => &[SampleFormat::Signed16],
+        Some(24) => &[
+            SampleFormat::Signed24,
+            SampleFormat::Signed24Packed,
+            SampleFormat::Signed32,
+        ],
+        Some(32) => &[SampleFormat::Signed32],
+        Some(_) => &[],
+    }
+}
+
+/// Whether a stream in the given format plays a track with the given sample rate, bit depth and
+/// channels without resampling it or losing any of its bits.
+pub fn is_exact_format(
+    format: &FormatInfo,
+    sample_rate: u32,
+    bits_per_sample: Option<u32>,
+    channels: ChannelSpec,
+) -> bool {
+    format.sample_rate == sample_rate
+        && format.channels.count() == channels.count()
+        && exact_sample_types(bits_per_sample).contains(&format.sample_type)
+}
+
+/// Picks the format a device supporting the given formats should be opened with to play a track
+/// with the given sample rate, bit depth and channels exactly (see [is_exact_format]), or None if
+/// there isn't one. Anything the track doesn't decide is taken from the device's default format.
+pub fn find_exact_format(
+    default: FormatInfo,
+    supported: &[SupportedFormat],
+    sample_rate: u32,
+    bits_per_sample: Option<u32>,
+    channels: ChannelSpec,
+) -> Option<FormatInfo> {
+    exact_sample_types(bits_per_sample)
+        .iter()
+        .map(|&sample_type| FormatInfo {
+            sample_type,
+            sample_rate,
+            channels,
+            ..default
+        })
+        .find(|format| check_format_supported(format, supported).is_ok())
+}
+
+/// Returns the formats to try when opening a device, in order, along with a description of each
+/// for logging:
+///
+/// 1. the requested channel layout, at the device's default sample rate and sample type (only if
+///    a channel layout was requested)
+/// 2. the device's default format
+/// 3. stereo, 44.1kHz, signed 16-bit, as a last resort
+///
+/// The requested format always uses the device's default sample rate, because the playback thread
+/// resamples tracks to whatever rate the device is opened with. Formats that are the same as an
+/// earlier step are skipped.
+pub fn fallback_formats(
+    default: FormatInfo,
+    channels: Option<ChannelSpec>,
+) -> Vec<(&'static str, FormatInfo)> {
+    let mut formats = Vec::with_capacity(3);
+
+    if let Some(channels) = channels {
+        formats.push((
+            "requested",
+            FormatInfo {
+                channels,
+                ..default
+            },
+        ));
+    }
+
+    formats.push(("default", default));
+
+    formats.push((
+        "last resort",
+        FormatInfo {
+            sample_type: SampleFormat::Signed16,
+            sample_rate: 44100,
+            channels: ChannelSpec::Count(2),
+            ..default
+        },
+    ));
+
+    let mut seen = Vec::with_capacity(formats.len());
+    formats.retain(|(_, format)| {
+        if seen.contains(format) {
+            false
+        } else {
+            seen.push(*format);
+            true
+        }
+    });
+
+    formats
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn format(sample_type: SampleFormat, sample_rate: u32, channels: u16) -> FormatInfo {
+        FormatInfo {
+            originating_provider: "test",
+            sample_type,
+            sample_rate,
+            buffer_size: BufferSize::Unknown,
+            channels: ChannelSpec::Count(channels),
+        }
+    }
+
+    fn supported(
+        sample_type: SampleFormat,
+        sample_rates: (u32, u32),
+        channels: u16,
+    ) -> SupportedFormat {
+        SupportedFormat {
+            originating_provider: "test",
+            sample_type,
+            sample_rates,
+            buffer_size: BufferSize::Unknown,
+            channels: ChannelSpec::Count(channels),
+        }
+    }
+
+    #[test]
+    fn accepts_supported_formats() {
+        let supported = [
+            supported(SampleFormat::Signed16, (44100, 48000), 2),
+            supported(SampleFormat::Float32, (44100, 192000), 6),
+        ];
+
+        let check = |f| check_format_supported(&f, &supported);
+
+        assert_eq!(check(format(SampleFormat::Signed16, 44100, 2)), Ok(()));
+        assert_eq!(check(format(SampleFormat::Signed16, 48000, 2)), Ok(()));
+        assert_eq!(check(format(SampleFormat::Float32, 96000, 6)), Ok(()));
+    }
+
+    #[test]
+    fn says_which_part_of_the_format_was_rejected() {
+        let supported = [
+            supported(SampleFormat::Signed16, (44100, 48000), 2),
+            supported(SampleFormat::Float32, (44100, 192000), 6),
+        ];
+
+        let check = |f| check_format_supported(&f, &supported);
+
+        assert_eq!(
+            check(format(SampleFormat::Signed24, 44100, 2)),
+            Err(OpenError::UnsupportedSampleType(SampleFormat::Signed24))
+        );
+        assert_eq!(
+            check(format(SampleFormat::Signed16, 44100, 6)),
+            Err(OpenError::UnsupportedChannelCount(6))
+        );
+        assert_eq!(
+            check(format(SampleFormat::Signed16, 96000, 2)),
+            Err(OpenError::UnsupportedSampleRate(96000))
+        );
+        // the rate is supported, but not with this sample type and channel count
+        assert_eq!(
+            check(format(SampleFormat::Float32, 192000, 2)),
+            Err(OpenError::UnsupportedChannelCount(2))
+        );
+    }
+
+    #[test]
+    fn tries_the_requested_layout_then_the_default_then_the_last_resort() {
+        let default = format(SampleFormat::Float32, 48000, 2);
+        let formats = fallback_formats(default, Some(ChannelSpec::Count(6)));
+
+        assert_eq!(
+            formats,
+            [
+                ("requested", format(SampleFormat::Float32, 48000, 6)),
+                ("default", default),
+                ("last resort", format(SampleFormat::Signed16, 44100, 2)),
+            ]
+        );
+    }
+
+    #[test]
+    fn skips_fallback_formats_that_were_already_tried() {
+        let default = format(SampleFormat::Signed16, 44100, 2);
+
+        assert_eq!(fallback_formats(default, None), [("default", default)]);
+        assert_eq!(
+            fallback_formats(default, Some(ChannelSpec::Count(2))),
+            [("requested", default)]
+        );
+    }
+}
@@ -0,0 +1,328 @@
+use std::f32::consts::FRAC_1_SQRT_2;
+
+use crate::{
+    media::playback::{PlaybackFrame, Samples},
+    settings::playback::{DownmixCoefficients, DownmixMode},
+};
+
+use super::{
+    errors::MixError,
+    format::{ChannelSpec, Channels},
+    resample::convert_samples,
+};
+
+/// How loud a mono track is on each side of a stereo device (-3dB), so that it sounds as loud as
+/// it would on a single speaker.
+const MONO_UPMIX_GAIN: f32 = FRAC_1_SQRT_2;
+
+/// Returns how loud a channel at the given position is in the left and right channels when
+/// downmixing to stereo.
+fn stereo_gains(position: Channels, coefficients: &DownmixCoefficients) -> (f32, f32) {
+    let DownmixCoefficients {
+        center,
+        surround,
+        lfe,
+    } = *coefficients;
+
+    let left = Channels::FRONT_LEFT | Channels::FRONT_LEFT_OF_CENTER;
+    let right = Channels::FRONT_RIGHT | Channels::FRONT_RIGHT_OF_CENTER;
+    let centered = Channels::FRONT_CENTER | Channels::TOP_CENTER | Channels::TOP_FRONT_CENTER;
+    let surround_left = Channels::BACK_LEFT
+        | Channels::SIDE_LEFT
+        | Channels::TOP_FRONT_LEFT
+        | Channels::TOP_BACK_LEFT;
+    let surround_right = Channels::BACK_RIGHT
+        | Channels::SIDE_RIGHT
+        | Channels::TOP_FRONT_RIGHT
+        | Channels::TOP_BACK_RIGHT;
+
+    if left.contains(position) {
+        (1.0, 0.0)
+    } else if right.contains(position) {
+        (0.0, 1.0)
+    } else if centered.contains(position) {
+        (center, center)
+    } else if position == Channels::LOW_FREQUENCY {
+        (lfe, lfe)
+    } else if surround_left.contains(position) {
+        (surround, 0.0)
+    } else if surround_right.contains(position) {
+        (0.0, surround)
+    } else {
+        // back center channels are split between the back left and right channels
+        (surround * FRAC_1_SQRT_2, surround * FRAC_1_SQRT_2)
+    }
+}
+
+/// Maps the channels of a track onto the channels of the device it's played on. Channels are
+/// moved to the device's position for them, mono tracks are played on both sides of the device,
+/// and channels the device doesn't have are downmixed into the ones it does.
+///
+/// When the position of the channels isn't known for either the track or the device, and there's
+/// no usual layout for their number of channels, each channel is played on the device channel
+/// with the same index instead.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ChannelMixer {
+    /// How loud each of the track's channels is in each of the device's channels, indexed by
+    /// device channel and then track channel. None if the track's channels are played as they
+    /// are.
+    matrix: Option<Vec<Vec<f32>>>,
+}
+
+impl ChannelMixer {
+    /// Creates a mixer from the track's channels to the device's. Fails if the device has fewer
+    /// channels than the track and downmixing is turned off.
+    pub fn new(
+        source: ChannelSpec,
+        target: ChannelSpec,
+        mode: DownmixMode,
+        coefficients: &DownmixCoefficients,
+    ) -> Result<Self, MixError> {
+        let inputs = source.count() as usize;
+        let outputs = target.count() as usize;
+
+        if outputs > 0 && inputs > outputs && mode == DownmixMode::Refuse {
+            return Err(MixError::DownmixRefused(source.count(), target.count()));
+        }
+
+        let unchanged = inputs == outputs && (inputs == 1 || source.layout() == target.layout());
+        if unchanged || inputs == 0 || outputs == 0 {
+            return Ok(Self { matrix: None });
+        }
+
+        let matrix = match (source.layout(), target.layout()) {
+            (_, Some(target)) if inputs == 1 => upmix_mono(target),
+            (Some(source), Some(target)) => remix(source, target, coefficients),
+            _ => by_index(inputs, outputs),
+        };
+
+        Ok(Self {
+            matrix: Some(matrix),
+        })
+    }
+
+    /// Maps a frame of the track's audio onto the device's channels. Frames that are played as
+    /// they are keep their sample format, while mixed frames are converted to f32.
+    pub fn apply(&self, frame: PlaybackFrame) -> PlaybackFrame {
+        let Some(matrix) = &self.matrix else {
+            return frame;
+        };
+
+        let source: Vec<Vec<f32>> = convert_samples(frame.samples);
+        let frames = source.first().map_or(0, Vec::len);
+
+        let samples = matrix
+            .iter()
+            .map(|gains| {
+                (0..frames)
+                    .map(|i| {
+                        gains
+                            .iter()
+                            .zip(&source)
+                            .filter(|(gain, _)| **gain != 0.0)
+                            .map(|(gain, channel)| gain * channel[i])
+                            .sum::<f32>()
+                            .clamp(-1.0, 1.0)
+                    })
+                    .collect()
+            })
+            .collect();
+
+        PlaybackFrame {
+            samples: Samples::Float32(samples),
+            rate: frame.rate,
+        }
+    }
+}
+
+/// Plays a mono track on the front left and right channels of the device, at -3dB each. Devices
+/// without those get it on the front center channel instead, or their first channel.
+fn upmix_mono(target: Channels) -> Vec<Vec<f32>> {
+    let stereo = Channels::FRONT_LEFT | Channels::FRONT_RIGHT;
+
+    target
+        .iter()
+        .enumerate()
+        .map(|(index, position)| {
+            let gain = if target.contains(stereo) {
+                if stereo.contains(position) {
+                    MONO_UPMIX_GAIN
+                } else {
+                    0.0
+                }
+            } else if target.contains(Channels::FRONT_CENTER) {
+                if position == Channels::FRONT_CENTER {
+                    1.0
+                } else {
+                    0.0
+                }
+            } else if index == 0 {
+                1.0
+            } else {
+                0.0
+            };
+
+            vec![gain]
+        })
+        .collect()
+}
+
+/// Moves each of the track's channels to the device's channel at the same position. Channels the
+/// device doesn't have are downmixed into its front left and right channels. Mono devices get
+/// the average of what the left and right channels would have been.
+fn remix(source: Channels, target: Channels, coefficients: &DownmixCoefficients) -> Vec<Vec<f32>> {
+    if target.count() == 1 {
+        let gains = source
+            .iter()
+            .map(|position| {
+                let (left, right) = stereo_gains(position, coefficients);
+                (left + right) / 2.0
+            })
+            .collect();
+
+        return vec![gains];
+    }
+
+    let mut matrix = vec![vec![0.0; source.count() as usize]; target.count() as usize];
+    let target_index =
+        |position: Channels| target.iter().position(|candidate| candidate == position);
+
+    let left = target_index(Channels::FRONT_LEFT);
+    let right = target_index(Channels::FRONT_RIGHT);
+
+    for (input, position) in source.iter().enumerate() {
+        // 5.1 is sometimes laid out with side channels instead of back ones
+        let counterpart = counterpart(position).filter(|other| !source.contains(*other));
+
+        if let Some(output) = target_index(position).or_else(|| counterpart.and_then(target_index))
+        {
+            matrix[output][input] = 1.0;
+        } else if let (Some(left), Some(right)) = (left, right) {
+            let (left_gain, right_gain) = stereo_gains(position, coefficients);
+            matrix[left][input] += left_gain;
+            matrix[right][input] += right_gain;
+        }
+    }
+
+    matrix
+}
+
+/// Returns the position that a channel can be played at instead, if the device doesn't have its
+/// own position.
+fn counterpart(position: Channels) -> Option<Channels> {
+    if position == Channels::SIDE_LEFT {
+        Some(Channels::BACK_LEFT)
+    } else if position == Channels::SIDE_RIGHT {
+        Some(Channels::BACK_RIGHT)
+    } else if position == Channels::BACK_LEFT {
+        Some(Channels::SIDE_LEFT)
+    } else if position == Channels::BACK_RIGHT {
+        Some(Channels::SIDE_RIGHT)
+    } else {
+        None
+    }
+}
+
+/// Plays each of the track's channels on the device's channel with the same index, leaving out
+/// the channels the device doesn't have.
+fn by_index(inputs: usize, outputs: usize) -> Vec<Vec<f32>> {
+    (0..outputs)
+        .map(|output| {
+            (0..inputs)
+                .map(|input| if input == output { 1.0 } else { 0.0 })
+                .collect()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mixer(source: ChannelSpec, target: ChannelSpec) -> ChannelMixer {
+        ChannelMixer::new(
+            source,
+            target,
+            DownmixMode::Downmix,
+            &DownmixCoefficients::default(),
+        )
+        .unwrap()
+    }
+
+    fn mix(mixer: &ChannelMixer, samples: Vec<Vec<f32>>) -> Vec<Vec<f32>> {
+        let frame = PlaybackFrame {
+            samples: Samples::Float32(samples),
+            rate: 44100,
+        };
+
+        match mixer.apply(frame).samples {
+            Samples::Float32(samples) => samples,
+            _ => panic!("mixed frames should be f32"),
+        }
+    }
+
+    #[test]
+    fn plays_mono_on_both_sides_at_minus_3db() {
+        let mixer = mixer(ChannelSpec::Count(1), ChannelSpec::Count(2));
+        let side = 0.5 * MONO_UPMIX_GAIN;
+
+        assert_eq!(mix(&mixer, vec![vec![0.5]]), [[side], [side]]);
+    }
+
+    #[test]
+    fn averages_stereo_for_mono_devices() {
+        let mixer = mixer(ChannelSpec::Count(2), ChannelSpec::Count(1));
+
+        assert_eq!(mix(&mixer, vec![vec![0.25], vec![0.75]]), [[0.5]]);
+    }
+
+    #[test]
+    fn plays_matching_channels_as_they_are() {
+        let stereo = Channels::FRONT_LEFT | Channels::FRONT_RIGHT;
+        let mixer = mixer(ChannelSpec::Count(2), ChannelSpec::Bitmask(stereo));
+
+        assert_eq!(mixer, ChannelMixer::default());
+
+        let frame = PlaybackFrame {
+            samples: Samples::Signed16(vec![vec![1], vec![2]]),
+            rate: 44100,
+        };
+        assert!(matches!(mixer.apply(frame).samples, Samples::Signed16(_)));
+    }
+
+    #[test]
+    fn moves_side_channels_to_the_back() {
+        let front = Channels::FRONT_LEFT | Channels::FRONT_RIGHT;
+        let sides = front | Channels::SIDE_LEFT | Channels::SIDE_RIGHT;
+        let backs = front | Channels::BACK_LEFT | Channels::BACK_RIGHT;
+        let mixer = mixer(ChannelSpec::Bitmask(sides), ChannelSpec::Bitmask(backs));
+
+        let samples = vec![vec![0.1], vec![0.2], vec![0.3], vec![0.4]];
+        assert_eq!(mix(&mixer, samples.clone()), samples);
+    }
+
+    #[test]
+    fn downmixes_the_center_into_both_sides() {
+        let front = Channels::FRONT_LEFT | Channels::FRONT_RIGHT;
+        let mixer = mixer(
+            ChannelSpec::Bitmask(front | Channels::FRONT_CENTER),
+            ChannelSpec::Bitmask(front),
+        );
+        let center = 0.5 * FRAC_1_SQRT_2;
+
+        let mixed = mix(&mixer, vec![vec![0.0], vec![0.0], vec![0.5]]);
+        assert_eq!(mixed, [[center], [center]]);
+    }
+
+    #[test]
+    fn refuses_to_downmix_when_turned_off() {
+        let result = ChannelMixer::new(
+            ChannelSpec::Count(6),
+            ChannelSpec::Count(2),
+            DownmixMode::Refuse,
+            &DownmixCoefficients::default(),
+        );
+
+        assert!(matches!(result, Err(MixError::DownmixRefused(6, 2))));
+    }
+}
@@ -4,9 +4,16 @@ use intx::{I24, U24};
 use rubato::{FftFixedIn, VecResampler};
 use tracing::info;
 
-use crate::media::playback::{PlaybackFrame, Samples};
+use crate::{
+    media::playback::{PlaybackFrame, Samples},
+    settings::playback::DitherMode,
+};
 
-use super::format::{FormatInfo, SampleFormat};
+use super::{
+    dither::Dither,
+    format::{FormatInfo, SampleFormat},
+    mixer::ChannelMixer,
+};
 
 fn scale<T, U>(target: Vec<Vec<T>>) -> Vec<Vec<U>>
 where
@@ -21,7 +28,7 @@ where
 
 pub fn convert_samples<T>(target_frame: Samples) -> Vec<Vec<T>>
 where
-    T: Copy + SampleInto<f64> + SampleFrom<f64>,
+    T: Copy + SampleFrom<f64>,
 {
     match target_frame {
         Samples::Float64(v) => scale(v),
@@ -164,15 +171,56 @@ pub fn match_bit_depth(target_frame: PlaybackFrame, target_depth: SampleFormat)
     PlaybackFrame { samples, rate }
 }
 
+/// The slowest and fastest speeds tracks can be played at, as a multiple of their normal speed.
+pub const MIN_SPEED: f64 = 0.5;
+pub const MAX_SPEED: f64 = 2.0;
+
+/// Returns the sample rate a track's samples are treated as having, so that resampling them to
+/// the device's rate plays them at the given speed. Other than at normal speed, the rate is
+/// rounded to a multiple of 100 Hz, which keeps the resampler's FFTs small at the cost of the
+/// speed being slightly off (by less than 0.2%).
+fn speed_rate(orig_rate: u32, speed: f64) -> u32 {
+    if speed == 1.0 {
+        return orig_rate;
+    }
+
+    ((f64::from(orig_rate) * speed / 100.0).round() as u32 * 100).max(100)
+}
+
 pub struct Resampler {
     resampler: FftFixedIn<f32>,
+    /// Maps the track's channels onto the device's, before the samples are resampled.
+    mixer: ChannelMixer,
     duration: u64,
     input_buffer: Vec<VecDeque<f32>>,
     eof: bool,
+    /// The track's and the device's sample rates, and the number of channels the device has,
+    /// which the resampler is created again with when the speed changes.
+    orig_rate: u32,
+    target_rate: u32,
+    channels: u16,
+    /// How fast the track is played, as a multiple of its normal speed. Tracks are sped up or
+    /// slowed down by resampling them as if they had a different sample rate, so the pitch
+    /// changes along with the speed.
+    speed: f64,
+    /// Adds dither when the device's sample type has fewer bits than the track's.
+    dither: Dither,
 }
 
 impl Resampler {
-    pub fn new(orig_rate: u32, target_rate: u32, duration: u64, channels: u16) -> Self {
+    /// Creates a resampler for a track. `channels` is the number of channels the device has, which
+    /// is what `mixer` maps the track's channels to. `speed` is how fast the track is played (see
+    /// [Resampler::set_speed]), and `dither` is the dither added when bits are lost converting
+    /// samples to the device's sample type.
+    pub fn new(
+        orig_rate: u32,
+        target_rate: u32,
+        duration: u64,
+        channels: u16,
+        mixer: ChannelMixer,
+        speed: f64,
+        dither: DitherMode,
+    ) -> Self {
         if orig_rate != target_rate {
             info!(
                 "Resampling required, resampling from {:?} to {:?} (duration {:?})",
@@ -180,23 +228,69 @@ impl Resampler {
             );
         }
 
-        let resampler = FftFixedIn::<f32>::new(
-            orig_rate as usize,
-            target_rate as usize,
-            duration as usize,
-            2,
-            channels as usize,
-        )
-        .unwrap();
+        let speed = speed.clamp(MIN_SPEED, MAX_SPEED);
 
         Resampler {
-            resampler,
+            resampler: Self::create_resampler(orig_rate, target_rate, duration, channels, speed),
+            mixer,
             duration,
             input_buffer: (0..channels)
                 .map(|_| VecDeque::with_capacity(duration as usize * 2))
                 .collect(),
             eof: false,
+            orig_rate,
+            target_rate,
+            channels,
+            speed,
+            dither: Dither::new(dither),
+        }
+    }
+
+    fn create_resampler(
+        orig_rate: u32,
+        target_rate: u32,
+        duration: u64,
+        channels: u16,
+        speed: f64,
+    ) -> FftFixedIn<f32> {
+        FftFixedIn::<f32>::new(
+            speed_rate(orig_rate, speed) as usize,
+            target_rate as usize,
+            duration as usize,
+            2,
+            channels as usize,
+        )
+        .unwrap()
+    }
+
+    /// Changes how fast the track is played, as a multiple of its normal speed, without
+    /// restarting it. The position of the track isn't affected, since it's counted in the
+    /// track's own samples.
+    pub fn set_speed(&mut self, speed: f64) {
+        let speed = speed.clamp(MIN_SPEED, MAX_SPEED);
+        if speed == self.speed {
+            return;
         }
+
+        self.speed = speed;
+        self.resampler = Self::create_resampler(
+            self.orig_rate,
+            self.target_rate,
+            self.duration,
+            self.channels,
+            speed,
+        );
+
+        // samples waiting to be resampled would be skipped once no resampling is needed, but
+        // there's less than one chunk of them
+        if self.passthrough() {
+            self.input_buffer.iter_mut().for_each(VecDeque::clear);
+        }
+    }
+
+    /// Whether the track's samples can be played without resampling them.
+    fn passthrough(&self) -> bool {
+        self.orig_rate == self.target_rate && self.speed == 1.0
     }
 
     pub fn convert_formats(
@@ -204,8 +298,10 @@ impl Resampler {
         frame: PlaybackFrame,
         target_format: &FormatInfo,
     ) -> PlaybackFrame {
-        if target_format.sample_rate == frame.rate {
-            return match_bit_depth(frame, target_format.sample_type);
+        let frame = self.mixer.apply(frame);
+
+        if target_format.sample_rate == frame.rate && self.speed == 1.0 {
+            return self.convert_depth(frame, target_format.sample_type);
         }
         let source: Vec<Vec<f32>> = convert_samples(frame.samples);
 
@@ -227,7 +323,7 @@ impl Resampler {
             //         .expect("resampler error")
             // }
 
-            match_bit_depth(
+            self.convert_depth(
                 PlaybackFrame {
                     samples: Samples::Float32(Vec::with_capacity(0)),
                     rate: target_format.sample_rate,
@@ -246,7 +342,7 @@ impl Resampler {
                 .process(&split, None)
                 .expect("resampler error");
 
-            match_bit_depth(
+            self.convert_depth(
                 PlaybackFrame {
                     samples: Samples::Float32(resampled),
                     rate: target_format.sample_rate,
@@ -256,6 +352,25 @@ impl Resampler {
         }
     }
 
+    /// Changes the dither added when bits are lost converting samples to the device's sample
+    /// type. Takes effect from the next frame.
+    pub fn set_dither(&mut self, dither: DitherMode) {
+        self.dither = Dither::new(dither);
+    }
+
+    /// Converts the frame to the device's sample type (see [match_bit_depth]), adding dither if
+    /// that loses bits.
+    fn convert_depth(&mut self, frame: PlaybackFrame, target_depth: SampleFormat) -> PlaybackFrame {
+        if self.dither.needed(&frame.samples, target_depth) {
+            return PlaybackFrame {
+                samples: self.dither.apply(frame.samples, target_depth),
+                rate: frame.rate,
+            };
+        }
+
+        match_bit_depth(frame, target_depth)
+    }
+
     pub fn eof(&mut self) {
         self.eof = true;
     }
@@ -3,11 +3,12 @@
 use crate::media::playback::PlaybackFrame;
 
 use super::{
+    category::{DeviceCategory, classify_device_name},
     errors::{
         CloseError, FindError, InfoError, InitializationError, ListError, OpenError, ResetError,
         StateError, SubmissionError,
     },
-    format::{FormatInfo, SupportedFormat},
+    format::{ChannelSpec, FormatInfo, SupportedFormat, find_exact_format},
 };
 
 /// The DeviceProvider trait defines the methods used to interact with a device provider. A device
@@ -24,6 +25,35 @@ pub trait DeviceProvider {
     fn get_default_device(&mut self) -> Result<Box<dyn Device>, FindError>;
     /// Requests the device provider find and return a device by its UID.
     fn get_device_by_uid(&mut self, id: &str) -> Result<Box<dyn Device>, FindError>;
+    /// Returns the UID of the system's default device, or None if there isn't one. This is checked
+    /// every so often while playing on the default device, so that playback can move when the
+    /// user switches to another one. Providers that can ask the system without opening the device
+    /// should do so; by default, the default device is found and asked for its UID.
+    fn get_default_device_uid(&mut self) -> Option<String> {
+        self.get_default_device().ok()?.get_uid().ok()
+    }
+    /// Returns the name and UID of each device audio can be played through, for the user to pick
+    /// from. Devices whose name or UID can't be read are left out.
+    fn list_devices(&mut self) -> Result<Vec<DeviceInfo>, ListError> {
+        Ok(self
+            .get_devices()?
+            .iter()
+            .filter_map(|device| {
+                Some(DeviceInfo {
+                    name: device.get_name().ok()?,
+                    uid: device.get_uid().ok()?,
+                })
+            })
+            .collect())
+    }
+}
+
+/// A device as it's shown to the user (see [DeviceProvider::list_devices]).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeviceInfo {
+    pub name: String,
+    /// What the device is found by with [DeviceProvider::get_device_by_uid].
+    pub uid: String,
 }
 
 pub trait Device {
@@ -34,11 +64,36 @@ pub trait Device {
     fn get_supported_formats(&self) -> Result<Vec<SupportedFormat>, InfoError>;
     /// Returns the device's default format.
     fn get_default_format(&self) -> Result<FormatInfo, InfoError>;
+    /// Returns the format to open the device with to play a track with the given sample rate, bit
+    /// depth and channels without converting it, or None if the device can't. `bits_per_sample`
+    /// is None for tracks that are decoded to floating point samples. By default, the device's
+    /// supported formats are searched for an exact match.
+    fn get_exact_format(
+        &self,
+        sample_rate: u32,
+        bits_per_sample: Option<u32>,
+        channels: ChannelSpec,
+    ) -> Option<FormatInfo> {
+        find_exact_format(
+            self.get_default_format().ok()?,
+            &self.get_supported_formats().ok()?,
+            sample_rate,
+            bits_per_sample,
+            channels,
+        )
+    }
     /// Returns the name of the device.
     fn get_name(&self) -> Result<String, InfoError>;
     /// Returns the UID of the device. If the provider is unable to provide a UID, it should return
     /// the name of the device.
     fn get_uid(&self) -> Result<String, InfoError>;
+    /// Returns what kind of device this is. Providers that can ask the system for the device's
+    /// form factor should do so; by default, it's guessed from the device's name.
+    fn get_category(&self) -> DeviceCategory {
+        self.get_name()
+            .map(|name| classify_device_name(&name))
+            .unwrap_or(DeviceCategory::Unknown)
+    }
     /// This function returns true if resampling and bit-depth matching is required to play audio
     /// on this device. If the device supports playing arbitrary bit-depths and sample-rates
     /// without advanced notice, this function should return false. If the device requires a
@@ -78,4 +133,26 @@ pub trait OutputStream {
     /// control, and will instead use this value to adjust the volume of the audio data before
     /// submitting it to the device.
     fn set_volume(&mut self, volume: f64) -> Result<(), StateError>;
+    /// Returns how many frames (samples per channel) the device has taken from the stream since
+    /// it was opened, or None if the provider can't tell. The playback thread compares this with
+    /// what it has submitted, to notice when the device stops playing without reporting an error.
+    ///
+    /// Providers should count frames in the device's own callback, with an atomic counter or
+    /// something similarly cheap. Streams that report this must not block in submit_frame forever
+    /// once the device stops taking samples.
+    fn frames_consumed(&self) -> Option<u64> {
+        None
+    }
+    /// Returns how many times the device has run out of samples to play since the stream was
+    /// opened, or None if the provider can't tell. Each underrun is heard as a crackle or a short
+    /// dropout. Like frames_consumed, this should be counted in the device's own callback (see
+    /// [crate::devices::util::UnderrunCounter]).
+    fn underruns(&self) -> Option<u64> {
+        None
+    }
+    /// Returns how many frames the stream buffers between the playback thread and the device, if
+    /// the provider knows.
+    fn buffer_frames(&self) -> Option<usize> {
+        None
+    }
 }
@@ -1,3 +1,8 @@
+use std::sync::{
+    Arc,
+    atomic::{AtomicBool, AtomicU64, Ordering},
+};
+
 use intx::{I24, U24};
 
 use super::resample::{SampleFrom, SampleInto};
@@ -87,3 +92,32 @@ impl Scale for Vec<Vec<f64>> {
             .collect()
     }
 }
+
+/// Counts underruns in a device callback, for [crate::devices::traits::OutputStream::underruns].
+/// A run of callbacks that come up short counts as one underrun, and nothing counts until the
+/// stream has first been given something to play, since it starts out empty.
+///
+/// Some callbacks can't be mutable (like AudioGraph's event handlers), so the state is atomic.
+pub struct UnderrunCounter {
+    count: Arc<AtomicU64>,
+    starved: AtomicBool,
+}
+
+impl UnderrunCounter {
+    pub fn new(count: Arc<AtomicU64>) -> Self {
+        UnderrunCounter {
+            count,
+            starved: AtomicBool::new(true),
+        }
+    }
+
+    /// Records a callback that was given `read` of the `wanted` samples it needed.
+    pub fn record(&self, read: usize, wanted: usize) {
+        let short = read < wanted;
+        let was_starved = self.starved.swap(short, Ordering::Relaxed);
+
+        if short && !was_starved {
+            self.count.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
@@ -1,3 +1,4 @@
+pub mod album_query;
 pub mod db;
 pub mod playlist;
 pub mod scan;
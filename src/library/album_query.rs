@@ -0,0 +1,279 @@
+//! The queries the album list is loaded with. Sorting, filtering and leaving out hidden albums all
+//! change the same query, so they're put together here instead of having a query for every
+//! combination of them.
+
+use chrono::{Datelike, Local, NaiveTime, TimeZone, Utc};
+use sqlx::{QueryBuilder, Sqlite};
+
+use super::db::AlbumSortMethod;
+
+/// The year an album's release came out, from its release date or otherwise its release year.
+const RELEASE_YEAR: &str =
+    "COALESCE(CAST(strftime('%Y', p.release_date) AS INTEGER), p.release_year)";
+
+/// The year an album first came out, for reissues and remasters that were tagged with it.
+const ORIGINAL_YEAR: &str =
+    "COALESCE(CAST(strftime('%Y', p.original_date) AS INTEGER), p.original_year)";
+
+/// The year an album is shown, sorted and filtered by: the year it first came out, then the year
+/// of the release, or the other way around if original dates aren't preferred. This is the same
+/// as [`Album::display_date`](super::types::Album::display_date), in SQL.
+fn year(prefer_original: bool) -> String {
+    if prefer_original {
+        format!("COALESCE({ORIGINAL_YEAR}, {RELEASE_YEAR})")
+    } else {
+        format!("COALESCE({RELEASE_YEAR}, {ORIGINAL_YEAR})")
+    }
+}
+
+/// The full date an album is sorted by within a year, following the same preference as [`year`].
+fn date(prefer_original: bool) -> &'static str {
+    if prefer_original {
+        "COALESCE(p.original_date, p.release_date)"
+    } else {
+        "COALESCE(p.release_date, p.original_date)"
+    }
+}
+
+/// Albums whose tracks are all lossless. Tracks that haven't been scanned since the format was
+/// stored (where it's NULL) aren't known to be lossless, so their albums aren't included.
+const LOSSLESS_ALBUMS: &str =
+    "SELECT album_id FROM track GROUP BY album_id HAVING MIN(COALESCE(lossless, 0)) = 1";
+
+/// Albums with at least one lossy track.
+const LOSSY_ALBUMS: &str = "SELECT album_id FROM track WHERE lossless = 0";
+
+/// Whether an album's tracks are compressed without losing anything (like FLAC), or not (like
+/// MP3). Albums with both are counted as lossy.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum AlbumFormat {
+    Lossless,
+    Lossy,
+}
+
+/// Narrows down the album list. Albums have to match every filter that's set.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct AlbumFilter {
+    /// The first year of the decade the albums were released in, like 1970.
+    pub decade: Option<i64>,
+    pub format: Option<AlbumFormat>,
+    /// Only albums that were added to the library this month.
+    pub added_this_month: bool,
+}
+
+impl AlbumFilter {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Which filters match any albums in the library, so that the ones that don't can be left out.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct AlbumFilterOptions {
+    /// The decades albums were released in, oldest first.
+    pub decades: Vec<i64>,
+    pub lossless: bool,
+    pub lossy: bool,
+    pub added_this_month: bool,
+}
+
+/// How the album list is sorted and filtered.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AlbumQuery {
+    pub sort: AlbumSortMethod,
+    pub include_hidden: bool,
+    pub filter: AlbumFilter,
+    /// Whether albums are sorted and filtered by when they first came out, rather than when the
+    /// reissue or remaster did (see [`year`]).
+    pub prefer_original_date: bool,
+}
+
+impl AlbumQuery {
+    /// Builds the query for the album list. Each album's id is returned with its sort title, its
+    /// artist's sort name when sorting by artist, or the year it was released in (or an empty
+    /// string if that's unknown) when sorting by release date.
+    pub fn build(&self) -> QueryBuilder<'static, Sqlite> {
+        let (key, order) = sort_clauses(self.sort, self.prefer_original_date);
+        let by_artist = matches!(
+            self.sort,
+            AlbumSortMethod::ArtistAsc | AlbumSortMethod::ArtistDesc
+        );
+
+        let mut query = QueryBuilder::new("SELECT p.id, ");
+        query.push(key).push(" FROM album p");
+
+        // albums without an artist aren't listed when sorting by artist
+        if by_artist {
+            query.push(" JOIN artist a ON p.artist_id = a.id");
+        }
+
+        push_conditions(
+            &mut query,
+            self.include_hidden,
+            &self.filter,
+            self.prefer_original_date,
+        );
+        query.push(" ORDER BY ").push(order);
+
+        query
+    }
+}
+
+/// Returns the row key and the ORDER BY clause of a sort method.
+fn sort_clauses(sort: AlbumSortMethod, prefer_original: bool) -> (String, String) {
+    let year = year(prefer_original);
+    let date = date(prefer_original);
+    let year_key = format!("COALESCE(CAST({year} AS TEXT), '')");
+    let by_year = |direction: &str| {
+        format!("{year} {direction}, {date} {direction}, p.title_sortable COLLATE NOCASE ASC")
+    };
+
+    let (key, order) = match sort {
+        AlbumSortMethod::TitleAsc => (
+            "p.title_sortable".to_string(),
+            "p.title_sortable COLLATE NOCASE ASC".to_string(),
+        ),
+        AlbumSortMethod::TitleDesc => (
+            "p.title_sortable".to_string(),
+            "p.title_sortable COLLATE NOCASE DESC".to_string(),
+        ),
+        AlbumSortMethod::ArtistAsc => (
+            "a.name_sortable".to_string(),
+            "a.name_sortable COLLATE NOCASE ASC, p.release_date ASC".to_string(),
+        ),
+        AlbumSortMethod::ArtistDesc => (
+            "a.name_sortable".to_string(),
+            "a.name_sortable COLLATE NOCASE DESC, p.release_date ASC".to_string(),
+        ),
+        AlbumSortMethod::ReleaseAsc => (year_key, by_year("ASC")),
+        AlbumSortMethod::ReleaseDesc => (year_key, by_year("DESC")),
+        AlbumSortMethod::LabelAsc => (
+            "p.title_sortable".to_string(),
+            "p.label COLLATE NOCASE ASC, p.catalog_number COLLATE NOCASE ASC, \
+                p.release_date ASC"
+                .to_string(),
+        ),
+        AlbumSortMethod::LabelDesc => (
+            "p.title_sortable".to_string(),
+            "p.label COLLATE NOCASE DESC, p.catalog_number COLLATE NOCASE ASC, \
+                p.release_date ASC"
+                .to_string(),
+        ),
+        AlbumSortMethod::CatalogAsc => (
+            "p.title_sortable".to_string(),
+            "p.catalog_number COLLATE NOCASE ASC, p.release_date ASC".to_string(),
+        ),
+        AlbumSortMethod::CatalogDesc => (
+            "p.title_sortable".to_string(),
+            "p.catalog_number COLLATE NOCASE DESC, p.release_date ASC".to_string(),
+        ),
+    };
+
+    (key, order)
+}
+
+/// Adds the WHERE clause that leaves out hidden albums (unless they're included) and albums that
+/// don't match the filter. The album table has to be called `p`.
+fn push_conditions(
+    query: &mut QueryBuilder<'static, Sqlite>,
+    include_hidden: bool,
+    filter: &AlbumFilter,
+    prefer_original: bool,
+) {
+    query
+        .push(" WHERE (")
+        .push_bind(include_hidden)
+        .push(" OR NOT p.hidden)");
+
+    if let Some(decade) = filter.decade {
+        query
+            .push(" AND ")
+            .push(year(prefer_original))
+            .push(" BETWEEN ")
+            .push_bind(decade)
+            .push(" AND ")
+            .push_bind(decade + 9);
+    }
+
+    match filter.format {
+        Some(AlbumFormat::Lossless) => {
+            query.push(" AND p.id IN (").push(LOSSLESS_ALBUMS).push(")");
+        }
+        Some(AlbumFormat::Lossy) => {
+            query.push(" AND p.id IN (").push(LOSSY_ALBUMS).push(")");
+        }
+        None => {}
+    }
+
+    if filter.added_this_month {
+        query
+            .push(" AND p.created_at >= ")
+            .push_bind(start_of_month());
+    }
+}
+
+/// Builds the query for the decades albums were released in, oldest first.
+pub fn decades_query(include_hidden: bool, prefer_original: bool) -> QueryBuilder<'static, Sqlite> {
+    let year = year(prefer_original);
+
+    let mut query = QueryBuilder::new("SELECT DISTINCT ");
+    query.push(&year).push(" / 10 * 10 AS decade FROM album p");
+    push_conditions(
+        &mut query,
+        include_hidden,
+        &AlbumFilter::default(),
+        prefer_original,
+    );
+    query
+        .push(" AND ")
+        .push(year)
+        .push(" IS NOT NULL ORDER BY decade ASC");
+
+    query
+}
+
+/// Builds a query for whether any albums are lossless, whether any are lossy, and whether any
+/// were added this month, in that order.
+pub fn filter_flags_query(include_hidden: bool) -> QueryBuilder<'static, Sqlite> {
+    let filters = [
+        AlbumFilter {
+            format: Some(AlbumFormat::Lossless),
+            ..Default::default()
+        },
+        AlbumFilter {
+            format: Some(AlbumFormat::Lossy),
+            ..Default::default()
+        },
+        AlbumFilter {
+            added_this_month: true,
+            ..Default::default()
+        },
+    ];
+
+    let mut query = QueryBuilder::new("SELECT ");
+    for (i, filter) in filters.iter().enumerate() {
+        if i > 0 {
+            query.push(", ");
+        }
+
+        query.push("EXISTS (SELECT 1 FROM album p");
+        // none of these filter by decade, so which date is preferred doesn't matter
+        push_conditions(&mut query, include_hidden, filter, true);
+        query.push(")");
+    }
+
+    query
+}
+
+/// The start of the current month, in the format (and the time zone, UTC) that SQLite stores
+/// timestamps in.
+fn start_of_month() -> String {
+    let today = Local::now().date_naive();
+    let first = today.with_day(1).unwrap_or(today).and_time(NaiveTime::MIN);
+    let start = Local
+        .from_local_datetime(&first)
+        .earliest()
+        .map_or_else(|| first.and_utc(), |start| start.with_timezone(&Utc));
+
+    start.format("%Y-%m-%d %H:%M:%S").to_string()
+}
@@ -1,63 +1,180 @@
 use std::{path::Path, sync::Arc};
 
 use gpui::App;
+use rustc_hash::{FxHashMap, FxHashSet};
 use sqlx::{
-    SqlitePool,
+    Executor, Sqlite, SqliteConnection, SqlitePool,
+    migrate::{Migrate, MigrateError, Migration, Migrator},
     sqlite::{SqliteConnectOptions, SqliteJournalMode, SqliteSynchronous},
 };
-use tracing::debug;
+use tracing::{debug, error, info};
 
 use crate::{
-    library::types::{Playlist, PlaylistItem, PlaylistWithCount, TrackStats},
+    library::{
+        album_query::{AlbumFilterOptions, AlbumQuery, decades_query, filter_flags_query},
+        types::{
+            AlbumSummary, ArtSource, GroupingTrack, Playlist, PlaylistItem, PlaylistTrackSummary,
+            PlaylistWithCount, ResolvedTrack, TrackReference, TrackStats, UntaggedAlbum,
+        },
+    },
+    paths::DataPaths,
     ui::app::Pool,
 };
 
 use super::types::{Album, Artist, Track};
 
-pub async fn create_pool(path: impl AsRef<Path>) -> sqlx::Result<SqlitePool> {
-    debug!("Creating database pool at {:?}", path.as_ref());
-    let options = SqliteConnectOptions::new()
-        .filename(path)
+/// How many prepared statements each connection keeps around to be reused, so that frequent
+/// queries (like checking whether a playlist has a track for every visible row) aren't prepared
+/// again every time they run.
+///
+/// The cache used to be turned off entirely, without a recorded reason. The likely concern is
+/// memory: a cached statement can keep the values bound to it and the last row it returned until
+/// it's next used, which for the album art queries means holding on to whole images. Those
+/// queries are run with `persistent(false)` instead, so that they aren't cached.
+const STATEMENT_CACHE_CAPACITY: usize = 100;
+
+/// The options the library's connections are opened with, apart from which file they open.
+fn connect_options() -> SqliteConnectOptions {
+    SqliteConnectOptions::new()
         .optimize_on_close(true, None)
         .synchronous(SqliteSynchronous::Normal)
         .journal_mode(SqliteJournalMode::Wal)
-        .statement_cache_capacity(0)
-        .create_if_missing(true);
+        .statement_cache_capacity(STATEMENT_CACHE_CAPACITY)
+}
+
+pub async fn create_pool(path: impl AsRef<Path>) -> sqlx::Result<SqlitePool> {
+    debug!("Creating database pool at {:?}", path.as_ref());
+    let options = connect_options().filename(path).create_if_missing(true);
     let pool = SqlitePool::connect_with(options).await?;
 
     sqlx::query("PRAGMA mmap_size = 30000000000")
         .execute(&pool)
         .await?;
 
-    let migrations = sqlx::migrate!("./migrations")
-        .set_ignore_missing(true)
-        .run(&pool)
-        .await;
+    Ok(pool)
+}
+
+/// How far along the database migrations are.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MigrationProgress {
+    /// The migration being applied, counting from 1.
+    pub current: usize,
+    pub total: usize,
+}
 
-    if let Err(e) = migrations
-        && let sqlx::migrate::MigrateError::VersionMismatch(v) = e
-    {
-        match v {
-            20240730163128 | 20240730163151 | 20240730163200 | 20240817201809 | 20240817201912
-            | 20240917084650 | 20250424090924 | 20250512214434 | 20250512231103
-            | 20250825224757 | 20250825225240 | 20250825234341 | 20251022214837
-                if cfg!(target_os = "windows") =>
-            {
-                // it's likely this is because of a line-ending caused hash mismatch
-                // this is fixed but not on existing databases
-                let fix_query = include_str!("../../queries/windows_fix_checksums.sql");
-                sqlx::query(fix_query).execute(&pool).await?;
-
-                sqlx::migrate!("./migrations")
-                    .set_ignore_missing(true)
-                    .run(&pool)
-                    .await?;
+/// Returns the migrations that haven't been applied to the database yet, oldest first.
+///
+/// Fails if a migration was left half-applied, or if one that was applied has been changed since
+/// (`MigrateError::VersionMismatch`).
+async fn pending_migrations<'m>(
+    conn: &mut SqliteConnection,
+    migrator: &'m Migrator,
+) -> Result<Vec<&'m Migration>, MigrateError> {
+    conn.ensure_migrations_table().await?;
+
+    if let Some(version) = conn.dirty_version().await? {
+        return Err(MigrateError::Dirty(version));
+    }
+
+    let applied: FxHashMap<i64, _> = conn
+        .list_applied_migrations()
+        .await?
+        .into_iter()
+        .map(|migration| (migration.version, migration.checksum))
+        .collect();
+
+    let mut pending = Vec::new();
+
+    for migration in migrator.iter() {
+        if migration.migration_type.is_down_migration() {
+            continue;
+        }
+
+        match applied.get(&migration.version) {
+            Some(checksum) if *checksum != migration.checksum => {
+                return Err(MigrateError::VersionMismatch(migration.version));
             }
-            _ => (),
+            Some(_) => (),
+            None => pending.push(migration),
         }
     }
 
-    Ok(pool)
+    Ok(pending)
+}
+
+/// Applies the pending migrations one at a time, calling `on_progress` before each one and
+/// logging how long each one took.
+///
+/// Every migration is applied in its own transaction, together with the record that it was
+/// applied, so if the app is killed partway through, the migration being applied is rolled back
+/// and the next start picks up from it. None of the migrations opt out of this with
+/// `-- no-transaction`, and new ones mustn't either.
+async fn run_migrations(
+    pool: &SqlitePool,
+    on_progress: &impl Fn(MigrationProgress),
+) -> Result<(), MigrateError> {
+    let migrator = sqlx::migrate!("./migrations");
+    let mut conn = pool.acquire().await?;
+
+    conn.lock().await?;
+
+    let pending = pending_migrations(&mut conn, &migrator).await?;
+    let total = pending.len();
+
+    if total > 0 {
+        info!("Applying {total} database migrations");
+    }
+
+    for (idx, migration) in pending.into_iter().enumerate() {
+        on_progress(MigrationProgress {
+            current: idx + 1,
+            total,
+        });
+
+        let elapsed = conn.apply(migration).await.inspect_err(|err| {
+            error!(
+                version = migration.version,
+                ?err,
+                "Database migration {} failed: {err}",
+                migration.description
+            );
+        })?;
+
+        info!(
+            version = migration.version,
+            "Applied database migration {} ({} of {total}) in {elapsed:?}",
+            migration.description,
+            idx + 1,
+        );
+    }
+
+    conn.unlock().await?;
+
+    Ok(())
+}
+
+/// Brings the database up to date, calling `on_progress` before each migration is applied.
+pub async fn migrate(
+    pool: &SqlitePool,
+    on_progress: impl Fn(MigrationProgress),
+) -> sqlx::Result<()> {
+    match run_migrations(pool, &on_progress).await {
+        Err(MigrateError::VersionMismatch(
+            20240730163128 | 20240730163151 | 20240730163200 | 20240817201809 | 20240817201912
+            | 20240917084650 | 20250424090924 | 20250512214434 | 20250512231103 | 20250825224757
+            | 20250825225240 | 20250825234341 | 20251022214837,
+        )) if cfg!(target_os = "windows") => {
+            // it's likely this is because of a line-ending caused hash mismatch
+            // this is fixed but not on existing databases
+            let fix_query = include_str!("../../queries/windows_fix_checksums.sql");
+            sqlx::query(fix_query).execute(pool).await?;
+
+            run_migrations(pool, &on_progress).await?;
+        }
+        result => result?,
+    }
+
+    Ok(())
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -86,6 +203,10 @@ pub enum TrackSortMethod {
     DurationDesc,
     TrackNumberAsc,
     TrackNumberDesc,
+    BpmAsc,
+    BpmDesc,
+    CommentAsc,
+    CommentDesc,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -95,48 +216,38 @@ pub enum AlbumMethod {
     Metadata,
 }
 
-pub async fn list_albums(
-    pool: &SqlitePool,
-    sort_method: AlbumSortMethod,
-) -> sqlx::Result<Vec<(u32, String)>> {
-    let query = match sort_method {
-        AlbumSortMethod::TitleAsc => {
-            include_str!("../../queries/library/find_albums_title_asc.sql")
-        }
-        AlbumSortMethod::TitleDesc => {
-            include_str!("../../queries/library/find_albums_title_desc.sql")
-        }
-        AlbumSortMethod::ArtistAsc => {
-            include_str!("../../queries/library/find_albums_artist_asc.sql")
-        }
-        AlbumSortMethod::ArtistDesc => {
-            include_str!("../../queries/library/find_albums_artist_desc.sql")
-        }
-        AlbumSortMethod::ReleaseAsc => {
-            include_str!("../../queries/library/find_albums_release_asc.sql")
-        }
-        AlbumSortMethod::ReleaseDesc => {
-            include_str!("../../queries/library/find_albums_release_desc.sql")
-        }
-        AlbumSortMethod::LabelAsc => {
-            include_str!("../../queries/library/find_albums_label_asc.sql")
-        }
-        AlbumSortMethod::LabelDesc => {
-            include_str!("../../queries/library/find_albums_label_desc.sql")
-        }
-        AlbumSortMethod::CatalogAsc => {
-            include_str!("../../queries/library/find_albums_catnum_asc.sql")
-        }
-        AlbumSortMethod::CatalogDesc => {
-            include_str!("../../queries/library/find_albums_catnum_desc.sql")
-        }
-    };
+/// Lists the albums in the library, sorted and filtered as the query says (see [AlbumQuery]).
+pub async fn list_albums(pool: &SqlitePool, query: AlbumQuery) -> sqlx::Result<Vec<(u32, String)>> {
+    query
+        .build()
+        .build_query_as::<(u32, String)>()
+        .fetch_all(pool)
+        .await
+}
 
-    let albums = sqlx::query_as::<_, (u32, String)>(query)
+/// Finds out which album filters match any albums, so that the ones that don't aren't offered.
+pub async fn get_album_filter_options(
+    pool: &SqlitePool,
+    include_hidden: bool,
+    prefer_original: bool,
+) -> sqlx::Result<AlbumFilterOptions> {
+    let decades: Vec<i64> = decades_query(include_hidden, prefer_original)
+        .build_query_scalar()
         .fetch_all(pool)
         .await?;
 
-    Ok(albums)
+    let (lossless, lossy, added_this_month): (bool, bool, bool) =
+        filter_flags_query(include_hidden)
+            .build_query_as()
+            .fetch_one(pool)
+            .await?;
+
+    Ok(AlbumFilterOptions {
+        decades,
+        lossless,
+        lossy,
+        added_this_month,
+    })
 }
 
 pub async fn list_tracks(
@@ -174,6 +285,14 @@ pub async fn list_tracks(
         TrackSortMethod::TrackNumberDesc => {
             include_str!("../../queries/library/find_tracks_number_desc.sql")
         }
+        TrackSortMethod::BpmAsc => include_str!("../../queries/library/find_tracks_bpm_asc.sql"),
+        TrackSortMethod::BpmDesc => include_str!("../../queries/library/find_tracks_bpm_desc.sql"),
+        TrackSortMethod::CommentAsc => {
+            include_str!("../../queries/library/find_tracks_comment_asc.sql")
+        }
+        TrackSortMethod::CommentDesc => {
+            include_str!("../../queries/library/find_tracks_comment_desc.sql")
+        }
     };
 
     let tracks = sqlx::query_as::<_, (i64, String, Option<i64>, String)>(query)
@@ -212,7 +331,12 @@ pub async fn get_album_by_id(
     };
 
     let album: Arc<Album> = Arc::new({
-        let mut data: Album = sqlx::query_as(query).bind(album_id).fetch_one(pool).await?;
+        // album art isn't cached along with the statement, see STATEMENT_CACHE_CAPACITY
+        let mut data: Album = sqlx::query_as(query)
+            .persistent(matches!(method, AlbumMethod::Metadata))
+            .bind(album_id)
+            .fetch_one(pool)
+            .await?;
 
         match method {
             AlbumMethod::FullQuality => {
@@ -266,18 +390,128 @@ pub async fn get_track_by_id(pool: &SqlitePool, track_id: i64) -> sqlx::Result<A
     Ok(track)
 }
 
-/// Lists all albums for searching. Returns a vector of tuples containing the id, name, and artist
-/// name.
-pub async fn list_albums_search(pool: &SqlitePool) -> sqlx::Result<Vec<(u32, String, String)>> {
+/// Lists the albums to search through, as (id, title, artist, comments). The comments are those
+/// of the album's tracks, joined together.
+pub async fn list_albums_search(
+    pool: &SqlitePool,
+) -> sqlx::Result<Vec<(u32, String, String, Option<String>)>> {
     let query = include_str!("../../queries/library/find_albums_search.sql");
 
-    let albums = sqlx::query_as::<_, (u32, String, String)>(query)
+    let albums = sqlx::query_as::<_, (u32, String, String, Option<String>)>(query)
         .fetch_all(pool)
         .await?;
 
     Ok(albums)
 }
 
+/// Lists the artist's albums other than `exclude`, oldest first. Hidden albums are left out.
+pub async fn list_albums_by_artist(
+    pool: &SqlitePool,
+    artist_id: i64,
+    exclude: i64,
+    prefer_original: bool,
+) -> sqlx::Result<Vec<AlbumSummary>> {
+    let query = include_str!("../../queries/library/find_albums_by_artist.sql");
+
+    sqlx::query_as(query)
+        .bind(artist_id)
+        .bind(exclude)
+        .bind(prefer_original)
+        .fetch_all(pool)
+        .await
+}
+
+/// Lists up to `limit` albums released on the label other than `exclude`, newest first. Hidden
+/// albums are left out.
+pub async fn list_albums_by_label(
+    pool: &SqlitePool,
+    label: &str,
+    exclude: i64,
+    limit: u32,
+    prefer_original: bool,
+) -> sqlx::Result<Vec<AlbumSummary>> {
+    let query = include_str!("../../queries/library/find_albums_by_label.sql");
+
+    sqlx::query_as(query)
+        .bind(label)
+        .bind(exclude)
+        .bind(limit)
+        .bind(prefer_original)
+        .fetch_all(pool)
+        .await
+}
+
+/// Lists up to `limit` albums released in the year other than `exclude`, in a random order. Hidden
+/// albums are left out. Albums that are reissues are matched by the year they first came out in if
+/// `prefer_original` is true, and by the year of the reissue otherwise.
+pub async fn list_albums_by_year(
+    pool: &SqlitePool,
+    year: i64,
+    exclude: i64,
+    limit: u32,
+    prefer_original: bool,
+) -> sqlx::Result<Vec<AlbumSummary>> {
+    let query = include_str!("../../queries/library/find_albums_by_year.sql");
+
+    sqlx::query_as(query)
+        .bind(year)
+        .bind(exclude)
+        .bind(limit)
+        .bind(prefer_original)
+        .fetch_all(pool)
+        .await
+}
+
+/// Lists the albums that weren't tagged with a MusicBrainz release ID, by artist. Hidden albums
+/// are left out.
+pub async fn list_albums_without_mbid(pool: &SqlitePool) -> sqlx::Result<Vec<UntaggedAlbum>> {
+    let query = include_str!("../../queries/library/find_albums_without_mbid.sql");
+
+    sqlx::query_as(query).fetch_all(pool).await
+}
+
+/// Lists the albums whose art came from the given source, by artist. Hidden albums are left out.
+pub async fn list_albums_by_art_source(
+    pool: &SqlitePool,
+    source: ArtSource,
+) -> sqlx::Result<Vec<UntaggedAlbum>> {
+    let query = include_str!("../../queries/library/find_albums_by_art_source.sql");
+
+    sqlx::query_as(query).bind(source).fetch_all(pool).await
+}
+
+/// Lists the albums with a track whose embedded art was skipped while scanning for being too large
+/// (see [crate::library::scan::MAX_EMBEDDED_ART_SIZE]), by artist. Hidden albums are left out.
+pub async fn list_albums_with_oversized_art(pool: &SqlitePool) -> sqlx::Result<Vec<UntaggedAlbum>> {
+    let query = include_str!("../../queries/library/find_albums_with_oversized_art.sql");
+
+    sqlx::query_as(query).fetch_all(pool).await
+}
+
+/// Picks a random album from the library, avoiding `exclude` if any other album is available.
+/// Returns `None` if the library has no albums.
+pub async fn get_random_album_id(
+    pool: &SqlitePool,
+    exclude: Option<i64>,
+) -> sqlx::Result<Option<i64>> {
+    let query = include_str!("../../queries/library/find_random_album.sql");
+
+    let album_id: Option<i64> = sqlx::query_scalar(query)
+        .bind(exclude.unwrap_or(-1))
+        .fetch_optional(pool)
+        .await?;
+
+    // the excluded album might be the only one in the library
+    if album_id.is_none() && exclude.is_some() {
+        return sqlx::query_scalar(query)
+            .bind(-1)
+            .fetch_optional(pool)
+            .await;
+    }
+
+    Ok(album_id)
+}
+
 pub async fn add_playlist_item(
     pool: &SqlitePool,
     playlist_id: i64,
@@ -292,11 +526,146 @@ pub async fn add_playlist_item(
         .await?
         .last_insert_rowid();
 
+    touch_playlist(pool, playlist_id).await?;
+
     Ok(id)
 }
 
+/// Marks a playlist as just changed, so that it's listed first among recently used playlists.
+async fn touch_playlist<'c>(
+    executor: impl Executor<'c, Database = Sqlite>,
+    playlist_id: i64,
+) -> sqlx::Result<()> {
+    let query = include_str!("../../queries/playlist/touch_playlist.sql");
+    sqlx::query(query)
+        .bind(playlist_id)
+        .execute(executor)
+        .await?;
+
+    Ok(())
+}
+
+/// How often (in tracks) [`insert_playlist_items`] reports its progress.
+pub const PLAYLIST_PROGRESS_INTERVAL: usize = 100;
+
+/// The outcome of adding several tracks to a playlist at once.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct PlaylistAddResult {
+    pub added: usize,
+    /// The number of tracks that were skipped because they were already in the playlist.
+    pub skipped: usize,
+}
+
+/// Adds the given tracks to the end of a playlist, in order, in a single transaction.
+///
+/// A playlist can only contain a track once. If `skip_duplicates` is set, tracks that are already
+/// in the playlist (or appear more than once in `track_ids`) are skipped and counted, otherwise
+/// they cause the whole batch to fail.
+pub async fn add_playlist_items(
+    pool: &SqlitePool,
+    playlist_id: i64,
+    track_ids: &[i64],
+    skip_duplicates: bool,
+    progress: impl FnMut(usize),
+) -> sqlx::Result<PlaylistAddResult> {
+    let mut tx = pool.begin().await?;
+    let result =
+        insert_playlist_items(&mut tx, playlist_id, track_ids, skip_duplicates, progress).await?;
+    tx.commit().await?;
+
+    Ok(result)
+}
+
+/// Inserts tracks at the end of a playlist using an existing connection (or transaction). See
+/// [`add_playlist_items`]. `progress` is called with the number of tracks processed so far every
+/// [`PLAYLIST_PROGRESS_INTERVAL`] tracks.
+pub async fn insert_playlist_items(
+    conn: &mut SqliteConnection,
+    playlist_id: i64,
+    track_ids: &[i64],
+    skip_duplicates: bool,
+    mut progress: impl FnMut(usize),
+) -> sqlx::Result<PlaylistAddResult> {
+    let mut seen: FxHashSet<i64> = if skip_duplicates {
+        let query = include_str!("../../queries/playlist/get_track_ids.sql");
+        sqlx::query_scalar::<_, i64>(query)
+            .bind(playlist_id)
+            .fetch_all(&mut *conn)
+            .await?
+            .into_iter()
+            .collect()
+    } else {
+        FxHashSet::default()
+    };
+
+    // positions are shared between all playlists, see add_track.sql
+    let position_query = include_str!("../../queries/playlist/get_last_position.sql");
+    let last_position: i64 = sqlx::query_scalar(position_query)
+        .fetch_one(&mut *conn)
+        .await?;
+
+    let insert_query = include_str!("../../queries/playlist/add_track_at_position.sql");
+    let mut result = PlaylistAddResult::default();
+
+    for (idx, &track_id) in track_ids.iter().enumerate() {
+        if skip_duplicates && !seen.insert(track_id) {
+            result.skipped += 1;
+        } else {
+            sqlx::query(insert_query)
+                .bind(playlist_id)
+                .bind(track_id)
+                .bind(last_position + result.added as i64 + 1)
+                .execute(&mut *conn)
+                .await?;
+
+            result.added += 1;
+        }
+
+        if (idx + 1) % PLAYLIST_PROGRESS_INTERVAL == 0 {
+            progress(idx + 1);
+        }
+    }
+
+    if result.added > 0 {
+        touch_playlist(&mut *conn, playlist_id).await?;
+    }
+
+    Ok(result)
+}
+
+/// Returns `name` if no other playlist uses it, or otherwise the first of "name (2)", "name (3)"
+/// and so on that's free. Names are compared ignoring case, so that two playlists can't only be
+/// told apart by their capitalization.
+async fn unique_playlist_name(
+    pool: &SqlitePool,
+    name: &str,
+    exclude: Option<i64>,
+) -> sqlx::Result<String> {
+    let query = include_str!("../../queries/playlist/get_playlist_names.sql");
+
+    let taken: FxHashSet<String> = sqlx::query_scalar::<_, String>(query)
+        .bind(exclude)
+        .fetch_all(pool)
+        .await?
+        .into_iter()
+        .map(|name| name.to_lowercase())
+        .collect();
+
+    if !taken.contains(&name.to_lowercase()) {
+        return Ok(name.to_string());
+    }
+
+    Ok((2..)
+        .map(|n| format!("{name} ({n})"))
+        .find(|candidate| !taken.contains(&candidate.to_lowercase()))
+        .expect("there's always a free name"))
+}
+
+/// Creates an empty playlist. If the name is already used, a number is added to it - see
+/// [`unique_playlist_name`].
 pub async fn create_playlist(pool: &SqlitePool, name: &str) -> sqlx::Result<i64> {
     let query = include_str!("../../queries/playlist/create_playlist.sql");
+    let name = unique_playlist_name(pool, name, None).await?;
 
     let playlist_id = sqlx::query(query)
         .bind(name)
@@ -307,6 +676,27 @@ pub async fn create_playlist(pool: &SqlitePool, name: &str) -> sqlx::Result<i64>
     Ok(playlist_id)
 }
 
+/// Renames a user playlist, and returns the name it was given. As when creating a playlist, a
+/// number is added to the name if another playlist already uses it. System playlists can't be
+/// renamed.
+pub async fn rename_playlist(
+    pool: &SqlitePool,
+    playlist_id: i64,
+    name: &str,
+) -> sqlx::Result<String> {
+    let query = include_str!("../../queries/playlist/rename_playlist.sql");
+    let name = unique_playlist_name(pool, name, Some(playlist_id)).await?;
+
+    sqlx::query(query)
+        .bind(&name)
+        .bind(playlist_id)
+        .execute(pool)
+        .await?;
+
+    Ok(name)
+}
+
+/// Deletes a user playlist and its items. The liked songs playlist can't be deleted.
 pub async fn delete_playlist(pool: &SqlitePool, playlist_id: i64) -> sqlx::Result<()> {
     let query = include_str!("../../queries/playlist/delete_playlist.sql");
 
@@ -315,6 +705,38 @@ pub async fn delete_playlist(pool: &SqlitePool, playlist_id: i64) -> sqlx::Resul
     Ok(())
 }
 
+/// Returns the ID of the liked songs playlist, which is the only system playlist.
+pub async fn get_liked_playlist_id(pool: &SqlitePool) -> sqlx::Result<i64> {
+    let query = include_str!("../../queries/playlist/get_liked_playlist_id.sql");
+
+    let (id,): (i64,) = sqlx::query_as(query).fetch_one(pool).await?;
+
+    Ok(id)
+}
+
+/// Makes a playlist the liked songs playlist. The previous liked songs playlist becomes a regular
+/// playlist, and keeps its tracks.
+pub async fn set_liked_playlist(pool: &SqlitePool, playlist_id: i64) -> sqlx::Result<()> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query(include_str!(
+        "../../queries/playlist/clear_liked_playlist.sql"
+    ))
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query(include_str!(
+        "../../queries/playlist/mark_liked_playlist.sql"
+    ))
+    .bind(playlist_id)
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
 pub async fn get_all_playlists(pool: &SqlitePool) -> sqlx::Result<Arc<Vec<PlaylistWithCount>>> {
     let query = include_str!("../../queries/playlist/get_all_playlists.sql");
 
@@ -345,7 +767,13 @@ pub async fn get_playlist_track_files(
         .fetch_all(pool)
         .await?;
 
-    Ok(Arc::new(track_files.into_iter().map(|v| v.0).collect()))
+    let paths = DataPaths::get();
+    Ok(Arc::new(
+        track_files
+            .into_iter()
+            .map(|v| paths.resolve_stored(&v.0).to_string_lossy().into_owned())
+            .collect(),
+    ))
 }
 
 /// Returns (playlist_item_id, track_id, album_id)
@@ -363,6 +791,39 @@ pub async fn get_playlist_tracks(
     Ok(Arc::new(tracks))
 }
 
+/// Returns the sortable columns of every track in a playlist, in no particular order.
+pub async fn get_playlist_track_summaries(
+    pool: &SqlitePool,
+    playlist_id: i64,
+) -> sqlx::Result<Arc<Vec<PlaylistTrackSummary>>> {
+    let query = include_str!("../../queries/playlist/get_track_summaries.sql");
+
+    let summaries: Vec<PlaylistTrackSummary> = sqlx::query_as(query)
+        .bind(playlist_id)
+        .fetch_all(pool)
+        .await?;
+
+    Ok(Arc::new(summaries))
+}
+
+pub async fn get_playlist_tracks_in_bpm_range(
+    pool: &SqlitePool,
+    playlist_id: i64,
+    min_bpm: i64,
+    max_bpm: i64,
+) -> sqlx::Result<Arc<Vec<(i64, i64, i64)>>> {
+    let query = include_str!("../../queries/playlist/get_track_listing_bpm_range.sql");
+
+    let tracks: Vec<(i64, i64, i64)> = sqlx::query_as(query)
+        .bind(playlist_id)
+        .bind(min_bpm)
+        .bind(max_bpm)
+        .fetch_all(pool)
+        .await?;
+
+    Ok(Arc::new(tracks))
+}
+
 pub async fn move_playlist_item(
     pool: &SqlitePool,
     item_id: i64,
@@ -404,6 +865,8 @@ pub async fn remove_playlist_item(pool: &SqlitePool, item_id: i64) -> sqlx::Resu
         .execute(pool)
         .await?;
 
+    touch_playlist(pool, item.playlist_id).await?;
+
     Ok(())
 }
 
@@ -439,93 +902,565 @@ pub async fn playlist_has_track(
     Ok(has_track)
 }
 
-pub trait LibraryAccess {
-    fn list_albums(&self, sort_method: AlbumSortMethod) -> sqlx::Result<Vec<(u32, String)>>;
-    fn list_tracks(
-        &self,
-        sort_method: TrackSortMethod,
-    ) -> sqlx::Result<Vec<(i64, String, Option<i64>, String)>>;
-    fn list_tracks_in_album(&self, album_id: i64) -> sqlx::Result<Arc<Vec<Track>>>;
-    fn get_album_by_id(&self, album_id: i64, method: AlbumMethod) -> sqlx::Result<Arc<Album>>;
-    fn get_artist_name_by_id(&self, artist_id: i64) -> sqlx::Result<Arc<String>>;
-    fn get_artist_by_id(&self, artist_id: i64) -> sqlx::Result<Arc<Artist>>;
-    fn get_track_by_id(&self, track_id: i64) -> sqlx::Result<Arc<Track>>;
-    fn list_albums_search(&self) -> sqlx::Result<Vec<(u32, String, String)>>;
-    fn add_playlist_item(&self, playlist_id: i64, track_id: i64) -> sqlx::Result<i64>;
-    fn create_playlist(&self, name: &str) -> sqlx::Result<i64>;
-    fn delete_playlist(&self, playlist_id: i64) -> sqlx::Result<()>;
-    fn get_all_playlists(&self) -> sqlx::Result<Arc<Vec<PlaylistWithCount>>>;
-    fn get_playlist(&self, playlist_id: i64) -> sqlx::Result<Arc<Playlist>>;
-    fn get_playlist_track_files(&self, playlist_id: i64) -> sqlx::Result<Arc<Vec<String>>>;
-    fn get_playlist_tracks(&self, playlist_id: i64) -> sqlx::Result<Arc<Vec<(i64, i64, i64)>>>;
-    fn move_playlist_item(&self, item_id: i64, new_position: i64) -> sqlx::Result<()>;
-    fn remove_playlist_item(&self, item_id: i64) -> sqlx::Result<()>;
-    fn get_playlist_item(&self, item_id: i64) -> sqlx::Result<PlaylistItem>;
-    fn get_track_stats(&self) -> sqlx::Result<Arc<TrackStats>>;
-    fn playlist_has_track(&self, playlist_id: i64, track_id: i64) -> sqlx::Result<Option<i64>>;
-}
+pub async fn set_track_bpm(pool: &SqlitePool, track_id: i64, bpm: Option<i64>) -> sqlx::Result<()> {
+    let query = include_str!("../../queries/library/update_track_bpm.sql");
 
-impl LibraryAccess for App {
-    fn list_albums(&self, sort_method: AlbumSortMethod) -> sqlx::Result<Vec<(u32, String)>> {
-        let pool: &Pool = self.global();
-        crate::RUNTIME.block_on(list_albums(&pool.0, sort_method))
-    }
+    sqlx::query(query)
+        .bind(track_id)
+        .bind(bpm)
+        .execute(pool)
+        .await?;
 
-    fn list_tracks(
-        &self,
-        sort_method: TrackSortMethod,
-    ) -> sqlx::Result<Vec<(i64, String, Option<i64>, String)>> {
-        let pool: &Pool = self.global();
-        crate::RUNTIME.block_on(list_tracks(&pool.0, sort_method))
-    }
+    Ok(())
+}
 
-    fn list_tracks_in_album(&self, album_id: i64) -> sqlx::Result<Arc<Vec<Track>>> {
-        let pool: &Pool = self.global();
-        crate::RUNTIME.block_on(list_tracks_in_album(&pool.0, album_id))
-    }
+/// Sets a track's comment, replacing the one read from its file. The file itself isn't changed,
+/// and the comment is kept when the track is rescanned. `None` clears the comment.
+pub async fn set_track_comment(
+    pool: &SqlitePool,
+    track_id: i64,
+    comment: Option<&str>,
+) -> sqlx::Result<()> {
+    let query = include_str!("../../queries/library/update_track_comment.sql");
 
-    fn get_album_by_id(&self, album_id: i64, method: AlbumMethod) -> sqlx::Result<Arc<Album>> {
-        let pool: &Pool = self.global();
-        crate::RUNTIME.block_on(get_album_by_id(&pool.0, album_id, method))
-    }
+    sqlx::query(query)
+        .bind(track_id)
+        .bind(comment)
+        .execute(pool)
+        .await?;
 
-    fn get_artist_name_by_id(&self, artist_id: i64) -> sqlx::Result<Arc<String>> {
-        let pool: &Pool = self.global();
-        crate::RUNTIME.block_on(get_artist_name_by_id(&pool.0, artist_id))
-    }
+    Ok(())
+}
 
-    fn get_artist_by_id(&self, artist_id: i64) -> sqlx::Result<Arc<Artist>> {
-        let pool: &Pool = self.global();
-        crate::RUNTIME.block_on(get_artist_by_id(&pool.0, artist_id))
-    }
+/// Hides or unhides an album. Hidden albums (and their tracks) are left out of the album and track
+/// lists, search, and random album picks, but their files are kept in the library.
+pub async fn set_album_hidden(pool: &SqlitePool, album_id: i64, hidden: bool) -> sqlx::Result<()> {
+    let query = include_str!("../../queries/library/update_album_hidden.sql");
 
-    fn get_track_by_id(&self, track_id: i64) -> sqlx::Result<Arc<Track>> {
-        let pool: &Pool = self.global();
-        crate::RUNTIME.block_on(get_track_by_id(&pool.0, track_id))
-    }
+    sqlx::query(query)
+        .bind(album_id)
+        .bind(hidden)
+        .execute(pool)
+        .await?;
 
-    /// Lists all albums for searching. Returns a vector of tuples containing the id, name, and artist
-    /// name.
-    fn list_albums_search(&self) -> sqlx::Result<Vec<(u32, String, String)>> {
-        let pool: &Pool = self.global();
+    Ok(())
+}
+
+/// Lists an album's tracks along with the folders they're in, for fixing the album's grouping.
+pub async fn list_album_grouping(
+    pool: &SqlitePool,
+    album_id: i64,
+) -> sqlx::Result<Arc<Vec<GroupingTrack>>> {
+    let query = include_str!("../../queries/library/list_album_grouping.sql");
+
+    let tracks: Vec<GroupingTrack> = sqlx::query_as(query).bind(album_id).fetch_all(pool).await?;
+
+    Ok(Arc::new(tracks))
+}
+
+/// Moves some of an album's tracks to a new album, and returns the new album's ID. The new album
+/// starts out as a copy of the old one, with `art` (as full-size image, thumbnail, and where it
+/// came from) replacing its art if given. The old album is deleted if no tracks are left on it.
+///
+/// The moved tracks are locked to the new album, so that rescans don't put them back.
+pub async fn split_album(
+    pool: &SqlitePool,
+    album_id: i64,
+    track_ids: &[i64],
+    art: Option<(Vec<u8>, Vec<u8>, ArtSource)>,
+) -> sqlx::Result<i64> {
+    let mut tx = pool.begin().await?;
+
+    let (new_album_id,): (i64,) =
+        sqlx::query_as(include_str!("../../queries/library/split_album.sql"))
+            .bind(album_id)
+            .fetch_one(&mut *tx)
+            .await?;
+
+    if let Some((image, thumb, source)) = art {
+        sqlx::query(include_str!("../../queries/library/update_album_art.sql"))
+            .persistent(false)
+            .bind(new_album_id)
+            .bind(image)
+            .bind(thumb)
+            .bind(source)
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    for track_id in track_ids {
+        sqlx::query(include_str!(
+            "../../queries/library/move_track_to_album.sql"
+        ))
+        .bind(new_album_id)
+        .bind(track_id)
+        .bind(album_id)
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    sqlx::query(include_str!(
+        "../../queries/library/delete_album_if_empty.sql"
+    ))
+    .bind(album_id)
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(new_album_id)
+}
+
+/// Moves all of an album's tracks to another album, and deletes it. The moved tracks are locked
+/// to the other album, so that rescans don't put them back.
+pub async fn merge_albums(pool: &SqlitePool, album_id: i64, into: i64) -> sqlx::Result<()> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query(include_str!("../../queries/library/move_album_tracks.sql"))
+        .bind(into)
+        .bind(album_id)
+        .execute(&mut *tx)
+        .await?;
+
+    sqlx::query(include_str!(
+        "../../queries/library/delete_album_if_empty.sql"
+    ))
+    .bind(album_id)
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
+pub async fn set_track_gain_offset(
+    pool: &SqlitePool,
+    track_id: i64,
+    gain_offset: f64,
+) -> sqlx::Result<()> {
+    let query = include_str!("../../queries/library/update_track_gain_offset.sql");
+
+    sqlx::query(query)
+        .bind(track_id)
+        .bind(gain_offset)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+/// Returns the volume adjustment of the track at the given path, or `None` if the file isn't in
+/// the library.
+pub async fn get_track_gain_offset(pool: &SqlitePool, path: &Path) -> sqlx::Result<Option<f64>> {
+    let query = include_str!("../../queries/library/find_track_gain_offset_by_location.sql");
+
+    sqlx::query_scalar(query)
+        .bind(DataPaths::get().to_stored(path))
+        .fetch_optional(pool)
+        .await
+}
+
+/// Returns the loudness and peak of the track at the given path, as measured while scanning, or
+/// `None` if it hasn't been measured.
+pub async fn get_track_loudness(
+    pool: &SqlitePool,
+    path: &Path,
+) -> sqlx::Result<Option<(f64, f64)>> {
+    let query = include_str!("../../queries/library/find_track_loudness_by_location.sql");
+
+    sqlx::query_as(query)
+        .bind(DataPaths::get().to_stored(path))
+        .fetch_optional(pool)
+        .await
+}
+
+pub async fn get_track_id_by_path(pool: &SqlitePool, path: &Path) -> sqlx::Result<Option<i64>> {
+    let query = include_str!("../../queries/library/find_track_id_by_location.sql");
+
+    sqlx::query_scalar(query)
+        .bind(DataPaths::get().to_stored(path))
+        .fetch_optional(pool)
+        .await
+}
+
+/// Returns what's needed to find the track again in another copy of the library.
+pub async fn get_track_reference(
+    pool: &SqlitePool,
+    track_id: i64,
+) -> sqlx::Result<Option<TrackReference>> {
+    let query = include_str!("../../queries/library/find_track_reference.sql");
+
+    sqlx::query_as(query)
+        .bind(track_id)
+        .fetch_optional(pool)
+        .await
+}
+
+/// Finds a track in the library, trying (in order) the release's MusicBrainz ID and the track's
+/// position on it, the track's title, album and album artist, and finally the file's path.
+pub async fn resolve_track(
+    pool: &SqlitePool,
+    reference: &TrackReference,
+    path: &Path,
+) -> sqlx::Result<Option<ResolvedTrack>> {
+    if let Some(mbid) = &reference.album_mbid
+        && (reference.disc_number.is_some() || reference.track_number.is_some())
+    {
+        let query = include_str!("../../queries/library/resolve_track_by_album_mbid.sql");
+        let track = sqlx::query_as(query)
+            .bind(mbid)
+            .bind(reference.disc_number)
+            .bind(reference.track_number)
+            .fetch_optional(pool)
+            .await?;
+
+        if track.is_some() {
+            return Ok(track);
+        }
+    }
+
+    if let (Some(title), Some(album), Some(artist)) =
+        (&reference.title, &reference.album, &reference.artist)
+    {
+        let query = include_str!("../../queries/library/resolve_track_by_metadata.sql");
+        let track = sqlx::query_as(query)
+            .bind(title)
+            .bind(album)
+            .bind(artist)
+            .bind(reference.disc_number)
+            .bind(reference.track_number)
+            .fetch_optional(pool)
+            .await?;
+
+        if track.is_some() {
+            return Ok(track);
+        }
+    }
+
+    let query = include_str!("../../queries/library/resolve_track_by_location.sql");
+
+    sqlx::query_as(query)
+        .bind(DataPaths::get().to_stored(path))
+        .fetch_optional(pool)
+        .await
+}
+
+/// Rewrites the paths of the tracks and album folders inside the library root to be relative to
+/// it, for libraries that were scanned before the library root was set. Does nothing if there's
+/// no library root (see [`DataPaths`]).
+pub async fn relativize_library_paths(pool: &SqlitePool) -> sqlx::Result<()> {
+    let paths = DataPaths::get();
+    let Some(prefix) = paths.library_root_prefix() else {
+        return Ok(());
+    };
+    let to_stored = |path: String| paths.to_stored(Path::new(&path)).unwrap_or(path);
+
+    let mut tx = pool.begin().await?;
+
+    let tracks: Vec<(i64, String, Option<String>)> = sqlx::query_as(include_str!(
+        "../../queries/library/find_paths_under_root.sql"
+    ))
+    .bind(&prefix)
+    .fetch_all(&mut *tx)
+    .await?;
+
+    for (id, location, folder) in &tracks {
+        sqlx::query(include_str!("../../queries/library/update_track_paths.sql"))
+            .bind(id)
+            .bind(to_stored(location.clone()))
+            .bind(folder.clone().map(to_stored))
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    let album_paths: Vec<(i64, i64, String)> = sqlx::query_as(include_str!(
+        "../../queries/library/find_album_paths_under_root.sql"
+    ))
+    .bind(&prefix)
+    .fetch_all(&mut *tx)
+    .await?;
+
+    for (album_id, disc_num, path) in album_paths {
+        sqlx::query(include_str!("../../queries/library/update_album_path.sql"))
+            .bind(album_id)
+            .bind(disc_num)
+            .bind(to_stored(path))
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    tx.commit().await?;
+
+    if !tracks.is_empty() {
+        info!(
+            "Stored {} track paths relative to the library root",
+            tracks.len()
+        );
+    }
+
+    Ok(())
+}
+
+/// Returns whether a check was left for the user by a migration, and hasn't been dealt with yet.
+pub async fn has_pending_check(pool: &SqlitePool, name: &str) -> sqlx::Result<bool> {
+    let query = include_str!("../../queries/library/has_pending_check.sql");
+
+    sqlx::query_scalar(query).bind(name).fetch_one(pool).await
+}
+
+pub async fn clear_pending_check(pool: &SqlitePool, name: &str) -> sqlx::Result<()> {
+    let query = include_str!("../../queries/library/clear_pending_check.sql");
+
+    sqlx::query(query).bind(name).execute(pool).await?;
+
+    Ok(())
+}
+
+pub trait LibraryAccess {
+    fn list_albums(&self, query: AlbumQuery) -> sqlx::Result<Vec<(u32, String)>>;
+    fn list_tracks(
+        &self,
+        sort_method: TrackSortMethod,
+    ) -> sqlx::Result<Vec<(i64, String, Option<i64>, String)>>;
+    fn list_tracks_in_album(&self, album_id: i64) -> sqlx::Result<Arc<Vec<Track>>>;
+    fn get_album_by_id(&self, album_id: i64, method: AlbumMethod) -> sqlx::Result<Arc<Album>>;
+    fn get_artist_name_by_id(&self, artist_id: i64) -> sqlx::Result<Arc<String>>;
+    fn get_artist_by_id(&self, artist_id: i64) -> sqlx::Result<Arc<Artist>>;
+    fn get_track_by_id(&self, track_id: i64) -> sqlx::Result<Arc<Track>>;
+    fn list_albums_search(&self) -> sqlx::Result<Vec<(u32, String, String, Option<String>)>>;
+    fn get_random_album_id(&self, exclude: Option<i64>) -> sqlx::Result<Option<i64>>;
+    fn list_albums_by_artist(
+        &self,
+        artist_id: i64,
+        exclude: i64,
+        prefer_original: bool,
+    ) -> sqlx::Result<Vec<AlbumSummary>>;
+    fn list_albums_by_label(
+        &self,
+        label: &str,
+        exclude: i64,
+        limit: u32,
+        prefer_original: bool,
+    ) -> sqlx::Result<Vec<AlbumSummary>>;
+    fn list_albums_by_year(
+        &self,
+        year: i64,
+        exclude: i64,
+        limit: u32,
+        prefer_original: bool,
+    ) -> sqlx::Result<Vec<AlbumSummary>>;
+    fn list_albums_without_mbid(&self) -> sqlx::Result<Vec<UntaggedAlbum>>;
+    fn list_albums_by_art_source(&self, source: ArtSource) -> sqlx::Result<Vec<UntaggedAlbum>>;
+    fn list_albums_with_oversized_art(&self) -> sqlx::Result<Vec<UntaggedAlbum>>;
+    fn add_playlist_item(&self, playlist_id: i64, track_id: i64) -> sqlx::Result<i64>;
+    fn add_playlist_items(
+        &self,
+        playlist_id: i64,
+        track_ids: &[i64],
+        skip_duplicates: bool,
+    ) -> sqlx::Result<PlaylistAddResult>;
+    fn create_playlist(&self, name: &str) -> sqlx::Result<i64>;
+    fn rename_playlist(&self, playlist_id: i64, name: &str) -> sqlx::Result<String>;
+    fn delete_playlist(&self, playlist_id: i64) -> sqlx::Result<()>;
+    fn get_liked_playlist_id(&self) -> sqlx::Result<i64>;
+    fn set_liked_playlist(&self, playlist_id: i64) -> sqlx::Result<()>;
+    fn get_all_playlists(&self) -> sqlx::Result<Arc<Vec<PlaylistWithCount>>>;
+    fn get_playlist(&self, playlist_id: i64) -> sqlx::Result<Arc<Playlist>>;
+    fn get_playlist_track_files(&self, playlist_id: i64) -> sqlx::Result<Arc<Vec<String>>>;
+    fn get_playlist_tracks(&self, playlist_id: i64) -> sqlx::Result<Arc<Vec<(i64, i64, i64)>>>;
+    fn get_playlist_track_summaries(
+        &self,
+        playlist_id: i64,
+    ) -> sqlx::Result<Arc<Vec<PlaylistTrackSummary>>>;
+    fn move_playlist_item(&self, item_id: i64, new_position: i64) -> sqlx::Result<()>;
+    fn remove_playlist_item(&self, item_id: i64) -> sqlx::Result<()>;
+    fn get_playlist_item(&self, item_id: i64) -> sqlx::Result<PlaylistItem>;
+    fn get_track_stats(&self) -> sqlx::Result<Arc<TrackStats>>;
+    fn playlist_has_track(&self, playlist_id: i64, track_id: i64) -> sqlx::Result<Option<i64>>;
+    fn get_playlist_tracks_in_bpm_range(
+        &self,
+        playlist_id: i64,
+        min_bpm: i64,
+        max_bpm: i64,
+    ) -> sqlx::Result<Arc<Vec<(i64, i64, i64)>>>;
+    fn set_track_bpm(&self, track_id: i64, bpm: Option<i64>) -> sqlx::Result<()>;
+    fn set_track_comment(&self, track_id: i64, comment: Option<&str>) -> sqlx::Result<()>;
+    fn set_album_hidden(&self, album_id: i64, hidden: bool) -> sqlx::Result<()>;
+    fn list_album_grouping(&self, album_id: i64) -> sqlx::Result<Arc<Vec<GroupingTrack>>>;
+    fn split_album(
+        &self,
+        album_id: i64,
+        track_ids: &[i64],
+        art: Option<(Vec<u8>, Vec<u8>, ArtSource)>,
+    ) -> sqlx::Result<i64>;
+    fn merge_albums(&self, album_id: i64, into: i64) -> sqlx::Result<()>;
+    fn set_track_gain_offset(&self, track_id: i64, gain_offset: f64) -> sqlx::Result<()>;
+    fn get_track_id_by_path(&self, path: &Path) -> sqlx::Result<Option<i64>>;
+    fn has_pending_check(&self, name: &str) -> sqlx::Result<bool>;
+    fn clear_pending_check(&self, name: &str) -> sqlx::Result<()>;
+}
+
+impl LibraryAccess for App {
+    fn list_albums(&self, query: AlbumQuery) -> sqlx::Result<Vec<(u32, String)>> {
+        let pool: &Pool = self.global();
+        crate::RUNTIME.block_on(list_albums(&pool.0, query))
+    }
+
+    fn list_tracks(
+        &self,
+        sort_method: TrackSortMethod,
+    ) -> sqlx::Result<Vec<(i64, String, Option<i64>, String)>> {
+        let pool: &Pool = self.global();
+        crate::RUNTIME.block_on(list_tracks(&pool.0, sort_method))
+    }
+
+    fn list_tracks_in_album(&self, album_id: i64) -> sqlx::Result<Arc<Vec<Track>>> {
+        let pool: &Pool = self.global();
+        crate::RUNTIME.block_on(list_tracks_in_album(&pool.0, album_id))
+    }
+
+    fn get_album_by_id(&self, album_id: i64, method: AlbumMethod) -> sqlx::Result<Arc<Album>> {
+        let pool: &Pool = self.global();
+        crate::RUNTIME.block_on(get_album_by_id(&pool.0, album_id, method))
+    }
+
+    fn get_artist_name_by_id(&self, artist_id: i64) -> sqlx::Result<Arc<String>> {
+        let pool: &Pool = self.global();
+        crate::RUNTIME.block_on(get_artist_name_by_id(&pool.0, artist_id))
+    }
+
+    fn get_artist_by_id(&self, artist_id: i64) -> sqlx::Result<Arc<Artist>> {
+        let pool: &Pool = self.global();
+        crate::RUNTIME.block_on(get_artist_by_id(&pool.0, artist_id))
+    }
+
+    fn get_track_by_id(&self, track_id: i64) -> sqlx::Result<Arc<Track>> {
+        let pool: &Pool = self.global();
+        crate::RUNTIME.block_on(get_track_by_id(&pool.0, track_id))
+    }
+
+    /// Lists all albums for searching, as (id, title, artist, comments).
+    fn list_albums_search(&self) -> sqlx::Result<Vec<(u32, String, String, Option<String>)>> {
+        let pool: &Pool = self.global();
         crate::RUNTIME.block_on(list_albums_search(&pool.0))
     }
 
+    fn get_random_album_id(&self, exclude: Option<i64>) -> sqlx::Result<Option<i64>> {
+        let pool: &Pool = self.global();
+        crate::RUNTIME.block_on(get_random_album_id(&pool.0, exclude))
+    }
+
+    fn list_albums_by_artist(
+        &self,
+        artist_id: i64,
+        exclude: i64,
+        prefer_original: bool,
+    ) -> sqlx::Result<Vec<AlbumSummary>> {
+        let pool: &Pool = self.global();
+        crate::RUNTIME.block_on(list_albums_by_artist(
+            &pool.0,
+            artist_id,
+            exclude,
+            prefer_original,
+        ))
+    }
+
+    fn list_albums_by_label(
+        &self,
+        label: &str,
+        exclude: i64,
+        limit: u32,
+        prefer_original: bool,
+    ) -> sqlx::Result<Vec<AlbumSummary>> {
+        let pool: &Pool = self.global();
+        crate::RUNTIME.block_on(list_albums_by_label(
+            &pool.0,
+            label,
+            exclude,
+            limit,
+            prefer_original,
+        ))
+    }
+
+    fn list_albums_by_year(
+        &self,
+        year: i64,
+        exclude: i64,
+        limit: u32,
+        prefer_original: bool,
+    ) -> sqlx::Result<Vec<AlbumSummary>> {
+        let pool: &Pool = self.global();
+        crate::RUNTIME.block_on(list_albums_by_year(
+            &pool.0,
+            year,
+            exclude,
+            limit,
+            prefer_original,
+        ))
+    }
+
+    fn list_albums_without_mbid(&self) -> sqlx::Result<Vec<UntaggedAlbum>> {
+        let pool: &Pool = self.global();
+        crate::RUNTIME.block_on(list_albums_without_mbid(&pool.0))
+    }
+
+    fn list_albums_by_art_source(&self, source: ArtSource) -> sqlx::Result<Vec<UntaggedAlbum>> {
+        let pool: &Pool = self.global();
+        crate::RUNTIME.block_on(list_albums_by_art_source(&pool.0, source))
+    }
+
+    fn list_albums_with_oversized_art(&self) -> sqlx::Result<Vec<UntaggedAlbum>> {
+        let pool: &Pool = self.global();
+        crate::RUNTIME.block_on(list_albums_with_oversized_art(&pool.0))
+    }
+
     fn add_playlist_item(&self, playlist_id: i64, track_id: i64) -> sqlx::Result<i64> {
         let pool: &Pool = self.global();
         crate::RUNTIME.block_on(add_playlist_item(&pool.0, playlist_id, track_id))
     }
 
+    fn add_playlist_items(
+        &self,
+        playlist_id: i64,
+        track_ids: &[i64],
+        skip_duplicates: bool,
+    ) -> sqlx::Result<PlaylistAddResult> {
+        let pool: &Pool = self.global();
+        crate::RUNTIME.block_on(add_playlist_items(
+            &pool.0,
+            playlist_id,
+            track_ids,
+            skip_duplicates,
+            |_| {},
+        ))
+    }
+
     fn create_playlist(&self, name: &str) -> sqlx::Result<i64> {
         let pool: &Pool = self.global();
         crate::RUNTIME.block_on(create_playlist(&pool.0, name))
     }
 
+    fn rename_playlist(&self, playlist_id: i64, name: &str) -> sqlx::Result<String> {
+        let pool: &Pool = self.global();
+        crate::RUNTIME.block_on(rename_playlist(&pool.0, playlist_id, name))
+    }
+
     fn delete_playlist(&self, playlist_id: i64) -> sqlx::Result<()> {
         let pool: &Pool = self.global();
         crate::RUNTIME.block_on(delete_playlist(&pool.0, playlist_id))
     }
 
+    fn get_liked_playlist_id(&self) -> sqlx::Result<i64> {
+        let pool: &Pool = self.global();
+        crate::RUNTIME.block_on(get_liked_playlist_id(&pool.0))
+    }
+
+    fn set_liked_playlist(&self, playlist_id: i64) -> sqlx::Result<()> {
+        let pool: &Pool = self.global();
+        crate::RUNTIME.block_on(set_liked_playlist(&pool.0, playlist_id))
+    }
+
     fn get_all_playlists(&self) -> sqlx::Result<Arc<Vec<PlaylistWithCount>>> {
         let pool: &Pool = self.global();
         crate::RUNTIME.block_on(get_all_playlists(&pool.0))
@@ -546,6 +1481,14 @@ impl LibraryAccess for App {
         crate::RUNTIME.block_on(get_playlist_tracks(&pool.0, playlist_id))
     }
 
+    fn get_playlist_track_summaries(
+        &self,
+        playlist_id: i64,
+    ) -> sqlx::Result<Arc<Vec<PlaylistTrackSummary>>> {
+        let pool: &Pool = self.global();
+        crate::RUNTIME.block_on(get_playlist_track_summaries(&pool.0, playlist_id))
+    }
+
     fn move_playlist_item(&self, item_id: i64, new_position: i64) -> sqlx::Result<()> {
         let pool: &Pool = self.global();
         crate::RUNTIME.block_on(move_playlist_item(&pool.0, item_id, new_position))
@@ -570,4 +1513,906 @@ impl LibraryAccess for App {
         let pool: &Pool = self.global();
         crate::RUNTIME.block_on(playlist_has_track(&pool.0, playlist_id, track_id))
     }
+
+    fn get_playlist_tracks_in_bpm_range(
+        &self,
+        playlist_id: i64,
+        min_bpm: i64,
+        max_bpm: i64,
+    ) -> sqlx::Result<Arc<Vec<(i64, i64, i64)>>> {
+        let pool: &Pool = self.global();
+        crate::RUNTIME.block_on(get_playlist_tracks_in_bpm_range(
+            &pool.0,
+            playlist_id,
+            min_bpm,
+            max_bpm,
+        ))
+    }
+
+    fn set_track_bpm(&self, track_id: i64, bpm: Option<i64>) -> sqlx::Result<()> {
+        let pool: &Pool = self.global();
+        crate::RUNTIME.block_on(set_track_bpm(&pool.0, track_id, bpm))
+    }
+
+    fn set_track_comment(&self, track_id: i64, comment: Option<&str>) -> sqlx::Result<()> {
+        let pool: &Pool = self.global();
+        crate::RUNTIME.block_on(set_track_comment(&pool.0, track_id, comment))
+    }
+
+    fn set_album_hidden(&self, album_id: i64, hidden: bool) -> sqlx::Result<()> {
+        let pool: &Pool = self.global();
+        crate::RUNTIME.block_on(set_album_hidden(&pool.0, album_id, hidden))
+    }
+
+    fn list_album_grouping(&self, album_id: i64) -> sqlx::Result<Arc<Vec<GroupingTrack>>> {
+        let pool: &Pool = self.global();
+        crate::RUNTIME.block_on(list_album_grouping(&pool.0, album_id))
+    }
+
+    fn split_album(
+        &self,
+        album_id: i64,
+        track_ids: &[i64],
+        art: Option<(Vec<u8>, Vec<u8>, ArtSource)>,
+    ) -> sqlx::Result<i64> {
+        let pool: &Pool = self.global();
+        crate::RUNTIME.block_on(split_album(&pool.0, album_id, track_ids, art))
+    }
+
+    fn merge_albums(&self, album_id: i64, into: i64) -> sqlx::Result<()> {
+        let pool: &Pool = self.global();
+        crate::RUNTIME.block_on(merge_albums(&pool.0, album_id, into))
+    }
+
+    fn set_track_gain_offset(&self, track_id: i64, gain_offset: f64) -> sqlx::Result<()> {
+        let pool: &Pool = self.global();
+        crate::RUNTIME.block_on(set_track_gain_offset(&pool.0, track_id, gain_offset))
+    }
+
+    fn get_track_id_by_path(&self, path: &Path) -> sqlx::Result<Option<i64>> {
+        let pool: &Pool = self.global();
+        crate::RUNTIME.block_on(get_track_id_by_path(&pool.0, path))
+    }
+
+    fn has_pending_check(&self, name: &str) -> sqlx::Result<bool> {
+        let pool: &Pool = self.global();
+        crate::RUNTIME.block_on(has_pending_check(&pool.0, name))
+    }
+
+    fn clear_pending_check(&self, name: &str) -> sqlx::Result<()> {
+        let pool: &Pool = self.global();
+        crate::RUNTIME.block_on(clear_pending_check(&pool.0, name))
+    }
+}
+
+/// Runs a query on the runtime using the app's database pool. The returned future doesn't borrow
+/// the app, so it can be awaited from `cx.spawn` without blocking the UI thread.
+pub fn spawn_query<T, F, Fut>(cx: &App, query: F) -> impl Future<Output = sqlx::Result<T>> + 'static
+where
+    T: Send + 'static,
+    F: FnOnce(SqlitePool) -> Fut,
+    Fut: Future<Output = sqlx::Result<T>> + Send + 'static,
+{
+    let pool = cx.global::<Pool>().0.clone();
+    let task = crate::RUNTIME.spawn(query(pool));
+
+    async move {
+        match task.await {
+            Ok(result) => result,
+            Err(_) => Err(sqlx::Error::WorkerCrashed),
+        }
+    }
+}
+
+/// Non-blocking versions of the [`LibraryAccess`] queries used while rendering. Each query starts
+/// running on the runtime as soon as it's called, and the returned future resolves once it's
+/// finished.
+///
+/// [`LibraryAccess`] blocks the UI thread until the query is done, which freezes the interface if
+/// the database is busy (for example, while the scanner is inserting a large batch). Prefer these
+/// for anything done while opening a view or rendering a list.
+pub trait LibraryAccessAsync {
+    fn list_albums_async(
+        &self,
+        query: AlbumQuery,
+    ) -> impl Future<Output = sqlx::Result<Vec<(u32, String)>>> + 'static;
+    fn get_album_filter_options_async(
+        &self,
+        include_hidden: bool,
+        prefer_original: bool,
+    ) -> impl Future<Output = sqlx::Result<AlbumFilterOptions>> + 'static;
+    fn list_tracks_async(
+        &self,
+        sort_method: TrackSortMethod,
+    ) -> impl Future<Output = sqlx::Result<Vec<(i64, String, Option<i64>, String)>>> + 'static;
+    fn list_tracks_in_album_async(
+        &self,
+        album_id: i64,
+    ) -> impl Future<Output = sqlx::Result<Arc<Vec<Track>>>> + 'static;
+    fn get_album_by_id_async(
+        &self,
+        album_id: i64,
+        method: AlbumMethod,
+    ) -> impl Future<Output = sqlx::Result<Arc<Album>>> + 'static;
+    fn get_artist_name_by_id_async(
+        &self,
+        artist_id: i64,
+    ) -> impl Future<Output = sqlx::Result<Arc<String>>> + 'static;
+    fn get_track_by_id_async(
+        &self,
+        track_id: i64,
+    ) -> impl Future<Output = sqlx::Result<Arc<Track>>> + 'static;
+    fn playlist_has_track_async(
+        &self,
+        playlist_id: i64,
+        track_id: i64,
+    ) -> impl Future<Output = sqlx::Result<Option<i64>>> + 'static;
+}
+
+impl LibraryAccessAsync for App {
+    fn list_albums_async(
+        &self,
+        query: AlbumQuery,
+    ) -> impl Future<Output = sqlx::Result<Vec<(u32, String)>>> + 'static {
+        spawn_query(
+            self,
+            move |pool| async move { list_albums(&pool, query).await },
+        )
+    }
+
+    fn get_album_filter_options_async(
+        &self,
+        include_hidden: bool,
+        prefer_original: bool,
+    ) -> impl Future<Output = sqlx::Result<AlbumFilterOptions>> + 'static {
+        spawn_query(self, move |pool| async move {
+            get_album_filter_options(&pool, include_hidden, prefer_original).await
+        })
+    }
+
+    fn list_tracks_async(
+        &self,
+        sort_method: TrackSortMethod,
+    ) -> impl Future<Output = sqlx::Result<Vec<(i64, String, Option<i64>, String)>>> + 'static {
+        spawn_query(self, move |pool| async move {
+            list_tracks(&pool, sort_method).await
+        })
+    }
+
+    fn list_tracks_in_album_async(
+        &self,
+        album_id: i64,
+    ) -> impl Future<Output = sqlx::Result<Arc<Vec<Track>>>> + 'static {
+        spawn_query(self, move |pool| async move {
+            list_tracks_in_album(&pool, album_id).await
+        })
+    }
+
+    fn get_album_by_id_async(
+        &self,
+        album_id: i64,
+        method: AlbumMethod,
+    ) -> impl Future<Output = sqlx::Result<Arc<Album>>> + 'static {
+        spawn_query(self, move |pool| async move {
+            get_album_by_id(&pool, album_id, method).await
+        })
+    }
+
+    fn get_artist_name_by_id_async(
+        &self,
+        artist_id: i64,
+    ) -> impl Future<Output = sqlx::Result<Arc<String>>> + 'static {
+        spawn_query(self, move |pool| async move {
+            get_artist_name_by_id(&pool, artist_id).await
+        })
+    }
+
+    fn get_track_by_id_async(
+        &self,
+        track_id: i64,
+    ) -> impl Future<Output = sqlx::Result<Arc<Track>>> + 'static {
+        spawn_query(self, move |pool| async move {
+            get_track_by_id(&pool, track_id).await
+        })
+    }
+
+    fn playlist_has_track_async(
+        &self,
+        playlist_id: i64,
+        track_id: i64,
+    ) -> impl Future<Output = sqlx::Result<Option<i64>>> + 'static {
+        spawn_query(self, move |pool| async move {
+            playlist_has_track(&pool, playlist_id, track_id).await
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::{Connection, sqlite::SqlitePoolOptions};
+
+    use crate::library::{
+        album_query::{AlbumFilter, AlbumFormat},
+        types::{AlbumMatchBasis, ArtSource},
+    };
+
+    use super::*;
+
+    /// An empty database in memory, opened like the library is. It only has one connection, which
+    /// is kept open, since every connection to an in-memory database gets a database of its own.
+    async fn memory_pool() -> SqlitePool {
+        memory_pool_with(connect_options()).await
+    }
+
+    async fn memory_pool_with(options: SqliteConnectOptions) -> SqlitePool {
+        SqlitePoolOptions::new()
+            .max_connections(1)
+            .idle_timeout(None)
+            .max_lifetime(None)
+            .connect_with(options)
+            .await
+            .expect("couldn't open the database")
+    }
+
+    /// An empty library in memory.
+    async fn test_pool() -> SqlitePool {
+        let pool = memory_pool().await;
+
+        migrate(&pool, |_| {}).await.expect("couldn't migrate");
+        pool
+    }
+
+    async fn insert_tracks(pool: &SqlitePool, count: usize) -> Vec<i64> {
+        let mut ids = Vec::with_capacity(count);
+
+        for idx in 0..count {
+            let title = format!("Track {idx}");
+            let id = sqlx::query(
+                "INSERT INTO track (title, title_sortable, duration, location) \
+                VALUES ($1, $1, 0, $2)",
+            )
+            .bind(&title)
+            .bind(format!("/music/{title}.flac"))
+            .execute(pool)
+            .await
+            .expect("couldn't insert a track")
+            .last_insert_rowid();
+
+            ids.push(id);
+        }
+
+        ids
+    }
+
+    async fn playlist_track_ids(pool: &SqlitePool, playlist_id: i64) -> Vec<i64> {
+        sqlx::query_scalar(
+            "SELECT track_id FROM playlist_item WHERE playlist_id = $1 ORDER BY position",
+        )
+        .bind(playlist_id)
+        .fetch_all(pool)
+        .await
+        .expect("couldn't list the playlist")
+    }
+
+    /// Adds a release by "Artist" with the same track on two discs, returning the IDs of both.
+    async fn insert_release(pool: &SqlitePool) -> [i64; 2] {
+        let artist = sqlx::query("INSERT INTO artist (name, name_sortable) VALUES ($1, $1)")
+            .bind("Artist")
+            .execute(pool)
+            .await
+            .expect("couldn't insert the artist")
+            .last_insert_rowid();
+        let album = sqlx::query(
+            "INSERT INTO album (title, title_sortable, artist_id, mbid) VALUES ($1, $1, $2, $3)",
+        )
+        .bind("Album")
+        .bind(artist)
+        .bind("release-mbid")
+        .execute(pool)
+        .await
+        .expect("couldn't insert the album")
+        .last_insert_rowid();
+
+        let mut ids = [0; 2];
+        for (idx, id) in ids.iter_mut().enumerate() {
+            let disc = idx as i32 + 1;
+            *id = sqlx::query(
+                "INSERT INTO track \
+                (title, title_sortable, album_id, disc_number, track_number, duration, location) \
+                VALUES ($1, $1, $2, $3, 1, 0, $4)",
+            )
+            .bind("Intro")
+            .bind(album)
+            .bind(disc)
+            .bind(format!("/music/Album/{disc}-01 Intro.flac"))
+            .execute(pool)
+            .await
+            .expect("couldn't insert a track")
+            .last_insert_rowid();
+        }
+
+        ids
+    }
+
+    /// Returns the album a track is on, and whether it was put there by hand.
+    async fn track_album(pool: &SqlitePool, track_id: i64) -> (i64, bool) {
+        sqlx::query_as("SELECT album_id, album_locked FROM track WHERE id = $1")
+            .bind(track_id)
+            .fetch_one(pool)
+            .await
+            .expect("couldn't find the track")
+    }
+
+    async fn album_exists(pool: &SqlitePool, album_id: i64) -> bool {
+        sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM album WHERE id = $1)")
+            .bind(album_id)
+            .fetch_one(pool)
+            .await
+            .expect("couldn't look up the album")
+    }
+
+    /// The IDs of the albums whose art came from the given source, in order.
+    async fn art_source_ids(pool: &SqlitePool, source: ArtSource) -> Vec<i64> {
+        let albums = list_albums_by_art_source(pool, source)
+            .await
+            .expect("couldn't list the albums");
+
+        let mut ids: Vec<i64> = albums.into_iter().map(|album| album.id).collect();
+        ids.sort_unstable();
+        ids
+    }
+
+    /// Adds an album released in the given year, with a track for each entry of `lossless`
+    /// (`None` for tracks that haven't been scanned since the format was stored).
+    async fn insert_album(
+        pool: &SqlitePool,
+        title: &str,
+        year: i64,
+        lossless: &[Option<bool>],
+    ) -> u32 {
+        let album = sqlx::query(
+            "INSERT INTO album (title, title_sortable, release_year) VALUES ($1, $1, $2)",
+        )
+        .bind(title)
+        .bind(year)
+        .execute(pool)
+        .await
+        .expect("couldn't insert the album")
+        .last_insert_rowid();
+
+        for (idx, lossless) in lossless.iter().enumerate() {
+            sqlx::query(
+                "INSERT INTO track (title, title_sortable, album_id, duration, location, lossless) \
+                VALUES ($1, $1, $2, 0, $3, $4)",
+            )
+            .bind(format!("Track {idx}"))
+            .bind(album)
+            .bind(format!("/music/{title}/{idx}.flac"))
+            .bind(lossless)
+            .execute(pool)
+            .await
+            .expect("couldn't insert a track");
+        }
+
+        album as u32
+    }
+
+    /// The IDs of the albums listed with the given filter, sorted by title.
+    async fn filtered(pool: &SqlitePool, filter: AlbumFilter) -> Vec<u32> {
+        let query = AlbumQuery {
+            sort: AlbumSortMethod::TitleAsc,
+            include_hidden: false,
+            filter,
+            prefer_original_date: false,
+        };
+        let albums = list_albums(pool, query)
+            .await
+            .expect("couldn't list albums");
+
+        albums.into_iter().map(|(id, _)| id).collect()
+    }
+
+    /// The years the albums are listed under, newest first.
+    async fn release_years(pool: &SqlitePool, prefer_original_date: bool) -> Vec<String> {
+        let query = AlbumQuery {
+            sort: AlbumSortMethod::ReleaseDesc,
+            include_hidden: false,
+            filter: AlbumFilter::default(),
+            prefer_original_date,
+        };
+        let albums = list_albums(pool, query)
+            .await
+            .expect("couldn't list albums");
+
+        albums.into_iter().map(|(_, year)| year).collect()
+    }
+
+    /// How many prepared statements the pool's connection has cached.
+    async fn cached_statements(pool: &SqlitePool) -> usize {
+        let conn = pool.acquire().await.expect("couldn't get a connection");
+        conn.cached_statements_size()
+    }
+
+    /// A library with `count` albums by one artist, each with a single track. Every other track is
+    /// in a playlist. Returns the pool, the playlist, and the IDs of the albums and their tracks.
+    async fn seeded_pool(
+        options: SqliteConnectOptions,
+        count: usize,
+    ) -> (SqlitePool, i64, Vec<(i64, i64)>) {
+        let pool = memory_pool_with(options).await;
+        migrate(&pool, |_| {}).await.expect("couldn't migrate");
+        sqlx::query("INSERT INTO artist (id, name, name_sortable) VALUES (1, 'A', 'A')")
+            .execute(&pool)
+            .await
+            .expect("couldn't insert the artist");
+
+        let mut ids = Vec::with_capacity(count);
+        for idx in 0..count {
+            let album = insert_album(&pool, &format!("Album {idx}"), 2000, &[Some(true)]).await;
+            let track: i64 = sqlx::query_scalar("SELECT id FROM track WHERE album_id = $1")
+                .bind(album)
+                .fetch_one(&pool)
+                .await
+                .expect("couldn't find the track");
+            ids.push((i64::from(album), track));
+        }
+        sqlx::query("UPDATE album SET artist_id = 1")
+            .execute(&pool)
+            .await
+            .expect("couldn't set the artist");
+
+        let playlist = create_playlist(&pool, "Mix").await.unwrap();
+        for (_, track) in ids.iter().step_by(2) {
+            add_playlist_item(&pool, playlist, *track).await.unwrap();
+        }
+
+        (pool, playlist, ids)
+    }
+
+    #[tokio::test]
+    async fn reports_progress_for_each_migration() {
+        let pool = memory_pool().await;
+        let reported = std::sync::Mutex::new(Vec::new());
+
+        migrate(&pool, |progress| reported.lock().unwrap().push(progress))
+            .await
+            .unwrap();
+
+        let reported = reported.into_inner().unwrap();
+        let total = sqlx::migrate!("./migrations").iter().count();
+        assert_eq!(reported.len(), total);
+        assert!(
+            reported
+                .iter()
+                .enumerate()
+                .all(|(idx, progress)| progress.current == idx + 1 && progress.total == total)
+        );
+    }
+
+    #[tokio::test]
+    async fn migrating_again_does_nothing() {
+        let pool = test_pool().await;
+
+        migrate(&pool, |_| panic!("nothing should be applied"))
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn fails_when_an_applied_migration_was_changed() {
+        let pool = test_pool().await;
+        sqlx::query(
+            "UPDATE _sqlx_migrations SET checksum = x'00' \
+            WHERE version = (SELECT MAX(version) FROM _sqlx_migrations)",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let result = migrate(&pool, |_| {}).await;
+
+        assert!(matches!(
+            result,
+            Err(sqlx::Error::Migrate(err)) if matches!(*err, MigrateError::VersionMismatch(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn adds_tracks_in_order_after_the_existing_ones() {
+        let pool = test_pool().await;
+        let tracks = insert_tracks(&pool, 4).await;
+        let playlist = create_playlist(&pool, "Mix").await.unwrap();
+
+        add_playlist_item(&pool, playlist, tracks[3]).await.unwrap();
+        let result = add_playlist_items(&pool, playlist, &tracks[..3], false, |_| {})
+            .await
+            .unwrap();
+
+        assert_eq!((result.added, result.skipped), (3, 0));
+        assert_eq!(
+            playlist_track_ids(&pool, playlist).await,
+            [tracks[3], tracks[0], tracks[1], tracks[2]]
+        );
+    }
+
+    #[tokio::test]
+    async fn skips_duplicates_when_asked() {
+        let pool = test_pool().await;
+        let tracks = insert_tracks(&pool, 3).await;
+        let playlist = create_playlist(&pool, "Mix").await.unwrap();
+        add_playlist_item(&pool, playlist, tracks[0]).await.unwrap();
+
+        let batch = [tracks[0], tracks[1], tracks[1], tracks[2]];
+        let result = add_playlist_items(&pool, playlist, &batch, true, |_| {})
+            .await
+            .unwrap();
+
+        assert_eq!((result.added, result.skipped), (2, 2));
+        assert_eq!(playlist_track_ids(&pool, playlist).await, tracks);
+    }
+
+    #[tokio::test]
+    async fn duplicates_fail_the_whole_batch_otherwise() {
+        let pool = test_pool().await;
+        let tracks = insert_tracks(&pool, 2).await;
+        let playlist = create_playlist(&pool, "Mix").await.unwrap();
+
+        let batch = [tracks[0], tracks[1], tracks[0]];
+        let result = add_playlist_items(&pool, playlist, &batch, false, |_| {}).await;
+
+        assert!(result.is_err());
+        assert!(playlist_track_ids(&pool, playlist).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn reports_progress_at_each_interval() {
+        let pool = test_pool().await;
+        let tracks = insert_tracks(&pool, PLAYLIST_PROGRESS_INTERVAL * 2 + 1).await;
+        let playlist = create_playlist(&pool, "Mix").await.unwrap();
+
+        let mut reported = Vec::new();
+        add_playlist_items(&pool, playlist, &tracks, false, |done| reported.push(done))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            reported,
+            [PLAYLIST_PROGRESS_INTERVAL, PLAYLIST_PROGRESS_INTERVAL * 2]
+        );
+    }
+
+    #[tokio::test]
+    async fn resolves_tracks_by_release_and_position() {
+        let pool = test_pool().await;
+        let tracks = insert_release(&pool).await;
+        let reference = TrackReference {
+            album_mbid: Some("release-mbid".to_string()),
+            disc_number: Some(2),
+            track_number: Some(1),
+            title: Some("Renamed".to_string()),
+            ..Default::default()
+        };
+
+        let track = resolve_track(&pool, &reference, Path::new("/elsewhere.flac"))
+            .await
+            .unwrap()
+            .expect("the track should be found");
+
+        assert_eq!(track.id, tracks[1]);
+        assert_eq!(track.location, Path::new("/music/Album/2-01 Intro.flac"));
+    }
+
+    #[tokio::test]
+    async fn resolves_tracks_by_metadata() {
+        let pool = test_pool().await;
+        let tracks = insert_release(&pool).await;
+        let reference = TrackReference {
+            album_mbid: Some("another-mbid".to_string()),
+            disc_number: Some(2),
+            track_number: Some(1),
+            artist: Some("ARTIST".to_string()),
+            album: Some("album".to_string()),
+            title: Some("intro".to_string()),
+        };
+
+        let track = resolve_track(&pool, &reference, Path::new("/elsewhere.flac"))
+            .await
+            .unwrap()
+            .expect("the track should be found");
+
+        assert_eq!(track.id, tracks[1]);
+    }
+
+    #[tokio::test]
+    async fn resolves_tracks_by_path_as_a_last_resort() {
+        let pool = test_pool().await;
+        let tracks = insert_release(&pool).await;
+        let reference = TrackReference {
+            title: Some("Renamed".to_string()),
+            ..Default::default()
+        };
+
+        let track = resolve_track(&pool, &reference, Path::new("/music/Album/1-01 Intro.flac"))
+            .await
+            .unwrap()
+            .expect("the track should be found");
+
+        assert_eq!(track.id, tracks[0]);
+    }
+
+    #[tokio::test]
+    async fn resolves_nothing_when_no_track_matches() {
+        let pool = test_pool().await;
+        insert_release(&pool).await;
+        let reference = TrackReference {
+            album_mbid: Some("release-mbid".to_string()),
+            disc_number: Some(3),
+            track_number: Some(1),
+            artist: Some("Artist".to_string()),
+            album: Some("Album".to_string()),
+            title: Some("Outro".to_string()),
+        };
+
+        let track = resolve_track(&pool, &reference, Path::new("/elsewhere.flac"))
+            .await
+            .unwrap();
+
+        assert!(track.is_none());
+    }
+
+    #[tokio::test]
+    async fn splits_tracks_off_into_a_new_album() {
+        let pool = test_pool().await;
+        let tracks = insert_release(&pool).await;
+        let (album, _) = track_album(&pool, tracks[0]).await;
+
+        let new_album = split_album(&pool, album, &tracks[1..], None).await.unwrap();
+
+        assert_eq!(track_album(&pool, tracks[0]).await, (album, false));
+        assert_eq!(track_album(&pool, tracks[1]).await, (new_album, true));
+
+        let basis: AlbumMatchBasis =
+            sqlx::query_scalar("SELECT match_basis FROM album WHERE id = $1")
+                .bind(new_album)
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert_eq!(basis, AlbumMatchBasis::Manual);
+    }
+
+    #[tokio::test]
+    async fn splitting_off_every_track_deletes_the_album() {
+        let pool = test_pool().await;
+        let tracks = insert_release(&pool).await;
+        let (album, _) = track_album(&pool, tracks[0]).await;
+
+        let new_album = split_album(&pool, album, &tracks, None).await.unwrap();
+
+        assert!(!album_exists(&pool, album).await);
+        assert_eq!(track_album(&pool, tracks[0]).await, (new_album, true));
+    }
+
+    #[tokio::test]
+    async fn merges_albums_into_one() {
+        let pool = test_pool().await;
+        let tracks = insert_release(&pool).await;
+        let (album, _) = track_album(&pool, tracks[0]).await;
+        let other = split_album(&pool, album, &tracks[1..], None).await.unwrap();
+
+        merge_albums(&pool, other, album).await.unwrap();
+
+        assert!(!album_exists(&pool, other).await);
+        assert_eq!(track_album(&pool, tracks[1]).await, (album, true));
+    }
+
+    #[tokio::test]
+    async fn records_where_split_off_art_came_from() {
+        let pool = test_pool().await;
+        let tracks = insert_release(&pool).await;
+        let (album, _) = track_album(&pool, tracks[0]).await;
+        let art = (b"image".to_vec(), b"thumb".to_vec(), ArtSource::Folder);
+
+        let new_album = split_album(&pool, album, &tracks[1..], Some(art))
+            .await
+            .unwrap();
+
+        assert_eq!(art_source_ids(&pool, ArtSource::Folder).await, [new_album]);
+        assert_eq!(art_source_ids(&pool, ArtSource::Unknown).await, [album]);
+    }
+
+    #[tokio::test]
+    async fn split_off_albums_keep_the_source_of_the_old_art() {
+        let pool = test_pool().await;
+        let tracks = insert_release(&pool).await;
+        let (album, _) = track_album(&pool, tracks[0]).await;
+        sqlx::query("UPDATE album SET art_source = $1 WHERE id = $2")
+            .bind(ArtSource::Embedded)
+            .bind(album)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let new_album = split_album(&pool, album, &tracks[1..], None).await.unwrap();
+
+        assert_eq!(
+            art_source_ids(&pool, ArtSource::Embedded).await,
+            [album, new_album]
+        );
+    }
+
+    #[tokio::test]
+    async fn filters_albums_by_decade_and_format() {
+        let pool = test_pool().await;
+        let a = insert_album(&pool, "A", 1975, &[Some(true), Some(true)]).await;
+        let b = insert_album(&pool, "B", 1979, &[Some(true), Some(false)]).await;
+        let c = insert_album(&pool, "C", 1983, &[Some(true)]).await;
+        let d = insert_album(&pool, "D", 1972, &[None]).await;
+
+        let seventies = AlbumFilter {
+            decade: Some(1970),
+            ..Default::default()
+        };
+        let format = |format| AlbumFilter {
+            format: Some(format),
+            ..Default::default()
+        };
+
+        assert_eq!(filtered(&pool, seventies).await, [a, b, d]);
+        assert_eq!(filtered(&pool, format(AlbumFormat::Lossless)).await, [a, c]);
+        assert_eq!(filtered(&pool, format(AlbumFormat::Lossy)).await, [b]);
+        let lossless_seventies = AlbumFilter {
+            format: Some(AlbumFormat::Lossless),
+            ..seventies
+        };
+        assert_eq!(filtered(&pool, lossless_seventies).await, [a]);
+    }
+
+    #[tokio::test]
+    async fn filters_albums_added_this_month() {
+        let pool = test_pool().await;
+        let recent = insert_album(&pool, "Recent", 2020, &[]).await;
+        let old = insert_album(&pool, "Old", 2020, &[]).await;
+        sqlx::query("UPDATE album SET created_at = '2000-01-01 00:00:00' WHERE id = $1")
+            .bind(old)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let filter = AlbumFilter {
+            added_this_month: true,
+            ..Default::default()
+        };
+
+        assert_eq!(filtered(&pool, filter).await, [recent]);
+    }
+
+    #[tokio::test]
+    async fn leaves_out_hidden_albums_unless_asked() {
+        let pool = test_pool().await;
+        let shown = insert_album(&pool, "Shown", 2020, &[]).await;
+        let hidden = insert_album(&pool, "Hidden", 2020, &[]).await;
+        sqlx::query("UPDATE album SET hidden = 1 WHERE id = $1")
+            .bind(hidden)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let query = AlbumQuery {
+            sort: AlbumSortMethod::TitleAsc,
+            include_hidden: true,
+            filter: AlbumFilter::default(),
+            prefer_original_date: false,
+        };
+
+        assert_eq!(filtered(&pool, AlbumFilter::default()).await, [shown]);
+        assert_eq!(list_albums(&pool, query).await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn sorts_albums_by_year() {
+        let pool = test_pool().await;
+        insert_album(&pool, "A", 1999, &[]).await;
+        insert_album(&pool, "B", 2004, &[]).await;
+        let reissue = insert_album(&pool, "C", 2010, &[]).await;
+        let undated = insert_album(&pool, "D", 2020, &[]).await;
+        sqlx::query("UPDATE album SET original_year = 1971 WHERE id = $1")
+            .bind(reissue)
+            .execute(&pool)
+            .await
+            .unwrap();
+        sqlx::query("UPDATE album SET release_year = NULL WHERE id = $1")
+            .bind(undated)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            release_years(&pool, false).await,
+            ["2010", "2004", "1999", ""]
+        );
+        assert_eq!(
+            release_years(&pool, true).await,
+            ["2004", "1999", "1971", ""]
+        );
+    }
+
+    #[tokio::test]
+    async fn only_offers_filters_that_match_albums() {
+        let pool = test_pool().await;
+        insert_album(&pool, "A", 1975, &[Some(true)]).await;
+        insert_album(&pool, "B", 1994, &[Some(true)]).await;
+
+        let options = get_album_filter_options(&pool, false, false).await.unwrap();
+
+        assert_eq!(
+            options,
+            AlbumFilterOptions {
+                decades: vec![1970, 1990],
+                lossless: true,
+                lossy: false,
+                added_this_month: true,
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn caches_frequent_queries() {
+        let pool = test_pool().await;
+        let track = insert_tracks(&pool, 1).await[0];
+        let playlist = create_playlist(&pool, "Mix").await.unwrap();
+        let cached = cached_statements(&pool).await;
+
+        for _ in 0..10 {
+            let has_track = playlist_has_track(&pool, playlist, track).await.unwrap();
+            assert_eq!(has_track, None);
+        }
+
+        assert_eq!(cached_statements(&pool).await, cached + 1);
+    }
+
+    #[tokio::test]
+    async fn doesnt_cache_album_art_queries() {
+        let pool = test_pool().await;
+        let album = insert_album(&pool, "A", 2020, &[]).await.into();
+        sqlx::query("INSERT INTO artist (id, name, name_sortable) VALUES (1, 'B', 'B')")
+            .execute(&pool)
+            .await
+            .unwrap();
+        sqlx::query("UPDATE album SET artist_id = 1, image = x'0102' WHERE id = $1")
+            .bind(album)
+            .execute(&pool)
+            .await
+            .unwrap();
+        let cached = cached_statements(&pool).await;
+
+        let full = get_album_by_id(&pool, album, AlbumMethod::FullQuality).await;
+        assert_eq!(full.unwrap().image.as_deref(), Some(&[1, 2][..]));
+        assert_eq!(cached_statements(&pool).await, cached);
+
+        let metadata = get_album_by_id(&pool, album, AlbumMethod::Metadata).await;
+        assert_eq!(metadata.unwrap().image, None);
+        assert_eq!(cached_statements(&pool).await, cached + 1);
+    }
+
+    /// Times looking up 1,000 albums and whether their tracks are in a playlist, the way the album
+    /// table does for its rows, with and without the statement cache. Run it with
+    /// `cargo test --release lookup_benchmark -- --ignored --nocapture`.
+    #[tokio::test]
+    #[ignore = "benchmark"]
+    async fn lookup_benchmark() {
+        for capacity in [0, STATEMENT_CACHE_CAPACITY] {
+            let options = connect_options().statement_cache_capacity(capacity);
+            let (pool, playlist, ids) = seeded_pool(options, 1000).await;
+
+            let start = std::time::Instant::now();
+            for (album, track) in &ids {
+                get_album_by_id(&pool, *album, AlbumMethod::Metadata)
+                    .await
+                    .unwrap();
+                playlist_has_track(&pool, playlist, *track).await.unwrap();
+            }
+
+            println!("statement cache capacity {capacity}: {:?}", start.elapsed());
+        }
+    }
 }
@@ -3,14 +3,19 @@ use std::{ffi::OsStr, path::PathBuf};
 use anyhow::Context as _;
 use compact_str::CompactString;
 use futures::{StreamExt as _, TryFutureExt as _, TryStreamExt as _};
-use gpui::{App, PathPromptOptions};
+use gpui::{App, Global, PathPromptOptions};
 use sqlx::{Sqlite, SqlitePool};
 use tokio::{fs::File, io::BufWriter};
 use tracing::{Instrument as _, debug_span, error, info, warn};
 
-use crate::ui::{
-    app::Pool,
-    models::{Models, PlaylistEvent},
+use crate::{
+    library::db::{LibraryAccess, PlaylistAddResult, add_playlist_items, insert_playlist_items},
+    paths::DataPaths,
+    ui::{
+        app::Pool,
+        models::{Models, PlaylistEvent},
+        toasts::{ProgressToast, show_toast},
+    },
 };
 
 #[cfg(windows)]
@@ -18,6 +23,46 @@ const LINE_ENDING: &str = "\r\n";
 #[cfg(not(windows))]
 const LINE_ENDING: &str = "\n";
 
+/// Batches larger than this are added to playlists in the background, with a toast showing the
+/// progress, instead of blocking the UI until they're done.
+const BACKGROUND_ADD_THRESHOLD: usize = 500;
+
+/// The ID of the liked songs playlist, looked up once the database is open.
+pub struct LikedPlaylist(i64);
+
+impl Global for LikedPlaylist {}
+
+/// Looks up the liked songs playlist, so that [`get_liked_playlist_id`] can be used.
+pub fn load_liked_playlist(cx: &mut App) -> sqlx::Result<()> {
+    let playlist_id = cx.get_liked_playlist_id()?;
+    cx.set_global(LikedPlaylist(playlist_id));
+
+    Ok(())
+}
+
+/// Returns the ID of the liked songs playlist.
+pub fn get_liked_playlist_id(cx: &App) -> i64 {
+    cx.global::<LikedPlaylist>().0
+}
+
+/// Makes another playlist the liked songs playlist. The previous one becomes a regular playlist.
+pub fn change_liked_playlist(cx: &mut App, playlist_id: i64) -> sqlx::Result<()> {
+    let previous = get_liked_playlist_id(cx);
+
+    cx.set_liked_playlist(playlist_id)?;
+    cx.set_global(LikedPlaylist(playlist_id));
+
+    // both playlists changed type, and every track's star needs to be checked again
+    let playlist_tracker = cx.global::<Models>().playlist_tracker.clone();
+    playlist_tracker.update(cx, |_, cx| {
+        cx.emit(PlaylistEvent::PlaylistRenamed(previous));
+        cx.emit(PlaylistEvent::PlaylistRenamed(playlist_id));
+        cx.emit(PlaylistEvent::PlaylistUpdated(playlist_id));
+    });
+
+    Ok(())
+}
+
 #[derive(sqlx::FromRow)]
 struct PlaylistEntry {
     location: String,
@@ -47,6 +92,8 @@ async fn write_m3u(mut w: BufWriter<File>, pool: &SqlitePool, pl_id: i64) -> any
         }) = entries.try_next().await?
         {
             use std::io::Write as _;
+            let location = DataPaths::get().resolve_stored(&location);
+            let location = location.display();
             write!(
                 &mut buf,
                 "{LINE_ENDING}\
@@ -181,7 +228,7 @@ pub fn import_playlist(cx: &App, playlist_id: i64) {
                         let location = entry.location.clone();
                         let lookup_query = include_str!("../../queries/playlist/lookup_track.sql");
                         match sqlx::query_scalar::<Sqlite, i64>(lookup_query)
-                            .bind(entry.location.to_string_lossy().into_owned())
+                            .bind(DataPaths::get().to_stored(&entry.location))
                             .bind(entry.track_title)
                             .bind(entry.artist_name)
                             .bind(entry.album_title)
@@ -224,15 +271,10 @@ pub fn import_playlist(cx: &App, playlist_id: i64) {
                 .execute(&mut *tx)
                 .await?;
 
-            let insert_query = include_str!("../../queries/playlist/add_track.sql");
-            for track_id in ids {
-                sqlx::query(insert_query)
-                    .bind(playlist_id)
-                    .bind(track_id)
-                    .execute(&mut *tx)
-                    .instrument(debug_span!(parent: &span, "insert_track", track_id))
-                    .await?;
-            }
+            // a playlist can only contain a track once, so duplicate entries are dropped
+            insert_playlist_items(&mut tx, playlist_id, &ids, true, |_| {})
+                .instrument(debug_span!(parent: &span, "insert_tracks"))
+                .await?;
 
             tx.commit().await?;
 
@@ -249,3 +291,142 @@ pub fn import_playlist(cx: &App, playlist_id: i64) {
     })
     .detach();
 }
+
+fn describe_add_result(result: PlaylistAddResult) -> String {
+    let tracks = |count: usize| {
+        if count == 1 {
+            "1 track".to_string()
+        } else {
+            format!("{count} tracks")
+        }
+    };
+
+    if result.skipped == 0 {
+        format!("Added {} to playlist", tracks(result.added))
+    } else {
+        format!(
+            "Added {} to playlist, {} already in it",
+            tracks(result.added),
+            result.skipped
+        )
+    }
+}
+
+fn playlist_updated(cx: &mut App, playlist_id: i64) {
+    let playlist_tracker = cx.global::<Models>().playlist_tracker.clone();
+    playlist_tracker.update(cx, |_, cx| {
+        cx.emit(PlaylistEvent::PlaylistUpdated(playlist_id));
+    });
+}
+
+/// Adds tracks to the end of a playlist, skipping any that are already in it, and lets the user
+/// know how that went with a toast. Large batches are added in the background.
+pub fn add_tracks_to_playlist(cx: &mut App, playlist_id: i64, track_ids: Vec<i64>) {
+    if track_ids.len() <= BACKGROUND_ADD_THRESHOLD {
+        match cx.add_playlist_items(playlist_id, &track_ids, true) {
+            Ok(result) => {
+                playlist_updated(cx, playlist_id);
+                show_toast(cx, describe_add_result(result));
+            }
+            Err(err) => {
+                error!(
+                    ?err,
+                    "Failed to add tracks to playlist {playlist_id}: {err}"
+                );
+                show_toast(cx, "Failed to add tracks to playlist");
+            }
+        }
+
+        return;
+    }
+
+    let total = track_ids.len();
+    let toast = ProgressToast::show(cx, format!("Adding {total} tracks to playlist..."));
+    let pool = cx.global::<Pool>().0.clone();
+    let (progress_tx, mut progress_rx) = tokio::sync::watch::channel(0);
+
+    let task = crate::RUNTIME.spawn(async move {
+        add_playlist_items(&pool, playlist_id, &track_ids, true, |done| {
+            progress_tx.send_replace(done);
+        })
+        .await
+    });
+
+    cx.spawn(async move |cx| {
+        // the sender is dropped when the task finishes, which ends this loop
+        while progress_rx.changed().await.is_ok() {
+            let done = *progress_rx.borrow_and_update();
+            cx.update(|cx| toast.update(cx, format!("Adding tracks to playlist ({done}/{total})")))
+                .ok();
+        }
+
+        let message = match task.await {
+            Ok(Ok(result)) => describe_add_result(result),
+            Ok(Err(err)) => {
+                error!(
+                    ?err,
+                    "Failed to add tracks to playlist {playlist_id}: {err}"
+                );
+                "Failed to add tracks to playlist".to_string()
+            }
+            Err(err) => {
+                error!(?err, "Playlist task failed: {err}");
+                "Failed to add tracks to playlist".to_string()
+            }
+        };
+
+        cx.update(move |cx| {
+            playlist_updated(cx, playlist_id);
+            toast.finish(cx, message);
+        })
+        .ok();
+    })
+    .detach();
+}
+
+fn move_item_to_index(
+    cx: &mut App,
+    playlist_id: i64,
+    item_id: i64,
+    index: usize,
+) -> sqlx::Result<bool> {
+    let items = cx.get_playlist_tracks(playlist_id)?;
+
+    let Some(last) = items.len().checked_sub(1) else {
+        return Ok(false);
+    };
+    let target_id = items[index.min(last)].0;
+
+    if target_id == item_id {
+        return Ok(false);
+    }
+
+    // taking the position of the item currently at the index shifts everything in between by one,
+    // no matter how far the item moves
+    let target = cx.get_playlist_item(target_id)?;
+    cx.move_playlist_item(item_id, target.position)?;
+
+    Ok(true)
+}
+
+/// Moves a playlist item so that it ends up at `index` (counting from zero) in the playlist.
+/// Indices past the end of the playlist move the item to the bottom. Returns whether the item was
+/// moved.
+pub fn move_playlist_item_to_index(
+    cx: &mut App,
+    playlist_id: i64,
+    item_id: i64,
+    index: usize,
+) -> bool {
+    match move_item_to_index(cx, playlist_id, item_id, index) {
+        Ok(true) => {
+            playlist_updated(cx, playlist_id);
+            true
+        }
+        Ok(false) => false,
+        Err(err) => {
+            error!(?err, "Failed to move playlist item {item_id}: {err}");
+            false
+        }
+    }
+}
@@ -1,14 +1,19 @@
 use std::{
+    cmp::Reverse,
+    collections::VecDeque,
+    ffi::OsStr,
     fs::{self, File},
     io::{BufReader, Cursor, Write},
+    panic::{AssertUnwindSafe, catch_unwind},
     path::{Path, PathBuf},
     time::SystemTime,
 };
 
 use globwalk::GlobWalkerBuilder;
-use gpui::{App, Global};
+use gpui::{App, EventEmitter, Global};
 use image::{DynamicImage, EncodableLayout, codecs::jpeg::JpegEncoder, imageops::thumbnail};
-use rustc_hash::FxHashMap;
+use rustc_hash::{FxHashMap, FxHashSet};
+use sha2::{Digest, Sha256};
 use sqlx::SqlitePool;
 use tokio::sync::mpsc::{
     Receiver, Sender, UnboundedReceiver, UnboundedSender, channel, unbounded_channel,
@@ -17,24 +22,88 @@ use tracing::{debug, error, info, warn};
 
 /// The version of the scanning process. If this version number is incremented, a re-scan of all
 /// files will be forced (see [ScanCommand::ForceScan]).
-const SCAN_VERSION: u16 = 1;
+const SCAN_VERSION: u16 = 2;
+
+/// Embedded album art larger than this is skipped, rather than stored, and the track is listed in
+/// the album reports instead. Some files have pictures of many megabytes in their tags.
+pub const MAX_EMBEDDED_ART_SIZE: usize = 20 * 1024 * 1024;
 
 use crate::{
-    media::{builtin::symphonia::SymphoniaProvider, metadata::Metadata, traits::MediaProvider},
+    library::types::{AlbumMatchBasis, ArtSource},
+    media::{
+        builtin::symphonia::SymphoniaProvider,
+        loudness::{self, AnalyzedLoudness},
+        metadata::{Metadata, sanitize_metadata},
+        source::open_source,
+        tempo,
+        traits::MediaProvider,
+    },
+    paths::DataPaths,
     settings::scan::ScanSettings,
-    ui::{app::get_dirs, models::Models},
+    ui::{caching::forget_album_art, models::Models},
+    util::{limit_art_decoding, natural_cmp, round_millis},
 };
 
+/// The kind of scan that's running.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum ScanKind {
+    /// Only new and changed files are scanned (see [ScanCommand::Scan]).
+    Update,
+    /// Every file is scanned again and album information is re-created (see
+    /// [ScanCommand::ForceScan]).
+    Rebuild,
+}
+
+/// Progress through one of the scan paths. Scan paths are discovered and scanned one at a time,
+/// in the order they're listed in the settings.
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub struct RootProgress {
+    /// The position of the path in the scan settings, starting from 0.
+    pub index: usize,
+    /// How many paths are being scanned.
+    pub count: usize,
+    /// How many files to scan have been found in the path so far.
+    pub discovered: u64,
+    /// How many of those files have been scanned.
+    pub scanned: u64,
+}
+
 #[derive(Debug, PartialEq, Clone, Copy)]
 pub enum ScanEvent {
-    Cleaning,
-    DiscoverProgress(u64),
-    ScanProgress { current: u64, total: u64 },
+    Cleaning(ScanKind),
+    /// The number of files to scan found so far, in every path.
+    DiscoverProgress(u64, ScanKind, RootProgress),
+    ScanProgress {
+        current: u64,
+        total: u64,
+        kind: ScanKind,
+        root: RootProgress,
+    },
+    /// Files are being scanned during a force-scan (see [ScanCommand::ForceScan]). Albums are
+    /// re-created as they're encountered, so the library looks incomplete until this is over.
+    Rebuilding {
+        current: u64,
+        total: u64,
+    },
+    /// The loudness of tracks without ReplayGain tags is being measured, after the files have
+    /// been scanned (see [ScanSettings::analyze_loudness]). The library is complete by then, so
+    /// this doesn't count as a running scan.
+    Analyzing {
+        current: u64,
+        total: u64,
+    },
     ScanCompleteWatching,
     ScanCompleteIdle,
 }
 
+/// Emitted by the scan state model when an album's art was replaced during a scan, so that
+/// anything showing the old art can load it again. Contains the album's ID.
 #[derive(Debug, PartialEq, Clone, Copy)]
+pub struct AlbumArtChanged(pub i64);
+
+impl EventEmitter<AlbumArtChanged> for ScanEvent {}
+
+#[derive(Debug, PartialEq, Clone)]
 enum ScanCommand {
     Scan,
     /// A force-scan is different to a regular scan in that it will ignore all previous data and
@@ -42,20 +111,32 @@ enum ScanCommand {
     /// database schema has been changed, or a bug has been fixed with in the scanning proccess,
     /// and is usually triggered by the scan version changing (see [SCAN_VERSION]).
     ForceScan,
+    /// Replaces the scan settings. Takes effect the next time a scan is started.
+    UpdateSettings(ScanSettings),
+    /// Stops the scan once the current file has been scanned. If a force-scan is stopped, the
+    /// albums that have already been re-created are recorded, so that the next force-scan can
+    /// pick up where this one left off (see [rebuild_checkpoint_path]). Loudness analysis is
+    /// stopped once the current track has been measured.
     Stop,
 }
 
 pub struct ScanInterface {
     events_rx: Option<UnboundedReceiver<ScanEvent>>,
+    art_rx: Option<UnboundedReceiver<AlbumArtChanged>>,
     cmd_tx: Sender<ScanCommand>,
 }
 
 impl ScanInterface {
     pub(self) fn new(
         events_rx: Option<UnboundedReceiver<ScanEvent>>,
+        art_rx: Option<UnboundedReceiver<AlbumArtChanged>>,
         cmd_tx: Sender<ScanCommand>,
     ) -> Self {
-        ScanInterface { events_rx, cmd_tx }
+        ScanInterface {
+            events_rx,
+            art_rx,
+            cmd_tx,
+        }
     }
 
     pub fn scan(&self) {
@@ -70,6 +151,12 @@ impl ScanInterface {
             .expect("could not send force re-scan start command");
     }
 
+    pub fn update_settings(&self, settings: ScanSettings) {
+        self.cmd_tx
+            .blocking_send(ScanCommand::UpdateSettings(settings))
+            .expect("could not send scan settings update command");
+    }
+
     pub fn stop(&self) {
         self.cmd_tx
             .blocking_send(ScanCommand::Stop)
@@ -82,20 +169,37 @@ impl ScanInterface {
 
         let state_model = cx.global::<Models>().scan_state.clone();
 
+        if let Some(mut art_rx) = self.art_rx.take() {
+            let state_model = state_model.clone();
+
+            cx.spawn(async move |cx| {
+                while let Some(changed) = art_rx.recv().await {
+                    state_model
+                        .update(cx, |_, cx| {
+                            forget_album_art(changed.0, cx);
+                            cx.emit(changed);
+                        })
+                        .expect("failed to emit album art change");
+                }
+            })
+            .detach();
+        }
+
         let Some(mut events_rx) = events_rx else {
             return;
         };
         cx.spawn(async move |cx| {
-            loop {
-                while let Some(event) = events_rx.recv().await {
-                    state_model
-                        .update(cx, |m, cx| {
-                            *m = event;
-                            cx.notify()
-                        })
-                        .expect("failed to update scan state model");
-                }
+            while let Some(event) = events_rx.recv().await {
+                state_model
+                    .update(cx, |m, cx| {
+                        *m = event;
+                        cx.notify()
+                    })
+                    .expect("failed to update scan state model");
             }
+
+            // the scan thread only goes away if it panics; the library can still be browsed
+            error!("The scan thread stopped unexpectedly, the library won't be scanned");
         })
         .detach();
     }
@@ -103,35 +207,244 @@ impl ScanInterface {
 
 impl Global for ScanInterface {}
 
+/// Writes the file by writing to a temporary file next to it first, and then moving that over it,
+/// so that the file isn't left half-written if the app is closed partway through.
+fn write_atomically(path: &Path, data: &[u8]) -> std::io::Result<()> {
+    let temp_path = path.with_extension("tmp");
+
+    let mut file = File::create(&temp_path)?;
+    file.write_all(data)?;
+    file.sync_all()?;
+
+    fs::rename(temp_path, path)
+}
+
+/// The albums that were re-created by a force-scan that was stopped before it finished. It's
+/// written along with the scan record, which then only contains the files that were scanned.
+fn rebuild_checkpoint_path() -> PathBuf {
+    DataPaths::get().data_dir().join("rebuild_checkpoint.json")
+}
+
+fn read_rebuild_checkpoint() -> Option<Vec<i64>> {
+    let data = fs::read(rebuild_checkpoint_path()).ok()?;
+
+    match serde_json::from_slice(&data) {
+        Ok(albums) => Some(albums),
+        Err(err) => {
+            error!("Could not read rebuild checkpoint: {:?}", err);
+            error!("The library will be rebuilt from the start");
+            None
+        }
+    }
+}
+
+fn remove_rebuild_checkpoint() {
+    let path = rebuild_checkpoint_path();
+
+    if path.exists()
+        && let Err(err) = fs::remove_file(&path)
+    {
+        error!("Could not remove rebuild checkpoint: {:?}", err);
+    }
+}
+
+fn scan_version_path() -> PathBuf {
+    DataPaths::get().data_dir().join("scan_version")
+}
+
+/// Returns the version of the scanning process the library was last fully scanned with.
+fn read_scan_version() -> u16 {
+    fs::read_to_string(scan_version_path())
+        .ok()
+        .and_then(|version| version.trim().parse().ok())
+        // the version wasn't recorded until version 1
+        .unwrap_or(1)
+}
+
+fn write_scan_version() {
+    if let Err(err) = fs::write(scan_version_path(), SCAN_VERSION.to_string()) {
+        error!("Could not write scan version: {:?}", err);
+        error!("A library rebuild may be requested again on restart");
+    }
+}
+
+/// Returns whether the library was scanned with an older version of the scanning process, and
+/// needs to be rebuilt with a force-scan (see [ScanCommand::ForceScan]) to pick up the changes.
+pub fn needs_rebuild() -> bool {
+    // an empty library will be scanned with the current version anyway
+    DataPaths::get()
+        .data_dir()
+        .join("scan_record.json")
+        .exists()
+        && read_scan_version() < SCAN_VERSION
+}
+
 #[derive(Debug, PartialEq, Clone, Copy)]
 pub enum ScanState {
     Idle,
     Cleanup,
     Discovering,
     Scanning,
+    /// Measuring the loudness of tracks, once the scan itself is complete.
+    Analyzing,
+}
+
+/// Files that are created by the operating system or by other software, and never contain audio.
+const JUNK_FILE_NAMES: &[&str] = &["desktop.ini", "thumbs.db", ".ds_store"];
+
+/// Extensions used for files that are still being downloaded or synced.
+const PARTIAL_FILE_EXTENSIONS: &[&str] = &["tmp", "part", "partial", "crdownload", "download"];
+
+/// The reason a file or folder found during discovery was left out of the scan.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+enum IgnoreReason {
+    /// A macOS resource fork (`._` file). These often share the extension of the file they
+    /// belong to, but don't contain any audio.
+    ResourceFork,
+    Junk,
+    Hidden,
+    Empty,
+    TooSmall,
+}
+
+impl IgnoreReason {
+    fn describe(&self) -> &'static str {
+        match self {
+            IgnoreReason::ResourceFork => "resource forks",
+            IgnoreReason::Junk => "system or partial files",
+            IgnoreReason::Hidden => "hidden",
+            IgnoreReason::Empty => "empty",
+            IgnoreReason::TooSmall => "too small",
+        }
+    }
+}
+
+/// Returns whether a scan path can be read right now. Network shares often can't be, while the
+/// share is offline or the computer isn't on the right network.
+fn is_reachable(path: &Path) -> bool {
+    fs::read_dir(path).is_ok()
+}
+
+fn is_junk_file(name: &str) -> bool {
+    let lowercase = name.to_lowercase();
+
+    JUNK_FILE_NAMES.contains(&lowercase.as_str())
+        || Path::new(&lowercase)
+            .extension()
+            .and_then(OsStr::to_str)
+            .is_some_and(|ext| PARTIAL_FILE_EXTENSIONS.contains(&ext))
+}
+
+#[cfg(target_os = "windows")]
+fn is_hidden(name: &str, metadata: &fs::Metadata) -> bool {
+    use std::os::windows::fs::MetadataExt;
+
+    const FILE_ATTRIBUTE_HIDDEN: u32 = 0x2;
+
+    name.starts_with('.') || metadata.file_attributes() & FILE_ATTRIBUTE_HIDDEN != 0
+}
+
+#[cfg(not(target_os = "windows"))]
+fn is_hidden(name: &str, _: &fs::Metadata) -> bool {
+    name.starts_with('.')
+}
+
+/// Reads the entries of a folder in order of name, so that scans always run in the same order.
+/// Entries that can't be read are logged and left out.
+fn sorted_entries(path: &Path) -> std::io::Result<Vec<fs::DirEntry>> {
+    let mut entries: Vec<_> = fs::read_dir(path)?
+        .filter_map(|entry| {
+            entry
+                .inspect_err(|e| error!("Failed to read directory entry: {:?}", e))
+                .ok()
+        })
+        .collect();
+    entries.sort_by_key(|entry| entry.file_name());
+
+    Ok(entries)
+}
+
+/// Checks whether a file or folder should be skipped based on its name and attributes. The size
+/// of a file is only checked once it's known to be supported by a provider (see
+/// [size_ignore_reason]).
+fn ignore_reason(
+    name: &OsStr,
+    metadata: &fs::Metadata,
+    include_hidden: bool,
+) -> Option<IgnoreReason> {
+    let name = name.to_string_lossy();
+
+    if name.starts_with("._") {
+        Some(IgnoreReason::ResourceFork)
+    } else if metadata.is_file() && is_junk_file(&name) {
+        Some(IgnoreReason::Junk)
+    } else if !include_hidden && is_hidden(&name, metadata) {
+        Some(IgnoreReason::Hidden)
+    } else {
+        None
+    }
+}
+
+/// Checks whether a supported file is too small to contain any audio.
+fn size_ignore_reason(len: u64, min_file_size: u64) -> Option<IgnoreReason> {
+    if len == 0 {
+        Some(IgnoreReason::Empty)
+    } else if len < min_file_size {
+        Some(IgnoreReason::TooSmall)
+    } else {
+        None
+    }
 }
 
 pub struct ScanThread {
     event_tx: UnboundedSender<ScanEvent>,
+    art_tx: UnboundedSender<AlbumArtChanged>,
     command_rx: Receiver<ScanCommand>,
     pool: SqlitePool,
     scan_settings: ScanSettings,
     visited: Vec<PathBuf>,
-    discovered: Vec<PathBuf>,
-    to_process: Vec<PathBuf>,
+    /// The scan paths, as they were when the scan was started.
+    roots: Vec<PathBuf>,
+    /// The index of the next scan path to discover, once the current one is done.
+    next_root: usize,
+    root_progress: Vec<RootProgress>,
+    /// Folders in the current scan path that haven't been read yet. Folders are read
+    /// breadth-first, and their entries in order of name, so that scans always run in the same
+    /// order.
+    discovered: VecDeque<PathBuf>,
+    /// Files to scan, in the order they were found, along with the index of their scan path and
+    /// whether they've been scanned before (and so might have new album art).
+    to_process: VecDeque<(usize, PathBuf, bool)>,
     scan_state: ScanState,
     provider_table: Vec<(Vec<String>, Box<dyn MediaProvider>)>,
+    /// When each file was last modified, as of the last time it was scanned. Folders are recorded
+    /// with the time their album art images were last modified, or 0 if they have none (see
+    /// [ScanThread::folder_art_changed]).
     scan_record: FxHashMap<PathBuf, u64>,
     scan_record_path: Option<PathBuf>,
     scanned: u64,
     discovered_total: u64,
+    /// How many files and folders have been skipped during the current scan, by reason.
+    ignored: FxHashMap<IgnoreReason, u64>,
     /// Whether or not to force a rescan all files. This is set to true when a force-scan is
     /// requested, which results in all previous data being ignored.
     is_force: bool,
+    /// Whether every file is being scanned with the current version of the scanning process, in
+    /// which case the version is recorded once the scan is complete. This is the case for
+    /// force-scans, and for scans of an empty library.
+    is_full: bool,
     /// A list of enocuntered albums. When force-scan is enabled, this list will be used to
     /// determine whether or not an album should be inserted, instead of checking the
     /// album_title_artist_id_idx index.
     force_encountered_albums: Vec<i64>,
+    /// The albums that tracks were added to or updated in during the current scan, whose tracks
+    /// need to be put in order by file name again (see [ScanThread::update_fallback_order]).
+    scanned_albums: FxHashSet<i64>,
+    /// The tracks whose loudness hasn't been measured yet, by ID, and how many have been
+    /// measured since the analysis started.
+    to_analyze: VecDeque<(i64, PathBuf)>,
+    analyzed: u64,
+    analyze_total: u64,
 }
 
 fn build_provider_table() -> Vec<(Vec<String>, Box<dyn MediaProvider>)> {
@@ -160,25 +473,80 @@ fn file_is_scannable_with_provider(path: &Path, exts: &[String]) -> bool {
     false
 }
 
-type FileInformation = (Metadata, u64, Option<Box<[u8]>>);
+/// The metadata, duration (in milliseconds), album art (along with where it came from), (if the
+/// file has no BPM tag and estimation is enabled) estimated tempo of a file, whether its embedded
+/// art was skipped for being larger than [MAX_EMBEDDED_ART_SIZE], and whether its codec is
+/// lossless (if that's known).
+type FileInformation = (
+    Metadata,
+    u64,
+    Option<(Box<[u8]>, ArtSource)>,
+    Option<u32>,
+    bool,
+    Option<bool>,
+);
 
 fn scan_file_with_provider(
     path: &PathBuf,
     provider: &mut Box<dyn MediaProvider>,
+    estimate_bpm: bool,
 ) -> Result<FileInformation, ()> {
-    let src = std::fs::File::open(path).map_err(|_| ())?;
-    let mut stream = provider.open(src, None).map_err(|_| ())?;
+    let src = open_source(path).map_err(|_| ())?;
+    let mut stream = provider.open(src, path.extension()).map_err(|_| ())?;
     stream.start_playback().map_err(|_| ())?;
-    let metadata = stream.read_metadata().cloned().map_err(|_| ())?;
-    let image = stream.read_image().map_err(|_| ())?;
-    let len = stream.duration_secs().map_err(|_| ())?;
+    let mut metadata = stream.read_metadata().cloned().map_err(|_| ())?;
+
+    for correction in sanitize_metadata(&mut metadata) {
+        debug!(?path, "Ignoring metadata: {correction}");
+    }
+
+    let mut image = stream.read_image().map_err(|_| ())?;
+    let art_oversized = image
+        .as_ref()
+        .is_some_and(|image| image.len() > MAX_EMBEDDED_ART_SIZE);
+    if art_oversized {
+        warn!(?path, "Skipping embedded album art larger than 20MB");
+        image = None;
+    }
+
+    let image = image.map(|image| (image, ArtSource::Embedded));
+    let len = stream.duration_millis().map_err(|_| ())?;
+    let lossless = stream.is_lossless();
+    let estimated_bpm = if estimate_bpm && metadata.bpm.is_none() {
+        tempo::estimate_bpm(&mut stream)
+    } else {
+        None
+    };
+    stream.close().map_err(|_| ())?;
+    Ok((metadata, len, image, estimated_bpm, art_oversized, lossless))
+}
+
+/// Measures the loudness of a file. Returns `Ok(None)` if the file has ReplayGain tags (which are
+/// used instead) or its loudness couldn't be measured, and an error if it couldn't be opened.
+fn analyze_file_with_provider(
+    path: &Path,
+    provider: &mut Box<dyn MediaProvider>,
+) -> Result<Option<AnalyzedLoudness>, ()> {
+    let src = open_source(path).map_err(|_| ())?;
+    let mut stream = provider.open(src, path.extension()).map_err(|_| ())?;
+    stream.start_playback().map_err(|_| ())?;
+
+    let tagged = stream
+        .read_metadata()
+        .is_ok_and(|metadata| metadata.replaygain_track_gain.is_some());
+    let loudness = if tagged {
+        None
+    } else {
+        loudness::measure_loudness(&mut stream)
+    };
+
     stream.close().map_err(|_| ())?;
-    Ok((metadata, len, image))
+    Ok(loudness)
 }
 
 // Returns the first image (cover/front/folder.jpeg/png/jpeg) in the track's containing folder
 // Album art can be named anything, but this pattern is convention and the least likely to return a false positive
-fn scan_path_for_album_art(path: &Path) -> Option<Box<[u8]>> {
+pub fn scan_path_for_album_art(path: &Path) -> Option<Box<[u8]>> {
     let glob = GlobWalkerBuilder::from_patterns(
         path.parent().unwrap(),
         &["{folder,cover,front}.{jpg,jpeg,png}"],
@@ -197,21 +565,89 @@ fn scan_path_for_album_art(path: &Path) -> Option<Box<[u8]>> {
     None
 }
 
+/// Whether a file is one of the images used as album art for the folder it's in (see
+/// [scan_path_for_album_art]).
+fn is_folder_art(name: &OsStr) -> bool {
+    let name = name.to_string_lossy().to_lowercase();
+
+    let Some((stem, extension)) = name.rsplit_once('.') else {
+        return false;
+    };
+
+    matches!(stem, "folder" | "cover" | "front") && matches!(extension, "jpg" | "jpeg" | "png")
+}
+
+/// A hash of album art as it was read, before it's resized. It's stored with the album so that
+/// changes to the art can be spotted without loading the stored image.
+fn album_art_hash(image: &[u8]) -> Vec<u8> {
+    Sha256::digest(image).to_vec()
+}
+
+/// Decodes album art and returns the image to store (downscaled to 1024px if it is larger) along
+/// with a 70x70 BMP thumbnail.
+pub fn prepare_album_art(image: &[u8]) -> anyhow::Result<(Vec<u8>, Vec<u8>)> {
+    let mut reader = image::ImageReader::new(Cursor::new(image)).with_guessed_format()?;
+    limit_art_decoding(&mut reader);
+    let mut decoded = reader.decode()?.into_rgb8();
+
+    // for some reason, thumbnails don't load properly when saved as rgb8
+    // also, into_rgba8() causes the application to crash on certain images
+    //
+    // no, I don't no why, and no I can't fix it upstream
+    // this will have to do for now
+    let decoded_rgba = DynamicImage::ImageRgb8(decoded.clone()).into_rgba8();
+
+    let thumb = thumbnail(&decoded_rgba, 70, 70);
+
+    let mut buf: Cursor<Vec<u8>> = Cursor::new(Vec::new());
+
+    thumb
+        .write_to(&mut buf, image::ImageFormat::Bmp)
+        .expect("i don't know how Cursor could fail");
+    buf.flush().expect("could not flush buffer");
+
+    let resized = if decoded.dimensions().0 <= 1024 || decoded.dimensions().1 <= 1024 {
+        image.to_vec()
+    } else {
+        decoded =
+            image::imageops::resize(&decoded, 1024, 1024, image::imageops::FilterType::Lanczos3);
+        let mut buf: Cursor<Vec<u8>> = Cursor::new(Vec::new());
+        let mut encoder = JpegEncoder::new_with_quality(&mut buf, 70);
+
+        encoder.encode(
+            decoded.as_bytes(),
+            decoded.width(),
+            decoded.height(),
+            image::ExtendedColorType::Rgb8,
+        )?;
+        buf.flush()?;
+
+        buf.into_inner()
+    };
+
+    Ok((resized, buf.into_inner()))
+}
+
 impl ScanThread {
     pub fn start(pool: SqlitePool, settings: ScanSettings) -> ScanInterface {
         let (cmd_tx, commands_rx) = channel(10);
         let (events_tx, events_rx) = unbounded_channel();
+        let (art_tx, art_rx) = unbounded_channel();
 
         std::thread::Builder::new()
             .name("scanner".to_string())
             .spawn(move || {
                 let mut thread = ScanThread {
                     event_tx: events_tx,
+                    art_tx,
                     command_rx: commands_rx,
                     pool,
                     visited: Vec::new(),
-                    discovered: Vec::new(),
-                    to_process: Vec::new(),
+                    roots: Vec::new(),
+                    next_root: 0,
+                    root_progress: Vec::new(),
+                    discovered: VecDeque::new(),
+                    to_process: VecDeque::new(),
                     scan_state: ScanState::Idle,
                     provider_table: build_provider_table(),
                     scan_settings: settings,
@@ -219,20 +655,30 @@ impl ScanThread {
                     scan_record_path: None,
                     scanned: 0,
                     discovered_total: 0,
+                    ignored: FxHashMap::default(),
                     is_force: false,
+                    is_full: false,
                     force_encountered_albums: Vec::new(),
+                    scanned_albums: FxHashSet::default(),
+                    to_analyze: VecDeque::new(),
+                    analyzed: 0,
+                    analyze_total: 0,
                 };
 
-                thread.run();
+                // the panic hook has written a crash report by the time this returns, so what's
+                // been scanned so far is saved, rather than scanned all over again next time
+                if catch_unwind(AssertUnwindSafe(|| thread.run())).is_err() {
+                    error!("The scan thread panicked, saving the scan record and stopping");
+                    thread.write_scan_record();
+                }
             })
             .expect("could not start playback thread");
 
-        ScanInterface::new(Some(events_rx), cmd_tx)
+        ScanInterface::new(Some(events_rx), Some(art_rx), cmd_tx)
     }
 
     fn run(&mut self) {
-        let dirs = get_dirs();
-        let directory = dirs.data_dir();
+        let directory = DataPaths::get().data_dir();
         if !directory.exists() {
             fs::create_dir(directory).expect("couldn't create data directory");
         }
@@ -246,9 +692,14 @@ impl ScanThread {
             };
             let reader = BufReader::new(file);
 
-            match serde_json::from_reader(reader) {
+            match serde_json::from_reader::<_, FxHashMap<String, u64>>(reader) {
                 Ok(scan_record) => {
-                    self.scan_record = scan_record;
+                    // stored the same way as paths in the library, see DataPaths::to_stored
+                    let paths = DataPaths::get();
+                    self.scan_record = scan_record
+                        .into_iter()
+                        .map(|(path, modified)| (paths.resolve_stored(&path), modified))
+                        .collect();
                 }
                 Err(e) => {
                     error!("could not read scan record: {:?}", e);
@@ -276,55 +727,84 @@ impl ScanThread {
                 ScanState::Scanning => {
                     self.scan();
                 }
+                ScanState::Analyzing => {
+                    self.analyze();
+                }
             }
         }
     }
 
+    /// Whether a new scan can be started. Loudness analysis is stopped to make way for one, since
+    /// it picks up where it left off after the scan.
+    fn is_idle(&self) -> bool {
+        matches!(self.scan_state, ScanState::Idle | ScanState::Analyzing)
+    }
+
+    fn kind(&self) -> ScanKind {
+        if self.is_force {
+            ScanKind::Rebuild
+        } else {
+            ScanKind::Update
+        }
+    }
+
     fn read_commands(&mut self) {
         while let Ok(command) = self.command_rx.try_recv() {
             match command {
                 ScanCommand::Scan => {
-                    if self.scan_state == ScanState::Idle {
-                        self.discovered = self.scan_settings.paths.clone();
+                    if self.is_idle() {
+                        self.to_analyze.clear();
                         self.scan_state = ScanState::Cleanup;
                         self.scanned = 0;
                         self.discovered_total = 0;
-                        self.discovered = self.scan_settings.paths.clone();
-                        self.visited.clear();
-                        self.to_process.clear();
+                        self.queue_roots();
+                        self.ignored.clear();
                         self.is_force = false;
+                        self.is_full = self.scan_record.is_empty();
+
+                        // the scan record won't match the checkpoint once this scan is done
+                        remove_rebuild_checkpoint();
 
                         self.event_tx
-                            .send(ScanEvent::Cleaning)
+                            .send(ScanEvent::Cleaning(ScanKind::Update))
                             .expect("could not send scan event");
                     }
                 }
                 ScanCommand::ForceScan => {
-                    if self.scan_state == ScanState::Idle {
-                        self.discovered = self.scan_settings.paths.clone();
+                    if self.is_idle() {
+                        self.to_analyze.clear();
                         self.scan_state = ScanState::Cleanup;
                         self.scanned = 0;
                         self.discovered_total = 0;
-                        self.discovered = self.scan_settings.paths.clone();
-                        self.visited.clear();
-                        self.to_process.clear();
+                        self.queue_roots();
+                        self.ignored.clear();
 
                         self.is_force = true;
-                        self.force_encountered_albums.clear();
-
-                        self.scan_record = FxHashMap::default();
+                        self.is_full = true;
+
+                        // if the last force-scan was stopped, the files it scanned are still in
+                        // the scan record, and are skipped
+                        if let Some(albums) = read_rebuild_checkpoint() {
+                            info!("Resuming library rebuild, {} albums done", albums.len());
+                            self.force_encountered_albums = albums;
+                        } else {
+                            self.force_encountered_albums.clear();
+                            self.scan_record = FxHashMap::default();
+                        }
 
                         self.event_tx
-                            .send(ScanEvent::Cleaning)
+                            .send(ScanEvent::Cleaning(ScanKind::Rebuild))
                             .expect("could not send scan event");
                     }
                 }
-                ScanCommand::Stop => {
-                    self.scan_state = ScanState::Idle;
-                    self.visited.clear();
-                    self.discovered.clear();
-                    self.to_process.clear();
+                ScanCommand::UpdateSettings(settings) => {
+                    self.scan_settings = settings;
                 }
+                ScanCommand::Stop => match self.scan_state {
+                    ScanState::Idle => {}
+                    ScanState::Analyzing => self.stop_analysis(),
+                    _ => self.stop(),
+                },
             }
         }
 
@@ -332,21 +812,69 @@ impl ScanThread {
             self.discover();
         } else if self.scan_state == ScanState::Scanning {
             self.scan();
+        } else if self.scan_state == ScanState::Analyzing {
+            self.analyze();
         } else {
             std::thread::sleep(std::time::Duration::from_millis(100));
         }
     }
 
-    fn file_is_scannable(&mut self, path: &PathBuf) -> bool {
-        let timestamp = match fs::metadata(path) {
-            Ok(metadata) => metadata
-                .modified()
-                .unwrap()
-                .duration_since(SystemTime::UNIX_EPOCH)
-                .unwrap()
-                .as_secs(),
-            Err(_) => return false,
-        };
+    /// Queues up the scan paths to be discovered, highest priority first, and forgets about the
+    /// previous scan. Network shares that can't be reached are left out.
+    fn queue_roots(&mut self) {
+        let mut roots = self.scan_settings.paths.clone();
+
+        // relative folders are inside the library root, in portable mode
+        for root in &mut roots {
+            root.path = DataPaths::get().resolve_folder(&root.path);
+        }
+
+        // stable, so that folders with the same priority are scanned in the order they're listed
+        roots.sort_by_key(|root| Reverse(root.priority));
+        roots.retain(|root| {
+            let skip = root.network_volume && !is_reachable(&root.path);
+            if skip {
+                warn!(path = ?root.path, "Network folder can't be reached, skipping it");
+            }
+            !skip
+        });
+
+        let count = roots.len();
+
+        self.roots = roots.into_iter().map(|root| root.path).collect();
+        self.next_root = 0;
+        self.root_progress = (0..count)
+            .map(|index| RootProgress {
+                index,
+                count,
+                ..Default::default()
+            })
+            .collect();
+        self.visited.clear();
+        self.discovered.clear();
+        self.to_process.clear();
+    }
+
+    fn ignore(&mut self, path: &Path, reason: IgnoreReason) {
+        debug!("ignoring {:?} ({})", path, reason.describe());
+        *self.ignored.entry(reason).or_default() += 1;
+    }
+
+    /// Checks whether a file should be scanned, and records it as scanned if so. Files that
+    /// haven't changed since they were last scanned are skipped, unless `folder_art_changed` is
+    /// set.
+    fn file_is_scannable(
+        &mut self,
+        path: &PathBuf,
+        metadata: &fs::Metadata,
+        folder_art_changed: bool,
+    ) -> bool {
+        let timestamp = metadata
+            .modified()
+            .unwrap()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
 
         for (exts, _) in self.provider_table.iter() {
             let x = file_is_scannable_with_provider(path, exts);
@@ -354,8 +882,17 @@ impl ScanThread {
             if !x {
                 continue;
             }
+
+            if let Some(reason) =
+                size_ignore_reason(metadata.len(), self.scan_settings.min_file_size)
+            {
+                self.ignore(path, reason);
+                return false;
+            }
+
             if let Some(last_scan) = self.scan_record.get(path)
                 && *last_scan == timestamp
+                && !folder_art_changed
             {
                 return false;
             }
@@ -367,45 +904,106 @@ impl ScanThread {
         false
     }
 
-    fn discover(&mut self) {
-        if self.discovered.is_empty() {
-            self.scan_state = ScanState::Scanning;
-            return;
+    /// Records when the folder's album art images were last modified, and returns whether they've
+    /// changed since the last scan. If they have, the files in the folder are scanned again, so
+    /// that their albums pick up the new art.
+    fn folder_art_changed(&mut self, folder: &Path, entries: &[fs::DirEntry]) -> bool {
+        let modified = entries
+            .iter()
+            .filter(|entry| is_folder_art(&entry.file_name()))
+            .filter_map(|entry| entry.metadata().ok()?.modified().ok())
+            .filter_map(|modified| modified.duration_since(SystemTime::UNIX_EPOCH).ok())
+            .map(|modified| modified.as_secs())
+            .max()
+            .unwrap_or(0);
+
+        match self.scan_record.insert(folder.to_path_buf(), modified) {
+            Some(last_scan) => last_scan != modified,
+            // the folder hasn't been scanned before, so its files are all being scanned anyway
+            None => false,
         }
+    }
 
-        let path = self.discovered.pop().unwrap();
+    fn discover(&mut self) {
+        let Some(path) = self.discovered.pop_front() else {
+            // each scan path is discovered completely before moving on to the next one
+            if let Some(root) = self.next_root.checked_sub(1) {
+                self.event_tx
+                    .send(ScanEvent::DiscoverProgress(
+                        self.discovered_total,
+                        self.kind(),
+                        self.root_progress[root],
+                    ))
+                    .expect("could not send scan event");
+            }
+
+            if let Some(root) = self.roots.get(self.next_root) {
+                self.discovered.push_back(root.clone());
+                self.next_root += 1;
+            } else {
+                self.scan_state = ScanState::Scanning;
+            }
+
+            return;
+        };
 
         if self.visited.contains(&path) {
             return;
         }
 
-        let paths = fs::read_dir(&path).unwrap();
+        let root = self.next_root - 1;
 
-        for paths in paths {
-            let path = match paths {
-                Ok(entry) => match entry.path().canonicalize() {
-                    Ok(p) => p,
-                    Err(e) => {
-                        error!("Failed to canonicalize path {:?}: {:?}", entry.path(), e);
-                        continue;
-                    }
-                },
+        let entries = sorted_entries(&path).unwrap();
+
+        let folder_art_changed = self.folder_art_changed(&path, &entries);
+
+        for entry in entries {
+            let path = match entry.path().canonicalize() {
+                Ok(p) => p,
                 Err(e) => {
-                    error!("Failed to read directory entry: {:?}", e);
+                    error!("Failed to canonicalize path {:?}: {:?}", entry.path(), e);
                     continue;
                 }
             };
 
-            if path.is_dir() {
-                self.discovered.push(path);
-            } else if self.file_is_scannable(&path) {
-                self.to_process.push(path);
+            let metadata = match fs::metadata(&path) {
+                Ok(metadata) => metadata,
+                Err(e) => {
+                    error!("Failed to read metadata for {:?}: {:?}", path, e);
+                    continue;
+                }
+            };
+
+            // the name of the entry itself is checked, rather than the name of whatever it
+            // links to
+            let include_hidden = self.scan_settings.include_hidden;
+            if let Some(reason) = ignore_reason(&entry.file_name(), &metadata, include_hidden) {
+                self.ignore(&path, reason);
+                continue;
+            }
+
+            if metadata.is_dir() {
+                self.discovered.push_back(path);
+            } else {
+                let scanned_before = self.scan_record.contains_key(&path);
+
+                if !self.file_is_scannable(&path, &metadata, folder_art_changed) {
+                    continue;
+                }
+
+                self.to_process
+                    .push_back((root, path, scanned_before || folder_art_changed));
 
                 self.discovered_total += 1;
+                self.root_progress[root].discovered += 1;
 
                 if self.discovered_total.is_multiple_of(20) {
                     self.event_tx
-                        .send(ScanEvent::DiscoverProgress(self.discovered_total))
+                        .send(ScanEvent::DiscoverProgress(
+                            self.discovered_total,
+                            self.kind(),
+                            self.root_progress[root],
+                        ))
                         .expect("could not send scan event");
                 }
             }
@@ -446,11 +1044,14 @@ impl ScanThread {
         }
     }
 
+    /// Finds or creates the track's album. If the album already exists and `check_art` is set, its
+    /// art is replaced if the track's art is different (see [ScanThread::update_album_art]).
     async fn insert_album(
         &mut self,
         metadata: &Metadata,
         artist_id: Option<i64>,
-        image: &Option<Box<[u8]>>,
+        image: &Option<(Box<[u8]>, ArtSource)>,
+        check_art: bool,
     ) -> anyhow::Result<Option<i64>> {
         let Some(album) = &metadata.album else {
             return Ok(None);
@@ -481,63 +1082,26 @@ impl ScanThread {
         };
 
         match (result, should_force) {
-            (Ok(v), false) => Ok(Some(v.0)),
+            (Ok((id,)), false) => {
+                if check_art && let Some((image, source)) = image {
+                    self.update_album_art(id, image, *source).await?;
+                }
+
+                Ok(Some(id))
+            }
             (Err(sqlx::Error::RowNotFound), _) | (Ok(_), true) => {
-                let (resized_image, thumb) = match image {
-                    Some(image) => {
-                        // if there is a decode error, just ignore it and pretend there is no image
-                        let mut decoded = image::ImageReader::new(Cursor::new(&image))
-                            .with_guessed_format()?
-                            .decode()?
-                            .into_rgb8();
-
-                        // for some reason, thumbnails don't load properly when saved as rgb8
-                        // also, into_rgba8() causes the application to crash on certain images
-                        //
-                        // no, I don't no why, and no I can't fix it upstream
-                        // this will have to do for now
-                        let decoded_rgba = DynamicImage::ImageRgb8(decoded.clone()).into_rgba8();
-
-                        let thumb = thumbnail(&decoded_rgba, 70, 70);
-
-                        let mut buf: Cursor<Vec<u8>> = Cursor::new(Vec::new());
-
-                        thumb
-                            .write_to(&mut buf, image::ImageFormat::Bmp)
-                            .expect("i don't know how Cursor could fail");
-                        buf.flush().expect("could not flush buffer");
-
-                        let resized =
-                            if decoded.dimensions().0 <= 1024 || decoded.dimensions().1 <= 1024 {
-                                image.clone().to_vec()
-                            } else {
-                                decoded = image::imageops::resize(
-                                    &decoded,
-                                    1024,
-                                    1024,
-                                    image::imageops::FilterType::Lanczos3,
-                                );
-                                let mut buf: Cursor<Vec<u8>> = Cursor::new(Vec::new());
-                                let mut encoder = JpegEncoder::new_with_quality(&mut buf, 70);
-
-                                encoder.encode(
-                                    decoded.as_bytes(),
-                                    decoded.width(),
-                                    decoded.height(),
-                                    image::ExtendedColorType::Rgb8,
-                                )?;
-                                buf.flush()?;
-
-                                buf.get_mut().clone()
-                            };
-
-                        (Some(resized), Some(buf.get_mut().clone()))
+                let (resized_image, thumb, art_source) = match image {
+                    // if there is a decode error, just ignore it and pretend there is no image
+                    Some((image, source)) => {
+                        let (resized, thumb) = prepare_album_art(image)?;
+                        (Some(resized), Some(thumb), *source)
                     }
-                    None => (None, None),
+                    None => (None, None, ArtSource::Unknown),
                 };
 
                 let result: (i64,) =
                     sqlx::query_as(include_str!("../../queries/scan/create_album.sql"))
+                        .persistent(false)
                         .bind(album)
                         .bind(metadata.sort_album.as_ref().unwrap_or(album))
                         .bind(artist_id)
@@ -550,6 +1114,11 @@ impl ScanThread {
                         .bind(&metadata.isrc)
                         .bind(&mbid)
                         .bind(metadata.vinyl_numbering)
+                        .bind(AlbumMatchBasis::from_mbid(&mbid))
+                        .bind(image.as_ref().map(|(image, _)| album_art_hash(image)))
+                        .bind(art_source)
+                        .bind(metadata.original_date)
+                        .bind(metadata.original_year)
                         .fetch_one(&self.pool)
                         .await?;
 
@@ -559,17 +1128,70 @@ impl ScanThread {
         }
     }
 
+    /// Replaces the album's art with the given image, if it's different from the stored art. Only
+    /// the hash of the stored art is loaded to check.
+    async fn update_album_art(
+        &self,
+        album_id: i64,
+        image: &[u8],
+        source: ArtSource,
+    ) -> anyhow::Result<()> {
+        let hash = album_art_hash(image);
+
+        let (stored,): (Option<Vec<u8>>,) =
+            sqlx::query_as(include_str!("../../queries/scan/get_album_image_hash.sql"))
+                .bind(album_id)
+                .fetch_one(&self.pool)
+                .await?;
+
+        if stored.as_ref() == Some(&hash) {
+            return Ok(());
+        }
+
+        debug!("Album art changed for album {album_id}, replacing it");
+        let (resized, thumb) = prepare_album_art(image)?;
+
+        sqlx::query(include_str!("../../queries/scan/update_album_image.sql"))
+            .persistent(false)
+            .bind(album_id)
+            .bind(resized)
+            .bind(thumb)
+            .bind(hash)
+            .bind(source)
+            .execute(&self.pool)
+            .await?;
+
+        self.art_tx
+            .send(AlbumArtChanged(album_id))
+            .expect("could not send album art change");
+
+        Ok(())
+    }
+
+    /// Returns the album a track was moved to with the grouping tool, if any.
+    async fn locked_album(&self, path: &Path) -> anyhow::Result<Option<i64>> {
+        let result: Option<(i64,)> =
+            sqlx::query_as(include_str!("../../queries/scan/get_locked_album.sql"))
+                .bind(DataPaths::get().to_stored(path))
+                .fetch_optional(&self.pool)
+                .await?;
+
+        Ok(result.map(|v| v.0))
+    }
+
     async fn insert_track(
         &self,
-        metadata: &Metadata,
+        file: &FileInformation,
         album_id: Option<i64>,
         path: &Path,
-        length: u64,
+        locked: bool,
     ) -> anyhow::Result<()> {
         if album_id.is_none() {
             return Ok(());
         }
 
+        let (metadata, length_millis, _, estimated_bpm, art_oversized, lossless) = file;
+
         let disc_num = metadata.disc_current.map(|v| v as i64).unwrap_or(-1);
         let find_path: Result<(String,), _> =
             sqlx::query_as(include_str!("../../queries/scan/get_album_path.sql"))
@@ -579,17 +1201,22 @@ impl ScanThread {
                 .await;
 
         let parent = path.parent().unwrap();
+        let paths = DataPaths::get();
+        let stored_parent = paths.to_stored(parent);
 
         match find_path {
+            // the user put this track in its album by hand, so it doesn't need to live in the
+            // album's folder
+            _ if locked => {}
             Ok(path) => {
-                if path.0.as_str() != parent.as_os_str() {
+                if Some(path.0) != stored_parent {
                     return Ok(());
                 }
             }
             Err(sqlx::Error::RowNotFound) => {
                 sqlx::query(include_str!("../../queries/scan/create_album_path.sql"))
                     .bind(album_id)
-                    .bind(parent.to_str())
+                    .bind(&stored_parent)
                     .bind(disc_num)
                     .execute(&self.pool)
                     .await?;
@@ -607,6 +1234,19 @@ impl ScanThread {
             })
             .ok_or_else(|| anyhow::anyhow!("failed to retrieve filename"))?;
 
+        let (bpm, bpm_estimated) = match (metadata.bpm, *estimated_bpm) {
+            (Some(bpm), _) => (Some(bpm as i64), false),
+            (None, Some(bpm)) => (Some(bpm as i64), true),
+            (None, None) => (None, false),
+        };
+
+        // stored as a JSON array of [key, value] pairs
+        let extra_tags = if metadata.extra.is_empty() {
+            None
+        } else {
+            serde_json::to_string(&metadata.extra).ok()
+        };
+
         let result: Result<(i64,), sqlx::Error> =
             sqlx::query_as(include_str!("../../queries/scan/create_track.sql"))
                 .bind(&name)
@@ -614,11 +1254,19 @@ impl ScanThread {
                 .bind(album_id)
                 .bind(metadata.track_current.map(|x| x as i32))
                 .bind(metadata.disc_current.map(|x| x as i32))
-                .bind(length as i32)
-                .bind(path.to_str())
+                .bind(round_millis(*length_millis) as i32)
+                .bind(paths.to_stored(path))
                 .bind(&metadata.genre)
                 .bind(&metadata.artist)
-                .bind(parent.to_str())
+                .bind(&stored_parent)
+                .bind(bpm)
+                .bind(bpm_estimated)
+                .bind(extra_tags)
+                .bind(&metadata.mbid_recording)
+                .bind(&metadata.comment)
+                .bind(*length_millis as i64)
+                .bind(*art_oversized)
+                .bind(*lossless)
                 .fetch_one(&self.pool)
                 .await;
 
@@ -631,8 +1279,9 @@ impl ScanThread {
 
     async fn update_metadata(
         &mut self,
-        metadata: (Metadata, u64, Option<Box<[u8]>>),
+        metadata: FileInformation,
         path: &Path,
+        check_art: bool,
     ) -> anyhow::Result<()> {
         debug!(
             "Adding/updating record for {:?} - {:?}",
@@ -640,22 +1289,93 @@ impl ScanThread {
         );
 
         let artist_id = self.insert_artist(&metadata.0).await?;
-        let album_id = self
-            .insert_album(&metadata.0, artist_id, &metadata.2)
+        let locked_album = self.locked_album(path).await?;
+        let album_id = match locked_album {
+            Some(id) => Some(id),
+            None => {
+                self.insert_album(&metadata.0, artist_id, &metadata.2, check_art)
+                    .await?
+            }
+        };
+        self.insert_track(&metadata, album_id, path, locked_album.is_some())
             .await?;
-        self.insert_track(&metadata.0, album_id, path, metadata.1)
+
+        if let Some(album_id) = album_id {
+            self.scanned_albums.insert(album_id);
+        }
+
+        Ok(())
+    }
+
+    /// Numbers the album's tracks in the order of their file names, so that albums whose files
+    /// aren't tagged with track numbers are still listed in order (see [natural_cmp]).
+    async fn update_fallback_order(&self, album_id: i64) -> anyhow::Result<()> {
+        let query = include_str!("../../queries/scan/list_album_track_locations.sql");
+        let tracks: Vec<(i64, String)> = sqlx::query_as(query)
+            .bind(album_id)
+            .fetch_all(&self.pool)
             .await?;
 
+        let mut tracks: Vec<(i64, String)> = tracks
+            .into_iter()
+            .map(|(track_id, location)| {
+                let file_name = Path::new(&location).file_name().unwrap_or_default();
+                (track_id, file_name.to_string_lossy().into_owned())
+            })
+            .collect();
+        tracks.sort_by(|(_, a), (_, b)| natural_cmp(a, b));
+
+        let query = include_str!("../../queries/scan/update_track_fallback_order.sql");
+        let mut tx = self.pool.begin().await?;
+
+        for (order, (track_id, _)) in tracks.iter().enumerate() {
+            sqlx::query(query)
+                .bind(track_id)
+                .bind(order as i64)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        tx.commit().await?;
+
         Ok(())
     }
 
+    /// Updates the order by file name of the albums that were scanned, along with any albums that
+    /// haven't been put in order yet.
+    fn update_fallback_orders(&mut self) {
+        let mut albums = std::mem::take(&mut self.scanned_albums);
+
+        let result = crate::RUNTIME.block_on(async {
+            let unordered: Vec<(i64,)> = sqlx::query_as(include_str!(
+                "../../queries/scan/find_albums_without_fallback_order.sql"
+            ))
+            .fetch_all(&self.pool)
+            .await?;
+            albums.extend(unordered.into_iter().map(|(album_id,)| album_id));
+
+            for album_id in albums {
+                self.update_fallback_order(album_id).await?;
+            }
+
+            anyhow::Ok(())
+        });
+
+        if let Err(err) = result {
+            error!(?err, "Failed to order tracks by file name: {err}");
+        }
+    }
+
     fn read_metadata_for_path(&mut self, path: &PathBuf) -> Option<FileInformation> {
+        let estimate_bpm = self.scan_settings.estimate_bpm;
+
         for (exts, provider) in &mut self.provider_table {
             if file_is_scannable_with_provider(path, exts)
-                && let Ok(mut metadata) = scan_file_with_provider(path, provider)
+                && let Ok(mut metadata) = scan_file_with_provider(path, provider, estimate_bpm)
             {
                 if metadata.2.is_none() {
-                    metadata.2 = scan_path_for_album_art(path);
+                    metadata.2 =
+                        scan_path_for_album_art(path).map(|image| (image, ArtSource::Folder));
                 }
 
                 return Some(metadata);
@@ -667,9 +1387,14 @@ impl ScanThread {
 
     fn write_scan_record(&self) {
         if let Some(path) = self.scan_record_path.as_ref() {
-            let mut file = File::create(path).unwrap();
-            let data = serde_json::to_string(&self.scan_record).unwrap();
-            if let Err(err) = file.write_all(data.as_bytes()) {
+            let paths = DataPaths::get();
+            let scan_record: FxHashMap<String, u64> = self
+                .scan_record
+                .iter()
+                .filter_map(|(path, modified)| Some((paths.to_stored(path)?, *modified)))
+                .collect();
+            let data = serde_json::to_string(&scan_record).unwrap();
+            if let Err(err) = write_atomically(path, data.as_bytes()) {
                 error!("Could not write scan record: {:?}", err);
                 error!("Scan record will not be saved, this may cause rescans on restart");
             } else {
@@ -683,19 +1408,32 @@ impl ScanThread {
     fn scan(&mut self) {
         if self.to_process.is_empty() {
             info!("Scan complete, writing scan record and stopping");
+            self.update_fallback_orders();
+            self.log_ignored();
             self.write_scan_record();
+            if self.is_full {
+                write_scan_version();
+            }
+            if self.is_force {
+                remove_rebuild_checkpoint();
+            }
             self.scan_state = ScanState::Idle;
             self.event_tx
                 .send(ScanEvent::ScanCompleteIdle)
                 .expect("could not send scan event");
+
+            if self.scan_settings.analyze_loudness {
+                self.start_analysis();
+            }
             return;
         }
 
-        let path = self.to_process.pop().unwrap();
+        let (root, path, scanned_before) = self.to_process.pop_front().unwrap();
         let metadata = self.read_metadata_for_path(&path);
 
         if let Some(metadata) = metadata {
-            let result = crate::RUNTIME.block_on(self.update_metadata(metadata, &path));
+            let result =
+                crate::RUNTIME.block_on(self.update_metadata(metadata, &path, scanned_before));
 
             if let Err(err) = result {
                 error!(
@@ -705,13 +1443,31 @@ impl ScanThread {
             }
 
             self.scanned += 1;
+            self.root_progress[root].scanned += 1;
 
-            if self.scanned.is_multiple_of(5) {
-                self.event_tx
-                    .send(ScanEvent::ScanProgress {
+            let root_progress = self.root_progress[root];
+            let root_done = self
+                .to_process
+                .front()
+                .is_none_or(|(next_root, ..)| *next_root != root);
+
+            if self.scanned.is_multiple_of(5) || root_done {
+                let event = if self.is_force {
+                    ScanEvent::Rebuilding {
                         current: self.scanned,
                         total: self.discovered_total,
-                    })
+                    }
+                } else {
+                    ScanEvent::ScanProgress {
+                        current: self.scanned,
+                        total: self.discovered_total,
+                        kind: self.kind(),
+                        root: root_progress,
+                    }
+                };
+
+                self.event_tx
+                    .send(event)
                     .expect("could not send scan event");
             }
         } else {
@@ -719,10 +1475,175 @@ impl ScanThread {
         }
     }
 
+    /// Stops the scan between files. Files that were found but not scanned yet are taken out of
+    /// the scan record, so that the next scan picks them up. If a force-scan is being stopped, the
+    /// albums it has re-created so far are written to the rebuild checkpoint.
+    fn stop(&mut self) {
+        info!("Scan stopped, writing scan record");
+
+        for (_, path, _) in self.to_process.drain(..) {
+            self.scan_record.remove(&path);
+
+            // the folder's art is checked again along with the files in it
+            if let Some(folder) = path.parent() {
+                self.scan_record.remove(folder);
+            }
+        }
+
+        self.scan_state = ScanState::Idle;
+        self.visited.clear();
+        self.roots.clear();
+        self.discovered.clear();
+
+        self.update_fallback_orders();
+
+        if self.is_force {
+            let data = serde_json::to_string(&self.force_encountered_albums).unwrap();
+
+            if let Err(err) = write_atomically(&rebuild_checkpoint_path(), data.as_bytes()) {
+                error!("Could not write rebuild checkpoint: {:?}", err);
+                error!("The next library rebuild will start from the beginning");
+            } else {
+                info!(
+                    "Rebuild checkpoint written, {} albums done",
+                    self.force_encountered_albums.len()
+                );
+            }
+        }
+
+        self.write_scan_record();
+
+        self.event_tx
+            .send(ScanEvent::ScanCompleteIdle)
+            .expect("could not send scan event");
+    }
+
+    /// Looks up the tracks whose loudness hasn't been measured yet, and starts measuring them.
+    fn start_analysis(&mut self) {
+        let query = include_str!("../../queries/scan/find_tracks_without_loudness.sql");
+        let result: Result<Vec<(i64, String)>, sqlx::Error> =
+            crate::RUNTIME.block_on(sqlx::query_as(query).fetch_all(&self.pool));
+
+        let tracks = match result {
+            Ok(tracks) => tracks,
+            Err(err) => {
+                error!(?err, "Failed to find tracks to analyze: {err}");
+                return;
+            }
+        };
+
+        if tracks.is_empty() {
+            return;
+        }
+
+        info!("Measuring the loudness of {} tracks", tracks.len());
+
+        let paths = DataPaths::get();
+        self.to_analyze = tracks
+            .into_iter()
+            .map(|(id, location)| (id, paths.resolve_stored(&location)))
+            .collect();
+        self.analyzed = 0;
+        self.analyze_total = self.to_analyze.len() as u64;
+        self.scan_state = ScanState::Analyzing;
+
+        self.event_tx
+            .send(ScanEvent::Analyzing {
+                current: 0,
+                total: self.analyze_total,
+            })
+            .expect("could not send scan event");
+    }
+
+    /// Measures the loudness of the next track. Each track's result is written as soon as it's
+    /// measured, so that stopping (or quitting) doesn't lose any of them.
+    fn analyze(&mut self) {
+        if !self.scan_settings.analyze_loudness {
+            self.stop_analysis();
+            return;
+        }
+
+        let Some((id, path)) = self.to_analyze.pop_front() else {
+            info!("Loudness analysis complete");
+            self.scan_state = ScanState::Idle;
+            self.event_tx
+                .send(ScanEvent::ScanCompleteIdle)
+                .expect("could not send scan event");
+            return;
+        };
+
+        let result = self.provider_table.iter_mut().find_map(|(exts, provider)| {
+            file_is_scannable_with_provider(&path, exts)
+                .then(|| analyze_file_with_provider(&path, provider).ok())
+                .flatten()
+        });
+
+        // files that can't be opened right now (like ones on a network share that's gone away)
+        // are tried again after the next scan
+        if let Some(loudness) = result {
+            let query = include_str!("../../queries/scan/update_track_loudness.sql");
+            let result = crate::RUNTIME.block_on(
+                sqlx::query(query)
+                    .bind(loudness.map(|loudness| loudness.integrated))
+                    .bind(loudness.map(|loudness| loudness.peak))
+                    .bind(id)
+                    .execute(&self.pool),
+            );
+
+            if let Err(err) = result {
+                error!(?path, ?err, "Failed to store track loudness: {err}");
+            }
+        } else {
+            warn!("Could not measure the loudness of file: {:?}", path);
+        }
+
+        self.analyzed += 1;
+        self.event_tx
+            .send(ScanEvent::Analyzing {
+                current: self.analyzed,
+                total: self.analyze_total,
+            })
+            .expect("could not send scan event");
+    }
+
+    /// Stops loudness analysis between tracks. The tracks that are left are measured after the
+    /// next scan.
+    fn stop_analysis(&mut self) {
+        info!(
+            "Loudness analysis stopped, {} tracks left",
+            self.to_analyze.len()
+        );
+
+        self.to_analyze.clear();
+        self.scan_state = ScanState::Idle;
+        self.event_tx
+            .send(ScanEvent::ScanCompleteIdle)
+            .expect("could not send scan event");
+    }
+
+    fn log_ignored(&self) {
+        let total: u64 = self.ignored.values().sum();
+
+        if total == 0 {
+            return;
+        }
+
+        let mut reasons: Vec<_> = self.ignored.iter().collect();
+        reasons.sort_by(|a, b| b.1.cmp(a.1));
+
+        let breakdown = reasons
+            .iter()
+            .map(|(reason, count)| format!("{} {}", count, reason.describe()))
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        info!("{} files ignored ({})", total, breakdown);
+    }
+
     async fn delete_track(&mut self, path: &PathBuf) {
         debug!("track deleted or moved: {:?}", path);
         let result = sqlx::query(include_str!("../../queries/scan/delete_track.sql"))
-            .bind(path.to_str())
+            .bind(DataPaths::get().to_stored(path))
             .execute(&self.pool)
             .await;
 
@@ -736,10 +1657,21 @@ impl ScanThread {
     // This is done in one shot because it's required for data integrity
     // Cleanup cannot be cancelled
     fn cleanup(&mut self) {
+        // the tracks on network shares that can't be reached right now haven't been deleted
+        let unreachable: Vec<PathBuf> = self
+            .scan_settings
+            .paths
+            .iter()
+            .filter(|root| root.network_volume)
+            .map(|root| DataPaths::get().resolve_folder(&root.path))
+            .filter(|path| !is_reachable(path))
+            .collect();
+
         self.scan_record
             .clone()
             .iter()
             .filter(|v| !v.0.exists())
+            .filter(|v| !unreachable.iter().any(|root| v.0.starts_with(root)))
             .map(|v| v.0)
             .for_each(|v| {
                 crate::RUNTIME.block_on(self.delete_track(v));
@@ -748,3 +1680,112 @@ impl ScanThread {
         self.scan_state = ScanState::Discovering;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file_metadata() -> fs::Metadata {
+        fs::metadata(concat!(env!("CARGO_MANIFEST_DIR"), "/Cargo.toml")).unwrap()
+    }
+
+    fn folder_metadata() -> fs::Metadata {
+        fs::metadata(env!("CARGO_MANIFEST_DIR")).unwrap()
+    }
+
+    #[test]
+    fn recognizes_junk_files() {
+        for name in [
+            "Thumbs.db",
+            "desktop.ini",
+            ".DS_Store",
+            "song.flac.part",
+            "a.CRDOWNLOAD",
+        ] {
+            assert!(is_junk_file(name), "{name}");
+        }
+
+        for name in ["song.flac", "partial.flac", "thumbs.db.flac"] {
+            assert!(!is_junk_file(name), "{name}");
+        }
+    }
+
+    #[test]
+    fn ignores_files_by_name() {
+        let file = file_metadata();
+        let ignore =
+            |name: &str, include_hidden| ignore_reason(OsStr::new(name), &file, include_hidden);
+
+        assert_eq!(
+            ignore("._song.flac", true),
+            Some(IgnoreReason::ResourceFork)
+        );
+        assert_eq!(ignore("Thumbs.db", true), Some(IgnoreReason::Junk));
+        assert_eq!(ignore(".song.flac", false), Some(IgnoreReason::Hidden));
+        assert_eq!(ignore(".song.flac", true), None);
+        assert_eq!(ignore("song.flac", false), None);
+    }
+
+    #[test]
+    fn only_files_are_junk() {
+        let folder = folder_metadata();
+
+        assert_eq!(ignore_reason(OsStr::new("thumbs.db"), &folder, false), None);
+        assert_eq!(
+            ignore_reason(OsStr::new(".hidden"), &folder, false),
+            Some(IgnoreReason::Hidden)
+        );
+    }
+
+    #[test]
+    fn ignores_files_that_are_too_small() {
+        assert_eq!(size_ignore_reason(0, 0), Some(IgnoreReason::Empty));
+        assert_eq!(size_ignore_reason(0, 1024), Some(IgnoreReason::Empty));
+        assert_eq!(size_ignore_reason(1023, 1024), Some(IgnoreReason::TooSmall));
+        assert_eq!(size_ignore_reason(1024, 1024), None);
+        assert_eq!(size_ignore_reason(1, 0), None);
+    }
+
+    #[test]
+    fn reads_folders_in_order_of_name() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["b.flac", "a.flac", "B.flac", "10.flac", "2.flac"] {
+            File::create(dir.path().join(name)).unwrap();
+        }
+        fs::create_dir(dir.path().join("Disc 1")).unwrap();
+
+        let names: Vec<_> = sorted_entries(dir.path())
+            .unwrap()
+            .into_iter()
+            .map(|entry| entry.file_name())
+            .collect();
+
+        assert_eq!(
+            names,
+            ["10.flac", "2.flac", "B.flac", "Disc 1", "a.flac", "b.flac"]
+        );
+    }
+
+    #[test]
+    fn recognizes_folder_art() {
+        for name in ["folder.jpg", "Cover.JPEG", "front.png"] {
+            assert!(is_folder_art(OsStr::new(name)), "{name}");
+        }
+
+        for name in [
+            "folder",
+            "back.jpg",
+            "cover.gif",
+            "my cover.png",
+            "cover.jpg.part",
+        ] {
+            assert!(!is_folder_art(OsStr::new(name)), "{name}");
+        }
+    }
+
+    #[test]
+    fn hashes_art_by_its_contents() {
+        assert_eq!(album_art_hash(b"image"), album_art_hash(b"image"));
+        assert_ne!(album_art_hash(b"image"), album_art_hash(b"other image"));
+    }
+}
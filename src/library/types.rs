@@ -3,13 +3,30 @@ pub mod table;
 
 use std::{path::PathBuf, sync::Arc};
 
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Datelike, Utc};
 use gpui::{IntoElement, RenderImage, SharedString};
 use image::{Frame, RgbaImage};
+use serde::{Deserialize, Serialize};
 use smallvec::SmallVec;
 use sqlx::{Database, Decode, Sqlite, Type, encode::IsNull, error::BoxDynError};
 
-use crate::util::rgb_to_bgr;
+use crate::{
+    media::metadata::clean_text,
+    paths::DataPaths,
+    util::{rgb_to_bgr, round_millis},
+};
+
+/// A music file's path as it's stored in the library, which may be relative to the library root
+/// in portable mode (see [`DataPaths`]). Converts into the full path.
+#[derive(sqlx::Type)]
+#[sqlx(transparent)]
+pub struct StoredPath(String);
+
+impl From<StoredPath> for PathBuf {
+    fn from(stored: StoredPath) -> Self {
+        DataPaths::get().resolve_stored(&stored.0)
+    }
+}
 
 #[derive(sqlx::FromRow)]
 pub struct Artist {
@@ -197,6 +214,12 @@ pub struct Album {
     /// exists because some tagging software uses the date field as a year field, which cannot be
     /// handled properly as a date.
     pub release_year: Option<u16>,
+    /// When the album was first released, if it's a reissue or a remaster that was tagged with
+    /// it. Like the release date and year, only one of these is filled in.
+    #[sqlx(default)]
+    pub original_date: Option<DateTime<Utc>>,
+    #[sqlx(default)]
+    pub original_year: Option<u16>,
     pub created_at: DateTime<Utc>,
     #[sqlx(default)]
     pub image: Option<Box<[u8]>>,
@@ -216,6 +239,173 @@ pub struct Album {
     /// Whether this album uses vinyl-style track numbering (A1, A2, B1, B2, etc.)
     /// When true, disc numbers should be displayed as "SIDE A", "SIDE B", etc.
     pub vinyl_numbering: bool,
+    #[sqlx(default)]
+    /// Whether the user has hidden this album from the library. Hidden albums are only shown in
+    /// the album list when "Show Hidden" is turned on.
+    pub hidden: bool,
+    /// How the scanner decided which files belong to this album.
+    #[sqlx(default)]
+    pub match_basis: AlbumMatchBasis,
+    /// Where the album's art came from.
+    #[sqlx(default)]
+    pub art_source: ArtSource,
+    /// The album's MusicBrainz release ID, or `"none"` if it wasn't tagged with one. Use
+    /// [`Album::musicbrainz_id`] to read it.
+    #[sqlx(default)]
+    pub mbid: DBString,
+    /// The album artist's name. Only filled in when the album is loaded as a table row, so the
+    /// table doesn't have to look it up while rendering.
+    #[sqlx(skip)]
+    pub artist_name: Option<DBString>,
+}
+
+/// When an album was released, as precisely as it's known.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AlbumDate {
+    Date(DateTime<Utc>),
+    Year(u16),
+}
+
+impl AlbumDate {
+    pub fn year(&self) -> i64 {
+        match self {
+            AlbumDate::Date(date) => i64::from(date.year()),
+            AlbumDate::Year(year) => i64::from(*year),
+        }
+    }
+}
+
+impl Album {
+    /// When this release of the album came out: its date, or otherwise its year.
+    pub fn release(&self) -> Option<AlbumDate> {
+        self.release_date
+            .map(AlbumDate::Date)
+            .or(self.release_year.map(AlbumDate::Year))
+    }
+
+    /// When the album first came out, if it's a reissue or remaster that was tagged with it.
+    pub fn original_release(&self) -> Option<AlbumDate> {
+        self.original_date
+            .map(AlbumDate::Date)
+            .or(self.original_year.map(AlbumDate::Year))
+    }
+
+    /// The date the album is shown, sorted and filtered by. Albums tagged with both an original
+    /// date and a release date use the original date, unless `prefer_original` is false, in which
+    /// case the date of the reissue or remaster is used instead. Either way, full dates are used
+    /// over years, and whichever date is there is used if there's only one.
+    ///
+    /// The album list does the same in SQL (see `album_query::year`), so the two have to agree.
+    pub fn display_date(&self, prefer_original: bool) -> Option<AlbumDate> {
+        if prefer_original {
+            self.original_release().or(self.release())
+        } else {
+            self.release().or(self.original_release())
+        }
+    }
+
+    /// The date that isn't the [`Album::display_date`], if the album has both and they're in
+    /// different years.
+    pub fn other_date(&self, prefer_original: bool) -> Option<AlbumDate> {
+        let (shown, other) = if prefer_original {
+            (self.original_release()?, self.release()?)
+        } else {
+            (self.release()?, self.original_release()?)
+        };
+
+        (shown.year() != other.year()).then_some(other)
+    }
+
+    /// The album's MusicBrainz release ID, if it was tagged with one.
+    pub fn musicbrainz_id(&self) -> Option<&str> {
+        let mbid: &str = self.mbid.0.as_ref();
+        (!mbid.is_empty() && mbid != "none").then_some(mbid)
+    }
+}
+
+/// How the scanner decided which files belong to an album.
+#[derive(sqlx::Type, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[repr(i32)]
+pub enum AlbumMatchBasis {
+    /// Files with the same album title and MusicBrainz release ID.
+    ReleaseId = 0,
+    /// Files with the same album title, and no release ID.
+    #[default]
+    Title = 1,
+    /// The album was split off from another one by hand (see `db::split_album`). The scanner
+    /// never adds new files to it.
+    Manual = 2,
+}
+
+impl AlbumMatchBasis {
+    /// The basis the scanner uses for an album with the given release ID (`"none"` if missing).
+    pub fn from_mbid(mbid: &str) -> Self {
+        if mbid == "none" {
+            AlbumMatchBasis::Title
+        } else {
+            AlbumMatchBasis::ReleaseId
+        }
+    }
+
+    pub fn describe(&self) -> &'static str {
+        match self {
+            AlbumMatchBasis::ReleaseId => "Grouped by album title and MusicBrainz release ID",
+            AlbumMatchBasis::Title => "Grouped by album title (no MusicBrainz release ID)",
+            AlbumMatchBasis::Manual => "Grouped by hand",
+        }
+    }
+}
+
+/// Where an album's art came from. Set by whatever wrote the art.
+#[derive(sqlx::Type, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[repr(i32)]
+pub enum ArtSource {
+    /// The art was stored before its source was recorded, or the album has no art.
+    #[default]
+    Unknown = 0,
+    /// Embedded in the album's files.
+    Embedded = 1,
+    /// An image in the album's folder, like folder.jpg (see `scan::scan_path_for_album_art`).
+    Folder = 2,
+    /// An image file set aside to override the album's art.
+    Sidecar = 3,
+    /// Downloaded from an online service.
+    Online = 4,
+    /// Chosen by hand.
+    Manual = 5,
+}
+
+impl ArtSource {
+    /// Where the art came from, as in "Cover: embedded in files".
+    pub fn describe(&self) -> &'static str {
+        match self {
+            ArtSource::Unknown => "unknown source",
+            ArtSource::Embedded => "embedded in files",
+            ArtSource::Folder => "image in album folder",
+            ArtSource::Sidecar => "override image",
+            ArtSource::Online => "downloaded",
+            ArtSource::Manual => "chosen by hand",
+        }
+    }
+}
+
+/// A track as shown when fixing an album's grouping, with the details that tell the files on
+/// an album apart.
+#[derive(sqlx::FromRow, Clone, Debug)]
+pub struct GroupingTrack {
+    pub id: i64,
+    pub title: DBString,
+    #[sqlx(default)]
+    pub track_number: Option<i32>,
+    #[sqlx(default)]
+    pub disc_number: Option<i32>,
+    #[sqlx(default)]
+    pub folder: Option<DBString>,
+    pub artist_names: Option<DBString>,
+    #[sqlx(try_from = "StoredPath")]
+    pub location: PathBuf,
+    /// Whether the track was moved to this album by hand, in which case rescans leave it here.
+    pub album_locked: bool,
 }
 
 #[derive(sqlx::FromRow, Clone, Debug)]
@@ -229,15 +419,175 @@ pub struct Track {
     pub track_number: Option<i32>,
     #[sqlx(default)]
     pub disc_number: Option<i32>,
+    /// The length of the track in whole seconds. Use [`Track::duration_millis`] for the exact
+    /// length.
     pub duration: i64,
+    /// The length of the track in milliseconds. Tracks that haven't been scanned since this was
+    /// added to the database only have `duration`.
+    #[sqlx(default)]
+    pub duration_ms: Option<i64>,
     pub created_at: DateTime<Utc>,
     #[sqlx(skip)]
     pub genres: Option<Vec<DBString>>,
     #[sqlx(skip)]
     pub tags: Option<Vec<DBString>>,
-    #[sqlx(try_from = "String")]
+    #[sqlx(try_from = "StoredPath")]
     pub location: PathBuf,
     pub artist_names: Option<DBString>,
+    #[sqlx(default)]
+    pub bpm: Option<i64>,
+    /// Whether or not the BPM was estimated from the audio, rather than read from the file's tags
+    /// or entered manually.
+    #[sqlx(default)]
+    pub bpm_estimated: bool,
+    /// Extended tags read from the file (see `Metadata::extra`), as a JSON array of key-value
+    /// pairs. Use [`Track::extra_tags`] to read them.
+    #[sqlx(rename = "extra_tags", default)]
+    pub extra_tags_json: Option<String>,
+    /// A manual adjustment to the track's volume, in decibels, for tracks that are mastered much
+    /// quieter or louder than the rest of the library. Between -[`MAX_GAIN_OFFSET`] and
+    /// [`MAX_GAIN_OFFSET`].
+    #[sqlx(default)]
+    pub gain_offset: f64,
+    /// The track's MusicBrainz recording ID, if it was tagged with one.
+    #[sqlx(default)]
+    pub recording_mbid: Option<DBString>,
+    /// The track's comment, either read from the file or written in the app.
+    #[sqlx(default)]
+    pub comment: Option<DBString>,
+    /// Whether the comment was written in the app, in which case rescanning keeps it.
+    #[sqlx(default)]
+    pub comment_edited: bool,
+    /// The title of the track's album. Like the other `album_` fields, this is only filled in
+    /// when the track is loaded as a table or playlist row.
+    #[sqlx(skip)]
+    pub album_title: Option<DBString>,
+    /// The name of the album's artist, used when the track has no artist names of its own.
+    #[sqlx(skip)]
+    pub album_artist_name: Option<DBString>,
+    /// Whether the track's album uses vinyl-style track numbering.
+    #[sqlx(skip)]
+    pub album_vinyl_numbering: bool,
+}
+
+/// The largest adjustment (in either direction, in decibels) that can be made to a track's volume
+/// with [`Track::gain_offset`].
+pub const MAX_GAIN_OFFSET: f64 = 12.0;
+
+/// How many characters of a track's comment are shown in lists. The whole comment is shown in the
+/// track's properties.
+pub const COMMENT_PREVIEW_LENGTH: usize = 80;
+
+/// Formats a volume adjustment in decibels for display, such as "+3 dB" or "-1.5 dB".
+pub fn format_gain_offset(offset: f64) -> SharedString {
+    format!("{:+} dB", (offset * 10.0).round() / 10.0).into()
+}
+
+impl Track {
+    /// Returns the length of the track in milliseconds, falling back to the length in seconds if
+    /// the exact length hasn't been stored yet.
+    pub fn duration_millis(&self) -> u64 {
+        self.duration_ms
+            .unwrap_or(self.duration.saturating_mul(1000))
+            .max(0) as u64
+    }
+
+    /// Formats the track's length for display, rounded to the nearest second.
+    pub fn duration_display(&self) -> SharedString {
+        let secs = round_millis(self.duration_millis());
+        format!("{}:{:02}", secs / 60, secs % 60).into()
+    }
+
+    /// Formats the track's BPM for display, marking estimated values with a leading tilde.
+    pub fn bpm_display(&self) -> Option<SharedString> {
+        self.bpm.map(|bpm| {
+            if self.bpm_estimated {
+                format!("~{bpm}").into()
+            } else {
+                bpm.to_string().into()
+            }
+        })
+    }
+
+    /// Formats the track's volume adjustment for display, if it has one.
+    pub fn gain_offset_display(&self) -> Option<SharedString> {
+        (self.gain_offset != 0.0).then(|| format_gain_offset(self.gain_offset))
+    }
+
+    /// The track's comment cut down to its first line and [`COMMENT_PREVIEW_LENGTH`] characters,
+    /// for showing in lists.
+    pub fn comment_preview(&self) -> Option<SharedString> {
+        let comment = self.comment.as_ref()?.0.trim();
+        let first_line = comment.lines().next().unwrap_or_default();
+        let preview = clean_text(first_line, COMMENT_PREVIEW_LENGTH, false);
+
+        if first_line.chars().count() > COMMENT_PREVIEW_LENGTH || first_line.len() < comment.len() {
+            Some(format!("{preview}…").into())
+        } else {
+            Some(preview.into())
+        }
+    }
+
+    /// The extended tags (credits, comments, etc.) read from the file, as key-value pairs.
+    pub fn extra_tags(&self) -> Vec<(String, String)> {
+        self.extra_tags_json
+            .as_deref()
+            .and_then(|json| serde_json::from_str(json).ok())
+            .unwrap_or_default()
+    }
+}
+
+/// Identifies a track independently of where its file is, so that it can be found again in another
+/// copy of the same library. Used by queue sessions.
+#[derive(sqlx::FromRow, Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct TrackReference {
+    /// The MusicBrainz ID of the track's release, if it was tagged with one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub album_mbid: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub disc_number: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub track_number: Option<i32>,
+    /// The name of the album's artist.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub artist: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub album: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+}
+
+/// A track in the library that a [`TrackReference`] or path was resolved to.
+#[derive(sqlx::FromRow, Clone, Debug, PartialEq)]
+pub struct ResolvedTrack {
+    pub id: i64,
+    pub album_id: Option<i64>,
+    #[sqlx(try_from = "StoredPath")]
+    pub location: PathBuf,
+}
+
+/// The details of an album needed to show it as a card, such as in the related albums on a
+/// release's page.
+#[derive(sqlx::FromRow, Clone, Debug)]
+pub struct AlbumSummary {
+    pub id: i64,
+    pub title: DBString,
+    pub artist_name: DBString,
+    /// The year the album was released, taken from the release date if there is one.
+    pub year: Option<i64>,
+}
+
+/// An album as listed in the album reports, such as the albums that weren't tagged with a
+/// MusicBrainz release ID.
+#[derive(sqlx::FromRow, Clone, Debug)]
+pub struct UntaggedAlbum {
+    pub id: i64,
+    pub title: DBString,
+    pub artist_name: DBString,
+    /// The year the album was released, taken from the release date if there is one.
+    pub year: Option<i64>,
+    /// The folder the album's first disc was found in.
+    pub folder: Option<DBString>,
 }
 
 #[derive(sqlx::Type, Clone, Copy, Debug, PartialEq)]
@@ -264,6 +614,8 @@ pub struct PlaylistWithCount {
     #[sqlx(rename = "type")]
     pub playlist_type: PlaylistType,
     pub track_count: i64,
+    /// When a track was last added to or removed from the playlist.
+    pub last_modified: Option<DateTime<Utc>>,
 }
 
 #[derive(sqlx::FromRow, Clone, Debug, PartialEq)]
@@ -275,8 +627,116 @@ pub struct PlaylistItem {
     pub position: i64,
 }
 
+/// The columns of a playlist's track listing that it can be sorted by.
+#[derive(sqlx::FromRow, Clone, Debug)]
+pub struct PlaylistTrackSummary {
+    pub item_id: i64,
+    pub title_sortable: DBString,
+    /// The track's artist names, or the album artist's name if it has none.
+    pub artist_name: Option<DBString>,
+    pub album_title: Option<DBString>,
+    pub album_title_sortable: Option<DBString>,
+    /// The length of the track, in milliseconds.
+    pub duration: i64,
+    pub bpm: Option<i64>,
+    /// When the track was added to the playlist.
+    pub added: DateTime<Utc>,
+}
+
 #[derive(sqlx::FromRow, Clone)]
 pub struct TrackStats {
     pub track_count: i64,
+    /// The length of every track in the library put together, in milliseconds.
     pub total_duration: i64,
 }
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn date(year: i32) -> AlbumDate {
+        AlbumDate::Date(Utc.with_ymd_and_hms(year, 6, 1, 0, 0, 0).unwrap())
+    }
+
+    fn year(year: u16) -> AlbumDate {
+        AlbumDate::Year(year)
+    }
+
+    /// Splits a date into the column for full dates and the one for years.
+    fn columns(date: Option<AlbumDate>) -> (Option<DateTime<Utc>>, Option<u16>) {
+        match date {
+            Some(AlbumDate::Date(date)) => (Some(date), None),
+            Some(AlbumDate::Year(year)) => (None, Some(year)),
+            None => (None, None),
+        }
+    }
+
+    fn album(release: Option<AlbumDate>, original: Option<AlbumDate>) -> Album {
+        let (release_date, release_year) = columns(release);
+        let (original_date, original_year) = columns(original);
+
+        Album {
+            id: 1,
+            title: "Album".into(),
+            title_sortable: "Album".into(),
+            artist_id: 1,
+            release_date,
+            release_year,
+            original_date,
+            original_year,
+            created_at: Utc::now(),
+            image: None,
+            thumb: None,
+            image_mime: None,
+            tags: None,
+            label: None,
+            catalog_number: None,
+            isrc: None,
+            vinyl_numbering: false,
+            hidden: false,
+            match_basis: AlbumMatchBasis::default(),
+            art_source: ArtSource::default(),
+            mbid: "none".into(),
+            artist_name: None,
+        }
+    }
+
+    #[test]
+    fn shows_reissues_with_the_preferred_date() {
+        let reissue = album(Some(date(2010)), Some(year(1971)));
+
+        assert_eq!(reissue.display_date(true), Some(year(1971)));
+        assert_eq!(reissue.display_date(false), Some(date(2010)));
+    }
+
+    #[test]
+    fn falls_back_to_whichever_date_there_is() {
+        let original_only = album(None, Some(year(1971)));
+        let release_only = album(Some(year(2010)), None);
+
+        assert_eq!(original_only.display_date(false), Some(year(1971)));
+        assert_eq!(release_only.display_date(true), Some(year(2010)));
+        assert_eq!(album(None, None).display_date(true), None);
+    }
+
+    #[test]
+    fn uses_full_dates_over_years() {
+        let mut album = album(Some(date(2010)), None);
+        album.release_year = Some(2011);
+
+        assert_eq!(album.release(), Some(date(2010)));
+    }
+
+    #[test]
+    fn mentions_the_other_date_when_its_a_different_year() {
+        let reissue = album(Some(date(2010)), Some(year(1971)));
+        let same_year = album(Some(date(1971)), Some(year(1971)));
+
+        assert_eq!(reissue.other_date(false), Some(year(1971)));
+        assert_eq!(reissue.other_date(true), Some(date(2010)));
+        assert_eq!(same_year.other_date(false), None);
+        assert_eq!(album(Some(date(2010)), None).other_date(false), None);
+    }
+}
@@ -3,14 +3,29 @@ use std::sync::Arc;
 use gpui::{App, SharedString};
 use indexmap::IndexMap;
 use rustc_hash::FxBuildHasher;
+use tracing::error;
 
 use super::{Album, Track};
 use crate::{
-    library::db::{AlbumMethod, AlbumSortMethod, LibraryAccess, TrackSortMethod},
-    ui::components::{
-        drag_drop::{AlbumDragData, TrackDragData},
-        table::table_data::{Column, TableData, TableDragData, TableSort},
+    library::{
+        album_query::AlbumQuery,
+        db::{
+            AlbumMethod, AlbumSortMethod, LibraryAccess, LibraryAccessAsync, TrackSortMethod,
+            get_album_by_id, get_artist_name_by_id, get_track_by_id, spawn_query,
+        },
     },
+    settings::SettingsGlobal,
+    ui::{
+        components::{
+            drag_drop::{AlbumDragData, TrackDragData},
+            menu::{Menu, menu, menu_item},
+            table::table_data::{Column, TableData, TableDragData, TableSort},
+        },
+        format,
+        models::Models,
+        toasts::show_toast,
+    },
+    util::round_millis,
 };
 
 #[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
@@ -58,7 +73,7 @@ impl TableData<AlbumColumn> for Album {
     fn get_rows(
         cx: &mut gpui::App,
         sort: Option<TableSort<AlbumColumn>>,
-    ) -> anyhow::Result<Vec<Self::Identifier>> {
+    ) -> impl Future<Output = anyhow::Result<Vec<Self::Identifier>>> + 'static {
         let sort_method = match sort {
             Some(TableSort {
                 column: AlbumColumn::Title,
@@ -103,23 +118,48 @@ impl TableData<AlbumColumn> for Album {
             _ => AlbumSortMethod::ArtistAsc,
         };
 
-        Ok(cx.list_albums(sort_method)?)
+        let models = cx.global::<Models>();
+        let settings = cx.global::<SettingsGlobal>().model.read(cx);
+        let query = AlbumQuery {
+            sort: sort_method,
+            include_hidden: *models.show_hidden_albums.read(cx),
+            filter: *models.album_filter.read(cx),
+            prefer_original_date: settings.interface.prefer_original_date,
+        };
+
+        let rows = cx.list_albums_async(query);
+
+        async move { Ok(rows.await?) }
     }
 
-    fn get_row(cx: &mut gpui::App, id: Self::Identifier) -> anyhow::Result<Option<Arc<Self>>> {
-        Ok(cx.get_album_by_id(id.0 as i64, AlbumMethod::Metadata).ok())
+    fn get_row(
+        cx: &mut gpui::App,
+        id: Self::Identifier,
+    ) -> impl Future<Output = anyhow::Result<Option<Arc<Self>>>> + 'static {
+        let row = spawn_query(cx, move |pool| async move {
+            let album = get_album_by_id(&pool, id.0 as i64, AlbumMethod::Metadata).await?;
+            let mut album = (*album).clone();
+
+            album.artist_name = get_artist_name_by_id(&pool, album.artist_id)
+                .await
+                .ok()
+                .map(|name| (*name).clone().into());
+
+            Ok(Arc::new(album))
+        });
+
+        async move { Ok(row.await.ok()) }
     }
 
     fn get_column(&self, cx: &mut App, column: AlbumColumn) -> Option<SharedString> {
         match column {
             AlbumColumn::Title => Some(self.title.0.clone()),
-            AlbumColumn::Artist => cx
-                .get_artist_name_by_id(self.artist_id)
-                .ok()
-                .map(|v| (*v).clone().into()),
-            AlbumColumn::Date => self
-                .release_date
-                .map(|date| date.format("%x").to_string().into()),
+            AlbumColumn::Artist => self.artist_name.as_ref().map(|v| v.0.clone()),
+            AlbumColumn::Date => {
+                let settings = cx.global::<SettingsGlobal>().model.read(cx);
+                self.display_date(settings.interface.prefer_original_date)
+                    .map(|date| format::album_date_short(&date).into())
+            }
             AlbumColumn::Label => self.label.as_ref().map(|v| v.0.clone()),
             AlbumColumn::CatalogNumber => self.catalog_number.as_ref().map(|v| v.0.clone()),
         }
@@ -163,6 +203,77 @@ impl TableData<AlbumColumn> for Album {
             self.title.0.clone(),
         )))
     }
+
+    fn is_muted(&self) -> bool {
+        self.hidden
+    }
+
+    fn get_type_ahead_key(
+        id: &Self::Identifier,
+        sort: Option<TableSort<AlbumColumn>>,
+    ) -> Option<&str> {
+        // the row's key is the sort name of whichever of these the albums are sorted by
+        match sort.map(|sort| sort.column) {
+            None | Some(AlbumColumn::Title | AlbumColumn::Artist) => Some(&id.1),
+            _ => None,
+        }
+    }
+
+    fn get_section(
+        id: &Self::Identifier,
+        sort: Option<TableSort<AlbumColumn>>,
+    ) -> Option<SharedString> {
+        // the row's key is the artist's sort name, or the year when sorted by date
+        match sort.map(|sort| sort.column) {
+            None | Some(AlbumColumn::Artist) => {
+                let first = id.1.chars().next()?.to_uppercase().next()?;
+
+                Some(if first.is_alphabetic() {
+                    first.to_string().into()
+                } else {
+                    "#".into()
+                })
+            }
+            Some(AlbumColumn::Date) if id.1.is_empty() => Some("Unknown year".into()),
+            Some(AlbumColumn::Date) => Some(id.1.clone().into()),
+            _ => None,
+        }
+    }
+
+    fn get_jump_label(section: &SharedString) -> SharedString {
+        // years are listed by decade
+        match section.parse::<u32>() {
+            Ok(year) => format!("{}s", year - year % 10).into(),
+            Err(_) if section.chars().count() > 1 => "?".into(),
+            Err(_) => section.clone(),
+        }
+    }
+
+    fn get_context_menu(&self, _: &mut App) -> Option<Menu> {
+        let album_id = self.id;
+        let hidden = !self.hidden;
+
+        Some(menu().item(menu_item(
+            "toggle-album-hidden",
+            None::<&str>,
+            if hidden {
+                "Hide from library"
+            } else {
+                "Show in library"
+            },
+            move |_, _, cx| {
+                if let Err(err) = cx.set_album_hidden(album_id, hidden) {
+                    error!(?err, "Failed to update album {album_id}");
+                    show_toast(cx, "Couldn't update the album.");
+                    return;
+                }
+
+                // reloads the album list
+                let show_hidden_albums = cx.global::<Models>().show_hidden_albums.clone();
+                show_hidden_albums.update(cx, |_, cx| cx.notify());
+            },
+        )))
+    }
 }
 
 #[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
@@ -171,7 +282,9 @@ pub enum TrackColumn {
     Title,
     Album,
     Artist,
+    Bpm,
     Length,
+    Comment,
 }
 
 impl Column for TrackColumn {
@@ -181,7 +294,9 @@ impl Column for TrackColumn {
             TrackColumn::Title => "Title",
             TrackColumn::Album => "Album",
             TrackColumn::Artist => "Artist",
+            TrackColumn::Bpm => "BPM",
             TrackColumn::Length => "Length",
+            TrackColumn::Comment => "Comment",
         }
     }
 
@@ -189,13 +304,19 @@ impl Column for TrackColumn {
         !matches!(self, TrackColumn::Title)
     }
 
+    fn is_hidden_by_default(&self) -> bool {
+        matches!(self, TrackColumn::Bpm | TrackColumn::Comment)
+    }
+
     fn all_columns() -> &'static [Self] {
         &[
             TrackColumn::TrackNumber,
             TrackColumn::Title,
             TrackColumn::Album,
             TrackColumn::Artist,
+            TrackColumn::Bpm,
             TrackColumn::Length,
+            TrackColumn::Comment,
         ]
     }
 }
@@ -210,7 +331,7 @@ impl TableData<TrackColumn> for Track {
     fn get_rows(
         cx: &mut gpui::App,
         sort: Option<TableSort<TrackColumn>>,
-    ) -> anyhow::Result<Vec<Self::Identifier>> {
+    ) -> impl Future<Output = anyhow::Result<Vec<Self::Identifier>>> + 'static {
         let sort_method = match sort {
             Some(TableSort {
                 column: TrackColumn::Title,
@@ -244,6 +365,14 @@ impl TableData<TrackColumn> for Track {
                 column: TrackColumn::Length,
                 ascending: false,
             }) => TrackSortMethod::DurationDesc,
+            Some(TableSort {
+                column: TrackColumn::Bpm,
+                ascending: true,
+            }) => TrackSortMethod::BpmAsc,
+            Some(TableSort {
+                column: TrackColumn::Bpm,
+                ascending: false,
+            }) => TrackSortMethod::BpmDesc,
             Some(TableSort {
                 column: TrackColumn::TrackNumber,
                 ascending: true,
@@ -252,68 +381,81 @@ impl TableData<TrackColumn> for Track {
                 column: TrackColumn::TrackNumber,
                 ascending: false,
             }) => TrackSortMethod::TrackNumberDesc,
+            Some(TableSort {
+                column: TrackColumn::Comment,
+                ascending: true,
+            }) => TrackSortMethod::CommentAsc,
+            Some(TableSort {
+                column: TrackColumn::Comment,
+                ascending: false,
+            }) => TrackSortMethod::CommentDesc,
             _ => TrackSortMethod::ArtistAsc,
         };
 
-        Ok(cx.list_tracks(sort_method)?)
+        let rows = cx.list_tracks_async(sort_method);
+
+        async move { Ok(rows.await?) }
     }
 
-    fn get_row(cx: &mut gpui::App, id: Self::Identifier) -> anyhow::Result<Option<Arc<Self>>> {
-        Ok(cx.get_track_by_id(id.0).ok())
+    fn get_row(
+        cx: &mut gpui::App,
+        id: Self::Identifier,
+    ) -> impl Future<Output = anyhow::Result<Option<Arc<Self>>>> + 'static {
+        let row = spawn_query(cx, move |pool| async move {
+            let track = get_track_by_id(&pool, id.0).await?;
+            let Some(album_id) = track.album_id else {
+                return Ok(track);
+            };
+            let Ok(album) = get_album_by_id(&pool, album_id, AlbumMethod::Metadata).await else {
+                return Ok(track);
+            };
+
+            let mut track = (*track).clone();
+            track.album_title = Some(album.title.clone());
+            track.album_vinyl_numbering = album.vinyl_numbering;
+
+            if track.artist_names.is_none() {
+                track.album_artist_name = get_artist_name_by_id(&pool, album.artist_id)
+                    .await
+                    .ok()
+                    .map(|name| (*name).clone().into());
+            }
+
+            Ok(Arc::new(track))
+        });
+
+        async move { Ok(row.await.ok()) }
     }
 
-    fn get_column(&self, cx: &mut App, column: TrackColumn) -> Option<SharedString> {
+    fn get_column(&self, _: &mut App, column: TrackColumn) -> Option<SharedString> {
         match column {
-            TrackColumn::TrackNumber => {
-                let vinyl_numbering = self
-                    .album_id
-                    .and_then(|id| cx.get_album_by_id(id, AlbumMethod::Metadata).ok())
-                    .map(|album| album.vinyl_numbering)
-                    .unwrap_or(false);
-
-                match (self.disc_number, self.track_number) {
-                    (Some(disc), Some(track)) => {
-                        if vinyl_numbering {
-                            let side = (b'A' + (disc - 1) as u8) as char;
-                            Some(format!("{}{}", side, track).into())
-                        } else {
-                            Some(format!("{}-{}", disc, track).into())
-                        }
+            TrackColumn::TrackNumber => match (self.disc_number, self.track_number) {
+                (Some(disc), Some(track)) => {
+                    if self.album_vinyl_numbering {
+                        let side = (b'A' + (disc - 1) as u8) as char;
+                        Some(format!("{}{}", side, track).into())
+                    } else {
+                        Some(format!("{}-{}", disc, track).into())
                     }
-                    (None, Some(track)) => Some(track.to_string().into()),
-                    _ => None,
                 }
-            }
+                (None, Some(track)) => Some(track.to_string().into()),
+                _ => None,
+            },
             TrackColumn::Title => Some(self.title.0.clone()),
-            TrackColumn::Album => {
-                if let Some(album_id) = self.album_id {
-                    cx.get_album_by_id(album_id, AlbumMethod::Metadata)
-                        .ok()
-                        .map(|v| v.title.0.clone())
-                } else {
-                    None
-                }
-            }
-            TrackColumn::Artist => {
-                if let Some(artist) = &self.artist_names {
-                    Some(artist.0.clone())
-                } else if let Some(album_id) = self.album_id {
-                    cx.get_album_by_id(album_id, AlbumMethod::Metadata)
-                        .ok()
-                        .and_then(|album| {
-                            cx.get_artist_name_by_id(album.artist_id)
-                                .ok()
-                                .map(|v| (*v).clone().into())
-                        })
-                } else {
-                    None
-                }
-            }
+            TrackColumn::Album => self.album_title.as_ref().map(|v| v.0.clone()),
+            TrackColumn::Artist => self
+                .artist_names
+                .as_ref()
+                .or(self.album_artist_name.as_ref())
+                .map(|v| v.0.clone()),
+            TrackColumn::Bpm => self.bpm_display(),
             TrackColumn::Length => {
-                let minutes = self.duration / 60;
-                let seconds = self.duration % 60;
+                let duration = round_millis(self.duration_millis());
+                let minutes = duration / 60;
+                let seconds = duration % 60;
                 Some(format!("{:02}:{:02}", minutes, seconds).into())
             }
+            TrackColumn::Comment => self.comment_preview(),
         }
     }
 
@@ -326,7 +468,10 @@ impl TableData<TrackColumn> for Track {
     }
 
     fn column_monospace(column: TrackColumn) -> bool {
-        matches!(column, TrackColumn::Length | TrackColumn::TrackNumber)
+        matches!(
+            column,
+            TrackColumn::Length | TrackColumn::TrackNumber | TrackColumn::Bpm
+        )
     }
 
     fn get_element_id(&self) -> impl Into<gpui::ElementId> {
@@ -349,7 +494,9 @@ impl TableData<TrackColumn> for Track {
         columns.insert(TrackColumn::Title, 350.0);
         columns.insert(TrackColumn::Album, 250.0);
         columns.insert(TrackColumn::Artist, 225.0);
+        columns.insert(TrackColumn::Bpm, 75.0);
         columns.insert(TrackColumn::Length, 100.0);
+        columns.insert(TrackColumn::Comment, 250.0);
         columns
     }
 
@@ -362,3 +509,40 @@ impl TableData<TrackColumn> for Track {
         )))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn section(key: &str, column: AlbumColumn) -> Option<SharedString> {
+        let sort = TableSort {
+            column,
+            ascending: true,
+        };
+
+        Album::get_section(&(1, key.to_string()), Some(sort))
+    }
+
+    #[test]
+    fn sections_albums_by_the_artist_initial() {
+        assert_eq!(section("beatles", AlbumColumn::Artist), Some("B".into()));
+        assert_eq!(section("émilie", AlbumColumn::Artist), Some("É".into()));
+        assert_eq!(section("2Pac", AlbumColumn::Artist), Some("#".into()));
+        assert_eq!(section("", AlbumColumn::Artist), None);
+    }
+
+    #[test]
+    fn sections_albums_by_year() {
+        assert_eq!(section("1994", AlbumColumn::Date), Some("1994".into()));
+        assert_eq!(section("", AlbumColumn::Date), Some("Unknown year".into()));
+        assert_eq!(section("Title", AlbumColumn::Title), None);
+    }
+
+    #[test]
+    fn lists_years_by_decade_in_the_jump_bar() {
+        assert_eq!(Album::get_jump_label(&"1994".into()), "1990s");
+        assert_eq!(Album::get_jump_label(&"2000".into()), "2000s");
+        assert_eq!(Album::get_jump_label(&"Unknown year".into()), "?");
+        assert_eq!(Album::get_jump_label(&"B".into()), "B");
+    }
+}
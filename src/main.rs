@@ -8,9 +8,11 @@ use std::sync::LazyLock;
 
 use tracing_subscriber::{fmt::format::FmtSpan, prelude::*};
 
+mod crash;
 mod devices;
 mod library;
 mod media;
+mod paths;
 mod playback;
 mod services;
 mod settings;
@@ -52,5 +54,7 @@ fn main() -> anyhow::Result<()> {
 
     tracing::info!("version {VERSION_STRING}");
 
+    crash::install_panic_hook();
+
     crate::ui::app::run()
 }
@@ -1,5 +1,9 @@
 pub mod builtin;
 pub mod errors;
+pub mod id3v22;
+pub mod loudness;
 pub mod metadata;
 pub mod playback;
+pub mod source;
+pub mod tempo;
 pub mod traits;
@@ -1,15 +1,27 @@
-use std::{ffi::OsStr, fs::File};
+use std::{
+    borrow::Cow,
+    ffi::OsStr,
+    io::{self, Seek, SeekFrom},
+    sync::LazyLock,
+};
 
 use intx::{I24, U24};
 use regex::Regex;
 use symphonia::{
     core::{
-        audio::{AudioBufferRef, Channels, Signal},
-        codecs::{CODEC_TYPE_NULL, CodecRegistry, Decoder, DecoderOptions},
+        audio::{AudioBufferRef, Signal},
+        codecs::{
+            CODEC_TYPE_AAC, CODEC_TYPE_ALAC, CODEC_TYPE_FLAC, CODEC_TYPE_MP1, CODEC_TYPE_MP2,
+            CODEC_TYPE_MP3, CODEC_TYPE_NULL, CODEC_TYPE_OPUS, CODEC_TYPE_PCM_F32BE,
+            CODEC_TYPE_PCM_F32LE, CODEC_TYPE_PCM_F64BE, CODEC_TYPE_PCM_F64LE, CODEC_TYPE_PCM_S8,
+            CODEC_TYPE_PCM_S16BE, CODEC_TYPE_PCM_S16LE, CODEC_TYPE_PCM_S24BE, CODEC_TYPE_PCM_S24LE,
+            CODEC_TYPE_PCM_S32BE, CODEC_TYPE_PCM_S32LE, CODEC_TYPE_PCM_U8, CODEC_TYPE_VORBIS,
+            CODEC_TYPE_WAVPACK, CodecRegistry, CodecType, Decoder, DecoderOptions,
+        },
         errors::Error,
         formats::{FormatOptions, FormatReader, SeekMode, SeekTo},
         io::MediaSourceStream,
-        meta::{MetadataOptions, StandardTagKey, Tag, Value, Visual},
+        meta::{MetadataOptions, StandardTagKey, StandardVisualKey, Tag, Value, Visual},
         probe::{Hint, ProbeResult},
         units::{Time, TimeBase},
     },
@@ -21,15 +33,19 @@ use symphonia::{
 use symphonia_adapter_libopus::OpusDecoder;
 
 use crate::{
-    devices::format::ChannelSpec,
+    devices::format::{ChannelSpec, Channels},
     media::{
         errors::{
             ChannelRetrievalError, CloseError, FrameDurationError, MetadataError, OpenError,
             PlaybackReadError, PlaybackStartError, PlaybackStopError, SeekError,
             TrackDurationError,
         },
-        metadata::Metadata,
+        id3v22,
+        metadata::{
+            MAX_EXTRA_TAG_LENGTH, MAX_EXTRA_TAGS, Metadata, clean_metadata_text, clean_text,
+        },
         playback::{PlaybackFrame, Samples},
+        source::MediaSource,
         traits::{MediaProvider, MediaProviderFeatures, MediaStream},
     },
 };
@@ -42,19 +58,177 @@ pub struct SymphoniaStream {
     current_metadata: Metadata,
     current_track: u32,
     current_duration: u64,
+    /// The length of the current track and the position in it, in milliseconds.
     current_length: Option<u64>,
     current_position: u64,
     current_timebase: Option<TimeBase>,
     decoder: Option<Box<dyn Decoder>>,
     pending_metadata_update: bool,
-    last_image: Option<Visual>,
+    last_image: Option<Box<[u8]>>,
+}
+
+/// Codecs that keep all of the original audio. A-law and mu-law PCM aren't included, since they
+/// throw some of it away.
+const LOSSLESS_CODECS: &[CodecType] = &[
+    CODEC_TYPE_FLAC,
+    CODEC_TYPE_ALAC,
+    CODEC_TYPE_WAVPACK,
+    CODEC_TYPE_PCM_S8,
+    CODEC_TYPE_PCM_U8,
+    CODEC_TYPE_PCM_S16LE,
+    CODEC_TYPE_PCM_S16BE,
+    CODEC_TYPE_PCM_S24LE,
+    CODEC_TYPE_PCM_S24BE,
+    CODEC_TYPE_PCM_S32LE,
+    CODEC_TYPE_PCM_S32BE,
+    CODEC_TYPE_PCM_F32LE,
+    CODEC_TYPE_PCM_F32BE,
+    CODEC_TYPE_PCM_F64LE,
+    CODEC_TYPE_PCM_F64BE,
+];
+
+const LOSSY_CODECS: &[CodecType] = &[
+    CODEC_TYPE_MP1,
+    CODEC_TYPE_MP2,
+    CODEC_TYPE_MP3,
+    CODEC_TYPE_AAC,
+    CODEC_TYPE_VORBIS,
+    CODEC_TYPE_OPUS,
+];
+
+/// Prefixes of non-standard tag keys that are only meaningful to other software.
+const IGNORED_EXTRA_TAG_PREFIXES: &[&str] = &["MUSICBRAINZ", "REPLAYGAIN", "ACOUSTID", "ITUN"];
+
+/// Returns the front cover if there is one, or otherwise the first picture.
+fn preferred_visual(visuals: &[Visual]) -> Option<&Visual> {
+    visuals
+        .iter()
+        .find(|visual| visual.usage == Some(StandardVisualKey::FrontCover))
+        .or(visuals.first())
+}
+
+/// Converts a time to whole milliseconds, rounded to the nearest one.
+fn time_millis(time: Time) -> u64 {
+    time.seconds * 1000 + (time.frac * 1000.0).round() as u64
+}
+
+/// The names used for the standard tags that are captured as extended tags.
+fn extra_tag_key(key: StandardTagKey) -> Option<&'static str> {
+    Some(match key {
+        StandardTagKey::Arranger => "ARRANGER",
+        StandardTagKey::Comment => "COMMENT",
+        StandardTagKey::Conductor => "CONDUCTOR",
+        StandardTagKey::Copyright => "COPYRIGHT",
+        StandardTagKey::Engineer => "ENGINEER",
+        StandardTagKey::Lyricist => "LYRICIST",
+        StandardTagKey::MixDj => "DJMIXER",
+        StandardTagKey::MixEngineer => "MIXER",
+        StandardTagKey::OriginalDate => "ORIGINALDATE",
+        StandardTagKey::Performer => "PERFORMER",
+        StandardTagKey::Producer => "PRODUCER",
+        StandardTagKey::Remixer => "REMIXER",
+        StandardTagKey::Writer => "WRITER",
+        _ => return None,
+    })
+}
+
+/// The largest ReplayGain adjustment believed to be real, in decibels, either way.
+const MAX_PLAUSIBLE_REPLAYGAIN: f64 = 64.0;
+
+/// Reads a ReplayGain value, which is usually written as text like "-6.52 dB".
+fn parse_replaygain_value(value: &Value) -> Option<f64> {
+    let value = match value {
+        Value::Float(v) => *v,
+        Value::SignedInt(v) => *v as f64,
+        Value::UnsignedInt(v) => *v as f64,
+        // the unit (usually "dB") is left off
+        Value::String(v) => v
+            .trim()
+            .trim_end_matches(|c: char| c.is_ascii_alphabetic() || c.is_whitespace())
+            .parse()
+            .ok()?,
+        _ => return None,
+    };
+
+    value.is_finite().then_some(value)
+}
+
+fn parse_replaygain_gain(value: &Value) -> Option<f64> {
+    parse_replaygain_value(value).filter(|gain| gain.abs() <= MAX_PLAUSIBLE_REPLAYGAIN)
+}
+
+fn parse_replaygain_peak(value: &Value) -> Option<f64> {
+    parse_replaygain_value(value).filter(|peak| *peak > 0.0)
+}
+
+/// A track or disc number with the size of the set, ID3-style ("3/12").
+static POSITION_IN_SET: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(\d+)/(\d+)").expect("valid regex"));
+
+/// A vinyl-style track number: the side's letter and the track on it ("B2").
+static VINYL_TRACK: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^([A-Za-z])(\d+)$").expect("valid regex"));
+
+/// A tag's value as text, without copying it if it's already a string.
+fn tag_text(value: &Value) -> Cow<'_, str> {
+    match value {
+        Value::String(v) => Cow::Borrowed(v),
+        value => Cow::Owned(value.to_string()),
+    }
+}
+
+/// Reads a number that may be stored as text. Text that isn't a plain number gives `None`.
+fn parse_number(value: &Value) -> Option<u64> {
+    match value {
+        Value::String(v) => v.parse().ok(),
+        Value::UnsignedInt(v) => Some(*v),
+        _ => None,
+    }
+}
+
+/// A track or disc number, and the number of tracks or discs if it was given along with it.
+#[derive(Debug, PartialEq, Eq)]
+struct SetPosition {
+    number: Option<u64>,
+    total: Option<u64>,
+}
+
+/// Reads a track or disc number, given either on its own ("3", "03") or with the size of the set
+/// ("3/12"). Returns `None` for values that can't hold a number at all, which should be ignored;
+/// text that isn't a number gives a position without a number.
+fn parse_set_position(value: &Value) -> Option<SetPosition> {
+    if let Value::String(v) = value
+        && let Some(captures) = POSITION_IN_SET.captures(v)
+    {
+        return Some(SetPosition {
+            number: captures[1].parse().ok(),
+            total: captures[2].parse().ok(),
+        });
+    }
+
+    match value {
+        Value::String(_) | Value::UnsignedInt(_) => Some(SetPosition {
+            number: parse_number(value),
+            total: None,
+        }),
+        _ => None,
+    }
+}
+
+/// Reads a vinyl-style track number ("B2"), as the side (counting from 1, like a disc number)
+/// and the track on it.
+fn parse_vinyl_track(value: &Value) -> Option<(u64, Option<u64>)> {
+    let Value::String(v) = value else {
+        return None;
+    };
+    let captures = VINYL_TRACK.captures(v)?;
+    let side = captures[1].as_bytes()[0].to_ascii_uppercase();
+
+    Some((u64::from(side - b'A') + 1, captures[2].parse().ok()))
 }
 
 impl SymphoniaStream {
     fn break_metadata(&mut self, tags: &[Tag]) {
-        let id3_position_in_set_regex = Regex::new(r"(\d+)/(\d+)").unwrap();
-        let vinyl_track_regex = Regex::new(r"(?i)^([A-Z])(\d+)$").unwrap();
-
         for tag in tags {
             match tag.std_key {
                 Some(StandardTagKey::TrackTitle) => {
@@ -81,13 +255,7 @@ impl SymphoniaStream {
                 Some(StandardTagKey::ContentGroup) => {
                     self.current_metadata.grouping = Some(tag.value.to_string())
                 }
-                Some(StandardTagKey::Bpm) => {
-                    self.current_metadata.bpm = match &tag.value {
-                        Value::String(v) => v.clone().parse().ok(),
-                        Value::UnsignedInt(v) => Some(*v),
-                        _ => None,
-                    }
-                }
+                Some(StandardTagKey::Bpm) => self.current_metadata.bpm = parse_number(&tag.value),
                 Some(StandardTagKey::Compilation) => {
                     self.current_metadata.compilation = match tag.value {
                         Value::Boolean(v) => v,
@@ -96,74 +264,50 @@ impl SymphoniaStream {
                     }
                 }
                 Some(StandardTagKey::Date) => {
-                    if let Ok(date) = dateparser::parse(&tag.value.to_string()) {
+                    let text = tag_text(&tag.value);
+                    if let Ok(date) = dateparser::parse(&text) {
                         self.current_metadata.date = Some(date);
-                    } else if let Ok(year) = tag.value.to_string().parse::<u16>() {
+                    } else if let Ok(year) = text.parse::<u16>() {
                         self.current_metadata.year = Some(year);
                     }
                 }
-                Some(StandardTagKey::TrackNumber) => match &tag.value {
-                    Value::String(v) => {
-                        // check for vinyl style numbers
-                        if let Some(captures) = vinyl_track_regex.captures(v) {
-                            if let Some(side) = captures.get(1) {
-                                let side_char =
-                                    side.as_str().to_uppercase().chars().next().unwrap();
-                                let side_num = (side_char as u64) - ('A' as u64) + 1;
-                                self.current_metadata.disc_current = Some(side_num);
-                                self.current_metadata.vinyl_numbering = true;
-                            }
-                            if let Some(track) = captures.get(2) {
-                                self.current_metadata.track_current = track.as_str().parse().ok();
-                            }
-                        // check for MP3-style numbers
-                        } else if let Some(captures) = id3_position_in_set_regex.captures(v) {
-                            if let Some(track) = captures.get(1) {
-                                self.current_metadata.track_current = track.as_str().parse().ok();
-                            }
-                            if let Some(total) = captures.get(2) {
-                                self.current_metadata.track_max = total.as_str().parse().ok();
-                            }
-                        } else {
-                            self.current_metadata.track_current = v.clone().parse().ok();
-                        }
+                Some(StandardTagKey::OriginalDate) => {
+                    let text = tag_text(&tag.value);
+                    if let Ok(date) = dateparser::parse(&text) {
+                        self.current_metadata.original_date = Some(date);
+                    } else if let Ok(year) = text.parse::<u16>() {
+                        self.current_metadata.original_year = Some(year);
                     }
-                    Value::UnsignedInt(v) => {
-                        self.current_metadata.track_current = Some(*v);
+
+                    // still shown with the other extended tags
+                    self.capture_extra_tag(tag);
+                }
+                Some(StandardTagKey::TrackNumber) => {
+                    // vinyl-style numbers give the side in place of the disc
+                    if let Some((side, track)) = parse_vinyl_track(&tag.value) {
+                        self.current_metadata.disc_current = Some(side);
+                        self.current_metadata.vinyl_numbering = true;
+                        self.current_metadata.track_current = track;
+                    } else if let Some(position) = parse_set_position(&tag.value) {
+                        self.current_metadata.track_current = position.number;
+                        if position.total.is_some() {
+                            self.current_metadata.track_max = position.total;
+                        }
                     }
-                    _ => (),
-                },
+                }
                 Some(StandardTagKey::TrackTotal) => {
-                    self.current_metadata.track_max = match &tag.value {
-                        Value::String(v) => v.clone().parse().ok(),
-                        Value::UnsignedInt(v) => Some(*v),
-                        _ => None,
-                    }
+                    self.current_metadata.track_max = parse_number(&tag.value)
                 }
-                Some(StandardTagKey::DiscNumber) => match &tag.value {
-                    Value::String(v) => {
-                        if let Some(captures) = id3_position_in_set_regex.captures(v) {
-                            if let Some(disc) = captures.get(1) {
-                                self.current_metadata.disc_current = disc.as_str().parse().ok();
-                            }
-                            if let Some(total) = captures.get(2) {
-                                self.current_metadata.disc_max = total.as_str().parse().ok();
-                            }
-                        } else {
-                            self.current_metadata.disc_current = v.clone().parse().ok();
+                Some(StandardTagKey::DiscNumber) => {
+                    if let Some(position) = parse_set_position(&tag.value) {
+                        self.current_metadata.disc_current = position.number;
+                        if position.total.is_some() {
+                            self.current_metadata.disc_max = position.total;
                         }
                     }
-                    Value::UnsignedInt(v) => {
-                        self.current_metadata.disc_current = Some(*v);
-                    }
-                    _ => (),
-                },
+                }
                 Some(StandardTagKey::DiscTotal) => {
-                    self.current_metadata.disc_max = match &tag.value {
-                        Value::String(v) => v.clone().parse().ok(),
-                        Value::UnsignedInt(v) => Some(*v),
-                        _ => None,
-                    }
+                    self.current_metadata.disc_max = parse_number(&tag.value)
                 }
                 Some(StandardTagKey::Label) => {
                     self.current_metadata.label = Some(tag.value.to_string())
@@ -183,36 +327,133 @@ impl SymphoniaStream {
                 Some(StandardTagKey::MusicBrainzAlbumId) => {
                     self.current_metadata.mbid_album = Some(tag.value.to_string())
                 }
-                _ => (),
+                Some(StandardTagKey::MusicBrainzRecordingId) => {
+                    self.current_metadata.mbid_recording = Some(tag.value.to_string())
+                }
+                // Picard writes the recording ID as the "track ID" in most formats
+                Some(StandardTagKey::MusicBrainzTrackId) => {
+                    if self.current_metadata.mbid_recording.is_none() {
+                        self.current_metadata.mbid_recording = Some(tag.value.to_string())
+                    }
+                }
+                Some(StandardTagKey::ReplayGainTrackGain) => {
+                    self.current_metadata.replaygain_track_gain = parse_replaygain_gain(&tag.value)
+                }
+                Some(StandardTagKey::ReplayGainAlbumGain) => {
+                    self.current_metadata.replaygain_album_gain = parse_replaygain_gain(&tag.value)
+                }
+                Some(StandardTagKey::ReplayGainTrackPeak) => {
+                    self.current_metadata.replaygain_track_peak = parse_replaygain_peak(&tag.value)
+                }
+                Some(StandardTagKey::ReplayGainAlbumPeak) => {
+                    self.current_metadata.replaygain_album_peak = parse_replaygain_peak(&tag.value)
+                }
+                Some(StandardTagKey::Comment) if self.current_metadata.comment.is_none() => {
+                    let value = clean_text(&tag.value.to_string(), MAX_EXTRA_TAG_LENGTH, true);
+
+                    if !value.is_empty() {
+                        self.current_metadata.comment = Some(value);
+                    }
+                }
+                _ => self.capture_extra_tag(tag),
             }
         }
+
+        // tags are shown all over the interface, so anything that could make them slow to
+        // display or misleading is dealt with here, before they're used anywhere
+        clean_metadata_text(&mut self.current_metadata);
     }
 
-    fn read_base_metadata(&mut self, probed: &mut ProbeResult) {
+    /// Keeps tags that aren't mapped to a metadata field, if they're likely to be interesting to
+    /// the user. Standard keys are given consistent names, regardless of the tag format.
+    fn capture_extra_tag(&mut self, tag: &Tag) {
+        let extra = &mut self.current_metadata.extra;
+
+        if extra.len() >= MAX_EXTRA_TAGS {
+            return;
+        }
+
+        let key = match tag.std_key {
+            Some(std_key) => match extra_tag_key(std_key) {
+                Some(key) => key.to_string(),
+                None => return,
+            },
+            None => {
+                let key = tag.key.trim().to_uppercase();
+                let ignored = IGNORED_EXTRA_TAG_PREFIXES
+                    .iter()
+                    .any(|prefix| key.starts_with(prefix));
+
+                if key.is_empty() || ignored {
+                    return;
+                }
+
+                key
+            }
+        };
+
+        let value = match &tag.value {
+            Value::Binary(_) | Value::Flag => return,
+            value => clean_text(&value.to_string(), MAX_EXTRA_TAG_LENGTH, true),
+        };
+
+        if value.is_empty() {
+            return;
+        }
+
+        // files with more than one kind of tag often repeat the same information in each
+        if !extra.iter().any(|(k, v)| *k == key && *v == value) {
+            extra.push((key, value));
+        }
+    }
+
+    /// Reads the tags and picture found while probing. `fallback_image` is used if the probe
+    /// didn't find a picture, for tags Symphonia can't read pictures from (see [id3v22]).
+    fn read_base_metadata(&mut self, probed: &mut ProbeResult, fallback_image: Option<Vec<u8>>) {
         self.current_metadata = Metadata::default();
         self.last_image = None;
 
         if let Some(metadata) = probed.metadata.get().as_ref().and_then(|m| m.current()) {
             self.break_metadata(metadata.tags());
-            if !metadata.visuals().is_empty() {
-                self.last_image = Some(metadata.visuals()[0].clone());
+            if let Some(visual) = preferred_visual(metadata.visuals()) {
+                self.last_image = Some(visual.data.clone());
             }
         }
 
         if let Some(metadata) = probed.format.metadata().current() {
             self.break_metadata(metadata.tags());
-            if !metadata.visuals().is_empty() {
-                self.last_image = Some(metadata.visuals()[0].clone());
+            if let Some(visual) = preferred_visual(metadata.visuals()) {
+                self.last_image = Some(visual.data.clone());
             }
         }
 
+        if self.last_image.is_none() {
+            self.last_image = fallback_image.map(Vec::into_boxed_slice);
+        }
+
         self.pending_metadata_update = true;
     }
 }
 
 impl MediaProvider for SymphoniaProvider {
-    fn open(&mut self, file: File, ext: Option<&OsStr>) -> Result<Box<dyn MediaStream>, OpenError> {
-        let mss = MediaSourceStream::new(Box::new(file), Default::default());
+    fn open(
+        &mut self,
+        mut source: Box<dyn MediaSource>,
+        ext: Option<&OsStr>,
+    ) -> Result<Box<dyn MediaStream>, OpenError> {
+        // Symphonia skips over ID3v2.2 tags without reading their pictures, so they're read
+        // separately first
+        let fallback_image = if source.is_seekable() {
+            let picture = id3v22::read_picture(&mut source).ok().flatten();
+            source
+                .seek(SeekFrom::Start(0))
+                .map_err(|err| OpenError::Unknown(err.to_string()))?;
+            picture
+        } else {
+            None
+        };
+
+        let mss = MediaSourceStream::new(source, Default::default());
         let meta_opts: MetadataOptions = Default::default();
         let fmt_opts: FormatOptions = Default::default();
 
@@ -245,7 +486,7 @@ impl MediaProvider for SymphoniaProvider {
             last_image: None,
         };
 
-        stream.read_base_metadata(&mut probed);
+        stream.read_base_metadata(&mut probed, fallback_image);
         stream.format = Some(probed.format);
 
         Ok(Box::new(stream))
@@ -295,7 +536,7 @@ impl MediaStream for SymphoniaStream {
         if let Some(frame_count) = track.codec_params.n_frames
             && let Some(tb) = track.codec_params.time_base
         {
-            self.current_length = Some(tb.calc_time(frame_count).seconds);
+            self.current_length = Some(time_millis(tb.calc_time(frame_count)));
             self.current_timebase = Some(tb);
         }
 
@@ -352,6 +593,11 @@ impl MediaStream for SymphoniaStream {
             let packet = match format.next_packet() {
                 Ok(packet) => packet,
                 Err(Error::ResetRequired) => return Err(PlaybackReadError::Eof),
+                // running out of data is how the end of the stream is reported, anything else
+                // means the source stopped being readable partway through
+                Err(Error::IoError(err)) if err.kind() != io::ErrorKind::UnexpectedEof => {
+                    return Err(PlaybackReadError::SourceUnavailable(err.to_string()));
+                }
                 Err(_) => {
                     // TODO: Handle better
                     return Err(PlaybackReadError::Eof);
@@ -378,7 +624,7 @@ impl MediaStream for SymphoniaStream {
                     self.current_duration = decoded.capacity() as u64;
 
                     if let Some(tb) = &self.current_timebase {
-                        self.current_position = tb.calc_time(packet.ts()).seconds;
+                        self.current_position = time_millis(tb.calc_time(packet.ts()));
                     }
 
                     match decoded {
@@ -584,28 +830,24 @@ impl MediaStream for SymphoniaStream {
 
     fn read_image(&mut self) -> Result<Option<Box<[u8]>>, MetadataError> {
         if self.format.is_some() {
-            if let Some(visual) = &self.last_image {
-                let data = Ok(Some(visual.data.clone()));
-                self.last_image = None;
-                data
-            } else {
-                Ok(None)
-            }
+            Ok(self.last_image.take())
         } else {
             Err(MetadataError::InvalidState)
         }
     }
 
-    fn duration_secs(&self) -> Result<u64, TrackDurationError> {
-        if self.decoder.is_none() || self.current_length.is_none() {
-            Err(TrackDurationError::NeverStarted)
-        } else {
-            Ok(self.current_length.unwrap_or_default())
+    fn duration_millis(&self) -> Result<u64, TrackDurationError> {
+        if self.decoder.is_none() {
+            return Err(TrackDurationError::NeverStarted);
         }
+
+        // some formats don't say how long they are, which isn't the same as being empty
+        self.current_length
+            .ok_or_else(|| TrackDurationError::Unknown("the length of the track is unknown".into()))
     }
 
-    fn position_secs(&self) -> Result<u64, TrackDurationError> {
-        if self.decoder.is_none() || self.current_length.is_none() {
+    fn position_millis(&self) -> Result<u64, TrackDurationError> {
+        if self.decoder.is_none() {
             Err(TrackDurationError::NeverStarted)
         } else {
             Ok(self.current_position)
@@ -631,7 +873,7 @@ impl MediaStream for SymphoniaStream {
             .map_err(|e| SeekError::Unknown(e.to_string()))?;
 
         if let Some(timebase) = timebase {
-            self.current_position = timebase.calc_time(seek.actual_ts).seconds;
+            self.current_position = time_millis(timebase.calc_time(seek.actual_ts));
         }
 
         Ok(())
@@ -652,12 +894,231 @@ impl MediaStream for SymphoniaStream {
         // this "fixes" m4a container files but obviously poorly
         //
         // upstream issue: https://github.com/pdeljanov/Symphonia/issues/289
-        Ok(ChannelSpec::Count(
-            track
-                .codec_params
-                .channels
-                .map(Channels::count)
-                .unwrap_or(2) as u16,
-        ))
+        let Some(channels) = track.codec_params.channels else {
+            return Ok(ChannelSpec::Count(2));
+        };
+
+        // Symphonia's channel positions use the same bits as ours, but it knows about a few more
+        // (like a second LFE channel) - if any of those are used, only the count can be given
+        let layout = Channels::from_bits_truncate(channels.bits());
+
+        Ok(if layout.count() as usize == channels.count() {
+            ChannelSpec::Bitmask(layout)
+        } else {
+            ChannelSpec::Count(channels.count() as u16)
+        })
+    }
+
+    fn sample_rate(&self) -> Option<u32> {
+        self.format
+            .as_ref()?
+            .tracks()
+            .iter()
+            .find(|t| t.codec_params.codec != CODEC_TYPE_NULL)?
+            .codec_params
+            .sample_rate
+    }
+
+    fn bits_per_sample(&self) -> Option<u32> {
+        let codec_params = &self
+            .format
+            .as_ref()?
+            .tracks()
+            .iter()
+            .find(|t| t.codec_params.codec != CODEC_TYPE_NULL)?
+            .codec_params;
+
+        // lossy codecs are decoded to floating point samples, whatever bit depth they report
+        if LOSSY_CODECS.contains(&codec_params.codec) {
+            return None;
+        }
+
+        codec_params.bits_per_sample
+    }
+
+    fn is_lossless(&self) -> Option<bool> {
+        let codec = self
+            .format
+            .as_ref()?
+            .tracks()
+            .iter()
+            .find(|t| t.codec_params.codec != CODEC_TYPE_NULL)?
+            .codec_params
+            .codec;
+
+        if LOSSLESS_CODECS.contains(&codec) {
+            Some(true)
+        } else if LOSSY_CODECS.contains(&codec) {
+            Some(false)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(value: &str) -> Value {
+        Value::String(value.to_string())
+    }
+
+    #[test]
+    fn reads_replaygain_gains() {
+        assert_eq!(parse_replaygain_gain(&text("-6.52 dB")), Some(-6.52));
+        assert_eq!(parse_replaygain_gain(&text(" +3.10dB ")), Some(3.1));
+        assert_eq!(parse_replaygain_gain(&Value::Float(-1.5)), Some(-1.5));
+        assert_eq!(parse_replaygain_gain(&Value::SignedInt(-4)), Some(-4.0));
+    }
+
+    #[test]
+    fn ignores_broken_replaygain_gains() {
+        assert_eq!(parse_replaygain_gain(&text("-120 dB")), None);
+        assert_eq!(parse_replaygain_gain(&text("dB")), None);
+        assert_eq!(parse_replaygain_gain(&Value::Float(f64::NAN)), None);
+        assert_eq!(parse_replaygain_gain(&Value::Boolean(true)), None);
+    }
+
+    #[test]
+    fn only_reads_positive_replaygain_peaks() {
+        assert_eq!(parse_replaygain_peak(&text("0.988525")), Some(0.988525));
+        assert_eq!(parse_replaygain_peak(&text("0")), None);
+        assert_eq!(parse_replaygain_peak(&text("-1")), None);
+    }
+
+    fn position(number: Option<u64>, total: Option<u64>) -> Option<SetPosition> {
+        Some(SetPosition { number, total })
+    }
+
+    fn parse_text_position(value: &str) -> Option<SetPosition> {
+        parse_set_position(&text(value))
+    }
+
+    #[test]
+    fn reads_positions_in_a_set() {
+        assert_eq!(parse_text_position("3/12"), position(Some(3), Some(12)));
+        assert_eq!(parse_text_position("03"), position(Some(3), None));
+        assert_eq!(
+            parse_set_position(&Value::UnsignedInt(7)),
+            position(Some(7), None)
+        );
+    }
+
+    #[test]
+    fn reads_positions_that_arent_numbers_as_unknown() {
+        assert_eq!(parse_text_position("junk"), position(None, None));
+        assert_eq!(parse_text_position("B2"), position(None, None));
+        assert_eq!(parse_set_position(&Value::Boolean(true)), None);
+    }
+
+    #[test]
+    fn reads_vinyl_track_numbers() {
+        assert_eq!(parse_vinyl_track(&text("B2")), Some((2, Some(2))));
+        assert_eq!(parse_vinyl_track(&text("b2")), Some((2, Some(2))));
+        assert_eq!(parse_vinyl_track(&text("A10")), Some((1, Some(10))));
+        assert_eq!(parse_vinyl_track(&text("12")), None);
+        assert_eq!(parse_vinyl_track(&text("B")), None);
+        assert_eq!(parse_vinyl_track(&Value::UnsignedInt(2)), None);
+    }
+
+    /// A stream without a file, for reading tags into.
+    fn empty_stream() -> SymphoniaStream {
+        SymphoniaStream {
+            format: None,
+            current_metadata: Metadata::default(),
+            current_track: 0,
+            current_duration: 0,
+            current_length: None,
+            current_position: 0,
+            current_timebase: None,
+            decoder: None,
+            pending_metadata_update: false,
+            last_image: None,
+        }
+    }
+
+    /// The tags of a typical file tagged with MusicBrainz Picard.
+    fn picard_tags() -> Vec<Tag> {
+        use StandardTagKey as Key;
+
+        let tag = |std_key, key: &str, value: &str| Tag::new(Some(std_key), key, text(value));
+
+        vec![
+            tag(Key::TrackTitle, "TITLE", "Song"),
+            tag(Key::Artist, "ARTIST", "Artist"),
+            tag(Key::AlbumArtist, "ALBUMARTIST", "Artist"),
+            tag(Key::Album, "ALBUM", "Album"),
+            tag(Key::Genre, "GENRE", "Electronic"),
+            tag(Key::Date, "DATE", "2015-03-09"),
+            tag(Key::OriginalDate, "ORIGINALDATE", "2012-06-01"),
+            tag(Key::TrackNumber, "TRACKNUMBER", "3/12"),
+            tag(Key::DiscNumber, "DISCNUMBER", "1/2"),
+            tag(Key::Bpm, "BPM", "128"),
+            tag(Key::Label, "LABEL", "Label"),
+            tag(Key::IdentCatalogNumber, "CATALOGNUMBER", "CAT-001"),
+            tag(Key::IdentIsrc, "ISRC", "USRC17607839"),
+            tag(Key::MusicBrainzAlbumId, "MUSICBRAINZ_ALBUMID", "album-id"),
+            tag(Key::MusicBrainzTrackId, "MUSICBRAINZ_TRACKID", "track-id"),
+            tag(Key::ReplayGainTrackGain, "REPLAYGAIN_TRACK_GAIN", "-6.5 dB"),
+            tag(Key::ReplayGainTrackPeak, "REPLAYGAIN_TRACK_PEAK", "0.98"),
+            tag(Key::ReplayGainAlbumGain, "REPLAYGAIN_ALBUM_GAIN", "-7 dB"),
+            tag(Key::ReplayGainAlbumPeak, "REPLAYGAIN_ALBUM_PEAK", "1.0"),
+            tag(Key::Comment, "COMMENT", "Ripped from CD"),
+            tag(Key::MediaFormat, "MEDIA", "CD"),
+        ]
+    }
+
+    #[test]
+    fn reads_a_tagged_file() {
+        let mut stream = empty_stream();
+        stream.break_metadata(&picard_tags());
+
+        let metadata = stream.current_metadata;
+        assert_eq!(metadata.name.as_deref(), Some("Song"));
+        assert_eq!(metadata.track_current, Some(3));
+        assert_eq!(metadata.track_max, Some(12));
+        assert_eq!(metadata.disc_current, Some(1));
+        assert_eq!(metadata.disc_max, Some(2));
+        assert_eq!(metadata.bpm, Some(128));
+        assert_eq!(metadata.mbid_recording.as_deref(), Some("track-id"));
+        assert_eq!(metadata.replaygain_album_peak, Some(1.0));
+        assert_eq!(metadata.comment.as_deref(), Some("Ripped from CD"));
+    }
+
+    #[test]
+    fn cuts_off_long_comments_and_extra_tags() {
+        let long = "é".repeat(MAX_EXTRA_TAG_LENGTH + 10);
+        let tags = [
+            Tag::new(Some(StandardTagKey::Comment), "COMMENT", text(&long)),
+            Tag::new(None, "NOTES", text(&long)),
+            // the same value again, once it's been cleaned
+            Tag::new(None, "NOTES", text(&format!("\u{202E}{long}"))),
+        ];
+
+        let mut stream = empty_stream();
+        stream.break_metadata(&tags);
+
+        let metadata = stream.current_metadata;
+        let cut = "é".repeat(MAX_EXTRA_TAG_LENGTH);
+        assert_eq!(metadata.comment, Some(cut.clone()));
+        assert_eq!(metadata.extra, [("NOTES".to_string(), cut)]);
+    }
+
+    /// Times reading the tags of a file 10,000 times. Run it with
+    /// `cargo test --release tag_parsing_benchmark -- --ignored --nocapture`.
+    #[test]
+    #[ignore = "benchmark"]
+    fn tag_parsing_benchmark() {
+        let tags = picard_tags();
+        let mut stream = empty_stream();
+
+        let start = std::time::Instant::now();
+        for _ in 0..10_000 {
+            stream.current_metadata = Metadata::default();
+            stream.break_metadata(&tags);
+        }
+
+        println!("read 10,000 sets of tags in {:?}", start.elapsed());
     }
 }
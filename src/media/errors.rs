@@ -55,7 +55,7 @@ pub enum PlaybackStopError {
 pub enum PlaybackReadError {
     /// This error means that, for what ever reason, the decoder's setup failed in a manner which
     /// should be impossible. Do not use this error for general decoder errors (use DecodeFatal
-    /// instead), as it will cause the application to crash.
+    /// instead).
     #[error("The media file is not valid and cannot be played")]
     InvalidState,
     #[error("Media is open but was never started")]
@@ -66,6 +66,10 @@ pub enum PlaybackReadError {
     Unknown(String),
     #[error("Decode error: `{0}`")]
     DecodeFatal(String),
+    /// The file (or the archive it's in) couldn't be read anymore, for example because it was
+    /// deleted or the drive it's on was disconnected.
+    #[error("Source unavailable: `{0}`")]
+    SourceUnavailable(String),
 }
 
 #[derive(PartialEq, Eq, Debug, Clone, Error)]
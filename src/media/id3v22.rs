@@ -0,0 +1,230 @@
+use std::io::{self, Read};
+
+/// Tags larger than this aren't read. It leaves room for a picture as large as
+/// [crate::library::scan::MAX_EMBEDDED_ART_SIZE] along with the rest of the tag, so that oversized
+/// pictures are still found (and then reported) rather than silently ignored.
+const MAX_TAG_SIZE: usize = 64 * 1024 * 1024;
+
+/// The picture type of a front cover, which is preferred over any other picture in the tag.
+const FRONT_COVER: u8 = 3;
+
+/// Reads the ID3v2.2 tag at the start of a file, if it has one, and returns the picture in its
+/// `PIC` frame - the front cover, if there's more than one.
+///
+/// Symphonia reads ID3v2.3 and ID3v2.4 pictures, but not the older ID3v2.2 ones, which some old
+/// MP3s (such as those from early versions of iTunes) have instead. Returns `Ok(None)` if the
+/// file doesn't start with an ID3v2.2 tag or the tag has no picture, and an error if it can't be
+/// read.
+pub fn read_picture(reader: &mut impl Read) -> io::Result<Option<Vec<u8>>> {
+    let mut header = [0; 10];
+    reader.read_exact(&mut header)?;
+
+    let [b'I', b'D', b'3', 2, _, flags, ..] = header else {
+        return Ok(None);
+    };
+
+    // the compression flag was never given a compression scheme, so these tags can't be read
+    if flags & 0x40 != 0 {
+        return Ok(None);
+    }
+
+    let size = synchsafe(&header[6..10]) as usize;
+    if size > MAX_TAG_SIZE {
+        return Ok(None);
+    }
+
+    let mut tag = vec![0; size];
+    reader.read_exact(&mut tag)?;
+
+    // in ID3v2.2, unsynchronisation applies to the whole tag at once
+    if flags & 0x80 != 0 {
+        tag = resynchronise(&tag);
+    }
+
+    let found: Vec<(u8, &[u8])> = pictures(&tag).collect();
+    let picture = found
+        .iter()
+        .find(|(kind, _)| *kind == FRONT_COVER)
+        .or(found.first());
+
+    Ok(picture.map(|(_, data)| data.to_vec()))
+}
+
+/// Decodes a synchsafe integer, which has 7 bits in each byte.
+fn synchsafe(bytes: &[u8]) -> u32 {
+    bytes
+        .iter()
+        .fold(0, |value, byte| (value << 7) | u32::from(byte & 0x7f))
+}
+
+/// Undoes unsynchronisation, which inserts a zero byte after every 0xFF so that the tag can't be
+/// mistaken for the start of an MPEG frame.
+fn resynchronise(data: &[u8]) -> Vec<u8> {
+    let mut output = Vec::with_capacity(data.len());
+    let mut previous = 0;
+
+    for &byte in data {
+        if !(previous == 0xff && byte == 0) {
+            output.push(byte);
+        }
+        previous = byte;
+    }
+
+    output
+}
+
+/// Returns the picture type and image data of every `PIC` frame in the tag. Frames have a
+/// three-character ID and a three-byte size, and are followed by padding once they run out.
+fn pictures(mut tag: &[u8]) -> impl Iterator<Item = (u8, &[u8])> {
+    std::iter::from_fn(move || {
+        loop {
+            if tag.len() < 6 || tag[0] == 0 {
+                return None;
+            }
+
+            let size =
+                (usize::from(tag[3]) << 16) | (usize::from(tag[4]) << 8) | usize::from(tag[5]);
+            let frame = tag.get(6..6 + size)?;
+            let id = &tag[..3];
+            tag = &tag[6 + size..];
+
+            if id == b"PIC"
+                && let Some(picture) = parse_picture(frame)
+            {
+                return Some(picture);
+            }
+        }
+    })
+}
+
+/// Parses the body of a `PIC` frame: the text encoding, the image format (like "JPG"), the
+/// picture type, a description, and then the image itself.
+fn parse_picture(frame: &[u8]) -> Option<(u8, &[u8])> {
+    let (&encoding, rest) = frame.split_first()?;
+    let rest = rest.get(3..)?;
+    let (&kind, description) = rest.split_first()?;
+
+    // the description ends with a null character, which is two bytes long in UCS-2
+    let data = if encoding == 1 {
+        let end = description
+            .chunks_exact(2)
+            .position(|pair| pair == [0, 0])?;
+        &description[end * 2 + 2..]
+    } else {
+        let end = description.iter().position(|byte| *byte == 0)?;
+        &description[end + 1..]
+    };
+
+    (!data.is_empty()).then_some((kind, data))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A `PIC` frame with a Latin-1 description.
+    fn picture_frame(kind: u8, data: &[u8]) -> Vec<u8> {
+        let mut body = vec![0];
+        body.extend(b"JPG");
+        body.push(kind);
+        body.extend(b"Cover\0");
+        body.extend(data);
+
+        frame(b"PIC", &body)
+    }
+
+    fn frame(id: &[u8; 3], body: &[u8]) -> Vec<u8> {
+        let size = body.len().to_be_bytes();
+        let mut frame = id.to_vec();
+        frame.extend(&size[size.len() - 3..]);
+        frame.extend(body);
+        frame
+    }
+
+    /// An ID3v2.2 tag with the given flags, frames and some padding. If the unsynchronisation
+    /// flag is set, a zero byte is inserted after every 0xFF.
+    fn tag(flags: u8, frames: &[Vec<u8>]) -> Vec<u8> {
+        let mut body = frames.concat();
+        body.extend([0; 16]);
+
+        if flags & 0x80 != 0 {
+            let mut unsynchronised = Vec::new();
+            for byte in body {
+                unsynchronised.push(byte);
+                if byte == 0xff {
+                    unsynchronised.push(0);
+                }
+            }
+            body = unsynchronised;
+        }
+
+        let size = body.len() as u32;
+        let mut tag = vec![b'I', b'D', b'3', 2, 0, flags];
+        tag.extend((0..4).rev().map(|i| ((size >> (7 * i)) & 0x7f) as u8));
+        tag.extend(body);
+        tag
+    }
+
+    fn read(file: &[u8]) -> Option<Vec<u8>> {
+        read_picture(&mut &file[..]).unwrap()
+    }
+
+    #[test]
+    fn reads_the_front_cover() {
+        let file = tag(
+            0,
+            &[
+                frame(b"TT2", b"\0Title"),
+                picture_frame(0, b"other"),
+                picture_frame(FRONT_COVER, b"front"),
+            ],
+        );
+
+        assert_eq!(read(&file).as_deref(), Some(&b"front"[..]));
+    }
+
+    #[test]
+    fn falls_back_to_the_first_picture() {
+        let file = tag(0, &[picture_frame(0, b"first"), picture_frame(4, b"back")]);
+
+        assert_eq!(read(&file).as_deref(), Some(&b"first"[..]));
+    }
+
+    #[test]
+    fn skips_ucs2_descriptions() {
+        let mut body = vec![1];
+        body.extend(b"PNG");
+        body.push(FRONT_COVER);
+        body.extend([0xff, 0xfe, b'A', 0, 0, 0]);
+        body.extend(b"image");
+
+        let file = tag(0, &[frame(b"PIC", &body)]);
+
+        assert_eq!(read(&file).as_deref(), Some(&b"image"[..]));
+    }
+
+    #[test]
+    fn undoes_unsynchronisation() {
+        let file = tag(0x80, &[picture_frame(FRONT_COVER, &[0xff, 0xd8, 0xff])]);
+
+        assert_eq!(read(&file), Some(vec![0xff, 0xd8, 0xff]));
+    }
+
+    #[test]
+    fn ignores_other_tags() {
+        let mut file = tag(0, &[picture_frame(FRONT_COVER, b"front")]);
+        file[3] = 3;
+        assert_eq!(read(&file), None);
+
+        let compressed = tag(0x40, &[picture_frame(FRONT_COVER, b"front")]);
+        assert_eq!(read(&compressed), None);
+
+        assert_eq!(read(b"fLaC\0\0\0\0\0\0"), None);
+    }
+
+    #[test]
+    fn decodes_synchsafe_integers() {
+        assert_eq!(synchsafe(&[0, 0, 0x02, 0x01]), 257);
+        assert_eq!(synchsafe(&[0x7f, 0x7f, 0x7f, 0x7f]), (1 << 28) - 1);
+    }
+}
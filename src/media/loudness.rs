@@ -0,0 +1,309 @@
+use std::f64::consts::PI;
+
+use crate::{
+    devices::{
+        format::{ChannelSpec, Channels},
+        resample::convert_samples,
+    },
+    media::{playback::Samples, traits::MediaStream},
+};
+
+/// The length of each block that loudness is measured over, and how far apart the blocks start,
+/// in seconds. Blocks overlap by 75%, as EBU R128 specifies.
+const BLOCK_SECONDS: f64 = 0.4;
+const STEP_SECONDS: f64 = 0.1;
+
+/// Blocks quieter than this, in LUFS, are left out entirely.
+const ABSOLUTE_GATE: f64 = -70.0;
+
+/// Blocks more than this many LU quieter than the loudness of the other blocks are left out, so
+/// that quiet passages don't drag the result down.
+const RELATIVE_GATE: f64 = -10.0;
+
+/// The loudness that ReplayGain 2.0 brings tracks to, in LUFS.
+pub const REFERENCE_LOUDNESS: f64 = -18.0;
+
+/// The loudness of a track, measured while scanning.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AnalyzedLoudness {
+    /// The integrated loudness of the track, in LUFS.
+    pub integrated: f64,
+    /// The highest sample in the track, as a fraction of full scale.
+    pub peak: f64,
+}
+
+impl AnalyzedLoudness {
+    /// The adjustment, in decibels, that brings the track to the ReplayGain reference loudness.
+    pub fn gain(&self) -> f64 {
+        REFERENCE_LOUDNESS - self.integrated
+    }
+}
+
+/// A second-order IIR filter, for one channel.
+#[derive(Debug, Clone, Copy)]
+struct Biquad {
+    b: [f64; 3],
+    a: [f64; 2],
+    state: [f64; 2],
+}
+
+impl Biquad {
+    fn process(&mut self, input: f64) -> f64 {
+        let output = self.b[0] * input + self.state[0];
+        self.state[0] = self.b[1] * input - self.a[0] * output + self.state[1];
+        self.state[1] = self.b[2] * input - self.a[1] * output;
+        output
+    }
+}
+
+/// Returns the two stages of the K-weighting filter from ITU-R BS.1770 for the given sample rate:
+/// a high shelf that accounts for the acoustic effect of the head, and a high-pass filter. The
+/// coefficients are worked out the same way as libebur128 does, so that they're right at any
+/// sample rate, not just 48 kHz.
+fn k_weighting(rate: u32) -> [Biquad; 2] {
+    let rate = f64::from(rate);
+
+    let shelf = {
+        let f0 = 1681.974450955533;
+        let gain = 3.999843853973347;
+        let q = 0.7071752369554196;
+
+        let k = (PI * f0 / rate).tan();
+        let vh = 10_f64.powf(gain / 20.0);
+        let vb = vh.powf(0.4996667741545416);
+        let a0 = 1.0 + k / q + k * k;
+
+        Biquad {
+            b: [
+                (vh + vb * k / q + k * k) / a0,
+                2.0 * (k * k - vh) / a0,
+                (vh - vb * k / q + k * k) / a0,
+            ],
+            a: [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+            state: [0.0; 2],
+        }
+    };
+
+    let high_pass = {
+        let f0 = 38.13547087602444;
+        let q = 0.5003270373238773;
+
+        let k = (PI * f0 / rate).tan();
+        let a0 = 1.0 + k / q + k * k;
+
+        Biquad {
+            b: [1.0, -2.0, 1.0],
+            a: [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+            state: [0.0; 2],
+        }
+    };
+
+    [shelf, high_pass]
+}
+
+/// Returns how much each channel counts towards the loudness. Surround channels count for more,
+/// since they're heard from the side, and the low-frequency channel isn't counted at all. If the
+/// positions of the channels aren't known, every channel counts the same.
+fn channel_weights(spec: ChannelSpec) -> Vec<f64> {
+    let Some(layout) = spec.layout() else {
+        return vec![1.0; spec.count() as usize];
+    };
+
+    let surround =
+        Channels::SIDE_LEFT | Channels::SIDE_RIGHT | Channels::BACK_LEFT | Channels::BACK_RIGHT;
+
+    layout
+        .iter()
+        .map(|position| {
+            if position == Channels::LOW_FREQUENCY {
+                0.0
+            } else if surround.contains(position) {
+                1.41
+            } else {
+                1.0
+            }
+        })
+        .collect()
+}
+
+fn to_lufs(power: f64) -> f64 {
+    -0.691 + 10.0 * power.log10()
+}
+
+/// Measures the integrated loudness of an already started stream, as described by EBU R128, by
+/// decoding all of it. The peak is the highest sample, not the true (inter-sample) peak.
+///
+/// Returns `None` if the stream can't be decoded, is DSD, or is silent.
+pub fn measure_loudness(stream: &mut Box<dyn MediaStream>) -> Option<AnalyzedLoudness> {
+    let mut meter = LoudnessMeter::new(channel_weights(stream.channels().ok()?));
+
+    while let Ok(frame) = stream.read_samples() {
+        if matches!(frame.samples, Samples::Dsd(_)) || frame.rate == 0 {
+            return None;
+        }
+
+        let channels: Vec<Vec<f32>> = convert_samples(frame.samples);
+        meter.add(frame.rate, &channels);
+    }
+
+    meter.finish()
+}
+
+/// Measures loudness from audio fed to it a frame at a time (see [measure_loudness]).
+struct LoudnessMeter {
+    weights: Vec<f64>,
+    rate: u32,
+    filters: Vec<[Biquad; 2]>,
+    /// The weighted mean square of each 100ms step.
+    steps: Vec<f64>,
+    /// The weighted sum of squares of the current step, and how far into it we are.
+    step_sum: f64,
+    step_position: usize,
+    step_length: usize,
+    peak: f64,
+}
+
+impl LoudnessMeter {
+    fn new(weights: Vec<f64>) -> Self {
+        Self {
+            weights,
+            rate: 0,
+            filters: Vec::new(),
+            steps: Vec::new(),
+            step_sum: 0.0,
+            step_position: 0,
+            step_length: 0,
+            peak: 0.0,
+        }
+    }
+
+    /// Adds a frame of audio at the given sample rate, as one list of samples per channel.
+    fn add(&mut self, rate: u32, channels: &[Vec<f32>]) {
+        if rate != self.rate {
+            self.rate = rate;
+            self.filters = vec![k_weighting(rate); self.weights.len()];
+            self.step_length = ((f64::from(rate) * STEP_SECONDS) as usize).max(1);
+        }
+
+        let Some(length) = channels.iter().map(Vec::len).min() else {
+            return;
+        };
+
+        for i in 0..length {
+            for (channel, samples) in channels.iter().enumerate() {
+                let sample = f64::from(samples[i]);
+                self.peak = self.peak.max(sample.abs());
+
+                let (Some(weight), Some([shelf, high_pass])) =
+                    (self.weights.get(channel), self.filters.get_mut(channel))
+                else {
+                    continue;
+                };
+
+                let filtered = high_pass.process(shelf.process(sample));
+                self.step_sum += weight * filtered * filtered;
+            }
+
+            self.step_position += 1;
+            if self.step_position == self.step_length {
+                self.steps.push(self.step_sum / self.step_length as f64);
+                self.step_sum = 0.0;
+                self.step_position = 0;
+            }
+        }
+    }
+
+    /// Returns the loudness of everything added so far, or `None` if it was silent.
+    fn finish(self) -> Option<AnalyzedLoudness> {
+        let steps_per_block = (BLOCK_SECONDS / STEP_SECONDS).round() as usize;
+        let blocks: Vec<f64> = self
+            .steps
+            .windows(steps_per_block)
+            .map(|window| window.iter().sum::<f64>() / steps_per_block as f64)
+            .filter(|power| *power > 0.0 && to_lufs(*power) > ABSOLUTE_GATE)
+            .collect();
+
+        if blocks.is_empty() {
+            return None;
+        }
+
+        let mean = |powers: &[f64]| powers.iter().sum::<f64>() / powers.len() as f64;
+        let threshold = to_lufs(mean(&blocks)) + RELATIVE_GATE;
+
+        // the loudest block is always above the threshold, so there's at least one left
+        let gated: Vec<f64> = blocks
+            .into_iter()
+            .filter(|power| to_lufs(*power) > threshold)
+            .collect();
+
+        Some(AnalyzedLoudness {
+            integrated: to_lufs(mean(&gated)),
+            peak: self.peak,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `seconds` of a 997 Hz sine wave at the given level in dBFS, at 48 kHz.
+    fn sine(level: f64, seconds: f64) -> Vec<f32> {
+        let amplitude = 10_f64.powf(level / 20.0);
+
+        (0..(48000.0 * seconds) as usize)
+            .map(|i| (amplitude * (2.0 * PI * 997.0 * i as f64 / 48000.0).sin()) as f32)
+            .collect()
+    }
+
+    fn stereo() -> Vec<f64> {
+        channel_weights(ChannelSpec::Count(2))
+    }
+
+    #[test]
+    fn measures_a_sine_wave() {
+        // from EBU Tech 3341: a stereo sine wave at -23 dBFS measures -23 LUFS
+        let mut meter = LoudnessMeter::new(stereo());
+        let channel = sine(-23.0, 20.0);
+        meter.add(48000, &[channel.clone(), channel]);
+
+        let loudness = meter.finish().unwrap();
+
+        assert!((loudness.integrated + 23.0).abs() < 0.1, "{loudness:?}");
+        assert!((loudness.peak - 10_f64.powf(-23.0 / 20.0)).abs() < 1e-3);
+        assert!((loudness.gain() - 5.0).abs() < 0.1);
+    }
+
+    #[test]
+    fn leaves_out_quiet_passages() {
+        // from EBU Tech 3341: -36 dBFS, then -23 dBFS, then -36 dBFS again measures -23 LUFS
+        let mut meter = LoudnessMeter::new(stereo());
+        for (level, seconds) in [(-36.0, 10.0), (-23.0, 60.0), (-36.0, 10.0)] {
+            let channel = sine(level, seconds);
+            meter.add(48000, &[channel.clone(), channel]);
+        }
+
+        let loudness = meter.finish().unwrap();
+
+        assert!((loudness.integrated + 23.0).abs() < 0.1, "{loudness:?}");
+    }
+
+    #[test]
+    fn silence_has_no_loudness() {
+        let mut meter = LoudnessMeter::new(stereo());
+        meter.add(48000, &[vec![0.0; 48000], vec![0.0; 48000]]);
+
+        assert_eq!(meter.finish(), None);
+    }
+
+    #[test]
+    fn weighs_surround_channels_more() {
+        let surround = Channels::FRONT_LEFT | Channels::LOW_FREQUENCY | Channels::SIDE_LEFT;
+
+        assert_eq!(
+            channel_weights(ChannelSpec::Bitmask(surround)),
+            [1.0, 0.0, 1.41]
+        );
+        assert_eq!(channel_weights(ChannelSpec::Count(5)), [1.0; 5]);
+    }
+}
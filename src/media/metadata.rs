@@ -1,4 +1,24 @@
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Datelike, Utc};
+
+/// The maximum number of extended tags kept per file. Anything past this is dropped.
+pub const MAX_EXTRA_TAGS: usize = 32;
+
+/// The maximum length (in characters) of an extended tag's value. Longer values are cut off.
+pub const MAX_EXTRA_TAG_LENGTH: usize = 2048;
+
+/// The maximum length (in characters) of a tag shown as a single line, like a title or an artist.
+/// Longer values are cut off, so that broken tags can't slow down the views that show them.
+pub const MAX_TEXT_TAG_LENGTH: usize = 500;
+
+/// The earliest year a release is believed to be from. Anything earlier is a broken tag.
+const MIN_PLAUSIBLE_YEAR: i32 = 1860;
+
+/// The largest track or disc number (or count) believed to be real. Larger numbers usually come
+/// from tags that were filled with a placeholder, like 65535.
+const MAX_PLAUSIBLE_POSITION: u64 = 999;
+
+/// The range of tempos believed to be real, in beats per minute.
+const PLAUSIBLE_BPM: std::ops::RangeInclusive<u64> = 20..=400;
 
 #[derive(Debug, Default, PartialEq, Clone)]
 pub struct Metadata {
@@ -19,6 +39,10 @@ pub struct Metadata {
     /// exists because some tagging software uses the date field as a year field, which cannot be
     /// handled properly as a date.
     pub year: Option<u16>,
+    /// When the release was first released, for reissues and remasters. Like [`Metadata::date`]
+    /// and [`Metadata::year`], only one of these is filled in.
+    pub original_date: Option<DateTime<Utc>>,
+    pub original_year: Option<u16>,
 
     pub track_current: Option<u64>,
     pub track_max: Option<u64>,
@@ -31,4 +55,311 @@ pub struct Metadata {
     pub isrc: Option<String>,
 
     pub mbid_album: Option<String>,
+    /// The MusicBrainz recording ID of the track.
+    pub mbid_recording: Option<String>,
+
+    /// The ReplayGain adjustments of the track and of its album, in decibels.
+    pub replaygain_track_gain: Option<f64>,
+    pub replaygain_album_gain: Option<f64>,
+    /// The peak levels of the track and of its album, as a fraction of full scale. Used to keep
+    /// the ReplayGain adjustment from making the track clip.
+    pub replaygain_track_peak: Option<f64>,
+    pub replaygain_album_peak: Option<f64>,
+
+    /// The track's comment. Only the first comment is kept here; any others are kept in
+    /// [`Metadata::extra`]. Bounded by [`MAX_EXTRA_TAG_LENGTH`].
+    pub comment: Option<String>,
+
+    /// Tags that don't have a dedicated field, such as credits (performers, engineers, etc.),
+    /// comments and the original release date, as key-value pairs. Keys are upper case. Bounded
+    /// by [`MAX_EXTRA_TAGS`] and [`MAX_EXTRA_TAG_LENGTH`].
+    pub extra: Vec<(String, String)>,
+}
+
+/// Returns true for characters that change the direction of the text around them. These can make
+/// a tag look like something else entirely, like a file name that appears to end in ".mp3".
+fn is_bidi_control(c: char) -> bool {
+    matches!(
+        c,
+        '\u{061C}' | '\u{200E}' | '\u{200F}' | '\u{202A}'..='\u{202E}' | '\u{2066}'..='\u{2069}'
+    )
+}
+
+/// Removes control characters and direction overrides from a tag value, and cuts it off at
+/// `max_length` characters. Line breaks are kept if `multiline` is set, and turned into spaces
+/// otherwise, as are tabs.
+pub fn clean_text(value: &str, max_length: usize, multiline: bool) -> String {
+    value
+        .chars()
+        .filter_map(|c| match c {
+            '\n' if multiline => Some('\n'),
+            '\t' | '\n' | '\r' => Some(' '),
+            c if c.is_control() || is_bidi_control(c) => None,
+            c => Some(c),
+        })
+        .take(max_length)
+        .collect::<String>()
+        .trim()
+        .to_string()
+}
+
+/// Cleans every text field of the metadata with [`clean_text`]. Empty values are removed.
+///
+/// Returns the name of each field that was changed, for logging.
+pub fn clean_metadata_text(metadata: &mut Metadata) -> Vec<&'static str> {
+    let mut changed = Vec::new();
+
+    let lines = [
+        ("title", &mut metadata.name),
+        ("artist", &mut metadata.artist),
+        ("album artist", &mut metadata.album_artist),
+        ("artist sort name", &mut metadata.artist_sort),
+        ("original artist", &mut metadata.original_artist),
+        ("composer", &mut metadata.composer),
+        ("album", &mut metadata.album),
+        ("album sort name", &mut metadata.sort_album),
+        ("genre", &mut metadata.genre),
+        ("grouping", &mut metadata.grouping),
+        ("label", &mut metadata.label),
+        ("catalog number", &mut metadata.catalog),
+        ("ISRC", &mut metadata.isrc),
+        ("MusicBrainz album ID", &mut metadata.mbid_album),
+        ("MusicBrainz recording ID", &mut metadata.mbid_recording),
+    ];
+
+    for (name, value) in lines {
+        if let Some(text) = value {
+            let cleaned = clean_text(text, MAX_TEXT_TAG_LENGTH, false);
+            if cleaned != *text {
+                changed.push(name);
+                *value = (!cleaned.is_empty()).then_some(cleaned);
+            }
+        }
+    }
+
+    if let Some(comment) = &mut metadata.comment {
+        let cleaned = clean_text(comment, MAX_EXTRA_TAG_LENGTH, true);
+        if cleaned != *comment {
+            changed.push("comment");
+            metadata.comment = (!cleaned.is_empty()).then_some(cleaned);
+        }
+    }
+
+    let mut extra_changed = false;
+    metadata.extra.retain_mut(|(key, value)| {
+        let cleaned_key = clean_text(key, MAX_TEXT_TAG_LENGTH, false);
+        let cleaned_value = clean_text(value, MAX_EXTRA_TAG_LENGTH, true);
+
+        if cleaned_key != *key || cleaned_value != *value {
+            extra_changed = true;
+            *key = cleaned_key;
+            *value = cleaned_value;
+        }
+
+        !key.is_empty() && !value.is_empty()
+    });
+    if extra_changed {
+        changed.push("extended tags");
+    }
+
+    changed
+}
+
+/// Removes metadata values that can't be right, such as a year of 20019 or a track number of 0,
+/// so that they don't end up in the library. Values are removed rather than guessed at, except for
+/// text, which is cleaned up and cut short (see [`clean_metadata_text`]).
+///
+/// Returns a description of each value that was removed, for logging.
+pub fn sanitize_metadata(metadata: &mut Metadata) -> Vec<String> {
+    let mut corrections = Vec::new();
+    let years = MIN_PLAUSIBLE_YEAR..=Utc::now().year() + 1;
+
+    if let Some(date) = metadata.date
+        && !years.contains(&date.year())
+    {
+        corrections.push(format!("date {date} is out of range"));
+        metadata.date = None;
+    }
+
+    if let Some(year) = metadata.year
+        && !years.contains(&(year as i32))
+    {
+        corrections.push(format!("year {year} is out of range"));
+        metadata.year = None;
+    }
+
+    if let Some(date) = metadata.original_date
+        && !years.contains(&date.year())
+    {
+        corrections.push(format!("original date {date} is out of range"));
+        metadata.original_date = None;
+    }
+
+    if let Some(year) = metadata.original_year
+        && !years.contains(&(year as i32))
+    {
+        corrections.push(format!("original year {year} is out of range"));
+        metadata.original_year = None;
+    }
+
+    let positions = [
+        ("track number", &mut metadata.track_current),
+        ("track count", &mut metadata.track_max),
+        ("disc number", &mut metadata.disc_current),
+        ("disc count", &mut metadata.disc_max),
+    ];
+
+    for (name, value) in positions {
+        if let Some(number) = *value
+            && !(1..=MAX_PLAUSIBLE_POSITION).contains(&number)
+        {
+            corrections.push(format!("{name} {number} is out of range"));
+            *value = None;
+        }
+    }
+
+    if let Some(bpm) = metadata.bpm
+        && !PLAUSIBLE_BPM.contains(&bpm)
+    {
+        corrections.push(format!("BPM {bpm} is out of range"));
+        metadata.bpm = None;
+    }
+
+    for name in clean_metadata_text(metadata) {
+        corrections.push(format!(
+            "{name} was cut short or had hidden characters removed"
+        ));
+    }
+
+    corrections
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn removes_hidden_characters_from_text() {
+        assert_eq!(clean_text("a\u{0}b\u{202E}c\u{2066}", 100, false), "abc");
+        assert_eq!(clean_text("a\tb\r\nc", 100, false), "a b  c");
+        assert_eq!(clean_text(" one\ntwo ", 100, true), "one\ntwo");
+    }
+
+    #[test]
+    fn cuts_text_short_on_a_character_boundary() {
+        assert_eq!(clean_text("日本語のタイトル", 3, false), "日本語");
+        assert_eq!(clean_text("🎵🎵🎵", 2, false), "🎵🎵");
+    }
+
+    #[test]
+    fn reports_which_fields_were_cleaned() {
+        let mut metadata = Metadata {
+            name: Some("Song".to_string()),
+            album: Some("Album\u{0}".to_string()),
+            comment: Some("\u{202E}".to_string()),
+            extra: vec![("PERFORMER".to_string(), "Someone\u{7}".to_string())],
+            ..Default::default()
+        };
+
+        let changed = clean_metadata_text(&mut metadata);
+
+        assert_eq!(changed, ["album", "comment", "extended tags"]);
+        assert_eq!(metadata.comment, None);
+        assert_eq!(metadata.extra[0].1, "Someone");
+    }
+
+    #[test]
+    fn cleans_up_text() {
+        let mut metadata = Metadata {
+            name: Some("  Song\u{202E}\0 ".to_string()),
+            artist: Some("First\tSecond".to_string()),
+            comment: Some("One line\nand another".to_string()),
+            ..Default::default()
+        };
+
+        let corrections = sanitize_metadata(&mut metadata);
+
+        assert_eq!(metadata.name.as_deref(), Some("Song"));
+        assert_eq!(metadata.artist.as_deref(), Some("First Second"));
+        assert_eq!(metadata.comment.as_deref(), Some("One line\nand another"));
+        assert_eq!(
+            corrections,
+            [
+                "title was cut short or had hidden characters removed",
+                "artist was cut short or had hidden characters removed",
+            ]
+        );
+    }
+
+    #[test]
+    fn cuts_long_text_short() {
+        let mut metadata = Metadata {
+            album: Some("a".repeat(MAX_TEXT_TAG_LENGTH + 100)),
+            ..Default::default()
+        };
+
+        sanitize_metadata(&mut metadata);
+
+        assert_eq!(metadata.album.unwrap().len(), MAX_TEXT_TAG_LENGTH);
+    }
+
+    #[test]
+    fn removes_text_that_is_left_empty() {
+        let mut metadata = Metadata {
+            genre: Some(" \u{200E} ".to_string()),
+            extra: vec![
+                ("\u{7}".to_string(), "value".to_string()),
+                ("MOOD".to_string(), "Calm".to_string()),
+            ],
+            ..Default::default()
+        };
+
+        sanitize_metadata(&mut metadata);
+
+        assert_eq!(metadata.genre, None);
+        assert_eq!(metadata.extra, [("MOOD".to_string(), "Calm".to_string())]);
+    }
+
+    #[test]
+    fn removes_numbers_that_are_out_of_range() {
+        let mut metadata = Metadata {
+            year: Some(20019),
+            original_year: Some(200),
+            track_current: Some(0),
+            disc_max: Some(65535),
+            bpm: Some(1000),
+            ..Default::default()
+        };
+
+        let corrections = sanitize_metadata(&mut metadata);
+
+        assert_eq!(metadata, Metadata::default());
+        assert_eq!(
+            corrections,
+            [
+                "year 20019 is out of range",
+                "original year 200 is out of range",
+                "track number 0 is out of range",
+                "disc count 65535 is out of range",
+                "BPM 1000 is out of range",
+            ]
+        );
+    }
+
+    #[test]
+    fn keeps_plausible_values() {
+        let mut metadata = Metadata {
+            name: Some("Song".to_string()),
+            year: Some(1999),
+            original_year: Some(1971),
+            track_current: Some(3),
+            track_max: Some(12),
+            bpm: Some(120),
+            ..Default::default()
+        };
+        let original = metadata.clone();
+
+        assert!(sanitize_metadata(&mut metadata).is_empty());
+        assert_eq!(metadata, original);
+    }
 }
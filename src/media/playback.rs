@@ -35,6 +35,23 @@ impl Samples {
             Samples::Dsd(_) => format == SampleFormat::Dsd,
         }
     }
+
+    /// Returns the number of frames (samples per channel).
+    pub fn frames(&self) -> usize {
+        match self {
+            Samples::Float64(v) => v.first().map_or(0, Vec::len),
+            Samples::Float32(v) => v.first().map_or(0, Vec::len),
+            Samples::Signed32(v) => v.first().map_or(0, Vec::len),
+            Samples::Unsigned32(v) => v.first().map_or(0, Vec::len),
+            Samples::Signed24(v) => v.first().map_or(0, Vec::len),
+            Samples::Unsigned24(v) => v.first().map_or(0, Vec::len),
+            Samples::Signed16(v) => v.first().map_or(0, Vec::len),
+            Samples::Unsigned16(v) => v.first().map_or(0, Vec::len),
+            Samples::Signed8(v) => v.first().map_or(0, Vec::len),
+            Samples::Unsigned8(v) => v.first().map_or(0, Vec::len),
+            Samples::Dsd(v) => v.first().map_or(0, Vec::len),
+        }
+    }
 }
 
 pub trait Mute {
@@ -0,0 +1,232 @@
+use std::{
+    fs::{self, File},
+    io::{self, Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+pub use symphonia::core::io::MediaSource;
+
+/// Marks where the path of an archive ends and the path of the file inside it begins, as in
+/// `album.zip!/07 Song.flac`.
+const ARCHIVE_MARKER: &str = ".zip!";
+
+const EOCD_SIGNATURE: u32 = 0x06054b50;
+const CENTRAL_HEADER_SIGNATURE: u32 = 0x02014b50;
+const LOCAL_HEADER_SIGNATURE: u32 = 0x04034b50;
+const EOCD_MIN_SIZE: u64 = 22;
+/// The end of central directory record is followed by a comment of at most this many bytes.
+const MAX_ZIP_COMMENT: u64 = u16::MAX as u64;
+
+/// Splits a path like `album.zip!/07 Song.flac` into the archive's path and the name of the entry
+/// inside it. Returns `None` for regular files.
+fn split_archive_path(path: &Path) -> Option<(PathBuf, String)> {
+    let path = path.to_str()?;
+    let marker = path.to_ascii_lowercase().find(ARCHIVE_MARKER)?;
+    let (archive, rest) = path.split_at(marker + ARCHIVE_MARKER.len() - 1);
+    let entry = rest
+        .strip_prefix("!/")
+        .or_else(|| rest.strip_prefix("!\\"))?;
+
+    // entry names always use forward slashes
+    Some((PathBuf::from(archive), entry.replace('\\', "/")))
+}
+
+/// Opens a file, or a file inside an archive, for decoding.
+pub fn open_source(path: &Path) -> io::Result<Box<dyn MediaSource>> {
+    match split_archive_path(path) {
+        Some((archive, entry)) => Ok(Box::new(ZipEntrySource::open(&archive, &entry)?)),
+        None => Ok(Box::new(File::open(path)?)),
+    }
+}
+
+/// Returns whether the file (or, for a file inside an archive, the archive) exists.
+pub fn source_exists(path: &Path) -> bool {
+    match split_archive_path(path) {
+        Some((archive, _)) => archive.is_file(),
+        None => path.exists(),
+    }
+}
+
+/// Returns when the file (or, for a file inside an archive, the archive) was last modified.
+pub fn source_modified(path: &Path) -> io::Result<SystemTime> {
+    match split_archive_path(path) {
+        Some((archive, _)) => fs::metadata(archive)?.modified(),
+        None => fs::metadata(path)?.modified(),
+    }
+}
+
+fn read_u16(buf: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([buf[offset], buf[offset + 1]])
+}
+
+fn read_u32(buf: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([
+        buf[offset],
+        buf[offset + 1],
+        buf[offset + 2],
+        buf[offset + 3],
+    ])
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+/// A file stored (without compression) inside a zip archive. Reads are passed straight through
+/// to the archive, so nothing is extracted.
+pub struct ZipEntrySource {
+    archive: File,
+    /// Where the entry's data starts in the archive.
+    start: u64,
+    len: u64,
+    pos: u64,
+}
+
+impl ZipEntrySource {
+    pub fn open(archive_path: &Path, entry: &str) -> io::Result<Self> {
+        let mut archive = File::open(archive_path)?;
+        let (local_header, len) = Self::find_entry(&mut archive, entry)?;
+
+        let mut header = [0u8; 30];
+        archive.seek(SeekFrom::Start(local_header))?;
+        archive.read_exact(&mut header)?;
+
+        if read_u32(&header, 0) != LOCAL_HEADER_SIGNATURE {
+            return Err(invalid("zip entry has an invalid local header"));
+        }
+
+        let start = local_header + 30 + read_u16(&header, 26) as u64 + read_u16(&header, 28) as u64;
+        archive.seek(SeekFrom::Start(start))?;
+
+        Ok(Self {
+            archive,
+            start,
+            len,
+            pos: 0,
+        })
+    }
+
+    /// Looks the entry up in the archive's central directory, and returns the offset of its
+    /// local header and its size.
+    fn find_entry(archive: &mut File, entry: &str) -> io::Result<(u64, u64)> {
+        let archive_len = archive.metadata()?.len();
+        if archive_len < EOCD_MIN_SIZE {
+            return Err(invalid("file is too small to be a zip archive"));
+        }
+
+        // the end of central directory record is at the end of the file, before the comment
+        let tail_len = archive_len.min(EOCD_MIN_SIZE + MAX_ZIP_COMMENT);
+        let mut tail = vec![0u8; tail_len as usize];
+        archive.seek(SeekFrom::Start(archive_len - tail_len))?;
+        archive.read_exact(&mut tail)?;
+
+        let eocd = (0..=tail.len() - EOCD_MIN_SIZE as usize)
+            .rev()
+            .find(|&i| read_u32(&tail, i) == EOCD_SIGNATURE)
+            .ok_or_else(|| invalid("zip archive has no central directory"))?;
+
+        let entry_count = read_u16(&tail, eocd + 10);
+        let directory_len = read_u32(&tail, eocd + 12);
+        let directory_offset = read_u32(&tail, eocd + 16);
+
+        if entry_count == u16::MAX || directory_offset == u32::MAX {
+            return Err(invalid("zip64 archives aren't supported"));
+        }
+
+        let mut directory = vec![0u8; directory_len as usize];
+        archive.seek(SeekFrom::Start(directory_offset as u64))?;
+        archive.read_exact(&mut directory)?;
+
+        let mut offset = 0;
+        for _ in 0..entry_count {
+            if offset + 46 > directory.len()
+                || read_u32(&directory, offset) != CENTRAL_HEADER_SIGNATURE
+            {
+                return Err(invalid("zip archive has an invalid central directory"));
+            }
+
+            let method = read_u16(&directory, offset + 10);
+            let compressed_len = read_u32(&directory, offset + 20);
+            let name_len = read_u16(&directory, offset + 28) as usize;
+            let extra_len = read_u16(&directory, offset + 30) as usize;
+            let comment_len = read_u16(&directory, offset + 32) as usize;
+            let local_header = read_u32(&directory, offset + 42);
+
+            let name = directory
+                .get(offset + 46..offset + 46 + name_len)
+                .ok_or_else(|| invalid("zip archive has an invalid central directory"))?;
+
+            if String::from_utf8_lossy(name) == entry {
+                if method != 0 {
+                    return Err(io::Error::new(
+                        io::ErrorKind::Unsupported,
+                        "only files stored without compression can be played from zip archives",
+                    ));
+                }
+
+                return Ok((local_header as u64, compressed_len as u64));
+            }
+
+            offset += 46 + name_len + extra_len + comment_len;
+        }
+
+        Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("{entry} isn't in the archive"),
+        ))
+    }
+}
+
+impl Read for ZipEntrySource {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let remaining = self.len.saturating_sub(self.pos);
+        if remaining == 0 || buf.is_empty() {
+            return Ok(0);
+        }
+
+        let max = buf.len().min(remaining as usize);
+        let read = self.archive.read(&mut buf[..max])?;
+
+        // the archive was truncated or replaced while it was being read
+        if read == 0 {
+            return Err(io::Error::other(
+                "the archive ended before the file inside it",
+            ));
+        }
+
+        self.pos += read as u64;
+        Ok(read)
+    }
+}
+
+impl Seek for ZipEntrySource {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let new_pos = match pos {
+            SeekFrom::Start(pos) => Some(pos),
+            SeekFrom::End(offset) => self.len.checked_add_signed(offset),
+            SeekFrom::Current(offset) => self.pos.checked_add_signed(offset),
+        }
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "seek to a position before the start of the file",
+            )
+        })?;
+
+        self.archive.seek(SeekFrom::Start(self.start + new_pos))?;
+        self.pos = new_pos;
+
+        Ok(new_pos)
+    }
+}
+
+impl MediaSource for ZipEntrySource {
+    fn is_seekable(&self) -> bool {
+        true
+    }
+
+    fn byte_len(&self) -> Option<u64> {
+        Some(self.len)
+    }
+}
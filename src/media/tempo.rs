@@ -0,0 +1,126 @@
+use crate::{
+    devices::resample::convert_samples,
+    media::{playback::Samples, traits::MediaStream},
+};
+
+/// The amount of audio, in seconds, that is decoded when estimating the tempo of a track.
+const ANALYSIS_SECONDS: f64 = 60.0;
+
+/// The minimum amount of audio, in seconds, required to produce an estimate. Anything shorter
+/// than this doesn't contain enough beats for the autocorrelation to be meaningful.
+const MIN_ANALYSIS_SECONDS: f64 = 10.0;
+
+/// The rate of the onset envelope, in frames per second.
+const ENVELOPE_RATE: f64 = 100.0;
+
+const MIN_BPM: f64 = 60.0;
+const MAX_BPM: f64 = 200.0;
+
+/// Most music sits around this tempo. Candidate tempos are weighted towards it, which stops the
+/// estimator from reporting half or double the actual tempo when both correlate similarly.
+const PREFERRED_BPM: f64 = 120.0;
+
+/// Estimates the tempo of an already started stream by decoding (up to) the first minute of
+/// audio, building an onset envelope from the change in energy over time, and finding the
+/// strongest periodicity in that envelope with autocorrelation.
+///
+/// Returns `None` if the stream is too short, can't be decoded, or has no discernible pulse.
+pub fn estimate_bpm(stream: &mut Box<dyn MediaStream>) -> Option<u32> {
+    let mut envelope: Vec<f64> = Vec::new();
+    let mut window_energy = 0.0;
+    let mut window_len = 0usize;
+    let mut analyzed = 0.0;
+
+    while analyzed < ANALYSIS_SECONDS {
+        let Ok(frame) = stream.read_samples() else {
+            break;
+        };
+
+        if matches!(frame.samples, Samples::Dsd(_)) || frame.rate == 0 {
+            return None;
+        }
+
+        let hop = ((frame.rate as f64 / ENVELOPE_RATE) as usize).max(1);
+        let channels: Vec<Vec<f32>> = convert_samples(frame.samples);
+        let Some(length) = channels.iter().map(|v| v.len()).min() else {
+            continue;
+        };
+
+        for i in 0..length {
+            let mono = channels.iter().map(|c| c[i] as f64).sum::<f64>() / channels.len() as f64;
+            window_energy += mono * mono;
+            window_len += 1;
+
+            if window_len == hop {
+                envelope.push((window_energy / hop as f64 + 1e-10).ln());
+                window_energy = 0.0;
+                window_len = 0;
+            }
+        }
+
+        analyzed += length as f64 / frame.rate as f64;
+    }
+
+    if analyzed < MIN_ANALYSIS_SECONDS {
+        return None;
+    }
+
+    // only increases in energy are interesting, they're where the beats are
+    let onsets: Vec<f64> = envelope
+        .windows(2)
+        .map(|w| (w[1] - w[0]).max(0.0))
+        .collect();
+
+    let mean = onsets.iter().sum::<f64>() / onsets.len() as f64;
+    let onsets: Vec<f64> = onsets.iter().map(|v| v - mean).collect();
+
+    let min_lag = (60.0 * ENVELOPE_RATE / MAX_BPM).floor() as usize;
+    let max_lag = (60.0 * ENVELOPE_RATE / MIN_BPM).ceil() as usize;
+
+    if onsets.len() <= max_lag * 2 {
+        return None;
+    }
+
+    let correlation: Vec<f64> = (min_lag - 1..=max_lag + 1)
+        .map(|lag| {
+            onsets
+                .iter()
+                .zip(&onsets[lag..])
+                .map(|(a, b)| a * b)
+                .sum::<f64>()
+                / (onsets.len() - lag) as f64
+        })
+        .collect();
+
+    let weight = |lag: f64| {
+        let octaves = (60.0 * ENVELOPE_RATE / lag / PREFERRED_BPM).log2();
+        (-0.5 * octaves * octaves).exp()
+    };
+
+    // skip the first and last entries, they're only there for interpolation
+    let (best, peak) = (1..correlation.len() - 1)
+        .map(|i| (i, correlation[i] * weight((i + min_lag - 1) as f64)))
+        .max_by(|a, b| a.1.total_cmp(&b.1))?;
+
+    if peak <= 0.0 {
+        return None;
+    }
+
+    // parabolic interpolation around the peak gives sub-frame lag resolution
+    let (prev, cur, next) = (
+        correlation[best - 1],
+        correlation[best],
+        correlation[best + 1],
+    );
+    let denominator = prev - 2.0 * cur + next;
+    let offset = if denominator.abs() > f64::EPSILON {
+        (0.5 * (prev - next) / denominator).clamp(-0.5, 0.5)
+    } else {
+        0.0
+    };
+
+    let lag = (best + min_lag - 1) as f64 + offset;
+    let bpm = 60.0 * ENVELOPE_RATE / lag;
+
+    Some(bpm.round() as u32)
+}
@@ -1,4 +1,4 @@
-use std::{ffi::OsStr, fs::File};
+use std::ffi::OsStr;
 
 use bitflags::bitflags;
 
@@ -11,6 +11,7 @@ use super::{
     },
     metadata::Metadata,
     playback::PlaybackFrame,
+    source::MediaSource,
 };
 
 bitflags! {
@@ -52,10 +53,16 @@ bitflags! {
 /// Metadata retrieval, decoding, or both. This allows for a decoding Provider to retrieve
 /// in-codec metadata without opening the file twice.
 pub trait MediaProvider {
-    /// Requests the Provider open the specified file. The file is provided as a File object, and
-    /// the extension is provided as an Option<&OsStr>. If the extension is not provided, the
-    /// Provider attempts to determine the file type based off of the file's contents.
-    fn open(&mut self, file: File, ext: Option<&OsStr>) -> Result<Box<dyn MediaStream>, OpenError>;
+    /// Requests the Provider open the specified source. The source can be a File, or anything else
+    /// that can be read and seeked (such as a file inside an archive, see
+    /// [open_source](super::source::open_source)), and the extension is provided as an
+    /// Option<&OsStr>. If the extension is not provided, the Provider attempts to determine the
+    /// file type based off of the file's contents.
+    fn open(
+        &mut self,
+        source: Box<dyn MediaSource>,
+        ext: Option<&OsStr>,
+    ) -> Result<Box<dyn MediaStream>, OpenError>;
 
     /// Returns a list of mime-types that the Provider supports. Files will be checked against
     /// mime-types *before* being checked against extensions. If the mime-type is not
@@ -120,16 +127,30 @@ pub trait MediaStream {
     /// error.
     fn read_image(&mut self) -> Result<Option<Box<[u8]>>, MetadataError>;
 
-    /// Returns the duration of the currently opened file in seconds. If no file is opened, or
-    /// playback has not started, this function should return an error. This function should be
-    /// available immediately after playback has started, and should not require reading any
-    /// samples.
-    fn duration_secs(&self) -> Result<u64, TrackDurationError>;
+    /// Returns the duration of the currently opened file in milliseconds. If no file is opened,
+    /// playback has not started, or the duration of the file is unknown, this function should
+    /// return an error. Files that are shorter than a millisecond have a duration of 0. This
+    /// function should be available immediately after playback has started, and should not
+    /// require reading any samples.
+    fn duration_millis(&self) -> Result<u64, TrackDurationError>;
+
+    /// Returns the current playback position in milliseconds. If no file is opened, or playback
+    /// has not started, this function should return an error. The position is available even if
+    /// the duration of the file is unknown. This function should be available immediately after
+    /// playback has started, and should not require reading any samples.
+    fn position_millis(&self) -> Result<u64, TrackDurationError>;
+
+    /// Returns the duration of the currently opened file in whole seconds, rounded down. See
+    /// [MediaStream::duration_millis].
+    fn duration_secs(&self) -> Result<u64, TrackDurationError> {
+        self.duration_millis().map(|millis| millis / 1000)
+    }
 
-    /// Returns the current playback position in seconds. If no file is opened, or playback has not
-    /// started, this function should return an error. This function should be available
-    /// immediately after playback has started, and should not require reading any samples.
-    fn position_secs(&self) -> Result<u64, TrackDurationError>;
+    /// Returns the current playback position in whole seconds, rounded down. See
+    /// [MediaStream::position_millis].
+    fn position_secs(&self) -> Result<u64, TrackDurationError> {
+        self.position_millis().map(|millis| millis / 1000)
+    }
 
     /// Returns the chnanel specification used by the track being decoded. This function should be
     /// available immediately after playback has started, and should not require reading any
@@ -138,4 +159,24 @@ pub trait MediaStream {
     /// This function is used by the playback thread to determine whether or not the track's
     /// channel count can be handled by the current device, and if it is, change the channel count.
     fn channels(&self) -> Result<ChannelSpec, ChannelRetrievalError>;
+
+    /// Returns the sample rate the track being decoded was recorded at, or None if that isn't
+    /// known. Along with [MediaStream::bits_per_sample], this is used to open the device in the
+    /// track's own format when exclusive mode is on.
+    fn sample_rate(&self) -> Option<u32> {
+        None
+    }
+
+    /// Returns the bit depth of the track being decoded, or None if it's decoded to floating
+    /// point samples or the bit depth isn't known.
+    fn bits_per_sample(&self) -> Option<u32> {
+        None
+    }
+
+    /// Returns whether the track being decoded is compressed without losing any information (like
+    /// FLAC or WAV), or not (like MP3), or None if that isn't known. This is stored while
+    /// scanning, so that albums can be filtered by it.
+    fn is_lossless(&self) -> Option<bool> {
+        None
+    }
 }
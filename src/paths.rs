@@ -0,0 +1,264 @@
+//! Where Hummingbird keeps its data, and how the paths of music files are stored in the library.
+//!
+//! Normally, everything is kept in the user's data directory. In portable mode (when a
+//! `portable.txt` file is next to the executable, or a data directory is given with
+//! `--data-dir`), everything is kept in a folder the user chooses instead, so that Hummingbird
+//! can be carried around on a removable drive.
+//!
+//! Portable mode can also have a library root: a folder that the paths of music files inside it
+//! are stored relative to, so that the library keeps working when the drive is mounted somewhere
+//! else (like a different drive letter).
+
+use std::{
+    fs,
+    path::{Component, MAIN_SEPARATOR, Path, PathBuf},
+    sync::LazyLock,
+};
+
+use directories::ProjectDirs;
+use tracing::{info, warn};
+
+use crate::ui::arguments::data_dir_argument;
+
+/// The file that turns on portable mode, when it's next to the executable.
+const PORTABLE_MARKER: &str = "portable.txt";
+
+/// The folder data is kept in, next to the executable, in portable mode.
+const PORTABLE_DATA_DIR: &str = "data";
+
+static DATA_PATHS: LazyLock<DataPaths> = LazyLock::new(DataPaths::resolve);
+
+/// The folders Hummingbird keeps its data in. Decided once, the first time it's needed, and
+/// fixed for as long as Hummingbird is running.
+#[derive(Debug)]
+pub struct DataPaths {
+    data_dir: PathBuf,
+    portable: bool,
+    /// In portable mode, the folder that music file paths are stored relative to.
+    library_root: Option<PathBuf>,
+}
+
+impl DataPaths {
+    /// Returns the folders to use, deciding on them if that hasn't been done yet.
+    pub fn get() -> &'static DataPaths {
+        &DATA_PATHS
+    }
+
+    fn resolve() -> DataPaths {
+        let exe_dir = std::env::current_exe()
+            .ok()
+            .and_then(|exe| exe.parent().map(Path::to_path_buf));
+        let marker = exe_dir
+            .as_ref()
+            .map(|dir| dir.join(PORTABLE_MARKER))
+            .filter(|marker| marker.is_file());
+
+        // the first line of the marker, if there is one, is the library root
+        let library_root = marker
+            .as_ref()
+            .zip(exe_dir.as_ref())
+            .and_then(|(marker, dir)| {
+                let contents = fs::read_to_string(marker)
+                    .inspect_err(|err| warn!(?err, "Couldn't read {}: {err}", marker.display()))
+                    .ok()?;
+                let root = contents
+                    .lines()
+                    .map(str::trim)
+                    .find(|line| !line.is_empty())?;
+
+                Some(normalize(&dir.join(root)))
+            });
+
+        let paths = if let Some(data_dir) = data_dir_argument() {
+            DataPaths {
+                data_dir: std::path::absolute(&data_dir).unwrap_or(data_dir),
+                portable: true,
+                library_root,
+            }
+        } else if let Some(dir) = exe_dir.filter(|_| marker.is_some()) {
+            DataPaths {
+                data_dir: dir.join(PORTABLE_DATA_DIR),
+                portable: true,
+                library_root,
+            }
+        } else {
+            DataPaths {
+                data_dir: project_dirs().data_dir().to_path_buf(),
+                portable: false,
+                library_root: None,
+            }
+        };
+
+        if paths.portable {
+            info!(data_dir = ?paths.data_dir, library_root = ?paths.library_root, "Portable mode");
+        }
+
+        paths
+    }
+
+    /// The folder the library, settings, theme and everything else are kept in.
+    pub fn data_dir(&self) -> &Path {
+        &self.data_dir
+    }
+
+    /// Returns how a music file's path is stored in the library. Paths inside the library root
+    /// are stored relative to it, and all other paths are stored as they are. Returns `None` if
+    /// the path isn't valid Unicode.
+    pub fn to_stored(&self, path: &Path) -> Option<String> {
+        self.library_root
+            .as_ref()
+            .and_then(|root| relativize(path, root))
+            .or_else(|| path.to_str().map(str::to_string))
+    }
+
+    /// Returns the path to a music file from the way it's stored in the library. The reverse of
+    /// [`DataPaths::to_stored`].
+    pub fn resolve_stored(&self, stored: &str) -> PathBuf {
+        match &self.library_root {
+            Some(root) => resolve_relative(stored, root),
+            None => PathBuf::from(stored),
+        }
+    }
+
+    /// The library root, followed by a separator, for finding the paths stored before the
+    /// library root was set. `None` if there's no library root.
+    pub fn library_root_prefix(&self) -> Option<String> {
+        let root = self.library_root.as_ref()?.to_str()?;
+
+        Some(format!("{root}{MAIN_SEPARATOR}"))
+    }
+
+    /// Resolves a folder from the settings, which is relative to the library root if it isn't
+    /// absolute.
+    pub fn resolve_folder(&self, path: &Path) -> PathBuf {
+        match &self.library_root {
+            Some(root) if path.is_relative() => normalize(&root.join(path)),
+            _ => path.to_path_buf(),
+        }
+    }
+}
+
+/// The user's data directories. Versions of Hummingbird from before it was renamed kept their
+/// data somewhere else, which is still used if it exists.
+fn project_dirs() -> ProjectDirs {
+    let secondary_dirs = directories::ProjectDirs::from("me", "william341", "muzak")
+        .expect("couldn't generate project dirs (secondary)");
+
+    if secondary_dirs.data_dir().exists() {
+        return secondary_dirs;
+    }
+
+    directories::ProjectDirs::from("org", "mailliw", "hummingbird")
+        .expect("couldn't generate project dirs")
+}
+
+/// Removes `.` and `..` from a path without touching the file system, so that a root given as
+/// `..` still matches the paths found when scanning.
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            other => normalized.push(other),
+        }
+    }
+
+    normalized
+}
+
+/// Returns the path relative to the root, with `/` between its parts whatever the platform, or
+/// `None` if the path isn't inside the root.
+fn relativize(path: &Path, root: &Path) -> Option<String> {
+    let relative = path.strip_prefix(root).ok()?;
+    let parts = relative
+        .components()
+        .map(|component| match component {
+            Component::Normal(part) => part.to_str(),
+            _ => None,
+        })
+        .collect::<Option<Vec<_>>>()?;
+
+    (!parts.is_empty()).then(|| parts.join("/"))
+}
+
+/// Joins a path stored by [`relativize`] onto the root. Absolute paths are returned as they are.
+fn resolve_relative(stored: &str, root: &Path) -> PathBuf {
+    if is_absolute(stored) {
+        return PathBuf::from(stored);
+    }
+
+    let mut resolved = root.to_path_buf();
+    resolved.extend(stored.split('/'));
+    resolved
+}
+
+/// Returns whether a stored path is absolute on any platform, so that a library moved between
+/// Windows and other platforms doesn't have its absolute paths mistaken for relative ones.
+fn is_absolute(stored: &str) -> bool {
+    let bytes = stored.as_bytes();
+    let windows_drive = bytes.len() >= 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':';
+
+    stored.starts_with(['/', '\\']) || windows_drive || Path::new(stored).is_absolute()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn portable(root: &str) -> DataPaths {
+        DataPaths {
+            data_dir: PathBuf::from("/drive/data"),
+            portable: true,
+            library_root: Some(PathBuf::from(root)),
+        }
+    }
+
+    #[test]
+    fn stores_paths_inside_the_root_relative_to_it() {
+        let paths = portable("/drive/music");
+        let path = Path::new("/drive/music/Artist/Album/01 Intro.flac");
+
+        let stored = paths.to_stored(path).unwrap();
+
+        assert_eq!(stored, "Artist/Album/01 Intro.flac");
+        assert_eq!(paths.resolve_stored(&stored), path);
+    }
+
+    #[test]
+    fn stores_paths_outside_the_root_as_they_are() {
+        let paths = portable("/drive/music");
+        let path = Path::new("/home/user/Music/01 Intro.flac");
+
+        let stored = paths.to_stored(path).unwrap();
+
+        assert_eq!(stored, "/home/user/Music/01 Intro.flac");
+        assert_eq!(paths.resolve_stored(&stored), path);
+    }
+
+    #[test]
+    fn keeps_absolute_stored_paths() {
+        let resolve = |stored| resolve_relative(stored, Path::new("/drive/music"));
+
+        assert_eq!(resolve("/other/a.flac"), Path::new("/other/a.flac"));
+        assert_eq!(resolve("D:\\a.flac"), Path::new("D:\\a.flac"));
+    }
+
+    #[test]
+    fn does_not_relativize_paths_that_leave_the_root() {
+        let root = Path::new("/drive/music");
+
+        assert_eq!(relativize(Path::new("/drive/music/../a.flac"), root), None);
+        assert_eq!(relativize(root, root), None);
+    }
+
+    #[test]
+    fn normalizes_the_root() {
+        let root = normalize(Path::new("/drive/bin/../music/./"));
+
+        assert_eq!(root, Path::new("/drive/music"));
+    }
+}
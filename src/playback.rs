@@ -1,4 +1,12 @@
+pub mod crossfade;
+pub mod crossfeed;
+pub mod equalizer;
 pub mod events;
+pub mod fade;
 pub mod interface;
 pub mod queue;
+pub mod session;
+pub mod silence;
+pub mod sleep;
 pub mod thread;
+pub mod watchdog;
@@ -0,0 +1,230 @@
+use std::{f32::consts::FRAC_PI_2, path::PathBuf};
+
+use tracing::warn;
+
+use crate::{
+    devices::{format::FormatInfo, mixer::ChannelMixer, resample::Resampler},
+    media::{
+        errors::PlaybackReadError, loudness::AnalyzedLoudness, playback::Samples,
+        traits::MediaStream,
+    },
+    settings::playback::DitherMode,
+};
+
+/// The longest crossfade that can be set, in seconds.
+pub const MAX_CROSSFADE_SECS: f64 = 12.0;
+
+/// The track that's faded in during a crossfade. It's opened and decoded ahead of time, while the
+/// end of the current track is still playing.
+pub struct NextTrack {
+    pub stream: Box<dyn MediaStream>,
+    /// Maps the track's channels onto the device's.
+    pub mixer: ChannelMixer,
+    /// Converts the track's samples to the device's format. Created when the first samples are
+    /// read, like the playback thread's own resampler.
+    pub resampler: Option<Resampler>,
+    pub path: PathBuf,
+    /// The volume adjustment of the track, in decibels (see `Track::gain_offset`).
+    pub track_gain: f64,
+    /// The loudness of the track measured while scanning, if it has been measured.
+    pub loudness: Option<AnalyzedLoudness>,
+    /// The ReplayGain adjustment of the track, as an amplitude multiplier.
+    pub replay_gain: f64,
+    /// How fast the track is played, as a multiple of its normal speed.
+    speed: f64,
+    /// Whether the end of the track has been reached while decoding it.
+    ended: bool,
+}
+
+impl NextTrack {
+    pub fn new(
+        stream: Box<dyn MediaStream>,
+        mixer: ChannelMixer,
+        path: PathBuf,
+        track_gain: f64,
+        loudness: Option<AnalyzedLoudness>,
+        replay_gain: f64,
+        speed: f64,
+    ) -> Self {
+        Self {
+            stream,
+            mixer,
+            resampler: None,
+            path,
+            track_gain,
+            loudness,
+            replay_gain,
+            speed,
+            ended: false,
+        }
+    }
+
+    /// Changes how fast the track is played, like the playback thread's own resampler.
+    pub fn set_speed(&mut self, speed: f64) {
+        self.speed = speed;
+
+        if let Some(resampler) = &mut self.resampler {
+            resampler.set_speed(speed);
+        }
+    }
+
+    /// Decodes the next frame of the track and converts it to the device's format. Returns None
+    /// once the track has ended, or if it can't be decoded any further.
+    fn read(&mut self, format: &FormatInfo) -> Option<Vec<Vec<f32>>> {
+        let frame = match self.stream.read_samples() {
+            Ok(frame) => frame,
+            Err(PlaybackReadError::Eof) => {
+                self.ended = true;
+                return None;
+            }
+            Err(err) => {
+                warn!(
+                    ?err,
+                    "Failed to decode the next track during crossfade: {err}"
+                );
+                self.ended = true;
+                return None;
+            }
+        };
+
+        if self.resampler.is_none() {
+            let duration = self.stream.frame_duration().ok()?;
+
+            self.resampler = Some(Resampler::new(
+                frame.rate,
+                format.sample_rate,
+                duration,
+                format.channels.count(),
+                self.mixer.clone(),
+                self.speed,
+                // only float samples can be crossfaded, and those are never dithered
+                DitherMode::Off,
+            ));
+        }
+
+        let converted = self.resampler.as_mut()?.convert_formats(frame, format);
+
+        match converted.samples {
+            Samples::Float32(channels) => Some(channels),
+            _ => None,
+        }
+    }
+}
+
+/// Mixes the end of the current track with the start of the next one. The current track is faded
+/// out while the next one is faded in, with equal-power curves so that the overall loudness
+/// stays about the same.
+///
+/// Once the current track ends, the next track takes its place in the playback thread, and the
+/// rest of its fade-in is applied to it directly.
+pub struct Crossfade {
+    /// The track being faded in, until it takes over from the current track.
+    pub next: Option<NextTrack>,
+    /// How many frames (samples per channel) the fade lasts, and how many have been played.
+    length: usize,
+    position: usize,
+    /// The volume of the next track relative to the current one, so that the difference between
+    /// their volume adjustments is heard during the fade.
+    gain: f32,
+    /// Samples of the next track that have been decoded, but not played yet.
+    pending: Vec<Vec<f32>>,
+}
+
+impl Crossfade {
+    pub fn new(next: NextTrack, secs: f64, sample_rate: u32, gain: f32) -> Self {
+        Self {
+            next: Some(next),
+            length: ((secs * f64::from(sample_rate)) as usize).max(1),
+            position: 0,
+            gain,
+            pending: Vec::new(),
+        }
+    }
+
+    /// Returns true once the next track has been faded in completely.
+    pub fn is_finished(&self) -> bool {
+        self.next.is_none() && self.position >= self.length
+    }
+
+    fn pending_frames(&self) -> usize {
+        self.pending.first().map_or(0, Vec::len)
+    }
+
+    /// Returns how loud the current and next tracks are at the given frame of the fade.
+    fn gains(&self, frame: usize) -> (f32, f32) {
+        let progress = (frame as f32 / self.length as f32).min(1.0) * FRAC_PI_2;
+
+        (progress.cos(), progress.sin())
+    }
+
+    /// Applies the crossfade to a frame of audio, in place. While the current track is playing,
+    /// as much of the next track is decoded as the frame needs, and mixed in. After that, the
+    /// samples that were decoded but not played yet are put in front of the frame, and the rest
+    /// of the fade-in is applied.
+    pub fn process(&mut self, format: &FormatInfo, channels: &mut [Vec<f32>]) {
+        let Some(next) = &mut self.next else {
+            self.fade_in(channels);
+            return;
+        };
+
+        let frames = channels.first().map_or(0, Vec::len);
+
+        // the next track is borrowed, so the pending samples are counted through the field
+        while self.pending.first().map_or(0, Vec::len) < frames && !next.ended {
+            let Some(decoded) = next.read(format) else {
+                break;
+            };
+
+            if self.pending.is_empty() {
+                self.pending = vec![Vec::new(); decoded.len()];
+            }
+
+            for (pending, decoded) in self.pending.iter_mut().zip(decoded) {
+                pending.extend(decoded);
+            }
+        }
+
+        let mixed = self.pending_frames().min(frames);
+
+        for frame in 0..frames {
+            let (out_gain, in_gain) = self.gains(self.position + frame);
+
+            for (channel, samples) in channels.iter_mut().enumerate() {
+                let incoming = if frame < mixed {
+                    self.pending[channel][frame]
+                } else {
+                    0.0
+                };
+
+                samples[frame] =
+                    (samples[frame] * out_gain + incoming * self.gain * in_gain).clamp(-1.0, 1.0);
+            }
+        }
+
+        for pending in &mut self.pending {
+            pending.drain(..mixed);
+        }
+        self.position += frames;
+    }
+
+    fn fade_in(&mut self, channels: &mut [Vec<f32>]) {
+        if !self.pending.is_empty() {
+            for (channel, pending) in channels.iter_mut().zip(self.pending.drain(..)) {
+                let samples = std::mem::replace(channel, pending);
+                channel.extend(samples);
+            }
+        }
+
+        let frames = channels.first().map_or(0, Vec::len);
+
+        for frame in 0..frames {
+            let (_, in_gain) = self.gains(self.position + frame);
+
+            for samples in channels.iter_mut() {
+                samples[frame] *= in_gain;
+            }
+        }
+
+        self.position += frames;
+    }
+}
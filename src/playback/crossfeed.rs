@@ -0,0 +1,214 @@
+use std::f32::consts::PI;
+
+use crate::settings::playback::CrossfeedLevel;
+
+/// How long the opposite channel takes to reach each ear, in seconds. This is roughly the
+/// difference in arrival time for a speaker 30 degrees off-center.
+const DELAY_SECS: f32 = 0.0003;
+
+/// How long it takes to fade crossfeed in or out, in seconds, so that changing the level during
+/// playback doesn't click.
+const RAMP_SECS: f32 = 0.05;
+
+/// Mixes a low-passed, delayed and attenuated copy of each channel of a stereo signal into the
+/// other, like the sound from a pair of speakers reaches both ears. This makes hard-panned
+/// recordings less tiring to listen to on headphones.
+///
+/// Only the difference between the channels is fed across, so anything that's the same in both
+/// channels (including mono recordings) passes through unchanged, and the overall loudness
+/// doesn't change.
+pub struct Crossfeed {
+    level: CrossfeedLevel,
+    sample_rate: u32,
+    /// The coefficient of the one-pole low-pass filter applied to the difference signal.
+    coefficient: f32,
+    /// The current output of the low-pass filter.
+    lowpassed: f32,
+    /// The low-passed difference signal over the last `DELAY_SECS`, as a ring buffer.
+    delay: Vec<f32>,
+    delay_pos: usize,
+    /// The fraction of the difference signal that's currently fed across. This moves towards
+    /// `target` by `step` every sample.
+    amount: f32,
+    target: f32,
+    step: f32,
+}
+
+impl Crossfeed {
+    pub fn new(level: CrossfeedLevel) -> Self {
+        let mut crossfeed = Crossfeed {
+            level,
+            sample_rate: 0,
+            coefficient: 0.0,
+            lowpassed: 0.0,
+            delay: vec![0.0],
+            delay_pos: 0,
+            amount: 0.0,
+            target: 0.0,
+            step: 0.0,
+        };
+
+        crossfeed.set_level(level);
+        // start at the requested level, rather than fading in at the start of the first track
+        crossfeed.amount = crossfeed.target;
+        crossfeed
+    }
+
+    /// Changes the crossfeed level. The change is faded in over a short time.
+    pub fn set_level(&mut self, level: CrossfeedLevel) {
+        self.level = level;
+        self.target = level
+            .parameters()
+            .map(|(_, feed)| amount(feed))
+            .unwrap_or(0.0);
+        self.update_filter();
+    }
+
+    /// Prepares the filter for audio at the given sample rate. This only allocates when the
+    /// sample rate changes, which only happens when the output stream is recreated.
+    pub fn set_sample_rate(&mut self, sample_rate: u32) {
+        if sample_rate == self.sample_rate || sample_rate == 0 {
+            return;
+        }
+
+        let delay_len = ((sample_rate as f32 * DELAY_SECS).round() as usize).max(1);
+
+        self.sample_rate = sample_rate;
+        self.delay = vec![0.0; delay_len];
+        self.delay_pos = 0;
+        self.lowpassed = 0.0;
+        self.step = 1.0 / (sample_rate as f32 * RAMP_SECS);
+        self.update_filter();
+    }
+
+    fn update_filter(&mut self) {
+        if let Some((cutoff, _)) = self.level.parameters()
+            && self.sample_rate > 0
+        {
+            self.coefficient = 1.0 - (-2.0 * PI * cutoff / self.sample_rate as f32).exp();
+        }
+    }
+
+    /// Returns true if crossfeed is off and has finished fading out.
+    fn is_bypassed(&self) -> bool {
+        self.target == 0.0 && self.amount == 0.0
+    }
+
+    /// Applies crossfeed to a frame of audio, in place. Frames that aren't stereo are left alone.
+    pub fn process(&mut self, channels: &mut [Vec<f32>]) {
+        if self.is_bypassed() || self.sample_rate == 0 {
+            return;
+        }
+
+        let [left, right] = channels else {
+            return;
+        };
+
+        for (left, right) in left.iter_mut().zip(right.iter_mut()) {
+            let difference = *left - *right;
+            self.lowpassed += self.coefficient * (difference - self.lowpassed);
+
+            let delayed = std::mem::replace(&mut self.delay[self.delay_pos], self.lowpassed);
+            self.delay_pos = (self.delay_pos + 1) % self.delay.len();
+
+            if self.amount < self.target {
+                self.amount = (self.amount + self.step).min(self.target);
+            } else if self.amount > self.target {
+                self.amount = (self.amount - self.step).max(self.target);
+            }
+
+            // (1 - amount) of each channel, plus amount of the other
+            let bleed = self.amount * delayed;
+            *left = (*left - bleed).clamp(-1.0, 1.0);
+            *right = (*right + bleed).clamp(-1.0, 1.0);
+        }
+
+        // once it's faded out, forget the old signal so that it isn't heard when fading back in
+        if self.is_bypassed() {
+            self.lowpassed = 0.0;
+            self.delay.fill(0.0);
+        }
+    }
+}
+
+/// Returns the fraction of the difference signal to feed across so that, at low frequencies, the
+/// opposite channel is `feed` dB quieter than the direct one.
+fn amount(feed: f32) -> f32 {
+    let ratio = 10_f32.powf(-feed / 20.0);
+    ratio / (1.0 + ratio)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn crossfeed(level: CrossfeedLevel) -> Crossfeed {
+        let mut crossfeed = Crossfeed::new(level);
+        crossfeed.set_sample_rate(44100);
+        crossfeed
+    }
+
+    /// A second of a hard-panned constant signal, which the low-pass filter settles on.
+    fn hard_left() -> Vec<Vec<f32>> {
+        vec![vec![0.5; 44100], vec![0.0; 44100]]
+    }
+
+    #[test]
+    fn leaves_audio_alone_when_off() {
+        let mut channels = hard_left();
+        crossfeed(CrossfeedLevel::Off).process(&mut channels);
+
+        assert_eq!(channels, hard_left());
+    }
+
+    #[test]
+    fn leaves_mono_audio_alone() {
+        let mut channels = vec![vec![0.25, -0.5, 0.75], vec![0.25, -0.5, 0.75]];
+        crossfeed(CrossfeedLevel::High).process(&mut channels);
+
+        assert_eq!(channels, [[0.25, -0.5, 0.75], [0.25, -0.5, 0.75]]);
+    }
+
+    #[test]
+    fn leaves_other_channel_counts_alone() {
+        let mut channels = vec![vec![0.5; 16], vec![0.0; 16], vec![0.0; 16]];
+        crossfeed(CrossfeedLevel::High).process(&mut channels);
+
+        assert_eq!(channels, [vec![0.5; 16], vec![0.0; 16], vec![0.0; 16]]);
+    }
+
+    #[test]
+    fn feeds_each_channel_into_the_other_at_the_preset_level() {
+        for level in [
+            CrossfeedLevel::Low,
+            CrossfeedLevel::Medium,
+            CrossfeedLevel::High,
+        ] {
+            let mut channels = hard_left();
+            crossfeed(level).process(&mut channels);
+
+            let (_, feed) = level.parameters().unwrap();
+            let (left, right) = (channels[0][44099], channels[1][44099]);
+
+            assert!((right / left - 10_f32.powf(-feed / 20.0)).abs() < 1e-3);
+            // the sum of the channels is unchanged
+            assert!((left + right - 0.5).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn fades_between_levels() {
+        let mut crossfeed = crossfeed(CrossfeedLevel::Medium);
+        let mut channels = hard_left();
+        crossfeed.process(&mut channels);
+
+        crossfeed.set_level(CrossfeedLevel::Off);
+        let mut channels = hard_left();
+        crossfeed.process(&mut channels);
+
+        // still crossfeeding at the start, and back to the original signal once the fade is over
+        assert!(channels[1][0] > 0.1);
+        assert_eq!(channels[1][(44100.0 * RAMP_SECS) as usize + 1], 0.0);
+        assert!(crossfeed.is_bypassed());
+    }
+}
@@ -0,0 +1,153 @@
+use std::f32::consts::PI;
+
+use crate::settings::playback::{EQ_BANDS, EqSettings};
+
+/// How narrow each band is. A Q of about 1.41 makes each band an octave wide, so that neighbouring
+/// bands meet without much of a dip or bump between them.
+const BAND_Q: f32 = 1.41;
+
+/// The coefficients of a biquad filter, normalized so that a0 is 1.
+#[derive(Clone, Copy)]
+struct Coefficients {
+    b0: f32,
+    b1: f32,
+    b2: f32,
+    a1: f32,
+    a2: f32,
+}
+
+impl Coefficients {
+    /// A peaking filter that boosts or cuts the frequencies around `frequency` by `gain` dB,
+    /// from the Audio EQ Cookbook.
+    fn peaking(frequency: f32, gain: f32, sample_rate: u32) -> Self {
+        let a = 10_f32.powf(gain / 40.0);
+        let w0 = 2.0 * PI * frequency / sample_rate as f32;
+        let alpha = w0.sin() / (2.0 * BAND_Q);
+        let cos_w0 = w0.cos();
+        let a0 = 1.0 + alpha / a;
+
+        Coefficients {
+            b0: (1.0 + alpha * a) / a0,
+            b1: (-2.0 * cos_w0) / a0,
+            b2: (1.0 - alpha * a) / a0,
+            a1: (-2.0 * cos_w0) / a0,
+            a2: (1.0 - alpha / a) / a0,
+        }
+    }
+}
+
+/// The last two values a biquad filter remembers, for one channel.
+#[derive(Clone, Copy, Default)]
+struct FilterState {
+    z1: f32,
+    z2: f32,
+}
+
+impl FilterState {
+    /// Filters one sample, in transposed direct form II.
+    fn process(&mut self, filter: &Coefficients, input: f32) -> f32 {
+        let output = filter.b0 * input + self.z1;
+        self.z1 = filter.b1 * input - filter.a1 * output + self.z2;
+        self.z2 = filter.b2 * input - filter.a2 * output;
+        output
+    }
+}
+
+/// A 10-band graphic equalizer, made of a peaking filter for each band.
+///
+/// Bands that are at 0 dB are skipped, as are bands at or above half the sample rate, where they
+/// can't be represented. When the equalizer is off (or flat) audio passes through untouched.
+pub struct Equalizer {
+    settings: EqSettings,
+    sample_rate: u32,
+    /// The filter for each band, or None for bands that wouldn't change anything.
+    filters: [Option<Coefficients>; 10],
+    /// The linear gain of the pre-amp.
+    preamp: f32,
+    /// The state of each band's filter, for each channel.
+    state: Vec<[FilterState; 10]>,
+}
+
+impl Equalizer {
+    pub fn new(settings: EqSettings) -> Self {
+        let mut equalizer = Equalizer {
+            settings: EqSettings::default(),
+            sample_rate: 0,
+            filters: [None; 10],
+            preamp: 1.0,
+            state: Vec::new(),
+        };
+
+        equalizer.set_settings(settings);
+        equalizer
+    }
+
+    /// Changes the gains, the pre-amp or whether the equalizer is on. The new settings take
+    /// effect from the next frame, without resetting the bands that were already in use.
+    pub fn set_settings(&mut self, settings: EqSettings) {
+        // don't carry over anything left from before the equalizer was turned off
+        if self.settings.is_flat() {
+            self.state.clear();
+        }
+
+        self.settings = settings.clamped();
+        self.update_filters();
+    }
+
+    /// Recomputes the filters for audio at the given sample rate. This only happens when the
+    /// output stream is recreated.
+    pub fn set_sample_rate(&mut self, sample_rate: u32) {
+        if sample_rate == self.sample_rate || sample_rate == 0 {
+            return;
+        }
+
+        self.sample_rate = sample_rate;
+        self.state.clear();
+        self.update_filters();
+    }
+
+    fn update_filters(&mut self) {
+        let nyquist = self.sample_rate as f32 / 2.0;
+
+        self.preamp = 10_f32.powf(self.settings.preamp / 20.0);
+
+        for (band, (frequency, gain)) in EQ_BANDS.into_iter().zip(self.settings.gains).enumerate() {
+            let filter = (gain != 0.0 && frequency < nyquist)
+                .then(|| Coefficients::peaking(frequency, gain, self.sample_rate));
+
+            // a band that's coming back into use starts from silence, not from where it stopped
+            if self.filters[band].is_none() {
+                for state in &mut self.state {
+                    state[band] = FilterState::default();
+                }
+            }
+
+            self.filters[band] = filter;
+        }
+    }
+
+    /// Applies the equalizer to a frame of audio, in place.
+    pub fn process(&mut self, channels: &mut [Vec<f32>]) {
+        if self.settings.is_flat() || self.sample_rate == 0 {
+            return;
+        }
+
+        if self.state.len() != channels.len() {
+            self.state = vec![[FilterState::default(); 10]; channels.len()];
+        }
+
+        for (samples, state) in channels.iter_mut().zip(self.state.iter_mut()) {
+            for sample in samples.iter_mut() {
+                let mut value = *sample * self.preamp;
+
+                for (filter, state) in self.filters.iter().zip(state.iter_mut()) {
+                    if let Some(filter) = filter {
+                        value = state.process(filter, value);
+                    }
+                }
+
+                *sample = value.clamp(-1.0, 1.0);
+            }
+        }
+    }
+}
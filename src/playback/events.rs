@@ -1,9 +1,71 @@
 #![allow(dead_code)]
 
-use crate::media::metadata::Metadata;
+use crate::{
+    devices::traits::DeviceInfo,
+    media::metadata::Metadata,
+    settings::playback::{
+        CrossfeedLevel, DeviceProviderKind, DeviceRemovalPolicy, DitherMode, EqSettings,
+        ReplayGainMode,
+    },
+};
 
 use super::{queue::QueueItemData, thread::PlaybackState};
-use std::path::PathBuf;
+use std::{path::PathBuf, time::Duration};
+
+/// Why the playback thread recreated the output stream on its own (see
+/// [super::watchdog::OutputWatchdog]).
+#[derive(Debug, Clone, PartialEq, Copy)]
+pub enum OutputRecovery {
+    /// The device stopped taking samples for this long, while they were still being submitted.
+    Stalled(Duration),
+    /// The playback loop didn't run for this long, most likely because the system was suspended.
+    Resumed(Duration),
+}
+
+/// What a [PlaybackEvent::Error] is about, which decides how it's shown to the user.
+#[derive(Debug, Clone, PartialEq, Eq, Copy)]
+pub enum PlaybackErrorKind {
+    /// A track couldn't be opened or kept playing. Playback has moved on to the next track, or
+    /// stopped if there isn't one.
+    Track,
+    /// The audio device couldn't be opened or played on.
+    Device,
+}
+
+/// The audio backend the playback thread is using, along with the one that was asked for. They
+/// differ when the requested backend isn't available on this platform.
+#[derive(Debug, Clone, PartialEq, Eq, Copy)]
+pub struct ActiveDeviceProvider {
+    pub requested: DeviceProviderKind,
+    pub running: DeviceProviderKind,
+    /// Whether the backend was requested with the `DEVICE_PROVIDER` environment variable, rather
+    /// than the settings.
+    pub from_env: bool,
+}
+
+impl ActiveDeviceProvider {
+    /// Describes the backend for the about dialog and bug reports, like "cpal" or "requested
+    /// win_audiograph, running cpal".
+    pub fn describe(&self) -> String {
+        let backend = if self.requested.resolve() != self.running {
+            format!(
+                "requested {}, running {}",
+                self.requested.name(),
+                self.running.name()
+            )
+        } else if self.requested == DeviceProviderKind::Auto {
+            format!("{} (auto)", self.running.name())
+        } else {
+            self.running.name().to_string()
+        };
+
+        if self.from_env {
+            format!("{backend}, set by DEVICE_PROVIDER")
+        } else {
+            backend
+        }
+    }
+}
 
 #[derive(Debug, Clone, PartialEq, Copy)]
 pub enum RepeatState {
@@ -12,6 +74,18 @@ pub enum RepeatState {
     RepeatingOne,
 }
 
+/// When the sleep timer pauses playback. Playback is faded out over a few seconds first.
+#[derive(Debug, Clone, PartialEq, Copy, Default)]
+pub enum SleepTimer {
+    #[default]
+    Off,
+    /// Pauses playback once the given amount of time has passed. When reported by the playback
+    /// thread, this is the time that's left.
+    AfterDuration(Duration),
+    /// Pauses playback once the current track has finished, before the next one starts.
+    AfterCurrentTrack,
+}
+
 /// A command to the playback thread. This is used to control the playback thread from other
 /// threads. The playback thread recieves these commands from an MPSC channel, and processes them
 /// in the order they are recieved. They are processed every 10ms when playback is stopped, or
@@ -58,8 +132,80 @@ pub enum PlaybackCommand {
     JumpUnshuffled(usize),
     /// Requests that the playback thread seek to the specified position in the current file.
     Seek(f64),
-    /// Requests that the playback thread set the volume to the specified level.
+    /// Requests that the playback thread seek forward by the specified number of seconds. Seeking
+    /// past the end of the file moves on as if it had finished playing.
+    SeekForward(f64),
+    /// Requests that the playback thread seek backward by the specified number of seconds, or to
+    /// the start of the file if it's closer than that.
+    SeekBackward(f64),
+    /// Requests that the playback thread set the volume to the specified level. Unmutes playback
+    /// if it's muted.
     SetVolume(f64),
+    /// Requests that the playback thread raise the volume by the given step, up to 1.0. Steps are
+    /// added to the volume the playback thread last set, so several in a row add up.
+    VolumeUp(f64),
+    /// Requests that the playback thread lower the volume by the given step, down to 0.0.
+    VolumeDown(f64),
+    /// Requests that the playback thread mute playback, or unmute it if it's muted. The volume is
+    /// remembered while muted, and restored when unmuted.
+    ToggleMute,
+    /// Informs the playback thread that the volume adjustment (in decibels) of the track at the
+    /// given path has changed. Takes effect immediately if the track is currently playing.
+    SetTrackGain(PathBuf, f64),
+    /// Requests that the playback thread change the crossfeed level. The change is faded in, so it
+    /// can be made during playback.
+    SetCrossfeed(CrossfeedLevel),
+    /// Requests that the playback thread change the equalizer's settings. Takes effect
+    /// immediately, without reopening the device.
+    SetEqualizer(EqSettings),
+    /// Requests that the playback thread play the same mono mix on every output channel, or stop
+    /// doing so. Takes effect immediately.
+    SetMonoDownmix(bool),
+    /// Requests that the playback thread change the dither added when bits are lost converting
+    /// samples to the device's sample type. Takes effect immediately.
+    SetDither(DitherMode),
+    /// Requests that the playback thread change the balance between the left and right channels,
+    /// from -1.0 (left only) to 1.0 (right only). Takes effect immediately.
+    SetBalance(f64),
+    /// Requests that the playback thread change how long tracks are crossfaded for, in seconds. 0
+    /// turns crossfading off. A crossfade that has already started is finished as it was.
+    SetCrossfade(f64),
+    /// Requests that the playback thread pause when a track ends on its own and the next one is
+    /// from a different album, or stop doing so.
+    SetPauseAtAlbumBoundaries(bool),
+    /// Requests that the playback thread change which ReplayGain adjustment is applied, and the
+    /// pre-amp (in decibels) for tracks without ReplayGain tags. Takes effect immediately.
+    SetReplayGain(ReplayGainMode, f64),
+    /// Requests that the playback thread change how fast tracks are played, as a multiple of their
+    /// normal speed, from 0.5 to 2.0. Takes effect immediately, without restarting the track. The
+    /// pitch changes along with the speed.
+    SetSpeed(f64),
+    /// Requests that the playback thread repeat the section of the current track between the
+    /// given start and end times, in seconds, seeking back to the start whenever the end is
+    /// reached. `None` stops repeating it. The section is cleared when the track changes.
+    SetLoopRegion(Option<(f64, f64)>),
+    /// Requests that the playback thread start (or cancel) the sleep timer, replacing any timer
+    /// that's already running.
+    SetSleepTimer(SleepTimer),
+    /// Requests that the playback thread switch to another audio backend. Playback pauses while
+    /// the output is reopened on the new backend's default device, and then carries on. Ignored
+    /// if the backend was chosen with the `DEVICE_PROVIDER` environment variable.
+    SetDeviceProvider(DeviceProviderKind),
+    /// Requests that the playback thread play through the device with the given UID, or the
+    /// system's default device if it's `None`. Playback pauses while the output is reopened, and
+    /// then carries on. If the device can't be found, the default device is used instead.
+    SetDevice(Option<String>),
+    /// Requests that the playback thread turn exclusive mode on or off (see
+    /// [crate::settings::playback::PlaybackSettings::exclusive]). Playback pauses while the
+    /// output is reopened, and then carries on.
+    SetExclusive(bool),
+    /// Requests that the playback thread list the devices that can be played through, which it
+    /// replies to with [PlaybackEvent::DevicesListed].
+    ListDevices,
+    /// Requests that the playback thread stop once the current track has finished, instead of
+    /// moving on to the next one, or no longer do so. This only happens once: the request is
+    /// forgotten when the track ends, or when the user skips to the next track.
+    ToggleStopAfterCurrent,
     /// Requests that the playback thread replace the current queue with the specified queue.
     /// This will set the current playing track to the first item in the queue.
     ReplaceQueue(Vec<QueueItemData>),
@@ -72,6 +218,9 @@ pub enum PlaybackCommand {
     SetRepeat(RepeatState),
     /// Requests that the item at the index provided be removed from the queue.
     RemoveItem(usize),
+    /// Requests that the items at the indices provided be removed from the queue, all at once.
+    /// Indices that are out of range are ignored.
+    RemoveItems(Vec<usize>),
     /// Requests that an item be moved from one position to another in the queue.
     /// The first usize is the source index, the second is the destination index.
     MoveItem { from: usize, to: usize },
@@ -86,26 +235,105 @@ pub enum PlaybackEvent {
     StateChanged(PlaybackState),
     /// Indicates that the current file has changed providing the path to the new file.
     SongChanged(PathBuf),
-    /// Indicates that the duration of the current file has changed. The f64 is the new duration,
-    /// in seconds.
+    /// Indicates that the duration of the current file has changed. The u64 is the new duration,
+    /// in milliseconds, or 0 if it's unknown.
     DurationChanged(u64),
-    /// Indicates that the queue has been updated.
+    /// Indicates that the queue has been updated. Always followed by
+    /// [PlaybackEvent::QueuePositionChanged], since the current item may have moved.
     QueueUpdated,
-    /// Indicates that the position in the queue has changed. The usize is the new position.
-    QueuePositionChanged(usize),
+    /// Indicates that the queue was replaced or cleared, and contains the items that were in it.
+    /// Sent after [PlaybackEvent::QueueUpdated], so that the UI data of the items can be dropped
+    /// once nothing shows them any more.
+    QueueItemsRemoved(Vec<QueueItemData>),
+    /// Indicates that the position in the queue may have changed. Contains the index of the
+    /// current item, or None if nothing in the queue has been played since it was replaced or
+    /// cleared. This is the only place the UI gets the position from.
+    QueuePositionChanged(Option<usize>),
     /// Indicates that the MediaProvider has provided new metadata to be consumed by the user
     /// interface. The Metadata is boxed to avoid enum size bloat.
     MetadataUpdate(Box<Metadata>),
     /// Indicates that the MediaProvider has provided a new album art image to be consumed by the
     /// user interface.
     AlbumArtUpdate(Option<Box<[u8]>>),
-    /// Indicates that the position in the current file has changed. The f64 is the new position,
-    /// in seconds.
+    /// Indicates that the position in the current file has changed. The u64 is the new position,
+    /// in milliseconds. This is sent whenever the position reaches a new second.
     PositionChanged(u64),
-    /// Notification for when shuffling is disabled or enabled by the thread.
-    ShuffleToggled(bool, usize),
+    /// Indicates that a seek requested with `PlaybackCommand::Seek` has finished. Contains the
+    /// new position in milliseconds (which may differ from the requested one if it was out of
+    /// range), or `None` if the seek failed.
+    SeekCompleted(Option<u64>),
+    /// Notification for when shuffling is disabled or enabled by the thread, along with the index
+    /// of the current item in the reordered queue (as in [PlaybackEvent::QueuePositionChanged]).
+    ShuffleToggled(bool, Option<usize>),
     /// Indicates that repeat state has been changed.
     RepeatChanged(RepeatState),
     /// Indicates that the volume has changed. The f64 is the new volume, from 0.0 to 1.0.
     VolumeChanged(f64),
+    /// Indicates whether playback has been muted (see [PlaybackCommand::ToggleMute]). The volume
+    /// stays as it was while muted.
+    MuteChanged(bool),
+    /// Indicates that the playback speed has changed. The f64 is the new speed, as a multiple of
+    /// the normal speed.
+    SpeedChanged(f64),
+    /// Indicates that the balance between the left and right channels has changed (see
+    /// [PlaybackCommand::SetBalance]).
+    BalanceChanged(f64),
+    /// Indicates that the section of the current track being repeated has changed, or been
+    /// cleared. Contains its start and end times, in seconds.
+    LoopRegionChanged(Option<(f64, f64)>),
+    /// Indicates that the sleep timer has been set, cancelled or gone off, or that the time left
+    /// on it has reached another second.
+    SleepTimerChanged(SleepTimer),
+    /// Indicates whether playback will stop once the current track has finished (see
+    /// [PlaybackCommand::ToggleStopAfterCurrent]).
+    StopAfterCurrentChanged(bool),
+    /// Indicates that something went wrong that the user should be told about, such as the audio
+    /// device not being able to be opened. Contains what the error is about, and a message that
+    /// can be shown to the user. The playback thread carries on after sending this.
+    Error(PlaybackErrorKind, String),
+    /// Indicates that some of the items that were being added to the queue were skipped because
+    /// they were already in it (see [crate::settings::playback::PlaybackSettings]'s
+    /// `avoid_duplicate_queue_entries`). Contains how many were added and how many were skipped.
+    DuplicatesSkipped { added: usize, skipped: usize },
+    /// Indicates that the device playback was going to was disconnected, and that playback moved
+    /// to the new default device. Contains both devices' names, and the policy the thread applied
+    /// (if it was [DeviceRemovalPolicy::Ask], playback is paused until the user decides).
+    DeviceRemoved {
+        removed: String,
+        replacement: String,
+        policy: DeviceRemovalPolicy,
+    },
+    /// Indicates which audio backend the playback thread is using. Sent when the thread starts,
+    /// and whenever the backend is switched.
+    DeviceProviderChanged(ActiveDeviceProvider),
+    /// Contains the devices that can be played through, in answer to
+    /// [PlaybackCommand::ListDevices].
+    DevicesListed(Vec<DeviceInfo>),
+    /// Indicates that the output stream was recreated because it stopped playing without
+    /// reporting an error, or because the system was resumed from suspend. This is only used for
+    /// diagnostics.
+    OutputRecovered(OutputRecovery),
+    /// Indicates that there's no device to play on, for example because the only one was
+    /// unplugged. Playback is paused until a device is available again.
+    DeviceLost,
+    /// Indicates that a device is available again after [PlaybackEvent::DeviceLost]. Contains the
+    /// device's name. Playback resumes if it was paused because the device was lost, unless it
+    /// was stopped in the meantime.
+    DeviceRestored(String),
+    /// Indicates that the file of the track being played went away while it was playing, for
+    /// example because it was deleted or its drive was unplugged. Contains the file's path.
+    /// Playback has moved on to the next track.
+    TrackUnavailable(PathBuf),
+    /// Indicates whether the current track is played bit-perfect, at its own sample rate and bit
+    /// depth without being resampled. Sent whenever that changes.
+    BitPerfect(bool),
+    /// Indicates that the device has run out of samples to play since the last time this was
+    /// sent, which is heard as crackles or dropouts. Contains the number of underruns since
+    /// playback started. Sent at most once a second, and only while playing.
+    Underrun { count: u64 },
+    /// Indicates that playback paused at the end of an album because the next track in the queue
+    /// is from a different one (see [crate::settings::playback::PlaybackSettings]'s
+    /// `pause_at_album_boundaries`). Contains the database IDs of the album that finished and of
+    /// the album of the track that's waiting to be played.
+    PausedAtAlbumBoundary { finished: i64, next: Option<i64> },
 }
@@ -0,0 +1,61 @@
+/// What's done once playback has faded out.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FadeOutAction {
+    Pause,
+    Stop,
+}
+
+/// A short, linear change in volume, applied to the frames being played so that pausing,
+/// resuming and stopping don't click. It's applied to the samples themselves, rather than to the
+/// stream's volume, so the volume the user set is left alone.
+pub struct VolumeRamp {
+    from: f32,
+    to: f32,
+    /// How many frames (samples per channel) the ramp lasts, and how many have been played.
+    length: usize,
+    position: usize,
+}
+
+impl VolumeRamp {
+    pub fn new(from: f32, to: f32, millis: u32, sample_rate: u32) -> Self {
+        let length = u64::from(millis) * u64::from(sample_rate) / 1000;
+
+        Self {
+            from,
+            to,
+            length: (length as usize).max(1),
+            position: 0,
+        }
+    }
+
+    fn gain_at(&self, frame: usize) -> f32 {
+        let progress = (frame as f32 / self.length as f32).min(1.0);
+
+        self.from + (self.to - self.from) * progress
+    }
+
+    /// Returns how loud the ramp has got so far, so that another ramp can take over from it.
+    pub fn gain(&self) -> f32 {
+        self.gain_at(self.position)
+    }
+
+    /// Returns true once the ramp has reached its target volume.
+    pub fn is_finished(&self) -> bool {
+        self.position >= self.length
+    }
+
+    /// Applies the ramp to a frame of audio, in place.
+    pub fn process(&mut self, channels: &mut [Vec<f32>]) {
+        let frames = channels.first().map_or(0, Vec::len);
+
+        for frame in 0..frames {
+            let gain = self.gain_at(self.position + frame);
+
+            for samples in channels.iter_mut() {
+                samples[frame] *= gain;
+            }
+        }
+
+        self.position += frames;
+    }
+}
@@ -4,14 +4,24 @@ use std::{path::PathBuf, sync::Arc};
 
 use gpui::App;
 use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender};
+use tracing::{debug, error, info};
 
 use crate::{
-    playback::events::RepeatState,
-    ui::models::{CurrentTrack, ImageEvent, MMBSEvent, Models, PlaybackInfo},
+    library::db::{AlbumMethod, LibraryAccess},
+    playback::events::{RepeatState, SleepTimer},
+    settings::playback::{
+        CrossfeedLevel, DeviceProviderKind, DeviceRemovalPolicy, DitherMode, EqSettings,
+        ReplayGainMode,
+    },
+    ui::{
+        models::{CurrentTrack, ImageEvent, MMBSEvent, Models, PlaybackInfo, SeekCompleted},
+        toasts::{ProgressToast, show_error_toast, show_toast, show_toast_with_action},
+    },
+    util::round_millis,
 };
 
 use super::{
-    events::{PlaybackCommand, PlaybackEvent},
+    events::{PlaybackCommand, PlaybackErrorKind, PlaybackEvent},
     queue::QueueItemData,
     thread::PlaybackState,
 };
@@ -103,12 +113,132 @@ impl PlaybackInterface {
         self.cmd_tx.send(PlaybackCommand::Seek(position)).unwrap();
     }
 
+    pub fn seek_forward(&self, secs: f64) {
+        self.cmd_tx
+            .send(PlaybackCommand::SeekForward(secs))
+            .unwrap();
+    }
+
+    pub fn seek_backward(&self, secs: f64) {
+        self.cmd_tx
+            .send(PlaybackCommand::SeekBackward(secs))
+            .unwrap();
+    }
+
+    pub fn set_track_gain(&self, path: PathBuf, gain_offset: f64) {
+        self.cmd_tx
+            .send(PlaybackCommand::SetTrackGain(path, gain_offset))
+            .unwrap();
+    }
+
     pub fn set_volume(&self, volume: f64) {
         self.cmd_tx
             .send(PlaybackCommand::SetVolume(volume))
             .unwrap();
     }
 
+    pub fn volume_up(&self, step: f64) {
+        self.cmd_tx.send(PlaybackCommand::VolumeUp(step)).unwrap();
+    }
+
+    pub fn volume_down(&self, step: f64) {
+        self.cmd_tx.send(PlaybackCommand::VolumeDown(step)).unwrap();
+    }
+
+    pub fn toggle_mute(&self) {
+        self.cmd_tx.send(PlaybackCommand::ToggleMute).unwrap();
+    }
+
+    pub fn set_crossfeed(&self, level: CrossfeedLevel) {
+        self.cmd_tx
+            .send(PlaybackCommand::SetCrossfeed(level))
+            .unwrap();
+    }
+
+    pub fn set_equalizer(&self, settings: EqSettings) {
+        self.cmd_tx
+            .send(PlaybackCommand::SetEqualizer(settings))
+            .unwrap();
+    }
+
+    pub fn set_mono_downmix(&self, mono: bool) {
+        self.cmd_tx
+            .send(PlaybackCommand::SetMonoDownmix(mono))
+            .unwrap();
+    }
+
+    pub fn set_dither(&self, dither: DitherMode) {
+        self.cmd_tx
+            .send(PlaybackCommand::SetDither(dither))
+            .unwrap();
+    }
+
+    pub fn set_pause_at_album_boundaries(&self, pause: bool) {
+        self.cmd_tx
+            .send(PlaybackCommand::SetPauseAtAlbumBoundaries(pause))
+            .unwrap();
+    }
+
+    pub fn set_balance(&self, balance: f64) {
+        self.cmd_tx
+            .send(PlaybackCommand::SetBalance(balance))
+            .unwrap();
+    }
+
+    pub fn set_crossfade(&self, secs: f64) {
+        self.cmd_tx
+            .send(PlaybackCommand::SetCrossfade(secs))
+            .unwrap();
+    }
+
+    pub fn set_speed(&self, speed: f64) {
+        self.cmd_tx.send(PlaybackCommand::SetSpeed(speed)).unwrap();
+    }
+
+    pub fn set_loop_region(&self, region: Option<(f64, f64)>) {
+        self.cmd_tx
+            .send(PlaybackCommand::SetLoopRegion(region))
+            .unwrap();
+    }
+
+    pub fn set_sleep_timer(&self, timer: SleepTimer) {
+        self.cmd_tx
+            .send(PlaybackCommand::SetSleepTimer(timer))
+            .unwrap();
+    }
+
+    pub fn toggle_stop_after_current(&self) {
+        self.cmd_tx
+            .send(PlaybackCommand::ToggleStopAfterCurrent)
+            .unwrap();
+    }
+
+    pub fn set_device_provider(&self, kind: DeviceProviderKind) {
+        self.cmd_tx
+            .send(PlaybackCommand::SetDeviceProvider(kind))
+            .unwrap();
+    }
+
+    pub fn set_device(&self, uid: Option<String>) {
+        self.cmd_tx.send(PlaybackCommand::SetDevice(uid)).unwrap();
+    }
+
+    pub fn set_exclusive(&self, exclusive: bool) {
+        self.cmd_tx
+            .send(PlaybackCommand::SetExclusive(exclusive))
+            .unwrap();
+    }
+
+    pub fn list_devices(&self) {
+        self.cmd_tx.send(PlaybackCommand::ListDevices).unwrap();
+    }
+
+    pub fn set_replaygain(&self, mode: ReplayGainMode, fallback: f64) {
+        self.cmd_tx
+            .send(PlaybackCommand::SetReplayGain(mode, fallback))
+            .unwrap();
+    }
+
     pub fn replace_queue(&self, items: Vec<QueueItemData>) {
         self.cmd_tx
             .send(PlaybackCommand::ReplaceQueue(items))
@@ -131,6 +261,12 @@ impl PlaybackInterface {
         self.cmd_tx.send(PlaybackCommand::RemoveItem(idx)).unwrap();
     }
 
+    pub fn remove_items(&self, indices: Vec<usize>) {
+        self.cmd_tx
+            .send(PlaybackCommand::RemoveItems(indices))
+            .unwrap();
+    }
+
     pub fn move_item(&self, from: usize, to: usize) {
         self.cmd_tx
             .send(PlaybackCommand::MoveItem { from, to })
@@ -162,169 +298,405 @@ impl PlaybackInterface {
         };
 
         app.spawn(async move |cx| {
-            loop {
-                while let Some(event) = events_rx.recv().await {
-                    match event {
-                        PlaybackEvent::MetadataUpdate(v) => {
-                            let metadata = Arc::new(*v.clone());
+            // stays on screen for as long as there's no device to play on
+            let mut device_lost_toast: Option<ProgressToast> = None;
 
-                            metadata_model
-                                .update(cx, |m, cx| {
-                                    *m = *v;
-                                    cx.notify()
-                                })
-                                .expect("failed to update metadata");
+            while let Some(event) = events_rx.recv().await {
+                match event {
+                    PlaybackEvent::MetadataUpdate(v) => {
+                        let metadata = Arc::new(*v.clone());
 
-                            mmbs_model
-                                .update(cx, |_, cx| {
-                                    cx.emit(MMBSEvent::MetadataRecieved(metadata));
-                                })
-                                .expect("failed to broadcast MMBS event MetadataRecieved");
-                        }
-                        PlaybackEvent::AlbumArtUpdate(v) => {
-                            albumart_model
-                                .update(cx, |m, cx| {
-                                    if let Some(v) = v {
-                                        cx.emit(ImageEvent(v))
-                                    } else {
-                                        *m = None;
-                                        cx.notify()
-                                    }
-                                })
-                                .expect("failed to update albumart");
-                        }
-                        PlaybackEvent::StateChanged(v) => {
-                            playback_info
-                                .playback_state
-                                .update(cx, |m, cx| {
-                                    *m = v;
-                                    cx.notify()
-                                })
-                                .expect("failed to update playback state");
-
-                            if v == PlaybackState::Stopped {
-                                playback_info
-                                    .current_track
-                                    .update(cx, |m, cx| {
-                                        *m = None;
-                                        cx.notify()
-                                    })
-                                    .expect("failed to update current track");
-                            }
+                        metadata_model
+                            .update(cx, |m, cx| {
+                                *m = *v;
+                                cx.notify()
+                            })
+                            .expect("failed to update metadata");
 
-                            mmbs_model
-                                .update(cx, |_, cx| {
-                                    cx.emit(MMBSEvent::StateChanged(v));
-                                })
-                                .expect("failed to broadcast MMBS event StateChanged");
-                        }
-                        PlaybackEvent::PositionChanged(v) => {
-                            playback_info
-                                .position
-                                .update(cx, |m, cx| {
-                                    *m = v;
-                                    cx.notify()
-                                })
-                                .expect("failed to update position");
-                            mmbs_model
-                                .update(cx, |_, cx| {
-                                    cx.emit(MMBSEvent::PositionChanged(v));
-                                })
-                                .expect("failed to broadcast MMBS event PositionChanged");
-                        }
-                        PlaybackEvent::DurationChanged(v) => {
-                            playback_info
-                                .duration
-                                .update(cx, |m, cx| {
-                                    *m = v;
+                        mmbs_model
+                            .update(cx, |_, cx| {
+                                cx.emit(MMBSEvent::MetadataRecieved(metadata));
+                            })
+                            .expect("failed to broadcast MMBS event MetadataRecieved");
+                    }
+                    PlaybackEvent::AlbumArtUpdate(v) => {
+                        albumart_model
+                            .update(cx, |m, cx| {
+                                if let Some(v) = v {
+                                    cx.emit(ImageEvent(v))
+                                } else {
+                                    *m = None;
                                     cx.notify()
-                                })
-                                .expect("failed to update duration");
-                            mmbs_model
-                                .update(cx, |_, cx| {
-                                    cx.emit(MMBSEvent::DurationChanged(v));
-                                })
-                                .expect("failed to broadcast MMBS event DurationChanged");
-                        }
-                        PlaybackEvent::SongChanged(path) => {
+                                }
+                            })
+                            .expect("failed to update albumart");
+                    }
+                    PlaybackEvent::StateChanged(v) => {
+                        playback_info
+                            .playback_state
+                            .update(cx, |m, cx| {
+                                *m = v;
+                                cx.notify()
+                            })
+                            .expect("failed to update playback state");
+
+                        if v == PlaybackState::Stopped {
                             playback_info
                                 .current_track
                                 .update(cx, |m, cx| {
-                                    *m = Some(CurrentTrack::new(path.clone()));
+                                    *m = None;
                                     cx.notify()
                                 })
                                 .expect("failed to update current track");
-                            mmbs_model
-                                .update(cx, |_, cx| {
-                                    cx.emit(MMBSEvent::NewTrack(path));
-                                })
-                                .expect("failed to broadcast MMBS event NewTrack");
                         }
-                        PlaybackEvent::QueueUpdated => {
-                            queue_model
-                                .update(cx, |_, cx| cx.notify())
-                                .expect("failed to update queue");
-                        }
-                        PlaybackEvent::ShuffleToggled(v, _) => {
-                            playback_info
-                                .shuffling
-                                .update(cx, |m, cx| {
+
+                        mmbs_model
+                            .update(cx, |_, cx| {
+                                cx.emit(MMBSEvent::StateChanged(v));
+                            })
+                            .expect("failed to broadcast MMBS event StateChanged");
+                    }
+                    PlaybackEvent::PositionChanged(v) => {
+                        playback_info
+                            .position
+                            .update(cx, |m, cx| {
+                                *m = v;
+                                cx.notify()
+                            })
+                            .expect("failed to update position");
+                        mmbs_model
+                            .update(cx, |_, cx| {
+                                cx.emit(MMBSEvent::PositionChanged(v / 1000));
+                            })
+                            .expect("failed to broadcast MMBS event PositionChanged");
+                    }
+                    PlaybackEvent::SeekCompleted(v) => {
+                        playback_info
+                            .position
+                            .update(cx, |m, cx| {
+                                if let Some(v) = v {
                                     *m = v;
-                                    cx.notify()
+                                    cx.notify();
+                                }
+
+                                cx.emit(SeekCompleted(v));
+                            })
+                            .expect("failed to update position");
+
+                        if let Some(v) = v {
+                            mmbs_model
+                                .update(cx, |_, cx| {
+                                    cx.emit(MMBSEvent::PositionChanged(v / 1000));
                                 })
-                                .expect("failed to update shuffle state");
+                                .expect("failed to broadcast MMBS event PositionChanged");
                         }
-                        PlaybackEvent::VolumeChanged(v) => {
+                    }
+                    PlaybackEvent::DurationChanged(v) => {
+                        playback_info
+                            .duration
+                            .update(cx, |m, cx| {
+                                *m = v;
+                                cx.notify()
+                            })
+                            .expect("failed to update duration");
+                        mmbs_model
+                            .update(cx, |_, cx| {
+                                cx.emit(MMBSEvent::DurationChanged(round_millis(v)));
+                            })
+                            .expect("failed to broadcast MMBS event DurationChanged");
+                    }
+                    PlaybackEvent::SongChanged(path) => {
+                        playback_info
+                            .unavailable
+                            .update(cx, |m, cx| {
+                                if m.remove(&path) {
+                                    cx.notify();
+                                }
+                            })
+                            .expect("failed to update unavailable tracks");
+                        playback_info
+                            .current_track
+                            .update(cx, |m, cx| {
+                                *m = Some(CurrentTrack::new(path.clone()));
+                                cx.notify()
+                            })
+                            .expect("failed to update current track");
+                        mmbs_model
+                            .update(cx, |_, cx| {
+                                cx.emit(MMBSEvent::NewTrack(path));
+                            })
+                            .expect("failed to broadcast MMBS event NewTrack");
+                    }
+                    PlaybackEvent::QueueUpdated => {
+                        queue_model
+                            .update(cx, |_, cx| cx.notify())
+                            .expect("failed to update queue");
+                    }
+                    PlaybackEvent::QueueItemsRemoved(items) => {
+                        cx.update(|cx| {
+                            let images = items.iter().filter(|item| item.drop_data(cx)).count();
+
+                            debug!(
+                                "Dropped the data of {} removed queue items, and {images} images",
+                                items.len()
+                            );
+                        })
+                        .ok();
+                    }
+                    PlaybackEvent::ShuffleToggled(v, position) => {
+                        playback_info
+                            .shuffling
+                            .update(cx, |m, cx| {
+                                *m = v;
+                                cx.notify()
+                            })
+                            .expect("failed to update shuffle state");
+                        queue_model
+                            .update(cx, |m, cx| {
+                                m.position = position;
+                                cx.notify();
+                            })
+                            .expect("failed to update queue position");
+                    }
+                    PlaybackEvent::VolumeChanged(v) => {
+                        playback_info
+                            .volume
+                            .update(cx, |m, cx| {
+                                *m = v;
+                                cx.notify()
+                            })
+                            .expect("failed to update volume model");
+
+                        // Note: `prev_volume` should not be to small.
+                        // Its value needs to be visible in UI
+                        // while toggling volume `on` / `off` and even
+                        // an user used a slider to move volume to `0`
+                        if v > 0.05 {
                             playback_info
-                                .volume
+                                .prev_volume
                                 .update(cx, |m, cx| {
                                     *m = v;
                                     cx.notify()
                                 })
                                 .expect("failed to update volume model");
-
-                            // Note: `prev_volume` should not be to small.
-                            // Its value needs to be visible in UI
-                            // while toggling volume `on` / `off` and even
-                            // an user used a slider to move volume to `0`
-                            if v > 0.05 {
-                                playback_info
-                                    .prev_volume
-                                    .update(cx, |m, cx| {
-                                        *m = v;
-                                        cx.notify()
-                                    })
-                                    .expect("failed to update volume model");
-                            }
                         }
-                        PlaybackEvent::QueuePositionChanged(v) => queue_model
+                    }
+                    PlaybackEvent::MuteChanged(v) => {
+                        playback_info
+                            .muted
                             .update(cx, |m, cx| {
-                                m.position = v;
-                                cx.notify();
+                                *m = v;
+                                cx.notify()
                             })
-                            .expect("failed to update queue position"),
-                        PlaybackEvent::RepeatChanged(v) => playback_info
-                            .repeating
+                            .expect("failed to update mute model");
+                    }
+                    PlaybackEvent::SpeedChanged(v) => {
+                        playback_info
+                            .speed
                             .update(cx, |m, cx| {
                                 *m = v;
+                                cx.notify()
+                            })
+                            .expect("failed to update speed model");
+                    }
+                    PlaybackEvent::BalanceChanged(v) => {
+                        playback_info
+                            .balance
+                            .update(cx, |m, cx| {
+                                *m = v;
+                                cx.notify()
+                            })
+                            .expect("failed to update balance model");
+                    }
+                    PlaybackEvent::LoopRegionChanged(v) => {
+                        playback_info
+                            .loop_region
+                            .update(cx, |m, cx| {
+                                *m = v;
+                                cx.notify()
+                            })
+                            .expect("failed to update loop region model");
+                    }
+                    PlaybackEvent::SleepTimerChanged(v) => {
+                        playback_info
+                            .sleep_timer
+                            .update(cx, |m, cx| {
+                                *m = v;
+                                cx.notify()
+                            })
+                            .expect("failed to update sleep timer model");
+                    }
+                    PlaybackEvent::StopAfterCurrentChanged(v) => {
+                        playback_info
+                            .stop_after_current
+                            .update(cx, |m, cx| {
+                                *m = v;
+                                cx.notify()
+                            })
+                            .expect("failed to update stop after current model");
+                    }
+                    PlaybackEvent::DeviceProviderChanged(v) => {
+                        playback_info
+                            .device_provider
+                            .update(cx, |m, cx| {
+                                *m = Some(v);
+                                cx.notify()
+                            })
+                            .expect("failed to update device provider model");
+                    }
+                    PlaybackEvent::DevicesListed(v) => {
+                        playback_info
+                            .devices
+                            .update(cx, |m, cx| {
+                                *m = v;
+                                cx.notify()
+                            })
+                            .expect("failed to update devices model");
+                    }
+                    PlaybackEvent::QueuePositionChanged(v) => queue_model
+                        .update(cx, |m, cx| {
+                            m.position = v;
+                            cx.notify();
+                        })
+                        .expect("failed to update queue position"),
+                    PlaybackEvent::RepeatChanged(v) => playback_info
+                        .repeating
+                        .update(cx, |m, cx| {
+                            *m = v;
+                            cx.notify();
+                        })
+                        .expect("failed to update repeat model"),
+                    PlaybackEvent::Error(kind, message) => {
+                        cx.update(|cx| match kind {
+                            // playback has already moved on, so there's nothing left to do
+                            PlaybackErrorKind::Track => show_toast(cx, message),
+                            // nothing plays until it's sorted out
+                            PlaybackErrorKind::Device => show_error_toast(cx, message),
+                        })
+                        .ok();
+                    }
+                    PlaybackEvent::DuplicatesSkipped { added, skipped } => {
+                        let message = match (added, skipped) {
+                            (0, 1) => "Already in the queue.".to_string(),
+                            (0, _) => format!("All {skipped} tracks are already in the queue."),
+                            _ => format!("{added} added, {skipped} already in queue"),
+                        };
+
+                        cx.update(|cx| show_toast(cx, message)).ok();
+                    }
+                    PlaybackEvent::DeviceRemoved {
+                        removed,
+                        replacement,
+                        policy,
+                    } => {
+                        cx.update(|cx| match policy {
+                            DeviceRemovalPolicy::Pause => show_toast(
+                                cx,
+                                format!("Paused because {removed} was disconnected."),
+                            ),
+                            DeviceRemovalPolicy::Continue => show_toast(
+                                cx,
+                                format!(
+                                    "{removed} was disconnected. Now playing on {replacement}."
+                                ),
+                            ),
+                            DeviceRemovalPolicy::Ask => show_toast_with_action(
+                                cx,
+                                format!("{removed} was disconnected. Playback is paused."),
+                                format!("Resume on {replacement}"),
+                                |cx| cx.global::<PlaybackInterface>().play(),
+                            ),
+                        })
+                        .ok();
+                    }
+                    PlaybackEvent::OutputRecovered(reason) => {
+                        info!(?reason, "Audio output was recovered");
+                    }
+                    PlaybackEvent::DeviceLost => {
+                        device_lost_toast = cx
+                            .update(|cx| {
+                                ProgressToast::show(
+                                    cx,
+                                    "No audio device is connected. Playback will continue when \
+                                    one is.",
+                                )
+                            })
+                            .ok();
+                    }
+                    PlaybackEvent::DeviceRestored(name) => {
+                        if let Some(toast) = device_lost_toast.take() {
+                            cx.update(|cx| toast.finish(cx, format!("Connected to {name}.")))
+                                .ok();
+                        }
+                    }
+                    PlaybackEvent::TrackUnavailable(path) => {
+                        let name = path
+                            .file_name()
+                            .map(|name| name.to_string_lossy().into_owned())
+                            .unwrap_or_else(|| path.display().to_string());
+
+                        playback_info
+                            .unavailable
+                            .update(cx, |m, cx| {
+                                m.insert(path);
                                 cx.notify();
                             })
-                            .expect("failed to update repeat model"),
+                            .expect("failed to update unavailable tracks");
+                        cx.update(|cx| {
+                            show_toast(cx, format!("'{name}' is no longer available. Skipping it."))
+                        })
+                        .ok();
+                    }
+                    PlaybackEvent::BitPerfect(bit_perfect) => {
+                        playback_info
+                            .bit_perfect
+                            .update(cx, |m, cx| {
+                                *m = bit_perfect;
+                                cx.notify();
+                            })
+                            .expect("failed to update bit-perfect state");
+                    }
+                    PlaybackEvent::Underrun { count } => {
+                        playback_info
+                            .underruns
+                            .update(cx, |m, cx| {
+                                *m = count;
+                                cx.notify();
+                            })
+                            .expect("failed to update underrun count");
+                    }
+                    PlaybackEvent::PausedAtAlbumBoundary { finished, next } => {
+                        cx.update(|cx| {
+                            let title = |cx: &mut App, id: i64| {
+                                cx.get_album_by_id(id, AlbumMethod::Metadata)
+                                    .map(|album| album.title.0.clone())
+                                    .ok()
+                            };
+                            let finished = title(cx, finished);
+                            let next = next.and_then(|id| title(cx, id));
+
+                            let message = match (finished, next) {
+                                (Some(finished), Some(next)) => {
+                                    format!("Finished: {finished} — press play for {next}")
+                                }
+                                (Some(finished), None) => {
+                                    format!("Finished: {finished} — press play to continue")
+                                }
+                                (None, _) => "Finished the album — press play to continue".into(),
+                            };
+
+                            show_toast(cx, message);
+                        })
+                        .ok();
                     }
                 }
             }
+
+            // the playback thread only goes away if it panics, and the app can't carry on without
+            // it, so it's closed normally (saving its state) rather than left unable to play
+            error!("The playback thread stopped unexpectedly, quitting");
+            cx.update(|cx| cx.quit()).ok();
         })
         .detach();
     }
 }
-
-// TODO: this should be in a trait for AppContext
-/// Replace the current queue with the given items.
-pub fn replace_queue(items: Vec<QueueItemData>, app: &mut App) {
-    let playback_interface = app.global::<PlaybackInterface>();
-    playback_interface.replace_queue(items);
-
-    // let data_interface = app.global::<GPUIDataInterface>();
-
-    // data_interface.evict_cache();
-}
@@ -1,9 +1,17 @@
 use std::{fmt::Display, sync::Arc};
 
-use gpui::{App, AppContext, Entity, RenderImage, SharedString};
+use gpui::{App, AppContext, Entity, EntityId, RenderImage, SharedString};
 use std::path::PathBuf;
 
-use crate::{library::db::LibraryAccess, ui::data::Decode};
+use crate::{
+    library::db::{
+        AlbumMethod, get_album_by_id, get_artist_name_by_id, get_track_by_id, spawn_query,
+    },
+    ui::{
+        data::{Decode, metadata_from_file_name},
+        util::drop_image_from_app,
+    },
+};
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct QueueItemData {
@@ -31,6 +39,8 @@ pub struct QueueItemUIData {
     pub name: Option<SharedString>,
     /// The name of the artist, if it is known.
     pub artist_name: Option<SharedString>,
+    /// The length of the track in milliseconds, if it is known.
+    pub duration: Option<u64>,
     /// Whether the track's metadata is known from the file or the database.
     pub source: DataSource,
 }
@@ -66,32 +76,53 @@ impl QueueItemData {
             if m.is_some() {
                 return;
             }
-            *m = Some(QueueItemUIData {
-                image: None,
-                name: None,
-                artist_name: None,
-                source: DataSource::Library,
+            *m = Some(if track_id.is_some() {
+                QueueItemUIData {
+                    image: None,
+                    name: None,
+                    artist_name: None,
+                    duration: None,
+                    source: DataSource::Library,
+                }
+            } else {
+                // shown until the file has been read, which can take a while with large queues
+                metadata_from_file_name(&path)
             });
 
-            // if the database ids are known we can get the data from the database
+            // if the database ids are known we can get the data from the database, without
+            // holding up the UI thread (large queues hydrate a lot of items at once)
             if let (Some(track_id), Some(album_id)) = (track_id, album_id) {
-                let album =
-                    cx.get_album_by_id(album_id, crate::library::db::AlbumMethod::Thumbnail);
-                let track = cx.get_track_by_id(track_id);
+                let lookup = spawn_query(cx, move |pool| async move {
+                    let track = get_track_by_id(&pool, track_id).await?;
+                    let album = get_album_by_id(&pool, album_id, AlbumMethod::Thumbnail).await?;
+                    let artist_name = get_artist_name_by_id(&pool, album.artist_id).await.ok();
+
+                    Ok((track, album, artist_name))
+                });
+
+                cx.spawn(async move |this, cx| {
+                    let result = lookup.await;
+
+                    this.update(cx, |m: &mut Option<QueueItemUIData>, cx| {
+                        if let (Ok((track, album, artist_name)), Some(data)) = (result, m.as_mut())
+                        {
+                            data.name = Some(track.title.clone().into());
+                            data.image = album.thumb.clone().map(|v| v.0);
+                            data.artist_name = artist_name.map(|v| (*v).clone().into());
+                            data.duration = Some(track.duration_millis());
+                        }
+
+                        // vital information left blank, try retriving the metadata from disk
+                        if m.as_ref().is_some_and(|data| data.artist_name.is_none()) {
+                            cx.read_metadata(path, cx.entity()).detach();
+                        }
+
+                        cx.notify();
+                    })
+                    .ok();
+                })
+                .detach();
 
-                if let (Ok(track), Ok(album)) = (track, album) {
-                    m.as_mut().unwrap().name = Some(track.title.clone().into());
-                    m.as_mut().unwrap().image = album.thumb.clone().map(|v| v.0);
-
-                    if let Ok(artist_name) = cx.get_artist_name_by_id(album.artist_id) {
-                        m.as_mut().unwrap().artist_name = Some((*artist_name).clone().into());
-                    }
-                }
-
-                cx.notify();
-            }
-
-            if m.as_ref().unwrap().artist_name.is_some() {
                 return;
             }
 
@@ -103,17 +134,101 @@ impl QueueItemData {
         model
     }
 
-    /// Drop the UI data from the queue item. This means the data must be retrieved again from disk
-    /// if the item is used with get_data again.
-    pub fn drop_data(&self, cx: &mut App) {
-        self.data.update(cx, |m, cx| {
-            *m = None;
+    /// Drop the UI data from the queue item, along with its album art if it came from the
+    /// library. This means the data must be retrieved again from disk if the item is used with
+    /// get_data again. Returns whether album art was dropped.
+    pub fn drop_data(&self, cx: &mut App) -> bool {
+        let Some(data) = self.data.update(cx, |m, cx| {
             cx.notify();
-        });
+            m.take()
+        }) else {
+            return false;
+        };
+
+        match (data.image, data.source) {
+            (Some(image), DataSource::Library) => {
+                drop_image_from_app(cx, image);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Returns the ID of the entity holding the item's UI data, which is shared by its copies.
+    pub fn data_id(&self) -> EntityId {
+        self.data.entity_id()
     }
 
     /// Returns the file path of the queue item.
     pub fn get_path(&self) -> &PathBuf {
         &self.path
     }
+
+    /// Returns the database ID of the track the item is from, if it's in the library.
+    pub fn get_db_id(&self) -> Option<i64> {
+        self.db_id
+    }
+
+    /// Returns the database ID of the album the item is from, if it's in the library.
+    pub fn get_db_album_id(&self) -> Option<i64> {
+        self.db_album_id
+    }
+}
+
+/// Whether playing a track from album `next` straight after one from album `current` moves from
+/// one album on to a different one. Items that aren't known to be on an album (whose album ID is
+/// `None`) never start or end one.
+pub fn crosses_album_boundary(current: Option<i64>, next: Option<i64>) -> bool {
+    match (current, next) {
+        (Some(current), Some(next)) => current != next,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use gpui::TestAppContext;
+    use image::{Frame, RgbaImage};
+    use smallvec::smallvec;
+
+    use super::*;
+
+    /// Creates a queue item whose data has already been loaded from the given source.
+    fn loaded_item(cx: &mut App, name: &str, source: DataSource, image: bool) -> QueueItemData {
+        let item = QueueItemData::new(cx, PathBuf::from(name), None, None);
+        let frame = Frame::new(RgbaImage::new(1, 1));
+        let image = image.then(|| Arc::new(RenderImage::new(smallvec![frame])));
+
+        item.data.update(cx, |data, _| {
+            *data = Some(QueueItemUIData {
+                image,
+                name: None,
+                artist_name: None,
+                duration: None,
+                source,
+            })
+        });
+
+        item
+    }
+
+    #[gpui::test]
+    fn counts_the_album_art_dropped_from_the_library(cx: &mut TestAppContext) {
+        cx.update(|cx| {
+            let items = [
+                loaded_item(cx, "a.flac", DataSource::Library, true),
+                loaded_item(cx, "b.flac", DataSource::Library, false),
+                loaded_item(cx, "c.flac", DataSource::Metadata, true),
+                QueueItemData::new(cx, PathBuf::from("d.flac"), None, None),
+            ];
+
+            // only album art from the library is dropped from the app, so only that is counted
+            let images = items.iter().filter(|item| item.drop_data(cx)).count();
+            assert_eq!(images, 1);
+            assert!(items.iter().all(|item| item.data.read(cx).is_none()));
+
+            // the data is only dropped once
+            assert!(!items[0].drop_data(cx));
+        });
+    }
 }
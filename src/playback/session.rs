@@ -0,0 +1,259 @@
+use std::path::PathBuf;
+
+use anyhow::Context as _;
+use futures::TryFutureExt as _;
+use gpui::{App, PathPromptOptions};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use tracing::{error, info, warn};
+
+use crate::{
+    library::{
+        db::{get_track_reference, resolve_track},
+        types::TrackReference,
+    },
+    playback::{interface::PlaybackInterface, queue::QueueItemData, thread::PlaybackState},
+    ui::{
+        app::Pool,
+        models::{Models, PlaybackInfo},
+        toasts::show_toast,
+    },
+};
+
+/// The version of the session file format written by this version of Hummingbird.
+const SESSION_VERSION: u32 = 1;
+
+/// A single item in a saved queue.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SessionItem {
+    /// The path of the file when the session was saved. Only used if the track can't be found in
+    /// the library by its other details.
+    pub path: PathBuf,
+    #[serde(flatten)]
+    pub reference: TrackReference,
+}
+
+/// A saved queue: its items (in the order they were shown), the item that was playing, and how
+/// far into that item playback was.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Session {
+    pub version: u32,
+    #[serde(default)]
+    pub current_index: Option<usize>,
+    /// The position within the current item, in seconds.
+    #[serde(default)]
+    pub position: u64,
+    pub items: Vec<SessionItem>,
+}
+
+async fn build_session(
+    pool: &SqlitePool,
+    items: Vec<(PathBuf, Option<i64>)>,
+    current_index: Option<usize>,
+    position: u64,
+) -> anyhow::Result<Session> {
+    let mut session_items = Vec::with_capacity(items.len());
+
+    for (path, track_id) in items {
+        let reference = match track_id {
+            Some(track_id) => get_track_reference(pool, track_id)
+                .await?
+                .unwrap_or_default(),
+            None => TrackReference::default(),
+        };
+
+        session_items.push(SessionItem { path, reference });
+    }
+
+    Ok(Session {
+        version: SESSION_VERSION,
+        current_index,
+        position,
+        items: session_items,
+    })
+}
+
+/// Asks the user where to save the current queue, then saves it as a session file.
+pub fn save_session(cx: &mut App) -> anyhow::Result<()> {
+    let queue = cx.global::<Models>().queue.read(cx);
+    let items: Vec<_> = queue
+        .data
+        .read()
+        .expect("couldn't get queue")
+        .iter()
+        .map(|item| (item.get_path().clone(), item.get_db_id()))
+        .collect();
+    let queue_position = queue.position;
+
+    if items.is_empty() {
+        show_toast(cx, "The queue is empty.");
+        return Ok(());
+    }
+
+    let playback_info = cx.global::<PlaybackInfo>();
+    let stopped = *playback_info.playback_state.read(cx) == PlaybackState::Stopped;
+    // sessions keep the position in seconds
+    let position = *playback_info.position.read(cx) / 1000;
+    let current_index = queue_position.filter(|position| !stopped && *position < items.len());
+
+    let path_future = cx.prompt_for_new_path(
+        directories::UserDirs::new()
+            .context("Failed to get user directories")?
+            .document_dir()
+            .context("Failed to get documents directory")?,
+        Some("Session.json"),
+    );
+    let pool = cx.global::<Pool>().0.clone();
+
+    cx.spawn(async move |cx| {
+        let task = crate::RUNTIME.spawn(async move {
+            let Some(path) = path_future.await?? else {
+                info!("Session save cancelled by user");
+                return anyhow::Ok(false);
+            };
+
+            let session = build_session(&pool, items, current_index, position).await?;
+            let json = serde_json::to_vec_pretty(&session)?;
+            tokio::fs::write(&path, json).await?;
+
+            info!("Saved session to {}", path.display());
+            anyhow::Ok(true)
+        });
+
+        match task.err_into().await.flatten() {
+            Ok(true) => {
+                cx.update(|cx| show_toast(cx, "Session saved.")).ok();
+            }
+            Ok(false) => {}
+            Err(err) => {
+                error!(?err, "Failed to save session: {err}");
+                cx.update(|cx| show_toast(cx, "Couldn't save the session."))
+                    .ok();
+            }
+        }
+    })
+    .detach();
+
+    Ok(())
+}
+
+/// A session item after it's been looked up: the file to play, and the track's library IDs if it
+/// was found in the library.
+type ResolvedItem = (PathBuf, Option<i64>, Option<i64>);
+
+/// Finds each of the session's items in the library, falling back to the saved path if the file
+/// still exists. Items that can't be found at all are `None`.
+async fn resolve_session(
+    pool: &SqlitePool,
+    session: &Session,
+) -> anyhow::Result<Vec<Option<ResolvedItem>>> {
+    let mut resolved = Vec::with_capacity(session.items.len());
+
+    for item in &session.items {
+        if let Some(track) = resolve_track(pool, &item.reference, &item.path).await? {
+            resolved.push(Some((track.location, Some(track.id), track.album_id)));
+        } else if tokio::fs::try_exists(&item.path).await.unwrap_or(false) {
+            resolved.push(Some((item.path.clone(), None, None)));
+        } else {
+            warn!("Couldn't find session item {}", item.path.display());
+            resolved.push(None);
+        }
+    }
+
+    Ok(resolved)
+}
+
+fn restore_session(cx: &mut App, resolved: Vec<Option<ResolvedItem>>, session: &Session) {
+    let missing = resolved.iter().filter(|item| item.is_none()).count();
+
+    // the current item moves up by however many items before it couldn't be found
+    let current = session.current_index.and_then(|index| {
+        let found = resolved.get(index)?.is_some();
+        let index = resolved[..index]
+            .iter()
+            .filter(|item| item.is_some())
+            .count();
+        Some((index, found))
+    });
+
+    let items: Vec<_> = resolved
+        .into_iter()
+        .flatten()
+        .map(|(path, track_id, album_id)| QueueItemData::new(cx, path, track_id, album_id))
+        .collect();
+
+    if items.is_empty() {
+        show_toast(cx, "None of the tracks in the session could be found.");
+        return;
+    }
+
+    let item_count = items.len();
+    let playback_interface = cx.global::<PlaybackInterface>();
+    playback_interface.replace_queue(items);
+
+    if let Some((index, found)) = current
+        && index < item_count
+    {
+        playback_interface.jump_unshuffled(index);
+
+        // if the current item is missing, the next one is played from the start instead
+        if found && session.position > 0 {
+            playback_interface.seek(session.position as f64);
+        }
+    }
+
+    match missing {
+        0 => {}
+        1 => show_toast(cx, "1 track in the session couldn't be found."),
+        missing => show_toast(
+            cx,
+            format!("{missing} tracks in the session couldn't be found."),
+        ),
+    }
+}
+
+/// Asks the user for a session file, then replaces the queue with its contents.
+pub fn load_session(cx: &mut App) {
+    let path_future = cx.prompt_for_paths(PathPromptOptions {
+        files: true,
+        directories: false,
+        multiple: false,
+        prompt: Some("Select a session file...".into()),
+    });
+    let pool = cx.global::<Pool>().0.clone();
+
+    cx.spawn(async move |cx| {
+        let task = crate::RUNTIME.spawn(async move {
+            let Some(path) = path_future.await??.and_then(|v| v.into_iter().next()) else {
+                info!("Session load cancelled by user");
+                return anyhow::Ok(None);
+            };
+
+            let json = tokio::fs::read(&path).await?;
+            let session: Session = serde_json::from_slice(&json)?;
+
+            if session.version > SESSION_VERSION {
+                warn!(
+                    "Session was saved by a newer version of Hummingbird (version {})",
+                    session.version
+                );
+            }
+
+            let resolved = resolve_session(&pool, &session).await?;
+            anyhow::Ok(Some((resolved, session)))
+        });
+
+        match task.err_into().await.flatten() {
+            Ok(Some((resolved, session))) => {
+                cx.update(|cx| restore_session(cx, resolved, &session)).ok();
+            }
+            Ok(None) => {}
+            Err(err) => {
+                error!(?err, "Failed to load session: {err}");
+                cx.update(|cx| show_toast(cx, "Couldn't load the session."))
+                    .ok();
+            }
+        }
+    })
+    .detach();
+}
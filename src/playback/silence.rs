@@ -0,0 +1,247 @@
+use crate::{
+    devices::resample::SampleInto,
+    media::{playback::Samples, traits::MediaStream},
+};
+
+/// Trailing silence is only skipped once this much of the track has played, so that quiet
+/// passages earlier in the track are never cut short.
+const MIN_PROGRESS: f64 = 0.8;
+
+/// The level, in dBFS, that leading silence has to stay below. This is close to digital silence,
+/// so that quiet intros are never mistaken for a gap before the track starts.
+pub const LEADING_SILENCE_THRESHOLD_DB: f64 = -80.0;
+
+/// The most silence that's skipped at the start of a track, in seconds.
+pub const MAX_LEADING_SILENCE: f64 = 30.0;
+
+/// Watches the level of the frames being played for a long stretch of silence near the end of a
+/// track, like the gap before a hidden track on older CDs, or at the very start of one, like a
+/// hidden pregap that was ripped along with the track.
+pub struct SilenceDetector {
+    /// The RMS level, as a linear amplitude, below which a frame counts as silent.
+    threshold: f64,
+    /// How long the silence has to last, in seconds, before the track is skipped.
+    min_duration: f64,
+    /// How long the audio has been silent for, in seconds.
+    silent_for: f64,
+}
+
+impl SilenceDetector {
+    /// Creates a detector for silence below `threshold_db` (in dBFS) that lasts at least
+    /// `min_duration` seconds.
+    pub fn new(threshold_db: f64, min_duration: f64) -> Self {
+        SilenceDetector {
+            threshold: 10.0_f64.powf(threshold_db / 20.0),
+            min_duration: min_duration.max(0.0),
+            silent_for: 0.0,
+        }
+    }
+
+    /// Forgets any silence heard so far. Called whenever a new track is opened, or the position
+    /// changes.
+    pub fn reset(&mut self) {
+        self.silent_for = 0.0;
+    }
+
+    /// How long the audio has been silent for, in seconds.
+    pub fn silent_for(&self) -> f64 {
+        self.silent_for
+    }
+
+    /// Records the level of the next frame, which is `frame_secs` seconds long and played at
+    /// `position` seconds into a track that's `duration` seconds long. Returns true if the rest of
+    /// the track is silence that should be skipped.
+    ///
+    /// Tracks without a known duration are never skipped, since there's no way to tell whether
+    /// the silence is near the end.
+    pub fn feed(
+        &mut self,
+        rms: f64,
+        frame_secs: f64,
+        position: f64,
+        duration: Option<f64>,
+    ) -> bool {
+        let Some(duration) = duration.filter(|duration| *duration > 0.0) else {
+            self.silent_for = 0.0;
+            return false;
+        };
+
+        if rms >= self.threshold {
+            self.silent_for = 0.0;
+            return false;
+        }
+
+        self.silent_for += frame_secs;
+
+        position >= duration * MIN_PROGRESS && self.silent_for >= self.min_duration
+    }
+
+    /// Returns true if a frame with the given RMS level counts as silent.
+    pub fn is_silent(&self, rms: f64) -> bool {
+        rms < self.threshold
+    }
+
+    /// Records the level of the next frame at the start of a track, which is `frame_secs` seconds
+    /// long. Returns true once the track has been silent from the start for long enough that the
+    /// silence should be skipped.
+    ///
+    /// Unlike [SilenceDetector::feed], the caller stops feeding frames as soon as one isn't silent,
+    /// since only silence at the very start of the track is skipped.
+    pub fn feed_leading(&mut self, rms: f64, frame_secs: f64) -> bool {
+        if !self.is_silent(rms) {
+            self.silent_for = 0.0;
+            return false;
+        }
+
+        self.silent_for += frame_secs;
+
+        self.silent_for >= self.min_duration
+    }
+}
+
+/// Decodes ahead in a track that has been silent since it started, without playing anything, to
+/// find where the sound starts. Returns the position, in seconds, of the first frame that isn't
+/// silent, or [MAX_LEADING_SILENCE] if the silence lasts longer than that.
+///
+/// Returns `None` if the rest of the track is silent or can't be decoded, in which case nothing
+/// should be skipped.
+pub fn find_end_of_silence(
+    stream: &mut dyn MediaStream,
+    detector: &SilenceDetector,
+) -> Option<f64> {
+    loop {
+        let frame = stream.read_samples().ok()?;
+        // the position is that of the start of the frame that was just read
+        let position = stream.position_millis().ok()? as f64 / 1000.0;
+
+        if position >= MAX_LEADING_SILENCE {
+            return Some(MAX_LEADING_SILENCE);
+        }
+
+        let (rms, _) = frame_rms(&frame.samples)?;
+
+        if !detector.is_silent(rms) {
+            return Some(position);
+        }
+    }
+}
+
+fn channels_rms<T: Copy + SampleInto<f64>>(channels: &[Vec<T>]) -> (f64, usize) {
+    let mut sum = 0.0;
+    let mut count = 0usize;
+
+    for channel in channels {
+        for sample in channel {
+            let value: f64 = sample.sample_into();
+            sum += value * value;
+        }
+        count += channel.len();
+    }
+
+    let frames = channels.first().map(Vec::len).unwrap_or_default();
+
+    if count == 0 {
+        (0.0, frames)
+    } else {
+        ((sum / count as f64).sqrt(), frames)
+    }
+}
+
+/// Returns the RMS level of a frame across all of its channels, as a linear amplitude, along with
+/// the number of samples in each channel. Returns `None` for DSD audio, which can't be measured
+/// this way.
+pub fn frame_rms(samples: &Samples) -> Option<(f64, usize)> {
+    Some(match samples {
+        Samples::Float64(v) => {
+            let count: usize = v.iter().map(Vec::len).sum();
+            let sum: f64 = v.iter().flatten().map(|sample| sample * sample).sum();
+            let frames = v.first().map(Vec::len).unwrap_or_default();
+
+            if count == 0 {
+                (0.0, frames)
+            } else {
+                ((sum / count as f64).sqrt(), frames)
+            }
+        }
+        Samples::Float32(v) => channels_rms(v),
+        Samples::Signed32(v) => channels_rms(v),
+        Samples::Unsigned32(v) => channels_rms(v),
+        Samples::Signed24(v) => channels_rms(v),
+        Samples::Unsigned24(v) => channels_rms(v),
+        Samples::Signed16(v) => channels_rms(v),
+        Samples::Unsigned16(v) => channels_rms(v),
+        Samples::Signed8(v) => channels_rms(v),
+        Samples::Unsigned8(v) => channels_rms(v),
+        Samples::Dsd(_) => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn skips_long_silence_near_the_end() {
+        let mut detector = SilenceDetector::new(-60.0, 2.0);
+
+        assert!(!detector.feed(0.0, 1.0, 90.0, Some(100.0)));
+        assert!(detector.feed(0.0, 1.0, 91.0, Some(100.0)));
+    }
+
+    #[test]
+    fn never_skips_silence_early_in_the_track() {
+        let mut detector = SilenceDetector::new(-60.0, 2.0);
+
+        for position in 0..10 {
+            assert!(!detector.feed(0.0, 1.0, f64::from(position) + 50.0, Some(100.0)));
+        }
+        assert_eq!(detector.silent_for(), 10.0);
+    }
+
+    #[test]
+    fn never_skips_tracks_without_a_duration() {
+        let mut detector = SilenceDetector::new(-60.0, 0.0);
+
+        assert!(!detector.feed(0.0, 1.0, 90.0, None));
+        assert!(!detector.feed(0.0, 1.0, 90.0, Some(0.0)));
+        assert_eq!(detector.silent_for(), 0.0);
+    }
+
+    #[test]
+    fn sound_restarts_the_minimum_duration() {
+        let mut detector = SilenceDetector::new(-60.0, 2.0);
+
+        assert!(!detector.feed(0.0, 1.5, 90.0, Some(100.0)));
+        assert!(!detector.feed(0.5, 0.1, 91.5, Some(100.0)));
+        assert!(!detector.feed(0.0, 1.5, 91.6, Some(100.0)));
+        assert!(detector.feed(0.0, 0.5, 93.1, Some(100.0)));
+    }
+
+    #[test]
+    fn frames_at_the_threshold_are_not_silent() {
+        let detector = SilenceDetector::new(-60.0, 2.0);
+
+        assert!(detector.is_silent(0.0));
+        assert!(detector.is_silent(detector.threshold * 0.99));
+        assert!(!detector.is_silent(detector.threshold));
+        assert!((detector.threshold - 0.001).abs() < 1e-12);
+    }
+
+    #[test]
+    fn skips_leading_silence_once_it_lasts_long_enough() {
+        let mut detector = SilenceDetector::new(LEADING_SILENCE_THRESHOLD_DB, 1.0);
+
+        assert!(!detector.feed_leading(0.0, 0.5));
+        assert!(detector.feed_leading(0.0, 0.5));
+    }
+
+    #[test]
+    fn quiet_intros_are_not_leading_silence() {
+        let mut detector = SilenceDetector::new(LEADING_SILENCE_THRESHOLD_DB, 1.0);
+
+        // -60 dBFS is quiet, but well above the leading silence threshold
+        assert!(!detector.feed_leading(0.001, 0.5));
+        assert!(!detector.feed_leading(0.001, 0.5));
+        assert_eq!(detector.silent_for(), 0.0);
+    }
+}
@@ -0,0 +1,124 @@
+use std::time::{Duration, Instant};
+
+use super::events::SleepTimer;
+
+/// How long the volume is faded out for before the sleep timer pauses playback.
+const FADE_DURATION: Duration = Duration::from_secs(3);
+
+/// When a running sleep timer goes off.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum SleepDeadline {
+    At(Instant),
+    EndOfTrack,
+}
+
+/// A sleep timer that has been set, and how far it has got with fading out.
+///
+/// Timers that run for a duration start fading out once it has passed, and pause playback when
+/// the fade is over. Timers that wait for the end of the current track start fading out shortly
+/// before the end, so that the track is silent by the time it ends; the playback thread pauses
+/// when it moves on to the next track.
+pub struct ActiveSleepTimer {
+    deadline: SleepDeadline,
+    /// When the fade out started, once it has.
+    fade_start: Option<Instant>,
+    /// The time left (in whole seconds) when it was last reported to the UI.
+    reported_secs: Option<u64>,
+}
+
+impl ActiveSleepTimer {
+    /// Starts the given timer. Returns `None` for [SleepTimer::Off].
+    pub fn new(timer: SleepTimer) -> Option<Self> {
+        let deadline = match timer {
+            SleepTimer::Off => return None,
+            SleepTimer::AfterDuration(duration) => SleepDeadline::At(Instant::now() + duration),
+            SleepTimer::AfterCurrentTrack => SleepDeadline::EndOfTrack,
+        };
+
+        Some(Self {
+            deadline,
+            fade_start: None,
+            reported_secs: None,
+        })
+    }
+
+    /// Whether the timer goes off when the current track ends.
+    pub fn waits_for_track_end(&self) -> bool {
+        self.deadline == SleepDeadline::EndOfTrack
+    }
+
+    /// Whether the timer runs for a duration that has already passed.
+    pub fn has_expired(&self) -> bool {
+        matches!(self.deadline, SleepDeadline::At(at) if at <= Instant::now())
+    }
+
+    /// Returns the timer as it's reported to the UI, with the time left for timers that run for a
+    /// duration.
+    pub fn status(&self) -> SleepTimer {
+        match self.deadline {
+            SleepDeadline::At(at) => {
+                SleepTimer::AfterDuration(at.saturating_duration_since(Instant::now()))
+            }
+            SleepDeadline::EndOfTrack => SleepTimer::AfterCurrentTrack,
+        }
+    }
+
+    /// Returns the timer's status if the time left has reached another second since it was last
+    /// reported, so that the UI can count down without being sent an update on every frame.
+    pub fn status_update(&mut self) -> Option<SleepTimer> {
+        let status = self.status();
+        let secs = match status {
+            SleepTimer::AfterDuration(left) => left.as_secs(),
+            _ => 0,
+        };
+
+        (self.reported_secs.replace(secs) != Some(secs)).then_some(status)
+    }
+
+    /// Starts fading out when it's time to, and returns true once the fade is over and playback
+    /// should be paused. `track_left` is how long the current track has left to play, if it's
+    /// known.
+    ///
+    /// Timers waiting for the end of the track never return true, since the track ending is what
+    /// sets them off. If the user seeks back out of the fade, it's undone.
+    pub fn tick(&mut self, track_left: Option<Duration>) -> bool {
+        let now = Instant::now();
+
+        match self.deadline {
+            SleepDeadline::At(at) => {
+                if at <= now && self.fade_start.is_none() {
+                    self.fade_start = Some(now);
+                }
+
+                self.fade_start
+                    .is_some_and(|start| now.duration_since(start) >= FADE_DURATION)
+            }
+            SleepDeadline::EndOfTrack => {
+                match track_left {
+                    Some(left) if left <= FADE_DURATION => {
+                        self.fade_start.get_or_insert(now);
+                    }
+                    _ => self.fade_start = None,
+                }
+
+                false
+            }
+        }
+    }
+
+    /// Whether the volume is being faded out.
+    pub fn is_fading(&self) -> bool {
+        self.fade_start.is_some()
+    }
+
+    /// Returns how much the fade out reduces the volume by, as an amplitude multiplier: 1 before
+    /// it starts, falling to 0 by its end.
+    pub fn fade(&self) -> f64 {
+        let Some(start) = self.fade_start else {
+            return 1.0;
+        };
+        let progress = start.elapsed().as_secs_f64() / FADE_DURATION.as_secs_f64();
+
+        (1.0 - progress).clamp(0.0, 1.0)
+    }
+}
@@ -1,21 +1,38 @@
 use std::{
-    env::consts::OS,
+    fs,
+    hash::Hasher,
     mem::swap,
-    path::Path,
+    panic::{AssertUnwindSafe, catch_unwind},
+    path::{Path, PathBuf},
     sync::{Arc, RwLock},
     thread::sleep,
+    time::{Duration, Instant},
 };
 
+use gpui::EntityId;
 use itertools::Itertools as _;
 use rand::{rng, seq::SliceRandom};
+use rustc_hash::{FxHashSet, FxHasher};
+use sqlx::SqlitePool;
 use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender, unbounded_channel};
 use tracing::{debug, error, info, trace_span, warn};
 
 use crate::{
-    devices::builtin::cpal::CpalProvider, media::errors::PlaybackStartError,
+    crash::set_audio_backend,
+    devices::builtin::cpal::CpalProvider,
+    library::{
+        db::{get_track_gain_offset, get_track_loudness},
+        types::MAX_GAIN_OFFSET,
+    },
+    media::{errors::PlaybackStartError, loudness::AnalyzedLoudness},
     playback::events::RepeatState,
 };
-use crate::{devices::builtin::dummy::DummyDeviceProvider, settings::playback::PlaybackSettings};
+use crate::{
+    devices::builtin::dummy::DummyDeviceProvider,
+    settings::playback::{
+        DeviceProviderKind, DeviceRemovalPolicy, DitherMode, PlaybackSettings, ReplayGainMode,
+    },
+};
 // #[cfg(target_os = "linux")]
 // use crate::devices::builtin::pulse::PulseProvider;
 #[cfg(target_os = "windows")]
@@ -23,21 +40,37 @@ use crate::devices::builtin::win_audiograph::AudioGraphProvider;
 
 use crate::{
     devices::{
-        format::{ChannelSpec, FormatInfo},
-        resample::Resampler,
+        category::DeviceCategory,
+        errors::FindError,
+        format::{ChannelSpec, FormatInfo, SampleFormat, fallback_formats, is_exact_format},
+        mixer::ChannelMixer,
+        resample::{MAX_SPEED, MIN_SPEED, Resampler},
         traits::{Device, DeviceProvider, OutputStream},
     },
     media::{
         builtin::symphonia::SymphoniaProvider,
         errors::PlaybackReadError,
+        metadata::Metadata,
+        playback::Samples,
+        source::open_source,
         traits::{MediaProvider, MediaStream},
     },
 };
 
 use super::{
-    events::{PlaybackCommand, PlaybackEvent},
+    crossfade::{Crossfade, MAX_CROSSFADE_SECS, NextTrack},
+    crossfeed::Crossfeed,
+    equalizer::Equalizer,
+    events::{
+        ActiveDeviceProvider, OutputRecovery, PlaybackCommand, PlaybackErrorKind, PlaybackEvent,
+        SleepTimer,
+    },
+    fade::{FadeOutAction, VolumeRamp},
     interface::PlaybackInterface,
-    queue::QueueItemData,
+    queue::{QueueItemData, crosses_album_boundary},
+    silence::{LEADING_SILENCE_THRESHOLD_DB, SilenceDetector, find_end_of_silence, frame_rms},
+    sleep::ActiveSleepTimer,
+    watchdog::{OutputWatchdog, UnderrunMonitor},
 };
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -67,6 +100,9 @@ pub struct PlaybackThread {
     /// The current device provider.
     device_provider: Option<Box<dyn DeviceProvider>>,
 
+    /// Which audio backend the current device provider is, and which one was asked for.
+    active_provider: Option<ActiveDeviceProvider>,
+
     /// The current device.
     device: Option<Box<dyn Device>>,
 
@@ -89,6 +125,10 @@ pub struct PlaybackThread {
     /// The current format of the media.
     format: Option<FormatInfo>,
 
+    /// Maps the current track's channels onto the device's. Passed to the resampler when it's
+    /// created.
+    channel_mixer: ChannelMixer,
+
     /// The current queue. Do not hold an indefinite lock on this queue - it is read by the
     /// UI thread.
     queue: Arc<RwLock<Vec<QueueItemData>>>,
@@ -103,8 +143,8 @@ pub struct PlaybackThread {
     /// track is the last track in the queue.
     queue_next: usize,
 
-    /// The last timestamp of the current track. This is used to determine if the position has
-    /// changed since the last update.
+    /// The last timestamp of the current track, in milliseconds. This is used to determine if the
+    /// position has changed since the last update.
     last_timestamp: u64,
 
     /// Whether or not the stream should be reset before playback is continued.
@@ -116,16 +156,589 @@ pub struct PlaybackThread {
     /// The last recorded volume level. This is used to ensure that volume remains consistent, even
     /// after the thread is recreated.
     last_volume: f64,
+
+    /// The volume the user set, from 0 to 1, before it's scaled (see [scale_volume]). Volume steps
+    /// are applied to this, so that several steps in a row add up.
+    volume: f64,
+
+    /// Whether playback is muted. `last_volume` is kept while muted, so that it can be restored.
+    muted: bool,
+
+    /// The library database, used to look up the volume adjustment of each track when it's
+    /// opened.
+    pool: SqlitePool,
+
+    /// The path of the currently open track.
+    current_path: Option<PathBuf>,
+
+    /// The volume adjustment of the current track, in decibels (see `Track::gain_offset`).
+    track_gain: f64,
+
+    /// The ReplayGain adjustment of the current track, as an amplitude multiplier (see
+    /// [replay_gain]). Updated whenever the track's metadata changes.
+    replay_gain: f64,
+
+    /// The loudness of the current track measured while scanning, if it has been measured. Used
+    /// for ReplayGain when the track has no ReplayGain tags.
+    track_loudness: Option<AnalyzedLoudness>,
+
+    /// The last metadata sent to the UI for the current track. Some files (for example, ones with
+    /// both ID3v2 and APE tags) report a metadata update more than once with the same content, so
+    /// this is used to avoid sending the same metadata again.
+    last_metadata: Option<Metadata>,
+
+    /// A fingerprint of the last album art sent to the UI for the current track, or `None` if no
+    /// album art has been sent yet. `Some(None)` means the track has no album art.
+    last_image: Option<Option<ImageFingerprint>>,
+
+    /// When the metadata of the current track was last read. Metadata is re-read at most once every
+    /// `METADATA_READ_INTERVAL`.
+    last_metadata_read: Option<Instant>,
+
+    /// Watches for silence at the end of the current track, if skipping trailing silence is
+    /// enabled.
+    silence_detector: Option<SilenceDetector>,
+
+    /// Watches for silence at the start of the current track, if skipping leading silence is
+    /// enabled.
+    lead_in_detector: Option<SilenceDetector>,
+
+    /// Whether the current track has been silent since it started, so that the silence may still
+    /// be skipped. Cleared once it isn't, or when the user seeks.
+    in_lead_in: bool,
+
+    /// Mixes the channels of stereo tracks together for headphone listening. Applied after the
+    /// audio has been converted to the device's format, when that format is f32.
+    crossfeed: Crossfeed,
+
+    /// Boosts or cuts frequency bands as the user has set. Applied alongside crossfeed, and
+    /// recomputed for the device's sample rate whenever the output stream is recreated.
+    equalizer: Equalizer,
+
+    /// Whether every channel plays the same mono mix, for single-speaker setups. Applied after the
+    /// audio has been converted to the device's format and channels, when that format is f32.
+    mono_downmix: bool,
+
+    /// The balance between the left and right channels, from -1.0 (left only) to 1.0 (right
+    /// only). Applied along with `mono_downmix`.
+    balance: f64,
+
+    /// How long the end of each track is mixed with the start of the next one, in seconds. 0 if
+    /// crossfading is off.
+    crossfade_secs: f64,
+
+    /// How fast tracks are played, as a multiple of their normal speed (see
+    /// [Resampler::set_speed]).
+    speed: f64,
+
+    /// The section of the current track that's repeated, as start and end times in seconds.
+    /// Cleared when the track changes.
+    loop_region: Option<(f64, f64)>,
+
+    /// The crossfade that's in progress, if any. This holds the next track until the current one
+    /// ends, and then finishes fading it in.
+    crossfade: Option<Crossfade>,
+
+    /// Fades playback in after resuming, and out before pausing or stopping, so that it doesn't
+    /// click. Applied to the frames after they've been converted to the device's format.
+    ramp: Option<VolumeRamp>,
+
+    /// What's done once playback has faded out, if it's fading out.
+    fade_out: Option<FadeOutAction>,
+
+    /// Whether the next track that's opened is left paused at its start, instead of being played.
+    /// Only set while moving on to the next track after the user paused at the very end of the
+    /// current one, or at the end of an album (see [PlaybackThread::album_boundary]).
+    open_paused: bool,
+
+    /// Whether crossfading has been skipped for the current track, because the user seeked in it
+    /// or the next track couldn't be opened.
+    crossfade_skipped: bool,
+
+    /// Whether playback stops once the current track has finished, instead of moving on to the
+    /// next one. Cleared when that happens, or when the user skips to the next track.
+    stop_after_current: bool,
+
+    /// The sleep timer, while one is set. Playback is faded out and paused when it goes off.
+    sleep_timer: Option<ActiveSleepTimer>,
+
+    /// Notices when the output stream stops playing without reporting an error, or when the
+    /// system has been suspended, so that the stream can be recreated.
+    watchdog: OutputWatchdog,
+    /// Counts the times the device runs out of samples while playing.
+    underruns: UnderrunMonitor,
+
+    /// The UID of the system's default device when the stream was opened on it, or None if the
+    /// user chose a device to play through. Playback moves to the new default device when this
+    /// stops matching it.
+    default_device_uid: Option<String>,
+
+    /// When the devices were last checked, for a change of the default device (see
+    /// `default_device_uid`) or for a device to play on after losing the last one.
+    last_device_check: Instant,
+
+    /// The format the current track is decoded in, if its sample rate is known. In exclusive mode
+    /// (see [PlaybackSettings::exclusive]), the device is opened in this format if it can be.
+    source_format: Option<SourceFormat>,
+
+    /// Whether the current track is played bit-perfect, as last sent to the UI.
+    bit_perfect: Option<bool>,
+
+    /// The tracks in a row that couldn't be opened or stopped being readable, without anything
+    /// being played in between.
+    failed_tracks: FailedTracks,
+
+    /// Set while there's no device to play on. Contains whether playback was paused because of it,
+    /// in which case it resumes once a device can be opened again. Stopping playback clears that,
+    /// so that it isn't started again without the user asking.
+    device_lost: Option<bool>,
+}
+
+/// The shortest section of a track that can be looped, in seconds. Positions are only reported in
+/// whole seconds, so shorter loops couldn't be told apart from their start.
+const MIN_LOOP_LENGTH: f64 = 1.0;
+
+/// The shortest amount of time between two reads of the current track's metadata.
+const METADATA_READ_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How many tracks in a row can fail to play before playback stops, instead of moving on to the
+/// next one. This keeps a queue of files that are all gone (for example, on a drive that was
+/// unplugged) from being run through in one go.
+const MAX_CONSECUTIVE_FAILURES: u32 = 5;
+
+/// How often the devices are checked, to notice when the user switches the default device, or
+/// when a device is connected after the last one was lost.
+const DEVICE_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How many underruns between two reports count as a spike, which is logged along with the
+/// output's format and buffer size.
+const UNDERRUN_SPIKE: u64 = 3;
+
+/// The sample rate, bit depth and channels a track is decoded in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct SourceFormat {
+    sample_rate: u32,
+    /// None for tracks that are decoded to floating point samples.
+    bits_per_sample: Option<u32>,
+    channels: ChannelSpec,
+}
+
+impl SourceFormat {
+    /// Returns the format the given track is decoded in, if its sample rate is known.
+    fn of(stream: &dyn MediaStream) -> Option<Self> {
+        Some(Self {
+            sample_rate: stream.sample_rate()?,
+            bits_per_sample: stream.bits_per_sample(),
+            channels: stream.channels().ok()?,
+        })
+    }
+}
+
+/// A cheap summary of an image's bytes, used to tell whether the album art has changed without
+/// keeping a copy of the previous image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct ImageFingerprint {
+    length: usize,
+    first: u8,
+    last: u8,
+    hash: u64,
+}
+
+impl ImageFingerprint {
+    fn new(image: &[u8]) -> Self {
+        let mut hasher = FxHasher::default();
+        hasher.write(image);
+
+        Self {
+            length: image.len(),
+            first: image.first().copied().unwrap_or_default(),
+            last: image.last().copied().unwrap_or_default(),
+            hash: hasher.finish(),
+        }
+    }
+}
+
+/// Reads the audio backend requested with the `DEVICE_PROVIDER` environment variable, which
+/// overrides the settings for debugging.
+fn device_provider_override() -> Option<DeviceProviderKind> {
+    let name = std::env::var("DEVICE_PROVIDER").ok()?;
+
+    match DeviceProviderKind::from_name(&name) {
+        Some(kind) => {
+            info!("Using the {name} device provider from DEVICE_PROVIDER instead of the settings");
+            Some(kind)
+        }
+        None if name == "pulse" => {
+            warn!("pulseaudio support was removed, using the settings instead");
+            None
+        }
+        None => {
+            warn!("Unknown device provider in DEVICE_PROVIDER: {name}, using the settings instead");
+            None
+        }
+    }
 }
 
 pub const LN_50: f64 = 3.91202300543_f64;
 pub const LINEAR_SCALING_COEFFICIENT: f64 = 0.295751527165_f64;
 
+/// Converts a gain in decibels to a linear amplitude multiplier.
+fn db_to_linear(db: f64) -> f64 {
+    10_f64.powf(db / 20.0)
+}
+
+/// Maps the user's volume (the position of the volume slider, from 0 to 1) to the amplitude
+/// multiplier applied to the stream. The curve is exponential through most of the range, so that
+/// each step of the slider sounds about as big as the last, and linear near the bottom so that it
+/// still reaches silence.
+pub fn scale_volume(volume: f64) -> f64 {
+    if volume >= 0.99_f64 {
+        1_f64
+    } else if volume > 0.1 {
+        f64::exp(LN_50 * volume) / 50_f64
+    } else {
+        volume * LINEAR_SCALING_COEFFICIENT
+    }
+}
+
+/// Returns how much the user's volume attenuates the stream, in decibels, or `None` if the
+/// volume is zero (which is silent).
+pub fn volume_to_db(volume: f64) -> Option<f64> {
+    let scaled = scale_volume(volume);
+    (scaled > 0.0).then(|| 20.0 * scaled.log10())
+}
+
+/// The items [PlaybackThread::remove_items] removes from the queue, and what that does to the
+/// playing position.
+#[derive(Debug, PartialEq)]
+struct QueueRemoval {
+    /// The positions of the items to remove, in ascending order, without repeats or positions
+    /// past the end of the queue.
+    indices: Vec<usize>,
+    /// Whether the current track (the one before `queue_next`) is removed.
+    removed_current: bool,
+    /// How many of the removed items come before `queue_next`.
+    removed_before_next: usize,
+}
+
+impl QueueRemoval {
+    fn new(mut indices: Vec<usize>, queue_len: usize, queue_next: usize) -> Self {
+        indices.sort_unstable();
+        indices.dedup();
+        indices.retain(|idx| *idx < queue_len);
+
+        let removed_current = queue_next
+            .checked_sub(1)
+            .is_some_and(|current| indices.binary_search(&current).is_ok());
+        let removed_before_next = indices.partition_point(|idx| *idx < queue_next);
+
+        QueueRemoval {
+            indices,
+            removed_current,
+            removed_before_next,
+        }
+    }
+}
+
+/// Returns the `items` whose path isn't already in the `queue` or earlier in `items`. Paths are
+/// compared after canonicalization, so the same file reached through different paths is caught.
+fn unqueued<T>(queue: &[T], items: Vec<T>, path: impl Fn(&T) -> &Path) -> Vec<T> {
+    let canonical = |path: &Path| fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+
+    let mut seen: FxHashSet<PathBuf> = queue.iter().map(|item| canonical(path(item))).collect();
+
+    items
+        .into_iter()
+        .filter(|item| seen.insert(canonical(path(item))))
+        .collect()
+}
+
+/// Whether the file at `path` is gone, for example because it was deleted. A drive that was
+/// unplugged can report an error instead of the file not existing, so that counts as gone too.
+fn file_is_gone(path: &Path) -> bool {
+    !matches!(path.try_exists(), Ok(true))
+}
+
+/// Counts the tracks in a row that couldn't be played, so that playback can stop once there have
+/// been too many (see [MAX_CONSECUTIVE_FAILURES]).
+#[derive(Debug, Default)]
+struct FailedTracks(u32);
+
+impl FailedTracks {
+    /// Counts another track that couldn't be played. Returns whether playback should stop, in
+    /// which case counting starts over.
+    fn add(&mut self) -> bool {
+        self.0 += 1;
+
+        if self.0 < MAX_CONSECUTIVE_FAILURES {
+            return false;
+        }
+
+        self.0 = 0;
+        true
+    }
+
+    /// Starts counting over, once a track plays.
+    fn reset(&mut self) {
+        self.0 = 0;
+    }
+}
+
+/// Returns the position in the queue of the track to play again when moving on from the current
+/// one, if it should be repeated. Repeat-one only applies when a track ends on its own (not when
+/// `user_initiated`), and there's nothing to repeat if `queue_next` is 0, as it is when nothing
+/// has been played since the queue was replaced or cleared.
+fn track_to_repeat(
+    repeat: RepeatState,
+    user_initiated: bool,
+    queue_next: usize,
+    queue_len: usize,
+) -> Option<usize> {
+    if repeat != RepeatState::RepeatingOne || user_initiated {
+        return None;
+    }
+
+    queue_next.checked_sub(1).filter(|&idx| idx < queue_len)
+}
+
+/// What [PlaybackThread::next] does with the next track.
+#[derive(Debug, PartialEq)]
+enum NextTrackAction {
+    Play,
+    /// Open it, but leave it paused at its start.
+    OpenPaused,
+    /// Don't move on, and stop playback instead.
+    Stop,
+}
+
+/// Works out what's done with the next track when moving on from the current one. Skipping always
+/// plays it, but when the track ends on its own (not `user_initiated`) while playback is paused or
+/// fading out to pause or stop, the next one isn't played.
+fn next_track(
+    user_initiated: bool,
+    fade_out: Option<FadeOutAction>,
+    state: PlaybackState,
+) -> NextTrackAction {
+    if user_initiated {
+        return NextTrackAction::Play;
+    }
+
+    match fade_out {
+        Some(FadeOutAction::Stop) => NextTrackAction::Stop,
+        Some(FadeOutAction::Pause) => NextTrackAction::OpenPaused,
+        None if state == PlaybackState::Paused => NextTrackAction::OpenPaused,
+        None => NextTrackAction::Play,
+    }
+}
+
+/// Returns the album that's finishing when moving on from a track on album `current` to the next
+/// one in the queue, on album `next` (or `None` at the end of the queue), if that moves on to a
+/// different album (see [crosses_album_boundary]). Going back to the start of the queue when
+/// repeating it counts as well, since the album has finished either way.
+fn finished_album(
+    current: Option<i64>,
+    next: Option<Option<i64>>,
+    repeat: RepeatState,
+) -> Option<i64> {
+    if repeat == RepeatState::RepeatingOne {
+        return None;
+    }
+
+    match next {
+        Some(next) => current.filter(|_| crosses_album_boundary(current, next)),
+        None if repeat == RepeatState::Repeating => current,
+        // playback stops at the end of the queue anyway
+        None => None,
+    }
+}
+
+/// Combines everything that affects the loudness of the current track into the volume of the
+/// output stream. `volume` is the user's volume, after scaling.
+///
+/// The adjustments are applied in this order: the track's volume adjustment, then ReplayGain
+/// (`replay_gain`, as an amplitude multiplier), then the user's volume, then the limiter. They're
+/// all multiplied together, so the order only matters for limiting. ReplayGain comes already
+/// limited by the track's peak (see [replay_gain]), which doesn't take the track's adjustment
+/// into account. The limiter isn't supported yet - it should be applied last when it is.
+pub fn combined_gain(track_gain: f64, replay_gain: f64, volume: f64) -> f64 {
+    let track = db_to_linear(track_gain.clamp(-MAX_GAIN_OFFSET, MAX_GAIN_OFFSET));
+
+    track * replay_gain * volume
+}
+
+/// Replaces every channel of a frame with the average of all of them, so that each speaker plays
+/// the whole mix. Frames with a single channel are left alone.
+fn mix_to_mono(channels: &mut [Vec<f32>]) {
+    let Some((first, rest)) = channels.split_first_mut() else {
+        return;
+    };
+    if rest.is_empty() {
+        return;
+    }
+
+    let count = (rest.len() + 1) as f32;
+
+    for (frame, sample) in first.iter_mut().enumerate() {
+        let sum: f32 = rest.iter().map(|samples| samples[frame]).sum();
+        *sample = (*sample + sum) / count;
+    }
+
+    for samples in rest {
+        samples.copy_from_slice(first);
+    }
+}
+
+/// Turns down the left channel of a frame when `balance` is above 0, or the right channel when
+/// it's below 0. The first two channels are taken to be the front left and right. Frames with a
+/// single channel are left alone.
+fn apply_balance(channels: &mut [Vec<f32>], balance: f64) {
+    let [left, right, ..] = channels else {
+        return;
+    };
+
+    let (quieter, gain) = if balance > 0.0 {
+        (left, 1.0 - balance)
+    } else {
+        (right, 1.0 + balance)
+    };
+
+    for sample in quieter.iter_mut() {
+        *sample *= gain as f32;
+    }
+}
+
+/// Returns the ReplayGain adjustment for a track, as an amplitude multiplier. The adjustment is
+/// limited by the track's peak level, so that it never makes the track clip. Tracks without
+/// ReplayGain tags use the loudness measured while scanning, if there is one, and otherwise get
+/// the fallback pre-amp.
+pub fn replay_gain(
+    metadata: Option<&Metadata>,
+    loudness: Option<AnalyzedLoudness>,
+    settings: &PlaybackSettings,
+) -> f64 {
+    if settings.replaygain == ReplayGainMode::Off {
+        return 1.0;
+    }
+
+    let album = metadata
+        .filter(|_| settings.replaygain == ReplayGainMode::Album)
+        .and_then(|metadata| {
+            let peak = metadata
+                .replaygain_album_peak
+                .or(metadata.replaygain_track_peak);
+            metadata.replaygain_album_gain.map(|gain| (gain, peak))
+        });
+    let track = metadata.and_then(|metadata| {
+        metadata
+            .replaygain_track_gain
+            .map(|gain| (gain, metadata.replaygain_track_peak))
+    });
+
+    // albums aren't measured as a whole, so album mode uses the track's own loudness too
+    let analyzed = loudness.map(|loudness| (loudness.gain(), Some(loudness.peak)));
+
+    let Some((gain, peak)) = album.or(track).or(analyzed) else {
+        return db_to_linear(
+            settings
+                .replaygain_fallback
+                .clamp(-MAX_GAIN_OFFSET, MAX_GAIN_OFFSET),
+        );
+    };
+
+    match peak.filter(|peak| *peak > 0.0) {
+        Some(peak) => db_to_linear(gain).min(1.0 / peak),
+        None => db_to_linear(gain),
+    }
+}
+
 impl PlaybackThread {
+    /// Creates the thread's state. The media and device providers are only set up once it's
+    /// running (see [PlaybackThread::run]).
+    fn new(
+        commands_rx: UnboundedReceiver<PlaybackCommand>,
+        events_tx: UnboundedSender<PlaybackEvent>,
+        queue: Arc<RwLock<Vec<QueueItemData>>>,
+        settings: PlaybackSettings,
+        pool: SqlitePool,
+    ) -> Self {
+        PlaybackThread {
+            commands_rx,
+            events_tx,
+            media_provider: None,
+            media_stream: None,
+            device_provider: None,
+            active_provider: None,
+            device: None,
+            stream: None,
+            state: PlaybackState::Stopped,
+            resampler: None,
+            format: None,
+            channel_mixer: ChannelMixer::default(),
+            queue,
+            original_queue: Vec::new(),
+            shuffle: false,
+            queue_next: 0,
+            last_timestamp: u64::MAX,
+            pending_reset: false,
+            silence_detector: settings.skip_trailing_silence.then(|| {
+                SilenceDetector::new(settings.silence_threshold_db, settings.silence_min_duration)
+            }),
+            lead_in_detector: settings.skip_leading_silence.then(|| {
+                SilenceDetector::new(
+                    settings
+                        .silence_threshold_db
+                        .min(LEADING_SILENCE_THRESHOLD_DB),
+                    settings.silence_min_duration,
+                )
+            }),
+            in_lead_in: false,
+            crossfeed: Crossfeed::new(settings.crossfeed),
+            equalizer: Equalizer::new(settings.equalizer),
+            mono_downmix: settings.mono_downmix,
+            balance: settings.balance.clamp(-1.0, 1.0),
+            crossfade_secs: settings.crossfade.clamp(0.0, MAX_CROSSFADE_SECS),
+            speed: 1.0,
+            loop_region: None,
+            crossfade: None,
+            crossfade_skipped: false,
+            ramp: None,
+            fade_out: None,
+            open_paused: false,
+            stop_after_current: false,
+            sleep_timer: None,
+            watchdog: OutputWatchdog::default(),
+            underruns: UnderrunMonitor::default(),
+            default_device_uid: None,
+            last_device_check: Instant::now(),
+            device_lost: None,
+            failed_tracks: FailedTracks::default(),
+            source_format: None,
+            bit_perfect: None,
+            repeat: if settings.always_repeat {
+                RepeatState::Repeating
+            } else {
+                RepeatState::NotRepeating
+            },
+            playback_settings: settings,
+            last_volume: 1.0,
+            volume: 1.0,
+            muted: false,
+            pool,
+            current_path: None,
+            track_gain: 0.0,
+            replay_gain: 1.0,
+            track_loudness: None,
+            last_metadata: None,
+            last_image: None,
+            last_metadata_read: None,
+        }
+    }
+
     /// Starts the playback thread and returns the created interface.
     pub fn start(
         queue: Arc<RwLock<Vec<QueueItemData>>>,
         settings: PlaybackSettings,
+        pool: SqlitePool,
     ) -> PlaybackInterface {
         // TODO: use the refresh rate for the bounds
         let (cmd_tx, commands_rx) = unbounded_channel();
@@ -134,33 +747,13 @@ impl PlaybackThread {
         std::thread::Builder::new()
             .name("playback".to_string())
             .spawn(move || {
-                let mut thread = PlaybackThread {
-                    commands_rx,
-                    events_tx,
-                    media_provider: None,
-                    media_stream: None,
-                    device_provider: None,
-                    device: None,
-                    stream: None,
-                    state: PlaybackState::Stopped,
-                    resampler: None,
-                    format: None,
-                    queue,
-                    original_queue: Vec::new(),
-                    shuffle: false,
-                    queue_next: 0,
-                    last_timestamp: u64::MAX,
-                    pending_reset: false,
-                    repeat: if settings.always_repeat {
-                        RepeatState::Repeating
-                    } else {
-                        RepeatState::NotRepeating
-                    },
-                    playback_settings: settings,
-                    last_volume: 1.0,
-                };
+                let mut thread = PlaybackThread::new(commands_rx, events_tx, queue, settings, pool);
 
-                thread.run();
+                // the panic hook has written a crash report by the time this returns; the thread
+                // is dropped along with its event sender, which tells the UI to shut down cleanly
+                if catch_unwind(AssertUnwindSafe(|| thread.run())).is_err() {
+                    error!("The playback thread panicked, shutting down");
+                }
             })
             .expect("could not start playback thread");
 
@@ -188,54 +781,9 @@ impl PlaybackThread {
         //     self.device_provider = Some(Box::new(CpalProvider::default()));
         // }
 
-        let default_device_provider = match OS {
-            "linux" => "cpal", // TODO: reimplement pulse provider
-            "windows" => "win_audiograph",
-            _ => "cpal",
-        };
-
-        let requested_device_provider = std::env::var("DEVICE_PROVIDER")
-            .unwrap_or_else(|_| default_device_provider.to_string());
-
-        match requested_device_provider.as_str() {
-            "pulse" => {
-                // #[cfg(target_os = "linux")]
-                // {
-                //     self.device_provider = Some(Box::new(PulseProvider::default()));
-                // }
-                // #[cfg(not(target_os = "linux"))]
-                // {
-                //     warn!("pulse is not supported on this platform");
-                //     warn!("Falling back to CPAL");
-                //     self.device_provider = Some(Box::new(CpalProvider::default()));
-                // }
-                warn!("pulseaudio support was removed");
-                warn!("Falling back to CPAL");
-                self.device_provider = Some(Box::new(CpalProvider::default()));
-            }
-            "win_audiograph" => {
-                #[cfg(target_os = "windows")]
-                {
-                    self.device_provider = Some(Box::new(AudioGraphProvider::default()));
-                }
-                #[cfg(not(target_os = "windows"))]
-                {
-                    warn!("win_audiograph is not supported on this platform");
-                    warn!("Falling back to CPAL");
-                    self.device_provider = Some(Box::new(CpalProvider::default()));
-                }
-            }
-            "cpal" => {
-                self.device_provider = Some(Box::new(CpalProvider::default()));
-            }
-            "dummy" => {
-                self.device_provider = Some(Box::new(DummyDeviceProvider::new()));
-            }
-            _ => {
-                warn!("Unknown device provider: {}", requested_device_provider);
-                warn!("Falling back to CPAL");
-                self.device_provider = Some(Box::new(CpalProvider::default()));
-            }
+        match device_provider_override() {
+            Some(requested) => self.use_device_provider(requested, true),
+            None => self.use_device_provider(self.playback_settings.device_provider, false),
         }
 
         self.media_provider = Some(Box::new(SymphoniaProvider));
@@ -250,12 +798,15 @@ impl PlaybackThread {
 
     /// Start command intake and audio playback loop.
     pub fn main_loop(&mut self) {
+        self.check_suspend();
+        self.check_devices();
         self.command_intake();
+        self.check_sleep_timer();
 
         if self.state == PlaybackState::Playing {
             self.play_audio();
         } else {
-            sleep(std::time::Duration::from_millis(10));
+            sleep(Duration::from_millis(10));
         }
 
         self.broadcast_events();
@@ -266,24 +817,63 @@ impl PlaybackThread {
         let Some(stream) = &mut self.media_stream else {
             return;
         };
-        if !stream.metadata_updated() {
+        // the track's metadata is read when it's opened (for its ReplayGain tags), which clears
+        // the update flag, so the first metadata of each track is always sent
+        if !stream.metadata_updated() && self.last_metadata.is_some() {
             return;
         }
-        // TODO: proper error handling
-        let metadata = Box::new(
-            stream
-                .read_metadata()
-                .expect("failed to get metadata")
-                .clone(),
-        );
-        self.events_tx
-            .send(PlaybackEvent::MetadataUpdate(metadata))
-            .expect("unable to send event");
 
-        let image = stream.read_image().expect("failed to decode image");
-        self.events_tx
-            .send(PlaybackEvent::AlbumArtUpdate(image))
-            .expect("unable to send event");
+        // the update flag stays set until the metadata is read, so a skipped update is picked up
+        // once the interval has passed
+        if self
+            .last_metadata_read
+            .is_some_and(|last| last.elapsed() < METADATA_READ_INTERVAL)
+        {
+            return;
+        }
+        self.last_metadata_read = Some(Instant::now());
+
+        // both only fail if the decoder has gotten into a state it can't play from
+        let (metadata, image) = match (stream.read_metadata().cloned(), stream.read_image()) {
+            (Ok(metadata), Ok(image)) => (metadata, image),
+            (Err(err), _) | (_, Err(err)) => {
+                self.abandon_track(format!("Couldn't read the current track's tags: {err}"));
+                return;
+            }
+        };
+
+        if self.last_metadata.as_ref() != Some(&metadata) {
+            self.events_tx
+                .send(PlaybackEvent::MetadataUpdate(Box::new(metadata.clone())))
+                .expect("unable to send event");
+
+            let replay_gain = replay_gain(
+                Some(&metadata),
+                self.track_loudness,
+                &self.playback_settings,
+            );
+            self.last_metadata = Some(metadata);
+            if replay_gain != self.replay_gain {
+                self.replay_gain = replay_gain;
+                self.apply_volume();
+            }
+        }
+
+        let fingerprint = image.as_deref().map(ImageFingerprint::new);
+        if self.last_image != Some(fingerprint) {
+            self.last_image = Some(fingerprint);
+            self.events_tx
+                .send(PlaybackEvent::AlbumArtUpdate(image))
+                .expect("unable to send event");
+        }
+    }
+
+    /// Forgets the metadata and album art sent for the previous track, so that the next track's
+    /// are always sent.
+    fn reset_metadata_state(&mut self) {
+        self.last_metadata = None;
+        self.last_image = None;
+        self.last_metadata_read = None;
     }
 
     /// Read incoming commands from the command channel, and process them.
@@ -291,12 +881,20 @@ impl PlaybackThread {
         while let Ok(command) = self.commands_rx.try_recv() {
             match command {
                 PlaybackCommand::Play => self.play(),
-                PlaybackCommand::Pause => self.pause(),
+                PlaybackCommand::Pause => self.start_fade_out(FadeOutAction::Pause),
                 PlaybackCommand::TogglePlayPause => self.toggle_play_pause(),
                 PlaybackCommand::Open(path) => {
                     if let Err(err) = self.open(&path) {
-                        // todo: send error to the events channel, to display on the UI.
                         error!(path = %path.display(), ?err, "Failed to open media: {err}");
+
+                        // problems with the device or the track's channels are reported by open
+                        // itself
+                        if matches!(err, PlaybackStartError::MediaError(_)) {
+                            self.send_error(
+                                PlaybackErrorKind::Track,
+                                format!("Couldn't play '{}': {err}", path.display()),
+                            );
+                        }
                     }
                 }
                 PlaybackCommand::Queue(v) => self.queue(&v),
@@ -311,12 +909,43 @@ impl PlaybackThread {
                 PlaybackCommand::Jump(v) => self.jump(v),
                 PlaybackCommand::JumpUnshuffled(v) => self.jump_unshuffled(v),
                 PlaybackCommand::Seek(v) => self.seek(v),
+                PlaybackCommand::SeekForward(v) => self.seek_by(v),
+                PlaybackCommand::SeekBackward(v) => self.seek_by(-v),
                 PlaybackCommand::SetVolume(v) => self.set_volume(v),
+                PlaybackCommand::VolumeUp(step) => self.step_volume(step),
+                PlaybackCommand::VolumeDown(step) => self.step_volume(-step),
+                PlaybackCommand::ToggleMute => self.set_muted(!self.muted),
+                PlaybackCommand::SetTrackGain(path, gain) => self.set_track_gain(&path, gain),
+                PlaybackCommand::SetCrossfeed(level) => self.crossfeed.set_level(level),
+                PlaybackCommand::SetEqualizer(settings) => self.equalizer.set_settings(settings),
+                PlaybackCommand::SetMonoDownmix(mono) => self.mono_downmix = mono,
+                PlaybackCommand::SetDither(dither) => self.set_dither(dither),
+                PlaybackCommand::SetBalance(balance) => self.set_balance(balance),
+                PlaybackCommand::SetCrossfade(secs) => {
+                    self.crossfade_secs = secs.clamp(0.0, MAX_CROSSFADE_SECS);
+                }
+                PlaybackCommand::SetPauseAtAlbumBoundaries(pause) => {
+                    self.playback_settings.pause_at_album_boundaries = pause;
+                }
+                PlaybackCommand::SetReplayGain(mode, fallback) => {
+                    self.set_replaygain(mode, fallback)
+                }
+                PlaybackCommand::SetSpeed(speed) => self.set_speed(speed),
+                PlaybackCommand::SetLoopRegion(region) => self.set_loop_region(region),
+                PlaybackCommand::SetSleepTimer(timer) => self.set_sleep_timer(timer),
+                PlaybackCommand::SetDeviceProvider(kind) => self.set_device_provider(kind),
+                PlaybackCommand::SetDevice(uid) => self.set_device(uid),
+                PlaybackCommand::SetExclusive(exclusive) => self.set_exclusive(exclusive),
+                PlaybackCommand::ListDevices => self.list_devices(),
+                PlaybackCommand::ToggleStopAfterCurrent => {
+                    self.set_stop_after_current(!self.stop_after_current)
+                }
                 PlaybackCommand::ReplaceQueue(v) => self.replace_queue(v),
-                PlaybackCommand::Stop => self.stop(),
+                PlaybackCommand::Stop => self.start_fade_out(FadeOutAction::Stop),
                 PlaybackCommand::ToggleShuffle => self.toggle_shuffle(),
                 PlaybackCommand::SetRepeat(v) => self.set_repeat(v),
                 PlaybackCommand::RemoveItem(idx) => self.remove(idx),
+                PlaybackCommand::RemoveItems(indices) => self.remove_items(indices),
                 PlaybackCommand::MoveItem { from, to } => self.move_item(from, to),
             }
         }
@@ -324,15 +953,19 @@ impl PlaybackThread {
 
     /// Pause playback.
     pub fn pause(&mut self) {
+        self.ramp = None;
+        self.fade_out = None;
+
         if self.state == PlaybackState::Paused {
             return;
         }
 
         if self.state == PlaybackState::Playing {
             if let Some(stream) = &mut self.stream {
-                // stream is being played right now which means it has to be valid
-                // this is fine
-                stream.pause().expect("unable to pause stream");
+                // the device stops being given samples either way, so it runs dry and goes quiet
+                if let Err(err) = stream.pause() {
+                    warn!(?err, "Failed to pause the stream: {err}");
+                }
             }
 
             self.state = PlaybackState::Paused;
@@ -345,10 +978,22 @@ impl PlaybackThread {
 
     /// Resume playback. If the last track was the end of the queue, the queue will be restarted.
     pub fn play(&mut self) {
+        // playback hasn't been paused yet, so it only has to fade back in
+        if self.fade_out.take().is_some() {
+            self.start_fade_in();
+            return;
+        }
+
         if self.state == PlaybackState::Playing {
             return;
         }
 
+        // there's nothing to play on yet, so playback starts once a device is found
+        if self.device_lost.is_some() {
+            self.device_lost = Some(true);
+            return;
+        }
+
         if self.state == PlaybackState::Paused {
             if self.stream.is_some() {
                 if self.pending_reset {
@@ -360,7 +1005,11 @@ impl PlaybackThread {
                             "Failed to reset stream, recreating device instead... {:?}",
                             err
                         );
-                        self.recreate_stream(true, self.format.map(|v| v.channels));
+                        if !self.recreate_stream(true, self.format.map(|v| v.channels)) {
+                            // if the device is missing, it's played on once it's back
+                            self.device_lost = self.device_lost.map(|_| true);
+                            return;
+                        }
                     }
 
                     self.pending_reset = false;
@@ -372,20 +1021,25 @@ impl PlaybackThread {
                         "Failed to restart playback, recreating device and retrying... {:?}",
                         err
                     );
-                    self.recreate_stream(true, self.format.map(|v| v.channels));
+                    if !self.recreate_stream(true, self.format.map(|v| v.channels)) {
+                        self.device_lost = self.device_lost.map(|_| true);
+                        return;
+                    }
                     let final_result = self.stream.as_mut().unwrap().play();
 
-                    if final_result.is_err() {
-                        error!("Failed to start playback after recreation");
-                        error!("This likely indicates a problem with the audio device or driver");
-                        error!("(or an underlying issue in the used DeviceProvider)");
-                        error!("Please check your audio setup and try again.");
-                        panic!("Failed to submit frame after recreation");
+                    if let Err(err) = final_result {
+                        error!(?err, "Failed to start playback after recreation: {err}");
+                        self.lose_device();
+                        self.device_lost = Some(true);
+                        return;
                     }
                 }
             }
 
             self.state = PlaybackState::Playing;
+            self.watchdog.reset();
+            self.underruns.reset();
+            self.start_fade_in();
 
             self.events_tx
                 .send(PlaybackEvent::StateChanged(PlaybackState::Playing))
@@ -401,10 +1055,8 @@ impl PlaybackThread {
             if let Err(err) = self.open(&path) {
                 error!(path = %path.display(), ?err, "Unable to open file: {err}");
             }
-            self.events_tx
-                .send(PlaybackEvent::QueuePositionChanged(0))
-                .expect("unable to send event");
             self.queue_next = 1;
+            self.send_queue_position();
         }
 
         // nothing to play, womp womp
@@ -417,6 +1069,19 @@ impl PlaybackThread {
         if let Some(mut old_stream) = self.media_stream.take() {
             old_stream.close().ok();
         }
+        self.cancel_crossfade();
+        self.crossfade_skipped = false;
+        self.reset_metadata_state();
+        self.set_loop_region(None);
+
+        if let Some(detector) = &mut self.silence_detector {
+            detector.reset();
+        }
+
+        if let Some(detector) = &mut self.lead_in_detector {
+            detector.reset();
+        }
+        self.in_lead_in = self.lead_in_detector.is_some();
 
         let mut recreation_required = false;
 
@@ -428,7 +1093,8 @@ impl PlaybackThread {
             recreation_required = true;
         }
 
-        if let Some(stream) = self.stream.as_mut()
+        if !self.open_paused
+            && let Some(stream) = self.stream.as_mut()
             && let Err(err) = stream.play()
         {
             warn!("Failed to reset device, forcing recreation: {:?}", err);
@@ -440,11 +1106,11 @@ impl PlaybackThread {
         })?;
 
         self.resampler = None;
-        let src = std::fs::File::open(path)
+        let src = open_source(path)
             .map_err(|e| PlaybackStartError::MediaError(format!("Unable to open file: {}", e)))?;
 
         let mut media_stream = provider
-            .open(src, None)
+            .open(src, path.extension())
             .map_err(|e| PlaybackStartError::MediaError(format!("Unable to open file: {}", e)))?;
 
         media_stream.start_playback().map_err(|e| {
@@ -476,11 +1142,34 @@ impl PlaybackThread {
             recreation_required = true;
         }
 
+        self.source_format = SourceFormat::of(media_stream.as_ref());
+
+        if !recreation_required && self.needs_new_format() {
+            info!("Track's format differs from the stream's, re-opening in exclusive mode");
+            recreation_required = true;
+        }
+
         self.events_tx
             .send(PlaybackEvent::SongChanged(path.to_owned()))
             .expect("unable to send event");
 
-        if let Ok(duration) = media_stream.duration_secs() {
+        self.current_path = Some(path.to_owned());
+        self.track_gain = match crate::RUNTIME.block_on(get_track_gain_offset(&self.pool, path)) {
+            Ok(gain) => gain.unwrap_or_default(),
+            Err(err) => {
+                warn!("Failed to look up volume adjustment for track: {:?}", err);
+                0.0
+            }
+        };
+        self.track_loudness = self.track_loudness(path);
+        self.replay_gain = replay_gain(
+            media_stream.read_metadata().ok(),
+            self.track_loudness,
+            &self.playback_settings,
+        );
+        self.apply_volume();
+
+        if let Ok(duration) = media_stream.duration_millis() {
             self.events_tx
                 .send(PlaybackEvent::DurationChanged(duration))
                 .expect("unable to send event");
@@ -491,20 +1180,47 @@ impl PlaybackThread {
         }
 
         if recreation_required {
-            self.recreate_stream(true, Some(channels));
-            let play_result = self.stream.as_mut().unwrap().play();
+            if !self.recreate_stream(true, Some(channels)) {
+                return Err(PlaybackStartError::StreamError(
+                    "Unable to open the audio device".to_owned(),
+                ));
+            }
+
+            if !self.open_paused {
+                let play_result = self.stream.as_mut().unwrap().play();
 
-            if play_result.is_err() {
-                error!("Device was recreated and we still can't play");
-                panic!("couldn't play device")
+                if let Err(err) = play_result {
+                    error!(?err, "Device was recreated and we still can't play: {err}");
+                    self.send_error(
+                        PlaybackErrorKind::Device,
+                        format!("Couldn't play on the audio device: {err}"),
+                    );
+                    return Err(PlaybackStartError::StreamError(
+                        "Unable to play on the audio device".to_owned(),
+                    ));
+                }
             }
         }
 
-        self.media_stream = Some(media_stream);
-        self.state = PlaybackState::Playing;
+        self.send_bit_perfect();
 
+        // the device may not have been opened with the track's channels
+        self.channel_mixer = self.channel_mixer_for(channels).inspect_err(|err| {
+            self.send_error(
+                PlaybackErrorKind::Track,
+                format!("Couldn't play '{}': {err}", path.display()),
+            );
+        })?;
+
+        self.media_stream = Some(media_stream);
         self.update_ts();
 
+        // the track stays paused at its start, so that resuming plays it from the beginning
+        if self.open_paused {
+            return Ok(());
+        }
+
+        self.state = PlaybackState::Playing;
         self.events_tx
             .send(PlaybackEvent::StateChanged(PlaybackState::Playing))
             .expect("unable to send event");
@@ -513,15 +1229,94 @@ impl PlaybackThread {
     }
 
     /// Skip to the next track in the queue.
+    ///
+    /// Repeat-one only applies when a track ends on its own. Skipping with the next button always
+    /// moves on, and the next track is then repeated instead.
+    ///
+    /// If the user paused just before the track ended on its own (so that it finished while it
+    /// was fading out), the next track is opened but left paused at its start. The same happens
+    /// when the track that ended finished an album, if playback pauses between albums.
     fn next(&mut self, user_initiated: bool) {
+        if self.stop_after_current {
+            self.set_stop_after_current(false);
+
+            if !user_initiated {
+                info!("Stopping after the current track, as requested");
+                self.stop();
+                return;
+            }
+        }
+
+        let paused = match next_track(user_initiated, self.fade_out, self.state) {
+            NextTrackAction::Play => false,
+            NextTrackAction::OpenPaused => true,
+            NextTrackAction::Stop => {
+                info!("Track ended while stopping, not moving on");
+                self.stop();
+                return;
+            }
+        };
+
+        // worked out before moving on, since repeating the queue can reshuffle it
+        let boundary = if user_initiated || paused {
+            None
+        } else {
+            self.album_boundary()
+        };
+
+        if paused {
+            info!("Track ended while pausing, opening the next one paused");
+            // finishes the fade-out early, since the rest of it would be the next track
+            self.pause();
+            self.open_paused = true;
+        } else if boundary.is_some() {
+            info!("Reached the end of an album, opening the next track paused");
+            self.pause();
+            self.open_paused = true;
+        }
+
+        self.advance(user_initiated);
+        self.open_paused = false;
+
+        if let Some(finished) = boundary {
+            let queue = self.queue.read().expect("couldn't get the queue");
+            let next = self
+                .current_index()
+                .and_then(|idx| queue.get(idx))
+                .and_then(QueueItemData::get_db_album_id);
+            drop(queue);
+
+            self.events_tx
+                .send(PlaybackEvent::PausedAtAlbumBoundary { finished, next })
+                .expect("unable to send event");
+        }
+
+        // a sleep timer waiting for the end of the track goes off once it has ended on its own
+        if !user_initiated
+            && self
+                .sleep_timer
+                .as_ref()
+                .is_some_and(ActiveSleepTimer::waits_for_track_end)
+        {
+            self.finish_sleep_timer();
+        }
+    }
+
+    /// Opens the next track in the queue, or repeats the current one or the whole queue, for
+    /// [PlaybackThread::next].
+    fn advance(&mut self, user_initiated: bool) {
         let mut queue = self.queue.write().expect("couldn't get the queue");
 
-        if self.repeat == RepeatState::RepeatingOne {
+        if let Some(current) =
+            track_to_repeat(self.repeat, user_initiated, self.queue_next, queue.len())
+                .and_then(|idx| queue.get(idx))
+        {
             info!("Repeating current track");
-            let path = queue[self.queue_next - 1].get_path().clone();
+            let path = current.get_path().clone();
             drop(queue);
             if let Err(err) = self.open(&path) {
                 error!(path = %path.display(), ?err, "Unable to open file: {err}");
+                self.skip_failed_track();
             }
             return;
         }
@@ -530,27 +1325,29 @@ impl PlaybackThread {
             info!("Opening next file in queue");
             let path = queue[self.queue_next].get_path().clone();
             drop(queue);
-            if let Err(err) = self.open(&path) {
+            let result = self.open(&path);
+            self.queue_next += 1;
+            self.send_queue_position();
+
+            if let Err(err) = result {
                 error!(path = %path.display(), ?err, "Unable to open file: {err}");
+                self.skip_failed_track();
             }
-            self.events_tx
-                .send(PlaybackEvent::QueuePositionChanged(self.queue_next))
-                .expect("unable to send event");
-            self.queue_next += 1;
         } else if !user_initiated {
             if self.repeat == RepeatState::Repeating {
                 info!("End of queue reached, repeating.");
 
-                if self.shuffle {
+                let reshuffled = self.shuffle;
+                if reshuffled {
                     queue.shuffle(&mut rng());
-
-                    self.events_tx
-                        .send(PlaybackEvent::QueueUpdated)
-                        .expect("unable to send event");
                 }
 
                 drop(queue);
                 self.jump(0);
+
+                if reshuffled {
+                    self.send_queue_updated();
+                }
             } else {
                 info!("Playback queue is empty, stopping playback");
                 drop(queue);
@@ -559,11 +1356,27 @@ impl PlaybackThread {
         }
     }
 
+    /// Returns the album that's finishing, if playback should pause when the current track ends on
+    /// its own because the next one is from a different album (see [finished_album]).
+    fn album_boundary(&self) -> Option<i64> {
+        if !self.playback_settings.pause_at_album_boundaries {
+            return None;
+        }
+
+        let queue = self.queue.read().expect("couldn't get the queue");
+        let current = self.current_index().and_then(|idx| queue.get(idx))?;
+        let next = queue
+            .get(self.queue_next)
+            .map(QueueItemData::get_db_album_id);
+
+        finished_album(current.get_db_album_id(), next, self.repeat)
+    }
+
     /// Skip to the previous track in the queue.
     fn previous(&mut self) {
         if self.state == PlaybackState::Playing
             && self.playback_settings.prev_track_jump_first
-            && self.last_timestamp > 5
+            && self.last_timestamp > 5000
         {
             self.seek(0_f64);
             return;
@@ -579,19 +1392,13 @@ impl PlaybackThread {
             if let Err(err) = self.open(&path) {
                 error!(path = %path.display(), ?err, "Unable to open file: {err}");
             }
-            let new_position = self.queue_next - 1;
-            self.events_tx
-                .send(PlaybackEvent::QueuePositionChanged(new_position))
-                .expect("unable to send event");
+            self.send_queue_position();
         } else if self.queue_next > 1 {
             info!("Opening previous file in queue");
             let path = queue[self.queue_next - 2].get_path().clone();
             drop(queue);
-            let new_position = self.queue_next - 2;
-            self.events_tx
-                .send(PlaybackEvent::QueuePositionChanged(new_position))
-                .expect("unable to send event");
             self.queue_next -= 1;
+            self.send_queue_position();
             debug!("queue_next: {}", self.queue_next);
 
             if let Err(err) = self.open(&path) {
@@ -600,8 +1407,44 @@ impl PlaybackThread {
         }
     }
 
+    /// If duplicate queue entries are to be avoided, removes the items that are already in the
+    /// queue (or earlier in `items`) and tells the UI how many were skipped. Otherwise, returns
+    /// the items unchanged.
+    ///
+    /// The shuffled queue and [`Self::original_queue`] always hold the same items, so only the
+    /// queue has to be checked.
+    fn skip_duplicates(&self, items: Vec<QueueItemData>) -> Vec<QueueItemData> {
+        if !self.playback_settings.avoid_duplicate_queue_entries {
+            return items;
+        }
+
+        let total = items.len();
+        let items = unqueued(
+            &self.queue.read().expect("couldn't get the queue"),
+            items,
+            |item| item.get_path().as_path(),
+        );
+
+        let skipped = total - items.len();
+        if skipped > 0 {
+            info!("Skipped {skipped} items that were already in the queue");
+            self.events_tx
+                .send(PlaybackEvent::DuplicatesSkipped {
+                    added: items.len(),
+                    skipped,
+                })
+                .expect("unable to send event");
+        }
+
+        items
+    }
+
     /// Add a new [`QueueItemData`] to the queue. If nothing is playing, start playing it.
     fn queue(&mut self, item: &QueueItemData) {
+        if self.skip_duplicates(vec![item.clone()]).is_empty() {
+            return;
+        }
+
         info!("Adding file to queue: {}", item);
 
         let mut queue = self.queue.write().expect("couldn't get the queue");
@@ -622,14 +1465,9 @@ impl PlaybackThread {
                 error!(path = %path.display(), ?err, "Unable to open file: {err}");
             }
             self.queue_next = pre_len + 1;
-            self.events_tx
-                .send(PlaybackEvent::QueuePositionChanged(pre_len))
-                .expect("unable to send event");
         }
 
-        self.events_tx
-            .send(PlaybackEvent::QueueUpdated)
-            .expect("unable to send event");
+        self.send_queue_updated();
     }
 
     /// Move an item from one position to another in the queue.
@@ -648,65 +1486,75 @@ impl PlaybackThread {
         queue.insert(to, item);
         drop(queue);
 
-        let current_playing = if self.queue_next > 0 {
-            self.queue_next - 1
-        } else {
-            0
-        };
-
-        if from == current_playing {
-            self.queue_next = to + 1;
-            self.events_tx
-                .send(PlaybackEvent::QueuePositionChanged(to))
-                .expect("unable to send event");
-        } else if from < current_playing && to >= current_playing {
-            // before to after current
-            self.queue_next -= 1;
-            self.events_tx
-                .send(PlaybackEvent::QueuePositionChanged(self.queue_next - 1))
-                .expect("unable to send event");
-        } else if from > current_playing && to <= current_playing {
-            self.queue_next += 1;
-            self.events_tx
-                .send(PlaybackEvent::QueuePositionChanged(self.queue_next - 1))
-                .expect("unable to send event");
+        if let Some(current) = self.current_index() {
+            if from == current {
+                self.queue_next = to + 1;
+            } else if from < current && to >= current {
+                // before to after current
+                self.queue_next -= 1;
+            } else if from > current && to <= current {
+                self.queue_next += 1;
+            }
         }
 
-        self.events_tx
-            .send(PlaybackEvent::QueueUpdated)
-            .expect("unable to send event");
+        self.send_queue_updated();
     }
 
-    /// Remove an item from the queue.
+    /// Remove an item from the queue. If it's the current track, playback continues with the one
+    /// that followed it.
     fn remove(&mut self, idx: usize) {
+        self.remove_items(vec![idx]);
+    }
+
+    /// Remove several items from the queue in one pass. If the current track is removed, playback
+    /// continues with whatever followed the last removed item before it.
+    fn remove_items(&mut self, indices: Vec<usize>) {
         let mut queue = self.queue.write().expect("couldn't get the queue");
-        queue.remove(idx);
-        drop(queue);
 
-        self.events_tx
-            .send(PlaybackEvent::QueueUpdated)
-            .expect("unable to send event");
+        let QueueRemoval {
+            indices,
+            removed_current,
+            removed_before_next,
+        } = QueueRemoval::new(indices, queue.len(), self.queue_next);
 
-        if idx == 0 {
-            self.jump(0);
+        if indices.is_empty() {
+            return;
         }
 
-        if idx < self.queue_next {
-            self.queue_next -= 1;
+        // removing from the back keeps the indices that are still to be removed valid
+        for idx in indices.into_iter().rev() {
+            let item = queue.remove(idx);
+
+            if self.shuffle
+                && let Some(original) = self.original_queue.iter().position(|x| *x == item)
+            {
+                self.original_queue.remove(original);
+            }
+        }
+
+        let queue_len = queue.len();
+        drop(queue);
+
+        self.queue_next -= removed_before_next;
 
-            if idx == self.queue_next {
+        if removed_current {
+            if self.queue_next < queue_len {
                 self.next(true);
             } else {
-                self.events_tx
-                    .send(PlaybackEvent::QueuePositionChanged(self.queue_next - 1))
-                    .expect("unable to send event");
+                self.stop();
             }
         }
+
+        self.send_queue_updated();
     }
 
     /// Insert a [`QueueItemData`] at the specified position in the queue.
     /// If nothing is playing, start playing it.
     fn insert_at(&mut self, item: &QueueItemData, position: usize) {
+        if self.skip_duplicates(vec![item.clone()]).is_empty() {
+            return;
+        }
+
         info!("Inserting file to queue at position {}: {}", position, item);
 
         let mut queue = self.queue.write().expect("couldn't get the queue");
@@ -722,9 +1570,6 @@ impl PlaybackThread {
 
         if insert_pos < self.queue_next {
             self.queue_next += 1;
-            self.events_tx
-                .send(PlaybackEvent::QueuePositionChanged(self.queue_next - 1))
-                .expect("unable to send event");
         }
 
         if self.state == PlaybackState::Stopped {
@@ -734,19 +1579,15 @@ impl PlaybackThread {
                 error!(path = %path.display(), ?err, "Unable to open file: {err}");
             }
             self.queue_next = insert_pos + 1;
-            self.events_tx
-                .send(PlaybackEvent::QueuePositionChanged(insert_pos))
-                .expect("unable to send event");
         }
 
-        self.events_tx
-            .send(PlaybackEvent::QueueUpdated)
-            .expect("unable to send event");
+        self.send_queue_updated();
     }
 
     /// Insert a list of [`QueueItemData`] at the specified position in the queue.
     /// If nothing is playing, start playing the first track.
-    fn insert_list_at(&mut self, mut items: Vec<QueueItemData>, position: usize) {
+    fn insert_list_at(&mut self, items: Vec<QueueItemData>, position: usize) {
+        let mut items = self.skip_duplicates(items);
         if items.is_empty() {
             return;
         }
@@ -774,10 +1615,6 @@ impl PlaybackThread {
 
         if insert_pos < self.queue_next {
             self.queue_next += items_len;
-
-            self.events_tx
-                .send(PlaybackEvent::QueuePositionChanged(self.queue_next - 1))
-                .expect("unable to send event");
         }
 
         if self.state == PlaybackState::Stopped
@@ -789,19 +1626,19 @@ impl PlaybackThread {
                 error!(path = %path.display(), ?err, "Unable to open file: {err}");
             }
             self.queue_next = insert_pos + 1;
-            self.events_tx
-                .send(PlaybackEvent::QueuePositionChanged(insert_pos))
-                .expect("unable to send event");
         }
 
-        self.events_tx
-            .send(PlaybackEvent::QueueUpdated)
-            .expect("unable to send event");
+        self.send_queue_updated();
     }
 
     /// Add a list of [`QueueItemData`] to the queue. If nothing is playing, start playing the
     /// first track.
-    fn queue_list(&mut self, mut paths: Vec<QueueItemData>) {
+    fn queue_list(&mut self, paths: Vec<QueueItemData>) {
+        let mut paths = self.skip_duplicates(paths);
+        if paths.is_empty() {
+            return;
+        }
+
         info!("Adding files to queue: {:?}", paths);
 
         let mut queue = self.queue.write().expect("couldn't get the queue");
@@ -831,22 +1668,18 @@ impl PlaybackThread {
                 error!(path = %path.display(), ?err, "Unable to open file: {err}");
             }
             self.queue_next = pre_len + 1;
-            self.events_tx
-                .send(PlaybackEvent::QueuePositionChanged(pre_len))
-                .expect("unable to send event");
         }
 
-        self.events_tx
-            .send(PlaybackEvent::QueueUpdated)
-            .expect("unable to send event");
+        self.send_queue_updated();
     }
 
-    /// Emit a [`PositionChanged`] event if the timestamp has changed.
+    /// Emit a [`PositionChanged`] event if the timestamp has moved on to another second. Smaller
+    /// changes aren't sent, so that the position isn't reported for every frame.
     fn update_ts(&mut self) {
         if let Some(stream) = &self.media_stream
-            && let Ok(timestamp) = stream.position_secs()
+            && let Ok(timestamp) = stream.position_millis()
         {
-            if timestamp == self.last_timestamp {
+            if timestamp / 1000 == self.last_timestamp / 1000 {
                 return;
             }
 
@@ -860,11 +1693,69 @@ impl PlaybackThread {
 
     /// Seek to the specified timestamp (in seconds).
     fn seek(&mut self, timestamp: f64) {
-        if let Some(stream) = &mut self.media_stream {
-            stream.seek(timestamp).expect("unable to seek");
-            self.pending_reset = true;
-            self.update_ts();
+        let position = if let Some(stream) = &mut self.media_stream {
+            match stream.seek(timestamp) {
+                Ok(()) => {
+                    self.pending_reset = true;
+
+                    self.cancel_crossfade();
+                    self.crossfade_skipped = true;
+
+                    if let Some(detector) = &mut self.silence_detector {
+                        detector.reset();
+                    }
+                    // seeking manually within the silence at the start keeps it from being skipped
+                    self.in_lead_in = false;
+
+                    self.update_ts();
+
+                    self.media_stream
+                        .as_ref()
+                        .and_then(|stream| stream.position_millis().ok())
+                }
+                Err(err) => {
+                    warn!(?err, "Unable to seek to {timestamp}: {err}");
+                    None
+                }
+            }
+        } else {
+            None
+        };
+
+        self.events_tx
+            .send(PlaybackEvent::SeekCompleted(position))
+            .expect("unable to send event");
+    }
+
+    /// Seeks past the silence at the start of the current track, to `position` seconds. Unlike a
+    /// seek the user asked for, this doesn't keep the track from crossfading into the next one.
+    fn seek_past_lead_in(&mut self, position: f64) {
+        self.seek(position);
+        self.crossfade_skipped = false;
+    }
+
+    /// Seek by the specified number of seconds from the current position, backward if negative.
+    /// Seeking back past the start of the track restarts it, and seeking forward past its end
+    /// moves on as if the track had finished playing.
+    fn seek_by(&mut self, offset: f64) {
+        let Some(stream) = &self.media_stream else {
+            return;
+        };
+        let Ok(position) = stream.position_millis() else {
+            return;
+        };
+
+        let target = position as f64 / 1000.0 + offset;
+
+        if let Ok(duration) = stream.duration_millis()
+            && target >= duration as f64 / 1000.0
+        {
+            debug!("Seeked past the end of the track, moving on");
+            self.next(false);
+            return;
         }
+
+        self.seek(target.max(0.0));
     }
 
     /// Jump to the specified index in the queue.
@@ -879,9 +1770,7 @@ impl PlaybackThread {
                 error!(path = %path.display(), ?err, "Unable to open file: {err}");
             }
             self.queue_next = index + 1;
-            self.events_tx
-                .send(PlaybackEvent::QueuePositionChanged(index))
-                .expect("unable to send event");
+            self.send_queue_position();
         }
     }
 
@@ -894,8 +1783,10 @@ impl PlaybackThread {
         }
 
         let queue = self.queue.read().expect("couldn't get the queue");
-        let path = self.original_queue[index].get_path();
-        let pos = queue.iter().position(|a| a.get_path() == path);
+        let pos = self
+            .original_queue
+            .get(index)
+            .and_then(|item| queue.iter().position(|a| a.get_path() == item.get_path()));
         drop(queue);
 
         if let Some(pos) = pos {
@@ -909,6 +1800,13 @@ impl PlaybackThread {
 
         let mut queue = self.queue.write().expect("couldn't get the queue");
 
+        // items that are being queued again keep their data
+        let kept: FxHashSet<EntityId> = paths.iter().map(QueueItemData::data_id).collect();
+        let removed: Vec<QueueItemData> = queue
+            .drain(..)
+            .filter(|item| !kept.contains(&item.data_id()))
+            .collect();
+
         if self.shuffle {
             let mut shuffled_paths = paths.clone();
             shuffled_paths.shuffle(&mut rng());
@@ -925,31 +1823,122 @@ impl PlaybackThread {
         self.queue_next = 0;
         self.jump(0);
 
-        self.events_tx
-            .send(PlaybackEvent::QueueUpdated)
-            .expect("unable to send event");
+        self.send_queue_updated();
+        self.send_removed_items(removed);
     }
 
     /// Clear the current queue.
     fn clear_queue(&mut self) {
-        self.queue.write().expect("couldn't get the queue").clear();
+        let removed = std::mem::take(&mut *self.queue.write().expect("couldn't get the queue"));
         self.original_queue = Vec::new();
         self.queue_next = 0;
 
+        self.send_queue_updated();
+        self.send_removed_items(removed);
+    }
+
+    /// The index of the current track in the queue, or None if nothing in it has been played
+    /// since it was replaced or cleared.
+    fn current_index(&self) -> Option<usize> {
+        self.queue_next.checked_sub(1)
+    }
+
+    /// Tells the UI which item in the queue is the current one. The playback thread is the only
+    /// place this is worked out, so this is sent after anything that may have moved it.
+    fn send_queue_position(&self) {
         self.events_tx
-            .send(PlaybackEvent::QueuePositionChanged(0))
-            .unwrap();
-        self.events_tx.send(PlaybackEvent::QueueUpdated).unwrap();
+            .send(PlaybackEvent::QueuePositionChanged(self.current_index()))
+            .expect("unable to send event");
+    }
+
+    /// Tells the UI that the queue has changed, followed by where the current item now is.
+    fn send_queue_updated(&self) {
+        self.events_tx
+            .send(PlaybackEvent::QueueUpdated)
+            .expect("unable to send event");
+        self.send_queue_position();
+    }
+
+    /// Hands the items that were removed from the queue back to the UI, so that their data
+    /// (including any album art) is dropped straight away, rather than whenever the views that
+    /// showed them happen to be released.
+    fn send_removed_items(&self, removed: Vec<QueueItemData>) {
+        if removed.is_empty() {
+            return;
+        }
+
+        self.events_tx
+            .send(PlaybackEvent::QueueItemsRemoved(removed))
+            .expect("unable to send event");
+    }
+
+    /// Fades playback out, and then pauses or stops it. If it isn't playing, or fading is turned
+    /// off, it's paused or stopped straight away.
+    fn start_fade_out(&mut self, action: FadeOutAction) {
+        let millis = self.playback_settings.pause_fade_ms;
+        let format = self
+            .format
+            .filter(|_| self.state == PlaybackState::Playing && millis > 0);
+
+        let Some(format) = format else {
+            self.finish_fade_out(action);
+            return;
+        };
+
+        if let Some(pending) = &mut self.fade_out {
+            // stopping takes precedence over pausing
+            if action == FadeOutAction::Stop {
+                *pending = action;
+            }
+            return;
+        }
+
+        let from = self.ramp.as_ref().map_or(1.0, VolumeRamp::gain);
+        self.ramp = Some(VolumeRamp::new(from, 0.0, millis, format.sample_rate));
+        self.fade_out = Some(action);
+    }
+
+    fn finish_fade_out(&mut self, action: FadeOutAction) {
+        match action {
+            FadeOutAction::Pause => self.pause(),
+            FadeOutAction::Stop => self.stop(),
+        }
+    }
+
+    /// Fades playback in, from wherever a fade-out got to, or from silence after pausing.
+    fn start_fade_in(&mut self) {
+        let millis = self.playback_settings.pause_fade_ms;
+        let from = self.ramp.as_ref().map_or(0.0, VolumeRamp::gain);
+
+        self.ramp = self
+            .format
+            .filter(|_| millis > 0)
+            .map(|format| VolumeRamp::new(from, 1.0, millis, format.sample_rate));
     }
 
     /// Stop the current playback.
     fn stop(&mut self) {
+        self.ramp = None;
+        self.fade_out = None;
+
+        // the media is dropped either way, so failing to close it cleanly doesn't matter
         if let Some(mut stream) = self.media_stream.take() {
-            stream.stop_playback().expect("unable to stop playback");
-            stream.close().expect("unable to close media");
+            if let Err(err) = stream.stop_playback() {
+                warn!(?err, "Failed to stop playback: {err}");
+            }
+            if let Err(err) = stream.close() {
+                warn!(?err, "Failed to close media: {err}");
+            }
         }
+        self.cancel_crossfade();
+        self.reset_metadata_state();
+        self.set_loop_region(None);
         self.state = PlaybackState::Stopped;
 
+        if let Some(resume) = &mut self.device_lost {
+            *resume = false;
+        }
+
         self.events_tx
             .send(PlaybackEvent::StateChanged(PlaybackState::Stopped))
             .expect("unable to send event");
@@ -961,68 +1950,43 @@ impl PlaybackThread {
 
         if self.shuffle {
             // find the current track in the unshuffled queue
-            let index = if self.queue_next > 0 {
-                let path = queue[self.queue_next - 1].get_path();
-                let index = self
+            if let Some(current) = self.current_index() {
+                let path = queue[current].get_path();
+                self.queue_next = self
                     .original_queue
                     .iter()
                     .position(|x| x.get_path() == path)
-                    .unwrap();
-                self.queue_next = index + 1;
-                index
-            } else {
-                0
-            };
+                    .map_or(0, |index| index + 1);
+            }
 
             swap(&mut self.original_queue, &mut queue);
             self.original_queue = Vec::new();
             self.shuffle = false;
             drop(queue);
-
-            self.events_tx
-                .send(PlaybackEvent::ShuffleToggled(false, index))
-                .expect("unable to send event");
-            self.events_tx
-                .send(PlaybackEvent::QueueUpdated)
-                .expect("unable to send event");
-            if index != 0 {
-                self.events_tx
-                    .send(PlaybackEvent::QueuePositionChanged(index))
-                    .expect("unable to send event");
-            }
         } else {
             self.original_queue.clone_from(&queue);
             let length = queue.len();
-            queue[self.queue_next..length].shuffle(&mut rng());
+            queue[self.queue_next.min(length)..length].shuffle(&mut rng());
             self.shuffle = true;
-            let queue_next = self.queue_next;
             drop(queue);
-
-            self.events_tx
-                .send(PlaybackEvent::ShuffleToggled(true, queue_next))
-                .expect("unable to send event");
-            self.events_tx
-                .send(PlaybackEvent::QueueUpdated)
-                .expect("unable to send event");
         }
+
+        self.events_tx
+            .send(PlaybackEvent::ShuffleToggled(
+                self.shuffle,
+                self.current_index(),
+            ))
+            .expect("unable to send event");
+        self.send_queue_updated();
     }
 
     /// Sets the volume of the playback stream.
     fn set_volume(&mut self, volume: f64) {
-        if let Some(stream) = self.stream.as_mut() {
-            let volume_scaled = if volume >= 0.99_f64 {
-                1_f64
-            } else if volume > 0.1 {
-                f64::exp(LN_50 * volume) / 50_f64
-            } else {
-                volume * LINEAR_SCALING_COEFFICIENT
-            };
-
-            self.last_volume = volume_scaled;
-
-            stream
-                .set_volume(volume_scaled)
-                .expect("failed to set volume");
+        if self.stream.is_some() {
+            self.volume = volume;
+            self.last_volume = scale_volume(volume);
+            self.set_muted(false);
+            self.apply_volume();
 
             self.events_tx
                 .send(PlaybackEvent::VolumeChanged(volume))
@@ -1030,169 +1994,2441 @@ impl PlaybackThread {
         }
     }
 
-    /// Sets the repeat mode. The queue will loop infinitely when repeat mode is enabled. When
-    /// both repeat-once and shuffle mode are enabled, the queue will be reshuffled when looped.
-    fn set_repeat(&mut self, state: RepeatState) {
-        self.repeat = if state == RepeatState::NotRepeating && self.playback_settings.always_repeat
-        {
-            RepeatState::Repeating
-        } else {
-            state
-        };
+    /// Moves the volume by `step`, landing on a whole percentage and staying between 0 and 1.
+    fn step_volume(&mut self, step: f64) {
+        let volume = ((self.volume + step) * 100.0).round() / 100.0;
+        self.set_volume(volume.clamp(0.0, 1.0));
+    }
+
+    /// Mutes or unmutes playback. The volume isn't changed, so unmuting puts it back to what it
+    /// was.
+    fn set_muted(&mut self, muted: bool) {
+        if muted == self.muted {
+            return;
+        }
+
+        self.muted = muted;
+        self.apply_volume();
 
         self.events_tx
-            .send(PlaybackEvent::RepeatChanged(state))
+            .send(PlaybackEvent::MuteChanged(muted))
             .expect("unable to send event");
     }
 
-    /// Toggles between play/pause.
-    fn toggle_play_pause(&mut self) {
-        match self.state {
-            PlaybackState::Playing => self.pause(),
-            PlaybackState::Paused => self.play(),
-            _ => {}
+    /// Updates the volume adjustment of a track, applying it if the track is currently open.
+    fn set_track_gain(&mut self, path: &Path, gain: f64) {
+        if self.current_path.as_deref() == Some(path) {
+            self.track_gain = gain;
+            self.apply_volume();
         }
     }
 
-    /// Recreates the playback stream with the given channels if any are provided, otherwise uses
-    /// the device's default channel layout.
-    fn recreate_stream(&mut self, force: bool, channels: Option<ChannelSpec>) {
-        if let Some(mut stream) = self.stream.take() {
-            stream.close_stream().expect("failed to close stream");
-        }
-
-        let Some(device_provider) = self.device_provider.as_mut() else {
-            panic!("playback thread incorrectly initialized")
-        };
+    /// Changes which ReplayGain adjustment is applied, applying it to the current track straight
+    /// away.
+    fn set_replaygain(&mut self, mode: ReplayGainMode, fallback: f64) {
+        self.playback_settings.replaygain = mode;
+        self.playback_settings.replaygain_fallback = fallback;
 
-        let Ok(mut device) = device_provider.get_default_device() else {
-            error!("No playback device found, audio will not play");
-            return;
-        };
+        self.replay_gain = replay_gain(
+            self.last_metadata.as_ref(),
+            self.track_loudness,
+            &self.playback_settings,
+        );
+        self.apply_volume();
+    }
 
-        if self.device.as_ref().and_then(|v| v.get_uid().ok()) == device.get_uid().ok() && !force {
+    /// Changes how fast tracks are played, without restarting the current one. Applies to the
+    /// track being crossfaded in too.
+    fn set_speed(&mut self, speed: f64) {
+        let speed = speed.clamp(MIN_SPEED, MAX_SPEED);
+        if speed == self.speed {
             return;
         }
 
-        let mut format = device
-            .get_default_format()
-            .expect("failed to get device format");
-        let requested = channels.map(|channels| FormatInfo {
-            channels,
-            sample_rate: format.sample_rate,
-            ..format
-        });
-        self.stream.replace(
-            if let Some(req) = requested
-                && let Ok(stream) = device.open_device(req).inspect_err(|e| {
-                    warn!(?format, "Failed to open device with requested format: {e}");
-                    warn!("Falling back to default format");
-                })
-            {
-                format = req;
-                stream
-            } else {
-                device
-                    .open_device(format)
-                    .expect("failed to open device with default format")
-            },
-        );
+        self.speed = speed;
+
+        if let Some(resampler) = &mut self.resampler {
+            resampler.set_speed(speed);
+        }
 
-        self.stream
+        if let Some(next) = self
+            .crossfade
             .as_mut()
-            .unwrap()
-            .set_volume(self.last_volume)
-            .expect("failed to set volume after reset");
+            .and_then(|crossfade| crossfade.next.as_mut())
+        {
+            next.set_speed(speed);
+        }
 
-        self.device = Some(device);
-        info!(
-            "Opened device: {:?}, format: {:?}, rate: {}, channel_count: {}",
-            self.device.as_ref().unwrap().get_name(),
-            format.sample_type,
-            format.sample_rate,
-            format.channels.count()
-        );
+        self.events_tx
+            .send(PlaybackEvent::SpeedChanged(speed))
+            .expect("unable to send event");
+        self.send_bit_perfect();
     }
 
-    /// Uses the current media provider to decode audio samples and sends them to the current
-    /// playback stream.
-    fn play_audio(&mut self) {
-        let Some(stream) = &mut self.stream else {
-            return;
+    /// Changes the dither added when bits are lost converting samples to the device's sample type.
+    fn set_dither(&mut self, dither: DitherMode) {
+        self.playback_settings.dither = dither;
+
+        if let Some(resampler) = &mut self.resampler {
+            resampler.set_dither(dither);
+        }
+    }
+
+    /// Changes the balance between the left and right channels.
+    fn set_balance(&mut self, balance: f64) {
+        let balance = if balance.is_finite() {
+            balance.clamp(-1.0, 1.0)
+        } else {
+            0.0
         };
-        let Some(media_stream) = &mut self.media_stream else {
+        if balance == self.balance {
             return;
+        }
+
+        self.balance = balance;
+        self.events_tx
+            .send(PlaybackEvent::BalanceChanged(balance))
+            .expect("unable to send event");
+    }
+
+    /// Sets (or clears) the section of the current track that's repeated. Regions that end before
+    /// they start, or are shorter than [MIN_LOOP_LENGTH], are ignored.
+    fn set_loop_region(&mut self, region: Option<(f64, f64)>) {
+        let region = match region {
+            Some((start, end)) if start >= 0.0 && end - start >= MIN_LOOP_LENGTH => {
+                Some((start, end))
+            }
+            Some((start, end)) => {
+                warn!("Ignoring invalid loop region from {start}s to {end}s");
+                return;
+            }
+            None => None,
         };
-        // TODO: proper error handling
-        // Read the first samples ahead of time to determine the format.
-        let first_samples = match media_stream.read_samples() {
-            Ok(samples) => samples,
-            Err(e) => match e {
-                PlaybackReadError::InvalidState => {
-                    panic!("thread state is invalid: decoder state is invalid")
-                }
-                PlaybackReadError::NeverStarted => {
-                    panic!("thread state is invalid: playback never started")
-                }
-                PlaybackReadError::Eof => {
-                    info!("EOF, moving to next song");
-                    self.next(false);
-                    return;
-                }
-                PlaybackReadError::Unknown(s) => {
-                    error!("unknown decode error: {}", s);
-                    warn!("samples may be skipped");
-                    return;
-                }
-                PlaybackReadError::DecodeFatal(s) => {
-                    error!("fatal decoding error: {}, moving to next song", s);
-                    self.next(false);
-                    return;
-                }
+
+        if region == self.loop_region {
+            return;
+        }
+
+        self.loop_region = region;
+        // the end of the track is never reached while looping
+        self.cancel_crossfade();
+
+        self.events_tx
+            .send(PlaybackEvent::LoopRegionChanged(region))
+            .expect("unable to send event");
+    }
+
+    /// Seeks back to the start of the loop region once the end of it has been played.
+    fn check_loop_region(&mut self) {
+        let Some((start, end)) = self.loop_region else {
+            return;
+        };
+        let Some(position) = self
+            .media_stream
+            .as_ref()
+            .and_then(|stream| stream.position_millis().ok())
+        else {
+            return;
+        };
+
+        if position as f64 / 1000.0 >= end {
+            debug!("Reached the end of the loop region, seeking back to {start}s");
+            self.seek(start);
+        }
+    }
+
+    /// Looks up the loudness of the track at the given path, as measured while scanning.
+    fn track_loudness(&self, path: &Path) -> Option<AnalyzedLoudness> {
+        match crate::RUNTIME.block_on(get_track_loudness(&self.pool, path)) {
+            Ok(loudness) => {
+                loudness.map(|(integrated, peak)| AnalyzedLoudness { integrated, peak })
+            }
+            Err(err) => {
+                warn!("Failed to look up loudness for track: {:?}", err);
+                None
+            }
+        }
+    }
+
+    /// Applies the current volume and track volume adjustment to the playback stream.
+    fn apply_volume(&mut self) {
+        let output_volume = self.output_volume();
+
+        if let Some(stream) = self.stream.as_mut()
+            && let Err(err) = stream.set_volume(combined_gain(
+                self.track_gain,
+                self.replay_gain,
+                output_volume,
+            ))
+        {
+            warn!(?err, "Failed to set the volume: {err}");
+        }
+    }
+
+    /// Returns the user's volume, after scaling, with the sleep timer's fade out applied. Silent
+    /// while muted.
+    fn output_volume(&self) -> f64 {
+        if self.muted {
+            return 0.0;
+        }
+
+        let fade = self
+            .sleep_timer
+            .as_ref()
+            .map_or(1.0, ActiveSleepTimer::fade);
+
+        self.last_volume * fade
+    }
+
+    /// Sets whether playback stops once the current track has finished.
+    fn set_stop_after_current(&mut self, stop: bool) {
+        if stop == self.stop_after_current {
+            return;
+        }
+
+        self.stop_after_current = stop;
+
+        // the next track isn't faded in when playback is going to stop before it
+        if stop {
+            self.cancel_crossfade();
+        }
+
+        self.events_tx
+            .send(PlaybackEvent::StopAfterCurrentChanged(stop))
+            .expect("unable to send event");
+    }
+
+    /// Starts (or cancels) the sleep timer. A fade out that has already started is undone.
+    fn set_sleep_timer(&mut self, timer: SleepTimer) {
+        let was_fading = self
+            .sleep_timer
+            .as_ref()
+            .is_some_and(ActiveSleepTimer::is_fading);
+
+        self.sleep_timer = ActiveSleepTimer::new(timer);
+
+        if was_fading {
+            self.apply_volume();
+        }
+
+        // the next track isn't faded in when playback is about to be paused before it
+        if timer == SleepTimer::AfterCurrentTrack {
+            self.cancel_crossfade();
+        }
+
+        let status = self
+            .sleep_timer
+            .as_mut()
+            .and_then(ActiveSleepTimer::status_update)
+            .unwrap_or_default();
+
+        self.events_tx
+            .send(PlaybackEvent::SleepTimerChanged(status))
+            .expect("unable to send event");
+    }
+
+    /// Reports the time left on the sleep timer, and fades out and pauses playback once it goes
+    /// off.
+    fn check_sleep_timer(&mut self) {
+        let track_left = self.media_stream.as_ref().and_then(|stream| {
+            let duration = stream.duration_millis().ok()?;
+            let position = stream.position_millis().ok()?;
+            Some(Duration::from_millis(duration.saturating_sub(position)).div_f64(self.speed))
+        });
+        let Some(timer) = &mut self.sleep_timer else {
+            return;
+        };
+
+        if let Some(status) = timer.status_update() {
+            self.events_tx
+                .send(PlaybackEvent::SleepTimerChanged(status))
+                .expect("unable to send event");
+        }
+
+        if self.state != PlaybackState::Playing {
+            // there's nothing left to pause if the timer runs out while playback is paused
+            if timer.has_expired() {
+                self.finish_sleep_timer();
+            }
+            return;
+        }
+
+        let was_fading = timer.is_fading();
+
+        if timer.tick(track_left) {
+            self.finish_sleep_timer();
+        } else if was_fading || timer.is_fading() {
+            self.apply_volume();
+        }
+    }
+
+    /// Pauses playback once the sleep timer has gone off, and puts the volume back for when
+    /// playback is resumed.
+    fn finish_sleep_timer(&mut self) {
+        info!("Sleep timer went off, pausing playback");
+
+        self.pause();
+        self.sleep_timer = None;
+        self.apply_volume();
+
+        self.events_tx
+            .send(PlaybackEvent::SleepTimerChanged(SleepTimer::Off))
+            .expect("unable to send event");
+    }
+
+    /// Sets the repeat mode. The queue will loop infinitely when repeat mode is enabled. When
+    /// both repeat-once and shuffle mode are enabled, the queue will be reshuffled when looped.
+    fn set_repeat(&mut self, state: RepeatState) {
+        self.repeat = if state == RepeatState::NotRepeating && self.playback_settings.always_repeat
+        {
+            RepeatState::Repeating
+        } else {
+            state
+        };
+
+        self.events_tx
+            .send(PlaybackEvent::RepeatChanged(state))
+            .expect("unable to send event");
+    }
+
+    /// Toggles between play/pause.
+    fn toggle_play_pause(&mut self) {
+        match self.state {
+            PlaybackState::Playing if self.fade_out.is_some() => self.play(),
+            PlaybackState::Playing => self.start_fade_out(FadeOutAction::Pause),
+            PlaybackState::Paused => self.play(),
+            _ => {}
+        }
+    }
+
+    /// Creates the device provider for the given backend, falling back to cpal if it isn't
+    /// available on this platform, and tells the UI which one is in use. The stream has to be
+    /// recreated afterwards.
+    fn use_device_provider(&mut self, requested: DeviceProviderKind, from_env: bool) {
+        let running = requested.resolve();
+        if requested != DeviceProviderKind::Auto && running != requested {
+            warn!("{} is not supported on this platform", requested.name());
+            warn!("Falling back to {}", running.name());
+        }
+
+        let provider: Box<dyn DeviceProvider> = match running {
+            #[cfg(target_os = "windows")]
+            DeviceProviderKind::WinAudiograph => Box::new(AudioGraphProvider::default()),
+            DeviceProviderKind::Dummy => Box::new(DummyDeviceProvider::new()),
+            _ => Box::new(CpalProvider::default()),
+        };
+        self.device_provider = Some(provider);
+
+        let active = ActiveDeviceProvider {
+            requested,
+            running,
+            from_env,
+        };
+        info!("Using device provider: {}", active.describe());
+        set_audio_backend(active.describe());
+        self.active_provider = Some(active);
+
+        self.events_tx
+            .send(PlaybackEvent::DeviceProviderChanged(active))
+            .expect("unable to send event");
+    }
+
+    /// Switches to another audio backend, reopening the output on the chosen device (or the new
+    /// backend's default device). Playback pauses while that happens, and then carries on from
+    /// where it was.
+    fn set_device_provider(&mut self, requested: DeviceProviderKind) {
+        self.playback_settings.device_provider = requested;
+
+        let Some(active) = self.active_provider else {
+            return;
+        };
+        if active.from_env {
+            info!(
+                "Not switching to the {} device provider, DEVICE_PROVIDER is set to {}",
+                requested.name(),
+                active.requested.name()
+            );
+            return;
+        }
+        if active.requested == requested {
+            return;
+        }
+
+        self.reopen_output(|this| this.use_device_provider(requested, false));
+    }
+
+    /// Switches to another output device, or back to the system's default device if `uid` is
+    /// `None`. Playback pauses while that happens, and then carries on from where it was.
+    fn set_device(&mut self, uid: Option<String>) {
+        if uid == self.playback_settings.output_device {
+            return;
+        }
+
+        self.playback_settings.output_device = uid;
+        self.reopen_output(|_| {});
+    }
+
+    /// Turns exclusive mode (see [PlaybackSettings::exclusive]) on or off. Playback pauses while
+    /// the output is reopened in the format that calls for, and then carries on.
+    fn set_exclusive(&mut self, exclusive: bool) {
+        if exclusive == self.playback_settings.exclusive {
+            return;
+        }
+
+        self.reopen_output(|this| this.playback_settings.exclusive = exclusive);
+    }
+
+    /// Returns the format the given device should be opened with to play the current track
+    /// exactly, if exclusive mode is on and the device can.
+    fn exact_format(&self, device: &dyn Device) -> Option<FormatInfo> {
+        if !self.playback_settings.exclusive {
+            return None;
+        }
+
+        let source = self.source_format?;
+        device.get_exact_format(source.sample_rate, source.bits_per_sample, source.channels)
+    }
+
+    /// Whether the stream has to be reopened for the current track in exclusive mode: in the
+    /// track's format, or in the device's own format if the device can't play the track's.
+    fn needs_new_format(&self) -> bool {
+        if !self.playback_settings.exclusive {
+            return false;
+        }
+
+        let (Some(device), Some(stream)) = (self.device.as_deref(), self.stream.as_deref()) else {
+            return false;
+        };
+        let Ok(current) = stream.get_current_format() else {
+            return false;
+        };
+        let wanted = match self.exact_format(device) {
+            Some(exact) => exact,
+            None => match device.get_default_format() {
+                Ok(default) => default,
+                Err(_) => return false,
             },
         };
 
-        // Convert the first samples to the device format
-        let converted = self
-            .resampler
-            .get_or_insert_with(|| {
-                // Set up the resampler
-                let duration = media_stream.frame_duration().expect("can't get duration");
-                let &device_format = stream.get_current_format().unwrap();
-                let count = media_stream
-                    .channels()
-                    .expect("can't get channel count")
-                    .count();
-
-                self.format.replace(device_format);
-
-                Resampler::new(
-                    first_samples.rate,
-                    device_format.sample_rate,
-                    duration,
-                    count,
-                )
-            })
-            .convert_formats(first_samples, &self.format.unwrap());
+        (wanted.sample_rate, wanted.sample_type) != (current.sample_rate, current.sample_type)
+    }
 
-        // Submit the converted samples to the stream. FIXME: cloning vec<vec> in hottest fn???
-        let s = trace_span!("submit_frame").entered();
-        if let Err(err) = stream.submit_frame(converted.clone()) {
-            // If we get an error, recreate the stream and retry
-            warn!(parent: &s, ?err, "Failed to submit frame: {err}");
-            warn!(parent: &s, "Recreating device and retrying...");
-            self.recreate_stream(true, self.format.map(|v| v.channels));
-            if let Err(err) = self.stream.as_mut().unwrap().submit_frame(converted) {
-                error!(parent: &s, ?err, "Failed to submit frame after recreation: {err}");
-                error!(
-                    "This likely indicates a problem with the audio device or driver\n\
-                    (or an underlying issue in the used DeviceProvider)\n\
-                    Please check your audio setup and try again."
+    /// Tells the UI whether the current track is played bit-perfect: at its own sample rate and
+    /// bit depth, without being resampled. Only sent when that changes.
+    fn send_bit_perfect(&mut self) {
+        let stream_format = self
+            .stream
+            .as_deref()
+            .and_then(|stream| stream.get_current_format().ok());
+
+        let bit_perfect = match (self.source_format, stream_format) {
+            (Some(source), Some(format)) => {
+                self.speed == 1.0
+                    && is_exact_format(
+                        format,
+                        source.sample_rate,
+                        source.bits_per_sample,
+                        source.channels,
+                    )
+            }
+            _ => false,
+        };
+
+        if self.bit_perfect == Some(bit_perfect) {
+            return;
+        }
+
+        self.bit_perfect = Some(bit_perfect);
+        self.events_tx
+            .send(PlaybackEvent::BitPerfect(bit_perfect))
+            .expect("unable to send event");
+    }
+
+    /// Sends the devices that can be played through to the UI.
+    fn list_devices(&mut self) {
+        let Some(device_provider) = self.device_provider.as_mut() else {
+            return;
+        };
+
+        let devices = device_provider.list_devices().unwrap_or_else(|err| {
+            error!(?err, "Failed to list the output devices: {err}");
+            Vec::new()
+        });
+
+        self.events_tx
+            .send(PlaybackEvent::DevicesListed(devices))
+            .expect("unable to send event");
+    }
+
+    /// Closes the output and opens it again, pausing playback while that happens. `switch` is
+    /// called while the output is closed, to change what's opened next.
+    fn reopen_output(&mut self, switch: impl FnOnce(&mut Self)) {
+        let was_playing = self.state == PlaybackState::Playing;
+        self.pause();
+        self.cancel_crossfade();
+
+        if let Some(mut stream) = self.stream.take()
+            && let Err(err) = stream.close_stream()
+        {
+            warn!(?err, "Failed to close the old stream: {err}");
+        }
+        self.device = None;
+        switch(self);
+
+        // the new device might not use the same format
+        self.resampler = None;
+
+        if !self.recreate_stream(true, self.format.map(|v| v.channels)) {
+            match &mut self.device_lost {
+                // playback carries on once there's a device to play on
+                Some(resume) => *resume = was_playing,
+                None if was_playing => self.stop(),
+                None => {}
+            }
+            return;
+        }
+
+        if was_playing {
+            self.play();
+        }
+    }
+
+    /// Finds the device chosen in the settings, or the default device if none was chosen or the
+    /// chosen one isn't connected.
+    fn find_output_device(&mut self) -> Result<Box<dyn Device>, FindError> {
+        let Some(device_provider) = self.device_provider.as_mut() else {
+            panic!("playback thread incorrectly initialized")
+        };
+
+        if let Some(uid) = &self.playback_settings.output_device {
+            match device_provider.get_device_by_uid(uid) {
+                Ok(device) => return Ok(device),
+                Err(err) => {
+                    warn!("Couldn't find the output device {uid}, using the default device: {err}")
+                }
+            }
+        }
+
+        device_provider.get_default_device()
+    }
+
+    /// Recreates the playback stream with the given channels if any are provided, otherwise uses
+    /// the device's default channel layout.
+    /// Opens the chosen device (see [Self::find_output_device]), trying each of the formats given
+    /// by `fallback_formats` in turn. Returns false if no stream could be opened, in which case
+    /// the error is sent to the UI, or if there's no device at all, in which case playback is
+    /// paused until there is one (see [Self::lose_device]).
+    fn recreate_stream(&mut self, force: bool, channels: Option<ChannelSpec>) -> bool {
+        // the stream usually fails to close when its device was unplugged, which is why it's
+        // being recreated
+        if let Some(mut stream) = self.stream.take()
+            && let Err(err) = stream.close_stream()
+        {
+            warn!(?err, "Failed to close the old stream: {err}");
+        }
+
+        let Ok(mut device) = self.find_output_device() else {
+            error!("No playback device found, audio will not play");
+            self.lose_device();
+            return false;
+        };
+
+        if self.device.as_ref().and_then(|v| v.get_uid().ok()) == device.get_uid().ok() && !force {
+            return self.stream.is_some();
+        }
+
+        let default_format = match device.get_default_format() {
+            Ok(format) => format,
+            Err(err) => {
+                error!(?err, "Failed to get the device's default format: {err}");
+                self.send_error(
+                    PlaybackErrorKind::Device,
+                    format!("Couldn't open the audio device: {err}"),
                 );
-                panic!("Failed to submit frame after recreation");
+                return false;
             }
+        };
+
+        let mut formats = fallback_formats(default_format, channels);
+        if let Some(exact) = self.exact_format(device.as_ref()) {
+            formats.insert(0, ("exact", exact));
         }
 
-        self.update_ts();
+        let mut last_error = None;
+
+        for (step, format) in formats {
+            info!(
+                "Opening device with {step} format: {:?}, rate: {}, channel_count: {}",
+                format.sample_type,
+                format.sample_rate,
+                format.channels.count()
+            );
+
+            let mut stream = match device.open_device(format) {
+                Ok(stream) => stream,
+                Err(err) => {
+                    info!(?format, "Device rejected {step} format: {err}");
+                    last_error = Some(err);
+                    continue;
+                }
+            };
+
+            if let Err(err) = stream.set_volume(combined_gain(
+                self.track_gain,
+                self.replay_gain,
+                self.output_volume(),
+            )) {
+                warn!(
+                    ?err,
+                    "Failed to set the volume after opening the device: {err}"
+                );
+            }
+
+            self.equalizer.set_sample_rate(format.sample_rate);
+            self.stream = Some(stream);
+            self.device = Some(device);
+            self.watchdog.reset();
+            self.underruns.reset();
+
+            // a device the user chose is kept, even if the default device changes
+            self.default_device_uid = match self.playback_settings.output_device {
+                Some(_) => None,
+                None => self
+                    .device_provider
+                    .as_mut()
+                    .unwrap()
+                    .get_default_device_uid(),
+            };
+            info!(
+                "Opened device: {:?}, format: {:?}, rate: {}, channel_count: {}",
+                self.device.as_ref().unwrap().get_name(),
+                format.sample_type,
+                format.sample_rate,
+                format.channels.count()
+            );
+            self.send_bit_perfect();
+
+            return true;
+        }
+
+        // fallback_formats always returns at least one format
+        let err = last_error.expect("no formats were tried");
+        error!(
+            ?err,
+            "Failed to open device with any format, audio will not play"
+        );
+        self.send_error(
+            PlaybackErrorKind::Device,
+            format!("Couldn't open the audio device: {err}"),
+        );
+
+        false
+    }
+
+    /// Checks whether the device has stopped taking samples, and recreates the stream if it has.
+    fn check_output(&mut self) {
+        let Some(stream) = &self.stream else {
+            return;
+        };
+        let Some(stalled_for) = self.watchdog.check(stream.frames_consumed()) else {
+            return;
+        };
+
+        warn!(
+            "The audio device hasn't played anything for {:.1}s, recreating the stream",
+            stalled_for.as_secs_f64()
+        );
+        self.events_tx
+            .send(PlaybackEvent::OutputRecovered(OutputRecovery::Stalled(
+                stalled_for,
+            )))
+            .expect("unable to send event");
+
+        self.recover_stream();
+    }
+
+    /// Reports new underruns to the UI. Bursts of them are logged with the details of the output,
+    /// since they usually mean that the buffer is too small for how busy the system is.
+    fn check_underruns(&mut self) {
+        let Some(stream) = &self.stream else {
+            return;
+        };
+        let Some(new) = self.underruns.check(stream.underruns()) else {
+            return;
+        };
+
+        if new >= UNDERRUN_SPIKE {
+            warn!(
+                new,
+                total = self.underruns.total(),
+                format = ?stream.get_current_format().ok(),
+                buffer_frames = ?stream.buffer_frames(),
+                "The audio device ran out of samples {new} times in quick succession"
+            );
+        } else {
+            debug!(new, "The audio device ran out of samples");
+        }
+
+        self.events_tx
+            .send(PlaybackEvent::Underrun {
+                count: self.underruns.total(),
+            })
+            .expect("unable to send event");
+    }
+
+    /// Recreates the stream if the playback loop hasn't run for long enough that the system was
+    /// probably suspended, since some devices stop playing after a resume without any errors.
+    fn check_suspend(&mut self) {
+        let Some(gap) = self.watchdog.tick() else {
+            return;
+        };
+
+        info!(
+            "Playback loop didn't run for {:.1}s, the system was probably suspended; recreating the \
+            stream",
+            gap.as_secs_f64()
+        );
+        self.events_tx
+            .send(PlaybackEvent::OutputRecovered(OutputRecovery::Resumed(gap)))
+            .expect("unable to send event");
+
+        if self.stream.is_some() {
+            self.recover_stream();
+        }
+    }
+
+    /// Checks the devices every [DEVICE_CHECK_INTERVAL]: for a device to play on if the last one
+    /// was lost, and otherwise for a change of the default device.
+    fn check_devices(&mut self) {
+        if self.last_device_check.elapsed() < DEVICE_CHECK_INTERVAL {
+            return;
+        }
+        self.last_device_check = Instant::now();
+
+        if self.device_lost.is_some() {
+            self.reconnect_device();
+        } else {
+            self.check_default_device();
+        }
+    }
+
+    /// Moves playback to the system's default device when the user switches to another one (for
+    /// example, by connecting a Bluetooth headset), unless a device was chosen in the settings.
+    /// Playback carries on from the same position.
+    fn check_default_device(&mut self) {
+        let (Some(current), Some(device_provider)) =
+            (&self.default_device_uid, self.device_provider.as_mut())
+        else {
+            return;
+        };
+
+        // there can briefly be no default device while the system is switching between them
+        let Some(default) = device_provider.get_default_device_uid() else {
+            return;
+        };
+
+        if *current == default {
+            return;
+        }
+
+        // the device that was being played on has been disconnected if it can't be found, which
+        // is handled like any other disconnection, so that the user's choice of whether to keep
+        // playing applies
+        let disconnected = device_provider.get_device_by_uid(current).is_err();
+        info!(
+            disconnected,
+            "The default output device changed to {default}, moving playback"
+        );
+
+        // if the new device can't be opened, it isn't retried until the default changes again
+        self.default_device_uid = Some(default);
+
+        if disconnected {
+            self.recover_stream();
+        } else {
+            self.reopen_output(|_| {});
+        }
+    }
+
+    /// Pauses playback because there's no device to play on, and tells the UI so. A device is
+    /// looked for every [DEVICE_CHECK_INTERVAL] from then on (see [Self::reconnect_device]).
+    fn lose_device(&mut self) {
+        // the stream can't be played or paused without a device, so it's dropped without either
+        self.stream = None;
+        self.device = None;
+        self.default_device_uid = None;
+        self.resampler = None;
+        self.ramp = None;
+        self.fade_out = None;
+        self.cancel_crossfade();
+
+        let was_playing = self.state == PlaybackState::Playing;
+
+        if was_playing {
+            self.state = PlaybackState::Paused;
+            self.events_tx
+                .send(PlaybackEvent::StateChanged(PlaybackState::Paused))
+                .expect("unable to send event");
+        }
+
+        match &mut self.device_lost {
+            Some(resume) => *resume |= was_playing,
+            None => {
+                warn!("Lost the audio device, pausing until one is available");
+                self.device_lost = Some(was_playing);
+                self.events_tx
+                    .send(PlaybackEvent::DeviceLost)
+                    .expect("unable to send event");
+            }
+        }
+    }
+
+    /// Opens a device again after the last one was lost, if there is one, and resumes playback if
+    /// it was paused because of that.
+    fn reconnect_device(&mut self) {
+        let Some(resume) = self.device_lost else {
+            return;
+        };
+
+        // checked first so that nothing is logged while there's still no device
+        if self.find_output_device().is_err() {
+            return;
+        }
+
+        let device_lost = self.device_lost.take();
+
+        if !self.recreate_stream(true, self.format.map(|v| v.channels)) {
+            // the device was found but couldn't be opened, which has been reported; it's tried
+            // again at the next check
+            self.device_lost = device_lost;
+            return;
+        }
+
+        let name = self
+            .device_info()
+            .map_or_else(|| "Unknown device".to_owned(), |(_, name, _)| name);
+        info!("Audio device available again: {name}");
+
+        self.events_tx
+            .send(PlaybackEvent::DeviceRestored(name))
+            .expect("unable to send event");
+
+        if resume {
+            self.play();
+        }
+    }
+
+    /// Recreates the stream on the current default device, carrying on playback if it was
+    /// playing before.
+    fn recover_stream(&mut self) {
+        let previous = self.device_info();
+
+        if !self.recreate_stream(true, self.format.map(|v| v.channels)) {
+            if self.state == PlaybackState::Playing {
+                self.stop();
+            }
+            return;
+        }
+
+        if self.state != PlaybackState::Playing || !self.handle_device_change(previous) {
+            return;
+        }
+
+        if let Err(err) = self.stream.as_mut().unwrap().play() {
+            error!(
+                ?err,
+                "Failed to restart playback after recreating the stream: {err}"
+            );
+        }
+    }
+
+    /// Returns the current device's UID, name and category, if there is a device.
+    fn device_info(&self) -> Option<(String, String, DeviceCategory)> {
+        let device = self.device.as_ref()?;
+        let name = device
+            .get_name()
+            .unwrap_or_else(|_| "Unknown device".to_owned());
+        let uid = device.get_uid().unwrap_or_else(|_| name.clone());
+
+        Some((uid, name, device.get_category()))
+    }
+
+    /// Called after the stream has been recreated because it stopped working. If the stream is
+    /// now on a different device, the previous one was disconnected, and the user's policy for
+    /// that kind of device is applied. Returns whether playback should carry on.
+    fn handle_device_change(&mut self, previous: Option<(String, String, DeviceCategory)>) -> bool {
+        let (Some((old_uid, removed, category)), Some((new_uid, replacement, _))) =
+            (previous, self.device_info())
+        else {
+            return true;
+        };
+
+        if old_uid == new_uid {
+            return true;
+        }
+
+        let policy = self.playback_settings.removal_policy(category);
+        info!(
+            ?category,
+            ?policy,
+            "{removed} was disconnected, playback moved to {replacement}"
+        );
+
+        if policy != DeviceRemovalPolicy::Continue {
+            self.pause();
+        }
+
+        self.events_tx
+            .send(PlaybackEvent::DeviceRemoved {
+                removed,
+                replacement,
+                policy,
+            })
+            .expect("unable to send event");
+
+        policy == DeviceRemovalPolicy::Continue
+    }
+
+    /// Whether the current track's file is gone, for example because it was deleted or the drive
+    /// it's on was unplugged. This touches the file system, so it's only checked once reading the
+    /// track fails or ends.
+    fn current_file_vanished(&self) -> bool {
+        self.current_path.as_deref().is_some_and(file_is_gone)
+    }
+
+    /// Moves on from the current track after its file vanished while it was being played, and
+    /// tells the UI so that it can be marked in the queue.
+    fn file_vanished(&mut self) {
+        let Some(path) = self.current_path.clone() else {
+            return;
+        };
+
+        warn!(path = %path.display(), "The current track's file became unavailable");
+        self.events_tx
+            .send(PlaybackEvent::TrackUnavailable(path))
+            .expect("unable to send event");
+
+        if let Some(mut stream) = self.media_stream.take() {
+            stream.close().ok();
+        }
+
+        self.skip_failed_track();
+    }
+
+    /// Moves on to the next track after one couldn't be played, unless too many have failed in a
+    /// row (see [MAX_CONSECUTIVE_FAILURES]), in which case playback stops.
+    fn skip_failed_track(&mut self) {
+        if self.failed_tracks.add() {
+            warn!("{MAX_CONSECUTIVE_FAILURES} tracks in a row couldn't be played, stopping");
+            self.send_error(
+                PlaybackErrorKind::Track,
+                format!(
+                    "Playback stopped because {MAX_CONSECUTIVE_FAILURES} tracks in a row couldn't \
+                    be played."
+                ),
+            );
+            self.stop();
+            return;
+        }
+
+        self.advance(false);
+    }
+
+    /// Gives up on the current track after something went wrong with it that it can't recover
+    /// from, telling the user why, and moves on (see [Self::skip_failed_track]).
+    fn abandon_track(&mut self, message: String) {
+        error!("{message}, skipping the track");
+        self.send_error(PlaybackErrorKind::Track, message);
+        self.skip_failed_track();
+    }
+
+    /// Sends an error message to be shown to the user.
+    fn send_error(&self, kind: PlaybackErrorKind, message: String) {
+        self.events_tx
+            .send(PlaybackEvent::Error(kind, message))
+            .expect("unable to send event");
+    }
+
+    /// Opens the next track in the queue and starts crossfading to it, once the current track is
+    /// close enough to its end. Crossfading is skipped when repeating the current track, at the
+    /// end of the queue, and for tracks that are too short or have an unknown duration.
+    fn start_crossfade(&mut self) {
+        if self.crossfade_secs <= 0.0
+            || self.crossfade.is_some()
+            || self.crossfade_skipped
+            || self.loop_region.is_some()
+            || self.repeat == RepeatState::RepeatingOne
+            || self.stop_after_current
+            || self.album_boundary().is_some()
+            || self
+                .sleep_timer
+                .as_ref()
+                .is_some_and(ActiveSleepTimer::waits_for_track_end)
+        {
+            return;
+        }
+
+        // samples are mixed after they've been converted to the device's format
+        let Some(format) = self
+            .format
+            .filter(|f| f.sample_type == SampleFormat::Float32)
+        else {
+            return;
+        };
+        let Some(media_stream) = &self.media_stream else {
+            return;
+        };
+        let (Ok(duration), Ok(position)) = (
+            media_stream.duration_millis(),
+            media_stream.position_millis(),
+        ) else {
+            return;
+        };
+
+        // never fade for more than half of the track
+        let secs = self.crossfade_secs.min(duration as f64 / 2000.0);
+        let remaining = duration.saturating_sub(position) as f64 / 1000.0;
+        if duration == 0 || remaining > secs {
+            return;
+        }
+
+        let queue = self.queue.read().expect("couldn't get the queue");
+        let Some(path) = queue
+            .get(self.queue_next)
+            .map(|item| item.get_path().clone())
+        else {
+            return;
+        };
+        drop(queue);
+
+        match self.open_next_track(&path) {
+            Ok(next) => {
+                info!("Crossfading to '{}' over {remaining}s", path.display());
+
+                let gain = combined_gain(next.track_gain, next.replay_gain, 1.0)
+                    / combined_gain(self.track_gain, self.replay_gain, 1.0);
+                // the fade is timed in the device's samples, which the track's remaining time
+                // takes more or fewer of when it isn't played at normal speed
+                self.crossfade = Some(Crossfade::new(
+                    next,
+                    remaining / self.speed,
+                    format.sample_rate,
+                    gain as f32,
+                ));
+            }
+            Err(err) => {
+                warn!(path = %path.display(), ?err, "Not crossfading to next track: {err}");
+                self.crossfade_skipped = true;
+            }
+        }
+    }
+
+    /// Opens a track to crossfade to. Its channels are mapped onto the channels of the device the
+    /// current track is playing on, since they're mixed together.
+    fn open_next_track(&mut self, path: &Path) -> Result<NextTrack, PlaybackStartError> {
+        let provider = self.media_provider.as_deref_mut().ok_or_else(|| {
+            PlaybackStartError::MediaError("No media provider available".to_owned())
+        })?;
+
+        let src = open_source(path)
+            .map_err(|e| PlaybackStartError::MediaError(format!("Unable to open file: {}", e)))?;
+
+        let mut media_stream = provider
+            .open(src, path.extension())
+            .map_err(|e| PlaybackStartError::MediaError(format!("Unable to open file: {}", e)))?;
+
+        media_stream.start_playback().map_err(|e| {
+            PlaybackStartError::MediaError(format!("Unable to start playback: {}", e))
+        })?;
+
+        let channels = media_stream.channels().map_err(|e| {
+            PlaybackStartError::MediaError(format!("Unable to get channels: {}", e))
+        })?;
+
+        let mixer = self.channel_mixer_for(channels)?;
+
+        let track_gain = match crate::RUNTIME.block_on(get_track_gain_offset(&self.pool, path)) {
+            Ok(gain) => gain.unwrap_or_default(),
+            Err(err) => {
+                warn!("Failed to look up volume adjustment for track: {:?}", err);
+                0.0
+            }
+        };
+
+        let loudness = self.track_loudness(path);
+        let replay_gain = replay_gain(
+            media_stream.read_metadata().ok(),
+            loudness,
+            &self.playback_settings,
+        );
+
+        Ok(NextTrack::new(
+            media_stream,
+            mixer,
+            path.to_owned(),
+            track_gain,
+            loudness,
+            replay_gain,
+            self.speed,
+        ))
+    }
+
+    /// Called when the current track ends during a crossfade. The next track takes its place,
+    /// carrying on from where the crossfade got to. If the queue has changed so that it's no
+    /// longer the next track, or the current track is now being repeated, the crossfade is
+    /// dropped and playback moves on as usual.
+    fn finish_crossfade(&mut self) {
+        let next = self
+            .crossfade
+            .as_mut()
+            .and_then(|crossfade| crossfade.next.take());
+        let Some(next) = next else {
+            self.next(false);
+            return;
+        };
+
+        let queue = self.queue.read().expect("couldn't get the queue");
+        let still_next = queue
+            .get(self.queue_next)
+            .is_some_and(|item| item.get_path() == &next.path);
+        drop(queue);
+
+        if !still_next || self.repeat == RepeatState::RepeatingOne {
+            let mut stream = next.stream;
+            stream.close().ok();
+            self.crossfade = None;
+            self.next(false);
+            return;
+        }
+
+        info!("Crossfade reached end of track, moving to next song");
+
+        if let Some(mut old_stream) = self.media_stream.take() {
+            old_stream.close().ok();
+        }
+        self.reset_metadata_state();
+        self.set_loop_region(None);
+
+        if let Some(detector) = &mut self.silence_detector {
+            detector.reset();
+        }
+        // the start of the track has already been heard during the crossfade
+        self.in_lead_in = false;
+
+        let duration = next.stream.duration_millis().unwrap_or(0);
+
+        // the stream stays in the current track's format, so that the crossfade isn't cut short
+        self.source_format = SourceFormat::of(next.stream.as_ref());
+        self.send_bit_perfect();
+
+        self.media_stream = Some(next.stream);
+        self.resampler = next.resampler;
+        self.channel_mixer = next.mixer;
+        self.current_path = Some(next.path.clone());
+        self.track_gain = next.track_gain;
+        self.track_loudness = next.loudness;
+        self.replay_gain = next.replay_gain;
+        self.crossfade_skipped = false;
+        self.apply_volume();
+
+        self.events_tx
+            .send(PlaybackEvent::SongChanged(next.path))
+            .expect("unable to send event");
+        self.events_tx
+            .send(PlaybackEvent::DurationChanged(duration))
+            .expect("unable to send event");
+        self.queue_next += 1;
+        self.send_queue_position();
+
+        self.update_ts();
+    }
+
+    /// Creates a mixer from a track's channels to the channels of the current device, following
+    /// the user's downmix settings.
+    fn channel_mixer_for(&self, channels: ChannelSpec) -> Result<ChannelMixer, PlaybackStartError> {
+        let stream = self.stream.as_deref().ok_or_else(|| {
+            PlaybackStartError::StreamError("No audio stream available".to_owned())
+        })?;
+
+        let device_channels = stream
+            .get_current_format()
+            .map_err(|e| {
+                PlaybackStartError::StreamError(format!("Unable to get stream format: {}", e))
+            })?
+            .channels;
+
+        ChannelMixer::new(
+            channels,
+            device_channels,
+            self.playback_settings.downmix,
+            &self.playback_settings.downmix_coefficients,
+        )
+        .map_err(|e| PlaybackStartError::ChannelError(e.to_string()))
+    }
+
+    /// Drops the crossfade in progress, if any, along with the next track if it hasn't started
+    /// playing on its own yet.
+    fn cancel_crossfade(&mut self) {
+        if let Some(crossfade) = self.crossfade.take()
+            && let Some(next) = crossfade.next
+        {
+            let mut stream = next.stream;
+            stream.close().ok();
+        }
+    }
+
+    /// Uses the current media provider to decode audio samples and sends them to the current
+    /// playback stream.
+    fn play_audio(&mut self) {
+        self.start_crossfade();
+
+        let Some(stream) = &mut self.stream else {
+            return;
+        };
+        let Some(media_stream) = &mut self.media_stream else {
+            return;
+        };
+        // Read the first samples ahead of time to determine the format.
+        let first_samples = match media_stream.read_samples() {
+            Ok(samples) => {
+                self.failed_tracks.reset();
+                samples
+            }
+            Err(e) => match e {
+                PlaybackReadError::InvalidState | PlaybackReadError::NeverStarted => {
+                    self.abandon_track(format!("Couldn't decode the current track: {e}"));
+                    return;
+                }
+                PlaybackReadError::Eof if self.crossfade.is_some() => {
+                    self.finish_crossfade();
+                    return;
+                }
+                // the file being deleted or its drive being unplugged shows up as any of these,
+                // depending on where the decoder was when it happened
+                PlaybackReadError::Eof
+                | PlaybackReadError::Unknown(_)
+                | PlaybackReadError::DecodeFatal(_)
+                | PlaybackReadError::SourceUnavailable(_)
+                    if self.current_file_vanished() =>
+                {
+                    self.file_vanished();
+                    return;
+                }
+                PlaybackReadError::Eof => {
+                    info!("EOF, moving to next song");
+                    self.next(false);
+                    return;
+                }
+                PlaybackReadError::Unknown(s) => {
+                    error!("unknown decode error: {}", s);
+                    warn!("samples may be skipped");
+                    return;
+                }
+                PlaybackReadError::DecodeFatal(s) => {
+                    error!("fatal decoding error: {}, moving to next song", s);
+                    self.next(false);
+                    return;
+                }
+                PlaybackReadError::SourceUnavailable(s) => {
+                    error!("source became unavailable: {}, moving to next song", s);
+                    self.send_error(
+                        PlaybackErrorKind::Track,
+                        format!("Couldn't keep reading the current track: {s}"),
+                    );
+                    self.next(false);
+                    return;
+                }
+            },
+        };
+
+        if self.in_lead_in {
+            let level = frame_rms(&first_samples.samples).filter(|_| first_samples.rate > 0);
+
+            match (&mut self.lead_in_detector, level) {
+                (Some(detector), Some((rms, frames))) if detector.is_silent(rms) => {
+                    let frame_secs = frames as f64 / f64::from(first_samples.rate);
+
+                    if detector.feed_leading(rms, frame_secs) {
+                        self.in_lead_in = false;
+
+                        if let Some(end) = find_end_of_silence(media_stream.as_mut(), detector) {
+                            debug!("Skipping leading silence: {end:.1}s of silence");
+                            self.seek_past_lead_in(end);
+                            return;
+                        }
+
+                        // there's no sound to skip to, so go back to where the search started
+                        let position = detector.silent_for();
+                        debug!("Not skipping leading silence: no sound follows it");
+                        self.seek_past_lead_in(position);
+                        return;
+                    }
+                }
+                _ => self.in_lead_in = false,
+            }
+        }
+
+        if let Some(detector) = &mut self.silence_detector
+            && first_samples.rate > 0
+            && let Some((rms, frames)) = frame_rms(&first_samples.samples)
+        {
+            let frame_secs = frames as f64 / f64::from(first_samples.rate);
+            let position = media_stream.position_millis().unwrap_or_default() as f64 / 1000.0;
+            let duration = media_stream
+                .duration_millis()
+                .ok()
+                .map(|v| v as f64 / 1000.0);
+
+            if detector.feed(rms, frame_secs, position, duration) {
+                debug!(
+                    "Skipping trailing silence: {:.1}s of silence at {position}s, trimmed {:.1}s",
+                    detector.silent_for(),
+                    duration.unwrap_or_default() - position,
+                );
+                self.finish_crossfade();
+                return;
+            }
+        }
+
+        // Set up the resampler
+        if self.resampler.is_none() {
+            let duration = match media_stream.frame_duration() {
+                Ok(duration) => duration,
+                Err(err) => {
+                    self.abandon_track(format!("Couldn't decode the current track: {err}"));
+                    return;
+                }
+            };
+            let device_format = match stream.get_current_format() {
+                Ok(&format) => format,
+                Err(err) => {
+                    error!(?err, "Failed to get the stream's format: {err}");
+                    self.lose_device();
+                    return;
+                }
+            };
+
+            self.format.replace(device_format);
+            self.crossfeed.set_sample_rate(device_format.sample_rate);
+            self.equalizer.set_sample_rate(device_format.sample_rate);
+
+            self.resampler = Some(Resampler::new(
+                first_samples.rate,
+                device_format.sample_rate,
+                duration,
+                device_format.channels.count(),
+                self.channel_mixer.clone(),
+                self.speed,
+                self.playback_settings.dither,
+            ));
+        }
+
+        // Convert the first samples to the device format
+        let (Some(resampler), Some(format)) = (&mut self.resampler, self.format) else {
+            return;
+        };
+        let mut converted = resampler.convert_formats(first_samples, &format);
+
+        if let Samples::Float32(channels) = &mut converted.samples {
+            if let Some(crossfade) = &mut self.crossfade {
+                crossfade.process(&format, channels);
+
+                if crossfade.is_finished() {
+                    self.crossfade = None;
+                }
+            }
+
+            if self.mono_downmix {
+                mix_to_mono(channels);
+            }
+
+            if self.balance != 0.0 {
+                apply_balance(channels, self.balance);
+            }
+
+            self.equalizer.process(channels);
+            self.crossfeed.process(channels);
+
+            if let Some(ramp) = &mut self.ramp {
+                ramp.process(channels);
+            }
+        } else {
+            // only f32 frames can be faded, so other formats start and stop straight away
+            self.ramp = None;
+        }
+
+        // Submit the converted samples to the stream. FIXME: cloning vec<vec> in hottest fn???
+        let frames = converted.samples.frames();
+        let s = trace_span!("submit_frame").entered();
+        if let Err(err) = stream.submit_frame(converted.clone()) {
+            // If we get an error, recreate the stream and retry
+            warn!(parent: &s, ?err, "Failed to submit frame: {err}");
+            warn!(parent: &s, "Recreating device and retrying...");
+            let previous = self.device_info();
+            if !self.recreate_stream(true, self.format.map(|v| v.channels)) {
+                // a missing device pauses playback instead (see Self::lose_device)
+                if self.device_lost.is_none() {
+                    self.stop();
+                }
+                return;
+            }
+            if !self.handle_device_change(previous) {
+                // the frame is dropped, but it's only a few milliseconds of audio
+                return;
+            }
+            if let Err(err) = self.stream.as_mut().unwrap().submit_frame(converted) {
+                // the device usually disappears between being opened and being played on when
+                // it's in the middle of being unplugged
+                error!(parent: &s, ?err, "Failed to submit frame after recreation: {err}");
+                self.lose_device();
+                return;
+            }
+        }
+
+        self.watchdog.submitted(frames);
+        self.check_output();
+        self.check_underruns();
+
+        self.update_ts();
+        self.check_loop_region();
+
+        if self.ramp.as_ref().is_none_or(VolumeRamp::is_finished) {
+            self.ramp = None;
+
+            if let Some(action) = self.fade_out.take() {
+                self.finish_fade_out(action);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{ffi::OsStr, io::Read, sync::Mutex};
+
+    use gpui::TestAppContext;
+
+    use super::*;
+    use crate::devices::{
+        builtin::dummy::DummyDevice,
+        errors::{
+            CloseError as DeviceCloseError, InfoError, InitializationError, ListError,
+            OpenError as DeviceOpenError, ResetError, StateError, SubmissionError,
+        },
+        format::SupportedFormat,
+    };
+    use crate::media::{
+        errors::{
+            ChannelRetrievalError, CloseError, FrameDurationError, MetadataError, OpenError,
+            PlaybackStopError, SeekError, TrackDurationError,
+        },
+        playback::PlaybackFrame,
+        source::MediaSource,
+        traits::MediaProviderFeatures,
+    };
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() < 1e-9,
+            "expected {expected}, got {actual}"
+        );
+    }
+
+    #[test]
+    fn multiplies_every_adjustment() {
+        assert_close(combined_gain(0.0, 1.0, 1.0), 1.0);
+        assert_close(combined_gain(-20.0 * 2_f64.log10(), 1.0, 1.0), 0.5);
+        assert_close(combined_gain(-20.0 * 2_f64.log10(), 0.5, 0.5), 0.125);
+        assert_close(
+            combined_gain(6.0, 0.25, 0.8),
+            db_to_linear(6.0) * 0.25 * 0.8,
+        );
+    }
+
+    #[test]
+    fn zero_volume_is_silent() {
+        assert_eq!(combined_gain(12.0, 2.0, 0.0), 0.0);
+        assert_eq!(combined_gain(0.0, 1.0, scale_volume(0.0)), 0.0);
+    }
+
+    #[test]
+    fn clamps_the_track_adjustment() {
+        assert_close(
+            combined_gain(MAX_GAIN_OFFSET + 30.0, 1.0, 1.0),
+            db_to_linear(MAX_GAIN_OFFSET),
+        );
+        assert_close(
+            combined_gain(-MAX_GAIN_OFFSET - 30.0, 1.0, 1.0),
+            db_to_linear(-MAX_GAIN_OFFSET),
+        );
+    }
+
+    #[test]
+    fn skipping_always_plays_the_next_track() {
+        for fade_out in [None, Some(FadeOutAction::Pause), Some(FadeOutAction::Stop)] {
+            assert_eq!(
+                next_track(true, fade_out, PlaybackState::Paused),
+                NextTrackAction::Play
+            );
+        }
+    }
+
+    #[test]
+    fn tracks_ending_while_pausing_open_the_next_one_paused() {
+        let pausing = Some(FadeOutAction::Pause);
+
+        assert_eq!(
+            next_track(false, pausing, PlaybackState::Playing),
+            NextTrackAction::OpenPaused
+        );
+        assert_eq!(
+            next_track(false, None, PlaybackState::Paused),
+            NextTrackAction::OpenPaused
+        );
+        assert_eq!(
+            next_track(false, None, PlaybackState::Playing),
+            NextTrackAction::Play
+        );
+    }
+
+    #[test]
+    fn tracks_ending_while_stopping_stop_playback() {
+        let stopping = Some(FadeOutAction::Stop);
+
+        assert_eq!(
+            next_track(false, stopping, PlaybackState::Playing),
+            NextTrackAction::Stop
+        );
+    }
+
+    #[test]
+    fn notices_when_a_file_is_gone() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("track.flac");
+        fs::write(&path, b"").unwrap();
+
+        assert!(!file_is_gone(&path));
+
+        fs::remove_file(&path).unwrap();
+        assert!(file_is_gone(&path));
+    }
+
+    #[test]
+    fn stops_after_too_many_failed_tracks_in_a_row() {
+        let mut failed = FailedTracks::default();
+
+        for _ in 1..MAX_CONSECUTIVE_FAILURES {
+            assert!(!failed.add());
+        }
+        assert!(failed.add());
+
+        // counting starts over once playback has stopped
+        assert!(!failed.add());
+    }
+
+    #[test]
+    fn playing_a_track_resets_the_failures() {
+        let mut failed = FailedTracks::default();
+
+        for _ in 1..MAX_CONSECUTIVE_FAILURES {
+            failed.add();
+        }
+        failed.reset();
+
+        assert!(!failed.add());
+    }
+
+    #[test]
+    fn finishes_an_album_when_the_next_track_is_from_another() {
+        let repeat = RepeatState::NotRepeating;
+
+        assert_eq!(finished_album(Some(1), Some(Some(2)), repeat), Some(1));
+        assert_eq!(finished_album(Some(1), Some(Some(1)), repeat), None);
+    }
+
+    #[test]
+    fn tracks_without_an_album_never_finish_one() {
+        let repeat = RepeatState::NotRepeating;
+
+        assert_eq!(finished_album(None, Some(Some(2)), repeat), None);
+        assert_eq!(finished_album(Some(1), Some(None), repeat), None);
+    }
+
+    #[test]
+    fn finishes_an_album_when_the_queue_starts_over() {
+        let at_the_end = |repeat| finished_album(Some(1), None, repeat);
+
+        assert_eq!(at_the_end(RepeatState::Repeating), Some(1));
+        assert_eq!(at_the_end(RepeatState::NotRepeating), None);
+    }
+
+    #[test]
+    fn never_finishes_an_album_when_repeating_one_track() {
+        let repeat = RepeatState::RepeatingOne;
+
+        assert_eq!(finished_album(Some(1), Some(Some(2)), repeat), None);
+        assert_eq!(finished_album(Some(1), None, repeat), None);
+    }
+
+    fn replaygain_settings(mode: ReplayGainMode) -> PlaybackSettings {
+        PlaybackSettings {
+            replaygain: mode,
+            ..Default::default()
+        }
+    }
+
+    fn tagged(track_gain: f64, album_gain: Option<f64>, peak: Option<f64>) -> Metadata {
+        Metadata {
+            replaygain_track_gain: Some(track_gain),
+            replaygain_album_gain: album_gain,
+            replaygain_track_peak: peak,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn replaygain_does_nothing_when_off() {
+        let settings = replaygain_settings(ReplayGainMode::Off);
+        let metadata = tagged(-6.0, None, None);
+
+        assert_eq!(replay_gain(Some(&metadata), None, &settings), 1.0);
+    }
+
+    #[test]
+    fn replaygain_uses_the_gain_for_the_mode() {
+        let metadata = tagged(-6.0, Some(-3.0), None);
+        let gain = |mode| replay_gain(Some(&metadata), None, &replaygain_settings(mode));
+
+        assert_close(gain(ReplayGainMode::Track), db_to_linear(-6.0));
+        assert_close(gain(ReplayGainMode::Album), db_to_linear(-3.0));
+    }
+
+    #[test]
+    fn album_mode_falls_back_to_the_track_gain() {
+        let settings = replaygain_settings(ReplayGainMode::Album);
+        let gain = replay_gain(Some(&tagged(-6.0, None, None)), None, &settings);
+
+        assert_close(gain, db_to_linear(-6.0));
+    }
+
+    #[test]
+    fn replaygain_never_makes_tracks_clip() {
+        let settings = replaygain_settings(ReplayGainMode::Track);
+        let gain = replay_gain(Some(&tagged(6.0, None, Some(0.8))), None, &settings);
+
+        assert_close(gain, 1.25);
+    }
+
+    #[test]
+    fn untagged_tracks_get_the_fallback_pre_amp() {
+        let fallback = |replaygain_fallback| PlaybackSettings {
+            replaygain_fallback,
+            ..replaygain_settings(ReplayGainMode::Track)
+        };
+
+        assert_close(replay_gain(None, None, &fallback(3.0)), db_to_linear(3.0));
+        assert_close(
+            replay_gain(Some(&Metadata::default()), None, &fallback(40.0)),
+            db_to_linear(MAX_GAIN_OFFSET),
+        );
+    }
+
+    #[test]
+    fn untagged_tracks_use_their_measured_loudness() {
+        let settings = replaygain_settings(ReplayGainMode::Album);
+        let loudness = AnalyzedLoudness {
+            integrated: -12.0,
+            peak: 0.9,
+        };
+
+        let measured = replay_gain(None, Some(loudness), &settings);
+        assert_close(measured, db_to_linear(-6.0));
+
+        // tags win over the measured loudness
+        let metadata = tagged(-3.0, None, None);
+        let gain = replay_gain(Some(&metadata), Some(loudness), &settings);
+        assert_close(gain, db_to_linear(-3.0));
+    }
+
+    #[test]
+    fn combines_replaygain_with_the_other_adjustments() {
+        let settings = replaygain_settings(ReplayGainMode::Track);
+        let gain = replay_gain(Some(&tagged(6.0, None, Some(0.8))), None, &settings);
+
+        assert_close(
+            combined_gain(-3.0, gain, 0.5),
+            db_to_linear(-3.0) * 1.25 * 0.5,
+        );
+
+        // the peak only limits ReplayGain, so the track's adjustment still raises it
+        assert_close(combined_gain(3.0, gain, 1.0), db_to_linear(3.0) * 1.25);
+    }
+
+    #[test]
+    fn fingerprints_match_for_the_same_image() {
+        let image = vec![0x89, b'P', b'N', b'G', 1, 2, 3];
+
+        assert_eq!(
+            ImageFingerprint::new(&image),
+            ImageFingerprint::new(&image.clone())
+        );
+    }
+
+    #[test]
+    fn fingerprints_differ_when_the_image_changes() {
+        let image = [0x89, b'P', b'N', b'G', 1, 2, 3];
+        let original = ImageFingerprint::new(&image);
+        let mut changed = image;
+        changed[4] = 9;
+
+        assert_ne!(original, ImageFingerprint::new(&changed));
+        assert_ne!(original, ImageFingerprint::new(&image[..6]));
+        assert_ne!(original, ImageFingerprint::new(&[]));
+    }
+
+    /// Creates a playback thread with the given queue, without starting it, along with the ends
+    /// of its channels that the UI would hold. It has no media provider or device until a test
+    /// sets them up.
+    fn test_thread(
+        queue: Vec<QueueItemData>,
+    ) -> (
+        PlaybackThread,
+        UnboundedSender<PlaybackCommand>,
+        UnboundedReceiver<PlaybackEvent>,
+    ) {
+        let (commands_tx, commands_rx) = unbounded_channel();
+        let (events_tx, events_rx) = unbounded_channel();
+        // the pool starts its upkeep tasks on the runtime it's created in, and never connects
+        // until it's used
+        let pool = crate::RUNTIME
+            .block_on(async { SqlitePool::connect_lazy("sqlite::memory:") })
+            .expect("couldn't create the pool");
+
+        let thread = PlaybackThread::new(
+            commands_rx,
+            events_tx,
+            Arc::new(RwLock::new(queue)),
+            PlaybackSettings::default(),
+            pool,
+        );
+
+        (thread, commands_tx, events_rx)
+    }
+
+    /// Returns the events the thread has sent since this was last called.
+    fn drain(events: &mut UnboundedReceiver<PlaybackEvent>) -> Vec<PlaybackEvent> {
+        std::iter::from_fn(|| events.try_recv().ok()).collect()
+    }
+
+    /// Plays silence, with tags whose title can be changed while it's playing. Its album art is
+    /// made from the title, so it changes along with it. Like some decoders, it reports that its
+    /// tags have changed every time it's asked.
+    struct FakeStream {
+        title: Arc<Mutex<String>>,
+        metadata: Metadata,
+    }
+
+    impl FakeStream {
+        fn new(title: &Arc<Mutex<String>>) -> Self {
+            FakeStream {
+                title: title.clone(),
+                metadata: Metadata::default(),
+            }
+        }
+
+        fn title(&self) -> String {
+            self.title.lock().unwrap().clone()
+        }
+    }
+
+    impl MediaStream for FakeStream {
+        fn close(&mut self) -> Result<(), CloseError> {
+            Ok(())
+        }
+
+        fn start_playback(&mut self) -> Result<(), PlaybackStartError> {
+            Ok(())
+        }
+
+        fn stop_playback(&mut self) -> Result<(), PlaybackStopError> {
+            Ok(())
+        }
+
+        fn seek(&mut self, _: f64) -> Result<(), SeekError> {
+            Ok(())
+        }
+
+        fn read_samples(&mut self) -> Result<PlaybackFrame, PlaybackReadError> {
+            Ok(PlaybackFrame {
+                samples: Samples::Float32(vec![vec![0.0; 1024]; 2]),
+                rate: 44100,
+            })
+        }
+
+        fn frame_duration(&self) -> Result<u64, FrameDurationError> {
+            Ok(1024)
+        }
+
+        fn read_metadata(&mut self) -> Result<&Metadata, MetadataError> {
+            self.metadata.name = Some(self.title());
+            Ok(&self.metadata)
+        }
+
+        fn metadata_updated(&self) -> bool {
+            true
+        }
+
+        fn read_image(&mut self) -> Result<Option<Box<[u8]>>, MetadataError> {
+            Ok(Some(format!("art: {}", self.title()).into_bytes().into()))
+        }
+
+        fn duration_millis(&self) -> Result<u64, TrackDurationError> {
+            Ok(60_000)
+        }
+
+        fn position_millis(&self) -> Result<u64, TrackDurationError> {
+            Ok(0)
+        }
+
+        fn channels(&self) -> Result<ChannelSpec, ChannelRetrievalError> {
+            Ok(ChannelSpec::Count(2))
+        }
+    }
+
+    /// Returns the titles and album art sent to the UI since this was last called.
+    fn sent_tags(events: &mut UnboundedReceiver<PlaybackEvent>) -> Vec<String> {
+        drain(events)
+            .into_iter()
+            .filter_map(|event| match event {
+                PlaybackEvent::MetadataUpdate(metadata) => metadata.name,
+                PlaybackEvent::AlbumArtUpdate(image) => String::from_utf8(image?.into()).ok(),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn sends_each_change_of_tags_once() {
+        let (mut thread, _commands, mut events) = test_thread(Vec::new());
+        let title = Arc::new(Mutex::new("First".to_owned()));
+        thread.media_stream = Some(Box::new(FakeStream::new(&title)));
+        let wait_out_interval = |thread: &mut PlaybackThread| {
+            thread.last_metadata_read = Some(Instant::now() - METADATA_READ_INTERVAL);
+        };
+
+        for _ in 0..10 {
+            thread.broadcast_events();
+        }
+        assert_eq!(sent_tags(&mut events), ["First", "art: First"]);
+
+        // the stream still says the tags changed, but they haven't
+        wait_out_interval(&mut thread);
+        thread.broadcast_events();
+        assert!(sent_tags(&mut events).is_empty());
+
+        // changes aren't read again until the interval has passed
+        *title.lock().unwrap() = "Second".to_owned();
+        thread.broadcast_events();
+        assert!(sent_tags(&mut events).is_empty());
+
+        wait_out_interval(&mut thread);
+        for _ in 0..10 {
+            thread.broadcast_events();
+        }
+        assert_eq!(sent_tags(&mut events), ["Second", "art: Second"]);
+    }
+
+    /// Opens every file as a [FakeStream], except empty ones, which it treats as corrupt.
+    #[derive(Default)]
+    struct FakeProvider {
+        title: Arc<Mutex<String>>,
+    }
+
+    impl MediaProvider for FakeProvider {
+        fn open(
+            &mut self,
+            mut source: Box<dyn MediaSource>,
+            _: Option<&OsStr>,
+        ) -> Result<Box<dyn MediaStream>, OpenError> {
+            match source.read(&mut [0]) {
+                Ok(1) => Ok(Box::new(FakeStream::new(&self.title))),
+                _ => Err(OpenError::FileCorrupt),
+            }
+        }
+
+        fn supported_mime_types(&self) -> &[&str] {
+            &[]
+        }
+
+        fn supported_extensions(&self) -> &[&str] {
+            &["flac"]
+        }
+
+        fn supported_features(&self) -> MediaProviderFeatures {
+            MediaProviderFeatures::PROVIDES_DECODER
+        }
+    }
+
+    /// Creates a playback thread like [test_thread], playing on a dummy device, with a queue of
+    /// files by the given names that it opens with [FakeProvider]. The files are created in
+    /// `dir`, and the ones whose names start with "broken" are left empty.
+    fn playing_thread(
+        cx: &mut TestAppContext,
+        dir: &Path,
+        names: &[&str],
+    ) -> (
+        PlaybackThread,
+        UnboundedSender<PlaybackCommand>,
+        UnboundedReceiver<PlaybackEvent>,
+    ) {
+        let queue = names
+            .iter()
+            .map(|name| {
+                let path = dir.join(format!("{name}.flac"));
+                let contents = if name.starts_with("broken") {
+                    ""
+                } else {
+                    "fLaC"
+                };
+                fs::write(&path, contents).expect("couldn't create the track");
+
+                cx.update(|cx| QueueItemData::new(cx, path, None, None))
+            })
+            .collect();
+
+        let (mut thread, commands, events) = test_thread(queue);
+        thread.media_provider = Some(Box::new(FakeProvider::default()));
+        thread.use_device_provider(DeviceProviderKind::Dummy, false);
+        thread.recreate_stream(true, None);
+
+        (thread, commands, events)
+    }
+
+    /// Returns the name of the track that's open, without its extension.
+    fn current_track(thread: &PlaybackThread) -> Option<&str> {
+        thread.current_path.as_deref()?.file_stem()?.to_str()
+    }
+
+    #[gpui::test]
+    fn moves_on_from_tracks_that_cant_be_opened(cx: &mut TestAppContext) {
+        let dir = tempfile::tempdir().unwrap();
+        let names = ["first", "broken", "last"];
+        let (mut thread, _commands, _events) = playing_thread(cx, dir.path(), &names);
+
+        thread.jump(0);
+        assert_eq!(current_track(&thread), Some("first"));
+
+        // the first track ends, and the one after it can't be opened
+        thread.next(false);
+        assert_eq!(current_track(&thread), Some("last"));
+        assert_eq!(thread.current_index(), Some(2));
+
+        thread.main_loop();
+        assert_eq!(thread.state, PlaybackState::Playing);
+    }
+
+    #[gpui::test]
+    fn reports_tracks_that_cant_be_opened(cx: &mut TestAppContext) {
+        let dir = tempfile::tempdir().unwrap();
+        let (mut thread, commands, mut events) = playing_thread(cx, dir.path(), &[]);
+        let broken = dir.path().join("broken.flac");
+        fs::write(&broken, "").unwrap();
+
+        commands
+            .send(PlaybackCommand::Open(broken.clone()))
+            .unwrap();
+        thread.command_intake();
+
+        let errors: Vec<_> = drain(&mut events)
+            .into_iter()
+            .filter_map(|event| match event {
+                PlaybackEvent::Error(PlaybackErrorKind::Track, message) => Some(message),
+                _ => None,
+            })
+            .collect();
+        let expected = format!(
+            "Couldn't play '{}': Failed to process media: Unable to open file: File is corrupt",
+            broken.display()
+        );
+        assert_eq!(errors, [expected]);
+    }
+
+    /// Returns the position in the queue that was last sent to the UI, after checking that it's
+    /// where the open track is.
+    fn sent_position(
+        thread: &PlaybackThread,
+        events: &mut UnboundedReceiver<PlaybackEvent>,
+    ) -> Option<usize> {
+        let position = drain(events)
+            .into_iter()
+            .rev()
+            .find_map(|event| match event {
+                PlaybackEvent::QueuePositionChanged(position) => Some(position),
+                _ => None,
+            })
+            .expect("the position wasn't sent");
+
+        let queue = thread.queue.read().unwrap();
+        let item = position.map(|idx| queue[idx].get_path());
+        assert_eq!(item, thread.current_path.as_ref());
+
+        position
+    }
+
+    const TRACKS: [&str; 5] = ["a", "b", "c", "d", "e"];
+
+    #[gpui::test]
+    fn keeps_the_position_when_shuffling(cx: &mut TestAppContext) {
+        let dir = tempfile::tempdir().unwrap();
+        let (mut thread, _commands, mut events) = playing_thread(cx, dir.path(), &TRACKS);
+
+        thread.jump(2);
+        assert_eq!(sent_position(&thread, &mut events), Some(2));
+
+        // only the tracks after the current one are shuffled
+        thread.toggle_shuffle();
+        assert_eq!(sent_position(&thread, &mut events), Some(2));
+        assert_eq!(current_track(&thread), Some("c"));
+
+        thread.next(true);
+        assert_eq!(sent_position(&thread, &mut events), Some(3));
+        let next = current_track(&thread).unwrap().to_owned();
+
+        thread.toggle_shuffle();
+        let unshuffled = TRACKS.iter().position(|name| *name == next);
+        assert_eq!(sent_position(&thread, &mut events), unshuffled);
+    }
+
+    #[gpui::test]
+    fn keeps_the_position_when_removing_earlier_tracks(cx: &mut TestAppContext) {
+        let dir = tempfile::tempdir().unwrap();
+        let (mut thread, _commands, mut events) = playing_thread(cx, dir.path(), &TRACKS);
+
+        thread.jump(3);
+        thread.remove_items(vec![0, 2]);
+        assert_eq!(sent_position(&thread, &mut events), Some(1));
+        assert_eq!(current_track(&thread), Some("d"));
+
+        thread.remove(0);
+        assert_eq!(sent_position(&thread, &mut events), Some(0));
+        assert_eq!(current_track(&thread), Some("d"));
+
+        // removing the current track plays the one that followed it
+        thread.remove(0);
+        assert_eq!(sent_position(&thread, &mut events), Some(0));
+        assert_eq!(current_track(&thread), Some("e"));
+    }
+
+    #[gpui::test]
+    fn removes_tracks_from_the_unshuffled_queue_too(cx: &mut TestAppContext) {
+        let dir = tempfile::tempdir().unwrap();
+        let (mut thread, _commands, mut events) = playing_thread(cx, dir.path(), &TRACKS);
+
+        thread.jump(0);
+        thread.toggle_shuffle();
+        let removed = thread.queue.read().unwrap()[1].get_path().clone();
+        thread.remove(1);
+
+        thread.toggle_shuffle();
+        assert_eq!(sent_position(&thread, &mut events), Some(0));
+        assert_eq!(current_track(&thread), Some("a"));
+
+        let queue = thread.queue.read().unwrap();
+        assert_eq!(queue.len(), TRACKS.len() - 1);
+        assert!(queue.iter().all(|item| *item.get_path() != removed));
+    }
+
+    #[gpui::test]
+    fn jumps_to_tracks_in_the_unshuffled_queue(cx: &mut TestAppContext) {
+        let dir = tempfile::tempdir().unwrap();
+        let (mut thread, _commands, mut events) = playing_thread(cx, dir.path(), &TRACKS);
+
+        thread.jump_unshuffled(1);
+        assert_eq!(sent_position(&thread, &mut events), Some(1));
+        assert_eq!(current_track(&thread), Some("b"));
+
+        thread.toggle_shuffle();
+        thread.jump_unshuffled(3);
+        assert!(sent_position(&thread, &mut events).is_some());
+        assert_eq!(current_track(&thread), Some("d"));
+
+        // there's nothing to jump to past the end of the queue
+        thread.jump_unshuffled(TRACKS.len());
+        assert!(drain(&mut events).is_empty());
+        assert_eq!(current_track(&thread), Some("d"));
+    }
+
+    /// Returns the names of the items that were last handed back to the UI to drop their data.
+    fn removed_tracks(events: &mut UnboundedReceiver<PlaybackEvent>) -> Vec<String> {
+        drain(events)
+            .into_iter()
+            .rev()
+            .find_map(|event| match event {
+                PlaybackEvent::QueueItemsRemoved(items) => Some(items),
+                _ => None,
+            })
+            .expect("no items were removed")
+            .iter()
+            .map(|item| {
+                item.get_path()
+                    .file_stem()
+                    .unwrap()
+                    .to_string_lossy()
+                    .into()
+            })
+            .collect()
+    }
+
+    #[gpui::test]
+    fn hands_back_the_items_removed_from_the_queue(cx: &mut TestAppContext) {
+        let dir = tempfile::tempdir().unwrap();
+        let (mut thread, _commands, mut events) = playing_thread(cx, dir.path(), &TRACKS);
+
+        // the items that are queued again keep their data
+        let queue = thread.queue.read().unwrap().clone();
+        let extra = dir.path().join("f.flac");
+        fs::write(&extra, "fLaC").unwrap();
+        let extra = cx.update(|cx| QueueItemData::new(cx, extra, None, None));
+        thread.replace_queue(vec![queue[1].clone(), extra, queue[3].clone()]);
+        assert_eq!(removed_tracks(&mut events), ["a", "c", "e"]);
+
+        thread.clear_queue();
+        assert_eq!(removed_tracks(&mut events), ["b", "f", "d"]);
+
+        // nothing is sent when there was nothing to remove
+        thread.clear_queue();
+        assert!(
+            drain(&mut events)
+                .iter()
+                .all(|event| !matches!(event, PlaybackEvent::QueueItemsRemoved(_)))
+        );
+    }
+
+    /// The names of the devices that are plugged in, shared by [FakeDeviceProvider] and the
+    /// devices and streams it creates.
+    type Plugged = Arc<Mutex<Vec<&'static str>>>;
+
+    /// Plays on the first of its devices that's plugged in, like a system that moves its default
+    /// output to another device when the one it was using is unplugged.
+    struct FakeDeviceProvider {
+        plugged: Plugged,
+    }
+
+    impl FakeDeviceProvider {
+        fn device(&self, name: &'static str) -> Box<dyn Device> {
+            Box::new(FakeDevice {
+                name,
+                plugged: self.plugged.clone(),
+            })
+        }
+    }
+
+    impl DeviceProvider for FakeDeviceProvider {
+        fn initialize(&mut self) -> Result<(), InitializationError> {
+            Ok(())
+        }
+
+        fn get_devices(&mut self) -> Result<Vec<Box<dyn Device>>, ListError> {
+            let plugged = self.plugged.lock().unwrap().clone();
+            Ok(plugged.into_iter().map(|name| self.device(name)).collect())
+        }
+
+        fn get_default_device(&mut self) -> Result<Box<dyn Device>, FindError> {
+            let first = self.plugged.lock().unwrap().first().copied();
+            first
+                .map(|name| self.device(name))
+                .ok_or(FindError::DeviceDoesNotExist)
+        }
+
+        fn get_device_by_uid(&mut self, id: &str) -> Result<Box<dyn Device>, FindError> {
+            let found = self
+                .plugged
+                .lock()
+                .unwrap()
+                .iter()
+                .find(|name| **name == id)
+                .copied();
+            found
+                .map(|name| self.device(name))
+                .ok_or(FindError::DeviceDoesNotExist)
+        }
+    }
+
+    /// A device named after what it is, which otherwise works like the dummy device.
+    struct FakeDevice {
+        name: &'static str,
+        plugged: Plugged,
+    }
+
+    impl Device for FakeDevice {
+        fn open_device(
+            &mut self,
+            format: FormatInfo,
+        ) -> Result<Box<dyn OutputStream>, DeviceOpenError> {
+            Ok(Box::new(FakeOutput {
+                stream: DummyDevice {}.open_device(format)?,
+                name: self.name,
+                plugged: self.plugged.clone(),
+            }))
+        }
+
+        fn get_supported_formats(&self) -> Result<Vec<SupportedFormat>, InfoError> {
+            DummyDevice {}.get_supported_formats()
+        }
+
+        fn get_default_format(&self) -> Result<FormatInfo, InfoError> {
+            DummyDevice {}.get_default_format()
+        }
+
+        fn get_name(&self) -> Result<String, InfoError> {
+            Ok(self.name.to_owned())
+        }
+
+        fn get_uid(&self) -> Result<String, InfoError> {
+            Ok(self.name.to_owned())
+        }
+
+        fn requires_matching_format(&self) -> bool {
+            true
+        }
+    }
+
+    /// A dummy stream that fails to take any more samples once its device is unplugged.
+    struct FakeOutput {
+        stream: Box<dyn OutputStream>,
+        name: &'static str,
+        plugged: Plugged,
+    }
+
+    impl OutputStream for FakeOutput {
+        fn submit_frame(&mut self, frame: PlaybackFrame) -> Result<(), SubmissionError> {
+            if !self.plugged.lock().unwrap().contains(&self.name) {
+                return Err(SubmissionError::Unknown("device unplugged".to_owned()));
+            }
+
+            self.stream.submit_frame(frame)
+        }
+
+        fn close_stream(&mut self) -> Result<(), DeviceCloseError> {
+            self.stream.close_stream()
+        }
+
+        fn needs_input(&self) -> bool {
+            self.stream.needs_input()
+        }
+
+        fn get_current_format(&self) -> Result<&FormatInfo, InfoError> {
+            self.stream.get_current_format()
+        }
+
+        fn play(&mut self) -> Result<(), StateError> {
+            self.stream.play()
+        }
+
+        fn pause(&mut self) -> Result<(), StateError> {
+            self.stream.pause()
+        }
+
+        fn reset(&mut self) -> Result<(), ResetError> {
+            self.stream.reset()
+        }
+
+        fn set_volume(&mut self, volume: f64) -> Result<(), StateError> {
+            self.stream.set_volume(volume)
+        }
+
+        fn frames_consumed(&self) -> Option<u64> {
+            self.stream.frames_consumed()
+        }
+    }
+
+    /// Creates a thread like [playing_thread] that's playing a track on the first of the given
+    /// devices, and then unplugs that device.
+    fn unplugging_thread(
+        cx: &mut TestAppContext,
+        dir: &Path,
+        devices: &[&'static str],
+    ) -> (PlaybackThread, UnboundedReceiver<PlaybackEvent>) {
+        let (mut thread, _commands, mut events) = playing_thread(cx, dir, &TRACKS);
+        let plugged = Plugged::default();
+        *plugged.lock().unwrap() = devices.to_vec();
+        thread.device_provider = Some(Box::new(FakeDeviceProvider {
+            plugged: plugged.clone(),
+        }));
+        thread.recreate_stream(true, None);
+
+        thread.jump(0);
+        thread.play_audio();
+        assert_eq!(thread.state, PlaybackState::Playing);
+        drain(&mut events);
+
+        plugged.lock().unwrap().remove(0);
+
+        (thread, events)
+    }
+
+    /// Returns what the UI was told about the device that playback was on being removed.
+    fn sent_removal(
+        events: &mut UnboundedReceiver<PlaybackEvent>,
+    ) -> Vec<(String, String, DeviceRemovalPolicy)> {
+        drain(events)
+            .into_iter()
+            .filter_map(|event| match event {
+                PlaybackEvent::DeviceRemoved {
+                    removed,
+                    replacement,
+                    policy,
+                } => Some((removed, replacement, policy)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[gpui::test]
+    fn pauses_when_headphones_are_unplugged(cx: &mut TestAppContext) {
+        let dir = tempfile::tempdir().unwrap();
+        let devices = ["Headphones (USB Audio)", "Speakers (USB Audio)"];
+        let (mut thread, mut events) = unplugging_thread(cx, dir.path(), &devices);
+
+        // the stream fails to take the next samples, and is recreated on the speakers
+        thread.play_audio();
+
+        assert_eq!(
+            sent_removal(&mut events),
+            [(
+                devices[0].to_owned(),
+                devices[1].to_owned(),
+                DeviceRemovalPolicy::Pause
+            )]
+        );
+        assert_eq!(thread.state, PlaybackState::Paused);
+        assert_eq!(
+            thread.device.as_ref().unwrap().get_name().unwrap(),
+            devices[1]
+        );
+    }
+
+    #[gpui::test]
+    fn keeps_playing_when_speakers_are_unplugged(cx: &mut TestAppContext) {
+        let dir = tempfile::tempdir().unwrap();
+        let devices = ["Speakers (USB Audio)", "HDA Intel PCH, HDMI 0"];
+        let (mut thread, mut events) = unplugging_thread(cx, dir.path(), &devices);
+
+        thread.play_audio();
+
+        assert_eq!(
+            sent_removal(&mut events),
+            [(
+                devices[0].to_owned(),
+                devices[1].to_owned(),
+                DeviceRemovalPolicy::Continue
+            )]
+        );
+        assert_eq!(thread.state, PlaybackState::Playing);
+
+        // the samples that failed were played on the new device
+        let consumed = thread.stream.as_ref().unwrap().frames_consumed();
+        assert!(consumed.is_some_and(|frames| frames > 0));
+    }
+
+    /// Which track repeat-one plays again when the track at `queue_next - 1` ends on its own.
+    fn repeated(queue_next: usize, queue_len: usize) -> Option<usize> {
+        track_to_repeat(RepeatState::RepeatingOne, false, queue_next, queue_len)
+    }
+
+    #[test]
+    fn repeats_the_current_track_when_it_ends() {
+        assert_eq!(repeated(3, 5), Some(2));
+        assert_eq!(repeated(5, 5), Some(4));
+    }
+
+    #[test]
+    fn skipping_moves_on_while_repeating_one() {
+        assert_eq!(track_to_repeat(RepeatState::RepeatingOne, true, 3, 5), None);
+    }
+
+    #[test]
+    fn only_repeat_one_repeats_a_track() {
+        for repeat in [RepeatState::NotRepeating, RepeatState::Repeating] {
+            assert_eq!(track_to_repeat(repeat, false, 3, 5), None);
+        }
+    }
+
+    #[test]
+    fn nothing_is_repeated_before_anything_has_played() {
+        assert_eq!(repeated(0, 5), None);
+        // the queue was cleared after the track started
+        assert_eq!(repeated(3, 0), None);
+    }
+
+    #[test]
+    fn removes_each_item_once() {
+        let removal = QueueRemoval::new(vec![4, 1, 4, 9, 2], 5, 0);
+
+        assert_eq!(removal.indices, [1, 2, 4]);
+    }
+
+    #[test]
+    fn moves_the_position_back_past_removed_items() {
+        // playing the item at 3
+        assert_eq!(
+            QueueRemoval::new(vec![0, 2, 5], 6, 4),
+            QueueRemoval {
+                indices: vec![0, 2, 5],
+                removed_current: false,
+                removed_before_next: 2,
+            }
+        );
+    }
+
+    #[test]
+    fn notices_when_the_current_track_is_removed() {
+        let removal = QueueRemoval::new(vec![3, 4], 6, 4);
+
+        assert!(removal.removed_current);
+        assert_eq!(removal.removed_before_next, 1);
+    }
+
+    #[test]
+    fn nothing_is_current_before_playback_starts() {
+        let removal = QueueRemoval::new(vec![0, 1], 6, 0);
+
+        assert!(!removal.removed_current);
+        assert_eq!(removal.removed_before_next, 0);
+    }
+
+    fn unqueued_paths(queue: &[&str], items: &[&str]) -> Vec<PathBuf> {
+        let paths = |paths: &[&str]| paths.iter().map(PathBuf::from).collect::<Vec<_>>();
+
+        unqueued(&paths(queue), paths(items), PathBuf::as_path)
+    }
+
+    #[test]
+    fn skips_items_already_in_the_queue() {
+        let unqueued = unqueued_paths(&["/m/a.flac", "/m/b.flac"], &["/m/b.flac", "/m/c.flac"]);
+
+        assert_eq!(unqueued, [PathBuf::from("/m/c.flac")]);
+    }
+
+    #[test]
+    fn skips_repeats_within_the_new_items() {
+        let unqueued = unqueued_paths(&[], &["/m/a.flac", "/m/b.flac", "/m/a.flac"]);
+
+        assert_eq!(
+            unqueued,
+            [PathBuf::from("/m/a.flac"), PathBuf::from("/m/b.flac")]
+        );
+    }
+
+    #[test]
+    fn skips_the_same_file_reached_another_way() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("album")).unwrap();
+        fs::write(dir.path().join("album/a.flac"), b"").unwrap();
+
+        let queue = [dir.path().join("album/a.flac")];
+        let items = vec![dir.path().join("album/../album/a.flac")];
+
+        assert!(unqueued(&queue, items, PathBuf::as_path).is_empty());
     }
 }
@@ -0,0 +1,235 @@
+use std::time::{Duration, Instant, SystemTime};
+
+/// How long the device can go without taking any samples, while the playback thread keeps
+/// submitting them, before the stream is considered stalled.
+const STALL_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// How far the clock can jump between two iterations of the playback loop before the system is
+/// assumed to have been suspended. Iterations are normally a few milliseconds apart.
+const SUSPEND_GAP: Duration = Duration::from_secs(5);
+
+/// How often new underruns are reported, at most.
+const UNDERRUN_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Watches for the output device silently stopping, which some devices do after the system is
+/// suspended and resumed: the stream keeps accepting samples without returning errors, but never
+/// plays them.
+///
+/// The frames submitted to the stream are compared with the frames the device reports it has
+/// consumed (see [crate::devices::traits::OutputStream::frames_consumed]). If the device hasn't
+/// consumed anything for a while, even though more has been submitted, the stream has stalled.
+pub struct OutputWatchdog {
+    /// The number of frames submitted to the current stream.
+    submitted: u64,
+    /// The number of frames the device had consumed when it was last seen making progress, and
+    /// when that was, along with how many frames had been submitted by then.
+    consumed: Option<u64>,
+    last_progress: Instant,
+    submitted_at_progress: u64,
+    /// When the playback loop last ran, by both the monotonic and the wall clock.
+    last_tick: Option<(Instant, SystemTime)>,
+}
+
+impl Default for OutputWatchdog {
+    fn default() -> Self {
+        OutputWatchdog {
+            submitted: 0,
+            consumed: None,
+            last_progress: Instant::now(),
+            submitted_at_progress: 0,
+            last_tick: None,
+        }
+    }
+}
+
+impl OutputWatchdog {
+    /// Starts watching again from scratch. Called whenever the stream is recreated, and when
+    /// playback resumes, so that time spent paused doesn't count as a stall.
+    pub fn reset(&mut self) {
+        self.submitted = 0;
+        self.consumed = None;
+        self.last_progress = Instant::now();
+        self.submitted_at_progress = 0;
+    }
+
+    /// Records that the given number of frames were submitted to the stream.
+    pub fn submitted(&mut self, frames: usize) {
+        self.submitted += frames as u64;
+    }
+
+    /// Compares what has been submitted with what the device has consumed so far (None if the
+    /// stream can't tell). Returns how long the device has gone without consuming anything if
+    /// it has stalled.
+    pub fn check(&mut self, consumed: Option<u64>) -> Option<Duration> {
+        let consumed = consumed?;
+        let now = Instant::now();
+
+        if self.consumed != Some(consumed) {
+            self.consumed = Some(consumed);
+            self.last_progress = now;
+            self.submitted_at_progress = self.submitted;
+            return None;
+        }
+
+        let stalled_for = now.duration_since(self.last_progress);
+        let still_submitting = self.submitted > self.submitted_at_progress;
+
+        (stalled_for > STALL_TIMEOUT && still_submitting).then_some(stalled_for)
+    }
+
+    /// Called on every iteration of the playback loop. Returns how long the loop didn't run for,
+    /// if the gap is long enough that the system was probably suspended.
+    ///
+    /// The monotonic clock doesn't advance while the system is suspended on every platform, so
+    /// the wall clock is checked as well.
+    pub fn tick(&mut self) -> Option<Duration> {
+        let now = (Instant::now(), SystemTime::now());
+        let (instant, wall) = self.last_tick.replace(now)?;
+
+        let monotonic_gap = now.0.duration_since(instant);
+        // the wall clock can go backwards, when it's adjusted
+        let wall_gap = now.1.duration_since(wall).unwrap_or_default();
+        let gap = monotonic_gap.max(wall_gap);
+
+        (gap > SUSPEND_GAP).then_some(gap)
+    }
+}
+
+/// Keeps track of the device running out of samples to play (see
+/// [crate::devices::traits::OutputStream::underruns]), so that new underruns can be reported every
+/// so often rather than for every frame.
+pub struct UnderrunMonitor {
+    /// The current stream's count when it was last checked, or None if it hasn't been since the
+    /// monitor was reset.
+    last: Option<u64>,
+    last_report: Instant,
+    /// The number of underruns across every stream so far.
+    total: u64,
+}
+
+impl Default for UnderrunMonitor {
+    fn default() -> Self {
+        UnderrunMonitor {
+            last: None,
+            last_report: Instant::now(),
+            total: 0,
+        }
+    }
+}
+
+impl UnderrunMonitor {
+    /// Starts counting from the stream's current count at the next check. Called whenever the
+    /// stream is recreated, and when playback resumes, since the device runs dry whenever
+    /// playback stops and that isn't an underrun.
+    pub fn reset(&mut self) {
+        self.last = None;
+    }
+
+    /// Takes the stream's count (None if it can't tell). Returns how many underruns there have
+    /// been since the last report, if there have been any and it's time for another report.
+    pub fn check(&mut self, count: Option<u64>) -> Option<u64> {
+        let count = count?;
+        let now = Instant::now();
+
+        let Some(last) = self.last else {
+            self.last = Some(count);
+            return None;
+        };
+
+        if now.duration_since(self.last_report) < UNDERRUN_INTERVAL || count <= last {
+            return None;
+        }
+
+        self.last = Some(count);
+        self.last_report = now;
+        self.total += count - last;
+
+        Some(count - last)
+    }
+
+    /// The number of underruns across every stream so far.
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ago(secs: u64) -> Instant {
+        Instant::now() - Duration::from_secs(secs)
+    }
+
+    #[test]
+    fn progress_is_not_a_stall() {
+        let mut watchdog = OutputWatchdog::default();
+
+        assert_eq!(watchdog.check(Some(0)), None);
+        watchdog.submitted(1024);
+        watchdog.last_progress = ago(10);
+        assert_eq!(watchdog.check(Some(512)), None);
+    }
+
+    #[test]
+    fn notices_when_the_device_stops_consuming() {
+        let mut watchdog = OutputWatchdog::default();
+        watchdog.check(Some(512));
+        watchdog.submitted(1024);
+        watchdog.last_progress = ago(3);
+
+        let stalled_for = watchdog
+            .check(Some(512))
+            .expect("the stream should be stalled");
+        assert!(stalled_for >= Duration::from_secs(3));
+    }
+
+    #[test]
+    fn nothing_submitted_is_not_a_stall() {
+        let mut watchdog = OutputWatchdog::default();
+        watchdog.check(Some(512));
+        watchdog.last_progress = ago(3);
+
+        assert_eq!(watchdog.check(Some(512)), None);
+        assert_eq!(watchdog.check(None), None);
+    }
+
+    #[test]
+    fn notices_long_gaps_between_ticks() {
+        let mut watchdog = OutputWatchdog::default();
+
+        assert_eq!(watchdog.tick(), None);
+        assert_eq!(watchdog.tick(), None);
+
+        watchdog.last_tick = Some((ago(10), SystemTime::now()));
+        assert!(watchdog.tick().is_some());
+    }
+
+    #[test]
+    fn reports_new_underruns_every_so_often() {
+        let mut monitor = UnderrunMonitor::default();
+
+        // the first count is where counting starts from
+        assert_eq!(monitor.check(Some(5)), None);
+        assert_eq!(monitor.check(Some(7)), None);
+
+        monitor.last_report = ago(2);
+        assert_eq!(monitor.check(Some(8)), Some(3));
+        assert_eq!(monitor.total(), 3);
+    }
+
+    #[test]
+    fn counts_underruns_across_streams() {
+        let mut monitor = UnderrunMonitor::default();
+        monitor.check(Some(0));
+        monitor.last_report = ago(2);
+        monitor.check(Some(2));
+
+        monitor.reset();
+        monitor.check(Some(10));
+        monitor.last_report = ago(2);
+
+        assert_eq!(monitor.check(Some(11)), Some(1));
+        assert_eq!(monitor.total(), 3);
+    }
+}
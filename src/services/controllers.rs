@@ -3,6 +3,8 @@ mod macos;
 #[cfg(target_os = "linux")]
 mod mpris;
 #[cfg(target_os = "windows")]
+mod taskbar;
+#[cfg(target_os = "windows")]
 mod windows;
 
 use std::path::{Path, PathBuf};
@@ -24,6 +26,7 @@ use crate::{
         thread::PlaybackState,
     },
     ui::models::{ImageEvent, Models, PlaybackInfo},
+    util::round_millis,
 };
 
 /// Initialize a new [`PlaybackController`]. All playback controllers must implement this trait.
@@ -90,6 +93,10 @@ pub trait PlaybackController: Send {
     /// Indicates that a new file has started playing. The metadata, duration, position, and album
     /// art should be reset to default/empty values when this event is recieved.
     async fn new_file(&mut self, path: &Path) -> anyhow::Result<()>;
+
+    /// Indicates that the queue has changed. `queue_length` is the number of tracks in the
+    /// queue, which is zero when the queue is empty.
+    async fn queue_changed(&mut self, queue_length: usize) -> anyhow::Result<()>;
 }
 
 #[derive(Clone)]
@@ -142,6 +149,18 @@ impl ControllerBridge {
             .unwrap();
     }
 
+    pub fn seek_forward(&self, secs: f64) {
+        self.playback_thread
+            .send(PlaybackCommand::SeekForward(secs))
+            .unwrap();
+    }
+
+    pub fn seek_backward(&self, secs: f64) {
+        self.playback_thread
+            .send(PlaybackCommand::SeekBackward(secs))
+            .unwrap();
+    }
+
     pub fn set_volume(&self, volume: f64) {
         self.playback_thread
             .send(PlaybackCommand::SetVolume(volume))
@@ -180,6 +199,7 @@ enum PbcEvent {
     RepeatStateChanged(RepeatState),
     PlaybackStateChanged(PlaybackState),
     ShuffleStateChanged(bool),
+    QueueChanged(usize),
 }
 
 impl PbcEvent {
@@ -194,6 +214,7 @@ impl PbcEvent {
             Self::RepeatStateChanged(state) => pbc.repeat_state_changed(*state).await,
             Self::PlaybackStateChanged(state) => pbc.playback_state_changed(*state).await,
             Self::ShuffleStateChanged(shuffle) => pbc.shuffle_state_changed(*shuffle).await,
+            Self::QueueChanged(length) => pbc.queue_changed(*length).await,
         }
     }
 }
@@ -202,6 +223,7 @@ pub fn register_pbc_event_handlers(cx: &mut App) {
     let models = cx.global::<Models>();
     let metadata = models.metadata.clone();
     let albumart = models.albumart.clone();
+    let queue = models.queue.clone();
 
     cx.observe(&metadata, |e, cx| {
         let meta = e.read(cx).clone();
@@ -233,7 +255,8 @@ pub fn register_pbc_event_handlers(cx: &mut App) {
     cx.observe(&position, |e, cx| {
         let &pos = e.read(cx);
         let PbcHandle(tx, _) = cx.global();
-        if let Err(err) = tx.send(PbcEvent::PositionChanged(pos)) {
+        // controllers are given the position and duration in seconds
+        if let Err(err) = tx.send(PbcEvent::PositionChanged(pos / 1000)) {
             error!(msg = ?err.0, "failed to send pbc event: {err}");
         }
     })
@@ -242,7 +265,7 @@ pub fn register_pbc_event_handlers(cx: &mut App) {
     cx.observe(&duration, |e, cx| {
         let &dur = e.read(cx);
         let PbcHandle(tx, _) = cx.global();
-        if let Err(err) = tx.send(PbcEvent::DurationChanged(dur)) {
+        if let Err(err) = tx.send(PbcEvent::DurationChanged(round_millis(dur))) {
             error!(msg = ?err.0, "failed to send pbc event: {err}");
         }
     })
@@ -294,6 +317,20 @@ pub fn register_pbc_event_handlers(cx: &mut App) {
         }
     })
     .detach();
+
+    cx.observe(&queue, |e, cx| {
+        let length = e
+            .read(cx)
+            .data
+            .read()
+            .expect("couldn't get the queue")
+            .len();
+        let PbcHandle(tx, _) = cx.global();
+        if let Err(err) = tx.send(PbcEvent::QueueChanged(length)) {
+            error!(msg = ?err.0, "failed to send pbc event: {err}");
+        }
+    })
+    .detach();
 }
 
 pub fn init_pbc_task(cx: &mut App, window: &Window) {
@@ -333,6 +370,16 @@ pub fn init_pbc_task(cx: &mut App, window: &Window) {
 
     #[cfg(target_os = "windows")]
     {
+        match taskbar::TaskbarController::init(bridge.clone(), rwh) {
+            Ok(taskbar_pc) => {
+                list.insert("taskbar".to_string(), taskbar_pc);
+            }
+            Err(err) => {
+                error!(?err, "Failed to initialize TaskbarController: {err}");
+                warn!("Taskbar progress and buttons will be unavailable.");
+            }
+        }
+
         if let Ok(windows_pc) = windows::WindowsController::init(bridge, rwh) {
             list.insert("windows".to_string(), windows_pc);
         } else {
@@ -308,6 +308,9 @@ impl PlaybackController for MacMediaPlayerController {
     async fn shuffle_state_changed(&mut self, _shuffling: bool) -> anyhow::Result<()> {
         Ok(())
     }
+    async fn queue_changed(&mut self, _queue_length: usize) -> anyhow::Result<()> {
+        Ok(())
+    }
 }
 
 impl InitPlaybackController for MacMediaPlayerController {
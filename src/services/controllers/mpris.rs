@@ -215,12 +215,14 @@ impl PlayerInterface for MprisControllerServer {
     }
 
     async fn seek(&self, offset: Time) -> fdo::Result<()> {
-        let data = self.data.read().await;
+        // the offset is relative to the current position, which the playback thread knows more
+        // precisely than the last position it reported
+        let secs = offset.as_micros() as f64 / 1_000_000.0;
 
-        if let Some(position) = data.last_position {
-            let offset = offset.as_secs();
-            self.bridge
-                .seek(position.saturating_add_signed(offset) as f64);
+        if secs >= 0.0 {
+            self.bridge.seek_forward(secs);
+        } else {
+            self.bridge.seek_backward(-secs);
         }
 
         Ok(())
@@ -478,6 +480,10 @@ impl PlaybackController for MprisController {
         Ok(())
     }
 
+    async fn queue_changed(&mut self, _queue_length: usize) -> anyhow::Result<()> {
+        Ok(())
+    }
+
     async fn new_file(&mut self, path: &Path) -> anyhow::Result<()> {
         let mut data = self.data.write().await;
         data.last_file = Some(path.to_path_buf());
@@ -0,0 +1,469 @@
+//! Playback progress on the taskbar icon, and play/pause/next/previous buttons in the taskbar
+//! thumbnail.
+//!
+//! `ITaskbarList3` has to be used from the thread that owns the window, but playback controllers
+//! run on the runtime. The controller therefore only posts the latest state to the window as
+//! private messages, and a subclass of the window's procedure (which runs on the UI thread)
+//! applies it to the taskbar. The same subclass receives the thumbnail buttons' `WM_COMMAND`
+//! messages.
+
+use std::{cell::RefCell, ffi::c_void, path::Path};
+
+use async_trait::async_trait;
+use raw_window_handle::RawWindowHandle;
+use tracing::{error, warn};
+use windows::{
+    Win32::{
+        Foundation::{HWND, LPARAM, LRESULT, WPARAM},
+        System::Com::{CLSCTX_INPROC_SERVER, CoCreateInstance},
+        UI::{
+            Shell::{
+                DefSubclassProc, ITaskbarList3, RemoveWindowSubclass, SetWindowSubclass,
+                TBPF_INDETERMINATE, TBPF_NOPROGRESS, TBPF_NORMAL, TBPF_PAUSED, TBPFLAG, THB_FLAGS,
+                THB_ICON, THB_TOOLTIP, THBF_DISABLED, THBF_ENABLED, THBN_CLICKED, THUMBBUTTON,
+                TaskbarList,
+            },
+            WindowsAndMessaging::{
+                CreateIcon, DestroyIcon, HICON, PostMessageW, RegisterWindowMessageW, WM_APP,
+                WM_COMMAND, WM_NCDESTROY,
+            },
+        },
+    },
+    core::w,
+};
+
+use crate::{
+    media::metadata::Metadata,
+    playback::{events::RepeatState, thread::PlaybackState},
+    services::controllers::InitPlaybackController,
+};
+
+use super::{ControllerBridge, PlaybackController};
+
+/// Identifies the subclass installed on the window.
+const SUBCLASS_ID: usize = 0x4842;
+
+/// Carries the position (`wParam`) and duration (`lParam`) in seconds.
+const WM_TASKBAR_PROGRESS: u32 = WM_APP + 0x100;
+/// Carries the playback state (`wParam`), encoded with [`encode_state`].
+const WM_TASKBAR_STATE: u32 = WM_APP + 0x101;
+/// Carries the queue length (`wParam`).
+const WM_TASKBAR_QUEUE: u32 = WM_APP + 0x102;
+
+const BUTTON_PREVIOUS: u32 = 0;
+const BUTTON_PLAY_PAUSE: u32 = 1;
+const BUTTON_NEXT: u32 = 2;
+
+const ICON_SIZE: usize = 16;
+
+thread_local! {
+    /// The taskbar state for the window, owned by the UI thread.
+    static TASKBAR: RefCell<Option<Taskbar>> = const { RefCell::new(None) };
+}
+
+#[derive(Clone, Copy)]
+enum Glyph {
+    Play,
+    Pause,
+    Next,
+    Previous,
+}
+
+impl Glyph {
+    /// Returns whether the point (in icon pixels, from the top left) is inside the glyph.
+    fn contains(self, x: f32, y: f32) -> bool {
+        let triangle = |x: f32, left: f32, tip: f32| {
+            x >= left && x <= tip && (y - 8.0).abs() <= (tip - x) * 5.0 / (tip - left)
+        };
+
+        match self {
+            Glyph::Play => triangle(x, 4.0, 13.0),
+            Glyph::Pause => {
+                (3.0..13.0).contains(&y) && ((4.0..7.0).contains(&x) || (9.0..12.0).contains(&x))
+            }
+            Glyph::Next => {
+                triangle(x, 3.0, 10.5) || ((11.0..13.0).contains(&x) && (3.0..13.0).contains(&y))
+            }
+            Glyph::Previous => Glyph::Next.contains(ICON_SIZE as f32 - x, y),
+        }
+    }
+
+    /// Draws the glyph in white as an icon, with 4x4 supersampling for the edges.
+    fn create_icon(self) -> windows::core::Result<HICON> {
+        const SAMPLES: usize = 4;
+
+        let mut color = Vec::with_capacity(ICON_SIZE * ICON_SIZE * 4);
+
+        for row in 0..ICON_SIZE {
+            for column in 0..ICON_SIZE {
+                let mut covered = 0;
+
+                for sample in 0..SAMPLES * SAMPLES {
+                    let x = column as f32 + ((sample % SAMPLES) as f32 + 0.5) / SAMPLES as f32;
+                    let y = row as f32 + ((sample / SAMPLES) as f32 + 0.5) / SAMPLES as f32;
+
+                    if self.contains(x, y) {
+                        covered += 1;
+                    }
+                }
+
+                let alpha = (covered * 255 / (SAMPLES * SAMPLES)) as u8;
+                // BGRA
+                color.extend_from_slice(&[255, 255, 255, alpha]);
+            }
+        }
+
+        // with 32-bit color the alpha channel is used, so the mask can be left empty
+        let mask = vec![0u8; ICON_SIZE * ICON_SIZE / 8];
+
+        unsafe {
+            CreateIcon(
+                None,
+                ICON_SIZE as i32,
+                ICON_SIZE as i32,
+                1,
+                32,
+                mask.as_ptr(),
+                color.as_ptr(),
+            )
+        }
+    }
+}
+
+struct Icons {
+    play: HICON,
+    pause: HICON,
+    next: HICON,
+    previous: HICON,
+}
+
+impl Icons {
+    fn new() -> windows::core::Result<Self> {
+        Ok(Icons {
+            play: Glyph::Play.create_icon()?,
+            pause: Glyph::Pause.create_icon()?,
+            next: Glyph::Next.create_icon()?,
+            previous: Glyph::Previous.create_icon()?,
+        })
+    }
+}
+
+impl Drop for Icons {
+    fn drop(&mut self) {
+        for icon in [self.play, self.pause, self.next, self.previous] {
+            unsafe {
+                DestroyIcon(icon).ok();
+            }
+        }
+    }
+}
+
+/// Everything needed to update the taskbar, kept on the UI thread.
+struct Taskbar {
+    list: ITaskbarList3,
+    bridge: ControllerBridge,
+    icons: Icons,
+    /// The message sent when the taskbar button is (re)created, such as when Explorer restarts.
+    button_created_message: u32,
+    buttons_added: bool,
+    position: u64,
+    duration: u64,
+    state: PlaybackState,
+    queue_length: usize,
+}
+
+impl Taskbar {
+    fn update_progress(&self, hwnd: HWND) -> windows::core::Result<()> {
+        let flag: TBPFLAG = match self.state {
+            PlaybackState::Stopped => TBPF_NOPROGRESS,
+            PlaybackState::Paused => TBPF_PAUSED,
+            PlaybackState::Playing if self.duration == 0 => TBPF_INDETERMINATE,
+            PlaybackState::Playing => TBPF_NORMAL,
+        };
+
+        unsafe {
+            self.list.SetProgressState(hwnd, flag)?;
+
+            if flag != TBPF_NOPROGRESS && flag != TBPF_INDETERMINATE {
+                self.list.SetProgressValue(
+                    hwnd,
+                    self.position.min(self.duration),
+                    self.duration.max(1),
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn update_buttons(&mut self, hwnd: HWND) -> windows::core::Result<()> {
+        let playing = self.state == PlaybackState::Playing;
+        let has_queue = self.queue_length > 0;
+
+        let buttons = [
+            thumb_button(BUTTON_PREVIOUS, self.icons.previous, "Previous", has_queue),
+            if playing {
+                thumb_button(BUTTON_PLAY_PAUSE, self.icons.pause, "Pause", has_queue)
+            } else {
+                thumb_button(BUTTON_PLAY_PAUSE, self.icons.play, "Play", has_queue)
+            },
+            thumb_button(BUTTON_NEXT, self.icons.next, "Next", has_queue),
+        ];
+
+        unsafe {
+            if self.buttons_added {
+                self.list.ThumbBarUpdateButtons(hwnd, &buttons)?;
+            } else {
+                // buttons can only be added once per taskbar button, after that they're updated
+                self.list.ThumbBarAddButtons(hwnd, &buttons)?;
+                self.buttons_added = true;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn button_clicked(&self, id: u32) {
+        match id {
+            BUTTON_PREVIOUS => self.bridge.previous(),
+            BUTTON_PLAY_PAUSE => self.bridge.toggle_play_pause(),
+            BUTTON_NEXT => self.bridge.next(),
+            _ => (),
+        }
+    }
+}
+
+fn thumb_button(id: u32, icon: HICON, tooltip: &str, enabled: bool) -> THUMBBUTTON {
+    let mut tip = [0u16; 260];
+    for (dest, src) in tip.iter_mut().zip(tooltip.encode_utf16()) {
+        *dest = src;
+    }
+
+    THUMBBUTTON {
+        dwMask: THB_ICON | THB_TOOLTIP | THB_FLAGS,
+        iId: id,
+        iBitmap: 0,
+        hIcon: icon,
+        szTip: tip,
+        dwFlags: if enabled { THBF_ENABLED } else { THBF_DISABLED },
+    }
+}
+
+fn encode_state(state: PlaybackState) -> usize {
+    match state {
+        PlaybackState::Stopped => 0,
+        PlaybackState::Playing => 1,
+        PlaybackState::Paused => 2,
+    }
+}
+
+fn decode_state(state: usize) -> PlaybackState {
+    match state {
+        1 => PlaybackState::Playing,
+        2 => PlaybackState::Paused,
+        _ => PlaybackState::Stopped,
+    }
+}
+
+unsafe extern "system" fn subclass_proc(
+    hwnd: HWND,
+    msg: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+    _: usize,
+    _: usize,
+) -> LRESULT {
+    let low_word = (wparam.0 & 0xFFFF) as u32;
+    let high_word = ((wparam.0 >> 16) & 0xFFFF) as u32;
+
+    let consumed = TASKBAR.with(|cell| {
+        // taskbar calls can pump messages, so this may be re-entered while the state is in use
+        let mut taskbar = cell.try_borrow_mut().ok()?;
+        let taskbar = taskbar.as_mut()?;
+
+        let result = match msg {
+            WM_COMMAND if high_word == THBN_CLICKED => {
+                taskbar.button_clicked(low_word);
+                return Some(LRESULT(0));
+            }
+            WM_TASKBAR_PROGRESS => {
+                taskbar.position = wparam.0 as u64;
+                taskbar.duration = lparam.0 as u64;
+                taskbar.update_progress(hwnd)
+            }
+            WM_TASKBAR_STATE => {
+                taskbar.state = decode_state(wparam.0);
+                taskbar
+                    .update_progress(hwnd)
+                    .and(taskbar.update_buttons(hwnd))
+            }
+            WM_TASKBAR_QUEUE => {
+                taskbar.queue_length = wparam.0;
+                taskbar.update_buttons(hwnd)
+            }
+            m if m == taskbar.button_created_message => {
+                taskbar.buttons_added = false;
+                if let Err(err) = taskbar
+                    .update_progress(hwnd)
+                    .and(taskbar.update_buttons(hwnd))
+                {
+                    warn!(?err, "Failed to update the taskbar: {err}");
+                }
+
+                // not one of ours, so the window still gets to see it
+                return None;
+            }
+            _ => return None,
+        };
+
+        if let Err(err) = result {
+            warn!(?err, "Failed to update the taskbar: {err}");
+        }
+
+        Some(LRESULT(0))
+    });
+
+    if msg == WM_NCDESTROY {
+        unregister(hwnd);
+    }
+
+    match consumed {
+        Some(result) => result,
+        None => unsafe { DefSubclassProc(hwnd, msg, wparam, lparam) },
+    }
+}
+
+/// Clears the progress overlay and removes the subclass, so nothing is left on the taskbar once
+/// the window is gone.
+fn unregister(hwnd: HWND) {
+    let taskbar = TASKBAR.with(|cell| cell.try_borrow_mut().ok().and_then(|mut t| t.take()));
+
+    if let Some(taskbar) = taskbar {
+        unsafe {
+            if let Err(err) = taskbar.list.SetProgressState(hwnd, TBPF_NOPROGRESS) {
+                warn!(?err, "Failed to clear taskbar progress: {err}");
+            }
+        }
+    }
+
+    unsafe {
+        if !RemoveWindowSubclass(hwnd, Some(subclass_proc), SUBCLASS_ID).as_bool() {
+            warn!("Failed to remove the taskbar window subclass");
+        }
+    }
+}
+
+pub struct TaskbarController {
+    /// The window's handle. `HWND` isn't `Send`, so it's kept as an integer.
+    hwnd: isize,
+    /// The current track's duration, sent along with every position update.
+    duration: u64,
+}
+
+impl TaskbarController {
+    fn post(&self, msg: u32, wparam: usize, lparam: isize) {
+        let hwnd = HWND(self.hwnd as *mut c_void);
+
+        // fails once the window has closed, in which case there's nothing left to update
+        unsafe {
+            PostMessageW(Some(hwnd), msg, WPARAM(wparam), LPARAM(lparam)).ok();
+        }
+    }
+}
+
+impl InitPlaybackController for TaskbarController {
+    fn init(
+        bridge: ControllerBridge,
+        handle: Option<RawWindowHandle>,
+    ) -> anyhow::Result<Box<dyn PlaybackController>> {
+        let Some(RawWindowHandle::Win32(handle)) = handle else {
+            anyhow::bail!("non-Win32 window handle/invalid window handle");
+        };
+
+        let hwnd = HWND(handle.hwnd.get() as *mut c_void);
+
+        let list: ITaskbarList3 =
+            unsafe { CoCreateInstance(&TaskbarList, None, CLSCTX_INPROC_SERVER)? };
+        unsafe { list.HrInit()? };
+
+        let mut taskbar = Taskbar {
+            list,
+            bridge,
+            icons: Icons::new()?,
+            button_created_message: unsafe { RegisterWindowMessageW(w!("TaskbarButtonCreated")) },
+            buttons_added: false,
+            position: 0,
+            duration: 0,
+            state: PlaybackState::Stopped,
+            queue_length: 0,
+        };
+
+        // if the taskbar button doesn't exist yet, the buttons are added once it's created
+        if let Err(err) = taskbar.update_buttons(hwnd) {
+            warn!(?err, "Couldn't add taskbar buttons yet: {err}");
+        }
+
+        TASKBAR.set(Some(taskbar));
+
+        if let Err(err) =
+            unsafe { SetWindowSubclass(hwnd, Some(subclass_proc), SUBCLASS_ID, 0) }.ok()
+        {
+            error!(?err, "Failed to subclass the window: {err}");
+            unregister(hwnd);
+            return Err(err.into());
+        }
+
+        Ok(Box::new(TaskbarController {
+            hwnd: hwnd.0 as isize,
+            duration: 0,
+        }))
+    }
+}
+
+#[async_trait]
+impl PlaybackController for TaskbarController {
+    async fn position_changed(&mut self, new_position: u64) -> anyhow::Result<()> {
+        self.post(
+            WM_TASKBAR_PROGRESS,
+            new_position as usize,
+            self.duration as isize,
+        );
+        Ok(())
+    }
+    async fn duration_changed(&mut self, new_duration: u64) -> anyhow::Result<()> {
+        self.duration = new_duration;
+        self.post(WM_TASKBAR_PROGRESS, 0, new_duration as isize);
+        Ok(())
+    }
+    async fn volume_changed(&mut self, _new_volume: f64) -> anyhow::Result<()> {
+        Ok(())
+    }
+    async fn metadata_changed(&mut self, _metadata: &Metadata) -> anyhow::Result<()> {
+        Ok(())
+    }
+    async fn album_art_changed(&mut self, _album_art: &[u8]) -> anyhow::Result<()> {
+        Ok(())
+    }
+    async fn repeat_state_changed(&mut self, _repeat_state: RepeatState) -> anyhow::Result<()> {
+        Ok(())
+    }
+    async fn playback_state_changed(
+        &mut self,
+        playback_state: PlaybackState,
+    ) -> anyhow::Result<()> {
+        self.post(WM_TASKBAR_STATE, encode_state(playback_state), 0);
+        Ok(())
+    }
+    async fn shuffle_state_changed(&mut self, _shuffling: bool) -> anyhow::Result<()> {
+        Ok(())
+    }
+    async fn queue_changed(&mut self, queue_length: usize) -> anyhow::Result<()> {
+        self.post(WM_TASKBAR_QUEUE, queue_length, 0);
+        Ok(())
+    }
+    async fn new_file(&mut self, _path: &Path) -> anyhow::Result<()> {
+        self.duration = 0;
+        self.post(WM_TASKBAR_PROGRESS, 0, 0);
+        Ok(())
+    }
+}
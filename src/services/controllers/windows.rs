@@ -210,6 +210,9 @@ impl PlaybackController for WindowsController {
 
         Ok(())
     }
+    async fn queue_changed(&mut self, _queue_length: usize) -> anyhow::Result<()> {
+        Ok(())
+    }
     async fn new_file(&mut self, path: &Path) -> anyhow::Result<()> {
         self.display.ClearAll()?;
         self.display.SetType(MediaPlaybackType::Music)?;
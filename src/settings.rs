@@ -1,47 +1,71 @@
+pub mod interface;
+pub mod persist;
 pub mod playback;
 pub mod scan;
 pub mod storage;
 
-use std::{fs::File, path::PathBuf, sync::mpsc::channel, time::Duration};
+use std::{path::PathBuf, sync::mpsc::channel, time::Duration};
 
 use gpui::{App, AppContext, AsyncApp, Entity, Global};
 use notify::{Event, RecursiveMode, Watcher};
 use serde::{Deserialize, Serialize};
 use tracing::{info, warn};
 
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+use persist::Recovery;
+
+/// The format version of the settings file. Increase this when a change to the settings can't be
+/// read by older versions.
+const SETTINGS_VERSION: u32 = 2;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
 pub struct Settings {
     #[serde(default)]
     pub scanning: scan::ScanSettings,
     #[serde(default)]
     pub playback: playback::PlaybackSettings,
+    #[serde(default)]
+    pub interface: interface::InterfaceSettings,
 }
 
-pub fn create_settings(path: &PathBuf) -> Settings {
-    let Ok(file) = File::open(path) else {
-        return Settings::default();
-    };
-    let reader = std::io::BufReader::new(file);
+/// Loads the settings file, recovering it if it's been corrupted.
+fn load_settings(path: &PathBuf) -> (Settings, Option<Recovery>) {
+    let loaded = persist::load(path, SETTINGS_VERSION);
+    (loaded.value.unwrap_or_default(), loaded.recovery)
+}
+
+/// Updates the settings and writes them back to the settings file.
+pub fn update_settings(cx: &mut App, update: impl FnOnce(&mut Settings)) {
+    let global = cx.global::<SettingsGlobal>();
+    let model = global.model.clone();
+    let path = global.path.clone();
 
-    if let Ok(settings) = serde_json::from_reader(reader) {
-        settings
-    } else {
-        warn!("Failed to parse settings file, using default settings");
-        Settings::default()
+    let settings = model.update(cx, |settings, cx| {
+        update(settings);
+        cx.notify();
+        settings.clone()
+    });
+
+    if let Err(err) = persist::save(&path, &settings, SETTINGS_VERSION) {
+        warn!("Failed to write settings file: {:?}", err);
     }
 }
 
 pub struct SettingsGlobal {
     pub model: Entity<Settings>,
+    pub path: PathBuf,
     #[allow(dead_code)]
     pub watcher: Option<Box<dyn Watcher>>,
 }
 
 impl Global for SettingsGlobal {}
 
-pub fn setup_settings(cx: &mut App, path: PathBuf) {
-    let settings = cx.new(|_| create_settings(&path));
+/// Loads the settings and reloads them whenever the file changes. Returns what had to be done to
+/// recover the settings file, if it couldn't be read.
+pub fn setup_settings(cx: &mut App, path: PathBuf) -> Option<Recovery> {
+    let (settings, recovery) = load_settings(&path);
+    let settings = cx.new(|_| settings);
     let settings_model = settings.clone(); // for the closure
+    let settings_path = path.clone();
 
     // create and setup file watcher
     let (tx, rx) = channel::<notify::Result<Event>>();
@@ -53,11 +77,12 @@ pub fn setup_settings(cx: &mut App, path: PathBuf) {
 
         let global = SettingsGlobal {
             model: settings,
+            path,
             watcher: None,
         };
 
         cx.set_global(global);
-        return;
+        return recovery;
     };
     if let Err(e) = watcher.watch(path.parent().unwrap(), RecursiveMode::Recursive) {
         warn!("failed to watch settings file: {:?}", e);
@@ -68,24 +93,36 @@ pub fn setup_settings(cx: &mut App, path: PathBuf) {
             while let Ok(event) = rx.try_recv() {
                 match event {
                     Ok(v) => {
+                        // the data directory has other files in it, including the settings file's
+                        // temporary and backup files
                         if !v.paths.iter().any(|t| t.ends_with("settings.json")) {
-                            return;
+                            continue;
                         };
                         match v.kind {
                             notify::EventKind::Create(_) | notify::EventKind::Modify(_) => {
                                 info!("Settings changed, updating...");
-                                let settings = create_settings(&path);
+                                // a mistake made while editing the file by hand shouldn't reset
+                                // the settings
+                                let settings = match persist::read(&path, SETTINGS_VERSION) {
+                                    Ok(settings) => settings.unwrap_or_default(),
+                                    Err(err) => {
+                                        warn!("Failed to parse settings file, ignoring: {err:?}");
+                                        continue;
+                                    }
+                                };
                                 settings_model
-                                    .update(app, |v, _| {
+                                    .update(app, |v, cx| {
                                         *v = settings;
+                                        cx.notify();
                                     })
                                     .expect("settings model could not be updated");
                             }
                             notify::EventKind::Remove(_) => {
                                 info!("Settings file removed, using default settings");
                                 settings_model
-                                    .update(app, |v, _| {
+                                    .update(app, |v, cx| {
                                         *v = Settings::default();
+                                        cx.notify();
                                     })
                                     .expect("settings model could not be updated");
                             }
@@ -105,8 +142,11 @@ pub fn setup_settings(cx: &mut App, path: PathBuf) {
 
     let global = SettingsGlobal {
         model: settings,
+        path: settings_path,
         watcher: Some(Box::new(watcher)),
     };
 
     cx.set_global(global);
+
+    recovery
 }
@@ -0,0 +1,81 @@
+use serde::{Deserialize, Serialize};
+
+/// The font used when no other font has been chosen, or the chosen font isn't installed.
+pub const DEFAULT_FONT_FAMILY: &str = "Inter";
+
+pub const MIN_UI_SCALE: f32 = 0.9;
+pub const MAX_UI_SCALE: f32 = 1.5;
+
+/// How much the interface size commands change the scale by.
+pub const UI_SCALE_STEP: f32 = 0.1;
+
+/// User-set interface settings.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InterfaceSettings {
+    /// The font family used throughout the interface. If the font isn't installed, Inter is used
+    /// instead.
+    ///
+    /// Defaults to Inter.
+    #[serde(default = "default_font_family")]
+    pub font_family: String,
+
+    /// How much larger or smaller the interface should be than normal, from 0.9 (90%) to 1.5
+    /// (150%). Text, and the rows of lists containing text, are scaled by this amount.
+    ///
+    /// Defaults to 1.0.
+    #[serde(default = "default_ui_scale")]
+    pub ui_scale: f32,
+
+    /// Whether or not the window title should show the track that's currently playing (for
+    /// example, "Artist – Title — Hummingbird"), so that it can be seen in the taskbar.
+    ///
+    /// Defaults to true.
+    #[serde(default = "default_now_playing_title")]
+    pub now_playing_title: bool,
+
+    /// Whether reissues and remasters that are tagged with the date the album first came out are
+    /// shown, sorted and filtered by that date. If false, the date of the reissue or remaster is
+    /// used instead.
+    ///
+    /// Defaults to true.
+    #[serde(default = "default_prefer_original_date")]
+    pub prefer_original_date: bool,
+}
+
+fn default_font_family() -> String {
+    DEFAULT_FONT_FAMILY.to_string()
+}
+
+fn default_ui_scale() -> f32 {
+    1.0
+}
+
+fn default_now_playing_title() -> bool {
+    true
+}
+
+fn default_prefer_original_date() -> bool {
+    true
+}
+
+impl InterfaceSettings {
+    /// Returns the interface scale, clamped to the supported range.
+    pub fn scale(&self) -> f32 {
+        if self.ui_scale.is_finite() {
+            self.ui_scale.clamp(MIN_UI_SCALE, MAX_UI_SCALE)
+        } else {
+            1.0
+        }
+    }
+}
+
+impl Default for InterfaceSettings {
+    fn default() -> Self {
+        Self {
+            font_family: default_font_family(),
+            ui_scale: default_ui_scale(),
+            now_playing_title: default_now_playing_title(),
+            prefer_original_date: default_prefer_original_date(),
+        }
+    }
+}
@@ -0,0 +1,305 @@
+//! Reading and writing the JSON files the app keeps its configuration in.
+//!
+//! Files are replaced atomically: the new contents are written to a temporary file next to the
+//! original, flushed to disk and then renamed over it, so a crash or a full disk can never leave a
+//! half-written file behind. The previous version is kept as a `.bak` file, which is used if the
+//! file can't be read for any other reason.
+
+use std::{
+    ffi::OsString,
+    fs::{self, File},
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use serde::{Serialize, de::DeserializeOwned};
+use serde_json::Value;
+use tracing::{error, warn};
+
+/// The key the format version is stored under, at the top level of every file.
+const VERSION_KEY: &str = "version";
+
+/// What had to be done to load a file that couldn't be read.
+#[derive(Debug, Clone)]
+pub enum Recovery {
+    /// The previous version of the file was restored from its backup.
+    Backup,
+    /// Neither the file nor its backup could be read, so the defaults are used instead. The
+    /// unreadable file was moved to the given path.
+    Reset { moved_to: PathBuf },
+}
+
+impl Recovery {
+    /// Describes what happened to the user. `what` is the name of the file's contents, like "your
+    /// settings".
+    pub fn message(&self, what: &str) -> String {
+        match self {
+            Recovery::Backup => {
+                format!("Couldn't read {what}, so the previous version was restored.")
+            }
+            Recovery::Reset { moved_to } => format!(
+                "Couldn't read {what}, so the defaults are used. The old file was kept as {}.",
+                moved_to
+                    .file_name()
+                    .map(|name| name.to_string_lossy())
+                    .unwrap_or_default()
+            ),
+        }
+    }
+}
+
+/// A file loaded with [`load`].
+pub struct Loaded<T> {
+    /// The file's contents, or None if the file doesn't exist or couldn't be recovered.
+    pub value: Option<T>,
+    pub recovery: Option<Recovery>,
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().map(OsString::from).unwrap_or_default();
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
+/// Returns the path the previous version of a file is kept at.
+pub fn backup_path(path: &Path) -> PathBuf {
+    with_suffix(path, ".bak")
+}
+
+fn parse<T: DeserializeOwned>(bytes: &[u8], version: u32, path: &Path) -> anyhow::Result<T> {
+    let mut value: Value = serde_json::from_slice(bytes)?;
+
+    // files written before versioning was added don't have a version, and are read as version 0
+    let file_version = value
+        .as_object_mut()
+        .and_then(|object| object.remove(VERSION_KEY))
+        .and_then(|version| version.as_u64())
+        .unwrap_or(0);
+
+    if file_version > version as u64 {
+        warn!(
+            "{} was written by a newer version of Hummingbird (format {file_version}, expected \
+             {version}), some of it may be ignored",
+            path.display()
+        );
+    }
+
+    Ok(serde_json::from_value(value)?)
+}
+
+/// Reads a file without trying to recover it. Returns None if the file doesn't exist.
+///
+/// This is meant for reloading files that have been edited by hand while the app is running,
+/// where a mistake should be reported rather than treated as corruption.
+pub fn read<T: DeserializeOwned>(path: &Path, version: u32) -> anyhow::Result<Option<T>> {
+    match fs::read(path) {
+        Ok(bytes) => Ok(Some(parse(&bytes, version, path)?)),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Reads a file, recovering it if it can't be read. If the backup can be read, it's restored in
+/// place of the file. Otherwise the file is moved aside, so that it isn't overwritten with the
+/// defaults and can still be fixed by hand.
+pub fn load<T: DeserializeOwned>(path: &Path, version: u32) -> Loaded<T> {
+    let err = match read(path, version) {
+        Ok(value) => {
+            return Loaded {
+                value,
+                recovery: None,
+            };
+        }
+        Err(err) => err,
+    };
+
+    warn!("Couldn't read {}: {err:?}", path.display());
+
+    let backup = backup_path(path);
+    let restored = fs::read(&backup)
+        .map_err(anyhow::Error::from)
+        .and_then(|bytes| Ok((parse::<T>(&bytes, version, path)?, bytes)));
+
+    match restored {
+        Ok((value, bytes)) => {
+            warn!("Restored {} from its backup", path.display());
+
+            // the backup is left alone, in case this goes wrong too
+            if let Err(err) = replace(path, &bytes) {
+                error!("Couldn't restore {}: {err:?}", path.display());
+            }
+
+            Loaded {
+                value: Some(value),
+                recovery: Some(Recovery::Backup),
+            }
+        }
+        Err(backup_err) => {
+            if backup.exists() {
+                warn!("Couldn't read {} either: {backup_err:?}", backup.display());
+            }
+
+            let timestamp = chrono::Local::now().format("%Y%m%d-%H%M%S");
+            let moved_to = with_suffix(path, &format!(".corrupt-{timestamp}"));
+
+            if let Err(err) = fs::rename(path, &moved_to) {
+                error!("Couldn't move {} aside: {err:?}", path.display());
+            } else {
+                warn!("Moved {} to {}", path.display(), moved_to.display());
+            }
+
+            Loaded {
+                value: None,
+                recovery: Some(Recovery::Reset { moved_to }),
+            }
+        }
+    }
+}
+
+/// Writes `value` to the file, along with its format version. The previous contents of the file
+/// are kept as its backup, as long as they could be read.
+pub fn save<T: Serialize>(path: &Path, value: &T, version: u32) -> anyhow::Result<()> {
+    let mut json = serde_json::to_value(value)?;
+    if let Some(object) = json.as_object_mut() {
+        object.insert(VERSION_KEY.to_string(), version.into());
+    }
+    let bytes = serde_json::to_vec_pretty(&json)?;
+
+    // a backup of a broken file wouldn't be any use
+    if let Ok(previous) = fs::read(path)
+        && serde_json::from_slice::<Value>(&previous).is_ok()
+    {
+        replace(&backup_path(path), &previous).context("couldn't back up the previous version")?;
+    }
+
+    replace(path, &bytes)
+}
+
+/// Atomically replaces the contents of a file.
+fn replace(path: &Path, bytes: &[u8]) -> anyhow::Result<()> {
+    let temp_path = with_suffix(path, ".tmp");
+
+    let result = File::create(&temp_path)
+        .and_then(|mut file| {
+            file.write_all(bytes)?;
+            file.sync_all()
+        })
+        .and_then(|_| fs::rename(&temp_path, path));
+
+    if let Err(err) = result {
+        fs::remove_file(&temp_path).ok();
+        return Err(err).with_context(|| format!("couldn't write {}", path.display()));
+    }
+
+    // make sure the rename itself has reached the disk
+    #[cfg(unix)]
+    if let Some(dir) = path.parent()
+        && let Ok(dir) = File::open(dir)
+    {
+        dir.sync_all().ok();
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use super::*;
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    #[serde(deny_unknown_fields)]
+    struct Config {
+        name: String,
+        count: u32,
+    }
+
+    fn config(name: &str) -> Config {
+        Config {
+            name: name.to_string(),
+            count: 3,
+        }
+    }
+
+    #[test]
+    fn loads_what_was_saved() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.json");
+
+        save(&path, &config("first"), 2).unwrap();
+        let loaded = load::<Config>(&path, 2);
+
+        assert_eq!(loaded.value, Some(config("first")));
+        assert!(loaded.recovery.is_none());
+    }
+
+    #[test]
+    fn missing_files_load_as_nothing() {
+        let dir = tempfile::tempdir().unwrap();
+        let loaded = load::<Config>(&dir.path().join("config.json"), 2);
+
+        assert!(loaded.value.is_none());
+        assert!(loaded.recovery.is_none());
+    }
+
+    #[test]
+    fn keeps_the_previous_version_as_a_backup() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.json");
+
+        save(&path, &config("first"), 2).unwrap();
+        save(&path, &config("second"), 2).unwrap();
+
+        assert_eq!(read(&backup_path(&path), 2).unwrap(), Some(config("first")));
+        assert_eq!(read(&path, 2).unwrap(), Some(config("second")));
+    }
+
+    #[test]
+    fn restores_corrupt_files_from_the_backup() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.json");
+
+        save(&path, &config("first"), 2).unwrap();
+        save(&path, &config("second"), 2).unwrap();
+        fs::write(&path, b"{\"name\": \"sec").unwrap();
+
+        let loaded = load::<Config>(&path, 2);
+
+        assert_eq!(loaded.value, Some(config("first")));
+        assert!(matches!(loaded.recovery, Some(Recovery::Backup)));
+        // the restored backup replaces the broken file
+        assert_eq!(read(&path, 2).unwrap(), Some(config("first")));
+    }
+
+    #[test]
+    fn moves_unrecoverable_files_aside() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.json");
+        fs::write(&path, b"not json").unwrap();
+
+        let loaded = load::<Config>(&path, 2);
+
+        let Some(Recovery::Reset { moved_to }) = loaded.recovery else {
+            panic!("expected the file to be reset");
+        };
+        assert!(loaded.value.is_none());
+        assert!(!path.exists());
+        assert_eq!(fs::read(moved_to).unwrap(), b"not json");
+    }
+
+    #[test]
+    fn reads_files_from_other_versions() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.json");
+
+        // written before versioning was added
+        fs::write(&path, br#"{"name": "old", "count": 3}"#).unwrap();
+        assert_eq!(read(&path, 2).unwrap(), Some(config("old")));
+
+        // written by a newer version
+        save(&path, &config("new"), 5).unwrap();
+        assert_eq!(read(&path, 2).unwrap(), Some(config("new")));
+    }
+}
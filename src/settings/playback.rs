@@ -1,7 +1,9 @@
 use serde::{Deserialize, Serialize};
 
+use crate::devices::category::DeviceCategory;
+
 /// User-set playback settings, to be passed to the playback thread.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct PlaybackSettings {
     /// Whether or not the playback thread should allow for repeating to be disabled.
     ///
@@ -28,14 +30,567 @@ pub struct PlaybackSettings {
     /// prefer this behavior)
     #[serde(default)]
     pub prev_track_jump_first: bool,
+
+    /// Whether or not the playback thread should move on to the next track early when the end of
+    /// a track is a long stretch of silence, like the gap before a hidden track on older CDs.
+    ///
+    /// Silence is only skipped once 80% of the track has been played, and never on tracks with an
+    /// unknown duration.
+    ///
+    /// Defaults to false.
+    #[serde(default)]
+    pub skip_trailing_silence: bool,
+
+    /// Whether or not the playback thread should skip a long stretch of silence at the very start
+    /// of a track, like a hidden pregap that was ripped along with it.
+    ///
+    /// Only near-digital silence is skipped, so that quiet intros are left alone, and at most 30
+    /// seconds of it.
+    ///
+    /// Defaults to false.
+    #[serde(default)]
+    pub skip_leading_silence: bool,
+
+    /// The level, in dBFS, below which audio counts as silence when skipping trailing silence.
+    /// Leading silence also has to be below -80 dBFS.
+    ///
+    /// Defaults to -60.
+    #[serde(default = "default_silence_threshold_db")]
+    pub silence_threshold_db: f64,
+
+    /// How long, in seconds, the silence has to last before the rest of the track (or the rest of
+    /// the silence at its start) is skipped.
+    ///
+    /// Defaults to 3.
+    #[serde(default = "default_silence_min_duration")]
+    pub silence_min_duration: f64,
+
+    /// How much of each channel is mixed into the other, to make hard-panned stereo recordings
+    /// less tiring to listen to on headphones. Only applies to stereo tracks.
+    ///
+    /// Defaults to off.
+    #[serde(default)]
+    pub crossfeed: CrossfeedLevel,
+
+    /// The graphic equalizer, which boosts or cuts ten frequency bands from 31 Hz to 16 kHz. Gains
+    /// (and the pre-amp) are in decibels, and values outside of -12 to 12 are treated as the
+    /// nearest of those.
+    ///
+    /// Defaults to off, with every band at 0.
+    #[serde(default)]
+    pub equalizer: EqSettings,
+
+    /// How long, in seconds, the end of each track is mixed with the start of the next one. Set
+    /// to 0 to turn crossfading off. Values above 12 are treated as 12.
+    ///
+    /// Crossfading doesn't happen when repeating a single track, after seeking in the track that's
+    /// playing, or when the next track can't be played on the device the current one is playing
+    /// on.
+    ///
+    /// Defaults to 0.
+    #[serde(default)]
+    pub crossfade: f64,
+
+    /// How long, in milliseconds, playback fades out for before pausing or stopping, and fades
+    /// back in for after resuming. Set to 0 to pause and resume straight away.
+    ///
+    /// Defaults to 150.
+    #[serde(default = "default_pause_fade_ms")]
+    pub pause_fade_ms: u32,
+
+    /// Whether or not playback should pause when it reaches the end of an album and the next
+    /// track in the queue is from a different one, instead of carrying on into it. The next track
+    /// is opened but left paused at its start. Skipping to the next track by hand always moves on.
+    ///
+    /// Tracks that aren't in the library, and so aren't known to be on an album, never cause a
+    /// pause. When repeating the queue, playback also pauses when it goes back to the start. Tracks
+    /// aren't crossfaded into the track playback pauses before.
+    ///
+    /// Defaults to false.
+    #[serde(default)]
+    pub pause_at_album_boundaries: bool,
+
+    /// Which ReplayGain adjustment is applied to tracks, if any. Album mode uses the track's
+    /// adjustment for tracks that don't have an album adjustment. The adjustment is reduced when
+    /// it would make the track clip.
+    ///
+    /// Defaults to off.
+    #[serde(default)]
+    pub replaygain: ReplayGainMode,
+
+    /// The pre-amp, in decibels, applied to tracks without ReplayGain tags when ReplayGain is on,
+    /// so that they can be brought closer to the loudness of tagged tracks. Values outside of -12
+    /// to 12 are treated as the nearest of those.
+    ///
+    /// Defaults to 0.
+    #[serde(default)]
+    pub replaygain_fallback: f64,
+
+    /// What to do with tracks that have more channels than the device, like 5.1 tracks played on
+    /// stereo headphones.
+    ///
+    /// Defaults to downmixing.
+    #[serde(default)]
+    pub downmix: DownmixMode,
+
+    /// How loud each kind of channel is when downmixing to stereo, relative to the front left and
+    /// right channels.
+    ///
+    /// Defaults to the ITU-R BS.775 coefficients.
+    #[serde(default)]
+    pub downmix_coefficients: DownmixCoefficients,
+
+    /// Whether or not every output channel plays the same mono mix of the track, for setups with
+    /// a single speaker. The channels are averaged after the track has been converted to the
+    /// device's channels, so this works the same for mono, stereo and multichannel tracks.
+    ///
+    /// Defaults to false.
+    #[serde(default)]
+    pub mono_downmix: bool,
+
+    /// The balance between the left and right channels, from -1 (only the left channel) to 1
+    /// (only the right channel). The channel on the other side is turned down, rather than this
+    /// one being turned up, so that it never clips. Values outside of -1 to 1 are treated as the
+    /// nearest of those, and it has no effect on mono output.
+    ///
+    /// Defaults to 0.
+    #[serde(default)]
+    pub balance: f64,
+
+    /// Whether or not tracks that are already in the queue should be skipped when adding to it,
+    /// so that queuing an album twice (or a folder that overlaps with what's queued) doesn't add
+    /// the same tracks again. Paths are compared after resolving symlinks and relative parts.
+    ///
+    /// Defaults to false.
+    #[serde(default)]
+    pub avoid_duplicate_queue_entries: bool,
+
+    /// How many tracks can be queued at once before the user is asked to confirm it. Set to 0 to
+    /// never ask.
+    ///
+    /// Defaults to 1000.
+    #[serde(default = "default_large_queue_threshold")]
+    pub large_queue_threshold: usize,
+
+    /// How many tracks the queue can have before the user is asked to confirm replacing it with
+    /// "Play now". Set to 0 to never ask, which "Don't ask again" in the confirmation does.
+    ///
+    /// Defaults to 20.
+    #[serde(default = "default_replace_queue_threshold")]
+    pub replace_queue_threshold: usize,
+
+    /// What to do when the headphones playback was going to are disconnected. Playback moves to
+    /// whichever device is now the default, which usually means the speakers.
+    ///
+    /// Defaults to pausing.
+    #[serde(default = "default_headphones_removed")]
+    pub headphones_removed: DeviceRemovalPolicy,
+
+    /// What to do when the speakers (or line output) playback was going to are disconnected.
+    ///
+    /// Defaults to continuing on the new default device.
+    #[serde(default)]
+    pub speakers_removed: DeviceRemovalPolicy,
+
+    /// What to do when a device that couldn't be identified as either headphones or speakers is
+    /// disconnected.
+    ///
+    /// Defaults to continuing on the new default device.
+    #[serde(default)]
+    pub unknown_device_removed: DeviceRemovalPolicy,
+
+    /// The volume, in percent, that double-clicking the volume slider resets to. Values above
+    /// 100 are treated as 100.
+    ///
+    /// Defaults to 100.
+    #[serde(default = "default_volume")]
+    pub default_volume: u8,
+
+    /// Which audio backend playback goes through. Switching takes effect straight away: playback
+    /// pauses briefly while the output is reopened, and carries on from the same position.
+    ///
+    /// The `DEVICE_PROVIDER` environment variable (set to one of the same names) overrides this,
+    /// for debugging.
+    ///
+    /// Defaults to auto.
+    #[serde(default)]
+    pub device_provider: DeviceProviderKind,
+
+    /// The UID of the device to play through, or none to use the system's default device. If the
+    /// device isn't connected, the default device is used instead until it is.
+    ///
+    /// Defaults to none.
+    #[serde(default)]
+    pub output_device: Option<String>,
+
+    /// Whether the device is opened at each track's own sample rate and bit depth, so that it's
+    /// played without being resampled. When the device can't play a track's format, it's opened
+    /// as usual and the track is resampled.
+    ///
+    /// Defaults to false.
+    #[serde(default)]
+    pub exclusive: bool,
+
+    /// The dither added when the device takes samples with fewer bits than the track has (like a
+    /// 24-bit track played on a 16-bit device). Nothing is added when no bits are lost.
+    ///
+    /// Defaults to tpdf.
+    #[serde(default)]
+    pub dither: DitherMode,
+}
+
+impl PlaybackSettings {
+    /// Returns what to do when a device of the given kind is disconnected during playback.
+    pub fn removal_policy(&self, category: DeviceCategory) -> DeviceRemovalPolicy {
+        match category {
+            DeviceCategory::Headphones => self.headphones_removed,
+            DeviceCategory::Speakers => self.speakers_removed,
+            DeviceCategory::Unknown => self.unknown_device_removed,
+        }
+    }
+}
+
+/// The dither added to samples when bits are lost converting them to the device's format (see
+/// [PlaybackSettings::dither]).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DitherMode {
+    /// Samples are truncated to fit the device's format.
+    Off,
+    /// Triangular (TPDF) dither, which turns rounding distortion into a steady, very quiet hiss.
+    #[default]
+    Tpdf,
+    /// TPDF dither with first-order noise shaping, which moves most of the hiss up to higher
+    /// frequencies, where it's harder to hear.
+    NoiseShaped,
+}
+
+/// The strength of the crossfeed applied to stereo tracks (see [PlaybackSettings::crossfeed]).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CrossfeedLevel {
+    #[default]
+    Off,
+    Low,
+    Medium,
+    High,
+}
+
+impl CrossfeedLevel {
+    pub const ALL: [CrossfeedLevel; 4] = [
+        CrossfeedLevel::Off,
+        CrossfeedLevel::Low,
+        CrossfeedLevel::Medium,
+        CrossfeedLevel::High,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            CrossfeedLevel::Off => "Off",
+            CrossfeedLevel::Low => "Low",
+            CrossfeedLevel::Medium => "Medium",
+            CrossfeedLevel::High => "High",
+        }
+    }
+
+    /// The cutoff frequency of the low-pass filter applied to the crossfed signal, in Hz, and how
+    /// much quieter the crossfed signal is than the direct one at low frequencies, in dB. Returns
+    /// None when crossfeed is off.
+    ///
+    /// These are the presets used by the Bauer stereophonic-to-binaural DSP (bs2b) library.
+    pub fn parameters(self) -> Option<(f32, f32)> {
+        match self {
+            CrossfeedLevel::Off => None,
+            CrossfeedLevel::Low => Some((650.0, 9.5)),
+            CrossfeedLevel::Medium => Some((700.0, 6.0)),
+            CrossfeedLevel::High => Some((700.0, 4.5)),
+        }
+    }
+}
+
+/// The centre frequency of each of the equalizer's bands, in Hz.
+pub const EQ_BANDS: [f32; 10] = [
+    31.0, 62.0, 125.0, 250.0, 500.0, 1000.0, 2000.0, 4000.0, 8000.0, 16000.0,
+];
+
+/// How far each equalizer band (and the pre-amp) can be boosted or cut, in decibels.
+pub const MAX_EQ_GAIN: f32 = 12.0;
+
+/// The settings of the graphic equalizer (see [PlaybackSettings::equalizer]).
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EqSettings {
+    pub enabled: bool,
+    /// Applied before the bands, so that boosting them doesn't make loud tracks clip.
+    pub preamp: f32,
+    /// The gain of each band, from the lowest to the highest (see [EQ_BANDS]).
+    pub gains: [f32; 10],
+}
+
+impl EqSettings {
+    /// Returns the settings with the pre-amp and every gain brought within the allowed range.
+    pub fn clamped(self) -> Self {
+        let clamp = |gain: f32| {
+            if gain.is_finite() {
+                gain.clamp(-MAX_EQ_GAIN, MAX_EQ_GAIN)
+            } else {
+                0.0
+            }
+        };
+
+        Self {
+            enabled: self.enabled,
+            preamp: clamp(self.preamp),
+            gains: self.gains.map(clamp),
+        }
+    }
+
+    /// Returns true if the equalizer wouldn't change anything, either because it's off or because
+    /// every band and the pre-amp are at 0.
+    pub fn is_flat(&self) -> bool {
+        !self.enabled || (self.preamp == 0.0 && self.gains.iter().all(|gain| *gain == 0.0))
+    }
+
+    /// Returns the preset the gains and pre-amp match, if any.
+    pub fn preset(&self) -> Option<EqPreset> {
+        EqPreset::ALL
+            .into_iter()
+            .find(|preset| preset.gains() == self.gains && preset.preamp() == self.preamp)
+    }
+
+    /// Sets the gains and pre-amp to those of a preset, and turns the equalizer on.
+    pub fn apply_preset(&mut self, preset: EqPreset) {
+        self.enabled = true;
+        self.preamp = preset.preamp();
+        self.gains = preset.gains();
+    }
+}
+
+/// Ready-made equalizer settings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EqPreset {
+    Flat,
+    BassBoost,
+    TrebleBoost,
+    Vocal,
+    Loudness,
+}
+
+impl EqPreset {
+    pub const ALL: [EqPreset; 5] = [
+        EqPreset::Flat,
+        EqPreset::BassBoost,
+        EqPreset::TrebleBoost,
+        EqPreset::Vocal,
+        EqPreset::Loudness,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            EqPreset::Flat => "Flat",
+            EqPreset::BassBoost => "Bass Boost",
+            EqPreset::TrebleBoost => "Treble Boost",
+            EqPreset::Vocal => "Vocal",
+            EqPreset::Loudness => "Loudness",
+        }
+    }
+
+    pub fn gains(self) -> [f32; 10] {
+        match self {
+            EqPreset::Flat => [0.0; 10],
+            EqPreset::BassBoost => [6.0, 5.0, 4.0, 2.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0],
+            EqPreset::TrebleBoost => [0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 2.0, 4.0, 5.0, 6.0],
+            EqPreset::Vocal => [-3.0, -2.0, -1.0, 0.0, 2.0, 4.0, 4.0, 2.0, 0.0, -1.0],
+            EqPreset::Loudness => [5.0, 4.0, 2.0, 0.0, -1.0, -1.0, 0.0, 2.0, 3.0, 4.0],
+        }
+    }
+
+    /// The pre-amp that goes with the preset, which leaves enough headroom for its biggest boost.
+    pub fn preamp(self) -> f32 {
+        let loudest = self.gains().into_iter().fold(0.0, f32::max);
+
+        if loudest > 0.0 { -loudest } else { 0.0 }
+    }
+}
+
+/// Which ReplayGain adjustment the playback thread applies (see [PlaybackSettings::replaygain]).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReplayGainMode {
+    #[default]
+    Off,
+    /// Make every track about as loud as the others.
+    Track,
+    /// Make every album about as loud as the others, keeping the differences between the tracks
+    /// on it.
+    Album,
+}
+
+/// What the playback thread does with tracks that have more channels than the device (see
+/// [PlaybackSettings::downmix]).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DownmixMode {
+    /// Mix the extra channels into the ones the device has, using
+    /// [PlaybackSettings::downmix_coefficients].
+    #[default]
+    Downmix,
+    /// Don't play the track, and show an error instead.
+    Refuse,
+}
+
+/// How loud each kind of channel is mixed into the left and right channels when downmixing to
+/// stereo. Channels on one side are only mixed into that side, while center channels are mixed
+/// into both.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct DownmixCoefficients {
+    /// The front center channel.
+    pub center: f32,
+    /// The side and back channels.
+    pub surround: f32,
+    /// The low-frequency (subwoofer) channel.
+    pub lfe: f32,
+}
+
+impl Default for DownmixCoefficients {
+    /// The coefficients from ITU-R BS.775, which leaves the low-frequency channel out.
+    fn default() -> Self {
+        Self {
+            center: std::f32::consts::FRAC_1_SQRT_2,
+            surround: std::f32::consts::FRAC_1_SQRT_2,
+            lfe: 0.0,
+        }
+    }
+}
+
+/// What the playback thread does when the device it's playing on is disconnected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DeviceRemovalPolicy {
+    /// Pause playback. It resumes on the new default device when the user presses play.
+    Pause,
+    /// Keep playing on the new default device.
+    #[default]
+    Continue,
+    /// Pause playback, and show a toast offering to resume on the new default device.
+    Ask,
+}
+
+/// An audio backend the playback thread can play through (see
+/// [PlaybackSettings::device_provider]).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeviceProviderKind {
+    /// The usual backend for the platform: AudioGraph on Windows, and cpal everywhere else.
+    #[default]
+    Auto,
+    /// cpal, which plays through ALSA on Linux, CoreAudio on macOS and WASAPI on Windows.
+    Cpal,
+    /// Windows' AudioGraph API. cpal is used instead on other platforms.
+    WinAudiograph,
+    /// Discards the audio instead of playing it. Only useful for debugging.
+    Dummy,
+}
+
+impl DeviceProviderKind {
+    /// The name of the backend, as it's written in the settings file and the `DEVICE_PROVIDER`
+    /// environment variable.
+    pub fn name(self) -> &'static str {
+        match self {
+            DeviceProviderKind::Auto => "auto",
+            DeviceProviderKind::Cpal => "cpal",
+            DeviceProviderKind::WinAudiograph => "win_audiograph",
+            DeviceProviderKind::Dummy => "dummy",
+        }
+    }
+
+    /// Parses the name of a backend, as returned by [DeviceProviderKind::name].
+    pub fn from_name(name: &str) -> Option<Self> {
+        [
+            DeviceProviderKind::Auto,
+            DeviceProviderKind::Cpal,
+            DeviceProviderKind::WinAudiograph,
+            DeviceProviderKind::Dummy,
+        ]
+        .into_iter()
+        .find(|kind| kind.name() == name)
+    }
+
+    /// Returns the backend that's actually used for this one on the current platform: the
+    /// platform's usual backend for auto, and cpal for backends the platform doesn't have.
+    pub fn resolve(self) -> Self {
+        match self {
+            DeviceProviderKind::Auto if cfg!(target_os = "windows") => {
+                DeviceProviderKind::WinAudiograph
+            }
+            DeviceProviderKind::Auto => DeviceProviderKind::Cpal,
+            DeviceProviderKind::WinAudiograph if !cfg!(target_os = "windows") => {
+                DeviceProviderKind::Cpal
+            }
+            kind => kind,
+        }
+    }
+}
+
+fn default_silence_threshold_db() -> f64 {
+    -60.0
+}
+
+fn default_silence_min_duration() -> f64 {
+    3.0
+}
+
+fn default_pause_fade_ms() -> u32 {
+    150
+}
+
+fn default_large_queue_threshold() -> usize {
+    1000
+}
+
+fn default_replace_queue_threshold() -> usize {
+    20
+}
+
+fn default_volume() -> u8 {
+    100
+}
+
+fn default_headphones_removed() -> DeviceRemovalPolicy {
+    DeviceRemovalPolicy::Pause
 }
 
-#[allow(clippy::derivable_impls)]
 impl Default for PlaybackSettings {
     fn default() -> Self {
         Self {
             always_repeat: false,
             prev_track_jump_first: false,
+            skip_trailing_silence: false,
+            skip_leading_silence: false,
+            silence_threshold_db: default_silence_threshold_db(),
+            silence_min_duration: default_silence_min_duration(),
+            crossfeed: CrossfeedLevel::default(),
+            equalizer: EqSettings::default(),
+            crossfade: 0.0,
+            pause_fade_ms: default_pause_fade_ms(),
+            pause_at_album_boundaries: false,
+            replaygain: ReplayGainMode::default(),
+            replaygain_fallback: 0.0,
+            downmix: DownmixMode::default(),
+            downmix_coefficients: DownmixCoefficients::default(),
+            mono_downmix: false,
+            balance: 0.0,
+            avoid_duplicate_queue_entries: false,
+            large_queue_threshold: default_large_queue_threshold(),
+            replace_queue_threshold: default_replace_queue_threshold(),
+            headphones_removed: default_headphones_removed(),
+            speakers_removed: DeviceRemovalPolicy::default(),
+            unknown_device_removed: DeviceRemovalPolicy::default(),
+            default_volume: default_volume(),
+            device_provider: DeviceProviderKind::default(),
+            output_device: None,
+            exclusive: false,
+            dither: DitherMode::default(),
         }
     }
 }
@@ -1,23 +1,144 @@
-use std::{fs::exists, path::PathBuf};
+use std::{
+    fs::exists,
+    path::{Path, PathBuf},
+};
 
 use serde::{Deserialize, Serialize};
 use tracing::{error, warn};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// A folder to scan, along with how it's scanned.
+///
+/// Older settings files list folders as plain paths. These are read as folders with the default
+/// options, and are written back in full the next time the settings are saved.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(from = "ScanRootRepr")]
+pub struct ScanRoot {
+    pub path: PathBuf,
+
+    /// Folders with a higher priority are scanned first. Folders with the same priority are
+    /// scanned in the order they're listed in.
+    ///
+    /// Defaults to 0.
+    pub priority: u8,
+
+    /// Whether or not the folder is on a network share (or a removable drive). If the folder
+    /// can't be reached when the library is scanned, its tracks are kept in the library instead
+    /// of being removed as if they had been deleted.
+    ///
+    /// Defaults to false.
+    pub network_volume: bool,
+}
+
+impl ScanRoot {
+    /// A folder with the default options.
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            priority: 0,
+            network_volume: false,
+        }
+    }
+}
+
+/// How a [ScanRoot] can be written in the settings file.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ScanRootRepr {
+    Path(PathBuf),
+    Full {
+        path: PathBuf,
+        #[serde(default)]
+        priority: u8,
+        #[serde(default)]
+        network_volume: bool,
+    },
+}
+
+impl From<ScanRootRepr> for ScanRoot {
+    fn from(repr: ScanRootRepr) -> Self {
+        match repr {
+            ScanRootRepr::Path(path) => ScanRoot::new(path),
+            ScanRootRepr::Full {
+                path,
+                priority,
+                network_volume,
+            } => ScanRoot {
+                path,
+                priority,
+                network_volume,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ScanSettings {
     #[serde(default = "retrieve_default_paths")]
-    pub paths: Vec<PathBuf>,
+    pub paths: Vec<ScanRoot>,
+
+    /// Whether or not the scanner should estimate the tempo of tracks that don't have a BPM tag.
+    /// Estimation decodes the first minute of every such track, which makes scanning
+    /// considerably slower. Estimated values are marked as such in the library.
+    ///
+    /// Defaults to false.
+    #[serde(default)]
+    pub estimate_bpm: bool,
+
+    /// Whether or not the scanner should measure the loudness of tracks that don't have
+    /// ReplayGain tags, so that they can be normalized during playback too. Measuring decodes the
+    /// whole of every such track, so it happens after the rest of the scan, and can be stopped
+    /// at any point - the tracks that are left are measured after the next scan.
+    ///
+    /// Defaults to false.
+    #[serde(default)]
+    pub analyze_loudness: bool,
+
+    /// Whether or not hidden files and folders (names starting with a dot, or with the hidden
+    /// attribute set on Windows) inside the scanned folders should be scanned.
+    ///
+    /// Defaults to false.
+    #[serde(default)]
+    pub include_hidden: bool,
+
+    /// Files smaller than this many bytes are skipped. These are almost always broken downloads
+    /// or leftovers from other software rather than actual audio.
+    ///
+    /// Defaults to 4096.
+    #[serde(default = "default_min_file_size")]
+    pub min_file_size: u64,
 }
 
 impl Default for ScanSettings {
     fn default() -> Self {
         Self {
             paths: retrieve_default_paths(),
+            estimate_bpm: false,
+            analyze_loudness: false,
+            include_hidden: false,
+            min_file_size: default_min_file_size(),
         }
     }
 }
 
-fn retrieve_default_paths() -> Vec<PathBuf> {
+fn default_min_file_size() -> u64 {
+    4096
+}
+
+impl ScanSettings {
+    /// Returns whether the folder is already one of the scanned folders.
+    pub fn has_path(&self, path: &Path) -> bool {
+        self.paths.iter().any(|root| root.path == path)
+    }
+}
+
+fn retrieve_default_paths() -> Vec<ScanRoot> {
+    retrieve_default_folders()
+        .into_iter()
+        .map(ScanRoot::new)
+        .collect()
+}
+
+fn retrieve_default_folders() -> Vec<PathBuf> {
     #[cfg(target_os = "windows")]
     {
         use windows::Storage::{KnownLibraryId, StorageLibrary};
@@ -60,3 +181,52 @@ fn retrieve_default_paths() -> Vec<PathBuf> {
         vec![]
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(json: &str) -> ScanRoot {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn reads_plain_paths_with_the_default_options() {
+        assert_eq!(parse(r#""/music""#), ScanRoot::new("/music".into()));
+        assert_eq!(parse(r#""/music""#), parse(r#"{ "path": "/music" }"#));
+    }
+
+    #[test]
+    fn reads_the_options_of_full_entries() {
+        let root = parse(r#"{ "path": "/nas", "priority": 2, "network_volume": true }"#);
+
+        assert_eq!(
+            root,
+            ScanRoot {
+                path: "/nas".into(),
+                priority: 2,
+                network_volume: true,
+            }
+        );
+    }
+
+    #[test]
+    fn writes_full_entries_that_read_back_the_same() {
+        let root = ScanRoot {
+            priority: 1,
+            ..ScanRoot::new("/music".into())
+        };
+
+        assert_eq!(parse(&serde_json::to_string(&root).unwrap()), root);
+    }
+
+    #[test]
+    fn reads_a_mix_of_both_forms() {
+        let settings: ScanSettings =
+            serde_json::from_str(r#"{ "paths": ["/music", { "path": "/nas", "priority": 1 }] }"#)
+                .unwrap();
+
+        assert_eq!(settings.paths[0], ScanRoot::new("/music".into()));
+        assert_eq!(settings.paths[1].priority, 1);
+    }
+}
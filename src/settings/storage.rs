@@ -1,13 +1,21 @@
-use std::{collections::HashMap, fs, path::PathBuf};
+use std::{collections::HashMap, path::PathBuf};
 
-use gpui::{Pixels, px};
+use gpui::{Bounds, Pixels, WindowBounds, point, px, size};
 use serde::{Deserialize, Serialize};
 
-use crate::ui::models::CurrentTrack;
+use super::persist::{self, Recovery};
+use crate::{media::source::source_exists, ui::models::CurrentTrack};
+
+/// The format version of the app data file.
+const STORAGE_VERSION: u32 = 1;
 
 pub const DEFAULT_SIDEBAR_WIDTH: Pixels = px(225.0);
 pub const DEFAULT_QUEUE_WIDTH: Pixels = px(275.0);
 
+/// How much of the window has to be on a display, in both directions, for its saved position to be
+/// used. Anything less and the window is hard to get hold of, so it's centered instead.
+const MIN_VISIBLE_WINDOW_SIZE: f32 = 64.0;
+
 fn default_sidebar_width() -> f32 {
     f32::from(DEFAULT_SIDEBAR_WIDTH)
 }
@@ -28,6 +36,111 @@ pub struct TableSettings {
     pub hidden_columns: Vec<String>,
 }
 
+/// Where the main window was and how big it was, in logical pixels, so that it opens the same way
+/// next time. Logical sizes stay the same when the window moves between displays with different
+/// scale factors.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct WindowState {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+    /// The display the window was on. Display IDs can change between launches, so this is only
+    /// used to choose between displays the window overlaps.
+    #[serde(default)]
+    pub display_id: Option<u32>,
+    #[serde(default)]
+    pub maximized: bool,
+    #[serde(default)]
+    pub fullscreen: bool,
+}
+
+impl WindowState {
+    /// Records the bounds of a window. For maximized and fullscreen windows, these are the bounds
+    /// the window returns to afterwards.
+    pub fn new(bounds: WindowBounds, display_id: Option<u32>) -> Self {
+        let (restore, maximized, fullscreen) = match bounds {
+            WindowBounds::Windowed(bounds) => (bounds, false, false),
+            WindowBounds::Maximized(bounds) => (bounds, true, false),
+            WindowBounds::Fullscreen(bounds) => (bounds, false, true),
+        };
+
+        Self {
+            x: restore.origin.x.into(),
+            y: restore.origin.y.into(),
+            width: restore.size.width.into(),
+            height: restore.size.height.into(),
+            display_id,
+            maximized,
+            fullscreen,
+        }
+    }
+
+    /// Works out where to reopen the window, given the ID and bounds of every connected display.
+    /// Returns the display to open it on along with its bounds, or `None` if the window wouldn't
+    /// be visible on any of them (after a monitor is unplugged, for example) and should be put in
+    /// the default position instead.
+    ///
+    /// If the window overlaps more than one display, the one it was on last time is preferred,
+    /// then the one it overlaps the most. The window is shrunk and moved to fit on that display.
+    pub fn restore(&self, displays: &[(u32, Bounds<Pixels>)]) -> Option<(u32, WindowBounds)> {
+        let values = [self.x, self.y, self.width, self.height];
+        if values.iter().any(|value| !value.is_finite()) || self.width <= 0.0 || self.height <= 0.0
+        {
+            return None;
+        }
+
+        let (id, display) = displays
+            .iter()
+            .filter_map(|(id, display)| {
+                let (width, height) = self.overlap(display);
+
+                (width >= MIN_VISIBLE_WINDOW_SIZE && height >= MIN_VISIBLE_WINDOW_SIZE).then_some((
+                    (self.display_id == Some(*id), width * height),
+                    *id,
+                    display,
+                ))
+            })
+            .max_by(|(a, _, _), (b, _, _)| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal))
+            .map(|(_, id, display)| (id, display))?;
+
+        let left: f32 = display.origin.x.into();
+        let top: f32 = display.origin.y.into();
+        let display_width: f32 = display.size.width.into();
+        let display_height: f32 = display.size.height.into();
+
+        let width = self.width.min(display_width);
+        let height = self.height.min(display_height);
+        let x = self.x.clamp(left, left + display_width - width);
+        let y = self.y.clamp(top, top + display_height - height);
+
+        let bounds = Bounds::new(point(px(x), px(y)), size(px(width), px(height)));
+
+        let bounds = if self.fullscreen {
+            WindowBounds::Fullscreen(bounds)
+        } else if self.maximized {
+            WindowBounds::Maximized(bounds)
+        } else {
+            WindowBounds::Windowed(bounds)
+        };
+
+        Some((id, bounds))
+    }
+
+    /// How far the window extends onto the given display, horizontally and vertically.
+    fn overlap(&self, display: &Bounds<Pixels>) -> (f32, f32) {
+        let left: f32 = display.origin.x.into();
+        let top: f32 = display.origin.y.into();
+        let right = left + f32::from(display.size.width);
+        let bottom = top + f32::from(display.size.height);
+
+        let width = (self.x + self.width).min(right) - self.x.max(left);
+        let height = (self.y + self.height).min(bottom) - self.y.max(top);
+
+        (width.max(0.0), height.max(0.0))
+    }
+}
+
 /// Data to store while quitting the app
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StorageData {
@@ -40,6 +153,12 @@ pub struct StorageData {
     pub queue_width: f32,
     #[serde(default = "default_table_settings")]
     pub table_settings: HashMap<String, TableSettings>,
+    /// Where the main window was, if it's been opened before
+    #[serde(default)]
+    pub window: Option<WindowState>,
+    /// The version of the app that last ran, so that its changes can be shown after an update
+    #[serde(default)]
+    pub last_seen_version: Option<String>,
 }
 
 impl StorageData {
@@ -59,6 +178,8 @@ impl Default for StorageData {
             sidebar_width: f32::from(DEFAULT_SIDEBAR_WIDTH),
             queue_width: f32::from(DEFAULT_QUEUE_WIDTH),
             table_settings: HashMap::new(),
+            window: None,
+            last_seen_version: None,
         }
     }
 }
@@ -76,33 +197,107 @@ impl Storage {
 
     /// Save `StorageData` on file system
     pub fn save(&self, data: &StorageData) {
-        // save into file
-        let result = fs::File::create(self.path.clone())
-            .and_then(|file| serde_json::to_writer(file, &data).map_err(|e| e.into()));
         // ignore error, but log it
-        if let Err(e) = result {
+        if let Err(e) = persist::save(&self.path, data, STORAGE_VERSION) {
             tracing::warn!("could not save `AppState` {:?}", e);
         };
     }
 
-    /// Load `StorageData` from storage or use `StorageData::default` in case of any errors
-    pub fn load_or_default(&self) -> StorageData {
-        std::fs::File::open(self.path.clone())
-            .and_then(|file| {
-                serde_json::from_reader(file)
-                    .map_err(|e| e.into())
-                    .map(|data: StorageData| match &data.current_track {
-                        // validate whether path still exists
-                        Some(current_track) if !current_track.get_path().exists() => StorageData {
-                            current_track: None,
-                            // Preserve other settings when invalidating current_track
-                            sidebar_width: data.sidebar_width,
-                            queue_width: data.queue_width,
-                            table_settings: data.table_settings,
-                        },
-                        _ => data,
-                    })
+    /// Load `StorageData` from storage or use `StorageData::default` in case of any errors, along
+    /// with what had to be done to recover the file if it couldn't be read
+    pub fn load_or_default(&self) -> (StorageData, Option<Recovery>) {
+        let loaded = persist::load(&self.path, STORAGE_VERSION);
+
+        let data = loaded
+            .value
+            .map(|data: StorageData| match &data.current_track {
+                // validate whether path still exists
+                Some(current_track) if !source_exists(current_track.get_path()) => StorageData {
+                    current_track: None,
+                    // Preserve other settings when invalidating current_track
+                    ..data
+                },
+                _ => data,
             })
-            .unwrap_or_default()
+            .unwrap_or_default();
+
+        (data, loaded.recovery)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bounds(x: f32, y: f32, width: f32, height: f32) -> Bounds<Pixels> {
+        Bounds::new(point(px(x), px(y)), size(px(width), px(height)))
+    }
+
+    fn windowed(x: f32, y: f32) -> WindowBounds {
+        WindowBounds::Windowed(bounds(x, y, 800.0, 600.0))
+    }
+
+    fn window(x: f32, y: f32, display_id: u32) -> WindowState {
+        WindowState::new(windowed(x, y), Some(display_id))
+    }
+
+    /// Two 1920x1080 displays side by side.
+    fn displays() -> [(u32, Bounds<Pixels>); 2] {
+        [
+            (1, bounds(0.0, 0.0, 1920.0, 1080.0)),
+            (2, bounds(1920.0, 0.0, 1920.0, 1080.0)),
+        ]
+    }
+
+    #[test]
+    fn reopens_where_it_was() {
+        let restored = window(2000.0, 100.0, 2).restore(&displays());
+
+        assert_eq!(restored, Some((2, windowed(2000.0, 100.0))));
+    }
+
+    #[test]
+    fn prefers_the_display_it_was_on() {
+        // mostly on the first display, but it was last on the second
+        let restored = window(1500.0, 100.0, 2).restore(&displays());
+
+        assert_eq!(restored, Some((2, windowed(1920.0, 100.0))));
+    }
+
+    #[test]
+    fn uses_the_default_position_once_its_display_is_gone() {
+        let restored = window(2000.0, 100.0, 2).restore(&displays()[..1]);
+
+        assert_eq!(restored, None);
+    }
+
+    #[test]
+    fn moves_windows_that_are_partly_off_screen() {
+        let restored = window(1500.0, -50.0, 1).restore(&displays()[..1]);
+
+        assert_eq!(restored, Some((1, windowed(1120.0, 0.0))));
+    }
+
+    #[test]
+    fn shrinks_windows_larger_than_the_display() {
+        let state = WindowState::new(
+            WindowBounds::Maximized(bounds(0.0, 0.0, 2560.0, 1440.0)),
+            Some(1),
+        );
+
+        assert_eq!(
+            state.restore(&displays()),
+            Some((1, WindowBounds::Maximized(bounds(0.0, 0.0, 1920.0, 1080.0))))
+        );
+    }
+
+    #[test]
+    fn ignores_broken_sizes() {
+        let state = WindowState {
+            width: f32::NAN,
+            ..window(0.0, 0.0, 1)
+        };
+
+        assert_eq!(state.restore(&displays()), None);
     }
 }
@@ -1,18 +1,34 @@
 mod about;
 pub mod app;
-mod arguments;
+pub mod arguments;
 mod assets;
 mod caching;
 mod command_palette;
 pub mod components;
 mod constants;
 mod controls;
+mod crash_dialog;
+mod device_picker;
 pub mod data;
+mod equalizer;
+mod external_drag;
+mod font_picker;
+mod fonts;
+pub mod format;
 mod global_actions;
 mod header;
+mod large_queue;
 mod library;
+mod liked_playlist_picker;
+mod migration_window;
 pub mod models;
 mod queue;
+mod rebuild_dialog;
 mod search;
+mod shortcuts;
+mod stall_detector;
 mod theme;
+pub mod toasts;
 pub mod util;
+mod whats_new;
+mod window_title;
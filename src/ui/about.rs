@@ -1,11 +1,14 @@
 use gpui::{
-    FontWeight, InteractiveElement, IntoElement, ParentElement, RenderOnce,
+    App, FontWeight, InteractiveElement, IntoElement, ParentElement, RenderOnce,
     StatefulInteractiveElement, Styled, div, img, px,
 };
 
 use super::{
     components::modal::{OnExitHandler, modal},
+    fonts::SCRIPT_SAMPLES,
+    models::{Models, PlaybackInfo},
     theme::Theme,
+    whats_new::show_changelog,
 };
 
 const ISSUES_URL: &str = "https://github.com/143mailliw/hummingbird/issues";
@@ -93,6 +96,24 @@ impl RenderOnce for AboutDialog {
                                             )
                                             .child(" on GitHub."),
                                     )
+                                    .child(
+                                        div().flex().child(
+                                            div()
+                                                .id("about-whats-new-link")
+                                                .cursor_pointer()
+                                                .text_color(theme.text_link)
+                                                .hover(|this| {
+                                                    this.border_b_1().border_color(theme.text_link)
+                                                })
+                                                .on_click(|_, _, cx| {
+                                                    let show_about =
+                                                        cx.global::<Models>().show_about.clone();
+                                                    show_about.write(cx, false);
+                                                    show_changelog(cx);
+                                                })
+                                                .child("See what's new"),
+                                        ),
+                                    )
                                     .child(div().child(
                                         "Copyright © 2024 - 2026 William Whittaker and \
                                         contributors.",
@@ -119,12 +140,53 @@ impl RenderOnce for AboutDialog {
                                             ),
                                     ),
                             ),
-                        ),
+                        )
+                        .child(script_samples(cx))
+                        .child(audio_backend(cx)),
                 ),
         )
     }
 }
 
+/// Renders a sample of each script, so that it's easy to check which ones can be displayed with
+/// the installed fonts.
+fn script_samples(cx: &App) -> impl IntoElement {
+    let theme = cx.global::<Theme>();
+
+    div()
+        .mt(px(15.0))
+        .text_size(px(13.0))
+        .children(SCRIPT_SAMPLES.iter().map(|(script, sample)| {
+            div()
+                .flex()
+                .child(
+                    div()
+                        .w(px(80.0))
+                        .flex_shrink_0()
+                        .text_color(theme.text_secondary)
+                        .child(*script),
+                )
+                .child(*sample)
+        }))
+}
+
+/// Shows which audio backend playback goes through, and whether it fell back to another one, so
+/// that it can be included in bug reports.
+fn audio_backend(cx: &App) -> impl IntoElement {
+    let theme = cx.global::<Theme>();
+    let backend = cx
+        .global::<PlaybackInfo>()
+        .device_provider
+        .read(cx)
+        .map_or_else(|| "not started".to_string(), |active| active.describe());
+
+    div()
+        .mt(px(4.0))
+        .text_size(px(13.0))
+        .text_color(theme.text_secondary)
+        .child(format!("Audio backend: {backend}"))
+}
+
 pub fn about_dialog(on_exit: &'static OnExitHandler) -> AboutDialog {
     AboutDialog { on_exit }
 }
@@ -1,31 +1,37 @@
 use std::{
     fs,
+    path::PathBuf,
     sync::{Arc, RwLock},
+    time::Duration,
 };
 
-use directories::ProjectDirs;
 use gpui::*;
 use prelude::FluentBuilder;
 use sqlx::SqlitePool;
-use tracing::debug;
+use tracing::warn;
 
 use crate::{
     library::{
-        db::create_pool,
-        scan::{ScanInterface, ScanThread},
+        db::{LibraryAccess, create_pool, relativize_library_paths},
+        playlist::load_liked_playlist,
+        scan::{ScanInterface, ScanThread, needs_rebuild},
     },
+    paths::DataPaths,
     playback::{interface::PlaybackInterface, queue::QueueItemData, thread::PlaybackThread},
     services::controllers::{init_pbc_task, register_pbc_event_handlers},
     settings::{
-        SettingsGlobal, setup_settings,
-        storage::{Storage, StorageData},
+        SettingsGlobal,
+        interface::{DEFAULT_FONT_FAMILY, InterfaceSettings},
+        persist::Recovery,
+        setup_settings,
+        storage::{Storage, StorageData, WindowState},
     },
     ui::{
         assets::HummingbirdAssetSource,
         caching::HummingbirdImageCache,
         command_palette::{CommandPalette, CommandPaletteHolder},
         constants::APP_SHADOW_SIZE,
-        library,
+        external_drag, library,
     },
 };
 
@@ -35,16 +41,35 @@ use super::{
     components::{input, modal},
     constants::APP_ROUNDING,
     controls::Controls,
+    crash_dialog::crash_dialog,
+    device_picker::DevicePicker,
+    font_picker::FontPicker,
+    fonts::{InterfaceFontFallbacks, load_bundled_fonts, update_font_fallbacks},
     global_actions::register_actions,
     header::Header,
+    large_queue::{
+        Confirmation, LargeQueue, QueueEntry, add_to_queue, large_queue_dialog,
+        replace_queue_dialog,
+    },
     library::Library,
+    liked_playlist_picker::{LIKED_PLAYLIST_CHECK, LikedPlaylistPicker},
+    migration_window::migrate_then,
     models::{self, Models, PlaybackInfo, build_models},
-    queue::Queue,
+    queue::{self, Queue},
+    rebuild_dialog::{RebuildReason, rebuild_dialog},
     search::SearchView,
+    shortcuts::ShortcutHelp,
+    stall_detector::start_stall_detector,
     theme::{Theme, setup_theme},
-    util::drop_image_from_app,
+    toasts::{ToastView, show_toast},
+    util::{drop_image_from_app, ui_scale},
+    whats_new::{CURRENT_VERSION, Release, whats_new_dialog},
+    window_title::{APP_TITLE, register_window_title_handlers},
 };
 
+/// How long the window has to stay put after being moved or resized before its position is saved.
+const WINDOW_SAVE_DELAY: Duration = Duration::from_secs(2);
+
 struct WindowShadow {
     pub controls: Entity<Controls>,
     pub queue: Entity<Queue>,
@@ -53,8 +78,57 @@ struct WindowShadow {
     pub search: Entity<SearchView>,
     pub show_queue: Entity<bool>,
     pub show_about: Entity<bool>,
+    pub rebuild_prompt: Entity<Option<RebuildReason>>,
+    pub large_queue: Entity<LargeQueue>,
+    pub whats_new: Entity<Option<Vec<Release>>>,
+    pub crash_report: Entity<Option<PathBuf>>,
     pub palette: Entity<CommandPalette>,
+    pub toasts: Entity<ToastView>,
+    pub font_picker: Entity<FontPicker>,
+    pub device_picker: Entity<DevicePicker>,
+    pub shortcut_help: Entity<ShortcutHelp>,
+    pub liked_playlist_picker: Entity<LikedPlaylistPicker>,
     pub image_cache: Entity<HummingbirdImageCache>,
+    /// The font family used throughout the interface, after checking that it's installed.
+    pub font_family: SharedString,
+    /// Saves the window's position once it stops moving. Replaced on every move, which cancels
+    /// the previous save.
+    save_window_task: Option<Task<()>>,
+}
+
+/// Saves the layout, the window's position and the current track in the background.
+fn save_storage(cx: &App, storage: Storage) -> Task<()> {
+    let models = cx.global::<Models>();
+    let data = StorageData {
+        current_track: cx.global::<PlaybackInfo>().current_track.read(cx).clone(),
+        sidebar_width: (*models.sidebar_width.read(cx)).into(),
+        queue_width: (*models.queue_width.read(cx)).into(),
+        table_settings: models.table_settings.read(cx).clone(),
+        window: *models.window_state.read(cx),
+        last_seen_version: Some(CURRENT_VERSION.to_string()),
+    };
+
+    cx.background_executor().spawn(async move {
+        storage.save(&data);
+    })
+}
+
+/// Returns the font family chosen in the interface settings, or Inter if it isn't installed.
+fn resolve_font_family(cx: &App, settings: &InterfaceSettings) -> SharedString {
+    let family = &settings.font_family;
+
+    if family == DEFAULT_FONT_FAMILY
+        || cx
+            .text_system()
+            .all_font_names()
+            .iter()
+            .any(|name| name == family)
+    {
+        family.clone().into()
+    } else {
+        warn!("Font family {family:?} isn't installed, using {DEFAULT_FONT_FAMILY} instead");
+        DEFAULT_FONT_FAMILY.into()
+    }
 }
 
 impl Render for WindowShadow {
@@ -66,9 +140,15 @@ impl Render for WindowShadow {
         let shadow_size = APP_SHADOW_SIZE;
         let border_size = px(1.0);
         window.set_client_inset(shadow_size);
+        // anything sized in rems (including all of the text_* sizes) follows the interface scale
+        window.set_rem_size(px(16.0) * ui_scale(cx));
 
         let queue = self.queue.clone();
         let show_about = *self.show_about.clone().read(cx);
+        let rebuild_prompt = *self.rebuild_prompt.read(cx);
+        let confirmation = self.large_queue.read(cx).pending();
+        let whats_new = self.whats_new.read(cx).clone();
+        let crash_report = self.crash_report.read(cx).clone();
 
         let mut element = div()
             .image_cache(self.image_cache.clone())
@@ -145,7 +225,10 @@ impl Render for WindowShadow {
             .size_full()
             .child(
                 div()
-                    .font_family("Inter")
+                    .font(Font {
+                        fallbacks: Some(cx.global::<InterfaceFontFallbacks>().0.clone()),
+                        ..font(self.font_family.clone())
+                    })
                     .text_color(theme.text)
                     .cursor(CursorStyle::Arrow)
                     .map(|div| match decorations {
@@ -186,14 +269,13 @@ impl Render for WindowShadow {
                         cx.stop_propagation();
                     })
                     .on_drop(|ev: &ExternalPaths, _, cx| {
-                        let items = ev
+                        let entries = ev
                             .paths()
                             .iter()
-                            .map(|path| QueueItemData::new(cx, path.clone(), None, None))
+                            .map(|path| QueueEntry::new(path.clone(), None, None))
                             .collect();
 
-                        let playback_interface = cx.global::<PlaybackInterface>();
-                        playback_interface.queue_list(items);
+                        add_to_queue(cx, entries);
                     })
                     .overflow_hidden()
                     .bg(theme.background_primary)
@@ -217,11 +299,29 @@ impl Render for WindowShadow {
                     .child(self.controls.clone())
                     .child(self.search.clone())
                     .child(self.palette.clone())
+                    .child(self.toasts.clone())
+                    .child(self.font_picker.clone())
+                    .child(self.device_picker.clone())
+                    .child(self.shortcut_help.clone())
+                    .child(self.liked_playlist_picker.clone())
                     .when(show_about, |this| {
                         this.child(about_dialog(&|_, cx| {
                             let show_about = cx.global::<Models>().show_about.clone();
                             show_about.write(cx, false);
                         }))
+                    })
+                    .when_some(rebuild_prompt, |this, reason| {
+                        this.child(rebuild_dialog(reason))
+                    })
+                    .when_some(confirmation, |this, confirmation| match confirmation {
+                        Confirmation::Replace(count) => this.child(replace_queue_dialog(count)),
+                        Confirmation::LargeQueue(count) => this.child(large_queue_dialog(count)),
+                    })
+                    .when_some(whats_new, |this, releases| {
+                        this.child(whats_new_dialog(releases))
+                    })
+                    .when_some(crash_report, |this, report| {
+                        this.child(crash_dialog(report))
                     }),
             );
 
@@ -275,45 +375,16 @@ fn resize_edge(
     Some(edge)
 }
 
-pub fn find_fonts(cx: &mut App) -> gpui::Result<()> {
-    let paths = cx.asset_source().list("!bundled:fonts")?;
-    let mut fonts = vec![];
-    for path in paths {
-        if (path.ends_with(".ttf") || path.ends_with(".otf"))
-            && let Some(v) = cx.asset_source().load(&path)?
-        {
-            fonts.push(v);
-        }
-    }
-
-    let results = cx.text_system().add_fonts(fonts);
-    debug!("loaded fonts: {:?}", cx.text_system().all_font_names());
-    results
-}
-
 pub struct Pool(pub SqlitePool);
 
 impl Global for Pool {}
 
-pub fn get_dirs() -> ProjectDirs {
-    let secondary_dirs = directories::ProjectDirs::from("me", "william341", "muzak")
-        .expect("couldn't generate project dirs (secondary)");
-
-    if secondary_dirs.data_dir().exists() {
-        return secondary_dirs;
-    }
-
-    directories::ProjectDirs::from("org", "mailliw", "hummingbird")
-        .expect("couldn't generate project dirs")
-}
-
 pub struct DropImageDummyModel;
 
 impl EventEmitter<Vec<Arc<RenderImage>>> for DropImageDummyModel {}
 
 pub fn run() -> anyhow::Result<()> {
-    let dirs = get_dirs();
-    let data_dir = dirs.data_dir().to_path_buf();
+    let data_dir = DataPaths::get().data_dir().to_path_buf();
     fs::create_dir_all(&data_dir).inspect_err(|error| {
         tracing::error!(
             ?error,
@@ -331,153 +402,409 @@ pub fn run() -> anyhow::Result<()> {
     Application::new()
         .with_assets(HummingbirdAssetSource::new(pool.clone()))
         .run(move |cx: &mut App| {
-            let bounds = Bounds::centered(None, size(px(1024.0), px(700.0)), cx);
-            find_fonts(cx).expect("unable to load fonts");
-            register_actions(cx);
-
-            let queue: Arc<RwLock<Vec<QueueItemData>>> = Arc::new(RwLock::new(Vec::new()));
-            let storage = Storage::new(data_dir.join("app_data.json"));
-            let storage_data = storage.load_or_default();
-
-            setup_theme(cx, data_dir.join("theme.json"));
-            setup_settings(cx, data_dir.join("settings.json"));
-
-            build_models(
-                cx,
-                models::Queue {
-                    data: queue.clone(),
-                    position: 0,
-                },
-                &storage_data,
-            );
+            load_bundled_fonts(cx);
+            update_font_fallbacks(cx);
+            let theme_recovery = setup_theme(cx, data_dir.join("theme.json"));
+
+            // the rest of the app can't start until the library database is up to date
+            migrate_then(cx, pool.clone(), move |cx| {
+                start(cx, pool, data_dir, theme_recovery)
+            });
+        });
+
+    Ok(())
+}
+
+/// Sets up the app and opens the main window, once the library database is up to date.
+fn start(cx: &mut App, pool: SqlitePool, data_dir: PathBuf, theme_recovery: Option<Recovery>) {
+    register_actions(cx);
+
+    // libraries scanned before the library root was set still have absolute paths
+    if let Err(err) = crate::RUNTIME.block_on(relativize_library_paths(&pool)) {
+        warn!(
+            ?err,
+            "Couldn't store track paths relative to the library root: {err}"
+        );
+    }
+    start_stall_detector(cx);
+
+    let queue: Arc<RwLock<Vec<QueueItemData>>> = Arc::new(RwLock::new(Vec::new()));
+    let storage = Storage::new(data_dir.join("app_data.json"));
+    let (storage_data, storage_recovery) = storage.load_or_default();
+
+    // the window goes back where it was, unless that's no longer on any display
+    let displays = cx.displays();
+    let display_bounds: Vec<_> = displays
+        .iter()
+        .map(|display| (u32::from(display.id()), display.bounds()))
+        .collect();
+    let restored = storage_data
+        .window
+        .and_then(|window| window.restore(&display_bounds));
+    let display_id = restored.and_then(|(id, _)| {
+        displays
+            .iter()
+            .find(|display| u32::from(display.id()) == id)
+            .map(|display| display.id())
+    });
+    let window_bounds = restored.map(|(_, bounds)| bounds).unwrap_or_else(|| {
+        WindowBounds::Windowed(Bounds::centered(None, size(px(1024.0), px(700.0)), cx))
+    });
+
+    let settings_recovery = setup_settings(cx, data_dir.join("settings.json"));
+
+    build_models(
+        cx,
+        models::Queue {
+            data: queue.clone(),
+            position: None,
+        },
+        &storage_data,
+    );
+
+    // toasts can only be shown once the models exist
+    let recoveries = [
+        (settings_recovery, "your settings"),
+        (theme_recovery, "your theme"),
+        (storage_recovery, "your saved layout"),
+    ];
+    for (recovery, what) in recoveries {
+        if let Some(recovery) = recovery {
+            show_toast(cx, recovery.message(what));
+        }
+    }
 
-            input::bind_actions(cx);
-            modal::bind_actions(cx);
-            library::bind_actions(cx);
+    input::bind_actions(cx);
+    modal::bind_actions(cx);
+    library::bind_actions(cx);
+    queue::bind_actions(cx);
+
+    let settings = cx.global::<SettingsGlobal>().model.read(cx);
+    let playback_settings = settings.playback.clone();
+    let mut scan_interface: ScanInterface =
+        ScanThread::start(pool.clone(), settings.scanning.clone());
+
+    // if the library needs to be rebuilt, the user gets to choose between rebuilding and
+    // a regular scan first
+    if needs_rebuild() {
+        let rebuild_prompt = cx.global::<Models>().rebuild_prompt.clone();
+        rebuild_prompt.write(cx, Some(RebuildReason::FormatUpdated));
+    } else {
+        scan_interface.scan();
+    }
+    scan_interface.start_broadcast(cx);
 
-            let settings = cx.global::<SettingsGlobal>().model.read(cx);
-            let playback_settings = settings.playback.clone();
-            let mut scan_interface: ScanInterface =
-                ScanThread::start(pool.clone(), settings.scanning.clone());
-            scan_interface.scan();
-            scan_interface.start_broadcast(cx);
+    cx.set_global(scan_interface);
 
-            cx.set_global(scan_interface);
-            cx.set_global(Pool(pool));
+    // pass changes to the scan settings on to the scanner, and rescan if the paths changed
+    let settings_model = cx.global::<SettingsGlobal>().model.clone();
+    let mut last_scan_settings = settings_model.read(cx).scanning.clone();
+    cx.observe(&settings_model, move |settings, cx| {
+        let scan_settings = settings.read(cx).scanning.clone();
 
-            let drop_model = cx.new(|_| DropImageDummyModel);
+        if scan_settings == last_scan_settings {
+            return;
+        }
 
-            cx.subscribe(&drop_model, |_, vec, cx| {
-                for image in vec.clone() {
-                    drop_image_from_app(cx, image);
-                }
-            })
-            .detach();
-
-            let mut playback_interface: PlaybackInterface =
-                PlaybackThread::start(queue, playback_settings);
-            playback_interface.start_broadcast(cx);
-
-            if !parse_args_and_prepare(cx, &playback_interface)
-                && let Some(track) = storage_data.current_track
-            {
-                // open current track,
-                playback_interface.open(track.get_path().clone());
-                // but stop it immediately
-                playback_interface.pause();
-            }
-            cx.set_global(playback_interface);
-
-            cx.activate(true);
-
-            cx.open_window(
-                WindowOptions {
-                    window_bounds: Some(WindowBounds::Windowed(bounds)),
-                    window_background: WindowBackgroundAppearance::Opaque,
-                    window_decorations: Some(WindowDecorations::Client),
-                    window_min_size: Some(size(px(800.0), px(600.0))),
-                    titlebar: Some(TitlebarOptions {
-                        title: Some(SharedString::from("Hummingbird")),
-                        appears_transparent: true,
-                        traffic_light_position: Some(Point {
-                            x: px(12.0),
-                            y: px(11.0),
-                        }),
-                    }),
-                    app_id: Some("org.mailliw.hummingbird".to_string()),
-                    kind: WindowKind::Normal,
-                    ..Default::default()
-                },
-                |window, cx| {
-                    window.set_window_title("Hummingbird");
-
-                    register_pbc_event_handlers(cx);
-                    init_pbc_task(cx, window);
-
-                    let palette = CommandPalette::new(cx, window);
-
-                    cx.set_global(CommandPaletteHolder::new(palette.clone()));
-
-                    cx.new(|cx| {
-                        cx.observe_window_appearance(window, |_, _, cx| {
-                            cx.refresh_windows();
-                        })
-                        .detach();
-
-                        // Update `StorageData` and save it to file system while quitting the app
-                        cx.on_app_quit({
-                            let current_track = cx.global::<PlaybackInfo>().current_track.clone();
-                            let sidebar_width = cx.global::<Models>().sidebar_width.clone();
-                            let queue_width = cx.global::<Models>().queue_width.clone();
-                            let table_settings = cx.global::<Models>().table_settings.clone();
-                            move |_, cx| {
-                                let current_track = current_track.read(cx).clone();
-                                let sidebar_width: f32 = (*sidebar_width.read(cx)).into();
-                                let queue_width: f32 = (*queue_width.read(cx)).into();
-                                let table_settings = table_settings.read(cx).clone();
-                                let storage = storage.clone();
-                                cx.background_executor().spawn(async move {
-                                    storage.save(&StorageData {
-                                        current_track,
-                                        sidebar_width,
-                                        queue_width,
-                                        table_settings,
-                                    });
-                                })
-                            }
-                        })
-                        .detach();
-
-                        let show_queue = cx.new(|_| true);
-                        let show_about = cx.global::<Models>().show_about.clone();
-
-                        cx.observe(&show_about, |_, _, cx| {
-                            cx.notify();
-                        })
-                        .detach();
-
-                        WindowShadow {
-                            controls: Controls::new(cx, show_queue.clone()),
-                            queue: Queue::new(cx, show_queue.clone()),
-                            library: Library::new(cx),
-                            header: Header::new(cx),
-                            search: SearchView::new(cx),
-                            show_queue,
-                            show_about,
-                            palette,
-                            // use a really small global image cache
-                            // this is literally just to ensure that images are *always* removed
-                            // from memory *at some point*
-                            //
-                            // if your view uses a lot of images you need to have your own image
-                            // cache
-                            image_cache: HummingbirdImageCache::new(20, cx),
+        let paths_changed = scan_settings.paths != last_scan_settings.paths;
+        last_scan_settings = scan_settings.clone();
+
+        let scanner = cx.global::<ScanInterface>();
+        scanner.update_settings(scan_settings);
+
+        if paths_changed {
+            scanner.scan();
+        }
+    })
+    .detach();
+
+    cx.set_global(Pool(pool));
+    load_liked_playlist(cx).expect("the liked songs playlist is created when migrating");
+    library::album_filters::watch_filter_options(cx);
+
+    let drop_model = cx.new(|_| DropImageDummyModel);
+
+    cx.subscribe(&drop_model, |_, vec, cx| {
+        for image in vec.clone() {
+            drop_image_from_app(cx, image);
+        }
+    })
+    .detach();
+
+    let mut playback_interface: PlaybackInterface =
+        PlaybackThread::start(queue, playback_settings, cx.global::<Pool>().0.clone());
+    playback_interface.start_broadcast(cx);
+
+    if !parse_args_and_prepare(cx, &playback_interface)
+        && let Some(track) = storage_data.current_track
+    {
+        // open current track,
+        playback_interface.open(track.get_path().clone());
+        // but stop it immediately
+        playback_interface.pause();
+    }
+    cx.set_global(playback_interface);
+
+    // crossfeed, the equalizer, mono downmixing, balance, crossfade, pausing between albums,
+    // ReplayGain, the device provider and the output device can be changed during playback, the
+    // other playback settings only apply after a restart
+    let mut last_crossfeed = settings_model.read(cx).playback.crossfeed;
+    let mut last_equalizer = settings_model.read(cx).playback.equalizer;
+    let mut last_mono_downmix = settings_model.read(cx).playback.mono_downmix;
+    let mut last_balance = settings_model.read(cx).playback.balance;
+    let mut last_crossfade = settings_model.read(cx).playback.crossfade;
+    let mut last_pause_at_album_boundaries =
+        settings_model.read(cx).playback.pause_at_album_boundaries;
+    let mut last_replaygain = {
+        let playback = &settings_model.read(cx).playback;
+        (playback.replaygain, playback.replaygain_fallback)
+    };
+    let mut last_device_provider = settings_model.read(cx).playback.device_provider;
+    let mut last_output_device = settings_model.read(cx).playback.output_device.clone();
+    let mut last_exclusive = settings_model.read(cx).playback.exclusive;
+    let mut last_dither = settings_model.read(cx).playback.dither;
+    cx.observe(&settings_model, move |settings, cx| {
+        let crossfeed = settings.read(cx).playback.crossfeed;
+        let equalizer = settings.read(cx).playback.equalizer;
+        let mono_downmix = settings.read(cx).playback.mono_downmix;
+        let balance = settings.read(cx).playback.balance;
+        let crossfade = settings.read(cx).playback.crossfade;
+        let pause_at_album_boundaries = settings.read(cx).playback.pause_at_album_boundaries;
+        let mode = settings.read(cx).playback.replaygain;
+        let fallback = settings.read(cx).playback.replaygain_fallback;
+        let device_provider = settings.read(cx).playback.device_provider;
+        let output_device = settings.read(cx).playback.output_device.clone();
+        let exclusive = settings.read(cx).playback.exclusive;
+        let dither = settings.read(cx).playback.dither;
+
+        if crossfeed != last_crossfeed {
+            last_crossfeed = crossfeed;
+            cx.global::<PlaybackInterface>().set_crossfeed(crossfeed);
+        }
+
+        if equalizer != last_equalizer {
+            last_equalizer = equalizer;
+            cx.global::<PlaybackInterface>().set_equalizer(equalizer);
+        }
+
+        if mono_downmix != last_mono_downmix {
+            last_mono_downmix = mono_downmix;
+            cx.global::<PlaybackInterface>()
+                .set_mono_downmix(mono_downmix);
+        }
+
+        if balance != last_balance {
+            last_balance = balance;
+            cx.global::<PlaybackInterface>().set_balance(balance);
+        }
+
+        if crossfade != last_crossfade {
+            last_crossfade = crossfade;
+            cx.global::<PlaybackInterface>().set_crossfade(crossfade);
+        }
+
+        if pause_at_album_boundaries != last_pause_at_album_boundaries {
+            last_pause_at_album_boundaries = pause_at_album_boundaries;
+            cx.global::<PlaybackInterface>()
+                .set_pause_at_album_boundaries(pause_at_album_boundaries);
+        }
+
+        if (mode, fallback) != last_replaygain {
+            last_replaygain = (mode, fallback);
+            cx.global::<PlaybackInterface>()
+                .set_replaygain(mode, fallback);
+        }
+
+        if device_provider != last_device_provider {
+            last_device_provider = device_provider;
+            cx.global::<PlaybackInterface>()
+                .set_device_provider(device_provider);
+        }
+
+        if output_device != last_output_device {
+            last_output_device = output_device.clone();
+            cx.global::<PlaybackInterface>().set_device(output_device);
+        }
+
+        if exclusive != last_exclusive {
+            last_exclusive = exclusive;
+            cx.global::<PlaybackInterface>().set_exclusive(exclusive);
+        }
+
+        if dither != last_dither {
+            last_dither = dither;
+            cx.global::<PlaybackInterface>().set_dither(dither);
+        }
+    })
+    .detach();
+
+    cx.activate(true);
+
+    cx.open_window(
+        WindowOptions {
+            window_bounds: Some(window_bounds),
+            display_id,
+            window_background: WindowBackgroundAppearance::Opaque,
+            window_decorations: Some(WindowDecorations::Client),
+            window_min_size: Some(size(px(800.0), px(600.0))),
+            titlebar: Some(TitlebarOptions {
+                title: Some(SharedString::from("Hummingbird")),
+                appears_transparent: true,
+                traffic_light_position: Some(Point {
+                    x: px(12.0),
+                    y: px(11.0),
+                }),
+            }),
+            app_id: Some("org.mailliw.hummingbird".to_string()),
+            kind: WindowKind::Normal,
+            ..Default::default()
+        },
+        |window, cx| {
+            window.set_window_title(APP_TITLE);
+
+            register_pbc_event_handlers(cx);
+            init_pbc_task(cx, window);
+            external_drag::install(window);
+            register_window_title_handlers(cx, window);
+
+            let palette = CommandPalette::new(cx, window);
+
+            cx.set_global(CommandPaletteHolder::new(palette.clone()));
+
+            cx.new(|cx| {
+                cx.observe_window_appearance(window, |_, _, cx| {
+                    cx.refresh_windows();
+                })
+                .detach();
+
+                // Update `StorageData` and save it to file system while quitting the app
+                cx.on_app_quit({
+                    let storage = storage.clone();
+                    move |_, cx| save_storage(cx, storage.clone())
+                })
+                .detach();
+
+                // the window's position is also saved shortly after it's moved, in case the app
+                // doesn't get to quit normally
+                let window_state = cx.global::<Models>().window_state.clone();
+                cx.observe_window_bounds(window, move |this: &mut WindowShadow, window, cx| {
+                    let display_id = window.display(cx).map(|display| u32::from(display.id()));
+                    let state = WindowState::new(window.window_bounds(), display_id);
+
+                    if window_state.read(cx) == &Some(state) {
+                        return;
+                    }
+                    window_state.write(cx, Some(state));
+
+                    let storage = storage.clone();
+                    this.save_window_task = Some(cx.spawn(async move |_, cx| {
+                        cx.background_executor().timer(WINDOW_SAVE_DELAY).await;
+
+                        if let Ok(task) = cx.update(|cx| save_storage(cx, storage)) {
+                            task.await;
                         }
-                    })
-                },
-            )
-            .unwrap();
-        });
+                    }));
+                })
+                .detach();
 
-    Ok(())
+                let show_queue = cx.new(|_| true);
+                let show_about = cx.global::<Models>().show_about.clone();
+
+                cx.observe(&show_about, |_, _, cx| {
+                    cx.notify();
+                })
+                .detach();
+
+                let rebuild_prompt = cx.global::<Models>().rebuild_prompt.clone();
+
+                cx.observe(&rebuild_prompt, |_, _, cx| {
+                    cx.notify();
+                })
+                .detach();
+
+                let large_queue = cx.global::<Models>().large_queue.clone();
+
+                cx.observe(&large_queue, |_, _, cx| {
+                    cx.notify();
+                })
+                .detach();
+
+                let whats_new = cx.global::<Models>().whats_new.clone();
+
+                cx.observe(&whats_new, |_, _, cx| {
+                    cx.notify();
+                })
+                .detach();
+
+                let crash_report = cx.global::<Models>().crash_report.clone();
+
+                cx.observe(&crash_report, |_, _, cx| {
+                    cx.notify();
+                })
+                .detach();
+
+                // apply interface setting changes live - every view has to be redrawn,
+                // since most of them cache their contents
+                let settings_model = cx.global::<SettingsGlobal>().model.clone();
+                let mut last_interface_settings = settings_model.read(cx).interface.clone();
+                cx.observe(
+                    &settings_model,
+                    move |this: &mut WindowShadow, settings, cx| {
+                        let interface_settings = settings.read(cx).interface.clone();
+
+                        if interface_settings == last_interface_settings {
+                            return;
+                        }
+
+                        if interface_settings.font_family != last_interface_settings.font_family {
+                            this.font_family = resolve_font_family(cx, &interface_settings);
+                        }
+
+                        last_interface_settings = interface_settings;
+                        cx.refresh_windows();
+                    },
+                )
+                .detach();
+
+                let font_family = resolve_font_family(cx, &settings_model.read(cx).interface);
+                let show_font_picker = cx.global::<Models>().show_font_picker.clone();
+                let show_device_picker = cx.global::<Models>().show_device_picker.clone();
+                let show_shortcuts = cx.global::<Models>().show_shortcuts.clone();
+                let show_liked_playlist_picker = cx.new(|cx| {
+                    cx.has_pending_check(LIKED_PLAYLIST_CHECK)
+                        .unwrap_or_default()
+                });
+
+                WindowShadow {
+                    controls: Controls::new(cx, show_queue.clone()),
+                    queue: Queue::new(cx, show_queue.clone()),
+                    library: Library::new(cx),
+                    header: Header::new(cx),
+                    search: SearchView::new(cx),
+                    show_queue,
+                    show_about,
+                    rebuild_prompt,
+                    large_queue,
+                    whats_new,
+                    crash_report,
+                    palette,
+                    toasts: ToastView::new(cx),
+                    font_picker: FontPicker::new(cx, show_font_picker),
+                    device_picker: DevicePicker::new(cx, show_device_picker),
+                    shortcut_help: ShortcutHelp::new(cx, show_shortcuts),
+                    liked_playlist_picker: LikedPlaylistPicker::new(cx, show_liked_playlist_picker),
+                    // use a really small global image cache
+                    // this is literally just to ensure that images are *always* removed
+                    // from memory *at some point*
+                    //
+                    // if your view uses a lot of images you need to have your own image
+                    // cache
+                    image_cache: HummingbirdImageCache::new("global", 20, cx),
+                    font_family,
+                    save_window_task: None,
+                }
+            })
+        },
+    )
+    .unwrap();
 }
@@ -11,6 +11,18 @@ use crate::playback::{interface::PlaybackInterface, queue::QueueItemData};
 struct Args {
     #[arg()]
     files: Option<Vec<PathBuf>>,
+
+    /// Keep the library, settings and everything else in this folder (portable mode).
+    #[arg(long, value_name = "PATH")]
+    data_dir: Option<PathBuf>,
+}
+
+/// Returns the data directory given with `--data-dir`, if any. See [`crate::paths::DataPaths`].
+///
+/// Invalid arguments are ignored here, since this can be called before they're checked (or, in
+/// tests, with arguments meant for the test harness).
+pub fn data_dir_argument() -> Option<PathBuf> {
+    Args::try_parse().ok().and_then(|args| args.data_dir)
 }
 
 /// Parses the arguments provided by the user and handles them. Returns true if files were provided
@@ -25,8 +25,13 @@ pub fn load(pool: &SqlitePool, url: Url) -> gpui::Result<Option<Cow<'static, [u8
                 _ => unimplemented!("invalid image type '{image_type}'"),
             };
 
-            let (image,) =
-                crate::RUNTIME.block_on(sqlx::query_as(query).bind(id).fetch_one(pool))?;
+            // not cached, so that the statement doesn't hold on to the image afterwards
+            let (image,) = crate::RUNTIME.block_on(
+                sqlx::query_as(query)
+                    .persistent(false)
+                    .bind(id)
+                    .fetch_one(pool),
+            )?;
             Ok(Some(Cow::Owned(image)))
         }
         _ => Ok(None),
@@ -2,53 +2,72 @@ use std::{collections::VecDeque, mem::take};
 
 use futures::FutureExt;
 use gpui::{
-    App, AppContext, Asset, AssetLogger, ElementId, Entity, ImageAssetLoader, ImageCache,
-    ImageCacheItem, ImageCacheProvider, ImageSource, Resource, hash,
+    App, AppContext, Asset, AssetLogger, Entity, ImageAssetLoader, ImageCache, ImageCacheItem,
+    ImageSource, Pixels, Resource, SharedString, Window, hash,
 };
 use rustc_hash::{FxBuildHasher, FxHashMap};
-use tracing::{error, trace};
+use tracing::{debug, error, trace};
 
-pub fn hummingbird_cache(
-    id: impl Into<ElementId>,
-    max_items: usize,
-) -> HummingbirdImageCacheProvider {
-    HummingbirdImageCacheProvider {
-        id: id.into(),
-        max_items,
-    }
-}
+use crate::{library::scan::AlbumArtChanged, ui::models::Models};
 
-pub struct HummingbirdImageCacheProvider {
-    id: ElementId,
-    max_items: usize,
+/// The number of extra rows (above and below the viewport) that a scrolling surface's image cache
+/// keeps around, so that small scrolls in either direction don't immediately re-decode images.
+const CACHE_MARGIN_ROWS: usize = 8;
+
+/// Calculates an appropriate image cache capacity for a scrolling surface, based on the number of
+/// items that can be visible in the window at once (rows × columns) plus a margin.
+pub fn visible_capacity(window: &Window, row_height: Pixels, columns: usize) -> usize {
+    let rows = (window.viewport_size().height / row_height).ceil() as usize;
+
+    (rows + CACHE_MARGIN_ROWS) * columns.max(1)
 }
 
-impl ImageCacheProvider for HummingbirdImageCacheProvider {
-    fn provide(&mut self, window: &mut gpui::Window, cx: &mut App) -> gpui::AnyImageCache {
-        window
-            .with_global_id(self.id.clone(), |id, window| {
-                window.with_element_state(id, |cache, _| {
-                    let cache =
-                        cache.unwrap_or_else(|| HummingbirdImageCache::new(self.max_items, cx));
+/// The resources an album's art is loaded from, as both a thumbnail and a full-size image.
+fn album_art_resources(album_id: i64) -> impl Iterator<Item = Resource> {
+    ["thumb", "full"].into_iter().filter_map(move |kind| {
+        let path = SharedString::from(format!("!db://album/{album_id}/{kind}"));
 
-                    (cache.clone(), cache)
-                })
-            })
-            .into()
+        match ImageSource::from(path) {
+            ImageSource::Resource(resource) => Some(resource),
+            _ => None,
+        }
+    })
+}
+
+/// Drops the album's art from the asset cache and redraws, so that the art is loaded from the
+/// database again. Image caches drop their own copies when the scan state model emits
+/// [AlbumArtChanged].
+pub fn forget_album_art(album_id: i64, cx: &mut App) {
+    for resource in album_art_resources(album_id) {
+        ImageSource::Resource(resource).remove_asset(cx);
     }
+
+    cx.refresh_windows();
 }
 
 pub struct HummingbirdImageCache {
+    name: &'static str,
     max_items: usize,
     usage_list: VecDeque<u64>,
     cache: FxHashMap<u64, (ImageCacheItem, Resource)>,
+    hits: u64,
+    misses: u64,
 }
 
 impl HummingbirdImageCache {
-    pub fn new(max_items: usize, cx: &mut App) -> Entity<Self> {
+    /// Creates a new image cache. Views that display many images should own their cache, so that
+    /// it (and every image it holds) is dropped alongside the view.
+    pub fn new(name: &'static str, max_items: usize, cx: &mut App) -> Entity<Self> {
         cx.new(|cx| {
-            trace!("Creating HummingbirdImageCache");
+            trace!(cache = name, max_items, "Creating HummingbirdImageCache");
             cx.on_release(|this: &mut Self, cx| {
+                debug!(
+                    cache = this.name,
+                    hits = this.hits,
+                    misses = this.misses,
+                    "Releasing HummingbirdImageCache"
+                );
+
                 for (idx, (mut image, resource)) in take(&mut this.cache) {
                     if let Some(Ok(image)) = image.get() {
                         trace!("Dropping image {idx}");
@@ -60,13 +79,82 @@ impl HummingbirdImageCache {
             })
             .detach();
 
+            let scan_state = cx.global::<Models>().scan_state.clone();
+            cx.subscribe(
+                &scan_state,
+                |this: &mut Self, _, changed: &AlbumArtChanged, cx| {
+                    this.forget_album(changed.0, cx);
+                },
+            )
+            .detach();
+
             HummingbirdImageCache {
+                name,
                 max_items,
                 usage_list: VecDeque::with_capacity(max_items),
                 cache: FxHashMap::with_capacity_and_hasher(max_items, FxBuildHasher),
+                hits: 0,
+                misses: 0,
             }
         })
     }
+
+    /// Changes the maximum number of items held by the cache. If the cache currently holds more
+    /// items than the new maximum, the least recently used items are evicted immediately.
+    pub fn set_max_items(&mut self, max_items: usize, window: &mut Window, cx: &mut App) {
+        if self.max_items == max_items {
+            return;
+        }
+
+        trace!(
+            cache = self.name,
+            old = self.max_items,
+            new = max_items,
+            "Resizing HummingbirdImageCache"
+        );
+
+        self.max_items = max_items;
+
+        while self.usage_list.len() > self.max_items {
+            self.evict_oldest(window, cx);
+        }
+    }
+
+    /// Drops the album's art, if it's cached.
+    fn forget_album(&mut self, album_id: i64, cx: &mut App) {
+        for resource in album_art_resources(album_id) {
+            let hash = hash(&resource);
+
+            let Some((mut image, resource)) = self.cache.remove(&hash) else {
+                continue;
+            };
+            self.usage_list.retain(|item| *item != hash);
+
+            if let Some(Ok(image)) = image.get() {
+                cx.drop_image(image, None);
+            }
+
+            ImageSource::Resource(resource).remove_asset(cx);
+        }
+    }
+
+    fn evict_oldest(&mut self, window: &mut Window, cx: &mut App) {
+        let Some(oldest) = self.usage_list.pop_back() else {
+            return;
+        };
+
+        let mut image = self
+            .cache
+            .remove(&oldest)
+            .expect("usage_list has an item cache doesn't");
+
+        if let Some(Ok(image)) = image.0.get() {
+            trace!("requesting image to be dropped");
+            cx.drop_image(image, Some(window));
+        }
+
+        ImageSource::Resource(image.1).remove_asset(cx);
+    }
 }
 
 impl ImageCache for HummingbirdImageCache {
@@ -88,27 +176,31 @@ impl ImageCache for HummingbirdImageCache {
             self.usage_list.remove(current_idx);
             self.usage_list.push_front(hash);
 
+            self.hits += 1;
+            trace!(
+                cache = self.name,
+                hits = self.hits,
+                misses = self.misses,
+                "image cache hit"
+            );
+
             return item.0.get();
         }
 
+        self.misses += 1;
+        trace!(
+            cache = self.name,
+            hits = self.hits,
+            misses = self.misses,
+            "image cache miss"
+        );
+
         let load_future = AssetLogger::<ImageAssetLoader>::load(resource.clone(), cx);
         let task = cx.background_executor().spawn(load_future).shared();
 
         if self.usage_list.len() >= self.max_items {
             trace!("Image cache is full, evicting oldest item");
-
-            let oldest = self.usage_list.pop_back().unwrap();
-            let mut image = self
-                .cache
-                .remove(&oldest)
-                .expect("usage_list has an item cache doesn't");
-
-            if let Some(Ok(image)) = image.0.get() {
-                trace!("requesting image to be dropped");
-                cx.drop_image(image, Some(window));
-            }
-
-            ImageSource::Resource(image.1).remove_asset(cx);
+            self.evict_oldest(window, cx);
         }
 
         self.cache.insert(
@@ -12,9 +12,14 @@ use tracing::error;
 use crate::ui::{
     components::{
         modal::modal,
-        palette::{FinderItemLeft, Palette, PaletteItem},
+        palette::{AcceptIntent, FinderItemLeft, Palette, PaletteItem},
     },
-    global_actions::{About, ForceScan, Next, PlayPause, Previous, Quit, Search},
+    global_actions::{
+        About, AddMusicFolder, ChooseFont, ChooseOutputDevice, DecreaseUiScale, ForceScan,
+        IncreaseUiScale, Next, PlayPause, PlayRandomAlbum, Previous, Quit, ResetUiScale, Search,
+        ShowShortcuts, ToggleHiddenAlbums, UpdateLibrary,
+    },
+    shortcuts::{ActionGroup, binding_label, describe_action},
 };
 
 actions!(hummingbird, [OpenPalette]);
@@ -68,24 +73,12 @@ impl PaletteItem for Command {
     }
 
     fn right_content(&self, cx: &mut gpui::App) -> Option<SharedString> {
-        cx.key_bindings()
-            .borrow()
-            .bindings_for_action(&(*self.action))
-            .last()
-            .map(|binding| {
-                binding
-                    .keystrokes()
-                    .iter()
-                    .map(|key| key.to_string())
-                    .collect::<Vec<String>>()
-                    .join(" + ")
-                    .into()
-            })
+        binding_label(cx, &(*self.action))
     }
 }
 
 type MatcherFunc = Box<dyn Fn(&Arc<Command>, &mut App) -> Utf32String + 'static>;
-type OnAccept = Box<dyn Fn(&Arc<Command>, &mut App) + 'static>;
+type OnAccept = Box<dyn Fn(&Arc<Command>, AcceptIntent, &mut App) + 'static>;
 
 pub struct CommandPalette {
     show: Entity<bool>,
@@ -100,7 +93,7 @@ impl CommandPalette {
             let matcher: MatcherFunc = Box::new(|item, _| item.name.to_string().into());
 
             let show_clone = show.clone();
-            let on_accept: OnAccept = Box::new(move |item, cx| {
+            let on_accept: OnAccept = Box::new(move |item, _, cx| {
                 let item = item.clone();
                 let show_clone = show_clone.clone();
                 cx.defer(move |cx| {
@@ -155,6 +148,15 @@ impl CommandPalette {
                 ("hummingbird::search", 0),
                 Command::new(Some("Hummingbird"), "Search", Search, None),
             );
+            items.insert(
+                ("hummingbird::showshortcuts", 0),
+                Command::new(
+                    Some("Hummingbird"),
+                    "Keyboard Shortcuts",
+                    ShowShortcuts,
+                    None,
+                ),
+            );
 
             items.insert(
                 ("player::playpause", 0),
@@ -173,10 +175,64 @@ impl CommandPalette {
                 ("player::previous", 0),
                 Command::new(Some("Playback"), "Previous Track", Previous, None),
             );
+            items.insert(
+                ("player::chooseoutputdevice", 0),
+                Command::new(
+                    Some("Playback"),
+                    "Change Output Device",
+                    ChooseOutputDevice,
+                    None,
+                ),
+            );
+
+            items.insert(
+                ("library::playrandomalbum", 0),
+                Command::new(Some("Library"), "Play Random Album", PlayRandomAlbum, None),
+            );
+
+            items.insert(
+                ("library::togglehiddenalbums", 0),
+                Command::new(
+                    Some("Library"),
+                    "Show or Hide Hidden Albums",
+                    ToggleHiddenAlbums,
+                    None,
+                ),
+            );
+
+            items.insert(
+                ("scan::updatelibrary", 0),
+                Command::new(Some("Scan"), "Update Library", UpdateLibrary, None),
+            );
 
             items.insert(
                 ("scan::forcescan", 0),
-                Command::new(Some("Scan"), "Rescan Entire Library", ForceScan, None),
+                Command::new(Some("Scan"), "Rebuild Library Metadata", ForceScan, None),
+            );
+
+            items.insert(
+                ("scan::addmusicfolder", 0),
+                Command::new(Some("Scan"), "Add Music Folder", AddMusicFolder, None),
+            );
+
+            items.insert(
+                ("interface::choosefont", 0),
+                Command::new(Some("Interface"), "Change Font", ChooseFont, None),
+            );
+
+            items.insert(
+                ("interface::increaseuiscale", 0),
+                Command::new(Some("Interface"), "Increase Size", IncreaseUiScale, None),
+            );
+
+            items.insert(
+                ("interface::decreaseuiscale", 0),
+                Command::new(Some("Interface"), "Decrease Size", DecreaseUiScale, None),
+            );
+
+            items.insert(
+                ("interface::resetuiscale", 0),
+                Command::new(Some("Interface"), "Reset Size", ResetUiScale, None),
             );
 
             let palette = Palette::new(
@@ -189,6 +245,7 @@ impl CommandPalette {
 
             let weak_self = cx.weak_entity();
             let show_clone = show.clone();
+            describe_action(cx, ActionGroup::Navigation, "Command Palette", OpenPalette);
             App::on_action(cx, move |_: &OpenPalette, cx: &mut App| {
                 show_clone.update(cx, |show, cx| {
                     *show = true;
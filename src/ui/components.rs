@@ -2,9 +2,11 @@ pub mod button;
 pub mod checkbox;
 pub mod context;
 pub mod drag_drop;
+pub mod empty_state;
 pub mod icons;
 pub mod input;
 pub mod label;
+pub mod lightbox;
 pub mod menu;
 pub mod modal;
 pub mod nav_button;
@@ -1,18 +1,48 @@
 use gpui::*;
 use smallvec::SmallVec;
 use std::{
-    cell::RefCell,
+    cell::{Cell, RefCell},
     rc::Rc,
     sync::atomic::{AtomicBool, Ordering},
 };
 
-use crate::ui::theme::Theme;
+use crate::ui::{components::menu::MenuAction, theme::Theme};
+
+/// How far menus are kept from the edges of the window.
+const WINDOW_MARGIN: Pixels = px(8.0);
+
+thread_local! {
+    /// The state of the context menu whose menu is being laid out, so that the [Menu] inside it
+    /// can share its items' actions and find out which item is highlighted.
+    ///
+    /// [Menu]: super::menu::Menu
+    static RENDERING_MENU: RefCell<Option<Rc<ContextMenuState>>> = const { RefCell::new(None) };
+}
+
+/// Returns the state of the context menu that's currently being laid out, if any.
+pub(super) fn rendering_menu() -> Option<Rc<ContextMenuState>> {
+    RENDERING_MENU.with(|menu| menu.borrow().clone())
+}
+
+/// Returns true for the keys that open a context menu from the keyboard: the menu key, and
+/// Shift+F10.
+fn is_menu_keystroke(keystroke: &Keystroke) -> bool {
+    let modifiers = keystroke.modifiers;
+    let plain = !(modifiers.control || modifiers.alt || modifiers.platform || modifiers.function);
+
+    match keystroke.key.as_str() {
+        "menu" | "contextmenu" | "apps" => plain && !modifiers.shift,
+        "f10" => plain && modifiers.shift,
+        _ => false,
+    }
+}
 
 pub struct ContextMenu {
     pub(self) id: ElementId,
     pub(self) style: StyleRefinement,
     pub(self) element: Option<AnyElement>,
     pub(self) menu: Option<Div>,
+    pub(self) button: MouseButton,
 }
 
 impl ContextMenu {
@@ -20,6 +50,13 @@ impl ContextMenu {
         self.element = Some(element.into_any_element());
         self
     }
+
+    /// Sets the mouse button that opens the menu. Defaults to the right mouse button - use the
+    /// left mouse button for menus opened from a button.
+    pub fn button(mut self, button: MouseButton) -> Self {
+        self.button = button;
+        self
+    }
 }
 
 impl Styled for ContextMenu {
@@ -42,18 +79,97 @@ impl ParentElement for ContextMenu {
     }
 }
 
-struct ContextMenuState {
-    pub clicked_in: AtomicBool,
-    pub position: RefCell<Option<Point<Pixels>>>,
+pub(super) struct ContextMenuState {
+    clicked_in: AtomicBool,
+    position: RefCell<Option<Point<Pixels>>>,
+    /// The item chosen with the arrow keys. Indexes into `actions`.
+    highlighted: Cell<Option<usize>>,
+    /// What each of the menu's items does, or None for items that can't be chosen. Filled in by
+    /// the menu every time it's laid out.
+    actions: RefCell<Vec<Option<Rc<MenuAction>>>>,
 }
 
 impl ContextMenuState {
-    pub fn new() -> Self {
+    fn new() -> Self {
         ContextMenuState {
             clicked_in: AtomicBool::new(false),
             position: RefCell::new(None),
+            highlighted: Cell::new(None),
+            actions: RefCell::new(Vec::new()),
+        }
+    }
+
+    pub(super) fn highlighted(&self) -> Option<usize> {
+        self.highlighted.get()
+    }
+
+    pub(super) fn set_actions(&self, actions: Vec<Option<Rc<MenuAction>>>) {
+        *self.actions.borrow_mut() = actions;
+
+        // the items may have changed since the highlight was set
+        if let Some(idx) = self.highlighted.get()
+            && self.actions.borrow().get(idx).is_none_or(Option::is_none)
+        {
+            self.highlighted.set(idx.checked_sub(1));
+            if !self.move_highlight(true) {
+                self.highlighted.set(None);
+            }
         }
     }
+
+    fn open(&self, position: Point<Pixels>, highlighted: Option<usize>) {
+        *self.position.borrow_mut() = Some(position);
+        self.highlighted.set(highlighted);
+    }
+
+    fn close(&self) {
+        *self.position.borrow_mut() = None;
+        self.highlighted.set(None);
+    }
+
+    fn is_open(&self) -> bool {
+        self.position.borrow().is_some()
+    }
+
+    /// Moves the highlight to the next item that can be chosen, in the given direction, wrapping
+    /// around at the ends. Returns false if there's nothing to highlight.
+    fn move_highlight(&self, forwards: bool) -> bool {
+        let actions = self.actions.borrow();
+        let len = actions.len();
+        if len == 0 {
+            return false;
+        }
+
+        let start = match (self.highlighted.get(), forwards) {
+            (Some(idx), true) => idx + 1,
+            (Some(idx), false) => idx + len - 1,
+            (None, true) => 0,
+            (None, false) => len - 1,
+        };
+
+        let step = if forwards { 1 } else { len - 1 };
+        let next = (0..len)
+            .map(|offset| (start + offset * step) % len)
+            .find(|idx| actions[*idx].is_some());
+
+        if next.is_some() {
+            self.highlighted.set(next);
+        }
+
+        next.is_some()
+    }
+
+    /// Highlights the first (or last) item that can be chosen.
+    fn highlight_edge(&self, first: bool) {
+        self.highlighted.set(None);
+        self.move_highlight(first);
+    }
+
+    /// Returns what the highlighted item does, if an item is highlighted.
+    fn highlighted_action(&self) -> Option<Rc<MenuAction>> {
+        let idx = self.highlighted.get()?;
+        self.actions.borrow().get(idx).cloned().flatten()
+    }
 }
 
 impl Element for ContextMenu {
@@ -98,31 +214,39 @@ impl Element for ContextMenu {
                     let state_clone = state.clone();
                     let state_clone_2 = state.clone();
 
-                    let new = anchored().position(position).child(deferred(
-                        menu.occlude()
-                            .border_1()
-                            .shadow_sm()
-                            .rounded(px(6.0))
-                            .border_color(theme.elevated_border_color)
-                            .bg(theme.elevated_background)
-                            .id("menu")
-                            .on_click(move |_, window, _| {
-                                (*state_clone.position.borrow_mut()) = None;
-                                window.refresh()
-                            })
-                            .on_mouse_down_out(move |_, window, _| {
-                                (*state_clone_2.position.borrow_mut()) = None;
-                                window.refresh()
-                            }),
-                    ));
-                    (Some(new), state)
+                    // flips to the other side of the position if the menu doesn't fit, and is
+                    // kept inside the window if it doesn't fit either way
+                    let new = anchored()
+                        .position(position)
+                        .snap_to_window_with_margin(WINDOW_MARGIN)
+                        .child(deferred(
+                            menu.occlude()
+                                .border_1()
+                                .shadow_sm()
+                                .rounded(px(6.0))
+                                .border_color(theme.elevated_border_color)
+                                .bg(theme.elevated_background)
+                                .id("menu")
+                                .on_click(move |_, window, _| {
+                                    state_clone.close();
+                                    window.refresh()
+                                })
+                                .on_mouse_down_out(move |_, window, _| {
+                                    state_clone_2.close();
+                                    window.refresh()
+                                }),
+                        ));
+                    (Some((new, state.clone())), state)
                 } else {
                     (None, state)
                 }
             });
 
-        let state = if let Some(mut anchored) = anchored {
+        let state = if let Some((mut anchored, menu_state)) = anchored {
+            RENDERING_MENU.with(|menu| *menu.borrow_mut() = Some(menu_state));
             let layout = anchored.request_layout(None, None, window, cx);
+            RENDERING_MENU.with(|menu| *menu.borrow_mut() = None);
+
             layout_ids.push(layout.0);
             Some((anchored, layout.0, layout.1))
         } else {
@@ -192,9 +316,10 @@ impl Element for ContextMenu {
         window.with_element_state(id.unwrap(), |prev: Option<Rc<ContextMenuState>>, cx| {
             let state = prev.unwrap_or_else(|| Rc::new(ContextMenuState::new()));
             let state_clone = state.clone();
+            let button = self.button;
 
             cx.on_mouse_event(move |ev: &MouseDownEvent, phase, _, _| {
-                if ev.button == MouseButton::Right
+                if ev.button == button
                     && phase == DispatchPhase::Bubble
                     && bounds.contains(&ev.position)
                 {
@@ -208,15 +333,60 @@ impl Element for ContextMenu {
                 if phase == DispatchPhase::Bubble {
                     let clicked_in = state_clone_2.clicked_in.swap(false, Ordering::AcqRel);
 
-                    if ev.button == MouseButton::Right
-                        && bounds.contains(&ev.position)
-                        && clicked_in
-                    {
-                        (*state_clone_2.position.borrow_mut()) = Some(ev.position)
+                    if ev.button == button && bounds.contains(&ev.position) && clicked_in {
+                        state_clone_2.open(ev.position, None);
                     }
                 }
             });
 
+            let state_clone_3 = state.clone();
+
+            // only reached while something inside the element (like a focusable row) has focus
+            window.on_key_event(move |ev: &KeyDownEvent, phase, window, cx| {
+                if phase != DispatchPhase::Bubble {
+                    return;
+                }
+
+                let state = &state_clone_3;
+
+                if !state.is_open() {
+                    if is_menu_keystroke(&ev.keystroke) {
+                        // if the first item can't be chosen, the highlight moves on to the next
+                        // one that can once the menu has been laid out
+                        state.open(point(bounds.left() + px(16.0), bounds.bottom()), Some(0));
+                        window.refresh();
+                        cx.stop_propagation();
+                    }
+                    return;
+                }
+
+                match ev.keystroke.key.as_str() {
+                    "down" => {
+                        state.move_highlight(true);
+                    }
+                    "up" => {
+                        state.move_highlight(false);
+                    }
+                    "home" => state.highlight_edge(true),
+                    "end" => state.highlight_edge(false),
+                    "enter" | "space" => {
+                        if let Some(action) = state.highlighted_action() {
+                            let click = ClickEvent::Keyboard(KeyboardClickEvent {
+                                button: KeyboardButton::Enter,
+                                bounds,
+                            });
+                            state.close();
+                            action(&click, window, cx);
+                        }
+                    }
+                    "escape" => state.close(),
+                    _ => return,
+                }
+
+                window.refresh();
+                cx.stop_propagation();
+            });
+
             ((), state)
         })
     }
@@ -228,5 +398,6 @@ pub fn context(id: impl Into<ElementId>) -> ContextMenu {
         style: StyleRefinement::default(),
         element: None,
         menu: Some(div()),
+        button: MouseButton::Right,
     }
 }
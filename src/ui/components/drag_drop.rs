@@ -7,6 +7,7 @@ use gpui::{
 };
 
 use super::scrollbar::ScrollableHandle;
+use crate::ui::util::ui_px;
 
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub enum DropPosition {
@@ -162,6 +163,11 @@ impl DragDropListManager {
             container_bounds: None,
         })
     }
+
+    /// Updates the height of the list's items, for lists whose rows follow the interface scale.
+    pub fn set_item_height(&mut self, item_height: Pixels) {
+        self.config.item_height = item_height;
+    }
 }
 
 /// Visual state for a single item in a drag-drop list.
@@ -292,7 +298,7 @@ impl Render for DragPreview {
                     .shadow_md()
                     .child(
                         div()
-                            .text_size(px(14.0))
+                            .text_size(ui_px(14.0, cx))
                             .font_weight(FontWeight::SEMIBOLD)
                             .text_color(theme.text)
                             .child(self.label.clone()),
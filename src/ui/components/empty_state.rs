@@ -0,0 +1,116 @@
+use gpui::{
+    App, ClickEvent, ElementId, FontWeight, IntoElement, ParentElement, RenderOnce, SharedString,
+    Styled, Window, div, prelude::FluentBuilder, px,
+};
+
+use crate::ui::{
+    components::{
+        button::{ButtonIntent, button},
+        icons::icon,
+    },
+    theme::Theme,
+};
+
+type ClickEvHandler = Box<dyn Fn(&ClickEvent, &mut Window, &mut App)>;
+
+struct EmptyStateAction {
+    label: SharedString,
+    on_click: ClickEvHandler,
+}
+
+/// A placeholder shown in place of a list that has nothing in it. Fills its parent and centers
+/// its contents, so it can be dropped in wherever the list itself would be rendered.
+#[derive(IntoElement)]
+pub struct EmptyState {
+    id: ElementId,
+    icon: Option<SharedString>,
+    title: SharedString,
+    description: Option<SharedString>,
+    action: Option<EmptyStateAction>,
+}
+
+impl EmptyState {
+    pub fn icon(mut self, icon: impl Into<SharedString>) -> Self {
+        self.icon = Some(icon.into());
+        self
+    }
+
+    pub fn description(mut self, description: impl Into<SharedString>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    /// Adds a button below the description. The handler should do what the label says - this is
+    /// the user's way out of the empty state.
+    pub fn action(
+        mut self,
+        label: impl Into<SharedString>,
+        on_click: impl Fn(&ClickEvent, &mut Window, &mut App) + 'static,
+    ) -> Self {
+        self.action = Some(EmptyStateAction {
+            label: label.into(),
+            on_click: Box::new(on_click),
+        });
+        self
+    }
+}
+
+impl RenderOnce for EmptyState {
+    fn render(self, _: &mut Window, cx: &mut App) -> impl IntoElement {
+        let theme = cx.global::<Theme>();
+
+        div()
+            .size_full()
+            .flex_1()
+            .flex()
+            .flex_col()
+            .items_center()
+            .justify_center()
+            .p(px(24.0))
+            .gap(px(6.0))
+            .when_some(self.icon, |this, name| {
+                this.child(
+                    icon(name)
+                        .size(px(36.0))
+                        .mb(px(6.0))
+                        .text_color(theme.text_secondary),
+                )
+            })
+            .child(
+                div()
+                    .font_weight(FontWeight::SEMIBOLD)
+                    .text_center()
+                    .child(self.title),
+            )
+            .when_some(self.description, |this, description| {
+                this.child(
+                    div()
+                        .max_w(px(320.0))
+                        .text_sm()
+                        .text_center()
+                        .text_color(theme.text_secondary)
+                        .child(description),
+                )
+            })
+            .when_some(self.action, |this, action| {
+                this.child(
+                    button()
+                        .id(self.id)
+                        .mt(px(8.0))
+                        .intent(ButtonIntent::Primary)
+                        .child(action.label)
+                        .on_click(action.on_click),
+                )
+            })
+    }
+}
+
+pub fn empty_state(id: impl Into<ElementId>, title: impl Into<SharedString>) -> EmptyState {
+    EmptyState {
+        id: id.into(),
+        icon: None,
+        title: title.into(),
+        description: None,
+        action: None,
+    }
+}
@@ -7,9 +7,11 @@ pub const LAST_FM: &str = "!bundled:icons/brand-lastfm.svg";
 pub const CIRCLE_PLUS: &str = "!bundled:icons/circle-plus.svg";
 pub const FOLDER_CHECK: &str = "!bundled:icons/folder-check.svg";
 pub const FOLDER_SEARCH: &str = "!bundled:icons/folder-search.svg";
+pub const HEADPHONES: &str = "!bundled:icons/headphones.svg";
 pub const MAXIMIZE: &str = "!bundled:icons/maximize.svg";
 pub const MINIMIZE: &str = "!bundled:icons/minimize.svg";
 pub const MINUS: &str = "!bundled:icons/minus.svg";
+pub const PENCIL: &str = "!bundled:icons/pencil.svg";
 pub const PAUSE: &str = "!bundled:icons/player-pause.svg";
 pub const PLAY: &str = "!bundled:icons/player-play.svg";
 pub const NEXT_TRACK: &str = "!bundled:icons/player-track-next.svg";
@@ -36,3 +38,4 @@ pub const SIDEBAR_INACTIVE: &str = "!bundled:icons/layout-sidebar-inactive.svg";
 pub const SEARCH: &str = "!bundled:icons/search.svg";
 pub const CHECK: &str = "!bundled:icons/check.svg";
 pub const LOCK: &str = "!bundled:icons/lock.svg";
+pub const DOTS: &str = "!bundled:icons/dots.svg";
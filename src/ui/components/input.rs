@@ -5,13 +5,16 @@ use std::ops::Range;
 use gpui::{
     App, Bounds, ClipboardItem, Context, CursorStyle, ElementId, ElementInputHandler, Entity,
     EntityInputHandler, EventEmitter, FocusHandle, Focusable, GlobalElementId, KeyBinding,
-    LayoutId, MouseButton, MouseDownEvent, MouseMoveEvent, MouseUpEvent, PaintQuad, Pixels, Point,
-    ScrollHandle, ShapedLine, SharedString, Style, TextRun, UTF16Selection, UnderlineStyle, Window,
-    actions, div, fill, hsla, point, prelude::*, px, relative, rgba, size,
+    LayoutId, Modifiers, MouseButton, MouseDownEvent, MouseMoveEvent, MouseUpEvent, PaintQuad,
+    Pixels, Point, ScrollHandle, ShapedLine, SharedString, Style, TextRun, UTF16Selection,
+    UnderlineStyle, Window, actions, div, fill, hsla, point, prelude::*, px, relative, rgba, size,
 };
 use unicode_segmentation::*;
 
-use crate::ui::{global_actions::PlayPause, theme::Theme};
+use crate::ui::{
+    global_actions::{PlayPause, ShowShortcuts},
+    theme::Theme,
+};
 
 actions!(
     text_input,
@@ -39,7 +42,8 @@ actions!(
 pub enum EnrichedInputAction {
     Next,
     Previous,
-    Accept,
+    /// Enter was pressed, along with the modifiers that were held.
+    Accept(Modifiers),
 }
 
 pub fn bind_actions(cx: &mut App) {
@@ -53,12 +57,15 @@ pub fn bind_actions(cx: &mut App) {
         KeyBinding::new("home", Home, None),
         KeyBinding::new("end", End, None),
         KeyBinding::new("enter", Accept, None),
+        KeyBinding::new("ctrl-enter", Accept, None),
+        KeyBinding::new("shift-enter", Accept, None),
         KeyBinding::new("down", Next, None),
         KeyBinding::new("up", Previous, None),
     ]);
 
     if cfg!(target_os = "macos") {
         cx.bind_keys([
+            KeyBinding::new("cmd-enter", Accept, None),
             KeyBinding::new("cmd-a", SelectAll, None),
             KeyBinding::new("cmd-v", Paste, None),
             KeyBinding::new("cmd-c", Copy, None),
@@ -300,6 +307,12 @@ impl TextInput {
             .unwrap_or(self.content.len())
     }
 
+    /// Selects all of the text, so that typing replaces it.
+    pub fn select_all_content(&mut self, cx: &mut Context<Self>) {
+        self.move_to(0, cx);
+        self.select_to(self.content.len(), cx);
+    }
+
     pub fn reset(&mut self) {
         self.content = "".into();
         self.selected_range = 0..0;
@@ -314,6 +327,10 @@ impl TextInput {
         self.replace_text_in_range(None, " ", window, cx)
     }
 
+    fn question_mark(&mut self, _: &ShowShortcuts, window: &mut Window, cx: &mut Context<Self>) {
+        self.replace_text_in_range(None, "?", window, cx)
+    }
+
     pub fn next(&mut self, _: &Next, window: &mut Window, cx: &mut Context<Self>) {
         let Some(handler) = self.enriched_input_handler.as_mut() else {
             return;
@@ -332,7 +349,7 @@ impl TextInput {
         let Some(handler) = self.enriched_input_handler.as_mut() else {
             return;
         };
-        handler(EnrichedInputAction::Accept, window, cx);
+        handler(EnrichedInputAction::Accept(window.modifiers()), window, cx);
     }
 }
 
@@ -696,6 +713,7 @@ impl Render for TextInput {
             .on_action(cx.listener(Self::cut))
             .on_action(cx.listener(Self::copy))
             .on_action(cx.listener(Self::space))
+            .on_action(cx.listener(Self::question_mark))
             .on_action(cx.listener(Self::next))
             .on_action(cx.listener(Self::previous))
             .on_action(cx.listener(Self::accept))
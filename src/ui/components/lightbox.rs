@@ -0,0 +1,194 @@
+use std::{rc::Rc, sync::Arc};
+
+use futures::future::LocalBoxFuture;
+use gpui::*;
+use tracing::error;
+
+use crate::ui::{components::modal::CloseModal, data::decode_image, theme::Theme};
+
+/// Loads the encoded image shown by a [Lightbox]. Called every time the lightbox is opened.
+pub type LightboxSource = Rc<dyn Fn(&App) -> LocalBoxFuture<'static, anyhow::Result<Box<[u8]>>>>;
+
+/// How much of the window the image can take up, leaving room around it for the caption.
+const MAX_WIDTH_FRACTION: f32 = 0.9;
+const MAX_HEIGHT_FRACTION: f32 = 0.8;
+
+struct LoadedImage {
+    image: Arc<RenderImage>,
+    /// The size of the encoded image, in bytes.
+    byte_len: usize,
+}
+
+/// Formats a size in bytes the way file managers do (1 KB = 1000 bytes).
+fn format_size(bytes: usize) -> String {
+    match bytes {
+        0..1_000 => format!("{bytes} bytes"),
+        1_000..1_000_000 => format!("{:.0} KB", bytes as f64 / 1_000.0),
+        _ => format!("{:.1} MB", bytes as f64 / 1_000_000.0),
+    }
+}
+
+/// A full-window view of a single image, over a dimmed background, with the image's dimensions and
+/// size underneath. Clicking anywhere, scrolling or pressing escape closes it.
+///
+/// The image is only loaded and decoded while the lightbox is open, and is dropped as soon as it's
+/// closed, so that views showing a lightbox don't keep a full-size image around.
+pub struct Lightbox {
+    show: Entity<bool>,
+    source: LightboxSource,
+    image: Option<LoadedImage>,
+    failed: bool,
+    load_task: Option<Task<()>>,
+    focus_handle: FocusHandle,
+}
+
+impl Lightbox {
+    pub fn new(cx: &mut App, show: Entity<bool>, source: LightboxSource) -> Entity<Self> {
+        cx.new(|cx| {
+            cx.observe(&show, |this: &mut Self, show, cx| {
+                if *show.read(cx) {
+                    this.load(cx);
+                } else {
+                    this.unload(cx);
+                }
+
+                cx.notify();
+            })
+            .detach();
+
+            cx.on_release(|this: &mut Self, cx| {
+                if let Some(loaded) = this.image.take() {
+                    cx.drop_image(loaded.image, None);
+                }
+            })
+            .detach();
+
+            Self {
+                show,
+                source,
+                image: None,
+                failed: false,
+                load_task: None,
+                focus_handle: cx.focus_handle(),
+            }
+        })
+    }
+
+    fn load(&mut self, cx: &mut Context<Self>) {
+        let bytes = (self.source)(cx);
+        self.failed = false;
+
+        self.load_task = Some(cx.spawn(async move |this, cx| {
+            let result = async {
+                let bytes = bytes.await?;
+                let byte_len = bytes.len();
+                let image = crate::RUNTIME
+                    .spawn_blocking(move || decode_image(bytes, false))
+                    .await??;
+
+                anyhow::Ok(LoadedImage { image, byte_len })
+            }
+            .await;
+
+            this.update(cx, |this: &mut Self, cx| {
+                match result {
+                    Ok(loaded) => this.image = Some(loaded),
+                    Err(err) => {
+                        error!(?err, "Failed to load image: {err}");
+                        this.failed = true;
+                    }
+                }
+
+                cx.notify();
+            })
+            .ok();
+        }));
+    }
+
+    fn unload(&mut self, cx: &mut Context<Self>) {
+        self.load_task = None;
+
+        if let Some(loaded) = self.image.take() {
+            cx.drop_image(loaded.image, None);
+        }
+    }
+
+    fn close(&mut self, cx: &mut Context<Self>) {
+        self.show.write(cx, false);
+    }
+}
+
+impl Render for Lightbox {
+    fn render(&mut self, window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        if !*self.show.read(cx) {
+            return div().into_any_element();
+        }
+
+        // keeps keyboard input in the lightbox until it's closed
+        if !self.focus_handle.is_focused(window) {
+            self.focus_handle.focus(window, cx);
+        }
+
+        let theme = cx.global::<Theme>();
+        let viewport = window.viewport_size();
+        let max_width = f32::from(viewport.width) * MAX_WIDTH_FRACTION;
+        let max_height = f32::from(viewport.height) * MAX_HEIGHT_FRACTION;
+
+        let content = match &self.image {
+            Some(loaded) => {
+                let size = loaded.image.size(0);
+                let (width, height) = (size.width.0 as f32, size.height.0 as f32);
+                let scale = (max_width / width).min(max_height / height);
+
+                div()
+                    .flex()
+                    .flex_col()
+                    .items_center()
+                    .gap(px(10.0))
+                    .child(
+                        img(loaded.image.clone())
+                            .w(px(width * scale))
+                            .h(px(height * scale))
+                            .rounded(px(4.0))
+                            .shadow_lg(),
+                    )
+                    .child(
+                        div()
+                            .text_sm()
+                            .text_color(theme.text_secondary)
+                            .child(format!(
+                                "{} × {} · {}",
+                                size.width.0,
+                                size.height.0,
+                                format_size(loaded.byte_len)
+                            )),
+                    )
+            }
+            None if self.failed => div()
+                .text_sm()
+                .text_color(theme.text_secondary)
+                .child("Couldn't load the image."),
+            None => div(),
+        };
+
+        anchored()
+            .position(point(px(0.0), px(0.0)))
+            .child(deferred(
+                div()
+                    .id("lightbox")
+                    .track_focus(&self.focus_handle)
+                    .occlude()
+                    .w(viewport.width)
+                    .h(viewport.height)
+                    .flex()
+                    .items_center()
+                    .justify_center()
+                    .bg(theme.modal_overlay_bg)
+                    .on_any_mouse_down(cx.listener(|this, _, _, cx| this.close(cx)))
+                    .on_scroll_wheel(cx.listener(|this, _, _, cx| this.close(cx)))
+                    .on_action(cx.listener(|this, _: &CloseModal, _, cx| this.close(cx)))
+                    .child(content),
+            ))
+            .into_any_element()
+    }
+}
@@ -1,11 +1,19 @@
+use std::rc::Rc;
+
 use gpui::{prelude::FluentBuilder, *};
 
 use crate::ui::{
-    components::icons::{CHECK, LOCK, icon},
+    components::{
+        context::rendering_menu,
+        icons::{CHECK, LOCK, icon},
+    },
     theme::Theme,
 };
 
-type ClickEvHandler = Box<dyn Fn(&ClickEvent, &mut Window, &mut App)>;
+/// What a menu item does when it's chosen, with the mouse or the keyboard.
+pub type MenuAction = dyn Fn(&ClickEvent, &mut Window, &mut App);
+
+type ClickEvHandler = Rc<MenuAction>;
 
 #[derive(IntoElement)]
 pub struct MenuItem {
@@ -14,6 +22,8 @@ pub struct MenuItem {
     name: SharedString,
     on_click: ClickEvHandler,
     disabled: bool,
+    // highlighted with the keyboard, see [Menu]
+    highlighted: bool,
 }
 
 impl MenuItem {
@@ -27,8 +37,9 @@ impl MenuItem {
             id: id.into(),
             icon_path: icon.map(|v| v.into()),
             name: text.into(),
-            on_click: Box::new(func),
+            on_click: Rc::new(func),
             disabled: false,
+            highlighted: false,
         }
     }
 
@@ -51,7 +62,11 @@ impl RenderOnce for MenuItem {
             .pb(px(6.0))
             .line_height(rems(1.25))
             .min_w_full()
-            .bg(theme.menu_item)
+            .bg(if self.highlighted {
+                theme.menu_item_hover
+            } else {
+                theme.menu_item
+            })
             .text_sm()
             .font_weight(FontWeight::MEDIUM)
             .child(
@@ -81,7 +96,8 @@ impl RenderOnce for MenuItem {
         if self.disabled {
             base.cursor_default()
         } else {
-            base.on_click(self.on_click)
+            let on_click = self.on_click;
+            base.on_click(move |ev, window, cx| on_click(ev, window, cx))
                 .hover(|this| this.bg(theme.menu_item_hover))
                 .active(|this| this.bg(theme.menu_item_active))
         }
@@ -95,6 +111,8 @@ pub struct CheckMenuItem {
     name: SharedString,
     on_click: ClickEvHandler,
     disabled: bool,
+    // highlighted with the keyboard, see [Menu]
+    highlighted: bool,
 }
 
 impl CheckMenuItem {
@@ -108,8 +126,9 @@ impl CheckMenuItem {
             id: id.into(),
             checked,
             name: text.into(),
-            on_click: Box::new(func),
+            on_click: Rc::new(func),
             disabled: false,
+            highlighted: false,
         }
     }
 
@@ -140,7 +159,11 @@ impl RenderOnce for CheckMenuItem {
             .pb(px(6.0))
             .line_height(rems(1.25))
             .min_w_full()
-            .bg(theme.menu_item)
+            .bg(if self.highlighted {
+                theme.menu_item_hover
+            } else {
+                theme.menu_item
+            })
             .text_sm()
             .font_weight(FontWeight::MEDIUM)
             .child(
@@ -170,7 +193,8 @@ impl RenderOnce for CheckMenuItem {
         if self.disabled {
             base.cursor_default()
         } else {
-            base.on_click(self.on_click)
+            let on_click = self.on_click;
+            base.on_click(move |ev, window, cx| on_click(ev, window, cx))
                 .hover(|this| this.bg(theme.menu_item_hover))
                 .active(|this| this.bg(theme.menu_item_active))
         }
@@ -220,24 +244,83 @@ pub fn menu_separator() -> MenuSeparator {
     MenuSeparator
 }
 
+/// An entry in a [Menu]: an item, along with what choosing it with the keyboard does. Entries
+/// without an action (separators and disabled items) are skipped when moving through the menu with
+/// the arrow keys.
+pub struct MenuEntry {
+    render: Box<dyn FnOnce(bool) -> AnyElement>,
+    action: Option<Rc<MenuAction>>,
+}
+
+impl From<MenuItem> for MenuEntry {
+    fn from(item: MenuItem) -> Self {
+        MenuEntry {
+            action: (!item.disabled).then(|| item.on_click.clone()),
+            render: Box::new(move |highlighted| {
+                MenuItem {
+                    highlighted,
+                    ..item
+                }
+                .into_any_element()
+            }),
+        }
+    }
+}
+
+impl From<CheckMenuItem> for MenuEntry {
+    fn from(item: CheckMenuItem) -> Self {
+        MenuEntry {
+            action: (!item.disabled).then(|| item.on_click.clone()),
+            render: Box::new(move |highlighted| {
+                CheckMenuItem {
+                    highlighted,
+                    ..item
+                }
+                .into_any_element()
+            }),
+        }
+    }
+}
+
+impl From<MenuSeparator> for MenuEntry {
+    fn from(separator: MenuSeparator) -> Self {
+        MenuEntry {
+            action: None,
+            render: Box::new(move |_| separator.into_any_element()),
+        }
+    }
+}
+
 /// A container for menu items.
 #[derive(IntoElement)]
 pub struct Menu {
-    items: Vec<AnyElement>,
+    items: Vec<MenuEntry>,
     div: Div,
 }
 
 impl Menu {
     /// Adds an item to the menu.
-    pub fn item(mut self, item: impl IntoElement) -> Self {
-        self.items.push(item.into_any_element());
+    pub fn item(mut self, item: impl Into<MenuEntry>) -> Self {
+        self.items.push(item.into());
         self
     }
 }
 
 impl RenderOnce for Menu {
     fn render(self, _: &mut Window, _: &mut App) -> impl IntoElement {
-        self.div.flex().flex_col().children(self.items)
+        // when the menu is open in a context menu, the context menu needs to know what each item
+        // does to choose them with the keyboard, and says which one is highlighted
+        let highlighted = rendering_menu().and_then(|state| {
+            state.set_actions(self.items.iter().map(|item| item.action.clone()).collect());
+            state.highlighted()
+        });
+
+        self.div.flex().flex_col().children(
+            self.items
+                .into_iter()
+                .enumerate()
+                .map(|(idx, item)| (item.render)(highlighted == Some(idx))),
+        )
     }
 }
 
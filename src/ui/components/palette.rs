@@ -1,13 +1,13 @@
 mod finder;
 
-pub use finder::{ExtraItem, ExtraItemProvider, FinderItemLeft, PaletteItem};
+pub use finder::{AcceptIntent, ExtraItem, ExtraItemProvider, FinderItemLeft, PaletteItem};
 use tracing::trace;
 
 use std::sync::Arc;
 
 use gpui::{
-    App, AppContext, Context, Entity, EventEmitter, FocusHandle, IntoElement, ParentElement,
-    Render, Styled, Window, div, prelude::FluentBuilder, px,
+    App, AppContext, Context, Entity, EventEmitter, FocusHandle, FontWeight, IntoElement,
+    ParentElement, Render, SharedString, Styled, Window, div, prelude::FluentBuilder, px,
 };
 use nucleo::Utf32String;
 
@@ -15,26 +15,28 @@ use crate::ui::components::{
     input::{EnrichedInputAction, TextInput},
     palette::finder::Finder,
 };
-use crate::ui::theme::Theme;
+use crate::ui::{theme::Theme, util::ui_px};
 
 pub struct Palette<T, MatcherFunc, OnAccept>
 where
     T: Send + Sync + PartialEq + PaletteItem + 'static,
     MatcherFunc: Fn(&Arc<T>, &mut App) -> Utf32String + 'static,
-    OnAccept: Fn(&Arc<T>, &mut App) + 'static,
+    OnAccept: Fn(&Arc<T>, AcceptIntent, &mut App) + 'static,
 {
     input: Entity<TextInput>,
     handle: FocusHandle,
     finder: Option<Entity<Finder<T, MatcherFunc, OnAccept>>>,
     items: Vec<Arc<T>>,
     extra_providers: Vec<ExtraItemProvider>,
+    /// Shortcuts shown below the results, as (keys, description) pairs.
+    hints: Vec<(SharedString, SharedString)>,
 }
 
 impl<T, MatcherFunc, OnAccept> Palette<T, MatcherFunc, OnAccept>
 where
     T: Send + Sync + PartialEq + PaletteItem + 'static,
     MatcherFunc: Fn(&Arc<T>, &mut App) -> Utf32String + 'static,
-    OnAccept: Fn(&Arc<T>, &mut App) + 'static,
+    OnAccept: Fn(&Arc<T>, AcceptIntent, &mut App) + 'static,
 {
     pub fn new(
         cx: &mut App,
@@ -120,6 +122,7 @@ where
                 finder: None,
                 items,
                 extra_providers: Vec::new(),
+                hints: Vec::new(),
             }
         })
     }
@@ -142,6 +145,12 @@ where
         }
     }
 
+    /// Sets the shortcuts shown in a footer below the results. No footer is shown if there aren't
+    /// any.
+    pub fn set_hints(&mut self, hints: Vec<(SharedString, SharedString)>) {
+        self.hints = hints;
+    }
+
     pub fn register_extra_provider(&mut self, provider: ExtraItemProvider, cx: &mut Context<Self>) {
         let provider_clone = provider.clone();
 
@@ -160,7 +169,7 @@ impl<T, MatcherFunc, OnAccept> Render for Palette<T, MatcherFunc, OnAccept>
 where
     T: Send + Sync + PartialEq + PaletteItem + 'static,
     MatcherFunc: Fn(&Arc<T>, &mut App) -> Utf32String + 'static,
-    OnAccept: Fn(&Arc<T>, &mut App) + 'static,
+    OnAccept: Fn(&Arc<T>, AcceptIntent, &mut App) + 'static,
 {
     fn render(&mut self, _: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
         let theme = cx.global::<Theme>();
@@ -168,14 +177,15 @@ where
         div()
             .w_full()
             .h_full()
+            .relative()
             .overflow_hidden()
             .flex_col()
             .child(
                 div()
                     .w_full()
                     .p(px(12.0))
-                    .line_height(px(14.0))
-                    .h(px(40.0))
+                    .line_height(ui_px(14.0, cx))
+                    .h(ui_px(40.0, cx))
                     .text_sm()
                     .border_b(px(1.0))
                     .border_color(theme.border_color)
@@ -188,9 +198,43 @@ where
                     .h_full()
                     // FIXME: weird layout issue, this is a hack
                     // eventually this should be removed
-                    .pb(px(40.0))
+                    .when(self.hints.is_empty(), |this| this.pb(px(40.0)))
+                    .when(!self.hints.is_empty(), |this| {
+                        this.pb(px(40.0) + ui_px(28.0, cx))
+                    })
                     .when_some(self.finder.clone(), |this, finder| this.child(finder)),
             )
+            .when(!self.hints.is_empty(), |this| {
+                this.child(
+                    div()
+                        .absolute()
+                        .bottom_0()
+                        .left_0()
+                        .right_0()
+                        .h(ui_px(28.0, cx))
+                        .px(px(12.0))
+                        .flex()
+                        .items_center()
+                        .gap(px(14.0))
+                        .border_t(px(1.0))
+                        .border_color(theme.border_color)
+                        .bg(theme.background_secondary)
+                        .text_xs()
+                        .text_color(theme.text_secondary)
+                        .children(self.hints.iter().map(|(keys, description)| {
+                            div()
+                                .flex()
+                                .gap(px(4.0))
+                                .child(
+                                    div()
+                                        .font_weight(FontWeight::SEMIBOLD)
+                                        .text_color(theme.text)
+                                        .child(keys.clone()),
+                                )
+                                .child(description.clone())
+                        })),
+                )
+            })
     }
 }
 
@@ -198,7 +242,7 @@ impl<T, MatcherFunc, OnAccept> EventEmitter<Vec<Arc<T>>> for Palette<T, MatcherF
 where
     T: Send + Sync + PartialEq + PaletteItem + 'static,
     MatcherFunc: Fn(&Arc<T>, &mut App) -> Utf32String + 'static,
-    OnAccept: Fn(&Arc<T>, &mut App) + 'static,
+    OnAccept: Fn(&Arc<T>, AcceptIntent, &mut App) + 'static,
 {
 }
 
@@ -207,6 +251,6 @@ impl<T, MatcherFunc, OnAccept> EventEmitter<EnrichedInputAction>
 where
     T: Send + Sync + PartialEq + PaletteItem + 'static,
     MatcherFunc: Fn(&Arc<T>, &mut App) -> Utf32String + 'static,
-    OnAccept: Fn(&Arc<T>, &mut App) + 'static,
+    OnAccept: Fn(&Arc<T>, AcceptIntent, &mut App) + 'static,
 {
 }
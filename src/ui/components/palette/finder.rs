@@ -2,7 +2,7 @@ use std::{marker::PhantomData, sync::Arc, time::Duration};
 
 use gpui::{
     App, AppContext, Context, ElementId, Entity, EventEmitter, FontWeight, InteractiveElement,
-    IntoElement, ListAlignment, ListState, ParentElement, Render, SharedString,
+    IntoElement, ListAlignment, ListState, Modifiers, ParentElement, Render, SharedString,
     StatefulInteractiveElement, Styled, WeakEntity, Window, div, img, list, prelude::FluentBuilder,
     px,
 };
@@ -14,12 +14,66 @@ use rustc_hash::FxHashMap;
 use tokio::sync::mpsc::channel;
 use tracing::{debug, trace};
 
-use crate::ui::{components::input::EnrichedInputAction, theme::Theme};
+use crate::ui::{
+    caching::{HummingbirdImageCache, visible_capacity},
+    components::{empty_state::empty_state, icons::SEARCH, input::EnrichedInputAction},
+    theme::Theme,
+};
+
+/// Approximate height of a single finder item, in pixels, before the interface scale is applied.
+const FINDER_ITEM_HEIGHT: f32 = 32.0;
+
+/// The longest text (in characters) an item can be matched on. Anything past this is left out, so
+/// that an item with a huge tag can't slow down matching for every query.
+const MAX_SEARCH_TEXT_LENGTH: usize = 256;
+
+/// Cuts the text an item is matched on down to [`MAX_SEARCH_TEXT_LENGTH`] characters.
+fn bound_search_text(text: Utf32String) -> Utf32String {
+    if text.len() <= MAX_SEARCH_TEXT_LENGTH {
+        return text;
+    }
+
+    Utf32String::from(
+        text.to_string()
+            .chars()
+            .take(MAX_SEARCH_TEXT_LENGTH)
+            .collect::<String>(),
+    )
+}
+
+/// What the user asked for when accepting an item, based on the modifiers they held.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AcceptIntent {
+    /// Enter or click: the item's usual action, like opening it.
+    Open,
+    /// Ctrl+Enter (or Cmd+Enter on macOS): add the item to the end of the queue.
+    Queue,
+    /// Shift+Enter: replace the queue with the item and start playing it.
+    Play,
+}
+
+impl AcceptIntent {
+    pub fn from_modifiers(modifiers: &Modifiers) -> Self {
+        if modifiers.shift {
+            AcceptIntent::Play
+        } else if modifiers.control || modifiers.platform {
+            AcceptIntent::Queue
+        } else {
+            AcceptIntent::Open
+        }
+    }
+}
 
 pub trait PaletteItem {
     fn left_content(&self, cx: &mut App) -> Option<FinderItemLeft>;
     fn middle_content(&self, cx: &mut App) -> SharedString;
     fn right_content(&self, cx: &mut App) -> Option<SharedString>;
+
+    /// Whether the item matches the query on its own name, rather than on extra text it's also
+    /// searchable by. Primary matches are listed first.
+    fn is_primary_match(&self, _query: &str) -> bool {
+        true
+    }
 }
 
 #[derive(Clone)]
@@ -37,14 +91,14 @@ type ViewsModel<T, MatcherFunc, OnAccept>
 where
     T: Send + Sync + PartialEq + PaletteItem + 'static,
     MatcherFunc: Fn(&Arc<T>, &mut App) -> Utf32String + 'static,
-    OnAccept: Fn(&Arc<T>, &mut App) + 'static,
+    OnAccept: Fn(&Arc<T>, AcceptIntent, &mut App) + 'static,
 = Entity<FxHashMap<usize, Entity<FinderItem<T, MatcherFunc, OnAccept>>>>;
 
 pub struct Finder<T, MatcherFunc, OnAccept>
 where
     T: Send + Sync + PartialEq + PaletteItem + 'static,
     MatcherFunc: Fn(&Arc<T>, &mut App) -> Utf32String + 'static,
-    OnAccept: Fn(&Arc<T>, &mut App) + 'static,
+    OnAccept: Fn(&Arc<T>, AcceptIntent, &mut App) + 'static,
 {
     query: String,
     matcher: Nucleo<Arc<T>>,
@@ -56,6 +110,7 @@ where
     list_state: ListState,
     current_selection: Entity<usize>,
     on_accept: Arc<OnAccept>,
+    image_cache: Entity<HummingbirdImageCache>,
     phantom: PhantomData<MatcherFunc>,
 }
 
@@ -63,7 +118,7 @@ impl<T, MatcherFunc, OnAccept> Finder<T, MatcherFunc, OnAccept>
 where
     T: Send + Sync + PartialEq + PaletteItem + 'static,
     MatcherFunc: Fn(&Arc<T>, &mut App) -> Utf32String + 'static,
-    OnAccept: Fn(&Arc<T>, &mut App) + 'static,
+    OnAccept: Fn(&Arc<T>, AcceptIntent, &mut App) + 'static,
 {
     pub fn new(
         cx: &mut App,
@@ -89,7 +144,7 @@ where
 
             for item in &items {
                 let item_clone = item.clone();
-                let search_text = (get_item_display)(&item_clone, cx);
+                let search_text = bound_search_text((get_item_display)(&item_clone, cx));
                 trace!("Injecting item with search text: '{search_text}'");
                 injector.push(item_clone, move |_v, dest| {
                     dest[0] = search_text.clone();
@@ -164,16 +219,17 @@ where
                         let idx = *this.current_selection.read(cx);
                         this.list_state.scroll_to_reveal_item(idx);
                     }
-                    EnrichedInputAction::Accept => {
+                    EnrichedInputAction::Accept(modifiers) => {
                         let idx = *this.current_selection.read(cx);
                         if idx < this.extra_items.len() {
+                            // extra items are actions, which don't have anything to queue
                             if let Some(extra) = this.extra_items.get(idx) {
                                 (extra.on_accept)(cx);
                             }
                         } else {
                             let match_idx = idx.saturating_sub(this.extra_items.len());
                             if let Some(item) = this.last_match.get(match_idx) {
-                                on_accept_clone(item, cx);
+                                on_accept_clone(item, AcceptIntent::from_modifiers(modifiers), cx);
                             }
                         }
                     }
@@ -189,7 +245,8 @@ where
 
                 for item in items {
                     let item_clone = item.clone();
-                    let search_text = (get_item_display_for_updates)(&item_clone, cx);
+                    let search_text =
+                        bound_search_text((get_item_display_for_updates)(&item_clone, cx));
                     injector.push(item_clone, move |_v, dest| {
                         dest[0] = search_text.clone();
                     });
@@ -212,6 +269,7 @@ where
                 current_selection,
                 list_state: Self::make_list_state(None),
                 on_accept,
+                image_cache: HummingbirdImageCache::new("finder", 0, cx),
                 phantom: PhantomData,
             }
         })
@@ -272,10 +330,12 @@ where
         let count = snapshot.matched_item_count();
         let limit = 100.min(count);
 
-        snapshot
+        let (primary, secondary): (Vec<_>, Vec<_>) = snapshot
             .matched_items(..limit)
             .map(|item| item.data.clone())
-            .collect()
+            .partition(|item| item.is_primary_match(&self.query));
+
+        primary.into_iter().chain(secondary).collect()
     }
 
     pub fn regenerate_list_state(&mut self, cx: &mut Context<Self>) {
@@ -302,7 +362,7 @@ impl<T, MatcherFunc, OnAccept> EventEmitter<String> for Finder<T, MatcherFunc, O
 where
     T: Send + Sync + PartialEq + PaletteItem + 'static,
     MatcherFunc: Fn(&Arc<T>, &mut App) -> Utf32String + 'static,
-    OnAccept: Fn(&Arc<T>, &mut App) + 'static,
+    OnAccept: Fn(&Arc<T>, AcceptIntent, &mut App) + 'static,
 {
 }
 
@@ -310,7 +370,7 @@ impl<T, MatcherFunc, OnAccept> EventEmitter<Vec<Arc<T>>> for Finder<T, MatcherFu
 where
     T: Send + Sync + PartialEq + PaletteItem + 'static,
     MatcherFunc: Fn(&Arc<T>, &mut App) -> Utf32String + 'static,
-    OnAccept: Fn(&Arc<T>, &mut App) + 'static,
+    OnAccept: Fn(&Arc<T>, AcceptIntent, &mut App) + 'static,
 {
 }
 
@@ -319,7 +379,7 @@ impl<T, MatcherFunc, OnAccept> EventEmitter<EnrichedInputAction>
 where
     T: Send + Sync + PartialEq + PaletteItem + 'static,
     MatcherFunc: Fn(&Arc<T>, &mut App) -> Utf32String + 'static,
-    OnAccept: Fn(&Arc<T>, &mut App) + 'static,
+    OnAccept: Fn(&Arc<T>, AcceptIntent, &mut App) + 'static,
 {
 }
 
@@ -327,11 +387,15 @@ impl<T, MatcherFunc, OnAccept> Render for Finder<T, MatcherFunc, OnAccept>
 where
     T: Send + Sync + PartialEq + PaletteItem + 'static,
     MatcherFunc: Fn(&Arc<T>, &mut App) -> Utf32String + 'static,
-    OnAccept: Fn(&Arc<T>, &mut App) + 'static,
+    OnAccept: Fn(&Arc<T>, AcceptIntent, &mut App) + 'static,
 {
-    fn render(&mut self, _: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
-        use crate::ui::caching::hummingbird_cache;
-        use crate::ui::util::{create_or_retrieve_view, prune_views};
+    fn render(&mut self, window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        use crate::ui::util::{create_or_retrieve_view, prune_views, ui_px};
+
+        let capacity = visible_capacity(window, ui_px(FINDER_ITEM_HEIGHT, cx), 1);
+        self.image_cache.update(cx, |cache, cx| {
+            cache.set_max_items(capacity, window, cx);
+        });
 
         let last_match = self.last_match.clone();
         let extra_items = self.extra_items.clone();
@@ -339,13 +403,16 @@ where
         let render_counter = self.render_counter.clone();
         let current_selection = self.current_selection.clone();
         let weak_finder = cx.weak_entity();
+        let no_results =
+            last_match.is_empty() && extra_items.is_empty() && !self.query.trim().is_empty();
 
         div()
             .w_full()
             .h_full()
-            .image_cache(hummingbird_cache("finder-cache", 50))
+            .image_cache(self.image_cache.clone())
             .id("finder")
             .flex()
+            .relative()
             .p(px(4.0))
             .child(
                 list(self.list_state.clone(), move |idx, _, cx| {
@@ -419,6 +486,15 @@ where
                 .w_full()
                 .h_full(),
             )
+            .when(no_results, |this| {
+                this.child(
+                    div().absolute().inset_0().child(
+                        empty_state("finder-empty", "No results")
+                            .icon(SEARCH)
+                            .description(format!("Nothing matches \"{}\".", self.query.trim())),
+                    ),
+                )
+            })
     }
 }
 
@@ -428,7 +504,7 @@ pub struct FinderItem<T, MatcherFunc, OnAccept>
 where
     T: Send + Sync + PartialEq + PaletteItem + 'static,
     MatcherFunc: Fn(&Arc<T>, &mut App) -> Utf32String + 'static,
-    OnAccept: Fn(&Arc<T>, &mut App) + 'static,
+    OnAccept: Fn(&Arc<T>, AcceptIntent, &mut App) + 'static,
 {
     id: ElementId,
     left: Option<FinderItemLeft>,
@@ -452,7 +528,7 @@ impl<T, MatcherFunc, OnAccept> FinderItem<T, MatcherFunc, OnAccept>
 where
     T: Send + Sync + PartialEq + PaletteItem + 'static,
     MatcherFunc: Fn(&Arc<T>, &mut App) -> Utf32String + 'static,
-    OnAccept: Fn(&Arc<T>, &mut App) + 'static,
+    OnAccept: Fn(&Arc<T>, AcceptIntent, &mut App) + 'static,
 {
     pub fn new(
         cx: &mut App,
@@ -528,7 +604,7 @@ impl<T, MatcherFunc, OnAccept> Render for FinderItem<T, MatcherFunc, OnAccept>
 where
     T: Send + Sync + PartialEq + PaletteItem + 'static,
     MatcherFunc: Fn(&Arc<T>, &mut App) -> Utf32String + 'static,
-    OnAccept: Fn(&Arc<T>, &mut App) + 'static,
+    OnAccept: Fn(&Arc<T>, AcceptIntent, &mut App) + 'static,
 {
     fn render(&mut self, _: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
         let theme = cx.global::<Theme>();
@@ -551,14 +627,15 @@ where
                 this.bg(theme.palette_item_hover)
             })
             .rounded(px(4.0))
-            .on_click(cx.listener(move |_, _, _, cx| {
+            .on_click(cx.listener(move |_, _, window, cx| {
                 if let Some(override_fn) = on_accept_override.clone() {
                     override_fn(cx);
                 } else if let Some(parent) = weak_parent.upgrade()
                     && let Some(item) = item_data.clone()
                 {
+                    let intent = AcceptIntent::from_modifiers(&window.modifiers());
                     parent.update(cx, |finder, cx| {
-                        (finder.on_accept)(&item, cx);
+                        (finder.on_accept)(&item, intent, cx);
                     });
                 }
             }))
@@ -611,3 +688,70 @@ where
             })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepting_without_modifiers_opens_the_item() {
+        assert_eq!(
+            AcceptIntent::from_modifiers(&Modifiers::default()),
+            AcceptIntent::Open
+        );
+        let alt = Modifiers {
+            alt: true,
+            ..Default::default()
+        };
+        assert_eq!(AcceptIntent::from_modifiers(&alt), AcceptIntent::Open);
+    }
+
+    #[test]
+    fn control_or_command_queues_the_item() {
+        let control = Modifiers {
+            control: true,
+            ..Default::default()
+        };
+        let command = Modifiers {
+            platform: true,
+            ..Default::default()
+        };
+
+        assert_eq!(AcceptIntent::from_modifiers(&control), AcceptIntent::Queue);
+        assert_eq!(AcceptIntent::from_modifiers(&command), AcceptIntent::Queue);
+    }
+
+    #[test]
+    fn shift_plays_the_item_even_with_control() {
+        let shift = Modifiers {
+            shift: true,
+            ..Default::default()
+        };
+        let control_shift = Modifiers {
+            control: true,
+            shift: true,
+            ..Default::default()
+        };
+
+        assert_eq!(AcceptIntent::from_modifiers(&shift), AcceptIntent::Play);
+        assert_eq!(
+            AcceptIntent::from_modifiers(&control_shift),
+            AcceptIntent::Play
+        );
+    }
+
+    #[test]
+    fn keeps_short_search_text() {
+        let text = bound_search_text(Utf32String::from("Artist - Album"));
+
+        assert_eq!(text.to_string(), "Artist - Album");
+    }
+
+    #[test]
+    fn cuts_long_search_text_short() {
+        let text = bound_search_text(Utf32String::from("é".repeat(MAX_SEARCH_TEXT_LENGTH * 2)));
+
+        assert_eq!(text.len(), MAX_SEARCH_TEXT_LENGTH);
+        assert_eq!(text.to_string(), "é".repeat(MAX_SEARCH_TEXT_LENGTH));
+    }
+}
@@ -6,7 +6,11 @@ use gpui::{
 
 use crate::{
     settings::storage::DEFAULT_SIDEBAR_WIDTH,
-    ui::{components::icons::icon, theme::Theme, util::MaybeStateful},
+    ui::{
+        components::icons::icon,
+        theme::Theme,
+        util::{MaybeStateful, ui_px},
+    },
 };
 
 #[derive(IntoElement)]
@@ -111,7 +115,7 @@ impl RenderOnce for SidebarItem {
             .rounded(px(4.0))
             .px(px(9.0))
             .py(px(7.0))
-            .line_height(px(18.0))
+            .line_height(ui_px(18.0, cx))
             .gap(px(6.0))
             .font_weight(FontWeight::SEMIBOLD)
             .hover(|this| this.bg(theme.nav_button_hover))
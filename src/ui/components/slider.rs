@@ -5,12 +5,15 @@ use gpui::*;
 use crate::ui::theme::Theme;
 
 type ClickHandler = dyn FnMut(f32, &mut Window, &mut App);
+type ReleaseHandler = dyn FnMut(&mut Window, &mut App);
 
 pub struct Slider {
     pub(self) id: Option<ElementId>,
     pub(self) style: StyleRefinement,
     pub(self) value: f32,
     pub(self) on_change: Option<Rc<RefCell<ClickHandler>>>,
+    pub(self) on_release: Option<Rc<RefCell<ReleaseHandler>>>,
+    pub(self) on_double_click: Option<Rc<RefCell<ReleaseHandler>>>,
     pub(self) hitbox: Option<Hitbox>,
 }
 
@@ -29,6 +32,20 @@ impl Slider {
         self.on_change = Some(Rc::new(RefCell::new(func)));
         self
     }
+
+    /// Called when the mouse is released after dragging the slider. Only called if `on_change`
+    /// is also set.
+    pub fn on_release(mut self, func: impl FnMut(&mut Window, &mut App) + 'static) -> Self {
+        self.on_release = Some(Rc::new(RefCell::new(func)));
+        self
+    }
+
+    /// Called when the slider is double-clicked, instead of starting a drag. Only called if
+    /// `on_change` is also set.
+    pub fn on_double_click(mut self, func: impl FnMut(&mut Window, &mut App) + 'static) -> Self {
+        self.on_double_click = Some(Rc::new(RefCell::new(func)));
+        self
+    }
 }
 
 impl Styled for Slider {
@@ -130,6 +147,9 @@ impl Element for Slider {
         ));
 
         if let Some(func) = self.on_change.as_ref() {
+            let on_release = self.on_release.clone();
+            let on_double_click = self.on_double_click.clone();
+
             window.with_optional_element_state(
                 id,
                 move |v: Option<Option<Rc<RefCell<bool>>>>, cx| {
@@ -147,6 +167,13 @@ impl Element for Slider {
                         window.prevent_default();
                         cx.stop_propagation();
 
+                        if ev.click_count == 2
+                            && let Some(on_double_click) = on_double_click.as_ref()
+                        {
+                            (on_double_click.borrow_mut())(window, cx);
+                            return;
+                        }
+
                         let relative = ev.position - bounds.origin;
                         let relative_x: f32 = relative.x.into();
                         let width: f32 = bounds.size.width.into();
@@ -171,8 +198,12 @@ impl Element for Slider {
 
                     let mouse_in_3 = mouse_in.clone();
 
-                    cx.on_mouse_event(move |_: &MouseUpEvent, _, _, _| {
-                        (*mouse_in_3.borrow_mut()) = false;
+                    cx.on_mouse_event(move |_: &MouseUpEvent, _, window, cx| {
+                        if mouse_in_3.replace(false)
+                            && let Some(on_release) = on_release.as_ref()
+                        {
+                            (on_release.borrow_mut())(window, cx);
+                        }
                     });
 
                     ((), Some(mouse_in))
@@ -188,6 +219,8 @@ pub fn slider() -> Slider {
         style: StyleRefinement::default(),
         value: 0.0,
         on_change: None,
+        on_release: None,
+        on_double_click: None,
         hitbox: None,
     }
 }
@@ -1,36 +1,54 @@
 mod column_resize_handle;
+mod row_state;
+mod scroll_anchor;
+mod sections;
 pub mod table_data;
 mod table_item;
+mod type_ahead;
 
 use std::{rc::Rc, sync::Arc};
 
 use column_resize_handle::column_resize_handle;
 use gpui::{prelude::FluentBuilder, *};
 use indexmap::IndexMap;
+use row_state::RowState;
 use rustc_hash::{FxBuildHasher, FxHashMap};
+use scroll_anchor::translate_scroll;
+use sections::{TableLayout, TableRow, group_rows};
 use table_data::{
     Column, TABLE_HEADER_GROUP, TABLE_IMAGE_COLUMN_WIDTH, TABLE_MAX_WIDTH, TableData, TableSort,
 };
 use table_item::TableItem;
+use type_ahead::{TYPE_AHEAD_TIMEOUT, is_type_ahead_input};
 
 use crate::{
     settings::storage::TableSettings,
     ui::{
-        caching::hummingbird_cache,
+        caching::{HummingbirdImageCache, visible_capacity},
         components::{
             context::context,
             icons::{CHEVRON_DOWN, CHEVRON_UP, icon},
+            input::Accept,
             menu::{menu, menu_check_item},
             scrollbar::{RightPad, floating_scrollbar},
         },
+        global_actions::PlayPause,
         models::Models,
         theme::Theme,
-        util::{create_or_retrieve_view, prune_views},
+        util::{create_or_retrieve_view, prune_views, ui_px},
     },
 };
 
 type RowMap<T, C> = FxHashMap<usize, Entity<TableItem<T, C>>>;
 
+/// A table's items in the order they're sorted in, and the rows they're shown in.
+#[derive(Clone)]
+struct LoadedRows<I> {
+    items: Arc<Vec<I>>,
+    // the items along with the headings of their sections, which is what's actually shown
+    layout: Arc<TableLayout<I>>,
+}
+
 #[allow(type_alias_bounds)]
 pub type OnSelectHandler<T, C>
 where
@@ -49,12 +67,29 @@ where
     hidden_column_widths: Entity<FxHashMap<C, f32>>,
     views: Entity<RowMap<T, C>>,
     render_counter: Entity<usize>,
-    items: Option<Arc<Vec<T::Identifier>>>,
+    rows: RowState<LoadedRows<T::Identifier>>,
     sort_method: Entity<Option<TableSort<C>>>,
     on_select: Option<OnSelectHandler<T, C>>,
     scroll_handle: UniformListScrollHandle,
+    image_cache: Entity<HummingbirdImageCache>,
+    load_task: Option<Task<()>>,
+    // the scroll offset to restore once the first rows have loaded
+    pending_scroll_offset: Option<f32>,
+    focus_handle: FocusHandle,
+    first_render: bool,
+    // what's been typed to jump to a row, cleared shortly after typing stops
+    type_ahead_query: String,
+    type_ahead_task: Option<Task<()>>,
+    // the row found by type-ahead, which is opened by pressing enter; headings are never found
+    highlighted: Option<usize>,
+    // shown between the table's name and its header, like the album list's filters
+    toolbar: Option<AnyView>,
 }
 
+/// Height of a single table row (and the header), in pixels, before the interface scale is
+/// applied.
+const TABLE_ROW_HEIGHT: f32 = 36.0;
+
 pub enum TableEvent {
     NewRows,
 }
@@ -87,29 +122,10 @@ where
             let render_counter = cx.new(|_| 0);
             let sort_method = cx.new(|_| None);
             let scroll_handle = UniformListScrollHandle::new();
+            let image_cache = HummingbirdImageCache::new(T::get_table_name(), 0, cx);
 
-            if let Some(offset) = initial_scroll_offset {
-                scroll_handle
-                    .0
-                    .borrow()
-                    .base_handle
-                    .set_offset(gpui::Point {
-                        x: px(0.0),
-                        y: px(-offset),
-                    });
-            }
-
-            let items = T::get_rows(cx, None).ok().map(Arc::new);
-
-            cx.observe(&sort_method, |this: &mut Table<T, C>, sort, cx| {
-                let sort_method = *sort.read(cx);
-                let items = T::get_rows(cx, sort_method).ok().map(Arc::new);
-
-                this.views = cx.new(|_| FxHashMap::default());
-                this.render_counter = cx.new(|_| 0);
-                this.items = items;
-
-                cx.notify();
+            cx.observe(&sort_method, |this: &mut Table<T, C>, _, cx| {
+                this.load_rows(cx);
             })
             .detach();
 
@@ -128,39 +144,108 @@ where
             .detach();
 
             cx.subscribe(&cx.entity(), |this, _, event, cx| match event {
-                TableEvent::NewRows => {
-                    let sort_method = *this.sort_method.read(cx);
-                    let items = T::get_rows(cx, sort_method).ok().map(Arc::new);
-
-                    this.views = cx.new(|_| FxHashMap::default());
-                    this.render_counter = cx.new(|_| 0);
-                    this.items = items;
-
-                    cx.notify();
-                }
+                TableEvent::NewRows => this.load_rows(cx),
             })
             .detach();
 
-            Self {
+            let mut table = Self {
                 columns,
                 hidden_column_widths,
                 views,
                 render_counter,
-                items,
+                rows: RowState::default(),
                 sort_method,
                 on_select,
                 scroll_handle,
-            }
+                image_cache,
+                load_task: None,
+                pending_scroll_offset: initial_scroll_offset,
+                focus_handle: cx.focus_handle(),
+                first_render: true,
+                type_ahead_query: String::new(),
+                type_ahead_task: None,
+                highlighted: None,
+                toolbar: None,
+            };
+
+            table.load_rows(cx);
+
+            table
         })
     }
 
+    pub fn set_toolbar(&mut self, toolbar: impl Into<AnyView>) {
+        self.toolbar = Some(toolbar.into());
+    }
+
+    /// Reloads the rows in the background. The current rows stay visible until the new ones have
+    /// loaded, and starting another load cancels this one.
+    fn load_rows(&mut self, cx: &mut Context<Self>) {
+        let sort_method = *self.sort_method.read(cx);
+        let rows = T::get_rows(cx, sort_method);
+
+        self.rows.start_loading();
+        cx.notify();
+
+        self.load_task = Some(cx.spawn(async move |this, cx| {
+            let loaded = rows.await.ok().map(|items| LoadedRows {
+                layout: Arc::new(group_rows(
+                    &items,
+                    |id| T::get_section(id, sort_method),
+                    T::get_jump_label,
+                )),
+                items: Arc::new(items),
+            });
+
+            this.update(cx, |this: &mut Self, cx| {
+                let previous = this.rows.finish(loaded);
+
+                // the view is kept on the same rows, unless a saved position is being restored
+                let offset = this.pending_scroll_offset.take().or_else(|| {
+                    let (old, new) = (previous?, this.rows.current()?);
+                    let row_height: f32 = ui_px(TABLE_ROW_HEIGHT, cx).into();
+
+                    Some(translate_scroll(
+                        &old.layout.rows,
+                        &new.layout.rows,
+                        this.get_scroll_offset(),
+                        row_height,
+                    ))
+                });
+
+                this.views = cx.new(|_| FxHashMap::default());
+                this.render_counter = cx.new(|_| 0);
+                this.highlighted = None;
+
+                if let Some(offset) = offset {
+                    this.scroll_handle
+                        .0
+                        .borrow()
+                        .base_handle
+                        .set_offset(gpui::Point {
+                            x: px(0.0),
+                            y: px(-offset),
+                        });
+                }
+
+                cx.notify();
+            })
+            .ok();
+        }));
+    }
+
+    /// Returns true while the rows are being loaded.
+    pub fn is_loading(&self) -> bool {
+        self.rows.is_loading()
+    }
+
     pub fn get_scroll_offset(&self) -> f32 {
         let offset = self.scroll_handle.0.borrow().base_handle.offset();
         (-offset.y).into()
     }
 
     pub fn get_items(&self) -> Option<Arc<Vec<T::Identifier>>> {
-        self.items.clone()
+        Some(self.rows.current()?.items.clone())
     }
 
     pub fn toggle_column(&mut self, column: C, cx: &mut App) {
@@ -233,22 +318,25 @@ where
     ) -> (IndexMap<C, f32, FxBuildHasher>, FxHashMap<C, f32>) {
         let default_columns = T::default_columns();
 
-        let Some(settings) = settings else {
-            return (default_columns, FxHashMap::default());
-        };
-
         let mut visible_columns = IndexMap::with_hasher(FxBuildHasher);
         let mut hidden_widths = FxHashMap::default();
 
         for (col, default_width) in &default_columns {
             let col_name = col.get_column_name();
-            let width = settings
-                .column_widths
-                .get(col_name)
-                .copied()
-                .unwrap_or(*default_width);
+            let saved_width = settings.and_then(|s| s.column_widths.get(col_name).copied());
 
-            if settings.hidden_columns.contains(&col_name.to_string()) && col.is_hideable() {
+            // columns that didn't exist when the settings were saved fall back to their default
+            // visibility
+            let hidden = match (settings, saved_width) {
+                (Some(settings), Some(_)) => {
+                    settings.hidden_columns.contains(&col_name.to_string())
+                }
+                _ => col.is_hidden_by_default(),
+            };
+
+            let width = saved_width.unwrap_or(*default_width);
+
+            if hidden && col.is_hideable() {
                 hidden_widths.insert(*col, width);
             } else {
                 visible_columns.insert(*col, width);
@@ -283,6 +371,90 @@ where
     pub fn get_table_name() -> &'static str {
         T::get_table_name()
     }
+
+    /// Adds typed text to the type-ahead query, and highlights the first row that starts with it.
+    /// Returns false if the rows can't be found by typing in their current order.
+    fn type_ahead(&mut self, text: &str, cx: &mut Context<Self>) -> bool {
+        let sort = *self.sort_method.read(cx);
+        let Some(LoadedRows { items, layout }) = self.rows.current().cloned() else {
+            return false;
+        };
+
+        if items
+            .first()
+            .is_none_or(|id| T::get_type_ahead_key(id, sort).is_none())
+        {
+            return false;
+        }
+
+        self.type_ahead_query.push_str(text);
+        let query = self.type_ahead_query.to_lowercase();
+
+        let found = items
+            .iter()
+            .position(|id| {
+                T::get_type_ahead_key(id, sort).is_some_and(|key| type_ahead::matches(key, &query))
+            })
+            .and_then(|idx| layout.row_of_item(idx));
+
+        if let Some(row) = found {
+            self.highlighted = Some(row);
+            self.scroll_handle
+                .scroll_to_item(row, ScrollStrategy::Center);
+        }
+
+        // replacing the task restarts the timeout
+        self.type_ahead_task = Some(cx.spawn(async move |this, cx| {
+            cx.background_executor().timer(TYPE_AHEAD_TIMEOUT).await;
+
+            this.update(cx, |this: &mut Self, cx| {
+                this.type_ahead_query.clear();
+                cx.notify();
+            })
+            .ok();
+        }));
+
+        cx.notify();
+        true
+    }
+
+    fn on_key_down(&mut self, event: &KeyDownEvent, _: &mut Window, cx: &mut Context<Self>) {
+        let modifiers = event.keystroke.modifiers;
+        if modifiers.control || modifiers.alt || modifiers.platform || modifiers.function {
+            cx.propagate();
+            return;
+        }
+
+        let handled = event
+            .keystroke
+            .key_char
+            .as_deref()
+            .filter(|text| is_type_ahead_input(text))
+            // spaces are left for play/pause unless they're part of a query
+            .filter(|text| *text != " " || !self.type_ahead_query.is_empty())
+            .is_some_and(|text| self.type_ahead(text, cx));
+
+        if !handled {
+            cx.propagate();
+        }
+    }
+
+    fn open_highlighted(&mut self, _: &Accept, _: &mut Window, cx: &mut Context<Self>) {
+        let id = self
+            .highlighted
+            .and_then(|row| self.rows.current()?.layout.item_at(row).cloned());
+
+        match (id, self.on_select.clone()) {
+            (Some(id), Some(on_select)) => on_select(cx, &id),
+            _ => cx.propagate(),
+        }
+    }
+
+    /// Scrolls a section, picked from the jump bar, to the top of the table.
+    fn jump_to(&mut self, row: usize, cx: &mut Context<Self>) {
+        self.scroll_handle.scroll_to_item(row, ScrollStrategy::Top);
+        cx.notify();
+    }
 }
 
 impl<T, C> Render for Table<T, C>
@@ -290,15 +462,29 @@ where
     T: TableData<C> + 'static,
     C: Column + 'static,
 {
-    fn render(&mut self, _: &mut Window, cx: &mut Context<'_, Self>) -> impl IntoElement {
+    fn render(&mut self, window: &mut Window, cx: &mut Context<'_, Self>) -> impl IntoElement {
+        let row_height = ui_px(TABLE_ROW_HEIGHT, cx);
+        let capacity = visible_capacity(window, row_height, 1);
+        self.image_cache.update(cx, |cache, cx| {
+            cache.set_max_items(capacity, window, cx);
+        });
+
+        if self.first_render {
+            self.first_render = false;
+            self.focus_handle.focus(window, cx);
+        }
+
         let theme = cx.global::<Theme>();
         let sort_method = self.sort_method.read(cx);
-        let items = self.items.clone();
+        let layout = self.rows.current().map(|rows| rows.layout.clone());
         let views_model = self.views.clone();
         let render_counter = self.render_counter.clone();
         let columns = self.columns.clone();
         let handler = self.on_select.clone();
         let scroll_handle = self.scroll_handle.clone();
+        let highlighted = self.highlighted;
+        let highlight_colors = (theme.nav_button_hover, theme.button_primary);
+        let header_colors = (theme.text_secondary, theme.border_color);
 
         // Calculate available width and extra width for final column expansion
         let columns_read = self.columns.read(cx);
@@ -322,7 +508,7 @@ where
             header = header.child(
                 div()
                     .w(px(TABLE_IMAGE_COLUMN_WIDTH))
-                    .h(px(36.0))
+                    .h(row_height)
                     .pl(px(21.0))
                     .pr(px(10.0))
                     .py(px(2.0))
@@ -353,7 +539,7 @@ where
                 div()
                     .flex()
                     .w(px(width))
-                    .h(px(36.0))
+                    .h(row_height)
                     .px(px(12.0))
                     .py(px(6.0))
                     .when(!T::has_images() && i == 0, |div| div.pl(px(21.0)))
@@ -432,8 +618,17 @@ where
             .child(div().bg(theme.elevated_background).child(column_menu));
 
         div()
-            .image_cache(hummingbird_cache((T::get_table_name(), 0_usize), 100))
+            .image_cache(self.image_cache.clone())
             .id(T::get_table_name())
+            .track_focus(&self.focus_handle)
+            .on_key_down(cx.listener(Self::on_key_down))
+            .on_action(cx.listener(Self::open_highlighted))
+            // the space key plays and pauses, except in the middle of typing a query
+            .on_action(cx.listener(|this, _: &PlayPause, _, cx| {
+                if this.type_ahead_query.is_empty() || !this.type_ahead(" ", cx) {
+                    cx.propagate();
+                }
+            }))
             .overflow_x_scroll()
             .flex()
             .flex_col()
@@ -444,35 +639,80 @@ where
                     .w_full()
                     .pb(px(11.0))
                     .px(px(16.0))
-                    .line_height(px(26.0))
+                    .line_height(ui_px(26.0, cx))
                     .font_weight(FontWeight::BOLD)
-                    .text_size(px(26.0))
+                    .text_size(ui_px(26.0, cx))
                     .child(T::get_table_name()),
             )
+            .when_some(self.toolbar.clone(), |this, toolbar| {
+                this.child(div().w_full().px(px(16.0)).pb(px(10.0)).child(toolbar))
+            })
             .child(header_with_context)
-            .when_some(items, |this, items| {
+            // drawn over the bottom of the header while new rows are loading
+            .child(
+                div()
+                    .w_full()
+                    .h(px(2.0))
+                    .mt(px(-2.0))
+                    .when(self.rows.is_loading() && layout.is_some(), |this| {
+                        this.bg(theme.slider_foreground)
+                    }),
+            )
+            .when_some(layout, |this, layout| {
+                let jump_targets = layout.jump_targets.clone();
+
                 this.child(
                     div()
                         .relative()
                         .w_full()
                         .h_full()
                         .child(
-                            uniform_list("table-list", items.len(), move |range, _, cx| {
+                            uniform_list("table-list", layout.rows.len(), move |range, _, cx| {
                                 let start = range.start;
                                 let is_templ_render = range.start == 0 && range.end == 1;
 
-                                items[range]
+                                layout.rows[range]
                                     .iter()
                                     .enumerate()
-                                    .map(|(idx, item)| {
+                                    .map(|(idx, row)| {
                                         let idx = idx + start;
 
                                         if !is_templ_render {
                                             prune_views(&views_model, &render_counter, idx, cx);
                                         }
 
+                                        let item = match row {
+                                            TableRow::Item(item) => item,
+                                            TableRow::Header(heading) => {
+                                                let (text_color, border_color) = header_colors;
+
+                                                return div()
+                                                    .w_full()
+                                                    .h(row_height)
+                                                    .flex()
+                                                    .items_end()
+                                                    .pl(px(21.0))
+                                                    .pb(px(4.0))
+                                                    .text_sm()
+                                                    .font_weight(FontWeight::BOLD)
+                                                    .text_color(text_color)
+                                                    .border_b_1()
+                                                    .border_color(border_color)
+                                                    .child(heading.clone())
+                                                    .into_any_element();
+                                            }
+                                        };
+
+                                        let (highlight_background, highlight_bar) =
+                                            highlight_colors;
+                                        let is_highlighted = highlighted == Some(idx);
+
                                         div()
                                             .w_full()
+                                            .relative()
+                                            .when(is_highlighted, |this| {
+                                                this.bg(highlight_background)
+                                            })
                                             .child(create_or_retrieve_view(
                                                 &views_model,
                                                 idx,
@@ -486,6 +726,17 @@ where
                                                 },
                                                 cx,
                                             ))
+                                            .when(is_highlighted, |this| {
+                                                this.child(
+                                                    div()
+                                                        .absolute()
+                                                        .left_0()
+                                                        .top_0()
+                                                        .bottom_0()
+                                                        .w(px(3.0))
+                                                        .bg(highlight_bar),
+                                                )
+                                            })
                                             .into_any_element()
                                     })
                                     .collect()
@@ -498,7 +749,70 @@ where
                             "table-scrollbar",
                             scroll_handle,
                             RightPad::Pad,
-                        )),
+                        ))
+                        // there's nothing to jump between with only one section
+                        .when(jump_targets.len() > 1, |this| {
+                            this.child(
+                                div()
+                                    .absolute()
+                                    .top_0()
+                                    .bottom_0()
+                                    .right(px(18.0))
+                                    .flex()
+                                    .flex_col()
+                                    .justify_center()
+                                    .child(
+                                        div()
+                                            .id("table-jump-bar")
+                                            .flex()
+                                            .flex_col()
+                                            .items_center()
+                                            .py(px(4.0))
+                                            .rounded(px(4.0))
+                                            .bg(theme.elevated_background)
+                                            .text_xs()
+                                            .text_color(theme.text_secondary)
+                                            .children(jump_targets.into_iter().enumerate().map(
+                                                |(i, target)| {
+                                                    div()
+                                                        .id(i)
+                                                        .px(px(5.0))
+                                                        .cursor_pointer()
+                                                        .hover(|this| this.text_color(theme.text))
+                                                        .on_click(cx.listener(
+                                                            move |this, _, _, cx| {
+                                                                this.jump_to(target.row, cx)
+                                                            },
+                                                        ))
+                                                        .child(target.label)
+                                                },
+                                            )),
+                                    ),
+                            )
+                        })
+                        .when(!self.type_ahead_query.is_empty(), |this| {
+                            this.child(
+                                div()
+                                    .absolute()
+                                    .bottom(px(16.0))
+                                    .left_0()
+                                    .right_0()
+                                    .flex()
+                                    .justify_center()
+                                    .child(
+                                        div()
+                                            .px(px(10.0))
+                                            .py(px(4.0))
+                                            .rounded(px(6.0))
+                                            .border_1()
+                                            .border_color(theme.elevated_border_color)
+                                            .bg(theme.elevated_background)
+                                            .shadow_md()
+                                            .text_sm()
+                                            .child(self.type_ahead_query.clone()),
+                                    ),
+                            )
+                        }),
                 )
             })
     }
@@ -4,8 +4,9 @@ use gpui::*;
 use indexmap::IndexMap;
 use rustc_hash::FxBuildHasher;
 
+use super::TABLE_ROW_HEIGHT;
 use super::table_data::{COLUMN_MIN_WIDTH, COLUMN_RESIZE_HANDLE_WIDTH, Column, TABLE_HEADER_GROUP};
-use crate::ui::theme::Theme;
+use crate::ui::{theme::Theme, util::ui_px};
 
 #[derive(Default)]
 struct ResizeState {
@@ -80,7 +81,7 @@ where
         let mut element = div()
             .id(self.id.clone())
             .w(px(COLUMN_RESIZE_HANDLE_WIDTH))
-            .h(px(36.0)) // Match header height
+            .h(ui_px(TABLE_ROW_HEIGHT, cx)) // Match header height
             .flex_shrink_0()
             .cursor_col_resize()
             .ml(px(-COLUMN_RESIZE_HANDLE_WIDTH / 2.0))
@@ -0,0 +1,113 @@
+/// The rows a table shows while new ones are loaded. The rows that are already shown stay on
+/// screen until the new ones are ready, and are then swapped for them all at once, so that
+/// reloading or re-sorting the table doesn't flash it blank.
+#[derive(Clone, Debug, PartialEq)]
+pub enum RowState<R> {
+    /// The rows that are shown, or None if none have been loaded.
+    Current(Option<R>),
+    /// New rows are being loaded, and the current rows are shown until they're ready.
+    Loading { current: Option<R> },
+}
+
+impl<R> Default for RowState<R> {
+    fn default() -> Self {
+        RowState::Current(None)
+    }
+}
+
+impl<R> RowState<R> {
+    /// Returns the rows to show.
+    pub fn current(&self) -> Option<&R> {
+        match self {
+            RowState::Current(current) | RowState::Loading { current } => current.as_ref(),
+        }
+    }
+
+    /// Returns true while new rows are being loaded.
+    pub fn is_loading(&self) -> bool {
+        matches!(self, RowState::Loading { .. })
+    }
+
+    /// Starts loading new rows, keeping the current ones until they're ready. Starting again
+    /// while rows are loading replaces that load, which is cancelled by the caller.
+    pub fn start_loading(&mut self) {
+        let current = self.take_current();
+        *self = RowState::Loading { current };
+    }
+
+    /// Swaps in the rows that have been loaded, or None if they couldn't be, and returns the
+    /// rows that they replaced.
+    pub fn finish(&mut self, rows: Option<R>) -> Option<R> {
+        let previous = self.take_current();
+        *self = RowState::Current(rows);
+        previous
+    }
+
+    fn take_current(&mut self) -> Option<R> {
+        match std::mem::take(self) {
+            RowState::Current(current) | RowState::Loading { current } => current,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn starts_without_rows() {
+        let state = RowState::<Vec<i32>>::default();
+
+        assert_eq!(state.current(), None);
+        assert!(!state.is_loading());
+    }
+
+    #[test]
+    fn loads_the_first_rows() {
+        let mut state = RowState::default();
+
+        state.start_loading();
+        assert_eq!(state, RowState::Loading { current: None });
+        assert_eq!(state.current(), None);
+
+        assert_eq!(state.finish(Some(vec![1, 2])), None);
+        assert_eq!(state, RowState::Current(Some(vec![1, 2])));
+    }
+
+    #[test]
+    fn keeps_showing_the_current_rows_while_loading() {
+        let mut state = RowState::Current(Some(vec![1, 2]));
+
+        state.start_loading();
+        assert!(state.is_loading());
+        assert_eq!(state.current(), Some(&vec![1, 2]));
+
+        // a load that's started again replaces the one that was running
+        state.start_loading();
+        assert_eq!(
+            state,
+            RowState::Loading {
+                current: Some(vec![1, 2])
+            }
+        );
+    }
+
+    #[test]
+    fn swaps_in_the_new_rows_once_loaded() {
+        let mut state = RowState::Current(Some(vec![1, 2]));
+        state.start_loading();
+
+        assert_eq!(state.finish(Some(vec![2, 1])), Some(vec![1, 2]));
+        assert_eq!(state, RowState::Current(Some(vec![2, 1])));
+        assert!(!state.is_loading());
+    }
+
+    #[test]
+    fn shows_nothing_when_the_rows_cant_be_loaded() {
+        let mut state = RowState::Current(Some(vec![1, 2]));
+        state.start_loading();
+
+        assert_eq!(state.finish(None), Some(vec![1, 2]));
+        assert_eq!(state, RowState::Current(None));
+    }
+}
@@ -0,0 +1,55 @@
+/// Returns the scroll offset to use once the rows have been replaced, so that the view doesn't
+/// jump. If the topmost visible row is still in the table, the view follows it to its new place
+/// (keeping however much of it was scrolled past); otherwise, the view stays at the same fraction
+/// of the way through the table.
+pub fn translate_scroll<I: PartialEq>(old: &[I], new: &[I], offset: f32, row_height: f32) -> f32 {
+    if old.is_empty() || new.is_empty() || offset <= 0.0 || row_height <= 0.0 {
+        return 0.0;
+    }
+
+    let top = ((offset / row_height) as usize).min(old.len() - 1);
+    let within_row = offset - top as f32 * row_height;
+
+    let translated = match new.iter().position(|id| *id == old[top]) {
+        Some(new_top) => new_top as f32 * row_height + within_row,
+        None => offset / (old.len() as f32 * row_height) * new.len() as f32 * row_height,
+    };
+
+    translated.min((new.len() - 1) as f32 * row_height)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn follows_the_top_row_to_its_new_place() {
+        // scrolled halfway past row 2, which has had a row inserted above it
+        assert_eq!(
+            translate_scroll(&[1, 2, 3, 4], &[0, 1, 2, 3, 4], 15.0, 10.0),
+            25.0
+        );
+    }
+
+    #[test]
+    fn keeps_the_same_fraction_when_the_top_row_is_gone() {
+        let new: Vec<i32> = (5..13).collect();
+
+        assert_eq!(translate_scroll(&[1, 2, 3, 4], &new, 20.0, 10.0), 40.0);
+    }
+
+    #[test]
+    fn stays_within_the_new_rows() {
+        let old: Vec<i32> = (1..=10).collect();
+
+        assert_eq!(translate_scroll(&old, &[9], 85.0, 10.0), 0.0);
+        assert_eq!(translate_scroll(&old, &[8, 9], 85.0, 10.0), 10.0);
+    }
+
+    #[test]
+    fn starts_at_the_top_without_rows_or_scrolling() {
+        assert_eq!(translate_scroll::<i32>(&[], &[1, 2], 15.0, 10.0), 0.0);
+        assert_eq!(translate_scroll(&[1, 2], &[], 15.0, 10.0), 0.0);
+        assert_eq!(translate_scroll(&[1, 2], &[1, 2], 0.0, 10.0), 0.0);
+    }
+}
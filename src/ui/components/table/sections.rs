@@ -0,0 +1,157 @@
+use gpui::SharedString;
+
+/// A row of a table: either one of its items, or the heading of the section that the items after
+/// it belong to.
+#[derive(Clone, Debug, PartialEq)]
+pub enum TableRow<I> {
+    Header(SharedString),
+    Item(I),
+}
+
+/// A place in the table that the jump bar scrolls to.
+#[derive(Clone, Debug, PartialEq)]
+pub struct JumpTarget {
+    pub label: SharedString,
+    pub row: usize,
+}
+
+/// The rows of a table, with a heading in front of each section.
+pub struct TableLayout<I> {
+    pub rows: Vec<TableRow<I>>,
+    /// The row that each item ended up in, by the item's index.
+    item_rows: Vec<usize>,
+    pub jump_targets: Vec<JumpTarget>,
+}
+
+impl<I> TableLayout<I> {
+    /// Returns the row that the item at the given index is shown in.
+    pub fn row_of_item(&self, index: usize) -> Option<usize> {
+        self.item_rows.get(index).copied()
+    }
+
+    /// Returns the item shown in the given row, or None if the row is a heading.
+    pub fn item_at(&self, row: usize) -> Option<&I> {
+        match self.rows.get(row)? {
+            TableRow::Item(item) => Some(item),
+            TableRow::Header(_) => None,
+        }
+    }
+}
+
+/// Lays out items that have already been sorted, with a heading in front of each run of items
+/// that are in the same section. Items without a section stay in the one before them, and if the
+/// first item has no section, there are no headings at all.
+///
+/// Each section is also listed in the jump bar, under the label `jump_label` gives it. Sections
+/// next to each other with the same label (like the years of a decade) share a single target,
+/// which is the first of them.
+pub fn group_rows<I: Clone>(
+    items: &[I],
+    section: impl Fn(&I) -> Option<SharedString>,
+    jump_label: impl Fn(&SharedString) -> SharedString,
+) -> TableLayout<I> {
+    let mut layout = TableLayout {
+        rows: Vec::with_capacity(items.len()),
+        item_rows: Vec::with_capacity(items.len()),
+        jump_targets: Vec::new(),
+    };
+
+    let grouped = items.first().and_then(&section).is_some();
+    let mut current: Option<SharedString> = None;
+
+    for item in items {
+        if grouped
+            && let Some(heading) = section(item)
+            && current.as_ref() != Some(&heading)
+        {
+            let label = jump_label(&heading);
+
+            if layout
+                .jump_targets
+                .last()
+                .is_none_or(|last| last.label != label)
+            {
+                layout.jump_targets.push(JumpTarget {
+                    label,
+                    row: layout.rows.len(),
+                });
+            }
+
+            layout.rows.push(TableRow::Header(heading.clone()));
+            current = Some(heading);
+        }
+
+        layout.item_rows.push(layout.rows.len());
+        layout.rows.push(TableRow::Item(item.clone()));
+    }
+
+    layout
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Sections by the first character, or none for empty items.
+    fn initial(item: &&str) -> Option<SharedString> {
+        item.get(..1).map(|initial| initial.to_string().into())
+    }
+
+    fn same(section: &SharedString) -> SharedString {
+        section.clone()
+    }
+
+    fn header(label: &'static str) -> TableRow<&'static str> {
+        TableRow::Header(label.into())
+    }
+
+    #[test]
+    fn puts_a_heading_in_front_of_each_section() {
+        let layout = group_rows(&["Abba", "Air", "Beck"], initial, same);
+
+        assert_eq!(
+            layout.rows,
+            [
+                header("A"),
+                TableRow::Item("Abba"),
+                TableRow::Item("Air"),
+                header("B"),
+                TableRow::Item("Beck"),
+            ]
+        );
+        assert_eq!(layout.row_of_item(2), Some(4));
+        assert_eq!(layout.item_at(0), None);
+        assert_eq!(layout.item_at(2), Some(&"Air"));
+    }
+
+    #[test]
+    fn keeps_items_without_a_section_in_the_one_before() {
+        let layout = group_rows(&["Abba", "", "Beck"], initial, same);
+
+        assert_eq!(layout.rows.len(), 5);
+        assert_eq!(layout.row_of_item(1), Some(2));
+    }
+
+    #[test]
+    fn has_no_headings_if_the_first_item_has_no_section() {
+        let layout = group_rows(&["", "Abba", "Beck"], initial, same);
+
+        assert_eq!(layout.rows.len(), 3);
+        assert!(layout.jump_targets.is_empty());
+    }
+
+    #[test]
+    fn neighbouring_sections_with_the_same_label_share_a_target() {
+        let year = |item: &&str| Some(SharedString::from(item.to_string()));
+        let decade = |year: &SharedString| SharedString::from(format!("{}0s", &year[..3]));
+
+        let layout = group_rows(&["1991", "1994", "2003"], year, decade);
+
+        let targets: Vec<(&str, usize)> = layout
+            .jump_targets
+            .iter()
+            .map(|target| (target.label.as_ref(), target.row))
+            .collect();
+        assert_eq!(targets, [("1990s", 0), ("2000s", 4)]);
+    }
+}
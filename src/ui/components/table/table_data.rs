@@ -4,7 +4,10 @@ use gpui::{App, ElementId, SharedString};
 use indexmap::IndexMap;
 use rustc_hash::FxBuildHasher;
 
-use crate::ui::components::drag_drop::{AlbumDragData, TrackDragData};
+use crate::ui::components::{
+    drag_drop::{AlbumDragData, TrackDragData},
+    menu::Menu,
+};
 
 #[derive(Clone, Debug)]
 pub enum TableDragData {
@@ -38,6 +41,12 @@ pub trait Column: Clone + Copy + Debug + Hash + PartialEq + Eq {
         true
     }
 
+    /// Returns whether this column should be hidden until the user chooses to show it.
+    /// Defaults to false.
+    fn is_hidden_by_default(&self) -> bool {
+        false
+    }
+
     /// Returns all possible column variants for this type.
     /// Required for building the column visibility menu.
     fn all_columns() -> &'static [Self];
@@ -58,7 +67,7 @@ pub trait TableData<C>: Sized
 where
     C: Column,
 {
-    type Identifier: Clone + Debug;
+    type Identifier: Clone + Debug + PartialEq;
 
     /// Retrieves the name of the table.
     fn get_table_name() -> &'static str;
@@ -66,14 +75,27 @@ where
     /// Retrieves the rows of the table. The rows are returned as a vector of identifiers, which
     /// can be used to retrieve the full row data. The sort parameter can be used to specify the
     /// sorting order of the rows.
-    fn get_rows(cx: &mut App, sort: Option<TableSort<C>>) -> anyhow::Result<Vec<Self::Identifier>>;
+    ///
+    /// The returned future must not block the UI thread: it's awaited in the background, and the
+    /// table keeps showing its previous rows until it resolves.
+    fn get_rows(
+        cx: &mut App,
+        sort: Option<TableSort<C>>,
+    ) -> impl Future<Output = anyhow::Result<Vec<Self::Identifier>>> + 'static;
 
     /// Retrieves a specific row of the table. The row is returned as an Arc to the table data,
     /// which can be used to retrieve the row data as SharedStrings. The id parameter is used to
     /// identify the row to retrieve.
-    fn get_row(cx: &mut App, id: Self::Identifier) -> anyhow::Result<Option<Arc<Self>>>;
-
-    /// Retrieves a column from the row.
+    ///
+    /// Like [`TableData::get_rows`], the returned future is awaited in the background. Anything
+    /// [`TableData::get_column`] needs from the database should be loaded here.
+    fn get_row(
+        cx: &mut App,
+        id: Self::Identifier,
+    ) -> impl Future<Output = anyhow::Result<Option<Arc<Self>>>> + 'static;
+
+    /// Retrieves a column from the row. This is called while rendering, so it shouldn't query
+    /// the database.
     fn get_column(&self, cx: &mut App, column: C) -> Option<SharedString>;
 
     /// Returns true if the rows may contain images. This is used during the layout phase to
@@ -104,4 +126,38 @@ where
     fn get_drag_data(&self) -> Option<TableDragData> {
         None
     }
+
+    /// Returns true if the row should be displayed muted, for example because it's been hidden
+    /// from the library. Default implementation returns false.
+    fn is_muted(&self) -> bool {
+        false
+    }
+
+    /// Returns the text that type-ahead matches against for the given row, with the rows in the
+    /// given order. If None is returned, type-ahead is disabled for that order. Default
+    /// implementation returns None.
+    fn get_type_ahead_key(_id: &Self::Identifier, _sort: Option<TableSort<C>>) -> Option<&str> {
+        None
+    }
+
+    /// Returns the heading of the section that the given row belongs to, with the rows in the
+    /// given order, like the first letter of the name they're sorted by. A heading is shown
+    /// whenever it changes. If None is returned for the first row, the rows aren't split into
+    /// sections for that order. Default implementation returns None.
+    fn get_section(_id: &Self::Identifier, _sort: Option<TableSort<C>>) -> Option<SharedString> {
+        None
+    }
+
+    /// Returns what the given section is listed as in the jump bar. Sections next to each other
+    /// that are listed the same way share a place in it. Default implementation returns the
+    /// heading itself.
+    fn get_jump_label(section: &SharedString) -> SharedString {
+        section.clone()
+    }
+
+    /// Returns the context menu for this row. If None is returned, the row has no context menu.
+    /// Default implementation returns None.
+    fn get_context_menu(&self, _cx: &mut App) -> Option<Menu> {
+        None
+    }
 }
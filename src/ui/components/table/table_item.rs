@@ -5,12 +5,17 @@ use indexmap::IndexMap;
 use rustc_hash::FxBuildHasher;
 
 use super::{
-    OnSelectHandler,
+    OnSelectHandler, TABLE_ROW_HEIGHT,
     table_data::{Column, TABLE_IMAGE_COLUMN_WIDTH, TABLE_MAX_WIDTH, TableData, TableDragData},
 };
 use crate::ui::{
-    components::drag_drop::{AlbumDragData, DragPreview, TrackDragData},
+    components::{
+        context::context,
+        drag_drop::{AlbumDragData, DragPreview, TrackDragData},
+    },
+    external_drag,
     theme::Theme,
+    util::ui_px,
 };
 
 /// Calculates the extra width to add to the final column to fill available space.
@@ -53,44 +58,52 @@ where
         columns: &Entity<Arc<IndexMap<C, f32, FxBuildHasher>>>,
         on_select: Option<OnSelectHandler<T, C>>,
     ) -> Entity<Self> {
-        let row = T::get_row(cx, id).ok().flatten();
-
-        let id = row.as_ref().map(|row| row.get_element_id().into());
-
+        let row = T::get_row(cx, id);
         let columns_read = columns.read(cx).clone();
 
-        let data = row.clone().map(|row| {
-            let keys = columns_read.keys();
-
-            keys.into_iter().map(|v| row.get_column(cx, *v)).collect()
-        });
-
-        let image_path = row.as_ref().and_then(|row| row.get_image_path());
-
         cx.new(|cx| {
             cx.observe(columns, |this: &mut TableItem<T, C>, m, cx| {
                 this.columns = m.read(cx).clone();
+                this.update_data(cx);
+
+                cx.notify();
+            })
+            .detach();
 
-                this.data = this.row.clone().map(|row| {
-                    let keys = this.columns.keys();
+            // the row is left empty until it's loaded
+            cx.spawn(async move |this, cx| {
+                let row = row.await.ok().flatten();
 
-                    keys.into_iter().map(|v| row.get_column(cx, *v)).collect()
-                });
+                this.update(cx, |this: &mut Self, cx| {
+                    this.id = row.as_ref().map(|row| row.get_element_id().into());
+                    this.image_path = row.as_ref().and_then(|row| row.get_image_path());
+                    this.row = row;
+                    this.update_data(cx);
 
-                cx.notify();
+                    cx.notify();
+                })
+                .ok();
             })
             .detach();
 
             Self {
-                data,
-                image_path,
+                data: None,
+                image_path: None,
                 columns: columns_read,
                 on_select,
-                id,
-                row,
+                id: None,
+                row: None,
             }
         })
     }
+
+    fn update_data(&mut self, cx: &mut App) {
+        self.data = self.row.clone().map(|row| {
+            let keys = self.columns.keys();
+
+            keys.into_iter().map(|v| row.get_column(cx, *v)).collect()
+        });
+    }
 }
 
 impl<T, C> Render for TableItem<T, C>
@@ -99,6 +112,9 @@ where
     C: Column + 'static,
 {
     fn render(&mut self, _: &mut Window, cx: &mut Context<'_, Self>) -> impl IntoElement {
+        let context_menu = self.row.as_ref().and_then(|row| row.get_context_menu(cx));
+        let muted = self.row.as_ref().is_some_and(|row| row.is_muted());
+
         let theme = cx.global::<Theme>();
         let row_data = self.row.clone();
 
@@ -107,11 +123,14 @@ where
         let mut row = div()
             .w_full()
             .flex()
-            .id(self.id.clone().unwrap_or("bad".into()))
+            .id(self.id.clone().unwrap_or(ElementId::View(cx.entity_id())))
+            .when(muted, |div| div.opacity(0.5))
             .when_some(self.on_select.clone(), move |div, on_select| {
                 div.on_click(move |_, _, cx| {
-                    let id = row_data.as_ref().unwrap().get_table_id();
-                    on_select(cx, &id)
+                    // rows can't be selected until they've loaded
+                    if let Some(row) = row_data.as_ref() {
+                        on_select(cx, &row.get_table_id())
+                    }
                 })
                 .cursor_pointer()
                 .hover(|this| this.bg(theme.nav_button_hover))
@@ -121,14 +140,16 @@ where
         row = match drag_data {
             Some(TableDragData::Track(track_data)) => {
                 let display_name = track_data.display_name.clone();
-                row.on_drag(track_data, move |_, _, _, cx| {
+                row.on_drag(track_data, move |track, _, _, cx| {
+                    external_drag::offer_files(vec![track.path.clone()]);
                     DragPreview::new(cx, display_name.clone())
                 })
                 .drag_over::<TrackDragData>(|style, _, _, _| style.bg(gpui::rgba(0x88888822)))
             }
             Some(TableDragData::Album(album_data)) => {
                 let display_name = album_data.display_name.clone();
-                row.on_drag(album_data, move |_, _, _, cx| {
+                row.on_drag(album_data, move |album, _, _, cx| {
+                    external_drag::offer_album(cx, album.album_id);
                     DragPreview::new(cx, display_name.clone())
                 })
                 .drag_over::<AlbumDragData>(|style, _, _, _| style.bg(gpui::rgba(0x88888822)))
@@ -140,7 +161,7 @@ where
             row = row.child(
                 div()
                     .w(px(TABLE_IMAGE_COLUMN_WIDTH))
-                    .h(px(36.0))
+                    .h(ui_px(TABLE_ROW_HEIGHT, cx))
                     .text_sm()
                     .pl(px(11.0))
                     .flex_shrink_0()
@@ -184,7 +205,7 @@ where
                 row = row.child(
                     div()
                         .w(px(width))
-                        .h(px(36.0))
+                        .h(ui_px(TABLE_ROW_HEIGHT, cx))
                         .px(px(12.0))
                         .py(px(6.0))
                         .when(!T::has_images() && i == 0, |div| div.pl(px(21.0)))
@@ -200,6 +221,12 @@ where
             }
         }
 
-        row
+        match context_menu {
+            Some(menu) => context(ElementId::View(cx.entity_id()))
+                .with(row)
+                .child(menu)
+                .into_any_element(),
+            None => row.into_any_element(),
+        }
     }
 }
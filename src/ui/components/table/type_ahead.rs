@@ -0,0 +1,29 @@
+use std::time::Duration;
+
+/// How long after the last key press the type-ahead query is forgotten.
+pub const TYPE_AHEAD_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Leading articles that are skipped when matching, so that "The National" can be found by typing
+/// "nat". Tags usually provide a sort name without the article, but not always.
+const ARTICLES: &[&str] = &["the ", "a ", "an "];
+
+/// Returns true if the row's key starts with the (lowercase) query, either as written or without
+/// its leading article.
+pub fn matches(key: &str, query: &str) -> bool {
+    let key = key.trim_start().to_lowercase();
+
+    if key.starts_with(query) {
+        return true;
+    }
+
+    ARTICLES
+        .iter()
+        .find_map(|article| key.strip_prefix(article))
+        .is_some_and(|rest| rest.trim_start().starts_with(query))
+}
+
+/// Returns true if the key press should be added to the type-ahead query, rather than being left
+/// for shortcuts.
+pub fn is_type_ahead_input(text: &str) -> bool {
+    !text.is_empty() && !text.chars().any(char::is_control)
+}
@@ -1,26 +1,40 @@
 use crate::{
-    playback::{events::RepeatState, interface::PlaybackInterface, thread::PlaybackState},
-    settings::SettingsGlobal,
+    library::db::LibraryAccess,
+    playback::{
+        events::{RepeatState, SleepTimer},
+        interface::PlaybackInterface,
+        thread::{PlaybackState, volume_to_db},
+    },
+    settings::{SettingsGlobal, playback::CrossfeedLevel, update_settings},
     ui::components::{
         context::context,
         icons::{
-            MENU, NEXT_TRACK, PAUSE, PLAY, PREV_TRACK, REPEAT, REPEAT_OFF, REPEAT_ONCE, SHUFFLE,
-            VOLUME, VOLUME_OFF, icon,
+            HEADPHONES, MENU, NEXT_TRACK, PAUSE, PLAY, PREV_TRACK, REPEAT, REPEAT_OFF, REPEAT_ONCE,
+            SHUFFLE, VOLUME, VOLUME_OFF, icon,
         },
-        menu::{menu, menu_item},
+        menu::{menu, menu_check_item, menu_item},
     },
+    util::round_millis,
 };
 use gpui::*;
 use prelude::FluentBuilder;
+use seek::SeekSuppression;
+use std::time::{Duration, Instant};
 
 use super::{
     components::slider::slider,
     constants::APP_ROUNDING,
+    equalizer::EqualizerPanel,
     global_actions::{Next, PlayPause, Previous},
-    models::{Models, PlaybackInfo},
+    library::edit_gain::EditGain,
+    models::{Models, PlaybackInfo, SeekCompleted},
     theme::Theme,
+    toasts::show_toast,
+    util::ui_px,
 };
 
+mod seek;
+
 pub struct Controls {
     info_section: Entity<InfoSection>,
     scrubber: Entity<Scrubber>,
@@ -72,6 +86,8 @@ pub struct InfoSection {
     artist_name: Option<SharedString>,
     albumart_actual: Option<ImageSource>,
     playback_info: PlaybackInfo,
+    edit_gain: Option<Entity<EditGain>>,
+    show_edit_gain: Entity<bool>,
 }
 
 impl InfoSection {
@@ -109,98 +125,156 @@ impl InfoSection {
                 track_name: None,
                 albumart_actual: None,
                 playback_info,
+                edit_gain: None,
+                show_edit_gain: cx.new(|_| false),
             }
         })
     }
 }
 
+impl InfoSection {
+    fn show_edit_gain(&mut self, cx: &mut Context<Self>) {
+        let Some(path) = self
+            .playback_info
+            .current_track
+            .read(cx)
+            .as_ref()
+            .map(|track| track.get_path().clone())
+        else {
+            return;
+        };
+
+        let Ok(Some(track_id)) = cx.get_track_id_by_path(&path) else {
+            show_toast(
+                cx,
+                "Only tracks in your library can have their volume adjusted.",
+            );
+            return;
+        };
+
+        if self
+            .edit_gain
+            .as_ref()
+            .is_none_or(|edit_gain| edit_gain.read(cx).track_id != track_id)
+        {
+            self.edit_gain = EditGain::new(cx, self.show_edit_gain.clone(), track_id);
+        }
+
+        self.show_edit_gain.write(cx, true);
+        cx.notify();
+    }
+}
+
 impl Render for InfoSection {
     fn render(&mut self, _: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
         let theme = cx.global::<Theme>();
         let state = self.playback_info.playback_state.read(cx);
+        let weak_self = cx.weak_entity();
 
-        div()
-            .id("info-section")
-            .flex()
-            .w(px(275.0))
-            .min_w(px(275.0))
-            .max_w(px(275.0))
-            .overflow_x_hidden()
-            .flex_shrink_0()
-            .child(
+        context("info-section-context")
+            .with(
                 div()
-                    .mx(px(12.0))
-                    .mt(px(12.0))
-                    .mb(px(6.0))
-                    .gap(px(10.0))
+                    .id("info-section")
                     .flex()
+                    .w(px(275.0))
+                    .min_w(px(275.0))
+                    .max_w(px(275.0))
                     .overflow_x_hidden()
+                    .flex_shrink_0()
                     .child(
                         div()
-                            .id("album-art")
-                            .rounded(px(4.0))
-                            .bg(theme.album_art_background)
-                            .shadow_sm()
-                            .w(px(36.0))
-                            .h(px(36.0))
+                            .mx(px(12.0))
+                            .mt(px(12.0))
                             .mb(px(6.0))
-                            .when(self.albumart_actual.is_some(), |div| {
-                                div.child(
-                                    img(self.albumart_actual.clone().unwrap())
-                                        .w(px(36.0))
-                                        .h(px(36.0))
-                                        .rounded(px(4.0)),
-                                )
-                            }),
-                    )
-                    .when(*state == PlaybackState::Stopped, |e| {
-                        e.child(
-                            div()
-                                .line_height(rems(1.0))
-                                .font_weight(FontWeight::EXTRA_BOLD)
-                                .text_size(px(15.0))
-                                .flex()
-                                .h_full()
-                                .items_center()
-                                .pb(px(6.0))
-                                .child("Hummingbird"),
-                        )
-                    })
-                    .when(*state != PlaybackState::Stopped, |e| {
-                        e.child(
-                            div()
-                                .flex()
-                                .flex_col()
-                                .line_height(rems(1.0))
-                                .text_size(px(15.0))
-                                .gap_1()
-                                .overflow_x_hidden()
-                                .child(
+                            .gap(px(10.0))
+                            .flex()
+                            .overflow_x_hidden()
+                            .child(
+                                div()
+                                    .id("album-art")
+                                    .rounded(px(4.0))
+                                    .bg(theme.album_art_background)
+                                    .shadow_sm()
+                                    .w(px(36.0))
+                                    .h(px(36.0))
+                                    .mb(px(6.0))
+                                    .when(self.albumart_actual.is_some(), |div| {
+                                        div.child(
+                                            img(self.albumart_actual.clone().unwrap())
+                                                .w(px(36.0))
+                                                .h(px(36.0))
+                                                .rounded(px(4.0)),
+                                        )
+                                    }),
+                            )
+                            .when(*state == PlaybackState::Stopped, |e| {
+                                e.child(
                                     div()
-                                        .overflow_x_hidden()
+                                        .line_height(rems(1.0))
                                         .font_weight(FontWeight::EXTRA_BOLD)
-                                        .text_ellipsis()
-                                        .child(
-                                            self.track_name
-                                                .clone()
-                                                .unwrap_or("Unknown Track".into()),
-                                        ),
+                                        .text_size(ui_px(15.0, cx))
+                                        .flex()
+                                        .h_full()
+                                        .items_center()
+                                        .pb(px(6.0))
+                                        .child("Hummingbird"),
                                 )
-                                .child(
+                            })
+                            .when(*state != PlaybackState::Stopped, |e| {
+                                e.child(
                                     div()
+                                        .flex()
+                                        .flex_col()
+                                        .line_height(rems(1.0))
+                                        .text_size(ui_px(15.0, cx))
+                                        .gap_1()
                                         .overflow_x_hidden()
-                                        .pb(px(6.0))
-                                        .text_ellipsis()
-                                        .overflow_x_hidden()
                                         .child(
-                                            self.artist_name
-                                                .clone()
-                                                .unwrap_or("Unknown Artist".into()),
+                                            div()
+                                                .overflow_x_hidden()
+                                                .font_weight(FontWeight::EXTRA_BOLD)
+                                                .text_ellipsis()
+                                                .child(
+                                                    self.track_name
+                                                        .clone()
+                                                        .unwrap_or("Unknown Track".into()),
+                                                ),
+                                        )
+                                        .child(
+                                            div()
+                                                .overflow_x_hidden()
+                                                .pb(px(6.0))
+                                                .text_ellipsis()
+                                                .overflow_x_hidden()
+                                                .child(
+                                                    self.artist_name
+                                                        .clone()
+                                                        .unwrap_or("Unknown Artist".into()),
+                                                ),
                                         ),
-                                ),
-                        )
+                                )
+                            }),
+                    )
+                    .when_some(self.edit_gain.clone(), |this, edit_gain| {
+                        this.child(edit_gain)
                     }),
             )
+            .when(*state != PlaybackState::Stopped, |this| {
+                this.child(
+                    div()
+                        .bg(theme.elevated_background)
+                        .child(menu().item(menu_item(
+                            "info-edit-gain",
+                            Some(VOLUME),
+                            "Adjust track volume…",
+                            move |_, _, cx| {
+                                weak_self
+                                    .update(cx, |this, cx| this.show_edit_gain(cx))
+                                    .ok();
+                            },
+                        ))),
+                )
+            })
     }
 }
 
@@ -444,7 +518,9 @@ impl Render for PlaybackSection {
 pub struct Scrubber {
     position: Entity<u64>,
     duration: Entity<u64>,
+    loop_region: Entity<Option<(f64, f64)>>,
     playback_section: Entity<PlaybackSection>,
+    seek: SeekSuppression,
 }
 
 impl Scrubber {
@@ -452,6 +528,7 @@ impl Scrubber {
         cx.new(|cx| {
             let position_model = cx.global::<PlaybackInfo>().position.clone();
             let duration_model = cx.global::<PlaybackInfo>().duration.clone();
+            let loop_region = cx.global::<PlaybackInfo>().loop_region.clone();
 
             cx.observe(&position_model, |_, _, cx| {
                 cx.notify();
@@ -463,10 +540,26 @@ impl Scrubber {
             })
             .detach();
 
+            cx.observe(&loop_region, |_, _, cx| {
+                cx.notify();
+            })
+            .detach();
+
+            cx.subscribe(
+                &position_model,
+                |this: &mut Self, _, _: &SeekCompleted, cx| {
+                    this.seek.seek_completed();
+                    cx.notify();
+                },
+            )
+            .detach();
+
             Self {
                 position: position_model,
                 duration: duration_model,
+                loop_region,
                 playback_section: PlaybackSection::new(cx),
+                seek: SeekSuppression::default(),
             }
         })
     }
@@ -475,9 +568,27 @@ impl Scrubber {
 impl Render for Scrubber {
     fn render(&mut self, window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
         let theme = cx.global::<Theme>();
-        let position = *self.position.read(cx);
-        let duration = *self.duration.read(cx);
-        let remaining = duration - position;
+        // while seeking, show where the user is seeking to instead of the reported position
+        let position_millis = self.seek.position(*self.position.read(cx), Instant::now());
+        let duration_millis = *self.duration.read(cx);
+        // the position is rounded down and the duration to the nearest second, so that the
+        // position only reaches the duration at the very end of the track
+        let position = position_millis / 1000;
+        let duration = round_millis(duration_millis);
+        let remaining = duration.saturating_sub(position);
+        let duration_secs = duration_millis as f64 / 1000.0;
+        // the part of the track that's being repeated, as fractions of its duration
+        let loop_region = self
+            .loop_region
+            .read(cx)
+            .filter(|_| duration_millis > 0)
+            .map(|(start, end)| {
+                let start = (start / duration_secs).clamp(0.0, 1.0) as f32;
+                let end = (end / duration_secs).clamp(0.0, 1.0) as f32;
+                (start, end)
+            });
+        let weak = cx.weak_entity();
+        let weak_release = weak.clone();
 
         let window_width = window.viewport_size().width;
 
@@ -489,7 +600,7 @@ impl Render for Scrubber {
             .flex_grow()
             .flex()
             .flex_col()
-            .text_size(px(15.0))
+            .text_size(ui_px(15.0, cx))
             .font_weight(FontWeight::SEMIBOLD)
             .relative()
             .child(
@@ -525,33 +636,163 @@ impl Render for Scrubber {
                     ))),
             )
             .child(
-                slider()
+                div()
+                    .relative()
                     .w_full()
-                    .h(px(6.0))
-                    .rounded(px(3.0))
-                    .id("scrubber-back")
-                    .value(position as f32 / duration as f32)
-                    .on_change(move |v, _, cx| {
-                        let info = cx.global::<PlaybackInfo>().clone();
-
-                        if duration > 0 && *info.playback_state.read(cx) != PlaybackState::Stopped {
-                            cx.global::<PlaybackInterface>()
-                                .seek(v as f64 * duration as f64);
-                        }
+                    .child(
+                        slider()
+                            .w_full()
+                            .h(px(6.0))
+                            .rounded(px(3.0))
+                            .id("scrubber-back")
+                            .value(position_millis as f32 / duration_millis as f32)
+                            .on_change(move |v, _, cx| {
+                                let info = cx.global::<PlaybackInfo>().clone();
+
+                                if duration_millis > 0
+                                    && *info.playback_state.read(cx) != PlaybackState::Stopped
+                                {
+                                    let target = v as f64 * duration_secs;
+                                    cx.global::<PlaybackInterface>().seek(target);
+
+                                    weak.update(cx, |this, cx| {
+                                        let target = (target * 1000.0) as u64;
+                                        this.seek.seek_requested(target, Instant::now());
+                                        cx.notify();
+                                    })
+                                    .ok();
+                                }
+                            })
+                            .on_release(move |_, cx| {
+                                weak_release
+                                    .update(cx, |this, cx| {
+                                        this.seek.drag_ended(Instant::now());
+                                        cx.notify();
+                                    })
+                                    .ok();
+                            }),
+                    )
+                    .when_some(loop_region, |this, (start, end)| {
+                        // drawn just below the scrubber, so that it doesn't cover the position
+                        this.child(
+                            div()
+                                .absolute()
+                                .top(px(8.0))
+                                .h(px(2.0))
+                                .rounded(px(1.0))
+                                .left(relative(start))
+                                .w(relative(end - start))
+                                .bg(theme.slider_foreground),
+                        )
                     }),
             )
     }
 }
 
+fn set_crossfeed(cx: &mut App, level: CrossfeedLevel) {
+    update_settings(cx, |settings| settings.playback.crossfeed = level);
+}
+
+/// The speeds that can be picked from the speed button, as multiples of the normal speed.
+const SPEED_PRESETS: [f64; 7] = [0.5, 0.75, 1.0, 1.25, 1.5, 1.75, 2.0];
+
+/// Returns the speed after the given one, going back to the slowest after the fastest.
+fn next_speed(speed: f64) -> f64 {
+    SPEED_PRESETS
+        .into_iter()
+        .find(|preset| *preset > speed)
+        .unwrap_or(SPEED_PRESETS[0])
+}
+
+/// Describes the sleep timer for the button that cancels it, with the time left as minutes and
+/// seconds. Returns `None` if the timer isn't set.
+fn describe_sleep_timer(timer: SleepTimer) -> Option<String> {
+    match timer {
+        SleepTimer::Off => None,
+        SleepTimer::AfterDuration(left) => {
+            let secs = left.as_secs();
+            Some(format!("Sleep {}:{:02}", secs / 60, secs % 60))
+        }
+        SleepTimer::AfterCurrentTrack => Some("Sleep after track".to_string()),
+    }
+}
+
+/// A button showing something that will happen to playback, like the sleep timer going off.
+/// Clicking it should cancel that.
+fn indicator_button(id: &'static str, theme: &Theme) -> Stateful<Div> {
+    div()
+        .rounded(px(3.0))
+        .px(px(4.0))
+        .h(px(25.0))
+        .mt(px(2.0))
+        .flex()
+        .items_center()
+        .justify_center()
+        .text_xs()
+        .border_color(theme.playback_button_border)
+        .id(id)
+        .cursor_pointer()
+        .bg(theme.playback_button)
+        .hover(|this| this.bg(theme.playback_button_hover))
+        .active(|this| this.bg(theme.playback_button_active))
+        .text_color(theme.playback_button_toggled)
+}
+
+/// How often the volume is sent to the playback thread while the volume slider is dragged. The
+/// final volume is always sent when the slider is released.
+const VOLUME_DRAG_INTERVAL: Duration = Duration::from_millis(50);
+
+/// How far one step of the scroll wheel moves the volume, normally and with shift held.
+const VOLUME_SCROLL_STEP: f64 = 0.02;
+const VOLUME_SCROLL_STEP_LARGE: f64 = 0.1;
+
+/// How far pressing up or down moves the volume, while the volume slider is focused.
+const VOLUME_KEY_STEP: f64 = 0.01;
+
+/// How far a trackpad has to scroll to move the volume by one step.
+const PIXELS_PER_VOLUME_STEP: f32 = 24.0;
+
+/// Describes a volume as the slider's position, followed by roughly how much quieter than full
+/// volume it is.
+fn describe_volume(volume: f64) -> String {
+    let percent = (volume * 100.0).round();
+
+    match volume_to_db(volume).map(f64::round) {
+        Some(db) if db < 0.0 => format!("{percent}% · {db} dB"),
+        Some(_) => format!("{percent}% · 0 dB"),
+        None => format!("{percent}% · muted"),
+    }
+}
+
 pub struct SecondaryControls {
     info: PlaybackInfo,
     show_queue: Entity<bool>,
+    volume_focus: FocusHandle,
+    volume_hovered: bool,
+    /// The position of the volume slider while it's being dragged, which is ahead of the volume
+    /// the playback thread has reported.
+    dragged_volume: Option<f64>,
+    /// When the volume was last sent to the playback thread during the current drag.
+    volume_sent_at: Option<Instant>,
+    /// Trackpad scrolling that hasn't added up to a whole step yet.
+    scroll_remainder: f32,
+    /// Where the section to repeat starts, in milliseconds, once it's been marked with the A-B
+    /// button but its end hasn't been yet.
+    loop_start: Option<u64>,
+    equalizer: Entity<EqualizerPanel>,
+    show_equalizer: Entity<bool>,
 }
 
 impl SecondaryControls {
     pub fn new(cx: &mut App, show_queue: Entity<bool>) -> Entity<Self> {
         cx.new(|cx| {
             let info = cx.global::<PlaybackInfo>().clone();
+            let muted = info.muted.clone();
+            cx.observe(&muted, |_, _, cx| {
+                cx.notify();
+            })
+            .detach();
+
             let volume = info.volume.clone();
 
             cx.observe(&volume, |_, _, cx| {
@@ -559,17 +800,231 @@ impl SecondaryControls {
             })
             .detach();
 
-            Self { info, show_queue }
+            let speed = info.speed.clone();
+            cx.observe(&speed, |_, _, cx| {
+                cx.notify();
+            })
+            .detach();
+
+            let current_track = info.current_track.clone();
+            cx.observe(&current_track, |this: &mut Self, _, cx| {
+                this.loop_start = None;
+                cx.notify();
+            })
+            .detach();
+
+            let loop_region = info.loop_region.clone();
+            cx.observe(&loop_region, |_, _, cx| {
+                cx.notify();
+            })
+            .detach();
+
+            let sleep_timer = info.sleep_timer.clone();
+            cx.observe(&sleep_timer, |_, _, cx| {
+                cx.notify();
+            })
+            .detach();
+
+            let stop_after_current = info.stop_after_current.clone();
+            cx.observe(&stop_after_current, |_, _, cx| {
+                cx.notify();
+            })
+            .detach();
+
+            let bit_perfect = info.bit_perfect.clone();
+            cx.observe(&bit_perfect, |_, _, cx| {
+                cx.notify();
+            })
+            .detach();
+
+            let underruns = info.underruns.clone();
+            cx.observe(&underruns, |_, _, cx| {
+                cx.notify();
+            })
+            .detach();
+
+            let settings = cx.global::<SettingsGlobal>().model.clone();
+            cx.observe(&settings, |_, _, cx| {
+                cx.notify();
+            })
+            .detach();
+
+            let show_equalizer = cx.new(|_| false);
+
+            Self {
+                info,
+                show_queue,
+                volume_focus: cx.focus_handle(),
+                volume_hovered: false,
+                dragged_volume: None,
+                volume_sent_at: None,
+                scroll_remainder: 0.0,
+                loop_start: None,
+                equalizer: EqualizerPanel::new(cx, show_equalizer.clone()),
+                show_equalizer,
+            }
         })
     }
+
+    fn drag_volume(&mut self, volume: f64, cx: &mut Context<Self>) {
+        self.dragged_volume = Some(volume);
+
+        if self
+            .volume_sent_at
+            .is_none_or(|sent_at| sent_at.elapsed() >= VOLUME_DRAG_INTERVAL)
+        {
+            cx.global::<PlaybackInterface>().set_volume(volume);
+            self.volume_sent_at = Some(Instant::now());
+        }
+
+        cx.notify();
+    }
+
+    fn finish_volume_drag(&mut self, cx: &mut Context<Self>) {
+        if let Some(volume) = self.dragged_volume.take() {
+            cx.global::<PlaybackInterface>().set_volume(volume);
+        }
+
+        self.volume_sent_at = None;
+        cx.notify();
+    }
+
+    /// Moves the volume by `step`. The playback thread adds the step to its own volume, so quick
+    /// steps in a row add up even before the slider has caught up.
+    fn step_volume(&mut self, step: f64, cx: &mut Context<Self>) {
+        let interface = cx.global::<PlaybackInterface>();
+
+        if step >= 0.0 {
+            interface.volume_up(step);
+        } else {
+            interface.volume_down(-step);
+        }
+    }
+
+    fn scroll_volume(&mut self, ev: &ScrollWheelEvent, cx: &mut Context<Self>) {
+        // some platforms turn vertical scrolling sideways while shift is held
+        let steps = match ev.delta {
+            ScrollDelta::Lines(lines) => {
+                let y = if lines.y != 0.0 { lines.y } else { lines.x };
+
+                // a notch of the wheel is always at least one step
+                if y == 0.0 {
+                    0.0
+                } else {
+                    y.signum() * y.abs().round().max(1.0)
+                }
+            }
+            ScrollDelta::Pixels(pixels) => {
+                let y = if pixels.y != px(0.0) {
+                    pixels.y
+                } else {
+                    pixels.x
+                };
+                self.scroll_remainder += f32::from(y);
+
+                let steps = (self.scroll_remainder / PIXELS_PER_VOLUME_STEP).trunc();
+                self.scroll_remainder -= steps * PIXELS_PER_VOLUME_STEP;
+                steps
+            }
+        };
+
+        if steps == 0.0 {
+            return;
+        }
+
+        let step = if ev.modifiers.shift {
+            VOLUME_SCROLL_STEP_LARGE
+        } else {
+            VOLUME_SCROLL_STEP
+        };
+
+        self.step_volume(step * steps as f64, cx);
+    }
+
+    /// Marks the start of the section to repeat at the current position, then its end, and then
+    /// stops repeating it.
+    fn mark_loop(&mut self, cx: &mut Context<Self>) {
+        let position = *self.info.position.read(cx);
+        let playback = cx.global::<PlaybackInterface>();
+
+        if self.info.loop_region.read(cx).is_some() {
+            playback.set_loop_region(None);
+        } else if let Some(start) = self.loop_start.filter(|start| position > *start) {
+            playback.set_loop_region(Some((start as f64 / 1000.0, position as f64 / 1000.0)));
+            self.loop_start = None;
+        } else {
+            self.loop_start = Some(position);
+        }
+
+        cx.notify();
+    }
+
+    fn reset_volume(&mut self, cx: &mut Context<Self>) {
+        let default = cx
+            .global::<SettingsGlobal>()
+            .model
+            .read(cx)
+            .playback
+            .default_volume
+            .min(100);
+
+        self.dragged_volume = None;
+        self.volume_sent_at = None;
+        cx.global::<PlaybackInterface>()
+            .set_volume(default as f64 / 100.0);
+    }
 }
 
 impl Render for SecondaryControls {
-    fn render(&mut self, _: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+    fn render(&mut self, window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
         let theme = cx.global::<Theme>();
         let volume = *self.info.volume.read(cx);
+        let slider_volume = self.dragged_volume.unwrap_or(volume);
+        let show_readout = self.volume_hovered
+            || self.dragged_volume.is_some()
+            || self.volume_focus.is_focused(window);
+        let this = cx.entity().downgrade();
+        let this_release = this.clone();
+        let this_reset = this.clone();
         let prev_volume = *self.info.prev_volume.read(cx);
+        let muted = *self.info.muted.read(cx);
         let show_queue = self.show_queue.clone();
+        let playback_settings = &cx.global::<SettingsGlobal>().model.read(cx).playback;
+        let crossfeed = playback_settings.crossfeed;
+        let equalizer_on = !playback_settings.equalizer.is_flat();
+        let show_equalizer = self.show_equalizer.clone();
+
+        let speed = *self.info.speed.read(cx);
+        let looping = self.info.loop_region.read(cx).is_some();
+        let marking_loop = self.loop_start.is_some();
+        let sleep_timer = describe_sleep_timer(*self.info.sleep_timer.read(cx));
+        let stop_after_current = *self.info.stop_after_current.read(cx);
+        // only shown in exclusive mode, where playing bit-perfect is the point
+        let has_track = self.info.current_track.read(cx).is_some();
+        let bit_perfect =
+            (playback_settings.exclusive && has_track).then(|| *self.info.bit_perfect.read(cx));
+        // only shown once there's been one, to explain crackles
+        let underruns = Some(*self.info.underruns.read(cx)).filter(|count| *count > 0);
+
+        let mut speed_menu = menu();
+        for preset in SPEED_PRESETS {
+            speed_menu = speed_menu.item(menu_check_item(
+                SharedString::from(format!("speed-{preset}")),
+                preset == speed,
+                format!("{preset}×"),
+                move |_, _, cx| cx.global::<PlaybackInterface>().set_speed(preset),
+            ));
+        }
+
+        let mut crossfeed_menu = menu();
+        for level in CrossfeedLevel::ALL {
+            crossfeed_menu = crossfeed_menu.item(menu_check_item(
+                SharedString::from(format!("crossfeed-{}", level.label())),
+                level == crossfeed,
+                level.label(),
+                move |_, _, cx| set_crossfeed(cx, level),
+            ));
+        }
 
         div().px(px(18.0)).flex().child(
             div()
@@ -577,36 +1032,233 @@ impl Render for SecondaryControls {
                 .my_auto()
                 .pb(px(2.0))
                 .gap(px(8.0))
+                .when_some(bit_perfect, |this, bit_perfect| {
+                    this.child(
+                        div()
+                            .px(px(4.0))
+                            .h(px(25.0))
+                            .mt(px(2.0))
+                            .flex()
+                            .items_center()
+                            .text_xs()
+                            .when(bit_perfect, |this| {
+                                this.text_color(theme.playback_button_toggled)
+                                    .child("Bit-perfect")
+                            })
+                            .when(!bit_perfect, |this| {
+                                this.text_color(theme.text_secondary).child("Resampled")
+                            }),
+                    )
+                })
+                .when_some(underruns, |this, count| {
+                    this.child(
+                        div()
+                            .px(px(4.0))
+                            .h(px(25.0))
+                            .mt(px(2.0))
+                            .flex()
+                            .items_center()
+                            .text_xs()
+                            .text_color(theme.text_secondary)
+                            .child(if count == 1 {
+                                "1 dropout".to_string()
+                            } else {
+                                format!("{count} dropouts")
+                            }),
+                    )
+                })
+                .when(stop_after_current, |this| {
+                    this.child(
+                        indicator_button("stop-after-current-button", theme)
+                            // clicking keeps playing after the current track after all
+                            .on_click(|_, _, cx| {
+                                cx.global::<PlaybackInterface>().toggle_stop_after_current()
+                            })
+                            .child("Stop after track"),
+                    )
+                })
+                .when_some(sleep_timer, |this, sleep_timer| {
+                    this.child(
+                        indicator_button("sleep-timer-button", theme)
+                            // clicking cancels the timer
+                            .on_click(|_, _, cx| {
+                                cx.global::<PlaybackInterface>()
+                                    .set_sleep_timer(SleepTimer::Off)
+                            })
+                            .child(sleep_timer),
+                    )
+                })
                 .child(
                     div()
                         .rounded(px(3.0))
-                        .w(px(28.0))
+                        .min_w(px(28.0))
+                        .px(px(4.0))
                         .h(px(25.0))
                         .mt(px(2.0))
                         .flex()
                         .items_center()
                         .justify_center()
+                        .text_xs()
                         .border_color(theme.playback_button_border)
-                        .id("volume-button")
+                        .id("loop-button")
                         .cursor_pointer()
                         .bg(theme.playback_button)
                         .hover(|this| this.bg(theme.playback_button_hover))
                         .active(|this| this.bg(theme.playback_button_active))
-                        .when(volume <= 0.0, |div| {
-                            div.child(icon(VOLUME_OFF).size(px(14.0)))
+                        .when(looping || marking_loop, |this| {
+                            this.text_color(theme.playback_button_toggled)
+                        })
+                        .on_click(cx.listener(|this, _, _, cx| this.mark_loop(cx)))
+                        .child(if marking_loop { "A–" } else { "A–B" }),
+                )
+                .child(
+                    context("speed-context")
+                        .with(
+                            div()
+                                .rounded(px(3.0))
+                                .min_w(px(28.0))
+                                .px(px(4.0))
+                                .h(px(25.0))
+                                .mt(px(2.0))
+                                .flex()
+                                .items_center()
+                                .justify_center()
+                                .text_xs()
+                                .border_color(theme.playback_button_border)
+                                .id("speed-button")
+                                .cursor_pointer()
+                                .bg(theme.playback_button)
+                                .hover(|this| this.bg(theme.playback_button_hover))
+                                .active(|this| this.bg(theme.playback_button_active))
+                                .when(speed != 1.0, |this| {
+                                    this.text_color(theme.playback_button_toggled)
+                                })
+                                // right click to pick the speed
                                 .on_click(move |_, _, cx| {
-                                    cx.global::<PlaybackInterface>().set_volume(prev_volume);
+                                    cx.global::<PlaybackInterface>()
+                                        .set_speed(next_speed(speed))
                                 })
-                        })
-                        .when(volume > 0.0, |div| {
-                            div.child(icon(VOLUME).size(px(14.0)))
+                                .child(format!("{speed}×")),
+                        )
+                        .child(div().bg(theme.elevated_background).child(speed_menu)),
+                )
+                .child(
+                    context("crossfeed-context")
+                        .with(
+                            div()
+                                .rounded(px(3.0))
+                                .w(px(28.0))
+                                .h(px(25.0))
+                                .mt(px(2.0))
+                                .flex()
+                                .items_center()
+                                .justify_center()
+                                .border_color(theme.playback_button_border)
+                                .id("crossfeed-button")
+                                .cursor_pointer()
+                                .bg(theme.playback_button)
+                                .hover(|this| this.bg(theme.playback_button_hover))
+                                .active(|this| this.bg(theme.playback_button_active))
+                                // right click to pick the level
                                 .on_click(move |_, _, cx| {
-                                    cx.global::<PlaybackInterface>().set_volume(0 as f64);
+                                    set_crossfeed(
+                                        cx,
+                                        if crossfeed == CrossfeedLevel::Off {
+                                            CrossfeedLevel::Medium
+                                        } else {
+                                            CrossfeedLevel::Off
+                                        },
+                                    )
                                 })
+                                .child(
+                                    icon(HEADPHONES)
+                                        .size(px(14.0))
+                                        .when(crossfeed != CrossfeedLevel::Off, |this| {
+                                            this.text_color(theme.playback_button_toggled)
+                                        }),
+                                ),
+                        )
+                        .child(div().bg(theme.elevated_background).child(crossfeed_menu)),
+                )
+                .child(
+                    div()
+                        .rounded(px(3.0))
+                        .min_w(px(28.0))
+                        .px(px(4.0))
+                        .h(px(25.0))
+                        .mt(px(2.0))
+                        .flex()
+                        .items_center()
+                        .justify_center()
+                        .text_xs()
+                        .border_color(theme.playback_button_border)
+                        .id("equalizer-button")
+                        .cursor_pointer()
+                        .bg(theme.playback_button)
+                        .hover(|this| this.bg(theme.playback_button_hover))
+                        .active(|this| this.bg(theme.playback_button_active))
+                        .when(equalizer_on, |this| {
+                            this.text_color(theme.playback_button_toggled)
+                        })
+                        .on_click(move |_, _, cx| show_equalizer.write(cx, true))
+                        .child("EQ"),
+                )
+                .child(
+                    div()
+                        .rounded(px(3.0))
+                        .w(px(28.0))
+                        .h(px(25.0))
+                        .mt(px(2.0))
+                        .flex()
+                        .items_center()
+                        .justify_center()
+                        .border_color(theme.playback_button_border)
+                        .id("volume-button")
+                        .cursor_pointer()
+                        .bg(theme.playback_button)
+                        .hover(|this| this.bg(theme.playback_button_hover))
+                        .active(|this| this.bg(theme.playback_button_active))
+                        .child(
+                            icon(if muted || volume <= 0.0 {
+                                VOLUME_OFF
+                            } else {
+                                VOLUME
+                            })
+                            .size(px(14.0)),
+                        )
+                        .on_click(move |_, _, cx| {
+                            let playback = cx.global::<PlaybackInterface>();
+
+                            // a volume that was turned all the way down can't be unmuted
+                            if volume <= 0.0 && !muted {
+                                playback.set_volume(prev_volume);
+                            } else {
+                                playback.toggle_mute();
+                            }
                         }),
                 )
                 .child(
                     div()
+                        .id("volume-control")
+                        .flex()
+                        .gap(px(8.0))
+                        .track_focus(&self.volume_focus)
+                        .on_hover(cx.listener(|this, hovered: &bool, _, cx| {
+                            this.volume_hovered = *hovered;
+                            cx.notify();
+                        }))
+                        .on_key_down(cx.listener(|this, ev: &KeyDownEvent, _, cx| {
+                            match ev.keystroke.key.as_str() {
+                                "up" => this.step_volume(VOLUME_KEY_STEP, cx),
+                                "down" => this.step_volume(-VOLUME_KEY_STEP, cx),
+                                _ => return,
+                            }
+
+                            cx.stop_propagation();
+                        }))
+                        .on_scroll_wheel(cx.listener(|this, ev: &ScrollWheelEvent, _, cx| {
+                            this.scroll_volume(ev, cx);
+                        }))
                         .child(
                             slider()
                                 .w(px(80.0))
@@ -614,18 +1266,32 @@ impl Render for SecondaryControls {
                                 .mt(px(11.0))
                                 .rounded(px(3.0))
                                 .id("volume")
-                                .value((volume) as f32)
-                                .on_change(move |v, _, cx| {
-                                    cx.global::<PlaybackInterface>().set_volume(v as f64);
+                                .value(slider_volume as f32)
+                                .on_change(move |v, window, cx| {
+                                    this.update(cx, |this, cx| {
+                                        // the slider stops the click from focusing it
+                                        this.volume_focus.focus(window, cx);
+                                        this.drag_volume(v as f64, cx);
+                                    })
+                                    .ok();
+                                })
+                                .on_release(move |_, cx| {
+                                    this_release
+                                        .update(cx, |this, cx| this.finish_volume_drag(cx))
+                                        .ok();
+                                })
+                                .on_double_click(move |_, cx| {
+                                    this_reset.update(cx, |this, cx| this.reset_volume(cx)).ok();
                                 }),
                         )
-                        .on_scroll_wheel(move |ev, _, cx| {
-                            let delta: f64 = ev.delta.pixel_delta(px(0.01666666)).y.into();
-                            cx.global::<PlaybackInterface>().set_volume(f64::clamp(
-                                volume + delta,
-                                0_f64,
-                                1_f64,
-                            ));
+                        .when(show_readout, |this| {
+                            this.child(
+                                div()
+                                    .mt(px(5.0))
+                                    .text_xs()
+                                    .text_color(theme.text_secondary)
+                                    .child(describe_volume(slider_volume)),
+                            )
                         }),
                 )
                 .child(
@@ -650,7 +1316,8 @@ impl Render for SecondaryControls {
                                 cx.notify();
                             })
                         }),
-                ),
+                )
+                .child(self.equalizer.clone()),
         )
     }
 }
@@ -0,0 +1,136 @@
+use std::time::{Duration, Instant};
+
+/// How long position updates keep being ignored after the scrubber is released (or after the
+/// last seek was issued), if the playback thread hasn't confirmed the seek by then.
+const GRACE_PERIOD: Duration = Duration::from_millis(750);
+
+/// Decides which position the scrubber shows while the user is seeking.
+///
+/// Seeking takes a moment to land, and until it does the playback thread keeps reporting the old
+/// position. Showing those positions would make the scrubber jump back and forth while dragging,
+/// and snap back briefly after releasing. Instead, the position the user asked for is shown until
+/// the drag is over and every seek issued during it has been completed - or until the grace
+/// period runs out, in case a completion never arrives.
+#[derive(Debug, Default)]
+pub struct SeekSuppression {
+    dragging: bool,
+    in_flight: usize,
+    target: Option<u64>,
+    expires: Option<Instant>,
+}
+
+impl SeekSuppression {
+    /// Call when a seek to `position` has been sent to the playback thread from the scrubber.
+    pub fn seek_requested(&mut self, position: u64, now: Instant) {
+        self.dragging = true;
+        self.in_flight += 1;
+        self.target = Some(position);
+        self.expires = Some(now + GRACE_PERIOD);
+    }
+
+    /// Call when the scrubber has been released.
+    pub fn drag_ended(&mut self, now: Instant) {
+        if self.dragging {
+            self.dragging = false;
+            self.expires = Some(now + GRACE_PERIOD);
+            self.finish_if_settled();
+        }
+    }
+
+    /// Call when the playback thread reports that a seek has finished, successfully or not.
+    pub fn seek_completed(&mut self) {
+        self.in_flight = self.in_flight.saturating_sub(1);
+        self.finish_if_settled();
+    }
+
+    fn finish_if_settled(&mut self) {
+        if !self.dragging && self.in_flight == 0 {
+            self.target = None;
+            self.expires = None;
+        }
+    }
+
+    pub fn is_suppressed(&self, now: Instant) -> bool {
+        self.target.is_some() && (self.dragging || self.expires.is_some_and(|e| now < e))
+    }
+
+    /// Returns the position that should be displayed, given the position last reported by the
+    /// playback thread.
+    pub fn position(&self, reported: u64, now: Instant) -> u64 {
+        match self.target {
+            Some(target) if self.is_suppressed(now) => target,
+            _ => reported,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shows_the_target_while_dragging() {
+        let now = Instant::now();
+        let mut seek = SeekSuppression::default();
+        assert_eq!(seek.position(10_000, now), 10_000);
+
+        seek.seek_requested(60_000, now);
+        assert!(seek.is_suppressed(now));
+        assert_eq!(seek.position(10_000, now), 60_000);
+
+        // the grace period doesn't apply until the scrubber is released
+        let later = now + GRACE_PERIOD * 2;
+        assert_eq!(seek.position(10_500, later), 60_000);
+    }
+
+    #[test]
+    fn follows_the_latest_seek() {
+        let now = Instant::now();
+        let mut seek = SeekSuppression::default();
+
+        seek.seek_requested(60_000, now);
+        seek.seek_requested(90_000, now);
+        seek.seek_completed();
+        assert_eq!(seek.position(60_000, now), 90_000);
+    }
+
+    #[test]
+    fn releases_once_every_seek_has_completed() {
+        let now = Instant::now();
+        let mut seek = SeekSuppression::default();
+
+        seek.seek_requested(60_000, now);
+        seek.seek_requested(90_000, now);
+        seek.drag_ended(now);
+        seek.seek_completed();
+        assert_eq!(seek.position(60_000, now), 90_000);
+
+        seek.seek_completed();
+        assert!(!seek.is_suppressed(now));
+        assert_eq!(seek.position(90_000, now), 90_000);
+    }
+
+    #[test]
+    fn releases_once_the_grace_period_runs_out() {
+        let now = Instant::now();
+        let mut seek = SeekSuppression::default();
+
+        seek.seek_requested(60_000, now);
+        seek.drag_ended(now);
+        assert_eq!(seek.position(10_000, now + GRACE_PERIOD / 2), 60_000);
+        assert_eq!(seek.position(10_000, now + GRACE_PERIOD), 10_000);
+    }
+
+    #[test]
+    fn ignores_completions_it_did_not_ask_for() {
+        let now = Instant::now();
+        let mut seek = SeekSuppression::default();
+
+        // a seek from the keyboard completes without the scrubber being involved
+        seek.seek_completed();
+        assert!(!seek.is_suppressed(now));
+
+        seek.seek_requested(60_000, now);
+        assert_eq!(seek.position(10_000, now), 60_000);
+    }
+}
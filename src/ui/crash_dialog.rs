@@ -0,0 +1,112 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use gpui::{
+    App, ClipboardItem, FontWeight, IntoElement, ParentElement, RenderOnce, Styled, Window, div, px,
+};
+use tracing::error;
+
+use crate::crash::crash_dir;
+
+use super::{
+    components::{
+        button::{ButtonIntent, button},
+        modal::modal,
+    },
+    models::Models,
+    theme::Theme,
+    toasts::show_toast,
+    util::ui_px,
+};
+
+fn close(cx: &mut App) {
+    let crash_report = cx.global::<Models>().crash_report.clone();
+    crash_report.write(cx, None);
+}
+
+fn open_crash_folder(cx: &mut App) {
+    if let Err(err) = open::that(crash_dir()) {
+        error!(?err, "Couldn't open the crash report folder: {err}");
+        show_toast(cx, "The crash report folder couldn't be opened.");
+    }
+}
+
+fn copy_report(cx: &mut App, report: &Path) {
+    match fs::read_to_string(report) {
+        Ok(contents) => {
+            cx.write_to_clipboard(ClipboardItem::new_string(contents));
+            show_toast(cx, "The crash report was copied to the clipboard.");
+        }
+        Err(err) => {
+            error!(?err, "Couldn't read the crash report: {err}");
+            show_toast(cx, "The crash report couldn't be read.");
+        }
+    }
+}
+
+/// Tells the user that Hummingbird closed unexpectedly the last time it ran, and offers to open
+/// the folder crash reports are kept in, or to copy the report so that it can be shared. Reports
+/// are never sent anywhere.
+#[derive(IntoElement)]
+pub struct CrashDialog {
+    report: PathBuf,
+}
+
+impl RenderOnce for CrashDialog {
+    fn render(self, _: &mut Window, cx: &mut App) -> impl IntoElement {
+        let theme = cx.global::<Theme>();
+        let report = self.report;
+
+        modal().on_exit(|_, cx| close(cx)).child(
+            div()
+                .w(px(450.0))
+                .p(px(20.0))
+                .flex()
+                .flex_col()
+                .gap(px(8.0))
+                .child(
+                    div()
+                        .font_weight(FontWeight::BOLD)
+                        .text_size(ui_px(20.0, cx))
+                        .child("Hummingbird closed unexpectedly last time"),
+                )
+                .child(div().text_sm().text_color(theme.text_secondary).child(
+                    "A crash report was saved on this computer. It hasn't been sent \
+                            anywhere, but sharing it along with a bug report helps track down \
+                            what went wrong.",
+                ))
+                .child(
+                    div()
+                        .flex()
+                        .justify_end()
+                        .gap(px(8.0))
+                        .mt(px(8.0))
+                        .child(
+                            button()
+                                .id("crash-open-folder")
+                                .child("Open Folder")
+                                .on_click(|_, _, cx| open_crash_folder(cx)),
+                        )
+                        .child(
+                            button()
+                                .id("crash-copy-report")
+                                .child("Copy Report")
+                                .on_click(move |_, _, cx| copy_report(cx, &report)),
+                        )
+                        .child(
+                            button()
+                                .id("crash-close")
+                                .intent(ButtonIntent::Primary)
+                                .child("Close")
+                                .on_click(|_, _, cx| close(cx)),
+                        ),
+                ),
+        )
+    }
+}
+
+pub fn crash_dialog(report: PathBuf) -> CrashDialog {
+    CrashDialog { report }
+}
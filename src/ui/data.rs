@@ -3,30 +3,45 @@ use std::{
     io::Cursor,
     path::{Path, PathBuf},
     sync::{Arc, LazyLock},
+    time::SystemTime,
 };
 
 use futures::TryFutureExt as _;
-use gpui::{App, Entity, RenderImage, Task};
+use gpui::{App, Entity, RenderImage, SharedString, Task};
 use image::{Frame, ImageReader, imageops::thumbnail};
 use moka::sync::Cache;
 use rustc_hash::FxHasher;
 use smallvec::smallvec;
+use tokio::sync::Semaphore;
 use tracing::{debug, error, trace_span, warn};
 
 use crate::{
-    media::{builtin::symphonia::SymphoniaProvider, metadata::Metadata, traits::MediaProvider},
+    media::{
+        builtin::symphonia::SymphoniaProvider,
+        metadata::Metadata,
+        source::{open_source, source_modified},
+        traits::MediaProvider,
+    },
     playback::queue::{DataSource, QueueItemUIData},
-    util::rgb_to_bgr,
+    util::{limit_art_decoding, rgb_to_bgr},
 };
 
 static ALBUM_CACHE: LazyLock<Cache<u64, Arc<RenderImage>>> = LazyLock::new(|| Cache::new(30));
 
+/// Metadata read from files, keyed by path and modification time, so that files that aren't in
+/// the library don't have to be read again every time they're shown in the queue.
+static METADATA_CACHE: LazyLock<Cache<(PathBuf, SystemTime), QueueItemUIData>> =
+    LazyLock::new(|| Cache::new(500));
+
+/// Limits how many files have their metadata read at once. Dropping a large folder onto the queue
+/// would otherwise try to open every file in it at the same time.
+static METADATA_READS: LazyLock<Semaphore> = LazyLock::new(|| Semaphore::new(4));
+
 #[tracing::instrument(level = "trace", skip(data))]
-fn decode_image(data: Box<[u8]>, thumb: bool) -> anyhow::Result<Arc<RenderImage>> {
-    let mut image = ImageReader::new(Cursor::new(data))
-        .with_guessed_format()?
-        .decode()?
-        .into_rgba8();
+pub fn decode_image(data: Box<[u8]>, thumb: bool) -> anyhow::Result<Arc<RenderImage>> {
+    let mut reader = ImageReader::new(Cursor::new(data)).with_guessed_format()?;
+    limit_art_decoding(&mut reader);
+    let mut image = reader.decode()?.into_rgba8();
 
     rgb_to_bgr(&mut image);
 
@@ -41,16 +56,17 @@ fn decode_image(data: Box<[u8]>, thumb: bool) -> anyhow::Result<Arc<RenderImage>
 
 #[tracing::instrument(level = "trace")]
 fn read_metadata(path: &Path) -> anyhow::Result<QueueItemUIData> {
-    let file = std::fs::File::open(path)?;
+    let source = open_source(path)?;
 
     // TODO: Switch to a different media provider based on the file
-    let mut stream = SymphoniaProvider.open(file, None)?;
+    let mut stream = SymphoniaProvider.open(source, path.extension())?;
     stream.start_playback()?;
 
     let Metadata { name, artist, .. } = stream.read_metadata()?;
     let mut ui_data = QueueItemUIData {
         name: name.as_ref().map(Into::into),
         artist_name: artist.as_ref().map(Into::into),
+        duration: stream.duration_millis().ok(),
         source: DataSource::Metadata,
         image: None,
     };
@@ -79,6 +95,53 @@ fn read_metadata(path: &Path) -> anyhow::Result<QueueItemUIData> {
     Ok(ui_data)
 }
 
+fn read_metadata_cached(path: &Path) -> anyhow::Result<QueueItemUIData> {
+    let key = (path.to_path_buf(), source_modified(path)?);
+
+    if let Some(data) = METADATA_CACHE.get(&key) {
+        return Ok(data);
+    }
+
+    let data = read_metadata(path)?;
+    METADATA_CACHE.insert(key, data.clone());
+
+    Ok(data)
+}
+
+/// Guesses the name (and possibly the artist) of a track from its file name, for when the file's
+/// metadata can't be read. "01 - Artist - Track_Name.mp3" becomes "Track Name" by "Artist".
+pub fn metadata_from_file_name(path: &Path) -> QueueItemUIData {
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().replace('_', " "))
+        .unwrap_or_default();
+
+    // strip leading track numbers, but only if they're followed by something else
+    let without_number = stem
+        .trim_start_matches(|c: char| c.is_ascii_digit())
+        .trim_start_matches(['.', '-', ' ']);
+    let cleaned = if without_number.len() < stem.len() && !without_number.is_empty() {
+        without_number.trim()
+    } else {
+        stem.trim()
+    };
+
+    let (artist_name, name) = match cleaned.split_once(" - ") {
+        Some((artist, name)) if !artist.trim().is_empty() && !name.trim().is_empty() => {
+            (Some(artist.trim()), name.trim())
+        }
+        _ => (None, cleaned),
+    };
+
+    QueueItemUIData {
+        image: None,
+        name: (!name.is_empty()).then(|| SharedString::from(name.to_string())),
+        artist_name: artist_name.map(|artist| SharedString::from(artist.to_string())),
+        duration: None,
+        source: DataSource::Metadata,
+    }
+}
+
 pub trait Decode {
     fn decode_image(
         &self,
@@ -113,16 +176,26 @@ impl Decode for App {
     fn read_metadata(&self, path: PathBuf, entity: Entity<Option<QueueItemUIData>>) -> Task<()> {
         self.spawn(async move |cx| {
             let span = trace_span!("read_metadata_outer", path = %path.display());
-            let task = crate::RUNTIME.spawn_blocking(move || read_metadata(&path));
-            match task.err_into().await.flatten() {
-                Err(err) => error!(parent: span, ?err, "Failed to read metadata: {err}"),
-                Ok(metadata) => entity
-                    .update(cx, |m, cx| {
-                        *m = Some(metadata);
-                        cx.notify();
-                    })
-                    .expect("Failed to update metadata entity"),
-            }
+            let file_path = path.clone();
+            let task = crate::RUNTIME.spawn(async move {
+                let _permit = METADATA_READS.acquire().await?;
+                tokio::task::spawn_blocking(move || read_metadata_cached(&path)).await?
+            });
+
+            let metadata = match task.err_into().await.flatten() {
+                Ok(metadata) => metadata,
+                Err(err) => {
+                    warn!(parent: &span, ?err, "Failed to read metadata: {err}");
+                    metadata_from_file_name(&file_path)
+                }
+            };
+
+            entity
+                .update(cx, |m, cx| {
+                    *m = Some(metadata);
+                    cx.notify();
+                })
+                .expect("Failed to update metadata entity");
         })
     }
 }
@@ -0,0 +1,185 @@
+use std::sync::Arc;
+
+use gpui::{
+    App, AppContext, Context, Entity, IntoElement, ParentElement, Render, SharedString, Styled,
+    Window, div, px,
+};
+use nucleo::Utf32String;
+
+use crate::{
+    devices::traits::DeviceInfo,
+    playback::interface::PlaybackInterface,
+    settings::{SettingsGlobal, update_settings},
+    ui::{
+        components::{
+            modal::modal,
+            palette::{AcceptIntent, FinderItemLeft, Palette, PaletteItem},
+        },
+        models::PlaybackInfo,
+    },
+};
+
+#[derive(PartialEq)]
+pub struct DeviceChoice {
+    name: SharedString,
+    /// The device's UID, or None for the system's default device.
+    uid: Option<String>,
+    current: bool,
+    /// Whether the device was chosen before, but isn't connected now.
+    missing: bool,
+}
+
+impl DeviceChoice {
+    /// Lists the system's default device, followed by each device the playback thread found. The
+    /// chosen device is listed even if it isn't connected, so that it's clear why the default
+    /// device is being used instead.
+    fn all(cx: &App, devices: &[DeviceInfo]) -> Vec<Arc<DeviceChoice>> {
+        let chosen = cx
+            .global::<SettingsGlobal>()
+            .model
+            .read(cx)
+            .playback
+            .output_device
+            .clone();
+
+        let mut choices = vec![Arc::new(DeviceChoice {
+            name: "System Default".into(),
+            uid: None,
+            current: chosen.is_none(),
+            missing: false,
+        })];
+
+        choices.extend(devices.iter().map(|device| {
+            Arc::new(DeviceChoice {
+                name: device.name.clone().into(),
+                current: chosen.as_ref() == Some(&device.uid),
+                uid: Some(device.uid.clone()),
+                missing: false,
+            })
+        }));
+
+        if let Some(uid) = chosen
+            && !devices.iter().any(|device| device.uid == uid)
+        {
+            choices.push(Arc::new(DeviceChoice {
+                name: uid.clone().into(),
+                uid: Some(uid),
+                current: true,
+                missing: true,
+            }));
+        }
+
+        choices
+    }
+}
+
+impl PaletteItem for DeviceChoice {
+    fn left_content(&self, _: &mut App) -> Option<FinderItemLeft> {
+        None
+    }
+
+    fn middle_content(&self, _: &mut App) -> SharedString {
+        self.name.clone()
+    }
+
+    fn right_content(&self, _: &mut App) -> Option<SharedString> {
+        if self.missing {
+            Some("Not Connected".into())
+        } else if self.current {
+            Some("Current".into())
+        } else {
+            None
+        }
+    }
+}
+
+type MatcherFunc = Box<dyn Fn(&Arc<DeviceChoice>, &mut App) -> Utf32String + 'static>;
+type OnAccept = Box<dyn Fn(&Arc<DeviceChoice>, AcceptIntent, &mut App) + 'static>;
+
+/// A dialog for choosing the device audio is played through.
+pub struct DevicePicker {
+    show: Entity<bool>,
+    palette: Entity<Palette<DeviceChoice, MatcherFunc, OnAccept>>,
+}
+
+impl DevicePicker {
+    pub fn new(cx: &mut App, show: Entity<bool>) -> Entity<Self> {
+        cx.new(|cx| {
+            let devices = cx.global::<PlaybackInfo>().devices.clone();
+
+            // devices can be connected while the app is running, so the playback thread is asked
+            // for them every time the picker is opened
+            cx.observe(&show, |this: &mut Self, show, cx| {
+                let choices = if *show.read(cx) {
+                    cx.global::<PlaybackInterface>().list_devices();
+
+                    let devices = cx.global::<PlaybackInfo>().devices.read(cx).clone();
+                    DeviceChoice::all(cx, &devices)
+                } else {
+                    Vec::new()
+                };
+
+                this.palette.update(cx, |this, cx| {
+                    cx.emit(choices);
+                    this.reset(cx);
+                });
+
+                cx.notify();
+            })
+            .detach();
+
+            cx.observe(&devices, |this: &mut Self, devices, cx| {
+                if !*this.show.read(cx) {
+                    return;
+                }
+
+                let choices = DeviceChoice::all(cx, devices.read(cx));
+                this.palette.update(cx, |_, cx| cx.emit(choices));
+            })
+            .detach();
+
+            let matcher: MatcherFunc = Box::new(|choice, _| choice.name.to_string().into());
+
+            let show_clone = show.clone();
+
+            let on_accept: OnAccept = Box::new(move |choice, _, cx| {
+                let uid = choice.uid.clone();
+                update_settings(cx, |settings| settings.playback.output_device = uid);
+                show_clone.write(cx, false);
+            });
+
+            let palette = Palette::new(cx, Vec::new(), matcher, on_accept, &show);
+
+            Self { show, palette }
+        })
+    }
+}
+
+impl Render for DevicePicker {
+    fn render(&mut self, window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let show = self.show.clone();
+        let palette = self.palette.clone();
+        let show_read = *self.show.read(cx);
+
+        if show_read {
+            cx.update_entity(&palette, |palette, cx| {
+                palette.focus(window, cx);
+            });
+
+            modal()
+                .child(div().w(px(550.0)).h(px(300.0)).child(palette.clone()))
+                .on_exit(move |_, cx| {
+                    show.update(cx, |show, cx| {
+                        *show = false;
+                        cx.update_entity(&palette, |palette, cx| {
+                            palette.reset(cx);
+                        });
+                        cx.notify();
+                    })
+                })
+                .into_any_element()
+        } else {
+            div().into_any_element()
+        }
+    }
+}
@@ -0,0 +1,306 @@
+use gpui::*;
+use prelude::FluentBuilder;
+
+use crate::settings::{
+    SettingsGlobal,
+    playback::{EQ_BANDS, EqPreset, EqSettings, MAX_EQ_GAIN},
+    update_settings,
+};
+
+use super::{
+    components::{
+        button::{ButtonIntent, button},
+        checkbox::checkbox,
+        modal::modal,
+        slider::slider,
+    },
+    models::PlaybackInfo,
+    theme::Theme,
+    util::ui_px,
+};
+
+/// How finely the gains can be set with the sliders, in decibels.
+const GAIN_STEP: f32 = 0.5;
+
+/// How finely the balance can be set with its slider.
+const BALANCE_STEP: f64 = 0.05;
+
+/// Which of the equalizer's sliders is being changed.
+#[derive(Clone, Copy)]
+enum Control {
+    Preamp,
+    Band(usize),
+}
+
+fn update_equalizer(cx: &mut App, update: impl FnOnce(&mut EqSettings)) {
+    let current = cx
+        .global::<SettingsGlobal>()
+        .model
+        .read(cx)
+        .playback
+        .equalizer;
+    let mut equalizer = current;
+    update(&mut equalizer);
+
+    // dragging a slider reports every move, so the settings are only written when a gain changes
+    if equalizer != current {
+        update_settings(cx, |settings| settings.playback.equalizer = equalizer);
+    }
+}
+
+fn set_gain(cx: &mut App, control: Control, gain: f32) {
+    update_equalizer(cx, |equalizer| match control {
+        Control::Preamp => equalizer.preamp = gain,
+        Control::Band(band) => equalizer.gains[band] = gain,
+    });
+}
+
+/// Converts a slider position (from 0 to 1) to a gain, rounded to [GAIN_STEP].
+fn gain_for_position(position: f32) -> f32 {
+    let gain = (position * 2.0 - 1.0) * MAX_EQ_GAIN;
+    ((gain / GAIN_STEP).round() * GAIN_STEP).clamp(-MAX_EQ_GAIN, MAX_EQ_GAIN)
+}
+
+fn position_for_gain(gain: f32) -> f32 {
+    (gain / MAX_EQ_GAIN + 1.0) / 2.0
+}
+
+fn set_balance(cx: &mut App, balance: f64) {
+    let settings = cx.global::<SettingsGlobal>().model.read(cx);
+
+    if balance != settings.playback.balance {
+        update_settings(cx, |settings| settings.playback.balance = balance);
+    }
+}
+
+/// Converts a slider position (from 0 to 1) to a balance, rounded to [BALANCE_STEP].
+fn balance_for_position(position: f32) -> f64 {
+    let balance = f64::from(position) * 2.0 - 1.0;
+    ((balance / BALANCE_STEP).round() * BALANCE_STEP).clamp(-1.0, 1.0)
+}
+
+/// Describes the balance as how far it's turned towards one side, like "L 30%".
+fn balance_label(balance: f64) -> String {
+    let percent = (balance.abs() * 100.0).round();
+
+    if percent == 0.0 {
+        "Center".to_string()
+    } else if balance < 0.0 {
+        format!("L {percent}%")
+    } else {
+        format!("R {percent}%")
+    }
+}
+
+fn band_label(frequency: f32) -> String {
+    if frequency >= 1000.0 {
+        format!("{} kHz", frequency / 1000.0)
+    } else {
+        format!("{frequency} Hz")
+    }
+}
+
+/// A row with a slider for the pre-amp or one of the bands. Double-clicking the slider sets it
+/// back to 0.
+fn gain_row(
+    id: impl Into<ElementId>,
+    label: impl Into<SharedString>,
+    control: Control,
+    gain: f32,
+    cx: &App,
+) -> impl IntoElement {
+    slider_row(
+        id,
+        label,
+        position_for_gain(gain),
+        format!("{gain:+} dB"),
+        move |position, cx| set_gain(cx, control, gain_for_position(position)),
+        move |cx| set_gain(cx, control, 0.0),
+        cx,
+    )
+}
+
+/// A labelled slider, with its value shown next to it. Double-clicking the slider resets it.
+fn slider_row(
+    id: impl Into<ElementId>,
+    label: impl Into<SharedString>,
+    position: f32,
+    value: String,
+    on_change: impl Fn(f32, &mut App) + 'static,
+    on_reset: impl Fn(&mut App) + 'static,
+    cx: &App,
+) -> impl IntoElement {
+    let theme = cx.global::<Theme>();
+
+    div()
+        .flex()
+        .items_center()
+        .gap(px(12.0))
+        .child(
+            div()
+                .w(px(64.0))
+                .text_sm()
+                .text_color(theme.text_secondary)
+                .child(label.into()),
+        )
+        .child(
+            slider()
+                .id(id)
+                .flex_grow()
+                .h(px(6.0))
+                .rounded(px(3.0))
+                .value(position)
+                .on_change(move |position, _, cx| on_change(position, cx))
+                .on_double_click(move |_, cx| on_reset(cx)),
+        )
+        .child(
+            div()
+                .w(px(64.0))
+                .text_sm()
+                .flex()
+                .justify_end()
+                .child(value),
+        )
+}
+
+/// A small panel for the graphic equalizer: turning it on and off, picking a preset, and setting
+/// the pre-amp and the gain of each band. The balance between the left and right channels is set
+/// here too. Changes are saved to the settings file, and are heard straight away.
+pub struct EqualizerPanel {
+    show: Entity<bool>,
+    balance: Entity<f64>,
+}
+
+impl EqualizerPanel {
+    pub fn new(cx: &mut App, show: Entity<bool>) -> Entity<Self> {
+        cx.new(|cx| {
+            cx.observe(&show, |_, _, cx| cx.notify()).detach();
+
+            let settings = cx.global::<SettingsGlobal>().model.clone();
+            cx.observe(&settings, |_, _, cx| cx.notify()).detach();
+
+            // the balance the playback thread is using, which is what the slider shows
+            let balance = cx.global::<PlaybackInfo>().balance.clone();
+            cx.observe(&balance, |_, _, cx| cx.notify()).detach();
+
+            Self { show, balance }
+        })
+    }
+}
+
+impl Render for EqualizerPanel {
+    fn render(&mut self, _: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        if !*self.show.read(cx) {
+            return div().into_any_element();
+        }
+
+        let theme = cx.global::<Theme>();
+        let equalizer = cx
+            .global::<SettingsGlobal>()
+            .model
+            .read(cx)
+            .playback
+            .equalizer;
+        let preset = equalizer.preset();
+        let balance = *self.balance.read(cx);
+        let show = self.show.clone();
+        let show_close = self.show.clone();
+
+        modal()
+            .on_exit(move |_, cx| show.write(cx, false))
+            .child(
+                div()
+                    .w(px(420.0))
+                    .p(px(20.0))
+                    .flex()
+                    .flex_col()
+                    .gap(px(12.0))
+                    .child(
+                        div()
+                            .flex()
+                            .items_center()
+                            .child(
+                                div()
+                                    .font_weight(FontWeight::BOLD)
+                                    .text_size(ui_px(20.0, cx))
+                                    .child("Equalizer"),
+                            )
+                            .child(
+                                div()
+                                    .id("equalizer-enabled")
+                                    .ml_auto()
+                                    .flex()
+                                    .items_center()
+                                    .gap(px(8.0))
+                                    .cursor_pointer()
+                                    .on_click(|_, _, cx| {
+                                        update_equalizer(cx, |equalizer| {
+                                            equalizer.enabled = !equalizer.enabled
+                                        })
+                                    })
+                                    .child(checkbox("equalizer-enabled-check", equalizer.enabled))
+                                    .child(div().text_sm().child("Enabled")),
+                            ),
+                    )
+                    .child(div().flex().flex_wrap().gap(px(6.0)).children(
+                        EqPreset::ALL.into_iter().map(|item| {
+                            button()
+                                .id(SharedString::from(format!("eq-preset-{}", item.label())))
+                                .intent(if preset == Some(item) && equalizer.enabled {
+                                    ButtonIntent::Primary
+                                } else {
+                                    ButtonIntent::Secondary
+                                })
+                                .on_click(move |_, _, cx| {
+                                    update_equalizer(cx, |equalizer| equalizer.apply_preset(item))
+                                })
+                                .child(item.label())
+                        }),
+                    ))
+                    .child(
+                        div()
+                            .flex()
+                            .flex_col()
+                            .gap(px(8.0))
+                            .when(!equalizer.enabled, |this| this.opacity(0.5))
+                            .child(gain_row(
+                                "eq-preamp",
+                                "Pre-amp",
+                                Control::Preamp,
+                                equalizer.preamp,
+                                cx,
+                            ))
+                            .child(div().h(px(1.0)).bg(theme.elevated_border_color))
+                            .children(EQ_BANDS.into_iter().enumerate().map(|(band, frequency)| {
+                                gain_row(
+                                    ("eq-band", band),
+                                    band_label(frequency),
+                                    Control::Band(band),
+                                    equalizer.gains[band],
+                                    cx,
+                                )
+                            })),
+                    )
+                    .child(div().h(px(1.0)).bg(theme.elevated_border_color))
+                    .child(slider_row(
+                        "balance",
+                        "Balance",
+                        ((balance + 1.0) / 2.0) as f32,
+                        balance_label(balance),
+                        |position, cx| set_balance(cx, balance_for_position(position)),
+                        |cx| set_balance(cx, 0.0),
+                        cx,
+                    ))
+                    .child(
+                        div().flex().justify_end().child(
+                            button()
+                                .id("equalizer-close")
+                                .intent(ButtonIntent::Primary)
+                                .child("Close")
+                                .on_click(move |_, _, cx| show_close.write(cx, false)),
+                        ),
+                    ),
+            )
+            .into_any_element()
+    }
+}
@@ -0,0 +1,31 @@
+//! Dragging tracks out of the window into other apps (like a file manager or a chat app), as
+//! files.
+//!
+//! gpui can only drag things around inside the window, so the app's own drags are handed over to
+//! the system once the cursor leaves the window with one of them. Drags that stay inside the
+//! window aren't affected. Before that, each drag of a track or an album offers its files with
+//! [offer_files] or [offer_album], in case it does leave.
+//!
+//! This is only supported on Windows. Everywhere else, these functions do nothing.
+
+#[cfg(target_os = "windows")]
+mod win32;
+
+#[cfg(target_os = "windows")]
+pub use win32::{install, offer_album, offer_files};
+
+#[cfg(not(target_os = "windows"))]
+mod unsupported {
+    use std::path::PathBuf;
+
+    use gpui::{App, Window};
+
+    pub fn install(_: &Window) {}
+
+    pub fn offer_files(_: Vec<PathBuf>) {}
+
+    pub fn offer_album(_: &mut App, _: i64) {}
+}
+
+#[cfg(not(target_os = "windows"))]
+pub use unsupported::{install, offer_album, offer_files};
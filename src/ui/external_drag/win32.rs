@@ -0,0 +1,196 @@
+//! The window is subclassed to watch the mouse while the left button is held. When the cursor
+//! leaves the window while files are offered, the drag is handed to the shell with
+//! `SHDoDragDrop`, which runs it until the button is released. Dropping then copies (or links) the
+//! files as a drag from Explorer would.
+
+use std::{
+    cell::{Cell, RefCell},
+    ffi::c_void,
+    path::PathBuf,
+    ptr,
+};
+
+use gpui::{App, Window};
+use raw_window_handle::{HasWindowHandle, RawWindowHandle};
+use tracing::{error, warn};
+use windows::{
+    Win32::{
+        Foundation::{HWND, LPARAM, LRESULT, RECT, WPARAM},
+        System::{
+            Com::{CoTaskMemFree, IBindCtx, IDataObject},
+            Ole::{DROPEFFECT_COPY, DROPEFFECT_LINK, IDropSource},
+        },
+        UI::{
+            Shell::{
+                BHID_DataObject, Common::ITEMIDLIST, DefSubclassProc, IShellItemArray,
+                RemoveWindowSubclass, SHCreateShellItemArrayFromIDLists, SHDoDragDrop,
+                SHParseDisplayName, SetWindowSubclass,
+            },
+            WindowsAndMessaging::{
+                GetClientRect, PostMessageW, WM_APP, WM_LBUTTONUP, WM_MOUSEMOVE, WM_NCDESTROY,
+            },
+        },
+    },
+    core::HSTRING,
+};
+
+use crate::library::db::LibraryAccess;
+
+/// Identifies the subclass installed on the window.
+const SUBCLASS_ID: usize = 0x4844;
+
+/// Starts the drag, once the window has finished handling the mouse movement that left it.
+const WM_EXTERNAL_DRAG: u32 = WM_APP + 0x200;
+
+/// Set in a mouse message's `wParam` while the left button is held (`MK_LBUTTON`).
+const LEFT_BUTTON: usize = 0x0001;
+
+thread_local! {
+    /// The files offered by the drag in progress, if any. Owned by the UI thread.
+    static OFFERED: RefCell<Option<Vec<PathBuf>>> = const { RefCell::new(None) };
+    /// Where the cursor last was during a drag, as the `lParam` of a mouse message.
+    static LAST_POSITION: Cell<isize> = const { Cell::new(0) };
+}
+
+/// Subclasses the window, so that drags can be handed over to the system when they leave it.
+pub fn install(window: &Window) {
+    let Ok(handle) = HasWindowHandle::window_handle(window) else {
+        return;
+    };
+    let RawWindowHandle::Win32(handle) = handle.as_raw() else {
+        return;
+    };
+
+    let hwnd = HWND(handle.hwnd.get() as *mut c_void);
+
+    if let Err(err) = unsafe { SetWindowSubclass(hwnd, Some(subclass_proc), SUBCLASS_ID, 0) }.ok() {
+        error!(
+            ?err,
+            "Failed to subclass the window, tracks can't be dragged out of it: {err}"
+        );
+    }
+}
+
+/// Offers the given files to other apps, in case the drag that's starting leaves the window.
+pub fn offer_files(paths: Vec<PathBuf>) {
+    OFFERED.set(Some(paths));
+}
+
+/// Offers the files of every track on an album (see [offer_files]).
+pub fn offer_album(cx: &mut App, album_id: i64) {
+    match cx.list_tracks_in_album(album_id) {
+        Ok(tracks) => offer_files(tracks.iter().map(|track| track.location.clone()).collect()),
+        Err(err) => warn!(
+            ?err,
+            "Failed to list the album's tracks for dragging: {err}"
+        ),
+    }
+}
+
+unsafe extern "system" fn subclass_proc(
+    hwnd: HWND,
+    msg: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+    _: usize,
+    _: usize,
+) -> LRESULT {
+    match msg {
+        WM_MOUSEMOVE if wparam.0 & LEFT_BUTTON != 0 => {
+            LAST_POSITION.set(lparam.0);
+
+            if OFFERED.with_borrow(Option::is_some) && is_outside(hwnd, lparam) {
+                unsafe { PostMessageW(Some(hwnd), WM_EXTERNAL_DRAG, WPARAM(0), LPARAM(0)) }.ok();
+            }
+        }
+        // the drag ended inside the window
+        WM_LBUTTONUP => OFFERED.set(None),
+        WM_EXTERNAL_DRAG => {
+            // several of these can be posted before the first one is handled
+            if let Some(paths) = OFFERED.take() {
+                drag_out(hwnd, &paths);
+            }
+
+            return LRESULT(0);
+        }
+        WM_NCDESTROY => unsafe {
+            if !RemoveWindowSubclass(hwnd, Some(subclass_proc), SUBCLASS_ID).as_bool() {
+                warn!("Failed to remove the drag window subclass");
+            }
+        },
+        _ => {}
+    }
+
+    unsafe { DefSubclassProc(hwnd, msg, wparam, lparam) }
+}
+
+/// Whether the position in a mouse message's `lParam` is outside the window's client area.
+fn is_outside(hwnd: HWND, lparam: LPARAM) -> bool {
+    // the coordinates are signed, since they're negative to the left of or above the window
+    let x = i32::from((lparam.0 & 0xFFFF) as i16);
+    let y = i32::from(((lparam.0 >> 16) & 0xFFFF) as i16);
+
+    let mut rect = RECT::default();
+    if unsafe { GetClientRect(hwnd, &mut rect) }.is_err() {
+        return false;
+    }
+
+    x < rect.left || y < rect.top || x >= rect.right || y >= rect.bottom
+}
+
+/// Hands the drag over to the shell. The shell's drag takes the button being released, so gpui is
+/// told about it afterwards, outside the window, to end its own drag without dropping anything.
+fn drag_out(hwnd: HWND, paths: &[PathBuf]) {
+    if let Err(err) = drag_files(hwnd, paths) {
+        warn!(?err, "Failed to drag files out of the window: {err}");
+    }
+
+    let position = LPARAM(LAST_POSITION.get());
+    unsafe { PostMessageW(Some(hwnd), WM_LBUTTONUP, WPARAM(0), position) }.ok();
+}
+
+fn drag_files(hwnd: HWND, paths: &[PathBuf]) -> windows::core::Result<()> {
+    let mut pidls: Vec<*mut ITEMIDLIST> = Vec::with_capacity(paths.len());
+
+    for path in paths {
+        let mut pidl = ptr::null_mut();
+        let name = HSTRING::from(path.as_os_str());
+
+        match unsafe { SHParseDisplayName(&name, None::<&IBindCtx>, &mut pidl, 0, None) } {
+            Ok(()) => pidls.push(pidl),
+            // the file may have been moved or deleted since the library was scanned
+            Err(err) => warn!(path = %path.display(), ?err, "Can't drag file: {err}"),
+        }
+    }
+
+    let result = start_drag(hwnd, &pidls);
+
+    for pidl in pidls {
+        unsafe { CoTaskMemFree(Some(pidl as *const c_void)) };
+    }
+
+    result
+}
+
+fn start_drag(hwnd: HWND, pidls: &[*mut ITEMIDLIST]) -> windows::core::Result<()> {
+    if pidls.is_empty() {
+        return Ok(());
+    }
+
+    let pidls: Vec<*const ITEMIDLIST> = pidls.iter().map(|pidl| pidl.cast_const()).collect();
+
+    unsafe {
+        let items: IShellItemArray = SHCreateShellItemArrayFromIDLists(&pidls)?;
+        let data: IDataObject = items.BindToHandler(None::<&IBindCtx>, &BHID_DataObject)?;
+
+        // without a drop source of our own, the shell's default one is used
+        SHDoDragDrop(
+            Some(hwnd),
+            &data,
+            None::<&IDropSource>,
+            DROPEFFECT_COPY | DROPEFFECT_LINK,
+        )?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,144 @@
+use std::sync::Arc;
+
+use gpui::{
+    App, AppContext, Context, Entity, IntoElement, ParentElement, Render, SharedString, Styled,
+    Window, div, px,
+};
+use nucleo::Utf32String;
+
+use crate::{
+    settings::{SettingsGlobal, interface::DEFAULT_FONT_FAMILY, update_settings},
+    ui::components::{
+        modal::modal,
+        palette::{AcceptIntent, FinderItemLeft, Palette, PaletteItem},
+    },
+};
+
+#[derive(PartialEq)]
+pub struct FontChoice {
+    family: SharedString,
+    current: bool,
+}
+
+impl FontChoice {
+    /// Lists every font family known to the text system, including the bundled fonts.
+    fn all(cx: &App) -> Vec<Arc<FontChoice>> {
+        let current = cx
+            .global::<SettingsGlobal>()
+            .model
+            .read(cx)
+            .interface
+            .font_family
+            .clone();
+
+        let mut families = cx.text_system().all_font_names();
+        families.sort_unstable_by_key(|family| family.to_lowercase());
+        families.dedup();
+
+        families
+            .into_iter()
+            .map(|family| {
+                Arc::new(FontChoice {
+                    current: family == current,
+                    family: family.into(),
+                })
+            })
+            .collect()
+    }
+}
+
+impl PaletteItem for FontChoice {
+    fn left_content(&self, _: &mut App) -> Option<FinderItemLeft> {
+        None
+    }
+
+    fn middle_content(&self, _: &mut App) -> SharedString {
+        self.family.clone()
+    }
+
+    fn right_content(&self, _: &mut App) -> Option<SharedString> {
+        if self.current {
+            Some("Current".into())
+        } else if self.family == DEFAULT_FONT_FAMILY {
+            Some("Default".into())
+        } else {
+            None
+        }
+    }
+}
+
+type MatcherFunc = Box<dyn Fn(&Arc<FontChoice>, &mut App) -> Utf32String + 'static>;
+type OnAccept = Box<dyn Fn(&Arc<FontChoice>, AcceptIntent, &mut App) + 'static>;
+
+/// A dialog for choosing the font used throughout the interface.
+pub struct FontPicker {
+    show: Entity<bool>,
+    palette: Entity<Palette<FontChoice, MatcherFunc, OnAccept>>,
+}
+
+impl FontPicker {
+    pub fn new(cx: &mut App, show: Entity<bool>) -> Entity<Self> {
+        cx.new(|cx| {
+            // fonts can be installed while the app is running, so they're listed every time the
+            // picker is opened
+            cx.observe(&show, |this: &mut Self, show, cx| {
+                let fonts = if *show.read(cx) {
+                    FontChoice::all(cx)
+                } else {
+                    Vec::new()
+                };
+
+                this.palette.update(cx, |this, cx| {
+                    cx.emit(fonts);
+                    this.reset(cx);
+                });
+
+                cx.notify();
+            })
+            .detach();
+
+            let matcher: MatcherFunc = Box::new(|choice, _| choice.family.to_string().into());
+
+            let show_clone = show.clone();
+
+            let on_accept: OnAccept = Box::new(move |choice, _, cx| {
+                let family = choice.family.to_string();
+                update_settings(cx, |settings| settings.interface.font_family = family);
+                show_clone.write(cx, false);
+            });
+
+            let palette = Palette::new(cx, Vec::new(), matcher, on_accept, &show);
+
+            Self { show, palette }
+        })
+    }
+}
+
+impl Render for FontPicker {
+    fn render(&mut self, window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let show = self.show.clone();
+        let palette = self.palette.clone();
+        let show_read = *self.show.read(cx);
+
+        if show_read {
+            cx.update_entity(&palette, |palette, cx| {
+                palette.focus(window, cx);
+            });
+
+            modal()
+                .child(div().w(px(550.0)).h(px(300.0)).child(palette.clone()))
+                .on_exit(move |_, cx| {
+                    show.update(cx, |show, cx| {
+                        *show = false;
+                        cx.update_entity(&palette, |palette, cx| {
+                            palette.reset(cx);
+                        });
+                        cx.notify();
+                    })
+                })
+                .into_any_element()
+        } else {
+            div().into_any_element()
+        }
+    }
+}
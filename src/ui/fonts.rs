@@ -0,0 +1,83 @@
+use gpui::{App, FontFallbacks, Global};
+use tracing::{debug, error, warn};
+
+/// Installed fonts that cover Chinese, Japanese and Korean text, in order of preference. Whichever
+/// of these are installed are tried when the interface font is missing a character, before
+/// falling back to whatever the platform picks.
+const SYSTEM_CJK_FONTS: &[&str] = &[
+    "Noto Sans CJK JP",
+    "Noto Sans CJK SC",
+    "Noto Sans CJK TC",
+    "Noto Sans CJK KR",
+    "Source Han Sans",
+    "Hiragino Sans",
+    "PingFang SC",
+    "Apple SD Gothic Neo",
+    "Yu Gothic UI",
+    "Meiryo UI",
+    "Microsoft YaHei UI",
+    "Microsoft JhengHei UI",
+    "Malgun Gothic",
+    "WenQuanYi Micro Hei",
+];
+
+/// Sample text for each script, shown in the about dialog to check which scripts can be displayed.
+pub const SCRIPT_SAMPLES: &[(&str, &str)] = &[
+    ("Latin", "The quick brown fox jumps"),
+    ("Cyrillic", "Съешь же ещё этих булок"),
+    ("Greek", "Γαζέες καὶ μυρτιὲς"),
+    ("Japanese", "いろはにほへと 夜明けの音楽"),
+    ("Chinese", "我能吞下玻璃而不伤身体"),
+    ("Korean", "다람쥐 헌 쳇바퀴에 타고파"),
+];
+
+/// The fonts tried, in order, when the interface font doesn't have a character.
+pub struct InterfaceFontFallbacks(pub FontFallbacks);
+
+impl Global for InterfaceFontFallbacks {}
+
+/// Loads the fonts bundled with the app. Fonts that fail to load are skipped, so that the app can
+/// still start with the system's fonts.
+pub fn load_bundled_fonts(cx: &mut App) {
+    let paths = match cx.asset_source().list("!bundled:fonts") {
+        Ok(paths) => paths,
+        Err(err) => {
+            error!("Failed to list bundled fonts, using system fonts instead: {err:?}");
+            return;
+        }
+    };
+
+    let mut fonts = vec![];
+    for path in paths {
+        if !(path.ends_with(".ttf") || path.ends_with(".otf")) {
+            continue;
+        }
+
+        match cx.asset_source().load(&path) {
+            Ok(Some(font)) => fonts.push(font),
+            Ok(None) => warn!("Bundled font {path} is missing"),
+            Err(err) => warn!("Failed to load bundled font {path}: {err:?}"),
+        }
+    }
+
+    if let Err(err) = cx.text_system().add_fonts(fonts) {
+        error!("Failed to load bundled fonts, using system fonts instead: {err:?}");
+    }
+
+    debug!("loaded fonts: {:?}", cx.text_system().all_font_names());
+}
+
+/// Builds the list of fonts tried when the interface font is missing a character, from the CJK
+/// fonts that are installed.
+pub fn update_font_fallbacks(cx: &mut App) {
+    let installed = cx.text_system().all_font_names();
+
+    let fonts = SYSTEM_CJK_FONTS
+        .iter()
+        .copied()
+        .filter(|family| installed.iter().any(|name| name == family))
+        .map(String::from)
+        .collect();
+
+    cx.set_global(InterfaceFontFallbacks(FontFallbacks::from_fonts(fonts)));
+}
@@ -0,0 +1,224 @@
+//! Formatting for the dates shown throughout the interface. Month names and the order of the day,
+//! month and year follow the system's locale.
+//!
+//! Days are put together by hand rather than with strftime's padding flags (like `%-e`), which
+//! aren't supported everywhere.
+
+use std::sync::LazyLock;
+
+use chrono::{DateTime, Datelike, Local, Locale, NaiveDate, TimeZone, Utc};
+
+use crate::library::types::AlbumDate;
+
+/// The system's locale, or US English if it can't be read or isn't one chrono knows about.
+static LOCALE: LazyLock<Locale> = LazyLock::new(|| {
+    sys_locale::get_locale()
+        .and_then(|tag| parse_locale(&tag))
+        .unwrap_or(Locale::en_US)
+});
+
+/// The order of the day, month and year in the system's locale.
+static ORDER: LazyLock<DateOrder> = LazyLock::new(|| DateOrder::for_locale(*LOCALE));
+
+/// Turns a locale as the system reports it (`en-US`, or `de_DE.UTF-8` on Unix) into one of
+/// chrono's. Locales given without a region use the region of the same name, if there is one
+/// (`de` becomes `de_DE`).
+fn parse_locale(tag: &str) -> Option<Locale> {
+    let tag = tag.split(['.', '@']).next()?.replace('-', "_");
+
+    Locale::try_from(tag.as_str()).ok().or_else(|| {
+        let region = tag.to_uppercase();
+        Locale::try_from(format!("{tag}_{region}").as_str()).ok()
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DateOrder {
+    DayMonthYear,
+    MonthDayYear,
+    YearMonthDay,
+}
+
+impl DateOrder {
+    /// Works out the order from the locale's numeric date format, by formatting a date whose day,
+    /// month and year can't be mistaken for each other.
+    fn for_locale(locale: Locale) -> Self {
+        let sample = NaiveDate::from_ymd_opt(2033, 11, 22)
+            .expect("valid date")
+            .format_localized("%x", locale)
+            .to_string();
+
+        let position = |part: &str| sample.find(part).unwrap_or(usize::MAX);
+        let (day, month, year) = (position("22"), position("11"), position("33"));
+
+        if year < month && year < day {
+            DateOrder::YearMonthDay
+        } else if month < day {
+            DateOrder::MonthDayYear
+        } else {
+            DateOrder::DayMonthYear
+        }
+    }
+}
+
+/// Puts a date together from its parts, in the given order. `month` is the month's name.
+fn assemble(order: DateOrder, day: u32, month: &str, year: i32) -> String {
+    match order {
+        DateOrder::DayMonthYear => format!("{day} {month} {year}"),
+        DateOrder::MonthDayYear => format!("{month} {day}, {year}"),
+        DateOrder::YearMonthDay => format!("{year} {month} {day}"),
+    }
+}
+
+fn format_date<Tz: TimeZone>(date: &DateTime<Tz>, month_format: &str) -> String
+where
+    Tz::Offset: std::fmt::Display,
+{
+    let month = date.format_localized(month_format, *LOCALE).to_string();
+    assemble(*ORDER, date.day(), &month, date.year())
+}
+
+/// Formats a date with the month written out, like "October 16, 2026".
+pub fn long_date<Tz: TimeZone>(date: &DateTime<Tz>) -> String
+where
+    Tz::Offset: std::fmt::Display,
+{
+    format_date(date, "%B")
+}
+
+/// Formats a date with the month abbreviated, like "Oct 16, 2026".
+pub fn medium_date<Tz: TimeZone>(date: &DateTime<Tz>) -> String
+where
+    Tz::Offset: std::fmt::Display,
+{
+    format_date(date, "%b")
+}
+
+/// Formats a date in the locale's numeric format, like "10/16/2026".
+pub fn short_date<Tz: TimeZone>(date: &DateTime<Tz>) -> String
+where
+    Tz::Offset: std::fmt::Display,
+{
+    date.format_localized("%x", *LOCALE).to_string()
+}
+
+/// Formats an album's date with [long_date], or just its year if the full date isn't known.
+pub fn album_date(date: &AlbumDate) -> String {
+    match date {
+        AlbumDate::Date(date) => long_date(date),
+        AlbumDate::Year(year) => year.to_string(),
+    }
+}
+
+/// Formats an album's date with [short_date], or just its year if the full date isn't known.
+pub fn album_date_short(date: &AlbumDate) -> String {
+    match date {
+        AlbumDate::Date(date) => short_date(date),
+        AlbumDate::Year(year) => year.to_string(),
+    }
+}
+
+/// How many days ago the given time was, counting the days as they're shown on the system's
+/// clock rather than in UTC, so that yesterday starts at local midnight.
+pub fn days_ago(time: DateTime<Utc>, now: DateTime<Local>) -> i64 {
+    let day = time.with_timezone(&Local).date_naive();
+    (now.date_naive() - day).num_days()
+}
+
+/// Describes a recent time relative to now, like "5 minutes ago" or "Yesterday". Anything from
+/// more than a week ago, or from the future, is given as a [medium_date] instead.
+pub fn relative_time(time: DateTime<Utc>, now: DateTime<Local>) -> String {
+    let elapsed = now.with_timezone(&Utc) - time;
+    let days = days_ago(time, now);
+
+    let plural = |count: i64, unit: &str| {
+        if count == 1 {
+            format!("1 {unit} ago")
+        } else {
+            format!("{count} {unit}s ago")
+        }
+    };
+
+    match days {
+        0 if elapsed.num_seconds() < 0 => medium_date(&time.with_timezone(&Local)),
+        0 if elapsed.num_minutes() < 1 => "Just now".to_string(),
+        0 if elapsed.num_hours() < 1 => plural(elapsed.num_minutes(), "minute"),
+        0 => plural(elapsed.num_hours(), "hour"),
+        1 => "Yesterday".to_string(),
+        2..=7 => plural(days, "day"),
+        _ => medium_date(&time.with_timezone(&Local)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+
+    use super::*;
+
+    /// Noon, so that times a few hours earlier are still on the same day.
+    fn noon() -> DateTime<Local> {
+        Local.with_ymd_and_hms(2026, 10, 16, 12, 0, 0).unwrap()
+    }
+
+    fn ago(elapsed: Duration) -> String {
+        let now = noon();
+        relative_time((now - elapsed).with_timezone(&Utc), now)
+    }
+
+    #[test]
+    fn reads_locales_the_way_systems_report_them() {
+        assert_eq!(parse_locale("en-US"), Some(Locale::en_US));
+        assert_eq!(parse_locale("de_DE.UTF-8"), Some(Locale::de_DE));
+        assert_eq!(parse_locale("sr_RS@latin"), Some(Locale::sr_RS));
+        assert_eq!(parse_locale("fr"), Some(Locale::fr_FR));
+        assert_eq!(parse_locale("xx"), None);
+    }
+
+    #[test]
+    fn orders_dates_like_the_locale() {
+        let order = DateOrder::for_locale;
+
+        assert_eq!(order(Locale::en_US), DateOrder::MonthDayYear);
+        assert_eq!(order(Locale::de_DE), DateOrder::DayMonthYear);
+        assert_eq!(order(Locale::ja_JP), DateOrder::YearMonthDay);
+    }
+
+    #[test]
+    fn puts_dates_together_in_order() {
+        let assemble = |order| assemble(order, 16, "October", 2026);
+
+        assert_eq!(assemble(DateOrder::MonthDayYear), "October 16, 2026");
+        assert_eq!(assemble(DateOrder::DayMonthYear), "16 October 2026");
+        assert_eq!(assemble(DateOrder::YearMonthDay), "2026 October 16");
+    }
+
+    #[test]
+    fn describes_recent_times() {
+        assert_eq!(ago(Duration::seconds(20)), "Just now");
+        assert_eq!(ago(Duration::minutes(1)), "1 minute ago");
+        assert_eq!(ago(Duration::minutes(5)), "5 minutes ago");
+        assert_eq!(ago(Duration::hours(3)), "3 hours ago");
+        assert_eq!(ago(Duration::days(1)), "Yesterday");
+        assert_eq!(ago(Duration::days(7)), "7 days ago");
+    }
+
+    #[test]
+    fn gives_older_and_future_times_as_dates() {
+        let now = noon();
+        let old = now - Duration::days(8);
+        let future = now + Duration::minutes(5);
+
+        assert_eq!(ago(Duration::days(8)), medium_date(&old));
+        assert_eq!(ago(-Duration::minutes(5)), medium_date(&future));
+    }
+
+    #[test]
+    fn counts_days_on_the_local_clock() {
+        let midnight = Local.with_ymd_and_hms(2026, 10, 16, 0, 0, 0).unwrap();
+        let before_midnight = midnight - Duration::minutes(1);
+
+        assert_eq!(days_ago(before_midnight.with_timezone(&Utc), midnight), 1);
+        assert_eq!(days_ago(midnight.with_timezone(&Utc), noon()), 0);
+    }
+}
@@ -1,30 +1,185 @@
-use gpui::{App, KeyBinding, Menu, MenuItem, SharedString, actions};
-use tracing::{debug, info};
+use gpui::{App, Global, KeyBinding, Menu, MenuItem, PathPromptOptions, SharedString, actions};
+use std::time::Duration;
+use tracing::{debug, error, info};
 
 use crate::{
-    library::scan::ScanInterface,
-    playback::{interface::PlaybackInterface, thread::PlaybackState},
-    ui::command_palette::OpenPalette,
+    library::{db::LibraryAccess, scan::ScanInterface},
+    playback::{events::SleepTimer, interface::PlaybackInterface, thread::PlaybackState},
+    settings::{
+        interface::{MAX_UI_SCALE, MIN_UI_SCALE, UI_SCALE_STEP},
+        scan::ScanRoot,
+        update_settings,
+    },
+    ui::{
+        command_palette::OpenPalette,
+        large_queue::{QueueEntry, play_now},
+        library::ViewSwitchMessage,
+        rebuild_dialog::RebuildReason,
+        shortcuts::{ActionGroup, describe_action},
+        toasts::show_toast,
+    },
 };
 
 use super::models::{Models, PlaybackInfo};
 
-actions!(hummingbird, [Quit, About, Search]);
-actions!(player, [PlayPause, Next, Previous]);
-actions!(scan, [ForceScan]);
+actions!(hummingbird, [Quit, About, Search, ShowShortcuts]);
+actions!(
+    player,
+    [
+        PlayPause,
+        Next,
+        Previous,
+        SeekForward,
+        SeekBackward,
+        VolumeUp,
+        VolumeDown,
+        ToggleStopAfterCurrent,
+        SleepIn15Minutes,
+        SleepIn30Minutes,
+        SleepIn60Minutes,
+        SleepAfterCurrentTrack,
+        CancelSleepTimer,
+        ChooseOutputDevice
+    ]
+);
+actions!(scan, [UpdateLibrary, ForceScan, AddMusicFolder]);
+actions!(library, [PlayRandomAlbum, ToggleHiddenAlbums]);
+actions!(
+    interface,
+    [ChooseFont, IncreaseUiScale, DecreaseUiScale, ResetUiScale]
+);
 actions!(hummingbird, [HideSelf, HideOthers, ShowAll]);
 
+/// How far the seek forward and backward shortcuts move through the current track, in seconds.
+const SEEK_STEP_SECS: f64 = 5.0;
+
+/// How far the volume up and down shortcuts move the volume.
+const VOLUME_STEP: f64 = 0.05;
+
 pub fn register_actions(cx: &mut App) {
     debug!("registering actions");
     cx.on_action(quit);
     cx.on_action(play_pause);
     cx.on_action(next);
     cx.on_action(previous);
+    cx.on_action(seek_forward);
+    cx.on_action(seek_backward);
+    cx.on_action(volume_up);
+    cx.on_action(volume_down);
+    cx.on_action(toggle_stop_after_current);
+    cx.on_action(sleep_in_15_minutes);
+    cx.on_action(sleep_in_30_minutes);
+    cx.on_action(sleep_in_60_minutes);
+    cx.on_action(sleep_after_current_track);
+    cx.on_action(cancel_sleep_timer);
+    cx.on_action(choose_output_device);
     cx.on_action(hide_self);
     cx.on_action(hide_others);
     cx.on_action(show_all);
     cx.on_action(about);
+    cx.on_action(show_shortcuts);
+    cx.on_action(update_library);
     cx.on_action(force_scan);
+    cx.on_action(add_music_folder);
+    cx.on_action(play_random_album);
+    cx.on_action(toggle_hidden_albums);
+    cx.on_action(choose_font);
+    cx.on_action(increase_ui_scale);
+    cx.on_action(decrease_ui_scale);
+    cx.on_action(reset_ui_scale);
+
+    describe_action(cx, ActionGroup::General, "Quit", Quit);
+    describe_action(cx, ActionGroup::General, "About Hummingbird", About);
+    describe_action(
+        cx,
+        ActionGroup::General,
+        "Keyboard Shortcuts",
+        ShowShortcuts,
+    );
+    if cfg!(target_os = "macos") {
+        describe_action(cx, ActionGroup::General, "Hide Hummingbird", HideSelf);
+        describe_action(cx, ActionGroup::General, "Hide Others", HideOthers);
+        describe_action(cx, ActionGroup::General, "Show All", ShowAll);
+    }
+    describe_action(
+        cx,
+        ActionGroup::Playback,
+        "Pause/Resume Current Track",
+        PlayPause,
+    );
+    describe_action(cx, ActionGroup::Playback, "Next Track", Next);
+    describe_action(cx, ActionGroup::Playback, "Previous Track", Previous);
+    describe_action(cx, ActionGroup::Playback, "Seek Forward", SeekForward);
+    describe_action(cx, ActionGroup::Playback, "Seek Backward", SeekBackward);
+    describe_action(cx, ActionGroup::Playback, "Volume Up", VolumeUp);
+    describe_action(cx, ActionGroup::Playback, "Volume Down", VolumeDown);
+    describe_action(
+        cx,
+        ActionGroup::Playback,
+        "Stop After Current Track",
+        ToggleStopAfterCurrent,
+    );
+    describe_action(
+        cx,
+        ActionGroup::Playback,
+        "Sleep Timer: 15 Minutes",
+        SleepIn15Minutes,
+    );
+    describe_action(
+        cx,
+        ActionGroup::Playback,
+        "Sleep Timer: 30 Minutes",
+        SleepIn30Minutes,
+    );
+    describe_action(
+        cx,
+        ActionGroup::Playback,
+        "Sleep Timer: 60 Minutes",
+        SleepIn60Minutes,
+    );
+    describe_action(
+        cx,
+        ActionGroup::Playback,
+        "Sleep Timer: End of Current Track",
+        SleepAfterCurrentTrack,
+    );
+    describe_action(
+        cx,
+        ActionGroup::Playback,
+        "Cancel Sleep Timer",
+        CancelSleepTimer,
+    );
+    describe_action(
+        cx,
+        ActionGroup::Playback,
+        "Change Output Device",
+        ChooseOutputDevice,
+    );
+    describe_action(
+        cx,
+        ActionGroup::Library,
+        "Play Random Album",
+        PlayRandomAlbum,
+    );
+    describe_action(
+        cx,
+        ActionGroup::Library,
+        "Show or Hide Hidden Albums",
+        ToggleHiddenAlbums,
+    );
+    describe_action(cx, ActionGroup::Library, "Update Library", UpdateLibrary);
+    describe_action(
+        cx,
+        ActionGroup::Library,
+        "Rebuild Library Metadata",
+        ForceScan,
+    );
+    describe_action(cx, ActionGroup::Library, "Add Music Folder", AddMusicFolder);
+    describe_action(cx, ActionGroup::Interface, "Change Font", ChooseFont);
+    describe_action(cx, ActionGroup::Interface, "Increase Size", IncreaseUiScale);
+    describe_action(cx, ActionGroup::Interface, "Decrease Size", DecreaseUiScale);
+    describe_action(cx, ActionGroup::Interface, "Reset Size", ResetUiScale);
+
     debug!("actions: {:?}", cx.all_action_names());
     debug!("action available: {:?}", cx.is_action_available(&Quit));
     if cfg!(target_os = "macos") {
@@ -39,12 +194,22 @@ pub fn register_actions(cx: &mut App) {
 
     cx.bind_keys([KeyBinding::new("secondary-right", Next, None)]);
     cx.bind_keys([KeyBinding::new("secondary-left", Previous, None)]);
+    // plain and shifted arrow keys move the cursor in text inputs
+    cx.bind_keys([KeyBinding::new("alt-right", SeekForward, None)]);
+    cx.bind_keys([KeyBinding::new("alt-left", SeekBackward, None)]);
+    cx.bind_keys([KeyBinding::new("alt-up", VolumeUp, None)]);
+    cx.bind_keys([KeyBinding::new("alt-down", VolumeDown, None)]);
     cx.bind_keys([KeyBinding::new("secondary-p", Search, None)]);
     cx.bind_keys([KeyBinding::new("secondary-f", Search, None)]);
     cx.bind_keys([KeyBinding::new("secondary-shift-p", OpenPalette, None)]);
 
     cx.bind_keys([KeyBinding::new("alt-shift-s", ForceScan, None)]);
+    cx.bind_keys([KeyBinding::new("secondary-shift-r", PlayRandomAlbum, None)]);
     cx.bind_keys([KeyBinding::new("space", PlayPause, None)]);
+    cx.bind_keys([KeyBinding::new("secondary-=", IncreaseUiScale, None)]);
+    cx.bind_keys([KeyBinding::new("secondary--", DecreaseUiScale, None)]);
+    cx.bind_keys([KeyBinding::new("secondary-0", ResetUiScale, None)]);
+    cx.bind_keys([KeyBinding::new("?", ShowShortcuts, None)]);
     cx.set_menus(vec![
         Menu {
             name: SharedString::from("Hummingbird"),
@@ -105,6 +270,63 @@ fn previous(_: &Previous, cx: &mut App) {
     interface.previous();
 }
 
+fn seek_forward(_: &SeekForward, cx: &mut App) {
+    let interface = cx.global::<PlaybackInterface>();
+    interface.seek_forward(SEEK_STEP_SECS);
+}
+
+fn seek_backward(_: &SeekBackward, cx: &mut App) {
+    let interface = cx.global::<PlaybackInterface>();
+    interface.seek_backward(SEEK_STEP_SECS);
+}
+
+fn volume_up(_: &VolumeUp, cx: &mut App) {
+    let interface = cx.global::<PlaybackInterface>();
+    interface.volume_up(VOLUME_STEP);
+}
+
+fn volume_down(_: &VolumeDown, cx: &mut App) {
+    let interface = cx.global::<PlaybackInterface>();
+    interface.volume_down(VOLUME_STEP);
+}
+
+fn toggle_stop_after_current(_: &ToggleStopAfterCurrent, cx: &mut App) {
+    let interface = cx.global::<PlaybackInterface>();
+    interface.toggle_stop_after_current();
+}
+
+fn set_sleep_timer(cx: &mut App, timer: SleepTimer) {
+    let interface = cx.global::<PlaybackInterface>();
+    interface.set_sleep_timer(timer);
+}
+
+fn sleep_after_minutes(cx: &mut App, minutes: u64) {
+    set_sleep_timer(
+        cx,
+        SleepTimer::AfterDuration(Duration::from_secs(minutes * 60)),
+    );
+}
+
+fn sleep_in_15_minutes(_: &SleepIn15Minutes, cx: &mut App) {
+    sleep_after_minutes(cx, 15);
+}
+
+fn sleep_in_30_minutes(_: &SleepIn30Minutes, cx: &mut App) {
+    sleep_after_minutes(cx, 30);
+}
+
+fn sleep_in_60_minutes(_: &SleepIn60Minutes, cx: &mut App) {
+    sleep_after_minutes(cx, 60);
+}
+
+fn sleep_after_current_track(_: &SleepAfterCurrentTrack, cx: &mut App) {
+    set_sleep_timer(cx, SleepTimer::AfterCurrentTrack);
+}
+
+fn cancel_sleep_timer(_: &CancelSleepTimer, cx: &mut App) {
+    set_sleep_timer(cx, SleepTimer::Off);
+}
+
 fn hide_self(_: &HideSelf, cx: &mut App) {
     cx.hide();
 }
@@ -122,7 +344,135 @@ fn about(_: &About, cx: &mut App) {
     show_about.write(cx, true);
 }
 
-fn force_scan(_: &ForceScan, cx: &mut App) {
+fn show_shortcuts(_: &ShowShortcuts, cx: &mut App) {
+    let show_shortcuts = cx.global::<Models>().show_shortcuts.clone();
+    show_shortcuts.write(cx, true);
+}
+
+fn choose_output_device(_: &ChooseOutputDevice, cx: &mut App) {
+    let show_device_picker = cx.global::<Models>().show_device_picker.clone();
+    show_device_picker.write(cx, true);
+}
+
+fn choose_font(_: &ChooseFont, cx: &mut App) {
+    let show_font_picker = cx.global::<Models>().show_font_picker.clone();
+    show_font_picker.write(cx, true);
+}
+
+fn set_ui_scale(cx: &mut App, scale: impl FnOnce(f32) -> f32) {
+    update_settings(cx, |settings| {
+        let scale = scale(settings.interface.scale()).clamp(MIN_UI_SCALE, MAX_UI_SCALE);
+        // avoid drifting away from round percentages after a few steps
+        settings.interface.ui_scale = (scale * 100.0).round() / 100.0;
+    });
+}
+
+fn increase_ui_scale(_: &IncreaseUiScale, cx: &mut App) {
+    set_ui_scale(cx, |scale| scale + UI_SCALE_STEP);
+}
+
+fn decrease_ui_scale(_: &DecreaseUiScale, cx: &mut App) {
+    set_ui_scale(cx, |scale| scale - UI_SCALE_STEP);
+}
+
+fn reset_ui_scale(_: &ResetUiScale, cx: &mut App) {
+    set_ui_scale(cx, |_| 1.0);
+}
+
+fn update_library(_: &UpdateLibrary, cx: &mut App) {
     let scanner = cx.global::<ScanInterface>();
-    scanner.force_scan();
+    scanner.scan();
+}
+
+/// Force-scans have to be confirmed first, since they take a long time on large libraries.
+fn force_scan(_: &ForceScan, cx: &mut App) {
+    let rebuild_prompt = cx.global::<Models>().rebuild_prompt.clone();
+    rebuild_prompt.write(cx, Some(RebuildReason::Requested));
+}
+
+fn add_music_folder(_: &AddMusicFolder, cx: &mut App) {
+    let path_future = cx.prompt_for_paths(PathPromptOptions {
+        files: false,
+        directories: true,
+        multiple: true,
+        prompt: Some("Add to library".into()),
+    });
+
+    cx.spawn(async move |cx| {
+        let Ok(Ok(Some(paths))) = path_future.await else {
+            info!("Adding music folder cancelled by user");
+            return;
+        };
+
+        cx.update(|cx| {
+            update_settings(cx, |settings| {
+                for path in paths {
+                    if !settings.scanning.has_path(&path) {
+                        settings.scanning.paths.push(ScanRoot::new(path));
+                    }
+                }
+            });
+        })
+        .ok();
+    })
+    .detach();
+}
+
+fn toggle_hidden_albums(_: &ToggleHiddenAlbums, cx: &mut App) {
+    let show_hidden_albums = cx.global::<Models>().show_hidden_albums.clone();
+    show_hidden_albums.update(cx, |show, cx| {
+        *show = !*show;
+        cx.notify();
+    });
+}
+
+/// The album most recently picked by `PlayRandomAlbum`, so that the same album isn't picked twice
+/// in a row.
+#[derive(Default)]
+struct LastRandomAlbum(Option<i64>);
+
+impl Global for LastRandomAlbum {}
+
+fn play_random_album(_: &PlayRandomAlbum, cx: &mut App) {
+    let last = cx.default_global::<LastRandomAlbum>().0;
+
+    let album_id = match cx.get_random_album_id(last) {
+        Ok(Some(album_id)) => album_id,
+        Ok(None) => {
+            show_toast(cx, "There are no albums in your library yet.");
+            return;
+        }
+        Err(err) => {
+            error!(?err, "Failed to pick a random album");
+            show_toast(cx, "Couldn't pick a random album.");
+            return;
+        }
+    };
+
+    cx.set_global(LastRandomAlbum(Some(album_id)));
+
+    let tracks = match cx.list_tracks_in_album(album_id) {
+        Ok(tracks) if !tracks.is_empty() => tracks,
+        Ok(_) => {
+            show_toast(cx, "The selected album has no tracks.");
+            return;
+        }
+        Err(err) => {
+            error!(?err, "Failed to retrieve tracks for album {album_id}");
+            show_toast(cx, "Couldn't load the selected album.");
+            return;
+        }
+    };
+
+    let entries = tracks
+        .iter()
+        .map(|track| QueueEntry::new(track.location.clone(), Some(track.id), track.album_id))
+        .collect();
+
+    play_now(cx, entries, None);
+
+    let switcher_model = cx.global::<Models>().switcher_model.clone();
+    switcher_model.update(cx, |_, cx| {
+        cx.emit(ViewSwitchMessage::Release(album_id));
+    });
 }
@@ -5,7 +5,7 @@ use prelude::FluentBuilder;
 use tracing::{info, warn};
 
 use crate::{
-    library::scan::ScanEvent,
+    library::scan::{RootProgress, ScanEvent, ScanInterface, ScanKind},
     services::mmb::lastfm::LASTFM_CREDS,
     ui::components::icons::{CROSS, FOLDER_CHECK, FOLDER_SEARCH, MAXIMIZE, MINUS, icon},
 };
@@ -140,6 +140,10 @@ impl Render for ScanStatus {
     fn render(&mut self, _: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
         let theme = cx.global::<Theme>();
         let status = self.scan_model.read(cx);
+        let stoppable = matches!(
+            status,
+            ScanEvent::Rebuilding { .. } | ScanEvent::Analyzing { .. }
+        );
 
         div()
             .flex()
@@ -158,18 +162,66 @@ impl Render for ScanStatus {
             .text_color(theme.text_secondary)
             .child(match status {
                 ScanEvent::ScanCompleteIdle => "".to_string(),
-                ScanEvent::ScanProgress { current, total } => {
+                ScanEvent::ScanProgress {
+                    current,
+                    total,
+                    kind,
+                    root,
+                } => {
                     format!(
-                        "Scanning ({}%)",
-                        (*current as f64 / *total as f64 * 100.0).round()
+                        "{} ({}%){}",
+                        match kind {
+                            ScanKind::Update => "Updating library",
+                            ScanKind::Rebuild => "Rebuilding library",
+                        },
+                        (*current as f64 / *total as f64 * 100.0).round(),
+                        folder_suffix(root)
                     )
                 }
-                ScanEvent::DiscoverProgress(progress) => {
-                    format!("Discovering files ({progress})")
-                }
-                ScanEvent::Cleaning => "".to_string(),
+                ScanEvent::Rebuilding { current, total } => format!(
+                    "Rebuilding library metadata — browsing may look incomplete ({}%)",
+                    (*current as f64 / *total as f64 * 100.0).round()
+                ),
+                ScanEvent::DiscoverProgress(progress, kind, root) => match kind {
+                    ScanKind::Update => {
+                        format!("Discovering files ({progress}){}", folder_suffix(root))
+                    }
+                    ScanKind::Rebuild => {
+                        format!(
+                            "Rebuilding library: discovering files ({progress}){}",
+                            folder_suffix(root)
+                        )
+                    }
+                },
+                ScanEvent::Analyzing { current, total } => format!(
+                    "Analyzing loudness ({}%)",
+                    (*current as f64 / *total as f64 * 100.0).round()
+                ),
+                ScanEvent::Cleaning(ScanKind::Update) => "".to_string(),
+                ScanEvent::Cleaning(ScanKind::Rebuild) => "Rebuilding library".to_string(),
                 ScanEvent::ScanCompleteWatching => "Watching for updates".to_string(),
             })
+            .when(stoppable, |this| {
+                this.child(
+                    div()
+                        .id("scan-stop-rebuild")
+                        .ml(px(8.0))
+                        .cursor_pointer()
+                        .text_color(theme.text_link)
+                        .hover(|this| this.border_b_1().border_color(theme.text_link))
+                        .child("Stop")
+                        .on_click(|_, _, cx| cx.global::<ScanInterface>().stop()),
+                )
+            })
+    }
+}
+
+/// Says which folder is being scanned, when there's more than one.
+fn folder_suffix(root: &RootProgress) -> String {
+    if root.count > 1 {
+        format!(", folder {} of {}", root.index + 1, root.count)
+    } else {
+        String::new()
     }
 }
 
@@ -0,0 +1,662 @@
+//! Queueing tracks. Every place tracks can be queued from does it with one of [play_now],
+//! [play_next] or [add_to_queue], and labels the action with the matching name ([PLAY_NOW],
+//! [PLAY_NEXT] or [ADD_TO_QUEUE]), so that each action means the same thing everywhere.
+
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use gpui::{
+    App, FontWeight, InteractiveElement, IntoElement, ParentElement, RenderOnce,
+    StatefulInteractiveElement, Styled, Window, div, px,
+};
+use rustc_hash::FxHashSet;
+
+use crate::{
+    playback::{interface::PlaybackInterface, queue::QueueItemData},
+    settings::{SettingsGlobal, update_settings},
+};
+
+use super::{
+    components::{
+        button::{ButtonIntent, button},
+        checkbox::checkbox,
+        modal::modal,
+    },
+    models::Models,
+    theme::Theme,
+    toasts::ProgressToast,
+    util::ui_px,
+};
+
+/// How many items are queued straight away, so that playback can start. The rest are queued in
+/// batches in the background.
+const FIRST_CHUNK_SIZE: usize = 100;
+
+/// How many items are queued at a time in the background. Building a batch has to stay well
+/// under ~100ms, since it happens on the UI thread.
+const BATCH_SIZE: usize = 250;
+
+/// How long to wait between batches, so that the UI gets to draw and handle input in between.
+const BATCH_INTERVAL: Duration = Duration::from_millis(16);
+
+/// How many items are queued when the user picks "Queue first" in the confirmation dialog.
+const LIMITED_COUNT: usize = 500;
+
+/// Replaces the queue with the tracks and starts playing them.
+pub const PLAY_NOW: &str = "Play now";
+/// Inserts the tracks right after the one that's playing.
+pub const PLAY_NEXT: &str = "Play next";
+/// Adds the tracks to the end of the queue.
+pub const ADD_TO_QUEUE: &str = "Add to queue";
+
+/// A track that's about to be queued. Building the [`QueueItemData`] for it is what's slow, so
+/// it's put off until the track is actually sent to the playback thread.
+#[derive(Clone, Debug)]
+pub struct QueueEntry {
+    path: PathBuf,
+    track_id: Option<i64>,
+    album_id: Option<i64>,
+}
+
+impl QueueEntry {
+    pub fn new(path: PathBuf, track_id: Option<i64>, album_id: Option<i64>) -> Self {
+        Self {
+            path,
+            track_id,
+            album_id,
+        }
+    }
+
+    pub fn path(&self) -> &PathBuf {
+        &self.path
+    }
+
+    fn build(&self, cx: &mut App) -> QueueItemData {
+        QueueItemData::new(cx, self.path.clone(), self.track_id, self.album_id)
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum EnqueueMode {
+    /// Replace the queue. If `start_at` is given, the entry at that index is played first;
+    /// otherwise the playback thread picks, as it does when replacing the queue normally.
+    Replace { start_at: Option<usize> },
+    /// Insert after the current track.
+    Next,
+    /// Add to the end of the queue.
+    Append,
+}
+
+/// What the user is being asked to confirm.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Confirmation {
+    /// Replacing a queue of this many tracks.
+    Replace(usize),
+    /// Queueing this many tracks at once.
+    LargeQueue(usize),
+}
+
+struct PendingEnqueue {
+    entries: Vec<QueueEntry>,
+    mode: EnqueueMode,
+    confirmation: Confirmation,
+}
+
+/// Enqueues that are waiting for the user to confirm them, or large ones being queued in the
+/// background.
+#[derive(Default)]
+pub struct LargeQueue {
+    pending: Option<PendingEnqueue>,
+    /// Whether "Don't ask again" is checked in the confirmation for replacing the queue.
+    dont_ask_again: bool,
+    /// Bumped whenever the queue is replaced, so that the background batches of an earlier
+    /// enqueue stop instead of adding to the new queue.
+    generation: usize,
+}
+
+impl LargeQueue {
+    /// What the user is being asked to confirm, if anything.
+    pub fn pending(&self) -> Option<Confirmation> {
+        self.pending.as_ref().map(|pending| pending.confirmation)
+    }
+}
+
+/// Replaces the queue with the entries and starts playing them ([PLAY_NOW]). If `start_at` is
+/// given, the entry at that index is played first.
+pub fn play_now(cx: &mut App, entries: Vec<QueueEntry>, start_at: Option<usize>) {
+    enqueue(cx, entries, EnqueueMode::Replace { start_at });
+}
+
+/// Inserts the entries right after the current track, so that they're played next
+/// ([PLAY_NEXT]). If nothing is playing, they're added to the end of the queue and the first one
+/// starts playing.
+pub fn play_next(cx: &mut App, entries: Vec<QueueEntry>) {
+    enqueue(cx, entries, EnqueueMode::Next);
+}
+
+/// Adds the entries to the end of the queue ([ADD_TO_QUEUE]).
+pub fn add_to_queue(cx: &mut App, entries: Vec<QueueEntry>) {
+    enqueue(cx, entries, EnqueueMode::Append);
+}
+
+/// Queues the given entries. Before replacing a long queue, or queueing more than the configured
+/// threshold, the user is asked to confirm first.
+fn enqueue(cx: &mut App, entries: Vec<QueueEntry>, mode: EnqueueMode) {
+    if entries.is_empty() {
+        return;
+    }
+
+    if matches!(mode, EnqueueMode::Replace { .. })
+        && let Some(replaced) = replaced_count(cx, &entries)
+    {
+        ask(cx, entries, mode, Confirmation::Replace(replaced));
+        return;
+    }
+
+    enqueue_confirmed(cx, entries, mode);
+}
+
+/// The length of the queue, if replacing it with the entries needs to be confirmed: if it's over
+/// the threshold, and has tracks that aren't among the entries, which would be lost.
+fn replaced_count(cx: &mut App, entries: &[QueueEntry]) -> Option<usize> {
+    let threshold = cx
+        .global::<SettingsGlobal>()
+        .model
+        .read(cx)
+        .playback
+        .replace_queue_threshold;
+
+    let queue = cx.global::<Models>().queue.read(cx).data.clone();
+    let queue = queue.read().expect("couldn't get the queue");
+    let queued: Vec<&Path> = queue.iter().map(|item| item.get_path().as_path()).collect();
+
+    confirm_replacing(threshold, &queued, entries)
+}
+
+/// The length of the `queue`, if it's longer than the `threshold` (which is off when it's 0) and
+/// replacing it with the entries would lose any of its tracks.
+fn confirm_replacing(threshold: usize, queue: &[&Path], entries: &[QueueEntry]) -> Option<usize> {
+    if threshold == 0 || queue.len() <= threshold {
+        return None;
+    }
+
+    let replacing: FxHashSet<&Path> = entries.iter().map(|entry| entry.path.as_path()).collect();
+    let loses_tracks = queue.iter().any(|path| !replacing.contains(path));
+
+    loses_tracks.then_some(queue.len())
+}
+
+/// Queues the entries once replacing the queue has been confirmed (or didn't need to be). If
+/// there are more than the configured threshold, the user is asked to confirm that too. Only the
+/// first few items are queued straight away, so that playback starts quickly; the rest follow in
+/// the background.
+fn enqueue_confirmed(cx: &mut App, entries: Vec<QueueEntry>, mode: EnqueueMode) {
+    let threshold = cx
+        .global::<SettingsGlobal>()
+        .model
+        .read(cx)
+        .playback
+        .large_queue_threshold;
+
+    if threshold > 0 && entries.len() > threshold {
+        let count = entries.len();
+        ask(cx, entries, mode, Confirmation::LargeQueue(count));
+        return;
+    }
+
+    enqueue_chunked(cx, entries, mode);
+}
+
+fn ask(cx: &mut App, entries: Vec<QueueEntry>, mode: EnqueueMode, confirmation: Confirmation) {
+    let large_queue = cx.global::<Models>().large_queue.clone();
+    large_queue.update(cx, |large_queue, cx| {
+        large_queue.pending = Some(PendingEnqueue {
+            entries,
+            mode,
+            confirmation,
+        });
+        large_queue.dont_ask_again = false;
+        cx.notify();
+    });
+}
+
+/// Where tracks that should be played next go: right after the current track, or at the end of
+/// the queue if there isn't one.
+fn next_position(cx: &mut App) -> usize {
+    let queue = cx.global::<Models>().queue.read(cx);
+    let len = queue.data.read().expect("couldn't get the queue").len();
+
+    position_after(queue.position, len)
+}
+
+/// The position right after `current` in a queue of `len` tracks, or the end of the queue if
+/// nothing is current.
+fn position_after(current: Option<usize>, len: usize) -> usize {
+    current.map_or(len, |current| (current + 1).min(len))
+}
+
+/// How an enqueue is split up: the chunk that's queued straight away, and the batches that follow
+/// in the background.
+#[derive(Debug, PartialEq)]
+struct Chunks<T> {
+    first: Vec<T>,
+    /// The index in `first` of the entry to play first, if one was asked for.
+    start_at: Option<usize>,
+    /// The batches to queue afterwards, in order, with where each one is inserted (or `None` to
+    /// add it to the end of the queue).
+    batches: Vec<(Vec<T>, Option<usize>)>,
+}
+
+impl<T: Clone> Chunks<T> {
+    /// Splits up the entries. `start_at` is the index of the entry to play first, and `next` is
+    /// where the entries are inserted when playing them next.
+    ///
+    /// If the entry to play isn't in the first chunk, the first chunk starts at it instead, so
+    /// that it can be played right away - the entries before it are inserted in front of it
+    /// afterwards. When playing next, the rest of the entries go right after the first chunk
+    /// rather than at the end of the queue.
+    fn new(mut entries: Vec<T>, start_at: Option<usize>, next: Option<usize>) -> Self {
+        let start_at = start_at.map(|index| index.min(entries.len().saturating_sub(1)));
+        let split = start_at
+            .filter(|index| *index >= FIRST_CHUNK_SIZE)
+            .unwrap_or(0);
+
+        let mut first = entries.split_off(split);
+        let trailing = first.split_off(FIRST_CHUNK_SIZE.min(first.len()));
+        let leading = entries;
+
+        let following = trailing.chunks(BATCH_SIZE).scan(
+            next.map(|next| next + first.len()),
+            |position, batch| {
+                let at = *position;
+                *position = position.map(|position| position + batch.len());
+                Some((batch.to_vec(), at))
+            },
+        );
+        let inserted = leading.chunks(BATCH_SIZE).scan(0, |position, batch| {
+            let at = *position;
+            *position += batch.len();
+            Some((batch.to_vec(), Some(at)))
+        });
+        let batches = following.chain(inserted).collect();
+
+        Chunks {
+            first,
+            start_at: start_at.map(|index| index - split),
+            batches,
+        }
+    }
+}
+
+fn enqueue_chunked(cx: &mut App, entries: Vec<QueueEntry>, mode: EnqueueMode) {
+    let large_queue = cx.global::<Models>().large_queue.clone();
+    let generation = large_queue.update(cx, |large_queue, _| {
+        if matches!(mode, EnqueueMode::Replace { .. }) {
+            large_queue.generation += 1;
+        }
+        large_queue.generation
+    });
+
+    let start_at = match mode {
+        EnqueueMode::Replace { start_at } => start_at,
+        EnqueueMode::Next | EnqueueMode::Append => None,
+    };
+    let next = (mode == EnqueueMode::Next).then(|| next_position(cx));
+    let chunks = Chunks::new(entries, start_at, next);
+
+    let items = chunks.first.iter().map(|entry| entry.build(cx)).collect();
+    let playback_interface = cx.global::<PlaybackInterface>();
+    match (mode, next) {
+        (EnqueueMode::Replace { .. }, _) => playback_interface.replace_queue(items),
+        (_, Some(next)) => playback_interface.insert_list_at(items, next),
+        (_, None) => playback_interface.queue_list(items),
+    }
+
+    if let Some(start_at) = chunks.start_at {
+        playback_interface.jump_unshuffled(start_at);
+    }
+
+    let remaining: usize = chunks.batches.iter().map(|(batch, _)| batch.len()).sum();
+    if remaining == 0 {
+        return;
+    }
+
+    let toast = ProgressToast::show(
+        cx,
+        format!("Adding {remaining} more tracks to the queue..."),
+    );
+
+    cx.spawn(async move |cx| {
+        let mut done = 0;
+
+        for (batch, position) in chunks.batches {
+            cx.background_executor().timer(BATCH_INTERVAL).await;
+
+            let current = cx
+                .update(|cx| {
+                    if large_queue.read(cx).generation != generation {
+                        return false;
+                    }
+
+                    let items = batch.iter().map(|entry| entry.build(cx)).collect();
+                    let playback_interface = cx.global::<PlaybackInterface>();
+                    match position {
+                        Some(position) => playback_interface.insert_list_at(items, position),
+                        None => playback_interface.queue_list(items),
+                    }
+
+                    done += batch.len();
+                    toast.update(
+                        cx,
+                        format!("Adding tracks to the queue ({done}/{remaining})"),
+                    );
+                    true
+                })
+                .unwrap_or(false);
+
+            if !current {
+                cx.update(|cx| toast.finish(cx, "Stopped adding tracks to the queue."))
+                    .ok();
+                return;
+            }
+        }
+
+        cx.update(|cx| toast.finish(cx, format!("Added {remaining} more tracks to the queue.")))
+            .ok();
+    })
+    .detach();
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Choice {
+    Replace,
+    All,
+    Limited,
+    Cancel,
+}
+
+fn close(cx: &mut App, choice: Choice) {
+    let large_queue = cx.global::<Models>().large_queue.clone();
+    let (pending, dont_ask_again) = large_queue.update(cx, |large_queue, cx| {
+        cx.notify();
+        (large_queue.pending.take(), large_queue.dont_ask_again)
+    });
+
+    let Some(PendingEnqueue {
+        mut entries,
+        mut mode,
+        ..
+    }) = pending
+    else {
+        return;
+    };
+
+    match choice {
+        Choice::Replace => {
+            if dont_ask_again {
+                update_settings(cx, |settings| settings.playback.replace_queue_threshold = 0);
+            }
+
+            enqueue_confirmed(cx, entries, mode);
+        }
+        Choice::All => enqueue_chunked(cx, entries, mode),
+        Choice::Limited => {
+            // keep the track that was going to be played, and the ones after it
+            if let EnqueueMode::Replace {
+                start_at: Some(start_at),
+            } = &mut mode
+            {
+                entries.drain(..(*start_at).min(entries.len() - 1));
+                *start_at = 0;
+            }
+
+            entries.truncate(LIMITED_COUNT);
+            enqueue_chunked(cx, entries, mode);
+        }
+        Choice::Cancel => {}
+    }
+}
+
+/// Asks the user to confirm queueing a large number of tracks at once, offering to queue only
+/// the first few hundred instead.
+#[derive(IntoElement)]
+pub struct LargeQueueDialog {
+    count: usize,
+}
+
+impl RenderOnce for LargeQueueDialog {
+    fn render(self, _: &mut Window, cx: &mut App) -> impl IntoElement {
+        let theme = cx.global::<Theme>();
+
+        modal()
+            .on_exit(move |_, cx| close(cx, Choice::Cancel))
+            .child(
+                div()
+                    .w(px(450.0))
+                    .p(px(20.0))
+                    .flex()
+                    .flex_col()
+                    .gap(px(8.0))
+                    .child(
+                        div()
+                            .font_weight(FontWeight::BOLD)
+                            .text_size(ui_px(20.0, cx))
+                            .child(format!("Queue {} tracks?", self.count)),
+                    )
+                    .child(div().text_sm().text_color(theme.text_secondary).child(
+                        "Playback will start right away, and the rest of the tracks \
+                                will be added to the queue in the background.",
+                    ))
+                    .child(
+                        div()
+                            .flex()
+                            .justify_end()
+                            .gap(px(8.0))
+                            .mt(px(8.0))
+                            .child(
+                                button()
+                                    .id("large-queue-cancel")
+                                    .child("Cancel")
+                                    .on_click(|_, _, cx| close(cx, Choice::Cancel)),
+                            )
+                            .child(
+                                button()
+                                    .id("large-queue-limited")
+                                    .child(format!("Queue first {LIMITED_COUNT}"))
+                                    .on_click(|_, _, cx| close(cx, Choice::Limited)),
+                            )
+                            .child(
+                                button()
+                                    .id("large-queue-all")
+                                    .intent(ButtonIntent::Primary)
+                                    .child("Queue all")
+                                    .on_click(|_, _, cx| close(cx, Choice::All)),
+                            ),
+                    ),
+            )
+    }
+}
+
+pub fn large_queue_dialog(count: usize) -> LargeQueueDialog {
+    LargeQueueDialog { count }
+}
+
+/// Asks the user to confirm replacing the queue with "Play now", since the tracks in it would be
+/// lost.
+#[derive(IntoElement)]
+pub struct ReplaceQueueDialog {
+    count: usize,
+}
+
+impl RenderOnce for ReplaceQueueDialog {
+    fn render(self, _: &mut Window, cx: &mut App) -> impl IntoElement {
+        let theme = cx.global::<Theme>();
+        let dont_ask_again = cx.global::<Models>().large_queue.read(cx).dont_ask_again;
+
+        modal()
+            .on_exit(move |_, cx| close(cx, Choice::Cancel))
+            .child(
+                div()
+                    .w(px(450.0))
+                    .p(px(20.0))
+                    .flex()
+                    .flex_col()
+                    .gap(px(8.0))
+                    .child(
+                        div()
+                            .font_weight(FontWeight::BOLD)
+                            .text_size(ui_px(20.0, cx))
+                            .child("Replace the queue?"),
+                    )
+                    .child(
+                        div()
+                            .text_sm()
+                            .text_color(theme.text_secondary)
+                            .child(format!(
+                                "This will replace your current queue of {} tracks.",
+                                self.count
+                            )),
+                    )
+                    .child(
+                        div()
+                            .flex()
+                            .items_center()
+                            .gap(px(8.0))
+                            .mt(px(8.0))
+                            .child(
+                                div()
+                                    .id("replace-queue-dont-ask")
+                                    .flex()
+                                    .items_center()
+                                    .gap(px(8.0))
+                                    .cursor_pointer()
+                                    .on_click(|_, _, cx| {
+                                        let large_queue = cx.global::<Models>().large_queue.clone();
+                                        large_queue.update(cx, |large_queue, cx| {
+                                            large_queue.dont_ask_again =
+                                                !large_queue.dont_ask_again;
+                                            cx.notify();
+                                        });
+                                    })
+                                    .child(checkbox("replace-queue-dont-ask-check", dont_ask_again))
+                                    .child(div().text_sm().child("Don't ask again")),
+                            )
+                            .child(
+                                button()
+                                    .id("replace-queue-cancel")
+                                    .ml_auto()
+                                    .child("Cancel")
+                                    .on_click(|_, _, cx| close(cx, Choice::Cancel)),
+                            )
+                            .child(
+                                button()
+                                    .id("replace-queue-replace")
+                                    .intent(ButtonIntent::Primary)
+                                    .child("Replace")
+                                    .on_click(|_, _, cx| close(cx, Choice::Replace)),
+                            ),
+                    ),
+            )
+    }
+}
+
+pub fn replace_queue_dialog(count: usize) -> ReplaceQueueDialog {
+    ReplaceQueueDialog { count }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entries(paths: &[&str]) -> Vec<QueueEntry> {
+        paths
+            .iter()
+            .map(|path| QueueEntry::new(PathBuf::from(path), None, None))
+            .collect()
+    }
+
+    fn batch(range: std::ops::Range<usize>, at: Option<usize>) -> (Vec<usize>, Option<usize>) {
+        (range.collect(), at)
+    }
+
+    #[test]
+    fn only_asks_before_losing_tracks_from_a_long_queue() {
+        let queue = [Path::new("a"), Path::new("b"), Path::new("c")];
+
+        assert_eq!(confirm_replacing(2, &queue, &entries(&["d"])), Some(3));
+        assert_eq!(confirm_replacing(3, &queue, &entries(&["d"])), None);
+        assert_eq!(confirm_replacing(0, &queue, &entries(&["d"])), None);
+        assert_eq!(
+            confirm_replacing(2, &queue, &entries(&["c", "b", "a", "d"])),
+            None
+        );
+    }
+
+    #[test]
+    fn plays_next_after_the_current_track() {
+        assert_eq!(position_after(Some(3), 10), 4);
+        assert_eq!(position_after(Some(9), 10), 10);
+        assert_eq!(position_after(Some(12), 10), 10);
+        assert_eq!(position_after(None, 10), 10);
+    }
+
+    #[test]
+    fn queues_small_lists_at_once() {
+        let chunks = Chunks::new((0..10).collect(), Some(4), None);
+
+        assert_eq!(chunks.first, Vec::from_iter(0..10));
+        assert_eq!(chunks.start_at, Some(4));
+        assert!(chunks.batches.is_empty());
+    }
+
+    #[test]
+    fn plays_the_last_track_when_starting_past_the_end() {
+        let chunks = Chunks::new((0..10).collect(), Some(20), None);
+
+        assert_eq!(chunks.start_at, Some(9));
+    }
+
+    #[test]
+    fn appends_the_rest_in_batches() {
+        let count = FIRST_CHUNK_SIZE + BATCH_SIZE + 1;
+        let chunks = Chunks::new((0..count).collect(), Some(0), None);
+        let second = FIRST_CHUNK_SIZE + BATCH_SIZE;
+
+        assert_eq!(chunks.first, Vec::from_iter(0..FIRST_CHUNK_SIZE));
+        assert_eq!(
+            chunks.batches,
+            [
+                batch(FIRST_CHUNK_SIZE..second, None),
+                batch(second..count, None),
+            ]
+        );
+    }
+
+    #[test]
+    fn starts_the_first_chunk_at_the_track_to_play() {
+        let start = FIRST_CHUNK_SIZE + 10;
+        let end = start + FIRST_CHUNK_SIZE;
+        let chunks = Chunks::new((0..end).collect(), Some(start), None);
+
+        assert_eq!(chunks.first, Vec::from_iter(start..end));
+        assert_eq!(chunks.start_at, Some(0));
+        assert_eq!(chunks.batches, [batch(0..start, Some(0))]);
+    }
+
+    #[test]
+    fn keeps_tracks_played_next_together() {
+        let count = FIRST_CHUNK_SIZE + BATCH_SIZE + 1;
+        let chunks = Chunks::new((0..count).collect(), None, Some(5));
+        let second = FIRST_CHUNK_SIZE + BATCH_SIZE;
+
+        assert_eq!(
+            chunks.batches,
+            [
+                batch(FIRST_CHUNK_SIZE..second, Some(5 + FIRST_CHUNK_SIZE)),
+                batch(second..count, Some(5 + second)),
+            ]
+        );
+    }
+}
@@ -13,24 +13,44 @@ struct ScrollStateStorage {
     track_view_scroll: Option<f32>,
 }
 
-use crate::ui::{
-    command_palette::{Command, CommandManager},
-    library::{
-        playlist_view::{Import, PlaylistView},
-        sidebar::Sidebar,
-        update_playlist::UpdatePlaylist,
+use crate::{
+    library::scan::ScanEvent,
+    settings::SettingsGlobal,
+    ui::{
+        command_palette::{Command, CommandManager},
+        components::{
+            empty_state::{EmptyState, empty_state},
+            icons::{DISC, FOLDER_SEARCH},
+        },
+        global_actions::AddMusicFolder,
+        library::{
+            playlist_view::{Import, PlaylistView},
+            sidebar::Sidebar,
+            update_playlist::UpdatePlaylist,
+        },
     },
 };
 
 use super::models::Models;
 
 mod add_to_playlist;
+pub mod album_filters;
+mod album_progress;
 mod album_view;
+mod credits;
+mod edit_bpm;
+pub mod edit_gain;
+mod fix_grouping;
+mod missing_mbids;
+mod move_to_position;
+mod musicbrainz;
 mod navigation;
 mod playlist_view;
+mod related_albums;
 mod release_view;
 mod sidebar;
 mod track_listing;
+mod track_properties;
 mod track_view;
 mod update_playlist;
 
@@ -68,6 +88,44 @@ pub enum ViewSwitchMessage {
 
 impl EventEmitter<ViewSwitchMessage> for VecDeque<ViewSwitchMessage> {}
 
+/// The empty state shown by the album and track views when their table has no rows. `items` is
+/// the plural name of whatever the table lists.
+fn library_empty_state(id: &'static str, items: &str, cx: &App) -> EmptyState {
+    let add_music_folder = |_: &ClickEvent, window: &mut Window, cx: &mut App| {
+        window.dispatch_action(Box::new(AddMusicFolder), cx);
+    };
+
+    match cx.global::<Models>().scan_state.read(cx) {
+        ScanEvent::DiscoverProgress(progress, ..) => empty_state(id, "Scanning your library")
+            .icon(FOLDER_SEARCH)
+            .description(format!("Discovering files ({progress})")),
+        ScanEvent::ScanProgress { current, total, .. } => empty_state(id, "Scanning your library")
+            .icon(FOLDER_SEARCH)
+            .description(format!("Scanned {current} of {total} files")),
+        ScanEvent::Rebuilding { current, total } => empty_state(id, "Rebuilding your library")
+            .icon(FOLDER_SEARCH)
+            .description(format!("Rebuilt {current} of {total} files")),
+        ScanEvent::Cleaning(_) => empty_state(id, "Scanning your library").icon(FOLDER_SEARCH),
+        ScanEvent::ScanCompleteIdle
+        | ScanEvent::ScanCompleteWatching
+        | ScanEvent::Analyzing { .. } => {
+            let settings = cx.global::<SettingsGlobal>().model.read(cx);
+
+            if settings.scanning.paths.is_empty() {
+                empty_state(id, "Add your music")
+                    .icon(FOLDER_SEARCH)
+                    .description("Choose the folders your music is stored in to get started.")
+                    .action("Add Music Folder", add_music_folder)
+            } else {
+                empty_state(id, format!("No {items} found"))
+                    .icon(DISC)
+                    .description("Your music folders don't contain any supported audio files.")
+                    .action("Add Music Folder", add_music_folder)
+            }
+        }
+    }
+}
+
 fn make_view(
     message: &ViewSwitchMessage,
     cx: &mut App,
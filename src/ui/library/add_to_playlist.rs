@@ -1,27 +1,77 @@
-use std::sync::Arc;
+use std::{cmp::Reverse, sync::Arc};
 
 use gpui::{
-    App, AppContext, Context, Entity, IntoElement, ParentElement, Render, SharedString, Styled,
-    Window, div, px,
+    App, AppContext, Context, Entity, FocusHandle, FontWeight, InteractiveElement, IntoElement,
+    KeyDownEvent, ParentElement, Render, SharedString, Styled, Window, div, px,
 };
 use nucleo::Utf32String;
+use tracing::error;
 
 use crate::{
-    library::{db::LibraryAccess, types::PlaylistWithCount},
+    library::{db::LibraryAccess, playlist::add_tracks_to_playlist, types::PlaylistWithCount},
     ui::{
         components::{
-            icons::PLAYLIST_ADD,
+            icons::{CHECK, PLAYLIST_ADD, icon},
+            input::{EnrichedInputAction, TextInput},
             modal::modal,
-            palette::{ExtraItem, ExtraItemProvider, FinderItemLeft, Palette, PaletteItem},
+            palette::{
+                AcceptIntent, ExtraItem, ExtraItemProvider, FinderItemLeft, Palette, PaletteItem,
+            },
         },
         models::{Models, PlaylistEvent},
+        theme::Theme,
+        util::ui_px,
     },
 };
 
+/// Every playlist, with the ones that tracks were most recently added to or removed from first.
+fn recent_playlists(cx: &App) -> Vec<PlaylistWithCount> {
+    let mut playlists = cx
+        .get_all_playlists()
+        .map(|playlists| (*playlists).clone())
+        .unwrap_or_default();
+
+    playlists
+        .sort_by_key(|playlist| Reverse(playlist.last_modified.unwrap_or(playlist.created_at)));
+    playlists
+}
+
+fn emit_playlist_event(cx: &mut App, event: PlaylistEvent) {
+    let playlist_tracker = cx.global::<Models>().playlist_tracker.clone();
+    playlist_tracker.update(cx, |_, cx| cx.emit(event));
+}
+
+/// Creates a playlist with the given name and adds the track to it.
+fn create_and_add(cx: &mut App, name: &str, track_id: i64) {
+    let playlist_id = match cx.create_playlist(name) {
+        Ok(playlist_id) => playlist_id,
+        Err(err) => {
+            error!(?err, "Failed to create playlist: {err}");
+            return;
+        }
+    };
+    emit_playlist_event(cx, PlaylistEvent::PlaylistCreated(playlist_id));
+
+    if let Err(err) = cx.add_playlist_item(playlist_id, track_id) {
+        error!(
+            ?err,
+            "Failed to add track {track_id} to playlist {playlist_id}: {err}"
+        );
+    }
+    emit_playlist_event(cx, PlaylistEvent::PlaylistUpdated(playlist_id));
+}
+
 // i64 here is the track ID
 impl PaletteItem for (i64, PlaylistWithCount) {
     fn left_content(&self, cx: &mut App) -> Option<FinderItemLeft> {
-        self.1.left_content(cx)
+        let has_track = cx.playlist_has_track(self.1.id, self.0).ok().flatten();
+
+        // playlists that already have the track are checked, and choosing them removes it
+        if has_track.is_some() {
+            Some(FinderItemLeft::Icon(CHECK.into()))
+        } else {
+            self.1.left_content(cx)
+        }
     }
 
     fn middle_content(&self, cx: &mut App) -> SharedString {
@@ -40,20 +90,29 @@ impl PaletteItem for (i64, PlaylistWithCount) {
 }
 
 type MatcherFunc = Box<dyn Fn(&Arc<(i64, PlaylistWithCount)>, &mut App) -> Utf32String + 'static>;
-type OnAccept = Box<dyn Fn(&Arc<(i64, PlaylistWithCount)>, &mut App) + 'static>;
+type OnAccept = Box<dyn Fn(&Arc<(i64, PlaylistWithCount)>, AcceptIntent, &mut App) + 'static>;
+
+/// The name of a new playlist being typed in, in place of the "New playlist…" row.
+struct Naming {
+    input: Entity<TextInput>,
+    focus_handle: FocusHandle,
+}
 
 pub struct AddToPlaylist {
     show: Entity<bool>,
+    track_id: i64,
     palette: Entity<Palette<(i64, PlaylistWithCount), MatcherFunc, OnAccept>>,
+    naming: Option<Naming>,
 }
 
 impl AddToPlaylist {
     pub fn new(cx: &mut App, show: Entity<bool>, track_id: i64) -> Entity<Self> {
         cx.new(|cx| {
             cx.observe(&show, move |this: &mut Self, _, cx| {
+                this.naming = None;
+
                 this.palette.update(cx, |this, cx| {
-                    let new_playlists = (*cx.get_all_playlists().unwrap())
-                        .clone()
+                    let new_playlists = recent_playlists(cx)
                         .into_iter()
                         .map(|playlist| (track_id, playlist))
                         .map(Arc::new)
@@ -72,7 +131,7 @@ impl AddToPlaylist {
 
             let show_clone = show.clone();
 
-            let on_accept: OnAccept = Box::new(move |playlist, cx| {
+            let on_accept: OnAccept = Box::new(move |playlist, _, cx| {
                 let has_track = cx
                     .playlist_has_track(playlist.1.id, track_id)
                     .ok()
@@ -84,17 +143,12 @@ impl AddToPlaylist {
                     cx.add_playlist_item(playlist.1.id, track_id).unwrap();
                 }
 
-                let playlist_tracker = cx.global::<Models>().playlist_tracker.clone();
-
-                playlist_tracker.update(cx, |_, cx| {
-                    cx.emit(PlaylistEvent::PlaylistUpdated(playlist.1.id));
-                });
+                emit_playlist_event(cx, PlaylistEvent::PlaylistUpdated(playlist.1.id));
 
                 show_clone.write(cx, false);
             });
 
-            let items = (*cx.get_all_playlists().unwrap())
-                .clone()
+            let items = recent_playlists(cx)
                 .into_iter()
                 .map(|playlist| (track_id, playlist))
                 .map(Arc::new)
@@ -103,14 +157,31 @@ impl AddToPlaylist {
             let palette = Palette::new(cx, items, matcher, on_accept, &show);
 
             let show_for_create = show.clone();
+            let weak_self = cx.weak_entity();
             let provider: ExtraItemProvider = Arc::new(move |query: &str| {
                 let name = query.trim();
+
+                // with nothing typed, the row asks for a name instead of using the query
                 if name.is_empty() {
-                    return Vec::new();
+                    let weak_self = weak_self.clone();
+
+                    return vec![ExtraItem {
+                        left: Some(FinderItemLeft::Icon(PLAYLIST_ADD.into())),
+                        middle: "New playlist…".into(),
+                        right: None,
+                        on_accept: Arc::new(move |cx| {
+                            let weak_self = weak_self.clone();
+                            cx.defer(move |cx| {
+                                weak_self
+                                    .update(cx, |this: &mut Self, cx| this.start_naming(cx))
+                                    .ok();
+                            });
+                        }),
+                    }];
                 }
 
                 let name_string = name.to_string();
-                let display = format!("Create new playlist '{}'", name_string);
+                let display = format!("New playlist '{}'", name_string);
 
                 let show_clone2 = show_for_create.clone();
 
@@ -119,13 +190,240 @@ impl AddToPlaylist {
                     middle: display.into(),
                     right: None,
                     on_accept: Arc::new(move |cx| {
-                        let playlist_id = cx.create_playlist(&name_string).unwrap();
-                        cx.add_playlist_item(playlist_id, track_id).unwrap();
+                        create_and_add(cx, &name_string, track_id);
+                        show_clone2.write(cx, false);
+                    }),
+                }]
+            });
+
+            cx.update_entity(&palette, |palette, cx| {
+                palette.register_extra_provider(provider.clone(), cx);
+            });
+
+            Self {
+                show,
+                track_id,
+                palette,
+                naming: None,
+            }
+        })
+    }
+
+    /// Replaces the "New playlist…" row with a text input for the new playlist's name.
+    fn start_naming(&mut self, cx: &mut Context<Self>) {
+        let focus_handle = cx.focus_handle();
+        let weak_self = cx.weak_entity();
+
+        // the input is still being updated when Enter is handled, so create the playlist afterwards
+        let handler = move |action, _: &mut Window, cx: &mut App| {
+            if let EnrichedInputAction::Accept(_) = action {
+                let weak_self = weak_self.clone();
+                cx.defer(move |cx| {
+                    weak_self
+                        .update(cx, |this: &mut Self, cx| this.commit_naming(cx))
+                        .ok();
+                });
+            }
+        };
+
+        let input = TextInput::new(
+            cx,
+            focus_handle.clone(),
+            None,
+            Some("Playlist name".into()),
+            Some(Box::new(handler)),
+        );
+
+        self.naming = Some(Naming {
+            input,
+            focus_handle,
+        });
+        cx.notify();
+    }
+
+    fn cancel_naming(&mut self, cx: &mut Context<Self>) {
+        self.naming = None;
+        cx.notify();
+    }
+
+    /// Creates the playlist that was named and adds the track to it. Nothing happens until a name
+    /// has been typed.
+    fn commit_naming(&mut self, cx: &mut Context<Self>) {
+        let Some(naming) = &self.naming else {
+            return;
+        };
+
+        let name = naming.input.read(cx).content.trim().to_string();
+        if name.is_empty() {
+            return;
+        }
+
+        self.naming = None;
+        create_and_add(cx, &name, self.track_id);
+        self.show.write(cx, false);
+    }
+}
+
+impl Render for AddToPlaylist {
+    fn render(&mut self, window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let show = self.show.clone();
+        let palette = self.palette.clone();
+        let show_read = *self.show.read(cx);
+
+        if show_read {
+            if let Some(naming) = &self.naming {
+                naming.focus_handle.focus(window, cx);
+            } else {
+                cx.update_entity(&palette, |palette, cx| {
+                    palette.focus(window, cx);
+                });
+            }
+
+            let theme = cx.global::<Theme>();
+            let naming_row = self.naming.as_ref().map(|naming| {
+                div()
+                    .flex()
+                    .items_center()
+                    .gap(px(8.0))
+                    .w_full()
+                    .px(px(12.0))
+                    .h(ui_px(40.0, cx))
+                    .text_sm()
+                    .border_b(px(1.0))
+                    .border_color(theme.border_color)
+                    .child(icon(PLAYLIST_ADD).size(px(16.0)).text_color(theme.text))
+                    .child(
+                        div()
+                            .flex_grow()
+                            .on_key_down(cx.listener(|this, ev: &KeyDownEvent, _, cx| {
+                                // back to the list, rather than closing it
+                                if ev.keystroke.key == "escape" {
+                                    this.cancel_naming(cx);
+                                    cx.stop_propagation();
+                                }
+                            }))
+                            .border_1()
+                            .border_color(theme.textbox_border)
+                            .rounded(px(3.0))
+                            .px(px(3.0))
+                            .bg(theme.textbox_background)
+                            .font_weight(FontWeight::NORMAL)
+                            .child(naming.input.clone()),
+                    )
+            });
 
-                        let playlist_tracker = cx.global::<Models>().playlist_tracker.clone();
-                        playlist_tracker.update(cx, |_, cx| {
-                            cx.emit(PlaylistEvent::PlaylistUpdated(playlist_id));
+            modal()
+                .child(
+                    div()
+                        .w(px(550.0))
+                        .h(px(300.0))
+                        .flex()
+                        .flex_col()
+                        .children(naming_row)
+                        .child(div().flex_1().min_h(px(0.0)).child(palette.clone())),
+                )
+                .on_exit(move |_, cx| {
+                    show.update(cx, |show, cx| {
+                        *show = false;
+                        cx.update_entity(&palette, |palette, cx| {
+                            palette.reset(cx);
                         });
+                        cx.notify();
+                    })
+                })
+                .into_any_element()
+        } else {
+            div().into_any_element()
+        }
+    }
+}
+
+/// A playlist that several tracks can be added to at once.
+#[derive(PartialEq)]
+pub struct PlaylistTarget(PlaylistWithCount);
+
+impl PaletteItem for PlaylistTarget {
+    fn left_content(&self, cx: &mut App) -> Option<FinderItemLeft> {
+        self.0.left_content(cx)
+    }
+
+    fn middle_content(&self, _: &mut App) -> SharedString {
+        format!("Add to {}", self.0.name).into()
+    }
+
+    fn right_content(&self, cx: &mut App) -> Option<SharedString> {
+        self.0.right_content(cx)
+    }
+}
+
+fn playlist_targets(cx: &App) -> Vec<Arc<PlaylistTarget>> {
+    recent_playlists(cx)
+        .into_iter()
+        .map(PlaylistTarget)
+        .map(Arc::new)
+        .collect()
+}
+
+type TracksMatcherFunc = Box<dyn Fn(&Arc<PlaylistTarget>, &mut App) -> Utf32String + 'static>;
+type TracksOnAccept = Box<dyn Fn(&Arc<PlaylistTarget>, AcceptIntent, &mut App) + 'static>;
+
+/// Like [`AddToPlaylist`], but for several tracks at once (for example, a whole album). Tracks
+/// that are already in the chosen playlist are skipped.
+pub struct AddTracksToPlaylist {
+    show: Entity<bool>,
+    palette: Entity<Palette<PlaylistTarget, TracksMatcherFunc, TracksOnAccept>>,
+}
+
+impl AddTracksToPlaylist {
+    pub fn new(cx: &mut App, show: Entity<bool>, track_ids: Arc<Vec<i64>>) -> Entity<Self> {
+        cx.new(|cx| {
+            cx.observe(&show, move |this: &mut Self, _, cx| {
+                this.palette.update(cx, |this, cx| {
+                    cx.emit(playlist_targets(cx));
+                    this.reset(cx);
+                });
+
+                cx.notify();
+            })
+            .detach();
+
+            let matcher: TracksMatcherFunc =
+                Box::new(|playlist, _| playlist.0.name.0.to_string().into());
+
+            let show_clone = show.clone();
+            let track_ids_clone = track_ids.clone();
+
+            let on_accept: TracksOnAccept = Box::new(move |playlist, _, cx| {
+                add_tracks_to_playlist(cx, playlist.0.id, (*track_ids_clone).clone());
+                show_clone.write(cx, false);
+            });
+
+            let palette = Palette::new(cx, playlist_targets(cx), matcher, on_accept, &show);
+
+            let show_for_create = show.clone();
+            let provider: ExtraItemProvider = Arc::new(move |query: &str| {
+                let name = query.trim();
+                if name.is_empty() {
+                    return Vec::new();
+                }
+
+                let name_string = name.to_string();
+                let display = format!("Create new playlist '{}'", name_string);
+
+                let show_clone2 = show_for_create.clone();
+                let track_ids = track_ids.clone();
+
+                vec![ExtraItem {
+                    left: Some(FinderItemLeft::Icon(PLAYLIST_ADD.into())),
+                    middle: display.into(),
+                    right: None,
+                    on_accept: Arc::new(move |cx| {
+                        match cx.create_playlist(&name_string) {
+                            Ok(playlist_id) => {
+                                add_tracks_to_playlist(cx, playlist_id, (*track_ids).clone())
+                            }
+                            Err(err) => error!(?err, "Failed to create playlist: {err}"),
+                        }
 
                         show_clone2.write(cx, false);
                     }),
@@ -141,7 +439,7 @@ impl AddToPlaylist {
     }
 }
 
-impl Render for AddToPlaylist {
+impl Render for AddTracksToPlaylist {
     fn render(&mut self, window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
         let show = self.show.clone();
         let palette = self.palette.clone();
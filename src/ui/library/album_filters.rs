@@ -0,0 +1,209 @@
+use gpui::*;
+use tracing::error;
+
+use crate::{
+    library::{
+        album_query::{AlbumFilter, AlbumFilterOptions, AlbumFormat},
+        db::LibraryAccessAsync,
+        scan::ScanEvent,
+    },
+    settings::SettingsGlobal,
+    ui::{
+        components::button::{ButtonIntent, ButtonStyle, button},
+        models::Models,
+    },
+};
+
+/// Loads which album filters match any albums, and loads them again whenever a scan finishes,
+/// hidden albums are shown, hidden, or changed, or original release dates are turned on or off.
+pub fn watch_filter_options(cx: &mut App) {
+    let scan_state = cx.global::<Models>().scan_state.clone();
+    let show_hidden = cx.global::<Models>().show_hidden_albums.clone();
+    let settings = cx.global::<SettingsGlobal>().model.clone();
+
+    cx.observe(&scan_state, |state, cx| {
+        if matches!(
+            state.read(cx),
+            ScanEvent::ScanCompleteIdle | ScanEvent::ScanCompleteWatching
+        ) {
+            load_filter_options(cx);
+        }
+    })
+    .detach();
+
+    cx.observe(&show_hidden, |_, cx| load_filter_options(cx))
+        .detach();
+
+    // albums can move to another decade when the other date is used
+    let mut last_prefer_original = settings.read(cx).interface.prefer_original_date;
+    cx.observe(&settings, move |settings, cx| {
+        let prefer_original = settings.read(cx).interface.prefer_original_date;
+
+        if prefer_original != last_prefer_original {
+            last_prefer_original = prefer_original;
+            load_filter_options(cx);
+        }
+    })
+    .detach();
+
+    load_filter_options(cx);
+}
+
+fn load_filter_options(cx: &mut App) {
+    let models = cx.global::<Models>();
+    let options = models.album_filter_options.clone();
+    let include_hidden = *models.show_hidden_albums.read(cx);
+    let settings = cx.global::<SettingsGlobal>().model.read(cx);
+    let task =
+        cx.get_album_filter_options_async(include_hidden, settings.interface.prefer_original_date);
+
+    cx.spawn(async move |cx| match task.await {
+        Ok(loaded) => {
+            options
+                .update(cx, |options, cx| {
+                    *options = loaded;
+                    cx.notify();
+                })
+                .ok();
+        }
+        Err(err) => error!(?err, "Couldn't load the album filters: {err}"),
+    })
+    .detach();
+}
+
+fn update_filter(cx: &mut App, update: impl FnOnce(&mut AlbumFilter)) {
+    let filter = cx.global::<Models>().album_filter.clone();
+    filter.update(cx, |filter, cx| {
+        update(filter);
+        cx.notify();
+    });
+}
+
+/// Names a decade the way people usually say it: "70s" for the 1970s, but "1900s" and "2010s".
+fn decade_label(decade: i64) -> String {
+    if (1910..2000).contains(&decade) {
+        format!("{}s", decade % 100)
+    } else {
+        format!("{decade}s")
+    }
+}
+
+/// A filter that's picked by clicking on it. Picked filters are highlighted.
+fn chip(
+    id: impl Into<ElementId>,
+    label: impl Into<SharedString>,
+    active: bool,
+    on_click: impl Fn(&mut App) + 'static,
+) -> impl IntoElement {
+    button()
+        .id(id)
+        .intent(if active {
+            ButtonIntent::Primary
+        } else {
+            ButtonIntent::Secondary
+        })
+        .on_click(move |_, _, cx| on_click(cx))
+        .child(label.into())
+}
+
+/// One-click filters for the album list, shown above it. Filters of different kinds are combined,
+/// and picking another decade or format replaces the one that was picked before. Only filters
+/// that match any albums are offered (along with any that are already picked).
+pub struct AlbumFilterBar {
+    filter: Entity<AlbumFilter>,
+    options: Entity<AlbumFilterOptions>,
+}
+
+impl AlbumFilterBar {
+    pub fn new(cx: &mut App) -> Entity<Self> {
+        cx.new(|cx| {
+            let filter = cx.global::<Models>().album_filter.clone();
+            let options = cx.global::<Models>().album_filter_options.clone();
+
+            cx.observe(&filter, |_, _, cx| cx.notify()).detach();
+            cx.observe(&options, |_, _, cx| cx.notify()).detach();
+
+            Self { filter, options }
+        })
+    }
+}
+
+impl Render for AlbumFilterBar {
+    fn render(&mut self, _: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let filter = *self.filter.read(cx);
+        let options = self.options.read(cx);
+
+        let mut decades = options.decades.clone();
+        if let Some(decade) = filter.decade
+            && !decades.contains(&decade)
+        {
+            decades.push(decade);
+            decades.sort();
+        }
+
+        let formats = [
+            (AlbumFormat::Lossless, "Lossless", options.lossless),
+            (AlbumFormat::Lossy, "Lossy", options.lossy),
+        ];
+        let show_added = options.added_this_month || filter.added_this_month;
+
+        div()
+            .flex()
+            .flex_wrap()
+            .gap(px(6.0))
+            .children(decades.into_iter().map(|decade| {
+                let active = filter.decade == Some(decade);
+
+                chip(
+                    ("album-filter-decade", decade as u32),
+                    decade_label(decade),
+                    active,
+                    move |cx| {
+                        update_filter(cx, |filter| filter.decade = (!active).then_some(decade))
+                    },
+                )
+            }))
+            .children(
+                formats
+                    .into_iter()
+                    .filter(|(format, _, available)| *available || filter.format == Some(*format))
+                    .map(|(format, label, _)| {
+                        let active = filter.format == Some(format);
+
+                        chip(
+                            ("album-filter-format", format as u32),
+                            label,
+                            active,
+                            move |cx| {
+                                update_filter(cx, |filter| {
+                                    filter.format = (!active).then_some(format)
+                                })
+                            },
+                        )
+                    }),
+            )
+            .when(show_added, |this| {
+                this.child(chip(
+                    "album-filter-added",
+                    "Added This Month",
+                    filter.added_this_month,
+                    |cx| {
+                        update_filter(cx, |filter| {
+                            filter.added_this_month = !filter.added_this_month;
+                        })
+                    },
+                ))
+            })
+            .when(!filter.is_empty(), |this| {
+                this.child(
+                    button()
+                        .id("album-filter-clear")
+                        .style(ButtonStyle::Minimal)
+                        .on_click(|_, _, cx| {
+                            update_filter(cx, |filter| *filter = AlbumFilter::default())
+                        })
+                        .child("Clear Filters"),
+                )
+            })
+    }
+}
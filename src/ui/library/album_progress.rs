@@ -0,0 +1,417 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use gpui::{
+    App, AppContext, Context, Entity, IntoElement, ParentElement, Render, Styled, Window, div, px,
+    relative,
+};
+use rustc_hash::{FxHashMap, FxHashSet};
+
+use crate::{
+    library::types::Track,
+    ui::{
+        models::{Models, PlaybackInfo},
+        theme::Theme,
+    },
+};
+
+/// The album track that's playing, and how far through it playback is.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PlayingTrack {
+    /// The track's index in the album's track list.
+    pub index: usize,
+    /// From 0 to 1.
+    pub fraction: f32,
+}
+
+/// Where playback is within the album, when the queue is the album.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AlbumRemaining {
+    /// The playing track's place in the queue, counting from 1.
+    pub track: usize,
+    pub of: usize,
+    /// Whole minutes (rounded up) until the album finishes. Unknown while shuffling, since the
+    /// tracks left aren't the ones after the playing track.
+    pub minutes: Option<u64>,
+}
+
+/// Whether the queue holds exactly the album's tracks, each of them once, in any order.
+fn queue_matches_album(queue: &[PathBuf], tracks: &[Track]) -> bool {
+    if queue.len() != tracks.len() {
+        return false;
+    }
+
+    let album: FxHashSet<&Path> = tracks
+        .iter()
+        .map(|track| track.location.as_path())
+        .collect();
+    let queued: FxHashSet<&Path> = queue.iter().map(PathBuf::as_path).collect();
+
+    album.len() == tracks.len() && queued == album
+}
+
+fn playing_track(
+    tracks: &[Track],
+    current: &Path,
+    position: u64,
+    duration: u64,
+) -> Option<PlayingTrack> {
+    let index = tracks.iter().position(|track| track.location == current)?;
+    let fraction = if duration > 0 {
+        (position as f32 / duration as f32).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+
+    Some(PlayingTrack { index, fraction })
+}
+
+fn album_remaining(
+    tracks: &[Track],
+    queue: &[PathBuf],
+    queue_position: usize,
+    position: u64,
+    duration: u64,
+    shuffling: bool,
+) -> Option<AlbumRemaining> {
+    if queue_position >= queue.len() || !queue_matches_album(queue, tracks) {
+        return None;
+    }
+
+    let minutes = (!shuffling).then(|| {
+        let durations: FxHashMap<&Path, u64> = tracks
+            .iter()
+            .map(|track| (track.location.as_path(), track.duration_millis()))
+            .collect();
+
+        let upcoming: u64 = queue[queue_position + 1..]
+            .iter()
+            .filter_map(|path| durations.get(path.as_path()))
+            .sum();
+
+        (duration.saturating_sub(position) + upcoming).div_ceil(60_000)
+    });
+
+    Some(AlbumRemaining {
+        track: queue_position + 1,
+        of: queue.len(),
+        minutes,
+    })
+}
+
+/// Follows playback while an album's page is open, and narrows it down to what the page shows:
+/// the playing track's progress, and how much of the album is left. Each is kept in its own model
+/// so that only the views showing it are redrawn when it changes.
+pub struct AlbumProgress {
+    tracks: Arc<Vec<Track>>,
+    pub playing: Entity<Option<PlayingTrack>>,
+    pub remaining: Entity<Option<AlbumRemaining>>,
+}
+
+impl AlbumProgress {
+    pub fn new(cx: &mut App, tracks: Arc<Vec<Track>>) -> Entity<Self> {
+        cx.new(|cx| {
+            let playback_info = cx.global::<PlaybackInfo>();
+            let position = playback_info.position.clone();
+            let duration = playback_info.duration.clone();
+            let current_track = playback_info.current_track.clone();
+            let shuffling = playback_info.shuffling.clone();
+            let queue = cx.global::<Models>().queue.clone();
+
+            cx.observe(&position, |this: &mut Self, _, cx| this.refresh(cx))
+                .detach();
+            cx.observe(&duration, |this: &mut Self, _, cx| this.refresh(cx))
+                .detach();
+            cx.observe(&current_track, |this: &mut Self, _, cx| this.refresh(cx))
+                .detach();
+            cx.observe(&shuffling, |this: &mut Self, _, cx| this.refresh(cx))
+                .detach();
+            cx.observe(&queue, |this: &mut Self, _, cx| this.refresh(cx))
+                .detach();
+
+            let mut this = Self {
+                tracks,
+                playing: cx.new(|_| None),
+                remaining: cx.new(|_| None),
+            };
+
+            this.refresh(cx);
+            this
+        })
+    }
+
+    fn refresh(&mut self, cx: &mut Context<Self>) {
+        let playback_info = cx.global::<PlaybackInfo>();
+        let position = *playback_info.position.read(cx);
+        let duration = *playback_info.duration.read(cx);
+        let shuffling = *playback_info.shuffling.read(cx);
+
+        let playing = playback_info
+            .current_track
+            .read(cx)
+            .as_ref()
+            .and_then(|current| {
+                playing_track(&self.tracks, current.get_path(), position, duration)
+            });
+
+        let remaining = playing.and_then(|_| {
+            let queue = cx.global::<Models>().queue.read(cx);
+            let items = queue.data.read().expect("could not read queue");
+
+            // checked first, so that long queues aren't copied every second
+            if items.len() != self.tracks.len() {
+                return None;
+            }
+
+            let paths: Vec<PathBuf> = items.iter().map(|item| item.get_path().clone()).collect();
+            album_remaining(
+                &self.tracks,
+                &paths,
+                queue.position?,
+                position,
+                duration,
+                shuffling,
+            )
+        });
+
+        if *self.playing.read(cx) != playing {
+            self.playing.write(cx, playing);
+        }
+
+        if *self.remaining.read(cx) != remaining {
+            self.remaining.write(cx, remaining);
+        }
+    }
+}
+
+/// A thin bar under a track in an album's listing, showing playback's progress through it while
+/// it's playing.
+pub struct TrackProgressBar {
+    index: usize,
+    playing: Entity<Option<PlayingTrack>>,
+}
+
+impl TrackProgressBar {
+    pub fn new(cx: &mut App, index: usize, playing: Entity<Option<PlayingTrack>>) -> Entity<Self> {
+        cx.new(|cx| {
+            // only the bars of the tracks that started or stopped playing need to be redrawn
+            let mut was_playing = false;
+            cx.observe(&playing, move |this: &mut Self, playing, cx| {
+                let is_playing = playing
+                    .read(cx)
+                    .is_some_and(|playing| playing.index == this.index);
+
+                if is_playing || was_playing {
+                    cx.notify();
+                }
+
+                was_playing = is_playing;
+            })
+            .detach();
+
+            Self { index, playing }
+        })
+    }
+}
+
+impl Render for TrackProgressBar {
+    fn render(&mut self, _: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let theme = cx.global::<Theme>();
+        let fraction = self
+            .playing
+            .read(cx)
+            .filter(|playing| playing.index == self.index)
+            .map(|playing| playing.fraction);
+
+        match fraction {
+            Some(fraction) => div()
+                .absolute()
+                .bottom_0()
+                .left_0()
+                .h(px(2.0))
+                .w(relative(fraction))
+                .bg(theme.slider_foreground),
+            None => div(),
+        }
+    }
+}
+
+/// A line in an album's header with the playing track's place in the album, and how long the
+/// album has left.
+pub struct AlbumProgressSummary {
+    remaining: Entity<Option<AlbumRemaining>>,
+}
+
+impl AlbumProgressSummary {
+    pub fn new(cx: &mut App, remaining: Entity<Option<AlbumRemaining>>) -> Entity<Self> {
+        cx.new(|cx| {
+            cx.observe(&remaining, |_, _, cx| cx.notify()).detach();
+
+            Self { remaining }
+        })
+    }
+}
+
+impl Render for AlbumProgressSummary {
+    fn render(&mut self, _: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let theme = cx.global::<Theme>();
+
+        let Some(remaining) = *self.remaining.read(cx) else {
+            return div();
+        };
+
+        let mut summary = format!("Track {} of {}", remaining.track, remaining.of);
+
+        match remaining.minutes {
+            Some(minutes) if minutes >= 60 => {
+                summary += &format!(
+                    " · {} hr {} min remaining in album",
+                    minutes / 60,
+                    minutes % 60
+                );
+            }
+            Some(minutes) => summary += &format!(" · {minutes} min remaining in album"),
+            None => (),
+        }
+
+        div()
+            .text_sm()
+            .text_color(theme.text_secondary)
+            .pb(px(10.0))
+            .child(summary)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn track(name: &str, duration_ms: i64) -> Track {
+        Track {
+            id: 0,
+            title: name.to_string().into(),
+            title_sortable: name.to_string().into(),
+            album_id: None,
+            track_number: None,
+            disc_number: None,
+            duration: duration_ms / 1000,
+            duration_ms: Some(duration_ms),
+            created_at: Default::default(),
+            genres: None,
+            tags: None,
+            location: PathBuf::from(format!("/music/{name}.flac")),
+            artist_names: None,
+            bpm: None,
+            bpm_estimated: false,
+            extra_tags_json: None,
+            gain_offset: 0.0,
+            recording_mbid: None,
+            comment: None,
+            comment_edited: false,
+            album_title: None,
+            album_artist_name: None,
+            album_vinyl_numbering: false,
+        }
+    }
+
+    /// An album of three tracks, two, three and four minutes long.
+    fn album() -> Vec<Track> {
+        vec![
+            track("a", 120_000),
+            track("b", 180_000),
+            track("c", 240_000),
+        ]
+    }
+
+    fn queue(names: &[&str]) -> Vec<PathBuf> {
+        names
+            .iter()
+            .map(|name| PathBuf::from(format!("/music/{name}.flac")))
+            .collect()
+    }
+
+    #[test]
+    fn matches_the_album_in_any_order() {
+        let album = album();
+
+        assert!(queue_matches_album(&queue(&["a", "b", "c"]), &album));
+        assert!(queue_matches_album(&queue(&["c", "a", "b"]), &album));
+        assert!(!queue_matches_album(&queue(&["a", "b"]), &album));
+        assert!(!queue_matches_album(&queue(&["a", "b", "c", "d"]), &album));
+        assert!(!queue_matches_album(&queue(&["a", "a", "b"]), &album));
+    }
+
+    #[test]
+    fn finds_the_playing_track() {
+        let album = album();
+        let current = Path::new("/music/b.flac");
+
+        assert_eq!(
+            playing_track(&album, current, 45_000, 180_000),
+            Some(PlayingTrack {
+                index: 1,
+                fraction: 0.25
+            })
+        );
+        assert_eq!(
+            playing_track(&album, Path::new("/music/other.flac"), 0, 180_000),
+            None
+        );
+    }
+
+    #[test]
+    fn keeps_the_fraction_in_range() {
+        let album = album();
+        let current = Path::new("/music/a.flac");
+        let fraction =
+            |position, duration| playing_track(&album, current, position, duration).unwrap();
+
+        assert_eq!(fraction(200_000, 120_000).fraction, 1.0);
+        assert_eq!(fraction(1_000, 0).fraction, 0.0);
+    }
+
+    #[test]
+    fn counts_the_time_left_in_the_album() {
+        let album = album();
+        let queue = queue(&["a", "b", "c"]);
+
+        // a minute into the second track: two minutes of it left, then four of the third
+        assert_eq!(
+            album_remaining(&album, &queue, 1, 60_000, 180_000, false),
+            Some(AlbumRemaining {
+                track: 2,
+                of: 3,
+                minutes: Some(6)
+            })
+        );
+        // partial minutes are rounded up
+        let remaining = album_remaining(&album, &queue, 2, 200_000, 240_000, false).unwrap();
+        assert_eq!(remaining.minutes, Some(1));
+    }
+
+    #[test]
+    fn only_knows_the_time_left_when_not_shuffling() {
+        let album = album();
+        let queue = queue(&["c", "a", "b"]);
+
+        let remaining = album_remaining(&album, &queue, 0, 0, 240_000, true).unwrap();
+
+        assert_eq!((remaining.track, remaining.minutes), (1, None));
+    }
+
+    #[test]
+    fn shows_nothing_unless_the_queue_is_the_album() {
+        let album = album();
+
+        assert_eq!(
+            album_remaining(&album, &queue(&["a", "b"]), 0, 0, 120_000, false),
+            None
+        );
+        assert_eq!(
+            album_remaining(&album, &queue(&["a", "b", "c"]), 3, 0, 120_000, false),
+            None
+        );
+    }
+}
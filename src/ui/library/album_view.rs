@@ -7,17 +7,29 @@ use crate::{
         scan::ScanEvent,
         types::{Album, table::AlbumColumn},
     },
+    settings::SettingsGlobal,
     ui::{
-        components::table::{Table, TableEvent, table_data::TABLE_MAX_WIDTH},
+        components::{
+            button::{ButtonSize, ButtonStyle, button},
+            icons::{CHECK, SHUFFLE, icon},
+            table::{Table, TableEvent, table_data::TABLE_MAX_WIDTH},
+        },
+        global_actions::{PlayRandomAlbum, ToggleHiddenAlbums},
         models::Models,
     },
 };
 
-use super::ViewSwitchMessage;
+use super::{
+    ViewSwitchMessage, album_filters::AlbumFilterBar, library_empty_state,
+    missing_mbids::MissingMbids,
+};
 
 #[derive(Clone)]
 pub struct AlbumView {
     table: Entity<Table<Album, AlbumColumn>>,
+    show_hidden: Entity<bool>,
+    show_missing_mbids: Entity<bool>,
+    missing_mbids: Entity<MissingMbids>,
 }
 
 impl AlbumView {
@@ -47,6 +59,9 @@ impl AlbumView {
                 initial_settings.as_ref(),
             );
 
+            let filter_bar = AlbumFilterBar::new(cx);
+            table.update(cx, |table, _| table.set_toolbar(filter_bar));
+
             let table_clone = table.clone();
 
             cx.observe(&state, move |_: &mut AlbumView, e, cx| {
@@ -65,7 +80,53 @@ impl AlbumView {
             })
             .detach();
 
-            AlbumView { table }
+            let show_hidden = cx.global::<Models>().show_hidden_albums.clone();
+            let table_clone = table.clone();
+
+            // also notified when an album is hidden, unhidden or regrouped
+            cx.observe(&show_hidden, move |_: &mut AlbumView, _, cx| {
+                table_clone.update(cx, |_, cx| cx.emit(TableEvent::NewRows));
+                cx.notify();
+            })
+            .detach();
+
+            let album_filter = cx.global::<Models>().album_filter.clone();
+            let table_clone = table.clone();
+
+            cx.observe(&album_filter, move |_: &mut AlbumView, _, cx| {
+                table_clone.update(cx, |_, cx| cx.emit(TableEvent::NewRows));
+                cx.notify();
+            })
+            .detach();
+
+            // albums are sorted by the date that's preferred, so they're reloaded when that changes
+            let settings = cx.global::<SettingsGlobal>().model.clone();
+            let table_clone = table.clone();
+            let mut last_prefer_original = settings.read(cx).interface.prefer_original_date;
+
+            cx.observe(&settings, move |_: &mut AlbumView, settings, cx| {
+                let prefer_original = settings.read(cx).interface.prefer_original_date;
+
+                if prefer_original != last_prefer_original {
+                    last_prefer_original = prefer_original;
+                    table_clone.update(cx, |_, cx| cx.emit(TableEvent::NewRows));
+                }
+            })
+            .detach();
+
+            // the empty state depends on both of these
+            cx.observe(&table, |_, _, cx| cx.notify()).detach();
+            cx.observe(&state, |_, _, cx| cx.notify()).detach();
+
+            let show_missing_mbids = cx.new(|_| false);
+            let missing_mbids = MissingMbids::new(cx, show_missing_mbids.clone());
+
+            AlbumView {
+                table,
+                show_hidden,
+                show_missing_mbids,
+                missing_mbids,
+            }
         })
     }
 
@@ -75,7 +136,15 @@ impl AlbumView {
 }
 
 impl Render for AlbumView {
-    fn render(&mut self, _: &mut Window, _: &mut Context<Self>) -> impl IntoElement {
+    fn render(&mut self, _: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let table = self.table.read(cx);
+        // when no albums match the filters, the table is still shown so they can be changed
+        let filtered = !cx.global::<Models>().album_filter.read(cx).is_empty();
+        let is_empty = !filtered
+            && !table.is_loading()
+            && table.get_items().is_none_or(|items| items.is_empty());
+        let show_hidden = *self.show_hidden.read(cx);
+
         div()
             .flex()
             .flex_col()
@@ -84,6 +153,58 @@ impl Render for AlbumView {
             .max_w(px(TABLE_MAX_WIDTH))
             .pt(px(10.0))
             .pb(px(0.0))
-            .child(self.table.clone())
+            .relative()
+            .map(|this| {
+                if is_empty {
+                    this.child(library_empty_state("albums-empty", "albums", cx))
+                } else {
+                    this.child(self.table.clone())
+                }
+            })
+            .when(!is_empty || show_hidden, |this| {
+                this.child(
+                    div()
+                        .absolute()
+                        .top(px(10.0))
+                        .right(px(16.0))
+                        .flex()
+                        .gap(px(4.0))
+                        .child(
+                            button()
+                                .id("show-hidden-albums")
+                                .size(ButtonSize::Regular)
+                                .style(ButtonStyle::Minimal)
+                                .on_click(|_, window, cx| {
+                                    window.dispatch_action(Box::new(ToggleHiddenAlbums), cx);
+                                })
+                                .when(show_hidden, |this| {
+                                    this.child(icon(CHECK).size(px(14.0)).my_auto())
+                                })
+                                .child("Show Hidden"),
+                        )
+                        .child(
+                            button()
+                                .id("missing-mbids")
+                                .size(ButtonSize::Regular)
+                                .style(ButtonStyle::Minimal)
+                                .on_click(cx.listener(|this: &mut AlbumView, _, _, cx| {
+                                    this.show_missing_mbids.write(cx, true);
+                                }))
+                                .child("Reports"),
+                        )
+                        .child(
+                            button()
+                                .id("play-random-album")
+                                .size(ButtonSize::Regular)
+                                .style(ButtonStyle::Minimal)
+                                .on_click(|_, window, cx| {
+                                    window.dispatch_action(Box::new(PlayRandomAlbum), cx);
+                                })
+                                .child(icon(SHUFFLE).size(px(14.0)).my_auto())
+                                .child("Random Album"),
+                        ),
+                )
+            })
+            .child(self.missing_mbids.clone())
     }
 }
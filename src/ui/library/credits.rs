@@ -0,0 +1,167 @@
+use gpui::{
+    App, ClickEvent, ElementId, FontWeight, InteractiveElement, IntoElement, ParentElement,
+    RenderOnce, SharedString, StatefulInteractiveElement, Styled, Window, div,
+    prelude::FluentBuilder, px,
+};
+
+use crate::{
+    library::types::Track,
+    media::metadata::clean_text,
+    ui::{
+        components::icons::{CHEVRON_DOWN, CHEVRON_UP, icon},
+        theme::Theme,
+    },
+};
+
+/// Values longer than this (in characters) are cut off with an ellipsis when displayed.
+const MAX_DISPLAY_LENGTH: usize = 200;
+
+/// Returns a human readable name for an extended tag key.
+fn credit_label(key: &str) -> SharedString {
+    match key {
+        "ARRANGER" => "Arranger".into(),
+        "COMMENT" => "Comment".into(),
+        "CONDUCTOR" => "Conductor".into(),
+        "COPYRIGHT" => "Copyright".into(),
+        "DJMIXER" => "DJ Mix".into(),
+        "ENGINEER" => "Engineer".into(),
+        "ENSEMBLE" => "Ensemble".into(),
+        "LYRICIST" => "Lyricist".into(),
+        "MIXER" => "Mixing Engineer".into(),
+        "ORIGINALDATE" => "Original Release Date".into(),
+        "PERFORMER" => "Performer".into(),
+        "PRODUCER" => "Producer".into(),
+        "REMIXER" => "Remixer".into(),
+        "WRITER" => "Writer".into(),
+        _ => {
+            // anything else is shown as-is, but in title case: "RECORDING LOCATION" becomes
+            // "Recording Location"
+            key.split(['_', ' '])
+                .filter(|word| !word.is_empty())
+                .map(|word| {
+                    let mut chars = word.chars();
+                    chars.next().map_or_else(String::new, |first| {
+                        first
+                            .to_uppercase()
+                            .chain(chars.flat_map(char::to_lowercase))
+                            .collect()
+                    })
+                })
+                .collect::<Vec<_>>()
+                .join(" ")
+                .into()
+        }
+    }
+}
+
+fn truncate(value: &str) -> SharedString {
+    let shown = clean_text(value, MAX_DISPLAY_LENGTH, true);
+
+    if value.chars().count() > MAX_DISPLAY_LENGTH {
+        format!("{shown}…").into()
+    } else {
+        shown.into()
+    }
+}
+
+/// Returns the extended tags shared by every one of the given tracks (such as the producer of an
+/// album), in the order they appear on the first track.
+pub fn common_credits(tracks: &[Track]) -> Vec<(String, String)> {
+    let Some((first, rest)) = tracks.split_first() else {
+        return Vec::new();
+    };
+
+    let rest: Vec<_> = rest.iter().map(Track::extra_tags).collect();
+
+    first
+        .extra_tags()
+        .into_iter()
+        .filter(|credit| rest.iter().all(|tags| tags.contains(credit)))
+        .collect()
+}
+
+type ToggleHandler = Box<dyn Fn(&ClickEvent, &mut Window, &mut App)>;
+
+/// An expandable list of credits (or any other extended tags), shown as label-value pairs.
+#[derive(IntoElement)]
+pub struct CreditsSection {
+    id: ElementId,
+    credits: Vec<(String, String)>,
+    expanded: bool,
+    on_toggle: Option<ToggleHandler>,
+}
+
+impl CreditsSection {
+    pub fn expanded(mut self, expanded: bool) -> Self {
+        self.expanded = expanded;
+        self
+    }
+
+    pub fn on_toggle(
+        mut self,
+        handler: impl Fn(&ClickEvent, &mut Window, &mut App) + 'static,
+    ) -> Self {
+        self.on_toggle = Some(Box::new(handler));
+        self
+    }
+}
+
+impl RenderOnce for CreditsSection {
+    fn render(self, _: &mut Window, cx: &mut App) -> impl IntoElement {
+        let theme = cx.global::<Theme>();
+
+        div()
+            .flex()
+            .flex_col()
+            .w_full()
+            .child(
+                div()
+                    .id(self.id)
+                    .flex()
+                    .items_center()
+                    .gap(px(6.0))
+                    .text_sm()
+                    .font_weight(FontWeight::SEMIBOLD)
+                    .text_color(theme.text_secondary)
+                    .cursor_pointer()
+                    .hover(|this| this.text_color(theme.text))
+                    .child("Credits")
+                    .child(
+                        icon(if self.expanded {
+                            CHEVRON_UP
+                        } else {
+                            CHEVRON_DOWN
+                        })
+                        .size(px(14.0)),
+                    )
+                    .when_some(self.on_toggle, |this, handler| this.on_click(handler)),
+            )
+            .when(self.expanded, |this| {
+                this.child(div().flex().flex_col().gap(px(4.0)).mt(px(8.0)).children(
+                    self.credits.into_iter().map(|(key, value)| {
+                        div()
+                            .flex()
+                            .gap(px(12.0))
+                            .text_sm()
+                            .child(
+                                div()
+                                    .w(px(160.0))
+                                    .flex_shrink_0()
+                                    .text_color(theme.text_secondary)
+                                    .child(credit_label(&key)),
+                            )
+                            .child(div().min_w_0().child(truncate(&value)))
+                    }),
+                ))
+            })
+    }
+}
+
+pub fn credits_section(id: impl Into<ElementId>, credits: Vec<(String, String)>) -> CreditsSection {
+    CreditsSection {
+        id: id.into(),
+        credits,
+        expanded: false,
+        on_toggle: None,
+    }
+}
@@ -0,0 +1,179 @@
+use std::sync::Arc;
+
+use gpui::{
+    App, AppContext, Context, Entity, IntoElement, ParentElement, Render, SharedString, Styled,
+    Window, div, px,
+};
+use nucleo::Utf32String;
+use tracing::error;
+
+use crate::{
+    library::db::LibraryAccess,
+    ui::components::{
+        icons::{CHECK, CROSS},
+        modal::modal,
+        palette::{
+            AcceptIntent, ExtraItem, ExtraItemProvider, FinderItemLeft, Palette, PaletteItem,
+        },
+    },
+};
+
+/// The largest BPM that can be entered manually. Anything above this is almost certainly a typo.
+const MAX_BPM: i64 = 999;
+
+#[derive(PartialEq)]
+pub enum BpmChoice {
+    Clear,
+    /// Corrects a value that is half of the actual tempo (common with estimated values).
+    Double(i64),
+    /// Corrects a value that is double the actual tempo (common with estimated values).
+    Halve(i64),
+}
+
+impl BpmChoice {
+    fn value(&self) -> Option<i64> {
+        match self {
+            BpmChoice::Clear => None,
+            BpmChoice::Double(bpm) => Some(bpm * 2),
+            BpmChoice::Halve(bpm) => Some(bpm / 2),
+        }
+    }
+
+    fn for_bpm(bpm: Option<i64>) -> Vec<Arc<BpmChoice>> {
+        let mut choices = vec![Arc::new(BpmChoice::Clear)];
+
+        if let Some(bpm) = bpm {
+            choices.push(Arc::new(BpmChoice::Double(bpm)));
+            choices.push(Arc::new(BpmChoice::Halve(bpm)));
+        }
+
+        choices
+    }
+}
+
+impl PaletteItem for BpmChoice {
+    fn left_content(&self, _: &mut App) -> Option<FinderItemLeft> {
+        match self {
+            BpmChoice::Clear => Some(FinderItemLeft::Icon(CROSS.into())),
+            _ => None,
+        }
+    }
+
+    fn middle_content(&self, _: &mut App) -> SharedString {
+        match self {
+            BpmChoice::Clear => "Clear BPM".into(),
+            BpmChoice::Double(bpm) => format!("Double BPM ({} → {})", bpm, bpm * 2).into(),
+            BpmChoice::Halve(bpm) => format!("Halve BPM ({} → {})", bpm, bpm / 2).into(),
+        }
+    }
+
+    fn right_content(&self, _: &mut App) -> Option<SharedString> {
+        None
+    }
+}
+
+type MatcherFunc = Box<dyn Fn(&Arc<BpmChoice>, &mut App) -> Utf32String + 'static>;
+type OnAccept = Box<dyn Fn(&Arc<BpmChoice>, AcceptIntent, &mut App) + 'static>;
+
+fn set_bpm(cx: &mut App, track_id: i64, bpm: Option<i64>) {
+    if let Err(err) = cx.set_track_bpm(track_id, bpm) {
+        error!("Failed to set BPM for track {}: {}", track_id, err);
+    }
+}
+
+/// A dialog for manually setting the BPM of a track. Typing a number offers to set the BPM to
+/// that value.
+pub struct EditBpm {
+    show: Entity<bool>,
+    palette: Entity<Palette<BpmChoice, MatcherFunc, OnAccept>>,
+}
+
+impl EditBpm {
+    pub fn new(cx: &mut App, show: Entity<bool>, track_id: i64) -> Entity<Self> {
+        cx.new(|cx| {
+            cx.observe(&show, move |this: &mut Self, _, cx| {
+                this.palette.update(cx, |this, cx| {
+                    let bpm = cx.get_track_by_id(track_id).ok().and_then(|t| t.bpm);
+
+                    cx.emit(BpmChoice::for_bpm(bpm));
+
+                    this.reset(cx);
+                });
+
+                cx.notify();
+            })
+            .detach();
+
+            let matcher: MatcherFunc =
+                Box::new(|choice, cx| choice.middle_content(cx).to_string().into());
+
+            let show_clone = show.clone();
+
+            let on_accept: OnAccept = Box::new(move |choice, _, cx| {
+                set_bpm(cx, track_id, choice.value());
+                show_clone.write(cx, false);
+            });
+
+            let bpm = cx.get_track_by_id(track_id).ok().and_then(|t| t.bpm);
+            let palette = Palette::new(cx, BpmChoice::for_bpm(bpm), matcher, on_accept, &show);
+
+            let show_for_set = show.clone();
+            let provider: ExtraItemProvider = Arc::new(move |query: &str| {
+                let Ok(bpm) = query.trim().parse::<i64>() else {
+                    return Vec::new();
+                };
+
+                if !(1..=MAX_BPM).contains(&bpm) {
+                    return Vec::new();
+                }
+
+                let show_clone2 = show_for_set.clone();
+
+                vec![ExtraItem {
+                    left: Some(FinderItemLeft::Icon(CHECK.into())),
+                    middle: format!("Set BPM to {}", bpm).into(),
+                    right: None,
+                    on_accept: Arc::new(move |cx| {
+                        set_bpm(cx, track_id, Some(bpm));
+                        show_clone2.write(cx, false);
+                    }),
+                }]
+            });
+
+            cx.update_entity(&palette, |palette, cx| {
+                palette.register_extra_provider(provider.clone(), cx);
+            });
+
+            Self { show, palette }
+        })
+    }
+}
+
+impl Render for EditBpm {
+    fn render(&mut self, window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let show = self.show.clone();
+        let palette = self.palette.clone();
+        let show_read = *self.show.read(cx);
+
+        if show_read {
+            cx.update_entity(&palette, |palette, cx| {
+                palette.focus(window, cx);
+            });
+
+            modal()
+                .child(div().w(px(550.0)).h(px(300.0)).child(palette.clone()))
+                .on_exit(move |_, cx| {
+                    show.update(cx, |show, cx| {
+                        *show = false;
+                        cx.update_entity(&palette, |palette, cx| {
+                            palette.reset(cx);
+                        });
+                        cx.notify();
+                    })
+                })
+                .into_any_element()
+        } else {
+            div().into_any_element()
+        }
+    }
+}
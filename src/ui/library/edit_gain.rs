@@ -0,0 +1,225 @@
+use std::{path::Path, sync::Arc};
+
+use gpui::{
+    App, AppContext, Context, Entity, IntoElement, ParentElement, Render, SharedString, Styled,
+    Window, div, px,
+};
+use nucleo::Utf32String;
+use tracing::error;
+
+use crate::{
+    library::{
+        db::LibraryAccess,
+        types::{MAX_GAIN_OFFSET, format_gain_offset},
+    },
+    playback::interface::PlaybackInterface,
+    ui::components::{
+        icons::{CHECK, CROSS, MINUS, PLUS},
+        modal::modal,
+        palette::{
+            AcceptIntent, ExtraItem, ExtraItemProvider, FinderItemLeft, Palette, PaletteItem,
+        },
+    },
+};
+
+/// How much the volume is changed by the "louder" and "quieter" choices, in decibels.
+const GAIN_STEP: f64 = 1.0;
+
+#[derive(PartialEq)]
+pub enum GainChoice {
+    Clear,
+    Louder(f64),
+    Quieter(f64),
+}
+
+impl GainChoice {
+    fn value(&self) -> f64 {
+        match self {
+            GainChoice::Clear => 0.0,
+            GainChoice::Louder(gain) => (gain + GAIN_STEP).min(MAX_GAIN_OFFSET),
+            GainChoice::Quieter(gain) => (gain - GAIN_STEP).max(-MAX_GAIN_OFFSET),
+        }
+    }
+
+    fn for_gain(gain: f64) -> Vec<Arc<GainChoice>> {
+        let mut choices = Vec::new();
+
+        if gain != 0.0 {
+            choices.push(Arc::new(GainChoice::Clear));
+        }
+        if gain < MAX_GAIN_OFFSET {
+            choices.push(Arc::new(GainChoice::Louder(gain)));
+        }
+        if gain > -MAX_GAIN_OFFSET {
+            choices.push(Arc::new(GainChoice::Quieter(gain)));
+        }
+
+        choices
+    }
+}
+
+impl PaletteItem for GainChoice {
+    fn left_content(&self, _: &mut App) -> Option<FinderItemLeft> {
+        Some(FinderItemLeft::Icon(
+            match self {
+                GainChoice::Clear => CROSS,
+                GainChoice::Louder(_) => PLUS,
+                GainChoice::Quieter(_) => MINUS,
+            }
+            .into(),
+        ))
+    }
+
+    fn middle_content(&self, _: &mut App) -> SharedString {
+        match self {
+            GainChoice::Clear => "Clear volume adjustment".into(),
+            GainChoice::Louder(gain) => format!(
+                "Louder ({} → {})",
+                format_gain_offset(*gain),
+                format_gain_offset(self.value())
+            )
+            .into(),
+            GainChoice::Quieter(gain) => format!(
+                "Quieter ({} → {})",
+                format_gain_offset(*gain),
+                format_gain_offset(self.value())
+            )
+            .into(),
+        }
+    }
+
+    fn right_content(&self, _: &mut App) -> Option<SharedString> {
+        None
+    }
+}
+
+type MatcherFunc = Box<dyn Fn(&Arc<GainChoice>, &mut App) -> Utf32String + 'static>;
+type OnAccept = Box<dyn Fn(&Arc<GainChoice>, AcceptIntent, &mut App) + 'static>;
+
+fn set_gain(cx: &mut App, track_id: i64, path: &Path, gain: f64) {
+    if let Err(err) = cx.set_track_gain_offset(track_id, gain) {
+        error!(
+            "Failed to set volume adjustment for track {}: {}",
+            track_id, err
+        );
+        return;
+    }
+
+    cx.global::<PlaybackInterface>()
+        .set_track_gain(path.to_path_buf(), gain);
+}
+
+/// A dialog for adjusting the volume of a single track, for tracks that are much quieter or louder
+/// than the rest of the library. Typing a number (in decibels) offers to set the adjustment to
+/// that value.
+pub struct EditGain {
+    pub track_id: i64,
+    show: Entity<bool>,
+    palette: Entity<Palette<GainChoice, MatcherFunc, OnAccept>>,
+}
+
+impl EditGain {
+    pub fn new(cx: &mut App, show: Entity<bool>, track_id: i64) -> Option<Entity<Self>> {
+        let path = cx.get_track_by_id(track_id).ok()?.location.clone();
+
+        Some(cx.new(|cx| {
+            cx.observe(&show, move |this: &mut Self, _, cx| {
+                this.palette.update(cx, |this, cx| {
+                    let gain = cx
+                        .get_track_by_id(track_id)
+                        .map(|t| t.gain_offset)
+                        .unwrap_or_default();
+
+                    cx.emit(GainChoice::for_gain(gain));
+
+                    this.reset(cx);
+                });
+
+                cx.notify();
+            })
+            .detach();
+
+            let matcher: MatcherFunc =
+                Box::new(|choice, cx| choice.middle_content(cx).to_string().into());
+
+            let show_clone = show.clone();
+            let path_clone = path.clone();
+
+            let on_accept: OnAccept = Box::new(move |choice, _, cx| {
+                set_gain(cx, track_id, &path_clone, choice.value());
+                show_clone.write(cx, false);
+            });
+
+            let gain = cx
+                .get_track_by_id(track_id)
+                .map(|t| t.gain_offset)
+                .unwrap_or_default();
+            let palette = Palette::new(cx, GainChoice::for_gain(gain), matcher, on_accept, &show);
+
+            let show_for_set = show.clone();
+            let provider: ExtraItemProvider = Arc::new(move |query: &str| {
+                let query = query.trim().trim_end_matches("dB").trim();
+                let Ok(gain) = query.trim_start_matches('+').parse::<f64>() else {
+                    return Vec::new();
+                };
+
+                if !(-MAX_GAIN_OFFSET..=MAX_GAIN_OFFSET).contains(&gain) {
+                    return Vec::new();
+                }
+
+                let gain = (gain * 10.0).round() / 10.0;
+                let show_clone2 = show_for_set.clone();
+                let path = path.clone();
+
+                vec![ExtraItem {
+                    left: Some(FinderItemLeft::Icon(CHECK.into())),
+                    middle: format!("Set volume adjustment to {}", format_gain_offset(gain)).into(),
+                    right: None,
+                    on_accept: Arc::new(move |cx| {
+                        set_gain(cx, track_id, &path, gain);
+                        show_clone2.write(cx, false);
+                    }),
+                }]
+            });
+
+            cx.update_entity(&palette, |palette, cx| {
+                palette.register_extra_provider(provider.clone(), cx);
+            });
+
+            Self {
+                track_id,
+                show,
+                palette,
+            }
+        }))
+    }
+}
+
+impl Render for EditGain {
+    fn render(&mut self, window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let show = self.show.clone();
+        let palette = self.palette.clone();
+        let show_read = *self.show.read(cx);
+
+        if show_read {
+            cx.update_entity(&palette, |palette, cx| {
+                palette.focus(window, cx);
+            });
+
+            modal()
+                .child(div().w(px(550.0)).h(px(300.0)).child(palette.clone()))
+                .on_exit(move |_, cx| {
+                    show.update(cx, |show, cx| {
+                        *show = false;
+                        cx.update_entity(&palette, |palette, cx| {
+                            palette.reset(cx);
+                        });
+                        cx.notify();
+                    })
+                })
+                .into_any_element()
+        } else {
+            div().into_any_element()
+        }
+    }
+}
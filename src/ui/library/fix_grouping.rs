@@ -0,0 +1,402 @@
+use std::sync::Arc;
+
+use gpui::{
+    App, AppContext, Context, Entity, FontWeight, InteractiveElement, IntoElement, ParentElement,
+    Render, SharedString, StatefulInteractiveElement, Styled, Window, div, prelude::FluentBuilder,
+    px,
+};
+use nucleo::Utf32String;
+use rustc_hash::FxHashSet;
+use tracing::error;
+
+use crate::{
+    library::{
+        db::LibraryAccess,
+        scan::{prepare_album_art, scan_path_for_album_art},
+        types::{AlbumMatchBasis, ArtSource, GroupingTrack},
+    },
+    ui::{
+        components::{
+            button::{ButtonIntent, button},
+            checkbox::Checkbox,
+            icons::{LOCK, icon},
+            modal::modal,
+            palette::{AcceptIntent, Palette},
+        },
+        library::ViewSwitchMessage,
+        models::Models,
+        search::album_item::AlbumPaletteItem,
+        theme::Theme,
+        toasts::show_toast,
+        util::ui_px,
+    },
+};
+
+type MatcherFunc = Box<dyn Fn(&Arc<AlbumPaletteItem>, &mut App) -> Utf32String + 'static>;
+type OnAccept = Box<dyn Fn(&Arc<AlbumPaletteItem>, AcceptIntent, &mut App) + 'static>;
+
+/// Every album except the one being fixed, as merge targets.
+fn merge_targets(cx: &App, album_id: i64) -> Vec<Arc<AlbumPaletteItem>> {
+    match cx.list_albums_search() {
+        Ok(albums) => AlbumPaletteItem::from_search_results(
+            albums
+                .into_iter()
+                .filter(|(id, ..)| *id as i64 != album_id)
+                .collect(),
+        ),
+        Err(err) => {
+            error!(?err, "Failed to load albums to merge into: {err}");
+            Vec::new()
+        }
+    }
+}
+
+/// Reloads the album list and opens the given album, once its tracks have moved.
+fn show_regrouped_album(cx: &mut App, album_id: i64) {
+    // the album list is reloaded whenever this is notified
+    let show_hidden_albums = cx.global::<Models>().show_hidden_albums.clone();
+    show_hidden_albums.update(cx, |_, cx| cx.notify());
+
+    let switcher_model = cx.global::<Models>().switcher_model.clone();
+    switcher_model.update(cx, |_, cx| {
+        cx.emit(ViewSwitchMessage::Release(album_id));
+    });
+}
+
+/// Groups tracks by the folder they're in, keeping the order they were listed in.
+fn group_by_folder(tracks: &[GroupingTrack]) -> Vec<(SharedString, Vec<&GroupingTrack>)> {
+    let mut groups: Vec<(SharedString, Vec<&GroupingTrack>)> = Vec::new();
+
+    for track in tracks {
+        let folder: SharedString = track
+            .folder
+            .clone()
+            .map(SharedString::from)
+            .unwrap_or_else(|| "Unknown folder".into());
+
+        match groups.last_mut() {
+            Some((last, tracks)) if *last == folder => tracks.push(track),
+            _ => groups.push((folder, vec![track])),
+        }
+    }
+
+    groups
+}
+
+/// Lets the user fix an album the scanner grouped wrongly, by splitting some of its tracks off
+/// into a new album or by merging it into another album. Tracks moved this way are left where
+/// they are by later scans.
+pub struct FixGrouping {
+    album_id: i64,
+    match_basis: AlbumMatchBasis,
+    show: Entity<bool>,
+    tracks: Arc<Vec<GroupingTrack>>,
+    selected: FxHashSet<i64>,
+    show_merge: Entity<bool>,
+    merge_palette: Entity<Palette<AlbumPaletteItem, MatcherFunc, OnAccept>>,
+}
+
+impl FixGrouping {
+    pub fn new(
+        cx: &mut App,
+        show: Entity<bool>,
+        album_id: i64,
+        match_basis: AlbumMatchBasis,
+    ) -> Entity<Self> {
+        cx.new(|cx| {
+            cx.observe(&show, |this: &mut Self, show, cx| {
+                if *show.read(cx) {
+                    this.reload(cx);
+                }
+
+                cx.notify();
+            })
+            .detach();
+
+            let show_merge = cx.new(|_| false);
+
+            cx.observe(&show_merge, move |this: &mut Self, _, cx| {
+                this.merge_palette.update(cx, |palette, cx| {
+                    cx.emit(merge_targets(cx, album_id));
+                    palette.reset(cx);
+                });
+
+                cx.notify();
+            })
+            .detach();
+
+            let matcher: MatcherFunc =
+                Box::new(|album, _| Utf32String::from(format!("{} {}", album.title, album.artist)));
+
+            let show_clone = show.clone();
+            let show_merge_clone = show_merge.clone();
+
+            let on_accept: OnAccept = Box::new(move |album, _, cx| {
+                let into = album.id as i64;
+
+                show_merge_clone.write(cx, false);
+                show_clone.write(cx, false);
+
+                match cx.merge_albums(album_id, into) {
+                    Ok(()) => {
+                        show_regrouped_album(cx, into);
+                        show_toast(cx, format!("Merged into {}.", album.title));
+                    }
+                    Err(err) => {
+                        error!(?err, "Failed to merge album {album_id} into {into}: {err}");
+                        show_toast(cx, "Couldn't merge the albums.");
+                    }
+                }
+            });
+
+            let merge_palette = Palette::new(
+                cx,
+                merge_targets(cx, album_id),
+                matcher,
+                on_accept,
+                &show_merge,
+            );
+
+            Self {
+                album_id,
+                match_basis,
+                show,
+                tracks: Arc::new(Vec::new()),
+                selected: FxHashSet::default(),
+                show_merge,
+                merge_palette,
+            }
+        })
+    }
+
+    fn reload(&mut self, cx: &mut Context<Self>) {
+        self.selected.clear();
+        self.tracks = match cx.list_album_grouping(self.album_id) {
+            Ok(tracks) => tracks,
+            Err(err) => {
+                error!(
+                    ?err,
+                    "Failed to load tracks for album {}: {err}", self.album_id
+                );
+                Arc::new(Vec::new())
+            }
+        };
+    }
+
+    fn toggle(&mut self, track_id: i64, cx: &mut Context<Self>) {
+        if !self.selected.remove(&track_id) {
+            self.selected.insert(track_id);
+        }
+
+        cx.notify();
+    }
+
+    fn split(&mut self, cx: &mut Context<Self>) {
+        let track_ids: Vec<i64> = self
+            .tracks
+            .iter()
+            .filter(|track| self.selected.contains(&track.id))
+            .map(|track| track.id)
+            .collect();
+
+        // the new album keeps the old album's art unless the split-off tracks have their own
+        let art = self
+            .tracks
+            .iter()
+            .find(|track| self.selected.contains(&track.id))
+            .and_then(|track| scan_path_for_album_art(&track.location))
+            .and_then(|image| prepare_album_art(&image).ok())
+            .map(|(image, thumb)| (image, thumb, ArtSource::Folder));
+
+        self.show.write(cx, false);
+
+        match cx.split_album(self.album_id, &track_ids, art) {
+            Ok(new_album_id) => {
+                show_regrouped_album(cx, new_album_id);
+                show_toast(cx, "Split the tracks into a new album.");
+            }
+            Err(err) => {
+                error!(?err, "Failed to split album {}: {err}", self.album_id);
+                show_toast(cx, "Couldn't split the album.");
+            }
+        }
+    }
+}
+
+impl Render for FixGrouping {
+    fn render(&mut self, window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        if *self.show_merge.read(cx) {
+            let show_merge = self.show_merge.clone();
+            let palette = self.merge_palette.clone();
+
+            cx.update_entity(&palette, |palette, cx| {
+                palette.focus(window, cx);
+            });
+
+            return modal()
+                .child(div().w(px(550.0)).h(px(300.0)).child(palette.clone()))
+                .on_exit(move |_, cx| {
+                    show_merge.write(cx, false);
+                })
+                .into_any_element();
+        }
+
+        if !*self.show.read(cx) {
+            return div().into_any_element();
+        }
+
+        let theme = cx.global::<Theme>();
+        let show = self.show.clone();
+        let can_split = !self.selected.is_empty() && self.selected.len() < self.tracks.len();
+
+        modal()
+            .on_exit(move |_, cx| show.write(cx, false))
+            .child(
+                div()
+                    .w(px(550.0))
+                    .p(px(20.0))
+                    .flex()
+                    .flex_col()
+                    .gap(px(8.0))
+                    .child(
+                        div()
+                            .font_weight(FontWeight::BOLD)
+                            .text_size(ui_px(20.0, cx))
+                            .child("Fix album grouping"),
+                    )
+                    .child(
+                        div()
+                            .text_sm()
+                            .text_color(theme.text_secondary)
+                            .child(self.match_basis.describe()),
+                    )
+                    .child(div().text_sm().text_color(theme.text_secondary).child(
+                        "Select the tracks that belong to a different album and split \
+                                them off, or merge this whole album into another one. Moved \
+                                tracks stay where you put them when the library is scanned.",
+                    ))
+                    .child(
+                        div()
+                            .id("grouping-tracks")
+                            .max_h(px(320.0))
+                            .overflow_y_scroll()
+                            .flex()
+                            .flex_col()
+                            .gap(px(8.0))
+                            .children(group_by_folder(&self.tracks).into_iter().map(
+                                |(folder, tracks)| {
+                                    div()
+                                        .flex()
+                                        .flex_col()
+                                        .child(
+                                            div()
+                                                .text_xs()
+                                                .font_weight(FontWeight::SEMIBOLD)
+                                                .text_color(theme.text_secondary)
+                                                .pb(px(4.0))
+                                                .truncate()
+                                                .child(folder),
+                                        )
+                                        .children(tracks.into_iter().map(|track| {
+                                            let track_id = track.id;
+                                            let number =
+                                                match (track.disc_number, track.track_number) {
+                                                    (Some(disc), Some(number)) => {
+                                                        format!("{disc}-{number}")
+                                                    }
+                                                    (None, Some(number)) => number.to_string(),
+                                                    _ => String::new(),
+                                                };
+
+                                            div()
+                                                .id(("grouping-track", track_id as usize))
+                                                .flex()
+                                                .items_center()
+                                                .gap(px(8.0))
+                                                .py(px(2.0))
+                                                .cursor_pointer()
+                                                .on_click(cx.listener(move |this, _, _, cx| {
+                                                    this.toggle(track_id, cx);
+                                                }))
+                                                .child(Checkbox::new(
+                                                    ("grouping-track-check", track_id as usize),
+                                                    self.selected.contains(&track_id),
+                                                ))
+                                                .child(
+                                                    div()
+                                                        .w(px(32.0))
+                                                        .flex_none()
+                                                        .text_sm()
+                                                        .text_color(theme.text_secondary)
+                                                        .child(number),
+                                                )
+                                                .child(
+                                                    div()
+                                                        .flex_1()
+                                                        .min_w_0()
+                                                        .flex()
+                                                        .flex_col()
+                                                        .child(
+                                                            div()
+                                                                .text_sm()
+                                                                .truncate()
+                                                                .child(track.title.clone()),
+                                                        )
+                                                        .when_some(
+                                                            track.artist_names.clone(),
+                                                            |this, artists| {
+                                                                this.child(
+                                                                    div()
+                                                                        .text_xs()
+                                                                        .text_color(
+                                                                            theme.text_secondary,
+                                                                        )
+                                                                        .truncate()
+                                                                        .child(artists),
+                                                                )
+                                                            },
+                                                        ),
+                                                )
+                                                .when(track.album_locked, |this| {
+                                                    this.child(
+                                                        icon(LOCK)
+                                                            .size(px(14.0))
+                                                            .flex_none()
+                                                            .text_color(theme.text_secondary),
+                                                    )
+                                                })
+                                        }))
+                                },
+                            )),
+                    )
+                    .child(
+                        div()
+                            .flex()
+                            .justify_end()
+                            .gap(px(8.0))
+                            .mt(px(8.0))
+                            .child(
+                                button()
+                                    .id("grouping-merge")
+                                    .child("Merge into another album…")
+                                    .on_click(cx.listener(|this, _, _, cx| {
+                                        this.show_merge.write(cx, true);
+                                    })),
+                            )
+                            .child(
+                                button()
+                                    .id("grouping-split")
+                                    .intent(ButtonIntent::Primary)
+                                    .when(!can_split, |this| this.opacity(0.5))
+                                    .child("Split selected into new album")
+                                    .on_click(cx.listener(move |this, _, _, cx| {
+                                        if can_split {
+                                            this.split(cx);
+                                        }
+                                    })),
+                            ),
+                    ),
+            )
+            .into_any_element()
+    }
+}
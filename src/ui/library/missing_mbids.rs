@@ -0,0 +1,305 @@
+use anyhow::Context as _;
+use futures::TryFutureExt as _;
+use gpui::{
+    App, AppContext, Context, Entity, FontWeight, InteractiveElement, IntoElement, ParentElement,
+    Render, StatefulInteractiveElement, Styled, Window, div, prelude::FluentBuilder, px,
+};
+use tracing::{error, info};
+
+use crate::{
+    library::{
+        db::LibraryAccess,
+        types::{ArtSource, UntaggedAlbum},
+    },
+    ui::{
+        components::{
+            button::{ButtonIntent, button},
+            modal::modal,
+        },
+        library::ViewSwitchMessage,
+        models::Models,
+        theme::Theme,
+        toasts::show_toast,
+        util::ui_px,
+    },
+};
+
+#[cfg(windows)]
+const LINE_ENDING: &str = "\r\n";
+#[cfg(not(windows))]
+const LINE_ENDING: &str = "\n";
+
+/// Which albums the report lists.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Report {
+    /// Albums that weren't tagged with a MusicBrainz release ID.
+    MissingMbids,
+    /// Albums whose art was taken from an image in their folder, which is often low quality,
+    /// rather than from their files.
+    FolderArt,
+    /// Albums with a track whose embedded art was too large to be used.
+    OversizedArt,
+}
+
+impl Report {
+    const ALL: [Report; 3] = [
+        Report::MissingMbids,
+        Report::FolderArt,
+        Report::OversizedArt,
+    ];
+
+    fn label(&self) -> &'static str {
+        match self {
+            Report::MissingMbids => "No MusicBrainz ID",
+            Report::FolderArt => "Cover from folder image",
+            Report::OversizedArt => "Cover too large",
+        }
+    }
+
+    fn summary(&self, count: usize) -> String {
+        match (self, count) {
+            (Report::MissingMbids, 0) => "Every album has a MusicBrainz release ID.".to_string(),
+            (Report::MissingMbids, 1) => {
+                "1 album doesn't have a MusicBrainz release ID.".to_string()
+            }
+            (Report::MissingMbids, _) => {
+                format!("{count} albums don't have a MusicBrainz release ID.")
+            }
+            (Report::FolderArt, 0) => {
+                "No album's cover came from an image in its folder.".to_string()
+            }
+            (Report::FolderArt, 1) => {
+                "1 album's cover came from an image in its folder.".to_string()
+            }
+            (Report::FolderArt, _) => {
+                format!("{count} albums' covers came from an image in their folder.")
+            }
+            (Report::OversizedArt, 0) => "No album has a cover that's too large.".to_string(),
+            (Report::OversizedArt, 1) => {
+                "1 album has a cover that was too large to use.".to_string()
+            }
+            (Report::OversizedArt, _) => {
+                format!("{count} albums have a cover that was too large to use.")
+            }
+        }
+    }
+
+    /// The name the report is exported as, by default.
+    fn file_name(&self) -> &'static str {
+        match self {
+            Report::MissingMbids => "Albums without MusicBrainz IDs.txt",
+            Report::FolderArt => "Albums with folder covers.txt",
+            Report::OversizedArt => "Albums with oversized covers.txt",
+        }
+    }
+}
+
+/// Formats an album as "Artist - Title (Year)".
+fn describe(album: &UntaggedAlbum) -> String {
+    match album.year {
+        Some(year) => format!("{} - {} ({year})", album.artist_name, album.title),
+        None => format!("{} - {}", album.artist_name, album.title),
+    }
+}
+
+/// Lists the albums as text, one per line, followed by the folder each one is in.
+fn report_text(albums: &[UntaggedAlbum]) -> String {
+    albums
+        .iter()
+        .map(|album| match &album.folder {
+            Some(folder) => format!("{}\t{folder}{LINE_ENDING}", describe(album)),
+            None => format!("{}{LINE_ENDING}", describe(album)),
+        })
+        .collect()
+}
+
+/// Asks where to save the report, and writes it there.
+fn export_report(cx: &App, report: Report, albums: &[UntaggedAlbum]) -> anyhow::Result<()> {
+    let path_future = cx.prompt_for_new_path(
+        directories::UserDirs::new()
+            .context("Failed to get user directories")?
+            .document_dir()
+            .context("Failed to get documents directory")?,
+        Some(report.file_name()),
+    );
+    let text = report_text(albums);
+
+    crate::RUNTIME.spawn(async move {
+        let path = match path_future.err_into().await.flatten() {
+            Ok(Some(path)) => path,
+            Ok(None) => return info!("Report export cancelled by user"),
+            Err(err) => return error!(?err, "Failed to prompt for path: {err}"),
+        };
+
+        if let Err(err) = tokio::fs::write(&path, text).await {
+            error!(?err, "Failed writing report to {}: {err}", path.display());
+        }
+    });
+
+    Ok(())
+}
+
+/// Lists the albums that need attention, so that they can be found and fixed: those that weren't
+/// tagged with a MusicBrainz release ID, whose art came from an image in their folder, or whose
+/// embedded art was too large to use. Hidden albums are left out.
+pub struct MissingMbids {
+    show: Entity<bool>,
+    report: Report,
+    albums: Vec<UntaggedAlbum>,
+}
+
+impl MissingMbids {
+    pub fn new(cx: &mut App, show: Entity<bool>) -> Entity<Self> {
+        cx.new(|cx| {
+            cx.observe(&show, |this: &mut Self, show, cx| {
+                if *show.read(cx) {
+                    this.reload(cx);
+                }
+
+                cx.notify();
+            })
+            .detach();
+
+            Self {
+                show,
+                report: Report::MissingMbids,
+                albums: Vec::new(),
+            }
+        })
+    }
+
+    fn reload(&mut self, cx: &mut Context<Self>) {
+        let albums = match self.report {
+            Report::MissingMbids => cx.list_albums_without_mbid(),
+            Report::FolderArt => cx.list_albums_by_art_source(ArtSource::Folder),
+            Report::OversizedArt => cx.list_albums_with_oversized_art(),
+        };
+
+        self.albums = match albums {
+            Ok(albums) => albums,
+            Err(err) => {
+                error!(
+                    ?err,
+                    "Failed to load albums for report {:?}: {err}", self.report
+                );
+                Vec::new()
+            }
+        };
+    }
+
+    fn switch_report(&mut self, report: Report, cx: &mut Context<Self>) {
+        self.report = report;
+        self.reload(cx);
+        cx.notify();
+    }
+
+    fn open_album(&mut self, album_id: i64, cx: &mut Context<Self>) {
+        self.show.write(cx, false);
+
+        let switcher_model = cx.global::<Models>().switcher_model.clone();
+        switcher_model.update(cx, |_, cx| {
+            cx.emit(ViewSwitchMessage::Release(album_id));
+        });
+    }
+
+    fn export(&mut self, cx: &mut Context<Self>) {
+        if let Err(err) = export_report(cx, self.report, &self.albums) {
+            error!(?err, "Failed to export report: {err}");
+            show_toast(cx, "Couldn't export the list.");
+        }
+    }
+}
+
+impl Render for MissingMbids {
+    fn render(&mut self, _: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        if !*self.show.read(cx) {
+            return div().into_any_element();
+        }
+
+        let theme = cx.global::<Theme>();
+        let show = self.show.clone();
+        let count = self.albums.len();
+
+        modal()
+            .on_exit(move |_, cx| show.write(cx, false))
+            .child(
+                div()
+                    .w(px(550.0))
+                    .p(px(20.0))
+                    .flex()
+                    .flex_col()
+                    .gap(px(8.0))
+                    .child(
+                        div()
+                            .font_weight(FontWeight::BOLD)
+                            .text_size(ui_px(20.0, cx))
+                            .child("Album Reports"),
+                    )
+                    .child(
+                        div()
+                            .flex()
+                            .gap(px(8.0))
+                            .children(Report::ALL.map(|report| {
+                                button()
+                                    .id(report.label())
+                                    .when(report == self.report, |this| {
+                                        this.intent(ButtonIntent::Primary)
+                                    })
+                                    .on_click(cx.listener(move |this, _, _, cx| {
+                                        this.switch_report(report, cx);
+                                    }))
+                                    .child(report.label())
+                            })),
+                    )
+                    .child(
+                        div()
+                            .text_sm()
+                            .text_color(theme.text_secondary)
+                            .child(self.report.summary(count)),
+                    )
+                    .when(count > 0, |this| {
+                        this.child(
+                            div()
+                                .id("missing-mbid-albums")
+                                .max_h(px(320.0))
+                                .overflow_y_scroll()
+                                .flex()
+                                .flex_col()
+                                .children(self.albums.iter().map(|album| {
+                                    let album_id = album.id;
+
+                                    div()
+                                        .id(("missing-mbid-album", album_id as usize))
+                                        .flex()
+                                        .flex_col()
+                                        .py(px(4.0))
+                                        .cursor_pointer()
+                                        .on_click(cx.listener(move |this, _, _, cx| {
+                                            this.open_album(album_id, cx);
+                                        }))
+                                        .child(div().text_sm().truncate().child(describe(album)))
+                                        .when_some(album.folder.clone(), |this, folder| {
+                                            this.child(
+                                                div()
+                                                    .text_xs()
+                                                    .text_color(theme.text_secondary)
+                                                    .truncate()
+                                                    .child(folder.0),
+                                            )
+                                        })
+                                }))
+                                .pb(px(4.0)),
+                        )
+                        .child(
+                            div().flex().justify_end().child(
+                                button()
+                                    .id("missing-mbid-export")
+                                    .on_click(cx.listener(|this, _, _, cx| this.export(cx)))
+                                    .child("Export as Text"),
+                            ),
+                        )
+                    }),
+            )
+            .into_any_element()
+    }
+}
@@ -0,0 +1,150 @@
+use std::sync::Arc;
+
+use gpui::{
+    App, AppContext, Context, Entity, IntoElement, ParentElement, Render, SharedString, Styled,
+    Window, div, px,
+};
+use nucleo::Utf32String;
+
+use crate::{
+    library::playlist::move_playlist_item_to_index,
+    ui::components::{
+        icons::{CHECK, CHEVRON_DOWN, CHEVRON_UP},
+        modal::modal,
+        palette::{
+            AcceptIntent, ExtraItem, ExtraItemProvider, FinderItemLeft, Palette, PaletteItem,
+        },
+    },
+};
+
+#[derive(PartialEq)]
+pub enum MoveChoice {
+    Top,
+    Bottom,
+}
+
+impl MoveChoice {
+    fn index(&self) -> usize {
+        match self {
+            MoveChoice::Top => 0,
+            // clamped to the last item
+            MoveChoice::Bottom => usize::MAX,
+        }
+    }
+}
+
+impl PaletteItem for MoveChoice {
+    fn left_content(&self, _: &mut App) -> Option<FinderItemLeft> {
+        Some(FinderItemLeft::Icon(
+            match self {
+                MoveChoice::Top => CHEVRON_UP,
+                MoveChoice::Bottom => CHEVRON_DOWN,
+            }
+            .into(),
+        ))
+    }
+
+    fn middle_content(&self, _: &mut App) -> SharedString {
+        match self {
+            MoveChoice::Top => "Move to top".into(),
+            MoveChoice::Bottom => "Move to bottom".into(),
+        }
+    }
+
+    fn right_content(&self, _: &mut App) -> Option<SharedString> {
+        None
+    }
+}
+
+type MatcherFunc = Box<dyn Fn(&Arc<MoveChoice>, &mut App) -> Utf32String + 'static>;
+type OnAccept = Box<dyn Fn(&Arc<MoveChoice>, AcceptIntent, &mut App) + 'static>;
+
+/// A dialog for moving a playlist item to a specific position. Typing a number offers to move the
+/// item to that position, counting from one.
+pub struct MoveToPosition {
+    show: Entity<bool>,
+    palette: Entity<Palette<MoveChoice, MatcherFunc, OnAccept>>,
+}
+
+impl MoveToPosition {
+    pub fn new(cx: &mut App, show: Entity<bool>, playlist_id: i64, item_id: i64) -> Entity<Self> {
+        cx.new(|cx| {
+            cx.observe(&show, |this: &mut Self, _, cx| {
+                this.palette.update(cx, |this, cx| this.reset(cx));
+                cx.notify();
+            })
+            .detach();
+
+            let matcher: MatcherFunc =
+                Box::new(|choice, cx| choice.middle_content(cx).to_string().into());
+
+            let show_clone = show.clone();
+
+            let on_accept: OnAccept = Box::new(move |choice, _, cx| {
+                move_playlist_item_to_index(cx, playlist_id, item_id, choice.index());
+                show_clone.write(cx, false);
+            });
+
+            let choices = vec![Arc::new(MoveChoice::Top), Arc::new(MoveChoice::Bottom)];
+            let palette = Palette::new(cx, choices, matcher, on_accept, &show);
+
+            let show_for_move = show.clone();
+            let provider: ExtraItemProvider = Arc::new(move |query: &str| {
+                let Ok(position) = query.trim().parse::<usize>() else {
+                    return Vec::new();
+                };
+
+                if position == 0 {
+                    return Vec::new();
+                }
+
+                let show_clone2 = show_for_move.clone();
+
+                vec![ExtraItem {
+                    left: Some(FinderItemLeft::Icon(CHECK.into())),
+                    middle: format!("Move to position {}", position).into(),
+                    right: None,
+                    on_accept: Arc::new(move |cx| {
+                        move_playlist_item_to_index(cx, playlist_id, item_id, position - 1);
+                        show_clone2.write(cx, false);
+                    }),
+                }]
+            });
+
+            cx.update_entity(&palette, |palette, cx| {
+                palette.register_extra_provider(provider.clone(), cx);
+            });
+
+            Self { show, palette }
+        })
+    }
+}
+
+impl Render for MoveToPosition {
+    fn render(&mut self, window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let show = self.show.clone();
+        let palette = self.palette.clone();
+        let show_read = *self.show.read(cx);
+
+        if show_read {
+            cx.update_entity(&palette, |palette, cx| {
+                palette.focus(window, cx);
+            });
+
+            modal()
+                .child(div().w(px(550.0)).h(px(300.0)).child(palette.clone()))
+                .on_exit(move |_, cx| {
+                    show.update(cx, |show, cx| {
+                        *show = false;
+                        cx.update_entity(&palette, |palette, cx| {
+                            palette.reset(cx);
+                        });
+                        cx.notify();
+                    })
+                })
+                .into_any_element()
+        } else {
+            div().into_any_element()
+        }
+    }
+}
@@ -0,0 +1,72 @@
+use gpui::{
+    App, ClipboardItem, ElementId, InteractiveElement, IntoElement, ParentElement,
+    StatefulInteractiveElement, Styled, div, px,
+};
+
+use crate::ui::{theme::Theme, toasts::show_toast};
+
+/// What a MusicBrainz ID identifies, which decides where it links to on MusicBrainz.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MusicBrainzEntity {
+    Release,
+    Recording,
+}
+
+impl MusicBrainzEntity {
+    fn path(self) -> &'static str {
+        match self {
+            MusicBrainzEntity::Release => "release",
+            MusicBrainzEntity::Recording => "recording",
+        }
+    }
+}
+
+/// Returns the page for the given ID on MusicBrainz.
+pub fn musicbrainz_url(entity: MusicBrainzEntity, mbid: &str) -> String {
+    format!("https://musicbrainz.org/{}/{mbid}", entity.path())
+}
+
+/// Shortens an ID to its first block, which is plenty to tell IDs apart at a glance.
+fn abbreviate(mbid: &str) -> String {
+    match mbid.split_once('-') {
+        Some((first, _)) => format!("{first}…"),
+        None => mbid.to_string(),
+    }
+}
+
+/// Shows a MusicBrainz ID, abbreviated, with a link to its page on MusicBrainz. Clicking the ID
+/// copies the full ID.
+pub fn musicbrainz_id(
+    id: &'static str,
+    entity: MusicBrainzEntity,
+    mbid: &str,
+    cx: &App,
+) -> impl IntoElement {
+    let theme = cx.global::<Theme>();
+    let full = mbid.to_string();
+    let url = musicbrainz_url(entity, mbid);
+
+    div()
+        .flex()
+        .gap(px(8.0))
+        .child(
+            div()
+                .id(ElementId::Name(format!("{id}-copy").into()))
+                .cursor_pointer()
+                .hover(|this| this.text_color(theme.text))
+                .on_click(move |_, _, cx| {
+                    cx.write_to_clipboard(ClipboardItem::new_string(full.clone()));
+                    show_toast(cx, "Copied the MusicBrainz ID.");
+                })
+                .child(format!("MusicBrainz ID {}", abbreviate(mbid))),
+        )
+        .child(
+            div()
+                .id(ElementId::Name(format!("{id}-open").into()))
+                .cursor_pointer()
+                .text_color(theme.text_link)
+                .hover(|this| this.border_b_1().border_color(theme.text_link))
+                .on_click(move |_, _, cx| cx.open_url(&url))
+                .child("Open on MusicBrainz"),
+        )
+}
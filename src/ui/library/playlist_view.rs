@@ -1,9 +1,10 @@
-use std::sync::Arc;
+use std::{cmp::Ordering, sync::Arc};
 
 use gpui::{
-    App, AppContext, Context, DragMoveEvent, Entity, FocusHandle, FontWeight, InteractiveElement,
-    IntoElement, KeyBinding, ParentElement, Render, SharedString, StatefulInteractiveElement,
-    Styled, UniformListScrollHandle, Window, actions, div, prelude::FluentBuilder, px, rems, rgba,
+    App, AppContext, ClickEvent, Context, DragMoveEvent, Entity, FocusHandle, FontWeight,
+    InteractiveElement, IntoElement, KeyBinding, MouseButton, MouseDownEvent, ParentElement,
+    Render, ScrollStrategy, SharedString, StatefulInteractiveElement, Styled,
+    UniformListScrollHandle, Window, actions, div, prelude::FluentBuilder, px, rems, rgba,
     uniform_list,
 };
 use rustc_hash::FxHashMap;
@@ -12,45 +13,174 @@ use tracing::error;
 use crate::{
     library::{
         db::LibraryAccess,
-        playlist::export_playlist,
-        types::{Playlist, PlaylistType},
-    },
-    playback::{
-        interface::{PlaybackInterface, replace_queue},
-        queue::QueueItemData,
+        playlist::{export_playlist, move_playlist_item_to_index},
+        types::{DBString, Playlist, PlaylistTrackSummary, PlaylistType},
     },
+    playback::interface::PlaybackInterface,
     ui::{
-        caching::hummingbird_cache,
+        caching::{HummingbirdImageCache, visible_capacity},
         command_palette::{Command, CommandManager},
         components::{
-            button::{ButtonIntent, ButtonSize, button},
+            button::{ButtonIntent, ButtonSize, ButtonStyle, button},
+            context::context,
             drag_drop::{
                 DragDropItemState, DragDropListConfig, DragDropListManager, DragPreview,
                 DropIndicator, TrackDragData, check_drag_cancelled, continue_edge_scroll,
                 handle_track_drag_move, handle_track_drop,
             },
-            icons::{CIRCLE_PLUS, PLAY, PLAYLIST, SHUFFLE, STAR, icon},
+            empty_state::{EmptyState, empty_state},
+            icons::{CHECK, CIRCLE_PLUS, PLAY, PLAYLIST, SHUFFLE, STAR, icon},
+            menu::{menu, menu_check_item, menu_item, menu_separator},
             scrollbar::{RightPad, ScrollableHandle, floating_scrollbar},
         },
-        library::track_listing::{
-            ArtistNameVisibility,
-            track_item::{TrackItem, TrackItemLeftField},
+        external_drag,
+        large_queue::{PLAY_NOW, add_to_queue, play_now},
+        library::{
+            ViewSwitchMessage,
+            track_listing::{
+                ArtistNameVisibility,
+                header::{TrackListColumn, TrackListSort, track_list_header},
+                track_item::{TrackItem, TrackItemLeftField, playlist_queue_entries},
+            },
         },
         models::{Models, PlaybackInfo, PlaylistEvent},
+        shortcuts::{ActionGroup, describe_action},
         theme::Theme,
-        util::{create_or_retrieve_view, prune_views},
+        toasts::show_toast,
+        util::{create_or_retrieve_view, prune_views, ui_px},
     },
 };
 
 use super::track_listing::track_item::TrackPlaylistInfo;
 
-actions!(playlist, [Export, Import]);
+actions!(
+    playlist,
+    [
+        Export,
+        Import,
+        RenamePlaylist,
+        MoveItemUp,
+        MoveItemDown,
+        SortByPosition,
+        SortByTitle,
+        SortByArtist,
+        SortByAlbum,
+        SortByDateAdded,
+        SortByBpm,
+        SortByDuration
+    ]
+);
 
-// height + border
+// height + border, before the interface scale is applied
 const PLAYLIST_ITEM_HEIGHT: f32 = 40.0;
 
+/// The BPM ranges offered by the playlist's quick filter.
+const BPM_RANGES: &[(i64, i64)] = &[(0, 99), (100, 119), (120, 140), (141, 160), (161, 999)];
+
+/// Compares two pieces of text ignoring case, putting missing text after any text.
+fn compare_text(a: Option<&DBString>, b: Option<&DBString>) -> Ordering {
+    match (a, b) {
+        (Some(a), Some(b)) => {
+            a.0.chars()
+                .flat_map(char::to_lowercase)
+                .cmp(b.0.chars().flat_map(char::to_lowercase))
+        }
+        (a, b) => compare_missing_last(a.is_some(), b.is_some()),
+    }
+}
+
+fn compare_missing_last(a_present: bool, b_present: bool) -> Ordering {
+    b_present.cmp(&a_present)
+}
+
+/// Sorts the playlist's rows for display. Rows that compare equal stay in playlist order.
+fn sort_rows(
+    rows: &mut [(i64, i64, i64)],
+    summaries: &FxHashMap<i64, PlaylistTrackSummary>,
+    sort: TrackListSort,
+) {
+    rows.sort_by(|(a, _, _), (b, _, _)| {
+        let (Some(a), Some(b)) = (summaries.get(a), summaries.get(b)) else {
+            return Ordering::Equal;
+        };
+
+        let ordering = match sort.column {
+            TrackListColumn::Number => Ordering::Equal,
+            TrackListColumn::Title => {
+                compare_text(Some(&a.title_sortable), Some(&b.title_sortable))
+            }
+            TrackListColumn::Artist => compare_text(a.artist_name.as_ref(), b.artist_name.as_ref()),
+            TrackListColumn::Album => compare_text(
+                a.album_title_sortable.as_ref(),
+                b.album_title_sortable.as_ref(),
+            ),
+            TrackListColumn::Added => a.added.cmp(&b.added),
+            TrackListColumn::Bpm => match (a.bpm, b.bpm) {
+                (Some(a), Some(b)) => a.cmp(&b),
+                (a, b) => compare_missing_last(a.is_some(), b.is_some()),
+            },
+            TrackListColumn::Duration => a.duration.cmp(&b.duration),
+        };
+
+        if sort.ascending {
+            ordering
+        } else {
+            ordering.reverse()
+        }
+    });
+
+    if sort.column == TrackListColumn::Number && !sort.ascending {
+        rows.reverse();
+    }
+}
+
+const REORDER_WHILE_SORTED: &str = "Sort by # to reorder the playlist.";
+
+/// Loads the sortable columns of every track in the playlist, by playlist item id.
+fn load_summaries(cx: &App, playlist_id: i64) -> FxHashMap<i64, PlaylistTrackSummary> {
+    match cx.get_playlist_track_summaries(playlist_id) {
+        Ok(summaries) => summaries
+            .iter()
+            .map(|summary| (summary.item_id, summary.clone()))
+            .collect(),
+        Err(err) => {
+            error!("Failed to load playlist track details: {}", err);
+            FxHashMap::default()
+        }
+    }
+}
+
+fn bpm_range_label(range: Option<(i64, i64)>) -> SharedString {
+    match range {
+        None => "Any BPM".into(),
+        Some((0, max)) => format!("Under {} BPM", max + 1).into(),
+        Some((min, 999)) => format!("Over {} BPM", min - 1).into(),
+        Some((min, max)) => format!("{}–{} BPM", min, max).into(),
+    }
+}
+
 pub fn bind_actions(cx: &mut App) {
-    cx.bind_keys([KeyBinding::new("secondary-s", Export, None)]);
+    cx.bind_keys([
+        KeyBinding::new("secondary-s", Export, None),
+        KeyBinding::new("f2", RenamePlaylist, None),
+        KeyBinding::new("secondary-up", MoveItemUp, None),
+        KeyBinding::new("secondary-down", MoveItemDown, None),
+    ]);
+
+    describe_action(cx, ActionGroup::Library, "Export Playlist to M3U", Export);
+    describe_action(cx, ActionGroup::Library, "Rename Playlist", RenamePlaylist);
+    describe_action(
+        cx,
+        ActionGroup::Library,
+        "Move Playlist Item Up",
+        MoveItemUp,
+    );
+    describe_action(
+        cx,
+        ActionGroup::Library,
+        "Move Playlist Item Down",
+        MoveItemDown,
+    );
 }
 
 /// Wrapper component for playlist track items that adds drag-and-drop support
@@ -61,6 +191,10 @@ pub struct PlaylistTrackItem {
     track_title: SharedString,
     drag_drop_manager: Entity<DragDropListManager>,
     list_id: gpui::ElementId,
+    /// The playlist item that keyboard reordering applies to, shared with the playlist view.
+    selected: Entity<Option<i64>>,
+    /// The playlist view's focus handle, so that keyboard reordering works after clicking a row.
+    focus_handle: FocusHandle,
     /// Track info for drag data
     track_id: i64,
     album_id: Option<i64>,
@@ -76,6 +210,8 @@ impl PlaylistTrackItem {
         track_title: SharedString,
         drag_drop_manager: Entity<DragDropListManager>,
         list_id: gpui::ElementId,
+        selected: Entity<Option<i64>>,
+        focus_handle: FocusHandle,
         track_id: i64,
         album_id: Option<i64>,
         track_path: std::path::PathBuf,
@@ -86,6 +222,11 @@ impl PlaylistTrackItem {
             })
             .detach();
 
+            cx.observe(&selected, |_, _, cx| {
+                cx.notify();
+            })
+            .detach();
+
             Self {
                 track_item,
                 idx,
@@ -93,6 +234,8 @@ impl PlaylistTrackItem {
                 track_title,
                 drag_drop_manager,
                 list_id,
+                selected,
+                focus_handle,
                 track_id,
                 album_id,
                 track_path,
@@ -101,6 +244,14 @@ impl PlaylistTrackItem {
     }
 }
 
+impl PlaylistTrackItem {
+    fn select(&mut self, _: &MouseDownEvent, window: &mut Window, cx: &mut Context<Self>) {
+        let item_id = self.playlist_item_id;
+        self.selected.write(cx, Some(item_id));
+        self.focus_handle.focus(window, cx);
+    }
+}
+
 impl Render for PlaylistTrackItem {
     fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
         let theme = cx.global::<Theme>();
@@ -108,6 +259,7 @@ impl Render for PlaylistTrackItem {
 
         let idx = self.idx;
         let track_title = self.track_title.clone();
+        let is_selected = *self.selected.read(cx) == Some(self.playlist_item_id);
 
         let drag_data = TrackDragData::from_track(
             self.track_id,
@@ -120,14 +272,30 @@ impl Render for PlaylistTrackItem {
         div()
             .id(("playlist-track-item", self.playlist_item_id as u64))
             .w_full()
-            .h(px(PLAYLIST_ITEM_HEIGHT))
+            .h(ui_px(PLAYLIST_ITEM_HEIGHT, cx))
             .relative()
             .when(item_state.is_being_dragged, |d| d.opacity(0.5))
-            .on_drag(drag_data, move |_, _, _, cx| {
+            // right clicking selects the row too, so that it stays selected after it's moved with
+            // the context menu
+            .on_mouse_down(MouseButton::Left, cx.listener(Self::select))
+            .on_mouse_down(MouseButton::Right, cx.listener(Self::select))
+            .on_drag(drag_data, move |track, _, _, cx| {
+                external_drag::offer_files(vec![track.path.clone()]);
                 DragPreview::new(cx, track_title.clone())
             })
             .drag_over::<TrackDragData>(move |style, _, _, _| style.bg(rgba(0x88888822)))
             .child(self.track_item.clone())
+            .when(is_selected, |this| {
+                this.child(
+                    div()
+                        .absolute()
+                        .left_0()
+                        .top_0()
+                        .bottom_0()
+                        .w(px(3.0))
+                        .bg(theme.button_primary),
+                )
+            })
             .child(DropIndicator::with_state(
                 item_state.is_drop_target_before,
                 item_state.is_drop_target_after,
@@ -146,6 +314,16 @@ pub struct PlaylistView {
     scroll_handle: UniformListScrollHandle,
     drag_drop_manager: Entity<DragDropListManager>,
     list_id: gpui::ElementId,
+    image_cache: Entity<HummingbirdImageCache>,
+    bpm_filter: Option<(i64, i64)>,
+    show_bpm: bool,
+    /// The order the rows are displayed in. Sorting doesn't change the playlist itself, so it's
+    /// still played (and reordered) in its own order.
+    sort: TrackListSort,
+    /// The sortable columns of each row, by playlist item id.
+    summaries: Arc<FxHashMap<i64, PlaylistTrackSummary>>,
+    /// The playlist item (not track) id of the selected row, if any.
+    selected: Entity<Option<i64>>,
 }
 
 impl PlaylistView {
@@ -159,15 +337,18 @@ impl PlaylistView {
 
             cx.subscribe(
                 &playlist_tracker,
-                move |this: &mut Self, _, ev: &PlaylistEvent, cx| {
-                    if let PlaylistEvent::PlaylistUpdated(id) = ev
-                        && *id == this.playlist.id
-                    {
-                        this.playlist_track_ids = cx.get_playlist_tracks(this.playlist.id).unwrap();
-
-                        this.views = cx.new(|_| FxHashMap::default());
-                        this.render_counter = cx.new(|_| 0);
+                move |this: &mut Self, _, ev: &PlaylistEvent, cx| match ev {
+                    PlaylistEvent::PlaylistUpdated(id) if *id == this.playlist.id => {
+                        this.reload_tracks(cx);
                     }
+                    PlaylistEvent::PlaylistRenamed(id) if *id == this.playlist.id => {
+                        match cx.get_playlist(*id) {
+                            Ok(playlist) => this.playlist = playlist,
+                            Err(err) => error!(?err, "Failed to reload playlist {id}: {err}"),
+                        }
+                        cx.notify();
+                    }
+                    _ => {}
                 },
             )
             .detach();
@@ -178,6 +359,20 @@ impl PlaylistView {
             .detach();
 
             let focus_handle = cx.focus_handle();
+            let playlist = cx.get_playlist(playlist_id).unwrap();
+
+            // the sidebar handles renaming, since that's where the name is edited
+            if playlist.playlist_type == PlaylistType::User {
+                cx.register_command(
+                    ("playlist::rename", playlist_id),
+                    Command::new(
+                        Some("Playlist"),
+                        "Rename Playlist",
+                        RenamePlaylist,
+                        Some(focus_handle.clone()),
+                    ),
+                );
+            }
 
             cx.register_command(
                 ("playlist::export", playlist_id),
@@ -189,14 +384,92 @@ impl PlaylistView {
                 ),
             );
 
+            // there's no other way to change the sort from the keyboard
+            let sort_commands = [
+                (
+                    "playlist::sort_by_position",
+                    Command::new(
+                        Some("Playlist"),
+                        "Sort by Position",
+                        SortByPosition,
+                        Some(focus_handle.clone()),
+                    ),
+                ),
+                (
+                    "playlist::sort_by_title",
+                    Command::new(
+                        Some("Playlist"),
+                        "Sort by Title",
+                        SortByTitle,
+                        Some(focus_handle.clone()),
+                    ),
+                ),
+                (
+                    "playlist::sort_by_artist",
+                    Command::new(
+                        Some("Playlist"),
+                        "Sort by Artist",
+                        SortByArtist,
+                        Some(focus_handle.clone()),
+                    ),
+                ),
+                (
+                    "playlist::sort_by_album",
+                    Command::new(
+                        Some("Playlist"),
+                        "Sort by Album",
+                        SortByAlbum,
+                        Some(focus_handle.clone()),
+                    ),
+                ),
+                (
+                    "playlist::sort_by_date_added",
+                    Command::new(
+                        Some("Playlist"),
+                        "Sort by Date Added",
+                        SortByDateAdded,
+                        Some(focus_handle.clone()),
+                    ),
+                ),
+                (
+                    "playlist::sort_by_bpm",
+                    Command::new(
+                        Some("Playlist"),
+                        "Sort by BPM",
+                        SortByBpm,
+                        Some(focus_handle.clone()),
+                    ),
+                ),
+                (
+                    "playlist::sort_by_duration",
+                    Command::new(
+                        Some("Playlist"),
+                        "Sort by Length",
+                        SortByDuration,
+                        Some(focus_handle.clone()),
+                    ),
+                ),
+            ];
+            let sort_command_names = sort_commands.each_ref().map(|(name, _)| *name);
+
+            for (name, command) in sort_commands {
+                cx.register_command((name, playlist_id), command);
+            }
+
             cx.on_release(move |_, cx| {
                 cx.unregister_command(("playlist::export", playlist_id));
+                cx.unregister_command(("playlist::rename", playlist_id));
+
+                for name in sort_command_names {
+                    cx.unregister_command((name, playlist_id));
+                }
             })
             .detach();
 
             Self {
-                playlist: cx.get_playlist(playlist_id).unwrap(),
+                playlist,
                 playlist_track_ids: cx.get_playlist_tracks(playlist_id).unwrap(),
+                summaries: Arc::new(load_summaries(cx, playlist_id)),
                 views: cx.new(|_| FxHashMap::default()),
                 render_counter: cx.new(|_| 0),
                 focus_handle,
@@ -204,10 +477,204 @@ impl PlaylistView {
                 scroll_handle: UniformListScrollHandle::new(),
                 drag_drop_manager,
                 list_id,
+                image_cache: HummingbirdImageCache::new("playlist", 0, cx),
+                bpm_filter: None,
+                show_bpm: false,
+                sort: TrackListSort::default(),
+                selected: cx.new(|_| None),
             }
         })
     }
 
+    fn reload_tracks(&mut self, cx: &mut Context<Self>) {
+        let selected = *self.selected.read(cx);
+        let position_of_selected = |items: &[(i64, i64, i64)]| {
+            selected.and_then(|selected| items.iter().position(|(id, _, _)| *id == selected))
+        };
+        let previous_idx = position_of_selected(&self.playlist_track_ids);
+
+        let mut rows = match self.bpm_filter {
+            Some((min, max)) => cx
+                .get_playlist_tracks_in_bpm_range(self.playlist.id, min, max)
+                .unwrap(),
+            None => cx.get_playlist_tracks(self.playlist.id).unwrap(),
+        };
+
+        self.summaries = Arc::new(load_summaries(cx, self.playlist.id));
+
+        if self.is_sorted() {
+            sort_rows(Arc::make_mut(&mut rows), &self.summaries, self.sort);
+        }
+
+        self.playlist_track_ids = rows;
+
+        // keep the selected row in view when it's been moved
+        if let Some(idx) = position_of_selected(&self.playlist_track_ids)
+            && previous_idx.is_some_and(|previous| previous != idx)
+        {
+            self.scroll_handle
+                .scroll_to_item(idx, ScrollStrategy::Center);
+        }
+
+        self.views = cx.new(|_| FxHashMap::default());
+        self.render_counter = cx.new(|_| 0);
+        cx.notify();
+    }
+
+    /// Moves the selected row up (negative offset) or down (positive offset) by the given number
+    /// of rows.
+    fn move_selected(&mut self, offset: isize, cx: &mut Context<Self>) {
+        let Some(item_id) = *self.selected.read(cx) else {
+            return;
+        };
+
+        if self.is_sorted() {
+            show_toast(cx, REORDER_WHILE_SORTED);
+            return;
+        }
+
+        let visible = &self.playlist_track_ids;
+        let Some(neighbour) = visible
+            .iter()
+            .position(|(id, _, _)| *id == item_id)
+            .and_then(|idx| idx.checked_add_signed(offset))
+            .and_then(|idx| visible.get(idx))
+            .map(|(id, _, _)| *id)
+        else {
+            return;
+        };
+
+        // when filtered by BPM, the rows next to each other aren't necessarily next to each other
+        // in the playlist, so the index is looked up in the full playlist
+        let Ok(items) = cx.get_playlist_tracks(self.playlist.id) else {
+            return;
+        };
+        let Some(index) = items.iter().position(|(id, _, _)| *id == neighbour) else {
+            return;
+        };
+
+        move_playlist_item_to_index(cx, self.playlist.id, item_id, index);
+    }
+
+    /// Whether the rows are displayed in a different order than the playlist's own.
+    fn is_sorted(&self) -> bool {
+        self.sort != TrackListSort::default()
+    }
+
+    /// Sorts the rows by the given column, or reverses the order if they're already sorted by it.
+    fn sort_by(&mut self, column: TrackListColumn, cx: &mut Context<Self>) {
+        self.sort = self.sort.toggle(column);
+        self.reload_tracks(cx);
+    }
+
+    fn set_bpm_filter(&mut self, filter: Option<(i64, i64)>, cx: &mut Context<Self>) {
+        self.bpm_filter = filter;
+        self.reload_tracks(cx);
+    }
+
+    fn toggle_show_bpm(&mut self, cx: &mut Context<Self>) {
+        self.show_bpm = !self.show_bpm;
+        // the track items need to be rebuilt to pick up the change
+        self.reload_tracks(cx);
+    }
+
+    /// What to show when there are no tracks to list, depending on why.
+    fn render_empty_state(&self, cx: &Context<Self>) -> EmptyState {
+        if self.bpm_filter.is_some() {
+            let weak = cx.weak_entity();
+
+            return empty_state("playlist-empty", "No tracks in this BPM range")
+                .icon(PLAYLIST)
+                .description("None of the tracks in this playlist have a BPM in this range.")
+                .action("Clear filter", move |_, _, cx| {
+                    weak.update(cx, |this, cx| this.set_bpm_filter(None, cx))
+                        .ok();
+                });
+        }
+
+        let browse_tracks = |_: &ClickEvent, _: &mut Window, cx: &mut App| {
+            let switcher_model = cx.global::<Models>().switcher_model.clone();
+            switcher_model.update(cx, |_, cx| {
+                cx.emit(ViewSwitchMessage::Tracks);
+            });
+        };
+
+        if self.playlist.playlist_type == PlaylistType::System {
+            empty_state("playlist-empty", "No liked songs yet")
+                .icon(STAR)
+                .description("Click the star next to any track to add it here.")
+                .action("Browse tracks", browse_tracks)
+        } else {
+            empty_state("playlist-empty", "No tracks yet")
+                .icon(PLAYLIST)
+                .description("Right click any track and choose \"Add to playlist\" to add it here.")
+                .action("Browse tracks", browse_tracks)
+        }
+    }
+
+    /// Renders the BPM quick filter. Clicking cycles through the available ranges, right-clicking
+    /// lists them all.
+    fn render_bpm_filter(&self, cx: &Context<Self>) -> impl IntoElement {
+        let weak_self = cx.weak_entity();
+
+        let next = match self.bpm_filter {
+            None => BPM_RANGES.first().copied(),
+            Some(current) => BPM_RANGES
+                .iter()
+                .position(|range| *range == current)
+                .and_then(|idx| BPM_RANGES.get(idx + 1))
+                .copied(),
+        };
+
+        let filter_menu = std::iter::once(None)
+            .chain(BPM_RANGES.iter().copied().map(Some))
+            .enumerate()
+            .map(|(idx, range)| {
+                let weak_self = weak_self.clone();
+
+                menu_item(
+                    ("playlist-bpm-range", idx),
+                    (self.bpm_filter == range).then_some(CHECK),
+                    bpm_range_label(range),
+                    move |_, _, cx| {
+                        weak_self
+                            .update(cx, |this, cx| this.set_bpm_filter(range, cx))
+                            .ok();
+                    },
+                )
+            })
+            .fold(menu(), |menu, item| menu.item(item));
+
+        let toggle_weak = weak_self.clone();
+
+        context("playlist-bpm-filter-context")
+            .with(
+                button()
+                    .id("playlist-bpm-filter")
+                    .size(ButtonSize::Large)
+                    .style(ButtonStyle::Minimal)
+                    .flex_none()
+                    .child(bpm_range_label(self.bpm_filter))
+                    .on_click(move |_, _, cx| {
+                        weak_self
+                            .update(cx, |this, cx| this.set_bpm_filter(next, cx))
+                            .ok();
+                    }),
+            )
+            .child(div().bg(cx.global::<Theme>().elevated_background).child(
+                filter_menu.item(menu_separator()).item(menu_check_item(
+                    "playlist-show-bpm",
+                    self.show_bpm,
+                    "Always show BPM",
+                    move |_, _, cx| {
+                        toggle_weak
+                            .update(cx, |this, cx| this.toggle_show_bpm(cx))
+                            .ok();
+                    },
+                )),
+            ))
+    }
+
     fn schedule_edge_scroll(
         manager: Entity<DragDropListManager>,
         scroll_handle: ScrollableHandle,
@@ -233,6 +700,10 @@ impl Render for PlaylistView {
     fn render(&mut self, window: &mut Window, cx: &mut Context<Self>) -> impl gpui::IntoElement {
         check_drag_cancelled(self.drag_drop_manager.clone(), cx);
 
+        let item_height = ui_px(PLAYLIST_ITEM_HEIGHT, cx);
+        self.drag_drop_manager
+            .update(cx, |manager, _| manager.set_item_height(item_height));
+
         let items_clone = self.playlist_track_ids.clone();
         let views_model = self.views.clone();
         let render_counter = self.render_counter.clone();
@@ -241,7 +712,31 @@ impl Render for PlaylistView {
         let scroll_handle = self.scroll_handle.clone();
         let drag_drop_manager = self.drag_drop_manager.clone();
         let list_id = self.list_id.clone();
+        let selected = self.selected.clone();
+        let focus_handle = self.focus_handle.clone();
         let item_count = items_clone.len();
+        let bpm_filter = self.bpm_filter;
+        // filtering by BPM is pointless if you can't see it
+        let show_bpm = self.show_bpm || bpm_filter.is_some();
+        let summaries = self.summaries.clone();
+
+        let columns = [
+            Some(TrackListColumn::Number),
+            Some(TrackListColumn::Title),
+            Some(TrackListColumn::Artist),
+            Some(TrackListColumn::Album),
+            Some(TrackListColumn::Added),
+            show_bpm.then_some(TrackListColumn::Bpm),
+            Some(TrackListColumn::Duration),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+
+        let capacity = visible_capacity(window, ui_px(PLAYLIST_ITEM_HEIGHT, cx), 1);
+        self.image_cache.update(cx, |cache, cx| {
+            cache.set_max_items(capacity, window, cx);
+        });
 
         if self.first_render {
             self.first_render = false;
@@ -251,10 +746,7 @@ impl Render for PlaylistView {
         let theme = cx.global::<Theme>();
 
         div()
-            .image_cache(hummingbird_cache(
-                ("playlist", self.playlist.id as u64),
-                100,
-            ))
+            .image_cache(self.image_cache.clone())
             .id("playlist-view")
             .track_focus(&self.focus_handle)
             .on_action(move |_: &Export, _, cx| {
@@ -262,6 +754,33 @@ impl Render for PlaylistView {
                     error!("Failed to export playlist: {}", err);
                 }
             })
+            .on_action(cx.listener(|this, _: &MoveItemUp, _, cx| this.move_selected(-1, cx)))
+            .on_action(cx.listener(|this, _: &MoveItemDown, _, cx| this.move_selected(1, cx)))
+            .on_action(cx.listener(|this, _: &SortByPosition, _, cx| {
+                this.sort_by(TrackListColumn::Number, cx)
+            }))
+            .on_action(
+                cx.listener(|this, _: &SortByTitle, _, cx| {
+                    this.sort_by(TrackListColumn::Title, cx)
+                }),
+            )
+            .on_action(cx.listener(|this, _: &SortByArtist, _, cx| {
+                this.sort_by(TrackListColumn::Artist, cx)
+            }))
+            .on_action(
+                cx.listener(|this, _: &SortByAlbum, _, cx| {
+                    this.sort_by(TrackListColumn::Album, cx)
+                }),
+            )
+            .on_action(cx.listener(|this, _: &SortByDateAdded, _, cx| {
+                this.sort_by(TrackListColumn::Added, cx)
+            }))
+            .on_action(
+                cx.listener(|this, _: &SortByBpm, _, cx| this.sort_by(TrackListColumn::Bpm, cx)),
+            )
+            .on_action(cx.listener(|this, _: &SortByDuration, _, cx| {
+                this.sort_by(TrackListColumn::Duration, cx)
+            }))
             .pt(px(10.0))
             .flex()
             .flex_col()
@@ -328,27 +847,15 @@ impl Render for PlaylistView {
                                             .font_weight(FontWeight::SEMIBOLD)
                                             .intent(ButtonIntent::Primary)
                                             .child(icon(PLAY).size(px(16.0)).my_auto())
-                                            .child("Play")
+                                            .child(PLAY_NOW)
                                             .on_click(cx.listener(|this, _, _, cx| {
-                                                let tracks = cx
-                                                    .get_playlist_track_files(this.playlist.id)
-                                                    .unwrap();
-
-                                                let queue_items = this
-                                                    .playlist_track_ids
-                                                    .iter()
-                                                    .zip(tracks.iter())
-                                                    .map(|((_, track, album), path)| {
-                                                        QueueItemData::new(
-                                                            cx,
-                                                            path.into(),
-                                                            Some(*track),
-                                                            Some(*album),
-                                                        )
-                                                    })
-                                                    .collect();
-
-                                                replace_queue(queue_items, cx);
+                                                let entries = playlist_queue_entries(
+                                                    cx,
+                                                    this.playlist.id,
+                                                    this.bpm_filter,
+                                                );
+
+                                                play_now(cx, entries, None);
                                             })),
                                     )
                                     .child(
@@ -358,26 +865,13 @@ impl Render for PlaylistView {
                                             .flex_none()
                                             .child(icon(CIRCLE_PLUS).size(px(16.0)).my_auto())
                                             .on_click(cx.listener(|this, _, _, cx| {
-                                                let tracks = cx
-                                                    .get_playlist_track_files(this.playlist.id)
-                                                    .unwrap();
-
-                                                let queue_items = this
-                                                    .playlist_track_ids
-                                                    .iter()
-                                                    .zip(tracks.iter())
-                                                    .map(|((_, track, album), path)| {
-                                                        QueueItemData::new(
-                                                            cx,
-                                                            path.into(),
-                                                            Some(*track),
-                                                            Some(*album),
-                                                        )
-                                                    })
-                                                    .collect();
-
-                                                cx.global::<PlaybackInterface>()
-                                                    .queue_list(queue_items);
+                                                let entries = playlist_queue_entries(
+                                                    cx,
+                                                    this.playlist.id,
+                                                    this.bpm_filter,
+                                                );
+
+                                                add_to_queue(cx, entries);
                                             })),
                                     )
                                     .child(
@@ -387,23 +881,11 @@ impl Render for PlaylistView {
                                             .flex_none()
                                             .child(icon(SHUFFLE).size(px(16.0)).my_auto())
                                             .on_click(cx.listener(|this, _, _, cx| {
-                                                let tracks = cx
-                                                    .get_playlist_track_files(this.playlist.id)
-                                                    .unwrap();
-
-                                                let queue_items = this
-                                                    .playlist_track_ids
-                                                    .iter()
-                                                    .zip(tracks.iter())
-                                                    .map(|((_, track, album), path)| {
-                                                        QueueItemData::new(
-                                                            cx,
-                                                            path.into(),
-                                                            Some(*track),
-                                                            Some(*album),
-                                                        )
-                                                    })
-                                                    .collect();
+                                                let entries = playlist_queue_entries(
+                                                    cx,
+                                                    this.playlist.id,
+                                                    this.bpm_filter,
+                                                );
 
                                                 if !(*cx
                                                     .global::<PlaybackInfo>()
@@ -414,12 +896,24 @@ impl Render for PlaylistView {
                                                         .toggle_shuffle();
                                                 }
 
-                                                replace_queue(queue_items, cx);
+                                                play_now(cx, entries, None);
                                             })),
-                                    ),
+                                    )
+                                    .child(self.render_bpm_filter(cx)),
                             ),
                     ),
             )
+            // the header sits outside of the list, so that it stays in place while the list scrolls
+            .child(div().mt(px(18.0)).w_full().flex_shrink_0().child(
+                track_list_header("playlist-header", columns, self.sort).on_sort({
+                    let weak_self = cx.weak_entity();
+                    move |column, _, cx| {
+                        weak_self
+                            .update(cx, |this, cx| this.sort_by(column, cx))
+                            .ok();
+                    }
+                }),
+            ))
             .child(
                 div()
                     .id("playlist-list-container")
@@ -427,12 +921,16 @@ impl Render for PlaylistView {
                     .w_full()
                     .h_full()
                     .relative()
-                    .mt(px(18.0))
                     .on_drag_move::<TrackDragData>(cx.listener(
                         move |this: &mut PlaylistView,
                               event: &DragMoveEvent<TrackDragData>,
                               window,
                               cx| {
+                            // the rows can't be reordered while they're sorted
+                            if this.is_sorted() {
+                                return;
+                            }
+
                             let scroll_handle: ScrollableHandle = this.scroll_handle.clone().into();
 
                             let scrolled = handle_track_drag_move(
@@ -471,6 +969,12 @@ impl Render for PlaylistView {
                             let playlist_track_ids = this.playlist_track_ids.clone();
                             let playlist_id = this.playlist.id;
 
+                            if this.is_sorted()
+                                && drag_data.source_list_id == Some(this.list_id.clone())
+                            {
+                                show_toast(cx, REORDER_WHILE_SORTED);
+                            }
+
                             handle_track_drop(
                                 this.drag_drop_manager.clone(),
                                 drag_data,
@@ -523,15 +1027,24 @@ impl Render for PlaylistView {
 
                                     let drag_drop_manager = drag_drop_manager.clone();
                                     let list_id = list_id.clone();
+                                    let selected = selected.clone();
+                                    let focus_handle = focus_handle.clone();
                                     let playlist_item_id = item.0;
                                     let track_id = item.1;
+                                    let summary = summaries.get(&playlist_item_id).cloned();
 
-                                    div().h(px(PLAYLIST_ITEM_HEIGHT)).child(
+                                    div().h(ui_px(PLAYLIST_ITEM_HEIGHT, cx)).child(
                                         create_or_retrieve_view(
                                             &views_model,
                                             idx,
                                             move |cx| {
-                                                let track = cx.get_track_by_id(track_id).unwrap();
+                                                let mut track = Arc::try_unwrap(
+                                                    cx.get_track_by_id(track_id).unwrap(),
+                                                )
+                                                .unwrap();
+                                                track.album_title = summary
+                                                    .as_ref()
+                                                    .and_then(|v| v.album_title.clone());
                                                 let track_title: SharedString =
                                                     track.title.clone().into();
                                                 let track_path = track.location.clone();
@@ -539,17 +1052,23 @@ impl Render for PlaylistView {
 
                                                 let track_item = TrackItem::new(
                                                     cx,
-                                                    Arc::try_unwrap(track).unwrap(),
+                                                    track,
                                                     false,
                                                     ArtistNameVisibility::Always,
                                                     TrackItemLeftField::Art,
                                                     Some(TrackPlaylistInfo {
                                                         id: pl_id,
                                                         item_id: playlist_item_id,
+                                                        bpm_range: bpm_filter,
                                                     }),
                                                     false, // vinyl_numbering - not applicable for playlists
                                                 );
 
+                                                track_item.update(cx, |item, _| {
+                                                    item.show_bpm = show_bpm;
+                                                    item.added = summary.map(|v| v.added);
+                                                });
+
                                                 PlaylistTrackItem::new(
                                                     cx,
                                                     track_item,
@@ -558,6 +1077,8 @@ impl Render for PlaylistView {
                                                     track_title,
                                                     drag_drop_manager,
                                                     list_id,
+                                                    selected,
+                                                    focus_handle,
                                                     track_id,
                                                     album_id,
                                                     track_path,
@@ -573,11 +1094,17 @@ impl Render for PlaylistView {
                         .h_full()
                         .flex()
                         .flex_col()
-                        .border_color(theme.border_color)
-                        .border_t_1()
                         .track_scroll(&scroll_handle),
                     )
-                    .child(floating_scrollbar("playlist", scroll_handle, RightPad::Pad)),
+                    .child(floating_scrollbar("playlist", scroll_handle, RightPad::Pad))
+                    .when(item_count == 0, |this| {
+                        this.child(
+                            div()
+                                .absolute()
+                                .inset_0()
+                                .child(self.render_empty_state(cx)),
+                        )
+                    }),
             )
     }
 }
@@ -0,0 +1,209 @@
+use gpui::*;
+use prelude::FluentBuilder;
+use sqlx::SqlitePool;
+
+use crate::{
+    library::{
+        db::{list_albums_by_artist, list_albums_by_label, list_albums_by_year},
+        types::{Album, AlbumSummary},
+    },
+    ui::{models::Models, theme::Theme},
+};
+
+use super::ViewSwitchMessage;
+
+/// If the artist has fewer other albums than this, albums from the same label or year are shown
+/// as well.
+const MIN_ARTIST_ALBUMS: usize = 3;
+
+/// The most albums shown in the label and year sections.
+const SUPPLEMENT_LIMIT: u32 = 10;
+
+const CARD_SIZE: f32 = 120.0;
+
+/// What a related album's card shows under its title.
+#[derive(Clone, Copy, PartialEq)]
+enum Subtitle {
+    Year,
+    Artist,
+}
+
+/// A titled row of albums related to the one being viewed.
+#[derive(Clone)]
+pub struct RelatedSection {
+    heading: SharedString,
+    albums: Vec<AlbumSummary>,
+    subtitle: Subtitle,
+}
+
+/// Finds the albums to show at the bottom of an album's page: the artist's other albums, and if
+/// there are only a few of those, other albums from the same label (or failing that, the same
+/// year). Sections without any albums are left out. Years are the ones the albums are shown with
+/// (see [`Album::display_date`]).
+pub async fn load_related_albums(
+    pool: &SqlitePool,
+    album: &Album,
+    artist_name: Option<SharedString>,
+    prefer_original: bool,
+) -> sqlx::Result<Vec<RelatedSection>> {
+    let mut sections = Vec::new();
+
+    let by_artist = list_albums_by_artist(pool, album.artist_id, album.id, prefer_original).await?;
+    let artist_count = by_artist.len();
+
+    if !by_artist.is_empty() {
+        sections.push(RelatedSection {
+            heading: match artist_name {
+                Some(name) => format!("More by {name}").into(),
+                None => "More by this artist".into(),
+            },
+            albums: by_artist,
+            subtitle: Subtitle::Year,
+        });
+    }
+
+    if artist_count >= MIN_ARTIST_ALBUMS {
+        return Ok(sections);
+    }
+
+    if let Some(label) = &album.label {
+        let by_label =
+            list_albums_by_label(pool, &label.0, album.id, SUPPLEMENT_LIMIT, prefer_original)
+                .await?;
+
+        if !by_label.is_empty() {
+            sections.push(RelatedSection {
+                heading: format!("Also from {}", label.0).into(),
+                albums: by_label,
+                subtitle: Subtitle::Artist,
+            });
+
+            return Ok(sections);
+        }
+    }
+
+    if let Some(year) = album.display_date(prefer_original).map(|date| date.year()) {
+        let by_year =
+            list_albums_by_year(pool, year, album.id, SUPPLEMENT_LIMIT, prefer_original).await?;
+
+        if !by_year.is_empty() {
+            sections.push(RelatedSection {
+                heading: format!("Also from {year}").into(),
+                albums: by_year,
+                subtitle: Subtitle::Artist,
+            });
+        }
+    }
+
+    Ok(sections)
+}
+
+fn album_card(album: &AlbumSummary, subtitle: Subtitle, cx: &App) -> impl IntoElement {
+    let theme = cx.global::<Theme>();
+    let album_id = album.id;
+
+    let subtitle = match subtitle {
+        Subtitle::Year => album.year.map(|year| SharedString::from(year.to_string())),
+        Subtitle::Artist => Some(album.artist_name.0.clone()),
+    };
+
+    div()
+        .id(("related-album", album_id as usize))
+        .flex()
+        .flex_col()
+        .flex_shrink_0()
+        .w(px(CARD_SIZE))
+        .cursor_pointer()
+        .on_click(move |_, _, cx| {
+            let switcher_model = cx.global::<Models>().switcher_model.clone();
+            switcher_model.update(cx, |_, cx| {
+                cx.emit(ViewSwitchMessage::Release(album_id));
+            });
+        })
+        .child(
+            div()
+                .rounded(px(4.0))
+                .bg(theme.album_art_background)
+                .shadow_sm()
+                .w(px(CARD_SIZE))
+                .h(px(CARD_SIZE))
+                .overflow_hidden()
+                .hover(|this| this.opacity(0.8))
+                .child(
+                    img(SharedString::from(format!("!db://album/{album_id}/thumb")))
+                        .w(px(CARD_SIZE))
+                        .h(px(CARD_SIZE))
+                        .object_fit(ObjectFit::Fill)
+                        .rounded(px(4.0)),
+                ),
+        )
+        .child(
+            div()
+                .pt(px(6.0))
+                .text_sm()
+                .font_weight(FontWeight::SEMIBOLD)
+                .overflow_x_hidden()
+                .text_ellipsis()
+                .whitespace_nowrap()
+                .child(album.title.0.clone()),
+        )
+        .when_some(subtitle, |this, subtitle| {
+            this.child(
+                div()
+                    .text_xs()
+                    .text_color(theme.text_secondary)
+                    .overflow_x_hidden()
+                    .text_ellipsis()
+                    .whitespace_nowrap()
+                    .child(subtitle),
+            )
+        })
+}
+
+/// A heading followed by a horizontally scrollable row of album cards. Clicking a card opens the
+/// album.
+#[derive(IntoElement)]
+pub struct AlbumCardRow {
+    id: ElementId,
+    section: RelatedSection,
+}
+
+pub fn album_card_row(id: impl Into<ElementId>, section: RelatedSection) -> AlbumCardRow {
+    AlbumCardRow {
+        id: id.into(),
+        section,
+    }
+}
+
+impl RenderOnce for AlbumCardRow {
+    fn render(self, _: &mut Window, cx: &mut App) -> impl IntoElement {
+        let cards: Vec<_> = self
+            .section
+            .albums
+            .iter()
+            .map(|album| album_card(album, self.section.subtitle, cx))
+            .collect();
+
+        div()
+            .flex()
+            .flex_col()
+            .pb(px(12.0))
+            .child(
+                div()
+                    .px(px(18.0))
+                    .pb(px(8.0))
+                    .font_weight(FontWeight::BOLD)
+                    .child(self.section.heading),
+            )
+            .child(
+                div()
+                    .id(self.id)
+                    .flex()
+                    .gap(px(12.0))
+                    .px(px(18.0))
+                    .pb(px(4.0))
+                    .overflow_x_scroll()
+                    .children(cards),
+            )
+    }
+}
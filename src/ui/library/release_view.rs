@@ -1,33 +1,98 @@
-use std::{f32, sync::Arc};
+use std::{f32, rc::Rc, sync::Arc};
 
+use anyhow::anyhow;
+use futures::FutureExt;
 use gpui::*;
 use prelude::FluentBuilder;
+use tracing::{error, warn};
 
 use crate::{
     library::{
-        db::{AlbumMethod, LibraryAccess},
-        types::{Album, DBString, Track},
-    },
-    playback::{
-        interface::{PlaybackInterface, replace_queue},
-        queue::QueueItemData,
-        thread::PlaybackState,
+        db::{AlbumMethod, LibraryAccessAsync},
+        types::{Album, ArtSource, DBString, Track},
     },
+    playback::{interface::PlaybackInterface, thread::PlaybackState},
+    settings::SettingsGlobal,
     ui::{
-        caching::hummingbird_cache,
+        app::Pool,
+        caching::HummingbirdImageCache,
         components::{
             button::{ButtonIntent, ButtonSize, button},
-            icons::{CIRCLE_PLUS, PAUSE, PLAY, SHUFFLE, icon},
+            icons::{CIRCLE_PLUS, FOLDER_SEARCH, PAUSE, PLAY, PLAYLIST_ADD, SHUFFLE, icon},
+            lightbox::{Lightbox, LightboxSource},
             scrollbar::{RightPad, floating_scrollbar},
         },
+        format,
         global_actions::PlayPause,
-        library::track_listing::{ArtistNameVisibility, TrackListing},
+        large_queue::{PLAY_NOW, QueueEntry, add_to_queue, play_now},
+        library::{
+            add_to_playlist::AddTracksToPlaylist,
+            album_progress::{AlbumProgress, AlbumProgressSummary},
+            credits::{common_credits, credits_section},
+            fix_grouping::FixGrouping,
+            musicbrainz::{MusicBrainzEntity, musicbrainz_id},
+            related_albums::{RelatedSection, album_card_row, load_related_albums},
+            track_listing::{ArtistNameVisibility, TrackListing},
+        },
         models::PlaybackInfo,
         theme::Theme,
+        toasts::show_toast,
     },
 };
 
+/// An album's page. The album is loaded in the background, and the page stays empty until it's
+/// ready.
 pub struct ReleaseView {
+    content: Option<Entity<ReleaseContent>>,
+}
+
+impl ReleaseView {
+    pub(super) fn new(cx: &mut App, album_id: i64) -> Entity<Self> {
+        cx.new(|cx| {
+            let album = cx.get_album_by_id_async(album_id, AlbumMethod::FullQuality);
+            let tracks = cx.list_tracks_in_album_async(album_id);
+
+            cx.spawn(async move |this, cx| {
+                let (album, tracks) = match (album.await, tracks.await) {
+                    (Ok(album), Ok(tracks)) => (album, tracks),
+                    (Err(err), _) | (_, Err(err)) => {
+                        error!(?err, "Failed to load album {album_id}: {err}");
+                        cx.update(|cx| show_toast(cx, "Couldn't open the album."))
+                            .ok();
+                        return;
+                    }
+                };
+
+                let Ok(artist_name) =
+                    cx.update(|cx| cx.get_artist_name_by_id_async(album.artist_id))
+                else {
+                    return;
+                };
+                let artist_name = artist_name.await.ok().map(|v| (*v).clone().into());
+
+                this.update(cx, |this: &mut Self, cx| {
+                    this.content = Some(ReleaseContent::new(cx, album, tracks, artist_name));
+                    cx.notify();
+                })
+                .ok();
+            })
+            .detach();
+
+            ReleaseView { content: None }
+        })
+    }
+}
+
+impl Render for ReleaseView {
+    fn render(&mut self, _: &mut Window, _: &mut Context<Self>) -> impl IntoElement {
+        match &self.content {
+            Some(content) => content.clone().into_any_element(),
+            None => div().into_any_element(),
+        }
+    }
+}
+
+struct ReleaseContent {
     album: Arc<Album>,
     artist_name: Option<DBString>,
     tracks: Arc<Vec<Track>>,
@@ -35,23 +100,31 @@ pub struct ReleaseView {
     release_info: Option<SharedString>,
     img_path: SharedString,
     scroll_handle: ScrollHandle,
+    image_cache: Entity<HummingbirdImageCache>,
+    show_add_to_playlist: Entity<bool>,
+    add_to_playlist: Entity<AddTracksToPlaylist>,
+    show_fix_grouping: Entity<bool>,
+    fix_grouping: Entity<FixGrouping>,
+    credits: Vec<(String, String)>,
+    credits_expanded: bool,
+    related: Vec<RelatedSection>,
+    related_image_cache: Entity<HummingbirdImageCache>,
+    _album_progress: Entity<AlbumProgress>,
+    progress_summary: Entity<AlbumProgressSummary>,
+    show_lightbox: Entity<bool>,
+    lightbox: Entity<Lightbox>,
 }
 
-impl ReleaseView {
-    pub(super) fn new(cx: &mut App, album_id: i64) -> Entity<Self> {
-        cx.new(|cx| {
-            // TODO: error handling
-            let album = cx
-                .get_album_by_id(album_id, AlbumMethod::FullQuality)
-                .expect("Failed to retrieve album");
-            let tracks = cx
-                .list_tracks_in_album(album_id)
-                .expect("Failed to retrieve tracks");
-            let artist_name = cx
-                .get_artist_name_by_id(album.artist_id)
-                .ok()
-                .map(|v| (*v).clone().into());
+impl ReleaseContent {
+    fn new(
+        cx: &mut App,
+        album: Arc<Album>,
+        tracks: Arc<Vec<Track>>,
+        artist_name: Option<DBString>,
+    ) -> Entity<Self> {
+        let album_id = album.id;
 
+        cx.new(|cx| {
             cx.on_release(|this: &mut Self, cx: &mut App| {
                 ImageSource::Resource(Resource::Embedded(this.img_path.clone())).remove_asset(cx);
             })
@@ -65,6 +138,14 @@ impl ReleaseView {
                 album.vinyl_numbering,
             );
 
+            let album_progress = AlbumProgress::new(cx, tracks.clone());
+            let (playing, remaining) = {
+                let progress = album_progress.read(cx);
+                (progress.playing.clone(), progress.remaining.clone())
+            };
+            track_listing.show_progress(cx, &playing);
+            let progress_summary = AlbumProgressSummary::new(cx, remaining);
+
             let release_info = {
                 let mut info = String::default();
 
@@ -87,7 +168,64 @@ impl ReleaseView {
                 }
             };
 
-            ReleaseView {
+            let show_add_to_playlist = cx.new(|_| false);
+            let add_to_playlist = AddTracksToPlaylist::new(
+                cx,
+                show_add_to_playlist.clone(),
+                Arc::new(tracks.iter().map(|track| track.id).collect()),
+            );
+
+            let show_fix_grouping = cx.new(|_| false);
+            let fix_grouping =
+                FixGrouping::new(cx, show_fix_grouping.clone(), album_id, album.match_basis);
+
+            let credits = common_credits(&tracks);
+
+            // the album's art is already loaded with the album, it's only decoded at full size
+            // while the lightbox is open
+            let show_lightbox = cx.new(|_| false);
+            let art_album = album.clone();
+            let lightbox_source: LightboxSource = Rc::new(move |_| {
+                let image = art_album.image.clone();
+                async move { image.ok_or_else(|| anyhow!("the album has no art")) }.boxed_local()
+            });
+            let lightbox = Lightbox::new(cx, show_lightbox.clone(), lightbox_source);
+
+            // related albums are loaded in the background, so that they don't hold up opening
+            // the page
+            let pool = cx.global::<Pool>().0.clone();
+            let related_album = album.clone();
+            let related_artist = artist_name.clone().map(SharedString::from);
+            let prefer_original = cx
+                .global::<SettingsGlobal>()
+                .model
+                .read(cx)
+                .interface
+                .prefer_original_date;
+            let weak_self = cx.weak_entity();
+
+            cx.spawn(async move |_, cx| {
+                let task = crate::RUNTIME.spawn(async move {
+                    load_related_albums(&pool, &related_album, related_artist, prefer_original)
+                        .await
+                });
+
+                match task.await {
+                    Ok(Ok(related)) => {
+                        weak_self
+                            .update(cx, |this: &mut Self, cx| {
+                                this.related = related;
+                                cx.notify();
+                            })
+                            .ok();
+                    }
+                    Ok(Err(err)) => warn!(?err, "Failed to load related albums: {err}"),
+                    Err(err) => warn!(?err, "Failed to load related albums: {err}"),
+                }
+            })
+            .detach();
+
+            ReleaseContent {
                 album,
                 artist_name,
                 tracks,
@@ -95,14 +233,46 @@ impl ReleaseView {
                 release_info,
                 img_path: SharedString::from(format!("!db://album/{album_id}/full")),
                 scroll_handle: ScrollHandle::new(),
+                // only the full-size album art is loaded through this cache
+                image_cache: HummingbirdImageCache::new("release", 1, cx),
+                show_add_to_playlist,
+                add_to_playlist,
+                show_fix_grouping,
+                fix_grouping,
+                credits,
+                credits_expanded: false,
+                related: Vec::new(),
+                related_image_cache: HummingbirdImageCache::new("release-related", 32, cx),
+                _album_progress: album_progress,
+                progress_summary,
+                show_lightbox,
+                lightbox,
             }
         })
     }
+
+    fn queue_entries(&self) -> Vec<QueueEntry> {
+        self.track_listing
+            .tracks()
+            .iter()
+            .map(|track| QueueEntry::new(track.location.clone(), Some(track.id), track.album_id))
+            .collect()
+    }
 }
 
-impl Render for ReleaseView {
+impl Render for ReleaseContent {
     fn render(&mut self, _: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
         let theme = cx.global::<Theme>();
+        let art_source = Some(self.album.art_source)
+            .filter(|source| self.album.image.is_some() && *source != ArtSource::Unknown);
+        let prefer_original = cx
+            .global::<SettingsGlobal>()
+            .model
+            .read(cx)
+            .interface
+            .prefer_original_date;
+        let release_date = self.album.display_date(prefer_original);
+        let other_date = self.album.other_date(prefer_original);
 
         let is_playing =
             cx.global::<PlaybackInfo>().playback_state.read(cx) == &PlaybackState::Playing;
@@ -121,7 +291,7 @@ impl Render for ReleaseView {
         let scroll_handle = self.scroll_handle.clone();
 
         div()
-            .image_cache(hummingbird_cache(("release", self.album.id as u64), 1))
+            .image_cache(self.image_cache.clone())
             .flex()
             .w_full()
             .max_h_full()
@@ -146,6 +316,7 @@ impl Render for ReleaseView {
                             .w_full()
                             .child(
                                 div()
+                                    .id("release-art")
                                     .rounded(px(4.0))
                                     .bg(theme.album_art_background)
                                     .shadow_sm()
@@ -153,6 +324,11 @@ impl Render for ReleaseView {
                                     .h(px(160.0))
                                     .flex_shrink_0()
                                     .overflow_hidden()
+                                    .when(self.album.image.is_some(), |this| {
+                                        this.cursor_pointer().on_click(cx.listener(
+                                            |this, _, _, cx| this.show_lightbox.write(cx, true),
+                                        ))
+                                    })
                                     .child(
                                         img(self.img_path.clone())
                                             .min_w(px(160.0))
@@ -192,6 +368,7 @@ impl Render for ReleaseView {
                                             .text_ellipsis()
                                             .child(self.album.title.clone()),
                                     )
+                                    .child(self.progress_summary.clone())
                                     .child(
                                         div()
                                             .gap(px(10.0))
@@ -205,22 +382,12 @@ impl Render for ReleaseView {
                                                     .intent(ButtonIntent::Primary)
                                                     .when(!current_track_in_album, |this| {
                                                         this.on_click(cx.listener(
-                                                            |this: &mut ReleaseView, _, _, cx| {
-                                                                let queue_items = this
-                                                                    .track_listing
-                                                                    .tracks()
-                                                                    .iter()
-                                                                    .map(|track| {
-                                                                        QueueItemData::new(
-                                                                            cx,
-                                                                            track.location.clone(),
-                                                                            Some(track.id),
-                                                                            track.album_id,
-                                                                        )
-                                                                    })
-                                                                    .collect();
-
-                                                                replace_queue(queue_items, cx)
+                                                            |this: &mut ReleaseContent, _, _, cx| {
+                                                                play_now(
+                                                                    cx,
+                                                                    this.queue_entries(),
+                                                                    None,
+                                                                )
                                                             },
                                                         ))
                                                     })
@@ -248,7 +415,7 @@ impl Render for ReleaseView {
                                                         if current_track_in_album && is_playing {
                                                             "Pause"
                                                         } else {
-                                                            "Play"
+                                                            PLAY_NOW
                                                         },
                                                     )),
                                             )
@@ -258,23 +425,8 @@ impl Render for ReleaseView {
                                                     .size(ButtonSize::Large)
                                                     .flex_none()
                                                     .on_click(cx.listener(
-                                                        |this: &mut ReleaseView, _, _, cx| {
-                                                            let queue_items = this
-                                                                .track_listing
-                                                                .tracks()
-                                                                .iter()
-                                                                .map(|track| {
-                                                                    QueueItemData::new(
-                                                                        cx,
-                                                                        track.location.clone(),
-                                                                        Some(track.id),
-                                                                        track.album_id,
-                                                                    )
-                                                                })
-                                                                .collect();
-
-                                                            cx.global::<PlaybackInterface>()
-                                                                .queue_list(queue_items);
+                                                        |this: &mut ReleaseContent, _, _, cx| {
+                                                            add_to_queue(cx, this.queue_entries());
                                                         },
                                                     ))
                                                     .child(
@@ -287,21 +439,7 @@ impl Render for ReleaseView {
                                                     .size(ButtonSize::Large)
                                                     .flex_none()
                                                     .on_click(cx.listener(
-                                                        |this: &mut ReleaseView, _, _, cx| {
-                                                            let queue_items = this
-                                                                .track_listing
-                                                                .tracks()
-                                                                .iter()
-                                                                .map(|track| {
-                                                                    QueueItemData::new(
-                                                                        cx,
-                                                                        track.location.clone(),
-                                                                        Some(track.id),
-                                                                        track.album_id,
-                                                                    )
-                                                                })
-                                                                .collect();
-
+                                                        |this: &mut ReleaseContent, _, _, cx| {
                                                             if !(*cx
                                                                 .global::<PlaybackInfo>()
                                                                 .shuffling
@@ -311,10 +449,41 @@ impl Render for ReleaseView {
                                                                     .toggle_shuffle();
                                                             }
 
-                                                            replace_queue(queue_items, cx)
+                                                            play_now(cx, this.queue_entries(), None)
                                                         },
                                                     ))
                                                     .child(icon(SHUFFLE).size(px(16.0)).my_auto()),
+                                            )
+                                            .child(
+                                                button()
+                                                    .id("release-add-to-playlist-button")
+                                                    .size(ButtonSize::Large)
+                                                    .flex_none()
+                                                    .on_click(cx.listener(
+                                                        |this: &mut ReleaseContent, _, _, cx| {
+                                                            this.show_add_to_playlist
+                                                                .write(cx, true);
+                                                        },
+                                                    ))
+                                                    .child(
+                                                        icon(PLAYLIST_ADD).size(px(16.0)).my_auto(),
+                                                    ),
+                                            )
+                                            .child(
+                                                button()
+                                                    .id("release-fix-grouping-button")
+                                                    .size(ButtonSize::Large)
+                                                    .flex_none()
+                                                    .on_click(cx.listener(
+                                                        |this: &mut ReleaseContent, _, _, cx| {
+                                                            this.show_fix_grouping.write(cx, true);
+                                                        },
+                                                    ))
+                                                    .child(
+                                                        icon(FOLDER_SEARCH)
+                                                            .size(px(16.0))
+                                                            .my_auto(),
+                                                    ),
                                             ),
                                     ),
                             ),
@@ -333,9 +502,10 @@ impl Render for ReleaseView {
                     })
                     .when(
                         self.release_info.is_some()
-                            || self.album.release_date.is_some()
-                            || self.album.release_year.is_some()
-                            || self.album.isrc.is_some(),
+                            || release_date.is_some()
+                            || self.album.isrc.is_some()
+                            || self.album.musicbrainz_id().is_some()
+                            || art_source.is_some(),
                         |this| {
                             this.child(
                                 div()
@@ -350,26 +520,75 @@ impl Render for ReleaseView {
                                     .when_some(self.release_info.clone(), |this, release_info| {
                                         this.child(div().child(release_info))
                                     })
-                                    .when_some(self.album.release_date, |this, date| {
+                                    .when_some(release_date, |this, date| {
                                         this.child(div().child(format!(
                                             "Released {}",
-                                            date.format("%B %-e, %Y")
+                                            format::album_date(&date)
                                         )))
                                     })
-                                    .when_some(self.album.release_year, |this, year| {
-                                        this.child(div().child(format!("Released {year}")))
+                                    // the reissue (or the original) is only mentioned if it came
+                                    // out in a different year
+                                    .when_some(other_date, |this, date| {
+                                        let label = if prefer_original {
+                                            "Reissued"
+                                        } else {
+                                            "Originally released"
+                                        };
+
+                                        this.child(div().child(format!(
+                                            "{label} {}",
+                                            format::album_date(&date)
+                                        )))
                                     })
                                     .when_some(self.album.isrc.as_ref(), |this, isrc| {
                                         this.child(div().child(isrc.clone()))
+                                    })
+                                    .when_some(art_source, |this, source| {
+                                        this.child(
+                                            div().child(format!("Cover: {}", source.describe())),
+                                        )
+                                    })
+                                    .when_some(self.album.musicbrainz_id(), |this, mbid| {
+                                        this.child(musicbrainz_id(
+                                            "release-mbid",
+                                            MusicBrainzEntity::Release,
+                                            mbid,
+                                            cx,
+                                        ))
                                     }),
                             )
                         },
-                    ),
+                    )
+                    .when(!self.credits.is_empty(), |this| {
+                        this.child(
+                            div().mx(px(18.0)).pb(px(12.0)).child(
+                                credits_section("release-credits-toggle", self.credits.clone())
+                                    .expanded(self.credits_expanded)
+                                    .on_toggle(cx.listener(|this: &mut Self, _, _, cx| {
+                                        this.credits_expanded = !this.credits_expanded;
+                                        cx.notify();
+                                    })),
+                            ),
+                        )
+                    })
+                    .when(!self.related.is_empty(), |this| {
+                        this.child(
+                            div()
+                                .image_cache(self.related_image_cache.clone())
+                                .pt(px(12.0))
+                                .children(self.related.iter().enumerate().map(|(i, section)| {
+                                    album_card_row(("related-albums", i), section.clone())
+                                })),
+                        )
+                    }),
             )
             .child(floating_scrollbar(
                 "release_scrollbar",
                 scroll_handle,
                 RightPad::Pad,
             ))
+            .child(self.add_to_playlist.clone())
+            .child(self.fix_grouping.clone())
+            .child(self.lightbox.clone())
     }
 }
@@ -52,7 +52,7 @@ impl Sidebar {
 impl Render for Sidebar {
     fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
         let theme = cx.global::<Theme>();
-        let stats_minutes = self.track_stats.total_duration / 60;
+        let stats_minutes = self.track_stats.total_duration / 60_000;
         let stats_hours = stats_minutes / 60;
         let current_view = self.nav_model.read(cx);
         let sidebar_width = cx.global::<Models>().sidebar_width.clone();
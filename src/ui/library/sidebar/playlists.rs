@@ -1,8 +1,9 @@
 use std::{collections::VecDeque, sync::Arc};
 
 use gpui::{
-    App, AppContext, Context, Entity, FontWeight, InteractiveElement, ParentElement, Render,
-    ScrollHandle, StatefulInteractiveElement, Styled, Window, div, prelude::FluentBuilder, px,
+    App, AppContext, ClickEvent, Context, Entity, FocusHandle, FontWeight, InteractiveElement,
+    KeyDownEvent, ParentElement, Render, ScrollHandle, SharedString, StatefulInteractiveElement,
+    Styled, Subscription, Window, div, prelude::FluentBuilder, px,
 };
 use tracing::error;
 
@@ -14,21 +15,35 @@ use crate::{
     ui::{
         components::{
             context::context,
-            icons::{CROSS, PLAYLIST, STAR},
+            icons::{CROSS, PENCIL, PLAYLIST, STAR},
+            input::{EnrichedInputAction, TextInput},
             menu::{menu, menu_item},
             scrollbar::{RightPad, floating_scrollbar},
             sidebar::sidebar_item,
         },
-        library::ViewSwitchMessage,
+        library::{ViewSwitchMessage, playlist_view::RenamePlaylist},
         models::{Models, PlaylistEvent},
         theme::Theme,
+        toasts::show_toast,
     },
 };
 
+/// A playlist whose name is being edited in place of its label.
+struct Renaming {
+    playlist_id: i64,
+    original: SharedString,
+    input: Entity<TextInput>,
+    focus_handle: FocusHandle,
+    /// Commits the new name when the input loses focus. Dropped when renaming ends, so that
+    /// cancelling doesn't commit.
+    _blur: Subscription,
+}
+
 pub struct PlaylistList {
     playlists: Arc<Vec<PlaylistWithCount>>,
     nav_model: Entity<VecDeque<ViewSwitchMessage>>,
     scroll_handle: ScrollHandle,
+    renaming: Option<Renaming>,
 }
 
 impl PlaylistList {
@@ -53,13 +68,126 @@ impl PlaylistList {
             })
             .detach();
 
+            // F2 renames the playlist that's open
+            let weak_self = cx.weak_entity();
+            App::on_action(cx, move |_: &RenamePlaylist, cx| {
+                let Some(this) = weak_self.upgrade() else {
+                    return;
+                };
+
+                let current = this.read(cx).nav_model.read(cx).back().copied();
+                if let Some(ViewSwitchMessage::Playlist(id)) = current
+                    && let Some(window) = cx.active_window()
+                {
+                    window
+                        .update(cx, |_, window, cx| {
+                            this.update(cx, |this, cx| this.start_rename(id, window, cx));
+                        })
+                        .ok();
+                }
+            });
+
             Self {
                 playlists: playlists.clone(),
                 nav_model,
                 scroll_handle: ScrollHandle::new(),
+                renaming: None,
             }
         })
     }
+
+    /// Replaces the playlist's label with a text input, so that it can be renamed. System
+    /// playlists can't be renamed.
+    fn start_rename(&mut self, playlist_id: i64, window: &mut Window, cx: &mut Context<Self>) {
+        let Some(original) = self
+            .playlists
+            .iter()
+            .find(|playlist| {
+                playlist.id == playlist_id && playlist.playlist_type == PlaylistType::User
+            })
+            .map(|playlist| playlist.name.0.clone())
+        else {
+            return;
+        };
+
+        // only one playlist can be renamed at a time
+        self.commit_rename(cx);
+
+        let focus_handle = cx.focus_handle();
+        let weak_self = cx.weak_entity();
+
+        // the input is still being updated when Enter is handled, so commit afterwards
+        let handler = move |action, _: &mut Window, cx: &mut App| {
+            if let EnrichedInputAction::Accept(_) = action {
+                let weak_self = weak_self.clone();
+                cx.defer(move |cx| {
+                    weak_self
+                        .update(cx, |this: &mut Self, cx| this.commit_rename(cx))
+                        .ok();
+                });
+            }
+        };
+
+        let input = TextInput::new(
+            cx,
+            focus_handle.clone(),
+            Some(original.clone()),
+            None,
+            Some(Box::new(handler)),
+        );
+        input.update(cx, |input, cx| input.select_all_content(cx));
+
+        let blur = cx.on_blur(&focus_handle, window, |this, _, cx| this.commit_rename(cx));
+        focus_handle.focus(window, cx);
+
+        self.renaming = Some(Renaming {
+            playlist_id,
+            original,
+            input,
+            focus_handle,
+            _blur: blur,
+        });
+        cx.notify();
+    }
+
+    fn cancel_rename(&mut self, cx: &mut Context<Self>) {
+        self.renaming = None;
+        cx.notify();
+    }
+
+    fn commit_rename(&mut self, cx: &mut Context<Self>) {
+        let Some(renaming) = self.renaming.take() else {
+            return;
+        };
+        cx.notify();
+
+        let name = renaming.input.read(cx).content.trim().to_string();
+        if name.is_empty() || name == renaming.original.as_ref() {
+            return;
+        }
+
+        let playlist_id = renaming.playlist_id;
+        match cx.rename_playlist(playlist_id, &name) {
+            Ok(new_name) => {
+                if new_name != name {
+                    let message = format!(
+                        "There's already a playlist called \"{name}\", so it was named \
+                         \"{new_name}\"."
+                    );
+                    show_toast(cx, message);
+                }
+
+                let playlist_tracker = cx.global::<Models>().playlist_tracker.clone();
+                playlist_tracker.update(cx, |_, cx| {
+                    cx.emit(PlaylistEvent::PlaylistRenamed(playlist_id));
+                });
+            }
+            Err(err) => {
+                error!(?err, "Failed to rename playlist {playlist_id}: {err}");
+                show_toast(cx, "Couldn't rename the playlist.");
+            }
+        }
+    }
 }
 
 impl Render for PlaylistList {
@@ -75,9 +203,14 @@ impl Render for PlaylistList {
             .track_scroll(&scroll_handle);
 
         let current_view = self.nav_model.read(cx);
+        let weak_self = cx.weak_entity();
 
         for playlist in &*self.playlists {
             let pl_id = playlist.id;
+            let renaming = self
+                .renaming
+                .as_ref()
+                .filter(|renaming| renaming.playlist_id == pl_id);
 
             let item = sidebar_item(("main-sidebar-pl", playlist.id as u64))
                 .icon(if playlist.playlist_type == PlaylistType::System {
@@ -85,7 +218,25 @@ impl Render for PlaylistList {
                 } else {
                     PLAYLIST
                 })
-                .child(playlist.name.clone())
+                .when_none(&renaming, |this| this.child(playlist.name.clone()))
+                .when_some(renaming, |this, renaming| {
+                    this.child(
+                        div()
+                            .on_key_down(cx.listener(|this, ev: &KeyDownEvent, _, cx| {
+                                if ev.keystroke.key == "escape" {
+                                    this.cancel_rename(cx);
+                                    cx.stop_propagation();
+                                }
+                            }))
+                            .border_1()
+                            .border_color(theme.textbox_border)
+                            .rounded(px(3.0))
+                            .px(px(3.0))
+                            .bg(theme.textbox_background)
+                            .font_weight(FontWeight::NORMAL)
+                            .child(renaming.input.clone()),
+                    )
+                })
                 .child(
                     div()
                         .font_weight(FontWeight::NORMAL)
@@ -98,7 +249,21 @@ impl Render for PlaylistList {
                             format!("{} songs", playlist.track_count)
                         }),
                 )
-                .on_click(cx.listener(move |this, _, _, cx| {
+                .on_click(cx.listener(move |this, ev: &ClickEvent, window, cx| {
+                    // clicks in the name input shouldn't do anything else
+                    if this
+                        .renaming
+                        .as_ref()
+                        .is_some_and(|renaming| renaming.playlist_id == pl_id)
+                    {
+                        return;
+                    }
+
+                    if ev.click_count() == 2 {
+                        this.start_rename(pl_id, window, cx);
+                        return;
+                    }
+
                     this.nav_model.update(cx, move |_, cx| {
                         cx.emit(ViewSwitchMessage::Playlist(pl_id));
                     });
@@ -111,37 +276,54 @@ impl Render for PlaylistList {
             if playlist.playlist_type != PlaylistType::System {
                 main = main.child(
                     context(("playlist", pl_id as usize)).with(item).child(
-                        div()
-                            .bg(theme.elevated_background)
-                            .child(menu().item(menu_item(
-                                "delete_playlist",
-                                Some(CROSS),
-                                "Delete playlist",
-                                move |_, _, cx| {
-                                    if let Err(err) = cx.delete_playlist(pl_id) {
-                                        error!("Failed to delete playlist: {}", err);
-                                    }
-
-                                    let playlist_tracker =
-                                        cx.global::<Models>().playlist_tracker.clone();
-
-                                    playlist_tracker.update(cx, |_, cx| {
-                                        cx.emit(PlaylistEvent::PlaylistDeleted(pl_id))
-                                    });
-
-                                    let switcher_model =
-                                        cx.global::<Models>().switcher_model.clone();
-
-                                    switcher_model.update(cx, |view_switch_messages, cx| {
-                                        view_switch_messages
-                                            .retain(|v| *v != ViewSwitchMessage::Playlist(pl_id));
-
-                                        cx.emit(ViewSwitchMessage::Refresh);
-
-                                        cx.notify();
-                                    })
-                                },
-                            ))),
+                        div().bg(theme.elevated_background).child(
+                            menu()
+                                .item(menu_item(
+                                    "rename_playlist",
+                                    Some(PENCIL),
+                                    "Rename playlist",
+                                    {
+                                        let weak_self = weak_self.clone();
+                                        move |_, window, cx| {
+                                            weak_self
+                                                .update(cx, |this, cx| {
+                                                    this.start_rename(pl_id, window, cx)
+                                                })
+                                                .ok();
+                                        }
+                                    },
+                                ))
+                                .item(menu_item(
+                                    "delete_playlist",
+                                    Some(CROSS),
+                                    "Delete playlist",
+                                    move |_, _, cx| {
+                                        if let Err(err) = cx.delete_playlist(pl_id) {
+                                            error!("Failed to delete playlist: {}", err);
+                                        }
+
+                                        let playlist_tracker =
+                                            cx.global::<Models>().playlist_tracker.clone();
+
+                                        playlist_tracker.update(cx, |_, cx| {
+                                            cx.emit(PlaylistEvent::PlaylistDeleted(pl_id))
+                                        });
+
+                                        let switcher_model =
+                                            cx.global::<Models>().switcher_model.clone();
+
+                                        switcher_model.update(cx, |view_switch_messages, cx| {
+                                            view_switch_messages.retain(|v| {
+                                                *v != ViewSwitchMessage::Playlist(pl_id)
+                                            });
+
+                                            cx.emit(ViewSwitchMessage::Refresh);
+
+                                            cx.notify();
+                                        })
+                                    },
+                                )),
+                        ),
                     ),
                 );
             } else {
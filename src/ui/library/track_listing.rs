@@ -1,3 +1,4 @@
+pub mod header;
 pub mod track_item;
 
 use std::sync::Arc;
@@ -6,7 +7,10 @@ use gpui::{App, Entity, IntoElement, ListAlignment, ListState, Pixels, Window};
 
 use crate::{
     library::types::{DBString, Track},
-    ui::library::track_listing::track_item::TrackItemLeftField,
+    ui::library::{
+        album_progress::{PlayingTrack, TrackProgressBar},
+        track_listing::track_item::TrackItemLeftField,
+    },
 };
 use track_item::TrackItem;
 
@@ -64,6 +68,14 @@ impl TrackListing {
         &self.original_tracks
     }
 
+    /// Shows a progress bar under whichever track is playing.
+    pub fn show_progress(&self, cx: &mut App, playing: &Entity<Option<PlayingTrack>>) {
+        for (index, item) in self.tracks.iter().enumerate() {
+            let bar = TrackProgressBar::new(cx, index, playing.clone());
+            item.update(cx, |item, _| item.progress_bar = Some(bar));
+        }
+    }
+
     pub fn track_list_state(&self) -> &ListState {
         &self.track_list_state
     }
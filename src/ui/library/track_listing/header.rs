@@ -0,0 +1,198 @@
+use std::rc::Rc;
+
+use gpui::{prelude::FluentBuilder, *};
+
+use crate::ui::{
+    components::icons::{CHEVRON_DOWN, CHEVRON_UP, icon},
+    theme::Theme,
+    util::ui_px,
+};
+
+/// Horizontal padding of the header and of each row, in pixels.
+pub const TRACK_LIST_PADDING_X: f32 = 18.0;
+
+/// Space between columns, in pixels. It's included in the width of every column after the title.
+const COLUMN_GAP: f32 = 12.0;
+
+/// The narrowest the title column gets before the artist and album columns start shrinking.
+const MIN_TITLE_WIDTH: f32 = 120.0;
+
+/// Height of the header, in pixels, before the interface scale is applied.
+const HEADER_HEIGHT: f32 = 32.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TrackListColumn {
+    /// The position in the list. In playlists this column shows the album art.
+    Number,
+    Title,
+    Artist,
+    Album,
+    /// When the track was added to the playlist.
+    Added,
+    Bpm,
+    Duration,
+}
+
+impl TrackListColumn {
+    pub fn label(self) -> &'static str {
+        match self {
+            TrackListColumn::Number => "#",
+            TrackListColumn::Title => "Title",
+            TrackListColumn::Artist => "Artist",
+            TrackListColumn::Album => "Album",
+            TrackListColumn::Added => "Added",
+            TrackListColumn::Bpm => "BPM",
+            TrackListColumn::Duration => "Length",
+        }
+    }
+
+    /// Returns the column's width in pixels, or None for the title, which takes up the remaining
+    /// space. The artist and album columns get narrower when there isn't enough room.
+    pub fn width(self) -> Option<f32> {
+        match self {
+            // the album art plus its margin
+            TrackListColumn::Number => Some(34.0),
+            TrackListColumn::Title => None,
+            TrackListColumn::Artist => Some(180.0),
+            TrackListColumn::Album => Some(180.0),
+            TrackListColumn::Added => Some(96.0),
+            TrackListColumn::Bpm => Some(48.0),
+            TrackListColumn::Duration => Some(60.0),
+        }
+    }
+
+    fn is_numeric(self) -> bool {
+        matches!(
+            self,
+            TrackListColumn::Added | TrackListColumn::Bpm | TrackListColumn::Duration
+        )
+    }
+}
+
+/// Lays out one cell of a track list column. Used by both the header and the rows, so that they
+/// always line up.
+pub fn column_cell(column: TrackListColumn) -> Div {
+    let cell = div().flex().flex_row().my_auto().overflow_x_hidden();
+
+    let cell = match (column, column.width()) {
+        (TrackListColumn::Artist | TrackListColumn::Album, Some(width)) => {
+            cell.w(px(width)).flex_shrink().min_w_0()
+        }
+        (_, Some(width)) => cell.w(px(width)).flex_shrink_0(),
+        (_, None) => cell.flex_1().min_w(px(MIN_TITLE_WIDTH)),
+    };
+
+    cell.when(
+        !matches!(column, TrackListColumn::Number | TrackListColumn::Title),
+        |this| this.pl(px(COLUMN_GAP)),
+    )
+    .when(column.is_numeric(), |this| this.justify_end())
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TrackListSort {
+    pub column: TrackListColumn,
+    pub ascending: bool,
+}
+
+impl Default for TrackListSort {
+    /// The list's own order.
+    fn default() -> Self {
+        TrackListSort {
+            column: TrackListColumn::Number,
+            ascending: true,
+        }
+    }
+}
+
+impl TrackListSort {
+    /// Returns the sort to use after the given column is chosen: ascending at first, then
+    /// switching direction each time it's chosen again.
+    pub fn toggle(self, column: TrackListColumn) -> TrackListSort {
+        TrackListSort {
+            column,
+            ascending: self.column != column || !self.ascending,
+        }
+    }
+}
+
+type OnSort = Rc<dyn Fn(TrackListColumn, &mut Window, &mut App)>;
+
+/// The header above a list of tracks. Clicking a column sorts by it, and clicking it again
+/// reverses the order.
+#[derive(IntoElement)]
+pub struct TrackListHeader {
+    id: ElementId,
+    columns: Vec<TrackListColumn>,
+    sort: TrackListSort,
+    on_sort: Option<OnSort>,
+}
+
+pub fn track_list_header(
+    id: impl Into<ElementId>,
+    columns: Vec<TrackListColumn>,
+    sort: TrackListSort,
+) -> TrackListHeader {
+    TrackListHeader {
+        id: id.into(),
+        columns,
+        sort,
+        on_sort: None,
+    }
+}
+
+impl TrackListHeader {
+    pub fn on_sort(
+        mut self,
+        on_sort: impl Fn(TrackListColumn, &mut Window, &mut App) + 'static,
+    ) -> Self {
+        self.on_sort = Some(Rc::new(on_sort));
+        self
+    }
+}
+
+impl RenderOnce for TrackListHeader {
+    fn render(self, _: &mut Window, cx: &mut App) -> impl IntoElement {
+        let theme = cx.global::<Theme>();
+
+        div()
+            .id(self.id)
+            .flex()
+            .flex_row()
+            .flex_shrink_0()
+            .w_full()
+            .h(ui_px(HEADER_HEIGHT, cx))
+            .px(px(TRACK_LIST_PADDING_X))
+            .border_b_1()
+            .border_color(theme.border_color)
+            .text_sm()
+            .font_weight(FontWeight::BOLD)
+            .text_color(theme.text_secondary)
+            .children(self.columns.into_iter().enumerate().map(|(idx, column)| {
+                let on_sort = self.on_sort.clone();
+                let sorted = (self.sort.column == column).then_some(self.sort);
+
+                column_cell(column)
+                    .id(("track-list-header-column", idx))
+                    .cursor_pointer()
+                    .hover(|this| this.text_color(theme.text))
+                    .child(column.label())
+                    .when_some(sorted, |this, sort| {
+                        this.child(
+                            icon(if sort.ascending {
+                                CHEVRON_UP
+                            } else {
+                                CHEVRON_DOWN
+                            })
+                            .size(px(14.0))
+                            .ml(px(4.0))
+                            .my_auto()
+                            .flex_shrink_0(),
+                        )
+                    })
+                    .when_some(on_sort, |this, on_sort| {
+                        this.on_click(move |_, window, cx| on_sort(column, window, cx))
+                    })
+            }))
+    }
+}
@@ -1,34 +1,51 @@
+use chrono::{DateTime, Local, Utc};
 use gpui::prelude::{FluentBuilder, *};
 use gpui::{App, Entity, FontWeight, IntoElement, SharedString, Window, div, img, px};
+use rustc_hash::FxHashSet;
 
 use crate::ui::components::drag_drop::{DragPreview, TrackDragData};
 use crate::ui::components::icons::{
-    PLAY, PLAYLIST_ADD, PLAYLIST_REMOVE, PLUS, STAR, STAR_FILLED, icon,
+    CHEVRON_DOWN, CHEVRON_UP, PLAY, PLAYLIST_ADD, PLAYLIST_REMOVE, PLUS, STAR, STAR_FILLED, VOLUME,
+    icon,
 };
 use crate::ui::components::menu::menu_separator;
 use crate::ui::library::add_to_playlist::AddToPlaylist;
+use crate::ui::library::album_progress::TrackProgressBar;
+use crate::ui::library::edit_bpm::EditBpm;
+use crate::ui::library::edit_gain::EditGain;
+use crate::ui::library::move_to_position::MoveToPosition;
+use crate::ui::library::track_properties::TrackProperties;
 use crate::ui::models::PlaylistEvent;
+use crate::ui::util::ui_px;
 use crate::{
-    library::{db::LibraryAccess, types::Track},
-    playback::{
-        interface::{PlaybackInterface, replace_queue},
-        queue::QueueItemData,
+    library::{
+        db::{LibraryAccess, LibraryAccessAsync},
+        playlist::{get_liked_playlist_id, move_playlist_item_to_index},
+        types::Track,
     },
     ui::{
         components::{
             context::context,
             menu::{menu, menu_item},
         },
+        external_drag, format,
+        large_queue::{
+            ADD_TO_QUEUE, PLAY_NEXT, PLAY_NOW, QueueEntry, add_to_queue, play_next, play_now,
+        },
         models::{Models, PlaybackInfo},
         theme::Theme,
     },
 };
 
 use super::ArtistNameVisibility;
+use super::header::{TrackListColumn, column_cell};
 
+#[derive(Clone, Copy)]
 pub struct TrackPlaylistInfo {
     pub id: i64,
     pub item_id: i64,
+    /// The BPM range the playlist is currently filtered to, if any.
+    pub bpm_range: Option<(i64, i64)>,
 }
 
 pub struct TrackItem {
@@ -37,12 +54,26 @@ pub struct TrackItem {
     pub artist_name_visibility: ArtistNameVisibility,
     pub is_liked: Option<i64>,
     pub hover_group: SharedString,
+    /// Whether or not the track's BPM should be displayed next to its duration.
+    pub show_bpm: bool,
+    /// When the track was added to the playlist, shown in its own column if set.
+    pub added: Option<DateTime<Utc>>,
     left_field: TrackItemLeftField,
     album_art: Option<SharedString>,
     pl_info: Option<TrackPlaylistInfo>,
     add_to: Entity<AddToPlaylist>,
     show_add_to: Entity<bool>,
+    edit_bpm: Option<Entity<EditBpm>>,
+    show_edit_bpm: Entity<bool>,
+    edit_gain: Option<Entity<EditGain>>,
+    show_edit_gain: Entity<bool>,
+    properties: Option<Entity<TrackProperties>>,
+    show_properties: Entity<bool>,
+    move_to: Option<Entity<MoveToPosition>>,
+    show_move_to: Entity<bool>,
     vinyl_numbering: bool,
+    /// Shown under the track while it's playing, on album pages.
+    pub progress_bar: Option<Entity<TrackProgressBar>>,
 }
 
 #[derive(Eq, PartialEq)]
@@ -64,35 +95,154 @@ impl TrackItem {
         cx.new(|cx| {
             let show_add_to = cx.new(|_| false);
             let add_to = AddToPlaylist::new(cx, show_add_to.clone(), track.id);
+            let show_edit_bpm = cx.new(|_| false);
+            let show_edit_gain = cx.new(|_| false);
+            let show_properties = cx.new(|_| false);
+            let show_move_to = cx.new(|_| false);
             let track_id = track.id;
 
-            let playlist_tracker = cx.global::<Models>().playlist_tracker.clone();
+            cx.observe(&show_edit_bpm, move |this: &mut Self, show, cx| {
+                if !*show.read(cx)
+                    && let Ok(track) = cx.get_track_by_id(track_id)
+                {
+                    this.track.bpm = track.bpm;
+                    this.track.bpm_estimated = track.bpm_estimated;
+
+                    // the track may have left the range the playlist is filtered to
+                    if let Some(TrackPlaylistInfo {
+                        id,
+                        bpm_range: Some(_),
+                        ..
+                    }) = this.pl_info
+                    {
+                        let playlist_tracker = cx.global::<Models>().playlist_tracker.clone();
+                        playlist_tracker.update(cx, |_, cx| {
+                            cx.emit(PlaylistEvent::PlaylistUpdated(id));
+                        });
+                    }
+
+                    cx.notify();
+                }
+            })
+            .detach();
 
-            cx.subscribe(&playlist_tracker, move |this: &mut Self, _, ev, cx| {
-                if PlaylistEvent::PlaylistUpdated(1) == *ev {
-                    this.is_liked = cx.playlist_has_track(1, track_id).unwrap_or_default();
+            cx.observe(&show_edit_gain, move |this: &mut Self, show, cx| {
+                if !*show.read(cx)
+                    && let Ok(track) = cx.get_track_by_id(track_id)
+                {
+                    this.track.gain_offset = track.gain_offset;
                     cx.notify();
                 }
             })
             .detach();
 
+            let playlist_tracker = cx.global::<Models>().playlist_tracker.clone();
+
+            cx.subscribe(&playlist_tracker, move |_: &mut Self, _, ev, cx| {
+                if PlaylistEvent::PlaylistUpdated(get_liked_playlist_id(cx)) == *ev {
+                    Self::load_liked(track_id, cx);
+                }
+            })
+            .detach();
+
+            Self::load_liked(track_id, cx);
+
             Self {
                 hover_group: format!("track-{}", track.id).into(),
-                is_liked: cx.playlist_has_track(1, track.id).unwrap_or_default(),
+                is_liked: None,
                 album_art: track
                     .album_id
                     .map(|v| format!("!db://album/{v}/thumb").into()),
                 add_to,
                 show_add_to,
+                edit_bpm: None,
+                show_edit_bpm,
+                edit_gain: None,
+                show_edit_gain,
+                properties: None,
+                show_properties,
+                move_to: None,
+                show_move_to,
+                show_bpm: false,
+                added: None,
                 track,
                 is_start,
                 artist_name_visibility: anv,
                 left_field,
                 pl_info,
                 vinyl_numbering,
+                progress_bar: None,
             }
         })
     }
+
+    /// Checks whether the track is in the liked songs playlist in the background, and updates
+    /// the star once it's known.
+    fn load_liked(track_id: i64, cx: &mut Context<Self>) {
+        let has_track = cx.playlist_has_track_async(get_liked_playlist_id(cx), track_id);
+
+        cx.spawn(async move |this, cx| {
+            let is_liked = has_track.await.unwrap_or_default();
+
+            this.update(cx, |this: &mut Self, cx| {
+                this.is_liked = is_liked;
+                cx.notify();
+            })
+            .ok();
+        })
+        .detach();
+    }
+
+    fn render_like_button(&self, cx: &Context<Self>) -> impl IntoElement {
+        let theme = cx.global::<Theme>();
+        let track_id = self.track.id;
+
+        div()
+            .id("like")
+            .mr(px(-4.0))
+            .ml_auto()
+            .my_auto()
+            .rounded_sm()
+            .p(px(4.0))
+            .child(
+                icon(if self.is_liked.is_some() {
+                    STAR_FILLED
+                } else {
+                    STAR
+                })
+                .size(px(14.0))
+                .text_color(theme.text_secondary),
+            )
+            .invisible()
+            .group(self.hover_group.clone())
+            .group_hover(self.hover_group.clone(), |this| this.visible())
+            .hover(|this| this.bg(theme.button_secondary_hover))
+            .active(|this| this.bg(theme.button_secondary_active))
+            .on_click(cx.listener(move |this, _, _, cx| {
+                cx.stop_propagation();
+
+                let liked_playlist_id = get_liked_playlist_id(cx);
+
+                if let Some(id) = this.is_liked {
+                    cx.remove_playlist_item(id).expect("could not unlike song");
+
+                    this.is_liked = None;
+                } else {
+                    this.is_liked = Some(
+                        cx.add_playlist_item(liked_playlist_id, track_id)
+                            .expect("could not like song"),
+                    );
+                }
+
+                let playlist_tracker = cx.global::<Models>().playlist_tracker.clone();
+
+                playlist_tracker.update(cx, |_, cx| {
+                    cx.emit(PlaylistEvent::PlaylistUpdated(liked_playlist_id));
+                });
+
+                cx.notify();
+            }))
+    }
 }
 
 impl Render for TrackItem {
@@ -114,6 +264,13 @@ impl Render for TrackItem {
         let track = self.track.clone();
 
         let show_clone = self.show_add_to.clone();
+        let show_edit_bpm = self.show_edit_bpm.clone();
+        let show_edit_gain = self.show_edit_gain.clone();
+        let show_properties = self.show_properties.clone();
+        let weak_self = cx.weak_entity();
+        let weak_edit_gain = cx.weak_entity();
+        let weak_properties = cx.weak_entity();
+        let weak_move_to = cx.weak_entity();
 
         context(("context", self.track.id as usize))
             .with(
@@ -124,10 +281,18 @@ impl Render for TrackItem {
                     .id(self.track.id as usize)
                     .on_click({
                         let track = self.track.clone();
-                        let plid = self.pl_info.as_ref().map(|pl| pl.id);
-                        move |_, _, cx| play_from_track(cx, &track, plid)
+                        let pl_info = self.pl_info;
+                        move |_, _, cx| play_from_track(cx, &track, pl_info)
                     })
                     .child(self.add_to.clone())
+                    .when_some(self.edit_bpm.clone(), |this, edit_bpm| this.child(edit_bpm))
+                    .when_some(self.edit_gain.clone(), |this, edit_gain| {
+                        this.child(edit_gain)
+                    })
+                    .when_some(self.properties.clone(), |this, properties| {
+                        this.child(properties)
+                    })
+                    .when_some(self.move_to.clone(), |this, move_to| this.child(move_to))
                     .when(self.is_start, |this| {
                         this.child(
                             div()
@@ -154,8 +319,9 @@ impl Render for TrackItem {
                         div()
                             .flex()
                             .flex_row()
+                            .relative()
                             .border_b_1()
-                            .h(px(39.0))
+                            .h(ui_px(39.0, cx))
                             .id(("track", self.track.id as u64))
                             .w_full()
                             .border_color(theme.border_color)
@@ -175,7 +341,8 @@ impl Render for TrackItem {
                                         track_location_for_drag,
                                         track_title_for_drag.clone(),
                                     ),
-                                    move |_, _, _, cx| {
+                                    move |track, _, _, cx| {
+                                        external_drag::offer_files(vec![track.path.clone()]);
                                         DragPreview::new(cx, track_title_for_drag.clone())
                                     },
                                 )
@@ -188,155 +355,216 @@ impl Render for TrackItem {
                                 })
                             })
                             .max_w_full()
-                            .when(self.left_field == TrackItemLeftField::TrackNum, |this| {
-                                this.child(div().w(px(62.0)).flex_shrink_0().child(format!(
-                                    "{}",
-                                    self.track.track_number.unwrap_or_default()
-                                )))
-                            })
-                            .when(self.left_field == TrackItemLeftField::Art, |this| {
+                            .when_some(self.progress_bar.clone(), |this, bar| this.child(bar))
+                            // playlists lay the track out in columns, under the list's header
+                            .when(self.pl_info.is_some(), |this| {
                                 this.child(
+                                    column_cell(TrackListColumn::Number).child(
+                                        div()
+                                            .w(px(22.0))
+                                            .h(px(22.0))
+                                            .rounded(px(3.0))
+                                            .bg(theme.album_art_background)
+                                            .when_some(self.album_art.clone(), |this, art| {
+                                                this.child(
+                                                    img(art)
+                                                        .w(px(22.0))
+                                                        .h(px(22.0))
+                                                        .rounded(px(3.0)),
+                                                )
+                                            }),
+                                    ),
+                                )
+                                .child(
+                                    column_cell(TrackListColumn::Title)
+                                        .child(
+                                            div()
+                                                .font_weight(FontWeight::SEMIBOLD)
+                                                .overflow_x_hidden()
+                                                .text_ellipsis()
+                                                .child(self.track.title.clone()),
+                                        )
+                                        .child(self.render_like_button(cx))
+                                        .when_some(
+                                            self.track.gain_offset_display(),
+                                            |this, gain| {
+                                                this.child(
+                                                    div()
+                                                        .ml(px(8.0))
+                                                        .my_auto()
+                                                        .flex_shrink_0()
+                                                        .text_xs()
+                                                        .text_color(theme.text_secondary)
+                                                        .child(gain),
+                                                )
+                                            },
+                                        ),
+                                )
+                                .child(
+                                    column_cell(TrackListColumn::Artist).child(
+                                        div()
+                                            .font_weight(FontWeight::LIGHT)
+                                            .text_sm()
+                                            .text_color(theme.text_secondary)
+                                            .text_ellipsis()
+                                            .overflow_x_hidden()
+                                            .when_some(
+                                                self.track.artist_names.clone(),
+                                                |this, v| this.child(v.0),
+                                            ),
+                                    ),
+                                )
+                                .child(
+                                    column_cell(TrackListColumn::Album).child(
+                                        div()
+                                            .font_weight(FontWeight::LIGHT)
+                                            .text_sm()
+                                            .text_color(theme.text_secondary)
+                                            .text_ellipsis()
+                                            .overflow_x_hidden()
+                                            .when_some(
+                                                self.track.album_title.clone(),
+                                                |this, v| this.child(v.0),
+                                            ),
+                                    ),
+                                )
+                                .when_some(self.added, |this, added| {
+                                    this.child(
+                                        column_cell(TrackListColumn::Added)
+                                            .text_sm()
+                                            .text_color(theme.text_secondary)
+                                            .child(format::relative_time(added, Local::now())),
+                                    )
+                                })
+                                .when(self.show_bpm, |this| {
+                                    this.child(
+                                        column_cell(TrackListColumn::Bpm)
+                                            .text_color(theme.text_secondary)
+                                            .when_some(self.track.bpm_display(), |this, bpm| {
+                                                this.child(bpm)
+                                            }),
+                                    )
+                                })
+                                .child(
+                                    column_cell(TrackListColumn::Duration)
+                                        .child(self.track.duration_display()),
+                                )
+                            })
+                            .when(self.pl_info.is_none(), |this| {
+                                this.when(self.left_field == TrackItemLeftField::TrackNum, |this| {
+                                    this.child(div().w(px(62.0)).flex_shrink_0().child(format!(
+                                        "{}",
+                                        self.track.track_number.unwrap_or_default()
+                                    )))
+                                })
+                                .when(self.left_field == TrackItemLeftField::Art, |this| {
+                                    this.child(
+                                        div()
+                                            .w(px(22.0))
+                                            .h(px(22.0))
+                                            .mr(px(12.0))
+                                            .my_auto()
+                                            .rounded(px(3.0))
+                                            .bg(theme.album_art_background)
+                                            .when_some(self.album_art.clone(), |this, art| {
+                                                this.child(
+                                                    img(art)
+                                                        .w(px(22.0))
+                                                        .h(px(22.0))
+                                                        .rounded(px(3.0)),
+                                                )
+                                            }),
+                                    )
+                                })
+                                .child(
                                     div()
-                                        .w(px(22.0))
-                                        .h(px(22.0))
-                                        .mr(px(12.0))
+                                        .font_weight(FontWeight::SEMIBOLD)
+                                        .overflow_x_hidden()
+                                        .text_ellipsis()
+                                        .child(self.track.title.clone()),
+                                )
+                                .child(self.render_like_button(cx))
+                                .child(
+                                    div()
+                                        .font_weight(FontWeight::LIGHT)
+                                        .text_sm()
                                         .my_auto()
-                                        .rounded(px(3.0))
-                                        .bg(theme.album_art_background)
-                                        .when_some(self.album_art.clone(), |this, art| {
-                                            this.child(
-                                                img(art).w(px(22.0)).h(px(22.0)).rounded(px(3.0)),
+                                        .text_color(theme.text_secondary)
+                                        .text_ellipsis()
+                                        .overflow_x_hidden()
+                                        .flex_shrink()
+                                        .ml(px(12.0))
+                                        .when(show_artist_name, |this| {
+                                            this.when_some(
+                                                self.track.artist_names.clone(),
+                                                |this, v| this.child(v.0),
                                             )
                                         }),
                                 )
-                            })
-                            .child(
-                                div()
-                                    .font_weight(FontWeight::SEMIBOLD)
-                                    .overflow_x_hidden()
-                                    .text_ellipsis()
-                                    .child(self.track.title.clone()),
-                            )
-                            .child(
-                                div()
-                                    .id("like")
-                                    .mr(px(-4.0))
-                                    .ml_auto()
-                                    .my_auto()
-                                    .rounded_sm()
-                                    .p(px(4.0))
-                                    .child(
-                                        icon(if self.is_liked.is_some() {
-                                            STAR_FILLED
-                                        } else {
-                                            STAR
-                                        })
-                                        .size(px(14.0))
-                                        .text_color(theme.text_secondary),
+                                .when(self.show_bpm, |this| {
+                                    this.child(
+                                        div()
+                                            .ml(px(12.0))
+                                            .w(px(36.0))
+                                            .flex_shrink_0()
+                                            .text_right()
+                                            .text_color(theme.text_secondary)
+                                            .when_some(self.track.bpm_display(), |this, bpm| {
+                                                this.child(bpm)
+                                            }),
                                     )
-                                    .invisible()
-                                    .group(self.hover_group.clone())
-                                    .group_hover(self.hover_group.clone(), |this| this.visible())
-                                    .hover(|this| this.bg(theme.button_secondary_hover))
-                                    .active(|this| this.bg(theme.button_secondary_active))
-                                    .on_click(cx.listener(move |this, _, _, cx| {
-                                        cx.stop_propagation();
-
-                                        if let Some(id) = this.is_liked {
-                                            cx.remove_playlist_item(id)
-                                                .expect("could not unlike song");
-
-                                            this.is_liked = None;
-                                        } else {
-                                            this.is_liked = Some(
-                                                cx.add_playlist_item(1, track_id)
-                                                    .expect("could not like song"),
-                                            );
-                                        }
-
-                                        let playlist_tracker =
-                                            cx.global::<Models>().playlist_tracker.clone();
-
-                                        playlist_tracker.update(cx, |_, cx| {
-                                            cx.emit(PlaylistEvent::PlaylistUpdated(1));
-                                        });
-
-                                        cx.notify();
-                                    })),
-                            )
-                            .child(
-                                div()
-                                    .font_weight(FontWeight::LIGHT)
-                                    .text_sm()
-                                    .my_auto()
-                                    .text_color(theme.text_secondary)
-                                    .text_ellipsis()
-                                    .overflow_x_hidden()
-                                    .flex_shrink()
-                                    .ml(px(12.0))
-                                    .when(show_artist_name, |this| {
-                                        this.when_some(
-                                            self.track.artist_names.clone(),
-                                            |this, v| this.child(v.0),
-                                        )
-                                    }),
-                            )
-                            .child(div().ml(px(12.0)).flex_shrink_0().child(format!(
-                                "{}:{:02}",
-                                self.track.duration / 60,
-                                self.track.duration % 60
-                            ))),
+                                })
+                                .when_some(self.track.gain_offset_display(), |this, gain| {
+                                    this.child(
+                                        div()
+                                            .ml(px(12.0))
+                                            .my_auto()
+                                            .flex_shrink_0()
+                                            .text_xs()
+                                            .text_color(theme.text_secondary)
+                                            .child(gain),
+                                    )
+                                })
+                                .child(
+                                    div()
+                                        .ml(px(12.0))
+                                        .flex_shrink_0()
+                                        .child(self.track.duration_display()),
+                                )
+                            }),
                     ),
             )
             .child(
                 div().bg(theme.elevated_background).child(
                     menu()
+                        .item(menu_item("track_play_now", Some(PLAY), PLAY_NOW, {
+                            let pl_info = self.pl_info;
+                            move |_, _, cx| play_from_track(cx, &track, pl_info)
+                        }))
                         .item(menu_item(
-                            "track_play",
-                            Some(PLAY),
-                            "Play",
+                            "track_play_next",
+                            None::<&str>,
+                            PLAY_NEXT,
                             move |_, _, cx| {
-                                let data = QueueItemData::new(
-                                    cx,
+                                let entry = QueueEntry::new(
                                     track_location.clone(),
                                     Some(track_id),
                                     album_id,
                                 );
-                                let playback_interface = cx.global::<PlaybackInterface>();
-                                let queue_length = cx
-                                    .global::<Models>()
-                                    .queue
-                                    .read(cx)
-                                    .data
-                                    .read()
-                                    .expect("couldn't get queue")
-                                    .len();
-                                playback_interface.queue(data);
-                                playback_interface.jump(queue_length);
-                            },
-                        ))
-                        .item(menu_item(
-                            "track_play_from_here",
-                            None::<&str>,
-                            "Play from here",
-                            {
-                                let plid = self.pl_info.as_ref().map(|pl| pl.id);
-                                move |_, _, cx| play_from_track(cx, &track, plid)
+                                play_next(cx, vec![entry]);
                             },
                         ))
                         .item(menu_item(
                             "track_add_to_queue",
                             Some(PLUS),
-                            "Add to queue",
+                            ADD_TO_QUEUE,
                             move |_, _, cx| {
-                                let data = QueueItemData::new(
-                                    cx,
+                                let entry = QueueEntry::new(
                                     track_location_2.clone(),
                                     Some(track_id),
                                     album_id,
                                 );
-                                let playback_interface = cx.global::<PlaybackInterface>();
-                                playback_interface.queue(data);
+                                add_to_queue(cx, vec![entry]);
                             },
                         ))
                         .item(menu_separator())
@@ -346,67 +574,183 @@ impl Render for TrackItem {
                             "Add to playlist",
                             move |_, _, cx| show_clone.write(cx, true),
                         ))
+                        .item(menu_item(
+                            "track_set_bpm",
+                            None::<&str>,
+                            "Set BPM",
+                            move |_, _, cx| {
+                                weak_self
+                                    .update(cx, |this, cx| {
+                                        if this.edit_bpm.is_none() {
+                                            this.edit_bpm = Some(EditBpm::new(
+                                                cx,
+                                                this.show_edit_bpm.clone(),
+                                                track_id,
+                                            ));
+                                        }
+                                    })
+                                    .ok();
+
+                                show_edit_bpm.write(cx, true);
+                            },
+                        ))
+                        .item(menu_item(
+                            "track_edit_gain",
+                            Some(VOLUME),
+                            "Adjust track volume…",
+                            move |_, _, cx| {
+                                weak_edit_gain
+                                    .update(cx, |this, cx| {
+                                        if this.edit_gain.is_none() {
+                                            this.edit_gain = EditGain::new(
+                                                cx,
+                                                this.show_edit_gain.clone(),
+                                                track_id,
+                                            );
+                                        }
+                                    })
+                                    .ok();
+
+                                show_edit_gain.write(cx, true);
+                            },
+                        ))
+                        .item(menu_item(
+                            "track_properties",
+                            None::<&str>,
+                            "Properties",
+                            move |_, _, cx| {
+                                weak_properties
+                                    .update(cx, |this, cx| {
+                                        if this.properties.is_none() {
+                                            this.properties = TrackProperties::new(
+                                                cx,
+                                                this.show_properties.clone(),
+                                                track_id,
+                                            );
+                                        }
+                                    })
+                                    .ok();
+
+                                show_properties.write(cx, true);
+                            },
+                        ))
                         .when_some(self.pl_info.as_ref(), |menu, info| {
                             let playlist_id = info.id;
                             let item_id = info.item_id;
                             let playlist_tracker = cx.global::<Models>().playlist_tracker.clone();
+                            let show_move_to = self.show_move_to.clone();
 
-                            menu.item(menu_item(
-                                "track_remove_from_playlist",
-                                Some(PLAYLIST_REMOVE),
-                                "Remove from playlist",
-                                move |_, _, cx| {
-                                    cx.remove_playlist_item(item_id).unwrap();
-                                    playlist_tracker.update(cx, |_, cx| {
-                                        cx.emit(PlaylistEvent::PlaylistUpdated(playlist_id));
-                                    })
-                                },
-                            ))
+                            menu.item(menu_separator())
+                                .item(menu_item(
+                                    "track_move_to_top",
+                                    Some(CHEVRON_UP),
+                                    "Move to top",
+                                    move |_, _, cx| {
+                                        move_playlist_item_to_index(cx, playlist_id, item_id, 0);
+                                    },
+                                ))
+                                .item(menu_item(
+                                    "track_move_to_bottom",
+                                    Some(CHEVRON_DOWN),
+                                    "Move to bottom",
+                                    move |_, _, cx| {
+                                        // clamped to the last item
+                                        move_playlist_item_to_index(
+                                            cx,
+                                            playlist_id,
+                                            item_id,
+                                            usize::MAX,
+                                        );
+                                    },
+                                ))
+                                .item(menu_item(
+                                    "track_move_to_position",
+                                    None::<&str>,
+                                    "Move to position…",
+                                    move |_, _, cx| {
+                                        weak_move_to
+                                            .update(cx, |this, cx| {
+                                                if this.move_to.is_none() {
+                                                    this.move_to = Some(MoveToPosition::new(
+                                                        cx,
+                                                        this.show_move_to.clone(),
+                                                        playlist_id,
+                                                        item_id,
+                                                    ));
+                                                }
+                                            })
+                                            .ok();
+
+                                        show_move_to.write(cx, true);
+                                    },
+                                ))
+                                .item(menu_item(
+                                    "track_remove_from_playlist",
+                                    Some(PLAYLIST_REMOVE),
+                                    "Remove from playlist",
+                                    move |_, _, cx| {
+                                        cx.remove_playlist_item(item_id).unwrap();
+                                        playlist_tracker.update(cx, |_, cx| {
+                                            cx.emit(PlaylistEvent::PlaylistUpdated(playlist_id));
+                                        })
+                                    },
+                                ))
                         }),
                 ),
             )
     }
 }
 
-pub fn play_from_track(cx: &mut App, track: &Track, pl_id: Option<i64>) {
-    let queue_items = if let Some(pl_id) = pl_id {
-        let ids = cx
-            .get_playlist_tracks(pl_id)
-            .expect("failed to retrieve playlist track info");
-        let paths = cx
-            .get_playlist_track_files(pl_id)
-            .expect("failed to retrieve playlist track paths");
-
-        ids.iter()
-            .zip(paths.iter())
-            .map(|((_, track, album), path)| {
-                QueueItemData::new(cx, path.into(), Some(*track), Some(*album))
-            })
+/// Lists every track in a playlist, in playlist order, ready to be queued. If a BPM range is
+/// given, only tracks within that range are included.
+pub fn playlist_queue_entries(
+    cx: &mut App,
+    playlist_id: i64,
+    bpm_range: Option<(i64, i64)>,
+) -> Vec<QueueEntry> {
+    let ids = cx
+        .get_playlist_tracks(playlist_id)
+        .expect("failed to retrieve playlist track info");
+    let paths = cx
+        .get_playlist_track_files(playlist_id)
+        .expect("failed to retrieve playlist track paths");
+
+    let in_range: Option<FxHashSet<i64>> = bpm_range.map(|(min, max)| {
+        cx.get_playlist_tracks_in_bpm_range(playlist_id, min, max)
+            .expect("failed to retrieve playlist track info")
+            .iter()
+            .map(|(item_id, _, _)| *item_id)
             .collect()
+    });
+
+    ids.iter()
+        .zip(paths.iter())
+        .filter(|((item_id, _, _), _)| in_range.as_ref().is_none_or(|v| v.contains(item_id)))
+        .map(|((_, track, album), path)| QueueEntry::new(path.into(), Some(*track), Some(*album)))
+        .collect()
+}
+
+pub fn play_from_track(cx: &mut App, track: &Track, pl_info: Option<TrackPlaylistInfo>) {
+    let entries: Vec<QueueEntry> = if let Some(pl_info) = pl_info {
+        playlist_queue_entries(cx, pl_info.id, pl_info.bpm_range)
     } else if let Some(album_id) = track.album_id {
         cx.list_tracks_in_album(album_id)
             .expect("Failed to retrieve tracks")
             .iter()
-            .map(|track| {
-                QueueItemData::new(cx, track.location.clone(), Some(track.id), track.album_id)
-            })
+            .map(|track| QueueEntry::new(track.location.clone(), Some(track.id), track.album_id))
             .collect()
     } else {
-        Vec::from([QueueItemData::new(
-            cx,
+        Vec::from([QueueEntry::new(
             track.location.clone(),
             Some(track.id),
             track.album_id,
         )])
     };
 
-    replace_queue(queue_items.clone(), cx);
+    let start_at = entries
+        .iter()
+        .position(|entry| entry.path() == &track.location)
+        .unwrap();
 
-    let playback_interface = cx.global::<PlaybackInterface>();
-    playback_interface.jump_unshuffled(
-        queue_items
-            .iter()
-            .position(|t| t.get_path() == &track.location)
-            .unwrap(),
-    )
+    play_now(cx, entries, Some(start_at));
 }
@@ -0,0 +1,309 @@
+use gpui::{
+    App, AppContext, Context, Entity, FocusHandle, FontWeight, InteractiveElement, IntoElement,
+    KeyDownEvent, ParentElement, Render, SharedString, StatefulInteractiveElement, Styled,
+    Subscription, Window, div, prelude::FluentBuilder, px,
+};
+use tracing::error;
+
+use crate::{
+    library::{
+        db::{AlbumMethod, LibraryAccess},
+        types::Track,
+    },
+    ui::{
+        components::{
+            input::{EnrichedInputAction, TextInput},
+            modal::modal,
+        },
+        theme::Theme,
+        toasts::show_toast,
+        util::ui_px,
+    },
+};
+
+use super::{
+    credits::credits_section,
+    musicbrainz::{MusicBrainzEntity, musicbrainz_id},
+};
+
+/// A dialog showing everything that's known about a track, including the credits read from its
+/// extended tags.
+pub struct TrackProperties {
+    show: Entity<bool>,
+    track: Track,
+    album_title: Option<SharedString>,
+    credits: Vec<(String, String)>,
+    credits_expanded: bool,
+    comment_editor: Option<CommentEditor>,
+}
+
+/// The input for the track's comment, while it's being edited.
+struct CommentEditor {
+    input: Entity<TextInput>,
+    focus_handle: FocusHandle,
+    _blur: Subscription,
+}
+
+/// The extended tags to show as credits. The comment is shown on its own, so it's left out.
+fn credits_without_comment(track: &Track) -> Vec<(String, String)> {
+    let comment = track.comment.as_ref().map(|comment| comment.0.as_ref());
+
+    track
+        .extra_tags()
+        .into_iter()
+        .filter(|(key, value)| key != "COMMENT" || Some(value.as_str()) != comment)
+        .collect()
+}
+
+impl TrackProperties {
+    pub fn new(cx: &mut App, show: Entity<bool>, track_id: i64) -> Option<Entity<Self>> {
+        let track = (*cx.get_track_by_id(track_id).ok()?).clone();
+        let album_title = track
+            .album_id
+            .and_then(|id| cx.get_album_by_id(id, AlbumMethod::Metadata).ok())
+            .map(|album| album.title.clone().into());
+        let credits = credits_without_comment(&track);
+
+        Some(cx.new(|cx| {
+            cx.observe(&show, move |this: &mut Self, show, cx| {
+                // the track may have been edited since the dialog was last open
+                if *show.read(cx)
+                    && let Ok(track) = cx.get_track_by_id(track_id)
+                {
+                    this.track = (*track).clone();
+                    this.credits = credits_without_comment(&this.track);
+                }
+
+                this.comment_editor = None;
+
+                cx.notify();
+            })
+            .detach();
+
+            Self {
+                show,
+                track,
+                album_title,
+                credits,
+                credits_expanded: true,
+                comment_editor: None,
+            }
+        }))
+    }
+
+    fn start_comment_edit(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        let focus_handle = cx.focus_handle();
+        let weak_self = cx.weak_entity();
+
+        // the input is still being updated when Enter is handled, so save afterwards
+        let handler = move |action, _: &mut Window, cx: &mut App| {
+            if let EnrichedInputAction::Accept(_) = action {
+                let weak_self = weak_self.clone();
+                cx.defer(move |cx| {
+                    weak_self
+                        .update(cx, |this: &mut Self, cx| this.save_comment(cx))
+                        .ok();
+                });
+            }
+        };
+
+        let input = TextInput::new(
+            cx,
+            focus_handle.clone(),
+            self.track.comment.as_ref().map(|comment| comment.0.clone()),
+            Some("Add a comment".into()),
+            Some(Box::new(handler)),
+        );
+        let blur = cx.on_blur(&focus_handle, window, |this, _, cx| this.save_comment(cx));
+        focus_handle.focus(window, cx);
+
+        self.comment_editor = Some(CommentEditor {
+            input,
+            focus_handle,
+            _blur: blur,
+        });
+        cx.notify();
+    }
+
+    fn save_comment(&mut self, cx: &mut Context<Self>) {
+        let Some(editor) = self.comment_editor.take() else {
+            return;
+        };
+        cx.notify();
+
+        let comment = editor.input.read(cx).content.trim().to_string();
+        let comment = (!comment.is_empty()).then_some(comment);
+        let current = self
+            .track
+            .comment
+            .as_ref()
+            .map(|comment| comment.0.as_ref());
+
+        if comment.as_deref() == current {
+            return;
+        }
+
+        let track_id = self.track.id;
+        if let Err(err) = cx.set_track_comment(track_id, comment.as_deref()) {
+            error!(
+                ?err,
+                "Failed to set the comment for track {track_id}: {err}"
+            );
+            show_toast(cx, "Couldn't save the comment.");
+            return;
+        }
+
+        if let Ok(track) = cx.get_track_by_id(track_id) {
+            self.track = (*track).clone();
+            self.credits = credits_without_comment(&self.track);
+        }
+    }
+
+    fn render_comment(&self, cx: &Context<Self>) -> impl IntoElement {
+        let theme = cx.global::<Theme>();
+
+        if let Some(editor) = &self.comment_editor {
+            return div()
+                .flex()
+                .flex_col()
+                .gap(px(4.0))
+                .child(
+                    div()
+                        .id("track-comment-input")
+                        .on_key_down(cx.listener(|this, ev: &KeyDownEvent, _, cx| {
+                            // cancels the edit, rather than closing the dialog
+                            if ev.keystroke.key == "escape" {
+                                this.comment_editor = None;
+                                cx.notify();
+                                cx.stop_propagation();
+                            }
+                        }))
+                        .border_1()
+                        .border_color(theme.textbox_border)
+                        .rounded(px(3.0))
+                        .px(px(4.0))
+                        .py(px(2.0))
+                        .bg(theme.textbox_background)
+                        .child(editor.input.clone()),
+                )
+                .child(
+                    div()
+                        .text_xs()
+                        .text_color(theme.text_secondary)
+                        .child("Saved in your library only - the file's tags aren't changed."),
+                )
+                .into_any_element();
+        }
+
+        let comment = self.track.comment.as_ref().map(|comment| comment.0.clone());
+
+        div()
+            .flex()
+            .flex_col()
+            .gap(px(2.0))
+            .when_some(comment.clone(), |this, comment| this.child(comment))
+            .child(
+                div()
+                    .id("track-comment-edit")
+                    .cursor_pointer()
+                    .text_color(theme.text_link)
+                    .hover(|this| this.border_b_1().border_color(theme.text_link))
+                    .on_click(cx.listener(|this, _, window, cx| {
+                        this.start_comment_edit(window, cx);
+                    }))
+                    .child(if comment.is_some() {
+                        "Edit comment"
+                    } else {
+                        "Add a comment"
+                    }),
+            )
+            .into_any_element()
+    }
+}
+
+fn property(label: &'static str, value: impl IntoElement, theme: &Theme) -> impl IntoElement {
+    div()
+        .flex()
+        .gap(px(12.0))
+        .text_sm()
+        .child(
+            div()
+                .w(px(160.0))
+                .flex_shrink_0()
+                .text_color(theme.text_secondary)
+                .child(label),
+        )
+        .child(div().min_w_0().child(value))
+}
+
+impl Render for TrackProperties {
+    fn render(&mut self, _: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        if !*self.show.read(cx) {
+            return div().into_any_element();
+        }
+
+        let theme = cx.global::<Theme>();
+        let show = self.show.clone();
+        let track = &self.track;
+
+        modal()
+            .child(
+                div()
+                    .w(px(550.0))
+                    .max_h(px(500.0))
+                    .p(px(20.0))
+                    .flex()
+                    .flex_col()
+                    .gap(px(4.0))
+                    .child(
+                        div()
+                            .font_weight(FontWeight::BOLD)
+                            .text_size(ui_px(20.0, cx))
+                            .mb(px(8.0))
+                            .child(track.title.clone()),
+                    )
+                    .when_some(track.artist_names.clone(), |this, artists| {
+                        this.child(property("Artist", SharedString::from(artists), theme))
+                    })
+                    .when_some(self.album_title.clone(), |this, album| {
+                        this.child(property("Album", album, theme))
+                    })
+                    .child(property("Duration", track.duration_display(), theme))
+                    .when_some(track.bpm_display(), |this, bpm| {
+                        this.child(property("BPM", bpm, theme))
+                    })
+                    .child(property(
+                        "Location",
+                        track.location.to_string_lossy().to_string(),
+                        theme,
+                    ))
+                    .when_some(track.recording_mbid.as_ref(), |this, mbid| {
+                        this.child(property(
+                            "Recording",
+                            musicbrainz_id(
+                                "track-recording-mbid",
+                                MusicBrainzEntity::Recording,
+                                &mbid.0,
+                                cx,
+                            ),
+                            theme,
+                        ))
+                    })
+                    .child(property("Comment", self.render_comment(cx), theme))
+                    .when(!self.credits.is_empty(), |this| {
+                        this.child(
+                            div().mt(px(12.0)).child(
+                                credits_section("track-credits-toggle", self.credits.clone())
+                                    .expanded(self.credits_expanded)
+                                    .on_toggle(cx.listener(|this: &mut Self, _, _, cx| {
+                                        this.credits_expanded = !this.credits_expanded;
+                                        cx.notify();
+                                    })),
+                            ),
+                        )
+                    }),
+            )
+            .on_exit(move |_, cx| show.write(cx, false))
+            .into_any_element()
+    }
+}
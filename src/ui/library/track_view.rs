@@ -7,14 +7,14 @@ use crate::{
         scan::ScanEvent,
         types::{Track, table::TrackColumn},
     },
-    playback::{interface::PlaybackInterface, queue::QueueItemData},
     ui::{
         components::table::{Table, TableEvent, table_data::TABLE_MAX_WIDTH},
+        large_queue::{QueueEntry, play_now},
         models::Models,
     },
 };
 
-use super::ViewSwitchMessage;
+use super::{ViewSwitchMessage, library_empty_state};
 
 #[derive(Clone)]
 pub struct TrackView {
@@ -44,24 +44,16 @@ impl TrackView {
                     if let Some(table) = table_ref_clone.borrow().as_ref() {
                         let items = table.read(cx).get_items();
                         if let Some(items) = items {
-                            let queue_items: Vec<QueueItemData> = items
+                            let entries: Vec<QueueEntry> = items
                                 .iter()
                                 .map(|(id, _, album_id, path)| {
-                                    QueueItemData::new(
-                                        cx,
-                                        PathBuf::from(path),
-                                        Some(*id),
-                                        *album_id,
-                                    )
+                                    QueueEntry::new(PathBuf::from(path), Some(*id), *album_id)
                                 })
                                 .collect();
 
                             let index = items.iter().position(|item| item.0 == id.0).unwrap_or(0);
 
-                            let playback = cx.global::<PlaybackInterface>();
-                            playback.replace_queue(queue_items);
-                            playback.jump(index);
-                            playback.play();
+                            play_now(cx, entries, Some(index));
                         }
                     }
                 },
@@ -93,6 +85,10 @@ impl TrackView {
             })
             .detach();
 
+            // the empty state depends on both of these
+            cx.observe(&table, |_, _, cx| cx.notify()).detach();
+            cx.observe(&state, |_, _, cx| cx.notify()).detach();
+
             TrackView { table }
         })
     }
@@ -103,7 +99,11 @@ impl TrackView {
 }
 
 impl Render for TrackView {
-    fn render(&mut self, _: &mut Window, _: &mut Context<Self>) -> impl IntoElement {
+    fn render(&mut self, _: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let table = self.table.read(cx);
+        let is_empty =
+            !table.is_loading() && table.get_items().is_none_or(|items| items.is_empty());
+
         div()
             .flex()
             .flex_col()
@@ -112,6 +112,12 @@ impl Render for TrackView {
             .max_w(px(TABLE_MAX_WIDTH))
             .pt(px(10.0))
             .pb(px(0.0))
-            .child(self.table.clone())
+            .map(|this| {
+                if is_empty {
+                    this.child(library_empty_state("tracks-empty", "tracks", cx))
+                } else {
+                    this.child(self.table.clone())
+                }
+            })
     }
 }
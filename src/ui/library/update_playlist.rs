@@ -15,7 +15,9 @@ use crate::{
     ui::components::{
         icons::{PLAYLIST, PLAYLIST_ADD, STAR_FILLED},
         modal::modal,
-        palette::{ExtraItem, ExtraItemProvider, FinderItemLeft, Palette, PaletteItem},
+        palette::{
+            AcceptIntent, ExtraItem, ExtraItemProvider, FinderItemLeft, Palette, PaletteItem,
+        },
     },
 };
 
@@ -37,7 +39,7 @@ impl PaletteItem for PlaylistWithCount {
 }
 
 type MatcherFunc = Box<dyn Fn(&Arc<PlaylistWithCount>, &mut App) -> Utf32String + 'static>;
-type OnAccept = Box<dyn Fn(&Arc<PlaylistWithCount>, &mut App) + 'static>;
+type OnAccept = Box<dyn Fn(&Arc<PlaylistWithCount>, AcceptIntent, &mut App) + 'static>;
 
 pub struct UpdatePlaylist {
     show: Entity<bool>,
@@ -68,7 +70,7 @@ impl UpdatePlaylist {
 
             let show_clone = show.clone();
 
-            let on_accept: OnAccept = Box::new(move |playlist, cx| {
+            let on_accept: OnAccept = Box::new(move |playlist, _, cx| {
                 import_playlist(cx, playlist.id);
                 show_clone.write(cx, false);
             });
@@ -0,0 +1,212 @@
+use std::sync::Arc;
+
+use gpui::{
+    App, AppContext, Context, Entity, FontWeight, IntoElement, ParentElement, Render, SharedString,
+    Styled, Window, div, px,
+};
+use nucleo::Utf32String;
+use tracing::error;
+
+use crate::{
+    library::{db::LibraryAccess, playlist::change_liked_playlist, types::PlaylistWithCount},
+    ui::{
+        components::{
+            icons::PLAYLIST_ADD,
+            modal::modal,
+            palette::{
+                AcceptIntent, ExtraItem, ExtraItemProvider, FinderItemLeft, Palette, PaletteItem,
+            },
+        },
+        theme::Theme,
+        toasts::show_toast,
+        util::ui_px,
+    },
+};
+
+/// The name of the pending check left by the migration that stopped assuming the liked songs
+/// playlist has ID 1.
+pub const LIKED_PLAYLIST_CHECK: &str = "liked_playlist";
+
+/// A playlist that could hold the user's liked songs.
+#[derive(PartialEq)]
+pub struct LikedPlaylistChoice(PlaylistWithCount);
+
+impl PaletteItem for LikedPlaylistChoice {
+    fn left_content(&self, cx: &mut App) -> Option<FinderItemLeft> {
+        self.0.left_content(cx)
+    }
+
+    fn middle_content(&self, _: &mut App) -> SharedString {
+        self.0.name.0.to_string().into()
+    }
+
+    fn right_content(&self, _: &mut App) -> Option<SharedString> {
+        Some(match self.0.track_count {
+            1 => "1 track".into(),
+            count => format!("{count} tracks").into(),
+        })
+    }
+}
+
+fn playlist_choices(cx: &App) -> Vec<Arc<LikedPlaylistChoice>> {
+    cx.get_all_playlists()
+        .map(|playlists| {
+            playlists
+                .iter()
+                .cloned()
+                .map(LikedPlaylistChoice)
+                .map(Arc::new)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Closes the picker for good. Whatever was picked (or nothing, if the picker was dismissed),
+/// the user isn't asked again.
+fn finish(cx: &mut App, show: &Entity<bool>) {
+    if let Err(err) = cx.clear_pending_check(LIKED_PLAYLIST_CHECK) {
+        error!(?err, "Failed to clear the liked playlist check: {err}");
+    }
+
+    show.write(cx, false);
+}
+
+fn use_for_liked_songs(cx: &mut App, playlist_id: i64, name: &str) {
+    match change_liked_playlist(cx, playlist_id) {
+        Ok(()) => show_toast(cx, format!("Liked songs will be saved to {name}.")),
+        Err(err) => {
+            error!(?err, "Failed to change the liked songs playlist: {err}");
+            show_toast(cx, "Couldn't change the liked songs playlist.");
+        }
+    }
+}
+
+type MatcherFunc = Box<dyn Fn(&Arc<LikedPlaylistChoice>, &mut App) -> Utf32String + 'static>;
+type OnAccept = Box<dyn Fn(&Arc<LikedPlaylistChoice>, AcceptIntent, &mut App) + 'static>;
+
+/// Asks the user which playlist holds their liked songs. Older versions always saved liked songs
+/// to the playlist with ID 1, which isn't the liked songs playlist in some libraries, so this is
+/// shown once if that playlist is a regular playlist.
+pub struct LikedPlaylistPicker {
+    show: Entity<bool>,
+    /// The name of the playlist with ID 1, which liked songs may have been saved to.
+    first_playlist: Option<SharedString>,
+    palette: Entity<Palette<LikedPlaylistChoice, MatcherFunc, OnAccept>>,
+}
+
+impl LikedPlaylistPicker {
+    pub fn new(cx: &mut App, show: Entity<bool>) -> Entity<Self> {
+        cx.new(|cx| {
+            cx.observe(&show, |_, _, cx| cx.notify()).detach();
+
+            let matcher: MatcherFunc = Box::new(|choice, _| choice.0.name.0.to_string().into());
+
+            let show_clone = show.clone();
+            let on_accept: OnAccept = Box::new(move |choice, _, cx| {
+                use_for_liked_songs(cx, choice.0.id, &choice.0.name.to_string());
+                finish(cx, &show_clone);
+            });
+
+            let palette = Palette::new(cx, playlist_choices(cx), matcher, on_accept, &show);
+
+            let show_for_create = show.clone();
+            let provider: ExtraItemProvider = Arc::new(move |_: &str| {
+                let show = show_for_create.clone();
+
+                vec![ExtraItem {
+                    left: Some(FinderItemLeft::Icon(PLAYLIST_ADD.into())),
+                    middle: "Start a new liked songs playlist".into(),
+                    right: None,
+                    on_accept: Arc::new(move |cx| {
+                        match cx.create_playlist("Liked Songs") {
+                            Ok(playlist_id) => {
+                                let name = cx
+                                    .get_playlist(playlist_id)
+                                    .map(|playlist| playlist.name.to_string())
+                                    .unwrap_or_else(|_| "the new playlist".to_string());
+                                use_for_liked_songs(cx, playlist_id, &name);
+                            }
+                            Err(err) => {
+                                error!(?err, "Failed to create playlist: {err}");
+                                show_toast(cx, "Couldn't create the playlist.");
+                            }
+                        }
+
+                        finish(cx, &show);
+                    }),
+                }]
+            });
+
+            cx.update_entity(&palette, |palette, cx| {
+                palette.register_extra_provider(provider, cx);
+            });
+
+            Self {
+                show,
+                first_playlist: cx
+                    .get_playlist(1)
+                    .ok()
+                    .map(|playlist| playlist.name.to_string().into()),
+                palette,
+            }
+        })
+    }
+}
+
+impl Render for LikedPlaylistPicker {
+    fn render(&mut self, window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        if !*self.show.read(cx) {
+            return div().into_any_element();
+        }
+
+        let show = self.show.clone();
+        let palette = self.palette.clone();
+
+        cx.update_entity(&palette, |palette, cx| {
+            palette.focus(window, cx);
+        });
+
+        let theme = cx.global::<Theme>();
+        let explanation = match &self.first_playlist {
+            Some(name) => format!(
+                "Earlier versions of Hummingbird may have saved your liked songs to \"{name}\". \
+                Choose the playlist your liked songs should be saved to from now on."
+            ),
+            None => {
+                "Choose the playlist your liked songs should be saved to from now on.".to_string()
+            }
+        };
+
+        modal()
+            .child(
+                div()
+                    .w(px(550.0))
+                    .flex()
+                    .flex_col()
+                    .child(
+                        div()
+                            .px(px(20.0))
+                            .pt(px(20.0))
+                            .pb(px(12.0))
+                            .flex()
+                            .flex_col()
+                            .gap(px(8.0))
+                            .child(
+                                div()
+                                    .font_weight(FontWeight::BOLD)
+                                    .text_size(ui_px(20.0, cx))
+                                    .child("Where are your liked songs?"),
+                            )
+                            .child(
+                                div()
+                                    .text_sm()
+                                    .text_color(theme.text_secondary)
+                                    .child(explanation),
+                            ),
+                    )
+                    .child(div().h(px(300.0)).child(palette)),
+            )
+            .on_exit(move |_, cx| finish(cx, &show))
+            .into_any_element()
+    }
+}
@@ -0,0 +1,149 @@
+use std::{pin::pin, time::Duration};
+
+use futures::{
+    StreamExt,
+    channel::mpsc,
+    future::{Either, select},
+};
+use gpui::{
+    App, AppContext, Bounds, Context, FontWeight, IntoElement, ParentElement, Render, SharedString,
+    Styled, TitlebarOptions, Window, WindowBounds, WindowHandle, WindowKind, WindowOptions, div,
+    px, size,
+};
+use sqlx::SqlitePool;
+use tracing::error;
+
+use crate::{
+    library::db::{MigrationProgress, migrate},
+    settings::interface::DEFAULT_FONT_FAMILY,
+    ui::theme::Theme,
+};
+
+/// How long the database can take to update before a window is shown, so that quick updates
+/// don't flash a window on screen.
+const SHOW_PROGRESS_AFTER: Duration = Duration::from_secs(1);
+
+/// Shown while the library database is updated, before the main window is opened.
+struct MigrationWindow {
+    progress: Option<MigrationProgress>,
+}
+
+impl Render for MigrationWindow {
+    fn render(&mut self, _: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let theme = cx.global::<Theme>();
+        let title: SharedString = match self.progress {
+            Some(MigrationProgress { current, total }) => {
+                format!("Updating library database ({current} of {total})…").into()
+            }
+            None => "Updating library database…".into(),
+        };
+
+        div()
+            .size_full()
+            .flex()
+            .flex_col()
+            .justify_center()
+            .gap(px(8.0))
+            .px(px(24.0))
+            .bg(theme.background_primary)
+            .text_color(theme.text)
+            .font_family(DEFAULT_FONT_FAMILY)
+            .child(div().font_weight(FontWeight::BOLD).child(title))
+            .child(div().text_sm().text_color(theme.text_secondary).child(
+                "This can take a while for large libraries. Please don't close \
+                        Hummingbird until it's finished.",
+            ))
+    }
+}
+
+fn open_migration_window(
+    cx: &mut App,
+    progress: Option<MigrationProgress>,
+) -> anyhow::Result<WindowHandle<MigrationWindow>> {
+    let bounds = Bounds::centered(None, size(px(460.0), px(140.0)), cx);
+
+    cx.open_window(
+        WindowOptions {
+            window_bounds: Some(WindowBounds::Windowed(bounds)),
+            titlebar: Some(TitlebarOptions {
+                title: Some("Hummingbird".into()),
+                ..Default::default()
+            }),
+            is_resizable: false,
+            app_id: Some("org.mailliw.hummingbird".to_string()),
+            kind: WindowKind::Normal,
+            ..Default::default()
+        },
+        |window, cx| {
+            // closing the window wouldn't stop the update, it'd only hide that it's happening
+            window.on_window_should_close(cx, |_, _| false);
+
+            cx.new(|_| MigrationWindow { progress })
+        },
+    )
+}
+
+/// Brings the library database up to date, then calls `then`. If the update takes longer than
+/// [SHOW_PROGRESS_AFTER], a window showing its progress is opened until it's done.
+pub fn migrate_then(cx: &mut App, pool: SqlitePool, then: impl FnOnce(&mut App) + 'static) {
+    let (progress_tx, mut progress_rx) = mpsc::unbounded();
+    let mut migration = crate::RUNTIME.spawn(async move {
+        migrate(&pool, move |progress| {
+            progress_tx.unbounded_send(progress).ok();
+        })
+        .await
+    });
+
+    cx.spawn(async move |cx| {
+        let delay = cx.background_executor().timer(SHOW_PROGRESS_AFTER);
+
+        let (result, window) = match select(&mut migration, pin!(delay)).await {
+            Either::Left((result, _)) => (result, None),
+            Either::Right((_, migration)) => {
+                let mut latest = None;
+                while let Ok(Some(progress)) = progress_rx.try_next() {
+                    latest = Some(progress);
+                }
+
+                let window = cx
+                    .update(|cx| open_migration_window(cx, latest))
+                    .and_then(|window| window)
+                    .inspect_err(|err| error!(?err, "Failed to open migration window: {err}"))
+                    .ok();
+
+                // the sender is dropped once the migrations are done
+                while let Some(progress) = progress_rx.next().await {
+                    if let Some(window) = window {
+                        window
+                            .update(cx, |view, _, cx| {
+                                view.progress = Some(progress);
+                                cx.notify();
+                            })
+                            .ok();
+                    }
+                }
+
+                (migration.await, window)
+            }
+        };
+
+        match result {
+            Ok(Ok(())) => (),
+            Ok(Err(err)) => error!(?err, "Failed to update the library database: {err}"),
+            Err(err) => error!(?err, "Database update task failed: {err}"),
+        }
+
+        cx.update(|cx| {
+            then(cx);
+
+            // the main window has to be open before this one is closed, or the app would quit
+            if let Some(window) = window {
+                window
+                    .update(cx, |_, window, _| window.remove_window())
+                    .ok();
+            }
+        })
+        .ok();
+    })
+    .detach();
+}
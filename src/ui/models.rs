@@ -6,16 +6,22 @@ use std::{
 };
 
 use gpui::{App, AppContext, Entity, EventEmitter, Global, Pixels, RenderImage};
-use rustc_hash::FxHashMap;
+use rustc_hash::{FxHashMap, FxHashSet};
 use serde::{Deserialize, Serialize};
 use tokio::sync::Mutex;
 use tracing::{debug, error, warn};
 
 use crate::{
-    library::scan::ScanEvent,
+    crash::take_unseen_report,
+    devices::traits::DeviceInfo,
+    library::{
+        album_query::{AlbumFilter, AlbumFilterOptions},
+        scan::ScanEvent,
+    },
     media::metadata::Metadata,
+    paths::DataPaths,
     playback::{
-        events::RepeatState,
+        events::{ActiveDeviceProvider, RepeatState, SleepTimer},
         queue::{QueueItemData, QueueItemUIData},
         thread::PlaybackState,
     },
@@ -25,9 +31,18 @@ use crate::{
     },
     settings::{
         SettingsGlobal,
-        storage::{DEFAULT_QUEUE_WIDTH, DEFAULT_SIDEBAR_WIDTH, StorageData, TableSettings},
+        storage::{
+            DEFAULT_QUEUE_WIDTH, DEFAULT_SIDEBAR_WIDTH, StorageData, TableSettings, WindowState,
+        },
+    },
+    ui::{
+        data::Decode,
+        large_queue::LargeQueue,
+        library::ViewSwitchMessage,
+        rebuild_dialog::RebuildReason,
+        toasts::Toasts,
+        whats_new::{CURRENT_VERSION, Release, changelog, releases_since},
     },
-    ui::{app::get_dirs, data::Decode, library::ViewSwitchMessage},
 };
 
 // yes this looks a little silly
@@ -38,6 +53,13 @@ pub struct ImageEvent(pub Box<[u8]>);
 
 impl EventEmitter<ImageEvent> for Option<Arc<RenderImage>> {}
 
+/// Emitted by the position model when the playback thread has finished a seek. Contains the
+/// position that playback actually ended up at, in milliseconds, or `None` if the seek failed.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct SeekCompleted(pub Option<u64>);
+
+impl EventEmitter<SeekCompleted> for u64 {}
+
 #[derive(Clone)]
 pub enum LastFMState {
     Disconnected,
@@ -56,10 +78,32 @@ pub struct Models {
     pub lastfm: Entity<LastFMState>,
     pub switcher_model: Entity<VecDeque<ViewSwitchMessage>>,
     pub show_about: Entity<bool>,
+    pub show_font_picker: Entity<bool>,
+    pub show_device_picker: Entity<bool>,
+    pub show_shortcuts: Entity<bool>,
+    /// Whether hidden albums are shown in the album list. Also notified when an album is hidden or
+    /// unhidden, so that album lists can be reloaded.
+    pub show_hidden_albums: Entity<bool>,
+    /// The filters the album list is narrowed down with. Kept until Hummingbird is closed.
+    pub album_filter: Entity<AlbumFilter>,
+    /// Which album filters match any albums, updated after each scan.
+    pub album_filter_options: Entity<AlbumFilterOptions>,
+    /// Set when the user should be asked to confirm a library rebuild.
+    pub rebuild_prompt: Entity<Option<RebuildReason>>,
+    /// Set when the user should be asked to confirm queueing a large number of tracks.
+    pub large_queue: Entity<LargeQueue>,
+    /// Set to the releases whose changes should be shown in the "What's new" dialog.
+    pub whats_new: Entity<Option<Vec<Release>>>,
+    /// Set to the crash report written the last time Hummingbird closed unexpectedly, so that
+    /// the user can be told about it.
+    pub crash_report: Entity<Option<PathBuf>>,
     pub playlist_tracker: Entity<PlaylistInfoTransfer>,
     pub sidebar_width: Entity<Pixels>,
     pub queue_width: Entity<Pixels>,
     pub table_settings: Entity<std::collections::HashMap<String, TableSettings>>,
+    /// Where the main window is, kept up to date as it's moved and resized.
+    pub window_state: Entity<Option<WindowState>>,
+    pub toasts: Entity<Toasts>,
 }
 
 impl Global for Models {}
@@ -85,6 +129,7 @@ impl PartialEq<std::path::PathBuf> for CurrentTrack {
 
 #[derive(Clone)]
 pub struct PlaybackInfo {
+    /// The position in the current track and its duration, in milliseconds.
     pub position: Entity<u64>,
     pub duration: Entity<u64>,
     pub playback_state: Entity<PlaybackState>,
@@ -93,6 +138,31 @@ pub struct PlaybackInfo {
     pub repeating: Entity<RepeatState>,
     pub volume: Entity<f64>,
     pub prev_volume: Entity<f64>,
+    /// Whether playback is muted. The volume is kept as it was while muted.
+    pub muted: Entity<bool>,
+    /// How fast tracks are played, as a multiple of their normal speed.
+    pub speed: Entity<f64>,
+    /// The balance between the left and right channels, from -1.0 (left only) to 1.0 (right
+    /// only).
+    pub balance: Entity<f64>,
+    /// The section of the current track that's being repeated, as start and end times in seconds.
+    pub loop_region: Entity<Option<(f64, f64)>>,
+    /// The sleep timer, with the time left on it if it runs for a duration.
+    pub sleep_timer: Entity<SleepTimer>,
+    /// Whether playback stops once the current track has finished.
+    pub stop_after_current: Entity<bool>,
+    /// The audio backend playback goes through, once the playback thread has started.
+    pub device_provider: Entity<Option<ActiveDeviceProvider>>,
+    /// The devices that can be played through, as last listed by the playback thread.
+    pub devices: Entity<Vec<DeviceInfo>>,
+    /// The files that went away while they were being played, so that they can be marked in the
+    /// queue. A file is removed again once it plays.
+    pub unavailable: Entity<FxHashSet<PathBuf>>,
+    /// Whether the current track is played at its own sample rate and bit depth, without being
+    /// resampled.
+    pub bit_perfect: Entity<bool>,
+    /// How many times the audio device has run out of samples to play since playback started.
+    pub underruns: Entity<u64>,
 }
 
 impl Global for PlaybackInfo {}
@@ -105,7 +175,8 @@ impl Global for PlaybackInfo {}
 #[derive(Debug, Clone)]
 pub struct Queue {
     pub data: Arc<RwLock<Vec<QueueItemData>>>,
-    pub position: usize,
+    /// The index of the current item, as last reported by the playback thread.
+    pub position: Option<usize>,
 }
 
 impl EventEmitter<(PathBuf, QueueItemUIData)> for Queue {}
@@ -118,7 +189,9 @@ pub enum MMBSEvent {
     NewTrack(PathBuf),
     MetadataRecieved(Arc<Metadata>),
     StateChanged(PlaybackState),
+    /// The position in the current track, in whole seconds.
     PositionChanged(u64),
+    /// The duration of the current track, in seconds, rounded to the nearest second.
     DurationChanged(u64),
 }
 
@@ -128,7 +201,9 @@ pub struct PlaylistInfoTransfer;
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum PlaylistEvent {
+    PlaylistCreated(i64),
     PlaylistUpdated(i64),
+    PlaylistRenamed(i64),
     PlaylistDeleted(i64),
 }
 
@@ -142,10 +217,27 @@ pub fn build_models(cx: &mut App, queue: Queue, storage_data: &StorageData) {
     let scan_state: Entity<ScanEvent> = cx.new(|_| ScanEvent::ScanCompleteIdle);
     let mmbs: Entity<MMBSList> = cx.new(|_| MMBSList(FxHashMap::default()));
     let show_about: Entity<bool> = cx.new(|_| false);
+    let show_font_picker: Entity<bool> = cx.new(|_| false);
+    let show_device_picker: Entity<bool> = cx.new(|_| false);
+    let show_shortcuts: Entity<bool> = cx.new(|_| false);
+    let show_hidden_albums: Entity<bool> = cx.new(|_| false);
+    let album_filter: Entity<AlbumFilter> = cx.new(|_| AlbumFilter::default());
+    let album_filter_options: Entity<AlbumFilterOptions> =
+        cx.new(|_| AlbumFilterOptions::default());
+    let rebuild_prompt: Entity<Option<RebuildReason>> = cx.new(|_| None);
+    let large_queue: Entity<LargeQueue> = cx.new(|_| LargeQueue::default());
+    // after an update, the changes since the last version that ran are shown once
+    let whats_new: Entity<Option<Vec<Release>>> = cx.new(|_| {
+        let releases = releases_since(
+            &changelog(),
+            storage_data.last_seen_version.as_deref(),
+            CURRENT_VERSION,
+        );
+        (!releases.is_empty()).then_some(releases)
+    });
+    let crash_report: Entity<Option<PathBuf>> = cx.new(|_| take_unseen_report());
     let lastfm: Entity<LastFMState> = cx.new(|cx| {
-        let dirs = get_dirs();
-        let directory = dirs.data_dir().to_path_buf();
-        let path = directory.join("lastfm.json");
+        let path = DataPaths::get().data_dir().join("lastfm.json");
 
         if LASTFM_CREDS.is_some() && let Ok(file) = File::open(path) {
             let reader = std::io::BufReader::new(file);
@@ -181,9 +273,7 @@ pub fn build_models(cx: &mut App, queue: Queue, storage_data: &StorageData) {
             cx.notify();
         });
 
-        let dirs = get_dirs();
-        let directory = dirs.data_dir().to_path_buf();
-        let path = directory.join("lastfm.json");
+        let path = DataPaths::get().data_dir().join("lastfm.json");
         let file = OpenOptions::new()
             .write(true)
             .truncate(true)
@@ -247,6 +337,8 @@ pub fn build_models(cx: &mut App, queue: Queue, storage_data: &StorageData) {
     });
 
     let table_settings = cx.new(|_| storage_data.table_settings.clone());
+    let window_state = cx.new(|_| storage_data.window);
+    let toasts = cx.new(|_| Toasts::default());
 
     cx.set_global(Models {
         metadata,
@@ -257,10 +349,22 @@ pub fn build_models(cx: &mut App, queue: Queue, storage_data: &StorageData) {
         lastfm,
         switcher_model,
         show_about,
+        show_font_picker,
+        show_device_picker,
+        show_shortcuts,
+        show_hidden_albums,
+        album_filter,
+        album_filter_options,
+        rebuild_prompt,
+        large_queue,
+        whats_new,
+        crash_report,
         playlist_tracker,
         sidebar_width,
         queue_width,
         table_settings,
+        window_state,
+        toasts,
     });
 
     const DEFAULT_VOLUME: f64 = 1.0;
@@ -282,6 +386,20 @@ pub fn build_models(cx: &mut App, queue: Queue, storage_data: &StorageData) {
     });
     let volume: Entity<f64> = cx.new(|_| DEFAULT_VOLUME);
     let prev_volume: Entity<f64> = cx.new(|_| DEFAULT_VOLUME);
+    let muted: Entity<bool> = cx.new(|_| false);
+    let speed: Entity<f64> = cx.new(|_| 1.0);
+    let balance: Entity<f64> = cx.new(|cx| {
+        let settings = cx.global::<SettingsGlobal>().model.read(cx);
+        settings.playback.balance.clamp(-1.0, 1.0)
+    });
+    let loop_region: Entity<Option<(f64, f64)>> = cx.new(|_| None);
+    let sleep_timer: Entity<SleepTimer> = cx.new(|_| SleepTimer::Off);
+    let stop_after_current: Entity<bool> = cx.new(|_| false);
+    let device_provider: Entity<Option<ActiveDeviceProvider>> = cx.new(|_| None);
+    let devices: Entity<Vec<DeviceInfo>> = cx.new(|_| Vec::new());
+    let unavailable: Entity<FxHashSet<PathBuf>> = cx.new(|_| FxHashSet::default());
+    let bit_perfect: Entity<bool> = cx.new(|_| false);
+    let underruns: Entity<u64> = cx.new(|_| 0);
 
     cx.set_global(PlaybackInfo {
         position,
@@ -292,6 +410,17 @@ pub fn build_models(cx: &mut App, queue: Queue, storage_data: &StorageData) {
         repeating,
         volume,
         prev_volume,
+        muted,
+        speed,
+        balance,
+        loop_region,
+        sleep_timer,
+        stop_after_current,
+        device_provider,
+        devices,
+        unavailable,
+        bit_perfect,
+        underruns,
     });
 }
 
@@ -1,7 +1,8 @@
 use crate::{
     playback::{
         interface::PlaybackInterface,
-        queue::{DataSource, QueueItemData},
+        queue::{QueueItemData, QueueItemUIData},
+        session::{load_session, save_session},
     },
     settings::storage::DEFAULT_QUEUE_WIDTH,
     ui::components::{
@@ -12,34 +13,173 @@ use crate::{
             continue_edge_scroll, get_edge_scroll_direction, handle_drag_move, handle_drop,
             perform_edge_scroll,
         },
-        icons::{CROSS, SHUFFLE, TRASH, icon},
-        menu::{menu, menu_item},
+        empty_state::empty_state,
+        icons::{CROSS, DOTS, PLAYLIST, SHUFFLE, TRASH, icon},
+        menu::{menu, menu_check_item, menu_item},
         nav_button::nav_button,
         resizable_sidebar::{ResizeSide, resizable_sidebar},
         scrollbar::{RightPad, ScrollableHandle, floating_scrollbar},
     },
+    util::round_millis,
 };
 use gpui::*;
 use prelude::FluentBuilder;
 use rustc_hash::FxHashMap;
+use std::path::PathBuf;
+use tracing::error;
 
 use super::{
     components::button::{ButtonSize, ButtonStyle, button},
+    external_drag,
+    library::ViewSwitchMessage,
     models::{Models, PlaybackInfo},
+    shortcuts::{ActionGroup, describe_action},
     theme::Theme,
-    util::{create_or_retrieve_view, drop_image_from_app, prune_views},
+    util::{create_or_retrieve_view, prune_views, ui_px},
 };
 
+actions!(queue, [RemoveSelected]);
+
 /// The list identifier for queue drag-drop operations
 const QUEUE_LIST_ID: &str = "queue";
-/// Height of each queue item in pixels
+/// Height of each queue item in pixels, before the interface scale is applied
 const QUEUE_ITEM_HEIGHT: f32 = 59.0;
+/// Below this queue width, items are shown on a single line without album art.
+const COMPACT_BELOW_WIDTH: Pixels = px(260.0);
+/// From this queue width up, items also show the track's length.
+const DURATION_FROM_WIDTH: Pixels = px(380.0);
+
+/// How queue items are laid out, depending on how wide the queue is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum QueueItemLayout {
+    /// "Title — Artist" on a single line, without album art.
+    Compact,
+    /// Album art, with the title and artist on separate lines.
+    Regular,
+    /// The regular layout, with the track's length on the right.
+    Wide,
+}
+
+impl QueueItemLayout {
+    fn for_width(width: Pixels) -> Self {
+        if width < COMPACT_BELOW_WIDTH {
+            QueueItemLayout::Compact
+        } else if width < DURATION_FROM_WIDTH {
+            QueueItemLayout::Regular
+        } else {
+            QueueItemLayout::Wide
+        }
+    }
+}
+
+pub fn bind_actions(cx: &mut App) {
+    cx.bind_keys([
+        KeyBinding::new("delete", RemoveSelected, Some("Queue")),
+        KeyBinding::new("backspace", RemoveSelected, Some("Queue")),
+    ]);
+
+    describe_action(
+        cx,
+        ActionGroup::Playback,
+        "Remove Selected Items from Queue",
+        RemoveSelected,
+    );
+}
+
+/// The queue items selected with ctrl- and shift-click. Each item's path is kept alongside its
+/// index, so that the selection can be dropped for items that moved or were replaced.
+#[derive(Default)]
+pub struct QueueSelection {
+    items: FxHashMap<usize, PathBuf>,
+    /// Where shift-click selects from.
+    anchor: Option<usize>,
+}
+
+impl QueueSelection {
+    fn contains(&self, idx: usize) -> bool {
+        self.items.contains_key(&idx)
+    }
+
+    fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    fn indices(&self) -> Vec<usize> {
+        self.items.keys().copied().collect()
+    }
+
+    fn clear(&mut self) {
+        self.items.clear();
+        self.anchor = None;
+    }
+
+    fn toggle(&mut self, idx: usize, queue: &[QueueItemData]) {
+        if self.items.remove(&idx).is_none()
+            && let Some(item) = queue.get(idx)
+        {
+            self.items.insert(idx, item.get_path().clone());
+        }
+
+        self.anchor = Some(idx);
+    }
+
+    fn select_to(&mut self, idx: usize, queue: &[QueueItemData]) {
+        let anchor = self.anchor.unwrap_or(idx);
+        let range = anchor.min(idx)..=anchor.max(idx);
+
+        self.items = range
+            .filter_map(|idx| queue.get(idx).map(|item| (idx, item.get_path().clone())))
+            .collect();
+        self.anchor = Some(anchor);
+    }
+
+    /// Drops the items that aren't at the same place in the queue anymore. Returns whether
+    /// anything was dropped.
+    fn retain_unchanged(&mut self, queue: &[QueueItemData]) -> bool {
+        let before = self.items.len();
+        self.items
+            .retain(|idx, path| queue.get(*idx).is_some_and(|item| item.get_path() == path));
+
+        if self.items.is_empty() {
+            self.anchor = None;
+        }
+
+        self.items.len() != before
+    }
+}
+
+/// The title and artist of a queue item on a single line, for the compact layout.
+fn compact_label(item: &QueueItemUIData) -> SharedString {
+    let name = item.name.clone().unwrap_or_else(|| "Unknown Track".into());
+
+    match &item.artist_name {
+        Some(artist) => format!("{name} — {artist}").into(),
+        None => name,
+    }
+}
+
+/// Removes every selected item from the queue.
+fn remove_selected(selection: &Entity<QueueSelection>, cx: &mut App) {
+    let indices = selection.read(cx).indices();
+
+    if !indices.is_empty() {
+        cx.global::<PlaybackInterface>().remove_items(indices);
+        selection.update(cx, |selection, cx| {
+            selection.clear();
+            cx.notify();
+        });
+    }
+}
 
 pub struct QueueItem {
     item: Option<QueueItemData>,
-    current: usize,
+    current: Option<usize>,
     idx: usize,
     drag_drop_manager: Entity<DragDropListManager>,
+    selection: Entity<QueueSelection>,
+    /// The queue's focus handle, so that the delete key works after selecting an item.
+    focus_handle: FocusHandle,
+    layout: QueueItemLayout,
 }
 
 impl QueueItem {
@@ -48,16 +188,13 @@ impl QueueItem {
         item: Option<QueueItemData>,
         idx: usize,
         drag_drop_manager: Entity<DragDropListManager>,
+        selection: Entity<QueueSelection>,
+        focus_handle: FocusHandle,
     ) -> Entity<Self> {
         cx.new(move |cx| {
             cx.on_release(|m: &mut QueueItem, cx| {
+                // the data may already have been dropped, if the item was removed from the queue
                 if let Some(item) = m.item.as_mut() {
-                    let data = item.get_data(cx).read(cx).as_ref().unwrap();
-
-                    if let (Some(image), DataSource::Library) = (data.image.clone(), data.source) {
-                        drop_image_from_app(cx, image);
-                    }
-
                     item.drop_data(cx);
                 }
             })
@@ -84,14 +221,77 @@ impl QueueItem {
             })
             .detach();
 
+            cx.observe(&selection, |_, _, cx| {
+                cx.notify();
+            })
+            .detach();
+
+            let stop_after_current = cx.global::<PlaybackInfo>().stop_after_current.clone();
+            cx.observe(&stop_after_current, |this: &mut QueueItem, _, cx| {
+                if this.current == Some(this.idx) {
+                    cx.notify();
+                }
+            })
+            .detach();
+
+            let unavailable = cx.global::<PlaybackInfo>().unavailable.clone();
+            cx.observe(&unavailable, |_, _, cx| {
+                cx.notify();
+            })
+            .detach();
+
+            // only redrawn when the layout actually changes, not on every step of a resize
+            let queue_width = cx.global::<Models>().queue_width.clone();
+            cx.observe(&queue_width, |this: &mut QueueItem, width, cx| {
+                let layout = QueueItemLayout::for_width(*width.read(cx));
+
+                if layout != this.layout {
+                    this.layout = layout;
+                    cx.notify();
+                }
+            })
+            .detach();
+
             Self {
                 item,
                 idx,
                 current: queue.read(cx).position,
                 drag_drop_manager,
+                selection,
+                focus_handle,
+                layout: QueueItemLayout::for_width(*queue_width.read(cx)),
             }
         })
     }
+
+    fn click(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        let modifiers = window.modifiers();
+        let idx = self.idx;
+
+        if !modifiers.secondary() && !modifiers.shift {
+            self.selection.update(cx, |selection, cx| {
+                selection.clear();
+                cx.notify();
+            });
+            cx.global::<PlaybackInterface>().jump(idx);
+            return;
+        }
+
+        let queue = cx.global::<Models>().queue.clone();
+        let queue = queue.read(cx).data.clone();
+        let queue = queue.read().expect("could not read queue");
+
+        self.selection.update(cx, |selection, cx| {
+            if modifiers.shift {
+                selection.select_to(idx, &queue);
+            } else {
+                selection.toggle(idx, &queue);
+            }
+            cx.notify();
+        });
+
+        self.focus_handle.focus(window, cx);
+    }
 }
 
 impl Render for QueueItem {
@@ -103,14 +303,25 @@ impl Render for QueueItem {
         let theme = cx.global::<Theme>().clone();
 
         if let Some(item) = data.as_ref() {
-            let is_current = self.current == self.idx;
+            let is_current = self.current == Some(self.idx);
+            let selection = self.selection.read(cx);
+            let is_selected = selection.contains(self.idx);
+            // removing an item that's part of a selection removes the whole selection
+            let remove_count = if is_selected { selection.len() } else { 1 };
             let album_art = item.image.as_ref().cloned();
             let idx = self.idx;
+            let layout = self.layout;
 
             let item_state =
                 DragDropItemState::for_index(&self.drag_drop_manager.read(cx), self.idx);
 
             let track_name = item.name.clone().unwrap_or_else(|| "Unknown Track".into());
+            let track_path = self.item.as_ref().map(|item| item.get_path().clone());
+            let stop_after_current = *cx.global::<PlaybackInfo>().stop_after_current.read(cx);
+            let is_unavailable = self.item.as_ref().is_some_and(|item| {
+                let unavailable = cx.global::<PlaybackInfo>().unavailable.read(cx);
+                unavailable.contains(item.get_path())
+            });
 
             context(ElementId::View(cx.entity_id()))
                 .with(
@@ -121,92 +332,179 @@ impl Render for QueueItem {
                         .flex_shrink_0()
                         .overflow_x_hidden()
                         .gap(px(11.0))
-                        .h(px(QUEUE_ITEM_HEIGHT))
+                        .h(ui_px(QUEUE_ITEM_HEIGHT, cx))
                         .p(px(11.0))
                         .cursor_pointer()
                         .relative()
                         // Default bottom border - always present
                         .border_b(px(1.0))
                         .border_color(theme.border_color)
-                        .when(item_state.is_being_dragged, |div| div.opacity(0.5))
+                        .when(item_state.is_being_dragged || is_unavailable, |div| {
+                            div.opacity(0.5)
+                        })
                         .when(is_current && !item_state.is_being_dragged, |div| {
                             div.bg(theme.queue_item_current)
                         })
-                        .on_click(move |_, _, cx| {
-                            cx.global::<PlaybackInterface>().jump(idx);
-                        })
+                        .on_click(cx.listener(|this, _, window, cx| this.click(window, cx)))
                         .when(!item_state.is_being_dragged, |div| {
                             div.hover(|div| div.bg(theme.queue_item_hover))
                                 .active(|div| div.bg(theme.queue_item_active))
                         })
                         .on_drag(DragData::new(idx, QUEUE_LIST_ID), move |_, _, _, cx| {
+                            if let Some(path) = track_path.clone() {
+                                external_drag::offer_files(vec![path]);
+                            }
                             DragPreview::new(cx, track_name.clone())
                         })
                         .drag_over::<DragData>(move |style, _, _, _| {
                             style.bg(gpui::rgba(0x88888822))
                         })
+                        .when(is_selected, |div| {
+                            div.child(
+                                div()
+                                    .absolute()
+                                    .left_0()
+                                    .top_0()
+                                    .bottom_0()
+                                    .w(px(3.0))
+                                    .bg(theme.button_primary),
+                            )
+                        })
+                        .when(
+                            is_selected && !is_current && !item_state.is_being_dragged,
+                            |div| div.bg(theme.queue_item_hover),
+                        )
                         .child(DropIndicator::with_state(
                             item_state.is_drop_target_before,
                             item_state.is_drop_target_after,
                             theme.button_primary,
                         ))
-                        .child(
-                            div()
-                                .id("album-art")
-                                .rounded(px(4.0))
-                                .bg(theme.album_art_background)
-                                .shadow_sm()
-                                .w(px(36.0))
-                                .h(px(36.0))
-                                .flex_shrink_0()
-                                .when(album_art.is_some(), |div| {
-                                    div.child(
-                                        img(album_art.unwrap())
-                                            .w(px(36.0))
-                                            .h(px(36.0))
-                                            .rounded(px(4.0)),
+                        .when(layout == QueueItemLayout::Compact, |this| {
+                            this.items_center().child(
+                                div()
+                                    .flex_1()
+                                    .min_w_0()
+                                    .text_size(ui_px(15.0, cx))
+                                    .whitespace_nowrap()
+                                    .text_ellipsis()
+                                    .child(compact_label(item)),
+                            )
+                        })
+                        .when(layout != QueueItemLayout::Compact, |this| {
+                            this.child(
+                                div()
+                                    .id("album-art")
+                                    .rounded(px(4.0))
+                                    .bg(theme.album_art_background)
+                                    .shadow_sm()
+                                    .w(px(36.0))
+                                    .h(px(36.0))
+                                    .flex_shrink_0()
+                                    .when(album_art.is_some(), |div| {
+                                        div.child(
+                                            img(album_art.unwrap())
+                                                .w(px(36.0))
+                                                .h(px(36.0))
+                                                .rounded(px(4.0)),
+                                        )
+                                    }),
+                            )
+                            .child(
+                                div()
+                                    .flex()
+                                    .flex_col()
+                                    .flex_1()
+                                    .min_w_0()
+                                    .line_height(rems(1.0))
+                                    .text_size(ui_px(15.0, cx))
+                                    .gap_1()
+                                    .overflow_x_hidden()
+                                    .child(
+                                        div()
+                                            .whitespace_nowrap()
+                                            .text_ellipsis()
+                                            .font_weight(FontWeight::EXTRA_BOLD)
+                                            .when_some(item.name.clone(), |this, string| {
+                                                this.child(string)
+                                            }),
                                     )
-                                }),
-                        )
-                        .child(
-                            div()
-                                .flex()
-                                .flex_col()
-                                .line_height(rems(1.0))
-                                .text_size(px(15.0))
-                                .gap_1()
-                                .overflow_x_hidden()
-                                .child(
+                                    .child(
+                                        div()
+                                            .whitespace_nowrap()
+                                            .text_ellipsis()
+                                            .when_some(item.artist_name.clone(), |this, string| {
+                                                this.child(string)
+                                            }),
+                                    ),
+                            )
+                        })
+                        .when(
+                            is_unavailable && layout != QueueItemLayout::Compact,
+                            |this| {
+                                this.child(
                                     div()
-                                        .text_ellipsis()
-                                        .font_weight(FontWeight::EXTRA_BOLD)
-                                        .when_some(item.name.clone(), |this, string| {
-                                            this.child(string)
-                                        }),
+                                        .flex_shrink_0()
+                                        .my_auto()
+                                        .text_size(ui_px(14.0, cx))
+                                        .text_color(theme.text_secondary)
+                                        .child("Unavailable"),
                                 )
-                                .child(
+                            },
+                        )
+                        .when_some(
+                            item.duration.filter(|_| layout == QueueItemLayout::Wide),
+                            |this, duration| {
+                                let duration = round_millis(duration);
+
+                                this.child(
                                     div()
-                                        .text_ellipsis()
-                                        .when_some(item.artist_name.clone(), |this, string| {
-                                            this.child(string)
-                                        }),
-                                ),
+                                        .flex_shrink_0()
+                                        .my_auto()
+                                        .text_size(ui_px(14.0, cx))
+                                        .text_color(theme.text_secondary)
+                                        .child(format!("{}:{:02}", duration / 60, duration % 60)),
+                                )
+                            },
                         ),
                 )
-                .child(menu().item(menu_item(
-                    "remove-item",
-                    Some(CROSS),
-                    "Remove from queue",
-                    move |_, _, cx| {
-                        let playback = cx.global::<PlaybackInterface>();
-                        playback.remove_item(idx);
-                    },
-                )))
+                .child(
+                    menu()
+                        .when(is_current, |menu| {
+                            menu.item(menu_check_item(
+                                "stop-after-current",
+                                stop_after_current,
+                                "Stop after this track",
+                                |_, _, cx| {
+                                    cx.global::<PlaybackInterface>().toggle_stop_after_current()
+                                },
+                            ))
+                        })
+                        .item(if remove_count > 1 {
+                            let selection = self.selection.clone();
+
+                            menu_item(
+                                "remove-item",
+                                Some(CROSS),
+                                format!("Remove {remove_count} from queue"),
+                                move |_, _, cx| remove_selected(&selection, cx),
+                            )
+                        } else {
+                            menu_item(
+                                "remove-item",
+                                Some(CROSS),
+                                "Remove from queue",
+                                move |_, _, cx| {
+                                    let playback = cx.global::<PlaybackInterface>();
+                                    playback.remove_item(idx);
+                                },
+                            )
+                        }),
+                )
                 .into_any_element()
         } else {
             // TODO: Skeleton for this
             div()
-                .h(px(QUEUE_ITEM_HEIGHT))
+                .h(ui_px(QUEUE_ITEM_HEIGHT, cx))
                 .border_t(px(1.0))
                 .border_color(theme.border_color)
                 .w_full()
@@ -223,6 +521,8 @@ pub struct Queue {
     show_queue: Entity<bool>,
     scroll_handle: UniformListScrollHandle,
     drag_drop_manager: Entity<DragDropListManager>,
+    selection: Entity<QueueSelection>,
+    focus_handle: FocusHandle,
 }
 
 impl Queue {
@@ -234,11 +534,21 @@ impl Queue {
 
             let config = DragDropListConfig::new(QUEUE_LIST_ID, px(QUEUE_ITEM_HEIGHT));
             let drag_drop_manager = DragDropListManager::new(cx, config);
+            let selection = cx.new(|_| QueueSelection::default());
 
-            cx.observe(&items, move |this: &mut Queue, _, cx| {
+            cx.observe(&items, move |this: &mut Queue, items, cx| {
                 this.views_model = cx.new(|_| FxHashMap::default());
                 this.render_counter = cx.new(|_| 0);
 
+                // the selection survives changes elsewhere in the queue, but not replacing it
+                let data = items.read(cx).data.clone();
+                let queue = data.read().expect("could not read queue");
+                this.selection.update(cx, |selection, cx| {
+                    if selection.retain_unchanged(&queue) {
+                        cx.notify();
+                    }
+                });
+
                 cx.notify();
             })
             .detach();
@@ -260,6 +570,8 @@ impl Queue {
                 show_queue,
                 scroll_handle: UniformListScrollHandle::new(),
                 drag_drop_manager,
+                selection,
+                focus_handle: cx.focus_handle(),
             }
         })
     }
@@ -269,6 +581,10 @@ impl Render for Queue {
     fn render(&mut self, _: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
         check_drag_cancelled(self.drag_drop_manager.clone(), cx);
 
+        let item_height = ui_px(QUEUE_ITEM_HEIGHT, cx);
+        self.drag_drop_manager
+            .update(cx, |manager, _| manager.set_item_height(item_height));
+
         let theme = cx.global::<Theme>();
         let queue = cx
             .global::<Models>()
@@ -284,6 +600,8 @@ impl Render for Queue {
         let render_counter = self.render_counter.clone();
         let scroll_handle = self.scroll_handle.clone();
         let drag_drop_manager = self.drag_drop_manager.clone();
+        let selection = self.selection.clone();
+        let focus_handle = self.focus_handle.clone();
 
         let queue_width = cx.global::<Models>().queue_width.clone();
 
@@ -296,6 +614,12 @@ impl Render for Queue {
                 div()
                     .h_full()
                     .w_full()
+                    .id("queue")
+                    .key_context("Queue")
+                    .track_focus(&self.focus_handle)
+                    .on_action(cx.listener(|this, _: &RemoveSelected, _, cx| {
+                        remove_selected(&this.selection, cx);
+                    }))
                     .border_l(px(1.0))
                     .border_color(theme.border_color)
                     .pb(px(0.0))
@@ -321,12 +645,38 @@ impl Render for Queue {
                             .pb(px(12.0))
                             .px(px(12.0))
                             .flex()
+                            .items_center()
                             .child(
                                 div()
-                                    .line_height(px(26.0))
+                                    .line_height(ui_px(26.0, cx))
                                     .font_weight(FontWeight::BOLD)
-                                    .text_size(px(26.0))
+                                    .text_size(ui_px(26.0, cx))
                                     .child("Queue"),
+                            )
+                            .child(
+                                context("queue-overflow")
+                                    .button(MouseButton::Left)
+                                    .ml_auto()
+                                    .with(nav_button("queue-overflow-button", DOTS))
+                                    .child(
+                                        menu()
+                                            .item(menu_item(
+                                                "save-session",
+                                                None::<&str>,
+                                                "Save queue as session…",
+                                                |_, _, cx| {
+                                                    if let Err(err) = save_session(cx) {
+                                                        error!("Failed to save session: {}", err);
+                                                    }
+                                                },
+                                            ))
+                                            .item(menu_item(
+                                                "load-session",
+                                                None::<&str>,
+                                                "Load session…",
+                                                |_, _, cx| load_session(cx),
+                                            )),
+                                    ),
                             ),
                     )
                     .child(
@@ -559,6 +909,12 @@ impl Render for Queue {
                             ))
                             .on_drop(cx.listener(
                                 move |this: &mut Queue, drag_data: &DragData, _, cx| {
+                                    // the selected items would end up in different places
+                                    this.selection.update(cx, |selection, cx| {
+                                        selection.clear();
+                                        cx.notify();
+                                    });
+
                                     handle_drop(
                                         this.drag_drop_manager.clone(),
                                         drag_data,
@@ -680,6 +1036,8 @@ impl Render for Queue {
                                                 }
 
                                                 let drag_drop_manager = drag_drop_manager.clone();
+                                                let selection = selection.clone();
+                                                let focus_handle = focus_handle.clone();
 
                                                 div().child(create_or_retrieve_view(
                                                     &views_model,
@@ -690,6 +1048,8 @@ impl Render for Queue {
                                                             Some(item),
                                                             idx,
                                                             drag_drop_manager,
+                                                            selection,
+                                                            focus_handle,
                                                         )
                                                     },
                                                     cx,
@@ -710,7 +1070,26 @@ impl Render for Queue {
                                 "queue_scrollbar",
                                 scroll_handle,
                                 RightPad::Pad,
-                            )),
+                            ))
+                            .when(queue_len == 0, |this| {
+                                this.child(
+                                    div().absolute().inset_0().child(
+                                        empty_state("queue-empty", "Queue is empty")
+                                            .icon(PLAYLIST)
+                                            .description(
+                                                "Play something from your library, or drag \
+                                                 tracks and albums here.",
+                                            )
+                                            .action("Browse library", |_, _, cx| {
+                                                let switcher_model =
+                                                    cx.global::<Models>().switcher_model.clone();
+                                                switcher_model.update(cx, |_, cx| {
+                                                    cx.emit(ViewSwitchMessage::Albums);
+                                                });
+                                            }),
+                                    ),
+                                )
+                            }),
                     ),
             )
     }
@@ -737,3 +1116,43 @@ impl Queue {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn layout(width: f32) -> QueueItemLayout {
+        QueueItemLayout::for_width(px(width))
+    }
+
+    #[test]
+    fn lays_out_narrow_queues_compactly() {
+        let compact_below = f32::from(COMPACT_BELOW_WIDTH);
+
+        assert_eq!(layout(0.0), QueueItemLayout::Compact);
+        assert_eq!(layout(compact_below - 0.1), QueueItemLayout::Compact);
+    }
+
+    #[test]
+    fn shows_album_art_from_the_compact_breakpoint() {
+        let compact_below = f32::from(COMPACT_BELOW_WIDTH);
+        let duration_from = f32::from(DURATION_FROM_WIDTH);
+
+        assert_eq!(layout(compact_below), QueueItemLayout::Regular);
+        assert_eq!(layout(compact_below + 0.1), QueueItemLayout::Regular);
+        assert_eq!(layout(duration_from - 0.1), QueueItemLayout::Regular);
+        assert_eq!(
+            QueueItemLayout::for_width(DEFAULT_QUEUE_WIDTH),
+            QueueItemLayout::Regular
+        );
+    }
+
+    #[test]
+    fn shows_durations_from_the_wide_breakpoint() {
+        let duration_from = f32::from(DURATION_FROM_WIDTH);
+
+        assert_eq!(layout(duration_from), QueueItemLayout::Wide);
+        assert_eq!(layout(duration_from + 0.1), QueueItemLayout::Wide);
+        assert_eq!(layout(2000.0), QueueItemLayout::Wide);
+    }
+}
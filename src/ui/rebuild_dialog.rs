@@ -0,0 +1,166 @@
+use gpui::{
+    App, FontWeight, IntoElement, ParentElement, RenderOnce, SharedString, Styled, Window, div,
+    prelude::FluentBuilder, px,
+};
+
+use crate::library::{
+    db::LibraryAccess,
+    scan::{ScanEvent, ScanInterface},
+};
+
+use super::{
+    components::{
+        button::{ButtonIntent, button},
+        modal::modal,
+    },
+    models::Models,
+    theme::Theme,
+    toasts::show_toast,
+    util::ui_px,
+};
+
+/// A rough estimate of how many tracks are scanned per second during a rebuild, used to tell the
+/// user how long it's going to take.
+const ESTIMATED_TRACKS_PER_SECOND: i64 = 25;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum RebuildReason {
+    /// The user asked to rebuild the library.
+    Requested,
+    /// The scanning process has changed since the library was last scanned. The regular startup
+    /// scan is held back until the user has decided whether to rebuild.
+    FormatUpdated,
+}
+
+fn describe_duration(track_count: i64) -> SharedString {
+    let minutes = track_count / ESTIMATED_TRACKS_PER_SECOND / 60;
+
+    match minutes {
+        0 => "This should take less than a minute.".into(),
+        1 => "This should take about a minute.".into(),
+        minutes => format!("This should take about {minutes} minutes.").into(),
+    }
+}
+
+fn close(cx: &mut App, reason: RebuildReason, rebuild: bool) {
+    let rebuild_prompt = cx.global::<Models>().rebuild_prompt.clone();
+    rebuild_prompt.write(cx, None);
+
+    let scan_running = !matches!(
+        cx.global::<Models>().scan_state.read(cx),
+        ScanEvent::ScanCompleteIdle | ScanEvent::ScanCompleteWatching | ScanEvent::Analyzing { .. }
+    );
+    let scanner = cx.global::<ScanInterface>();
+
+    if rebuild && scan_running {
+        // force-scans are ignored while another scan is running
+        show_toast(
+            cx,
+            "The library is already being scanned. Try again once it's finished.",
+        );
+    } else if rebuild {
+        scanner.force_scan();
+    } else if reason == RebuildReason::FormatUpdated {
+        scanner.scan();
+    }
+}
+
+/// Asks the user to confirm a library rebuild (see [ScanInterface::force_scan]), explaining what
+/// it does and roughly how long it'll take.
+#[derive(IntoElement)]
+pub struct RebuildDialog {
+    reason: RebuildReason,
+}
+
+impl RenderOnce for RebuildDialog {
+    fn render(self, _: &mut Window, cx: &mut App) -> impl IntoElement {
+        let reason = self.reason;
+        let track_count = cx
+            .get_track_stats()
+            .map(|stats| stats.track_count)
+            .unwrap_or_default();
+        let theme = cx.global::<Theme>();
+
+        let (title, cancel_label) = match reason {
+            RebuildReason::Requested => ("Rebuild library metadata?", "Cancel"),
+            RebuildReason::FormatUpdated => (
+                "Library format updated — a one-time rebuild is needed",
+                "Not now",
+            ),
+        };
+
+        modal()
+            .on_exit(move |_, cx| close(cx, reason, false))
+            .child(
+                div()
+                    .w(px(450.0))
+                    .p(px(20.0))
+                    .flex()
+                    .flex_col()
+                    .gap(px(8.0))
+                    .child(
+                        div()
+                            .font_weight(FontWeight::BOLD)
+                            .text_size(ui_px(20.0, cx))
+                            .child(title),
+                    )
+                    .child(
+                        div()
+                            .text_sm()
+                            .text_color(theme.text_secondary)
+                            .child(match reason {
+                                RebuildReason::Requested => {
+                                    "Every file in your library will be read again, and all album \
+                                    information will be re-created from scratch. This is only \
+                                    needed if you've changed how your files are tagged, or if \
+                                    albums are showing up incorrectly."
+                                }
+                                RebuildReason::FormatUpdated => {
+                                    "This version of Hummingbird reads your files differently. \
+                                    Every file in your library needs to be read again, and all \
+                                    album information will be re-created, for the changes to \
+                                    take effect."
+                                }
+                            }),
+                    )
+                    .child(
+                        div()
+                            .text_sm()
+                            .text_color(theme.text_secondary)
+                            .child("Your playlists and liked songs will be kept."),
+                    )
+                    .when(track_count > 0, |this| {
+                        this.child(
+                            div()
+                                .text_sm()
+                                .text_color(theme.text_secondary)
+                                .child(describe_duration(track_count)),
+                        )
+                    })
+                    .child(
+                        div()
+                            .flex()
+                            .justify_end()
+                            .gap(px(8.0))
+                            .mt(px(8.0))
+                            .child(
+                                button()
+                                    .id("rebuild-cancel")
+                                    .child(cancel_label)
+                                    .on_click(move |_, _, cx| close(cx, reason, false)),
+                            )
+                            .child(
+                                button()
+                                    .id("rebuild-confirm")
+                                    .intent(ButtonIntent::Primary)
+                                    .child("Rebuild")
+                                    .on_click(move |_, _, cx| close(cx, reason, true)),
+                            ),
+                    ),
+            )
+    }
+}
+
+pub fn rebuild_dialog(reason: RebuildReason) -> RebuildDialog {
+    RebuildDialog { reason }
+}
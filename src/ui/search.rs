@@ -4,10 +4,14 @@ pub mod model;
 use std::collections::VecDeque;
 
 use gpui::*;
-use model::SearchModel;
+use model::{DismissSearch, SearchModel};
 
 use super::{
-    components::modal::modal, global_actions::Search, library::ViewSwitchMessage, models::Models,
+    components::modal::modal,
+    global_actions::Search,
+    library::ViewSwitchMessage,
+    models::Models,
+    shortcuts::{ActionGroup, describe_action},
 };
 
 pub struct SearchView {
@@ -23,6 +27,7 @@ impl SearchView {
             let show_clone = show.clone();
             let search = SearchModel::new(cx, &show);
 
+            describe_action(cx, ActionGroup::Navigation, "Search", Search);
             App::on_action(cx, move |_: &Search, cx| {
                 show_clone.update(cx, |m, cx| {
                     *m = true;
@@ -41,6 +46,14 @@ impl SearchView {
             )
             .detach();
 
+            cx.subscribe(
+                &search,
+                |this: &mut SearchView, _, _: &DismissSearch, cx| {
+                    this.reset(cx);
+                },
+            )
+            .detach();
+
             cx.observe(&show, |_, _, cx| {
                 cx.notify();
             })
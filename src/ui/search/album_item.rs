@@ -9,17 +9,28 @@ pub struct AlbumPaletteItem {
     pub id: u32,
     pub title: String,
     pub artist: String,
+    /// The comments on the album's tracks, so that albums can be found by them.
+    pub comments: Option<String>,
 }
 
 impl AlbumPaletteItem {
-    pub fn new(id: u32, title: String, artist: String) -> Self {
-        Self { id, title, artist }
+    pub fn new(id: u32, title: String, artist: String, comments: Option<String>) -> Self {
+        Self {
+            id,
+            title,
+            artist,
+            comments,
+        }
     }
 
-    pub fn from_search_results(results: Vec<(u32, String, String)>) -> Vec<Arc<AlbumPaletteItem>> {
+    pub fn from_search_results(
+        results: Vec<(u32, String, String, Option<String>)>,
+    ) -> Vec<Arc<AlbumPaletteItem>> {
         results
             .into_iter()
-            .map(|(id, title, artist)| Arc::new(AlbumPaletteItem::new(id, title, artist)))
+            .map(|(id, title, artist, comments)| {
+                Arc::new(AlbumPaletteItem::new(id, title, artist, comments))
+            })
             .collect()
     }
 
@@ -40,4 +51,14 @@ impl PaletteItem for AlbumPaletteItem {
     fn right_content(&self, _cx: &mut App) -> Option<SharedString> {
         Some(self.artist.clone().into())
     }
+
+    fn is_primary_match(&self, query: &str) -> bool {
+        let title = self.title.to_lowercase();
+        let artist = self.artist.to_lowercase();
+
+        query.split_whitespace().all(|word| {
+            let word = word.to_lowercase();
+            title.contains(&word) || artist.contains(&word)
+        })
+    }
 }
@@ -2,21 +2,58 @@ use std::sync::Arc;
 
 use gpui::{App, AppContext, Context, Entity, EventEmitter, IntoElement, Render, Window};
 use nucleo::Utf32String;
-use tracing::debug;
+use tracing::{debug, error};
 
 use crate::{
     library::{db::LibraryAccess, scan::ScanEvent},
     ui::{
-        components::{input::EnrichedInputAction, palette::Palette},
+        components::{
+            input::EnrichedInputAction,
+            palette::{AcceptIntent, Palette},
+        },
+        large_queue::{ADD_TO_QUEUE, PLAY_NOW, QueueEntry, add_to_queue, play_now},
         library::ViewSwitchMessage,
         models::Models,
+        toasts::show_toast,
     },
 };
 
 use super::album_item::AlbumPaletteItem;
 
+/// The shortcut for adding an album to the queue, as shown in the search footer.
+#[cfg(target_os = "macos")]
+const QUEUE_SHORTCUT: &str = "Cmd+Enter";
+#[cfg(not(target_os = "macos"))]
+const QUEUE_SHORTCUT: &str = "Ctrl+Enter";
+
 type MatcherFunc = Box<dyn Fn(&Arc<AlbumPaletteItem>, &mut App) -> Utf32String + 'static>;
-type OnAccept = Box<dyn Fn(&Arc<AlbumPaletteItem>, &mut App) + 'static>;
+type OnAccept = Box<dyn Fn(&Arc<AlbumPaletteItem>, AcceptIntent, &mut App) + 'static>;
+
+/// Emitted when an item was accepted without navigating anywhere, so the search should close.
+pub struct DismissSearch;
+
+/// Looks up the tracks on an album, in order, ready to be queued.
+fn album_queue_entries(cx: &mut App, album_id: i64) -> Option<Vec<QueueEntry>> {
+    match cx.list_tracks_in_album(album_id) {
+        Ok(tracks) if !tracks.is_empty() => Some(
+            tracks
+                .iter()
+                .map(|track| {
+                    QueueEntry::new(track.location.clone(), Some(track.id), track.album_id)
+                })
+                .collect(),
+        ),
+        Ok(_) => {
+            show_toast(cx, "This album has no tracks.");
+            None
+        }
+        Err(err) => {
+            error!(?err, "Failed to retrieve tracks for album {album_id}");
+            show_toast(cx, "Couldn't load this album.");
+            None
+        }
+    }
+}
 
 pub struct SearchModel {
     palette: Entity<Palette<AlbumPaletteItem, MatcherFunc, OnAccept>>,
@@ -35,20 +72,53 @@ impl SearchModel {
 
             let weak_self = cx.weak_entity();
 
-            let matcher: MatcherFunc =
-                Box::new(|album, _| Utf32String::from(format!("{} {}", album.title, album.artist)));
+            let matcher: MatcherFunc = Box::new(|album, _| match &album.comments {
+                Some(comments) => {
+                    Utf32String::from(format!("{} {} {}", album.title, album.artist, comments))
+                }
+                None => Utf32String::from(format!("{} {}", album.title, album.artist)),
+            });
+
+            let on_accept: OnAccept = Box::new(move |album, intent, cx| {
+                let album_id = album.id as i64;
 
-            let on_accept: OnAccept = Box::new(move |album, cx| {
-                let event = ViewSwitchMessage::Release(album.id as i64);
+                match intent {
+                    AcceptIntent::Open => {
+                        if let Some(search_model) = weak_self.upgrade() {
+                            search_model.update(cx, |_: &mut SearchModel, cx| {
+                                cx.emit(ViewSwitchMessage::Release(album_id));
+                            });
+                        }
+                        return;
+                    }
+                    AcceptIntent::Queue => {
+                        if let Some(entries) = album_queue_entries(cx, album_id) {
+                            add_to_queue(cx, entries);
+                            show_toast(cx, format!("Added {} to the queue.", album.title));
+                        }
+                    }
+                    AcceptIntent::Play => {
+                        if let Some(entries) = album_queue_entries(cx, album_id) {
+                            play_now(cx, entries, None);
+                        }
+                    }
+                }
 
                 if let Some(search_model) = weak_self.upgrade() {
                     search_model.update(cx, |_: &mut SearchModel, cx| {
-                        cx.emit(event);
+                        cx.emit(DismissSearch);
                     });
                 }
             });
 
             let palette = Palette::new(cx, albums, matcher, on_accept, show);
+            palette.update(cx, |palette, _| {
+                palette.set_hints(vec![
+                    ("Enter".into(), "Open".into()),
+                    (QUEUE_SHORTCUT.into(), ADD_TO_QUEUE.into()),
+                    ("Shift+Enter".into(), PLAY_NOW.into()),
+                ]);
+            });
 
             let search_model = SearchModel { palette };
 
@@ -99,6 +169,7 @@ impl SearchModel {
 
 impl EventEmitter<String> for SearchModel {}
 impl EventEmitter<ViewSwitchMessage> for SearchModel {}
+impl EventEmitter<DismissSearch> for SearchModel {}
 impl EventEmitter<EnrichedInputAction> for SearchModel {}
 
 impl Render for SearchModel {
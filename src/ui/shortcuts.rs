@@ -0,0 +1,196 @@
+use std::sync::Arc;
+
+use gpui::{
+    Action, App, AppContext, Context, Entity, Global, IntoElement, ParentElement, Render,
+    SharedString, Styled, Window, div, px,
+};
+use nucleo::Utf32String;
+
+use crate::ui::components::{
+    modal::modal,
+    palette::{AcceptIntent, FinderItemLeft, Palette, PaletteItem},
+};
+
+/// The part of the app an action belongs to, used to group actions in the shortcut list.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+pub enum ActionGroup {
+    General,
+    Playback,
+    Navigation,
+    Library,
+    Interface,
+}
+
+impl ActionGroup {
+    fn name(&self) -> &'static str {
+        match self {
+            ActionGroup::General => "General",
+            ActionGroup::Playback => "Playback",
+            ActionGroup::Navigation => "Navigation",
+            ActionGroup::Library => "Library",
+            ActionGroup::Interface => "Interface",
+        }
+    }
+}
+
+/// An action, along with the name it's listed under in the shortcut list.
+pub struct ActionInfo {
+    group: ActionGroup,
+    name: SharedString,
+    action: Box<dyn Action + Sync>,
+}
+
+impl PartialEq for ActionInfo {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name && self.action.partial_eq(&(*other.action))
+    }
+}
+
+impl PaletteItem for ActionInfo {
+    fn left_content(&self, _: &mut App) -> Option<FinderItemLeft> {
+        Some(FinderItemLeft::Text(self.group.name().into()))
+    }
+
+    fn middle_content(&self, _: &mut App) -> SharedString {
+        self.name.clone()
+    }
+
+    fn right_content(&self, cx: &mut App) -> Option<SharedString> {
+        Some(binding_label(cx, &(*self.action)).unwrap_or_else(|| "—".into()))
+    }
+}
+
+/// Every action that has been described with [`describe_action`], in the order they were
+/// described.
+#[derive(Default)]
+struct ActionRegistry(Vec<Arc<ActionInfo>>);
+
+impl Global for ActionRegistry {}
+
+/// Lists an action in the shortcut list. This should be called wherever the action is registered
+/// or bound, so that the list can't get out of date.
+pub fn describe_action(
+    cx: &mut App,
+    group: ActionGroup,
+    name: impl Into<SharedString>,
+    action: impl Action + Sync,
+) {
+    cx.default_global::<ActionRegistry>()
+        .0
+        .push(Arc::new(ActionInfo {
+            group,
+            name: name.into(),
+            action: Box::new(action),
+        }));
+}
+
+/// Returns the keys currently bound to the action, formatted for display.
+pub fn binding_label(cx: &App, action: &dyn Action) -> Option<SharedString> {
+    cx.key_bindings()
+        .borrow()
+        .bindings_for_action(action)
+        .last()
+        .map(|binding| {
+            binding
+                .keystrokes()
+                .iter()
+                .map(|key| key.to_string())
+                .collect::<Vec<String>>()
+                .join(" + ")
+                .into()
+        })
+}
+
+/// Every described action, grouped by [`ActionGroup`].
+fn described_actions(cx: &App) -> Vec<Arc<ActionInfo>> {
+    let mut actions = cx
+        .try_global::<ActionRegistry>()
+        .map(|registry| registry.0.clone())
+        .unwrap_or_default();
+
+    // stable, so actions stay in the order they were described within each group
+    actions.sort_by_key(|info| info.group);
+    actions
+}
+
+type MatcherFunc = Box<dyn Fn(&Arc<ActionInfo>, &mut App) -> Utf32String + 'static>;
+type OnAccept = Box<dyn Fn(&Arc<ActionInfo>, AcceptIntent, &mut App) + 'static>;
+
+/// A searchable list of every action and the keys bound to it. Choosing an action runs it.
+pub struct ShortcutHelp {
+    show: Entity<bool>,
+    palette: Entity<Palette<ActionInfo, MatcherFunc, OnAccept>>,
+}
+
+impl ShortcutHelp {
+    pub fn new(cx: &mut App, show: Entity<bool>) -> Entity<Self> {
+        cx.new(|cx| {
+            // bindings can change while the app is running, so the list is rebuilt every time
+            // it's opened
+            cx.observe(&show, |this: &mut Self, show, cx| {
+                let actions = if *show.read(cx) {
+                    described_actions(cx)
+                } else {
+                    Vec::new()
+                };
+
+                this.palette.update(cx, |this, cx| {
+                    cx.emit(actions);
+                    this.reset(cx);
+                });
+
+                cx.notify();
+            })
+            .detach();
+
+            // the keys are searchable too, so that "space" finds what space does
+            let matcher: MatcherFunc = Box::new(|info, cx| {
+                let keys = binding_label(cx, &(*info.action)).unwrap_or_default();
+                format!("{} {} {}", info.name, info.group.name(), keys).into()
+            });
+
+            let show_clone = show.clone();
+            let on_accept: OnAccept = Box::new(move |info, _, cx| {
+                let info = info.clone();
+                show_clone.write(cx, false);
+
+                // run the action once the list is closed, so that it doesn't go to the list's
+                // text input
+                cx.defer(move |cx| cx.dispatch_action(&(*info.action)));
+            });
+
+            let palette = Palette::new(cx, Vec::new(), matcher, on_accept, &show);
+
+            Self { show, palette }
+        })
+    }
+}
+
+impl Render for ShortcutHelp {
+    fn render(&mut self, window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let show = self.show.clone();
+        let palette = self.palette.clone();
+        let show_read = *self.show.read(cx);
+
+        if show_read {
+            cx.update_entity(&palette, |palette, cx| {
+                palette.focus(window, cx);
+            });
+
+            modal()
+                .child(div().w(px(550.0)).h(px(400.0)).child(palette.clone()))
+                .on_exit(move |_, cx| {
+                    show.update(cx, |show, cx| {
+                        *show = false;
+                        cx.update_entity(&palette, |palette, cx| {
+                            palette.reset(cx);
+                        });
+                        cx.notify();
+                    })
+                })
+                .into_any_element()
+        } else {
+            div().into_any_element()
+        }
+    }
+}
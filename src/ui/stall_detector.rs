@@ -0,0 +1,50 @@
+use std::{
+    env,
+    time::{Duration, Instant},
+};
+
+use gpui::App;
+use tracing::warn;
+
+/// How often the detector checks in on the UI thread.
+const TICK: Duration = Duration::from_millis(16);
+
+/// The threshold used in debug builds when `HB_STALL_THRESHOLD_MS` isn't set.
+const DEFAULT_THRESHOLD_MS: u64 = 100;
+
+/// Returns how long the UI thread has to be blocked for before it's reported, or None if stall
+/// detection is turned off. It's on by default in debug builds; set `HB_STALL_THRESHOLD_MS` to
+/// turn it on in release builds or to change the threshold.
+fn threshold() -> Option<Duration> {
+    env::var("HB_STALL_THRESHOLD_MS")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .or(cfg!(debug_assertions).then_some(DEFAULT_THRESHOLD_MS))
+        .map(Duration::from_millis)
+}
+
+/// Starts a task on the UI thread that logs a warning whenever the thread was blocked long enough
+/// to freeze the interface, such as by a query run through
+/// [`LibraryAccess`](crate::library::db::LibraryAccess).
+pub fn start_stall_detector(cx: &mut App) {
+    let Some(threshold) = threshold() else {
+        return;
+    };
+
+    cx.spawn(async move |cx| {
+        loop {
+            let started = Instant::now();
+            cx.background_executor().timer(TICK).await;
+
+            // the task can only resume once the UI thread is free, so any time past the tick was
+            // spent blocked
+            let stalled = started.elapsed().saturating_sub(TICK);
+
+            if stalled >= threshold {
+                let stalled_ms = stalled.as_millis() as u64;
+                warn!(stalled_ms, "UI thread was blocked for {stalled_ms}ms");
+            }
+        }
+    })
+    .detach();
+}
@@ -1,10 +1,16 @@
-use std::{fs::File, io::BufReader, path::PathBuf, sync::mpsc::channel, time::Duration};
+use std::{path::PathBuf, sync::mpsc::channel, time::Duration};
 
 use gpui::{App, AppContext, AsyncApp, EventEmitter, Global, Rgba, rgb, rgba};
 use notify::{Event, RecursiveMode, Watcher};
 use serde::Deserialize;
 use tracing::{error, info, warn};
 
+use crate::settings::persist::{self, Recovery};
+
+/// The format version of the theme file. The theme is only ever written by hand, so this is just
+/// checked when it's read.
+const THEME_VERSION: u32 = 1;
+
 #[derive(Deserialize, Clone)]
 #[serde(default)]
 pub struct Theme {
@@ -187,19 +193,10 @@ impl Default for Theme {
 
 impl Global for Theme {}
 
-pub fn create_theme(path: &PathBuf) -> Theme {
-    if let Ok(file) = File::open(path) {
-        let reader = BufReader::new(file);
-
-        if let Ok(theme) = serde_json::from_reader(reader) {
-            theme
-        } else {
-            warn!("Theme file exists but it could not be loaded, using default");
-            Theme::default()
-        }
-    } else {
-        Theme::default()
-    }
+/// Loads the theme file, recovering it if it's been corrupted.
+fn load_theme(path: &PathBuf) -> (Theme, Option<Recovery>) {
+    let loaded = persist::load(path, THEME_VERSION);
+    (loaded.value.unwrap_or_default(), loaded.recovery)
 }
 
 #[derive(PartialEq, Clone)]
@@ -212,8 +209,11 @@ pub struct ThemeWatcher(pub Box<dyn Watcher>);
 
 impl Global for ThemeWatcher {}
 
-pub fn setup_theme(cx: &mut App, path: PathBuf) {
-    cx.set_global(create_theme(&path));
+/// Loads the theme and reloads it whenever the file changes. Returns what had to be done to
+/// recover the theme file, if it couldn't be read.
+pub fn setup_theme(cx: &mut App, path: PathBuf) -> Option<Recovery> {
+    let (theme, recovery) = load_theme(&path);
+    cx.set_global(theme);
     let theme_transmitter = cx.new(|_| ThemeEvTransmitter);
 
     cx.subscribe(&theme_transmitter, |_, theme, cx| {
@@ -240,7 +240,15 @@ pub fn setup_theme(cx: &mut App, path: PathBuf) {
                                 match v.kind {
                                     notify::EventKind::Create(_) | notify::EventKind::Modify(_) => {
                                         info!("Theme changed, updating...");
-                                        let theme = create_theme(&path);
+                                        // keep the current theme until a mistake made while
+                                        // editing it has been fixed
+                                        let theme = match persist::read(&path, THEME_VERSION) {
+                                            Ok(theme) => theme.unwrap_or_default(),
+                                            Err(err) => {
+                                                warn!("Theme file could not be loaded: {err:?}");
+                                                continue;
+                                            }
+                                        };
                                         theme_transmitter
                                             .update(cx, move |_, m| {
                                                 m.emit(theme);
@@ -276,4 +284,6 @@ pub fn setup_theme(cx: &mut App, path: PathBuf) {
     } else if let Err(e) = watcher {
         warn!("failed to watch settings directory: {:?}", e);
     }
+
+    recovery
 }
@@ -0,0 +1,225 @@
+use std::{rc::Rc, time::Duration};
+
+use gpui::*;
+
+use crate::ui::{
+    components::button::{ButtonIntent, button},
+    models::Models,
+    theme::Theme,
+};
+
+/// How long a toast stays on screen before it is dismissed automatically.
+const TOAST_DURATION: Duration = Duration::from_secs(4);
+
+/// How long a toast with a button stays on screen, so that there's time to read it and decide.
+const ACTION_TOAST_DURATION: Duration = Duration::from_secs(10);
+
+/// A button shown on a toast. Clicking it runs `on_click` and dismisses the toast.
+#[derive(Clone)]
+struct ToastAction {
+    label: SharedString,
+    on_click: Rc<dyn Fn(&mut App)>,
+}
+
+#[derive(Clone)]
+pub struct Toast {
+    id: usize,
+    message: SharedString,
+    action: Option<ToastAction>,
+}
+
+/// The list of toasts that are currently being displayed.
+#[derive(Default)]
+pub struct Toasts {
+    toasts: Vec<Toast>,
+    next_id: usize,
+}
+
+impl Toasts {
+    fn push(&mut self, message: SharedString, action: Option<ToastAction>) -> usize {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.toasts.push(Toast {
+            id,
+            message,
+            action,
+        });
+        id
+    }
+
+    fn remove(&mut self, id: usize) {
+        self.toasts.retain(|toast| toast.id != id);
+    }
+}
+
+/// Shows a short, non-blocking message to the user. The toast is dismissed automatically after a
+/// few seconds, or when it is clicked.
+pub fn show_toast(cx: &mut App, message: impl Into<SharedString>) {
+    let id = push_toast(cx, message.into(), None);
+    dismiss_later(cx, id, TOAST_DURATION);
+}
+
+/// Shows a toast with a button next to the message. The toast stays on screen for longer than
+/// usual, and is dismissed when the button or the toast is clicked.
+pub fn show_toast_with_action(
+    cx: &mut App,
+    message: impl Into<SharedString>,
+    label: impl Into<SharedString>,
+    on_click: impl Fn(&mut App) + 'static,
+) {
+    let action = ToastAction {
+        label: label.into(),
+        on_click: Rc::new(on_click),
+    };
+
+    let id = push_toast(cx, message.into(), Some(action));
+    dismiss_later(cx, id, ACTION_TOAST_DURATION);
+}
+
+/// Shows a toast that stays on screen until it's clicked, for errors that shouldn't be missed.
+pub fn show_error_toast(cx: &mut App, message: impl Into<SharedString>) {
+    push_toast(cx, message.into(), None);
+}
+
+fn push_toast(cx: &mut App, message: SharedString, action: Option<ToastAction>) -> usize {
+    let toasts = cx.global::<Models>().toasts.clone();
+    toasts.update(cx, |toasts, cx| {
+        let id = toasts.push(message, action);
+        cx.notify();
+        id
+    })
+}
+
+fn dismiss_later(cx: &mut App, id: usize, after: Duration) {
+    let toasts = cx.global::<Models>().toasts.clone();
+
+    cx.spawn(async move |cx| {
+        cx.background_executor().timer(after).await;
+
+        toasts
+            .update(cx, |toasts, cx| {
+                toasts.remove(id);
+                cx.notify();
+            })
+            .ok();
+    })
+    .detach();
+}
+
+/// A toast for a task that takes a while. It stays on screen until the task is finished, and its
+/// message can be replaced to report progress in the meantime.
+pub struct ProgressToast {
+    id: usize,
+}
+
+impl ProgressToast {
+    pub fn show(cx: &mut App, message: impl Into<SharedString>) -> Self {
+        Self {
+            id: push_toast(cx, message.into(), None),
+        }
+    }
+
+    pub fn update(&self, cx: &mut App, message: impl Into<SharedString>) {
+        let toasts = cx.global::<Models>().toasts.clone();
+        let message = message.into();
+
+        toasts.update(cx, |toasts, cx| {
+            if let Some(toast) = toasts.toasts.iter_mut().find(|toast| toast.id == self.id) {
+                toast.message = message;
+                cx.notify();
+            }
+        });
+    }
+
+    /// Replaces the message one last time, and dismisses the toast after the usual delay.
+    pub fn finish(self, cx: &mut App, message: impl Into<SharedString>) {
+        self.update(cx, message);
+        dismiss_later(cx, self.id, TOAST_DURATION);
+    }
+}
+
+pub struct ToastView {
+    toasts: Entity<Toasts>,
+}
+
+impl ToastView {
+    pub fn new(cx: &mut App) -> Entity<Self> {
+        cx.new(|cx| {
+            let toasts = cx.global::<Models>().toasts.clone();
+
+            cx.observe(&toasts, |_, _, cx| {
+                cx.notify();
+            })
+            .detach();
+
+            Self { toasts }
+        })
+    }
+}
+
+impl Render for ToastView {
+    fn render(&mut self, window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let toasts = self.toasts.read(cx).toasts.clone();
+
+        if toasts.is_empty() {
+            return div().into_any_element();
+        }
+
+        let theme = cx.global::<Theme>();
+        let size = window.viewport_size();
+
+        anchored()
+            .position(point(size.width - px(24.0), size.height - px(96.0)))
+            .anchor(Corner::BottomRight)
+            .child(deferred(div().flex().flex_col().gap(px(8.0)).children(
+                toasts.into_iter().map(|toast| {
+                    let toasts = self.toasts.clone();
+                    let id = toast.id;
+
+                    div()
+                        .id(("toast", id))
+                        .occlude()
+                        .max_w(px(360.0))
+                        .px(px(14.0))
+                        .py(px(10.0))
+                        .rounded(px(6.0))
+                        .border_1()
+                        .border_color(theme.elevated_border_color)
+                        .bg(theme.elevated_background)
+                        .shadow_md()
+                        .text_sm()
+                        .cursor_pointer()
+                        .flex()
+                        .items_center()
+                        .gap(px(12.0))
+                        .child(div().flex_shrink().child(toast.message))
+                        .when_some(toast.action, |this, action| {
+                            let toasts = self.toasts.clone();
+
+                            this.child(
+                                button()
+                                    .id(("toast-action", id))
+                                    .flex_shrink_0()
+                                    .intent(ButtonIntent::Primary)
+                                    .child(action.label)
+                                    .on_click(move |_, _, cx| {
+                                        cx.stop_propagation();
+                                        (action.on_click)(cx);
+                                        toasts.update(cx, |toasts, cx| {
+                                            toasts.remove(id);
+                                            cx.notify();
+                                        });
+                                    }),
+                            )
+                        })
+                        .on_click(move |_, _, cx| {
+                            toasts.update(cx, |toasts, cx| {
+                                toasts.remove(id);
+                                cx.notify();
+                            });
+                        })
+                }),
+            )))
+            .into_any_element()
+    }
+}
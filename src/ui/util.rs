@@ -3,11 +3,13 @@ use std::sync::Arc;
 use gpui::{
     AnyElement, App, Bounds, Element, ElementId, Entity, GlobalElementId, InspectorElementId,
     IntoElement, LayoutId, ParentElement, Pixels, Render, RenderImage, Stateful, StyleRefinement,
-    Styled, Window,
+    Styled, Window, px,
 };
 use rustc_hash::FxHashMap;
 use tracing::debug;
 
+use crate::settings::SettingsGlobal;
+
 pub fn prune_views<T>(
     views_model: &Entity<FxHashMap<usize, Entity<T>>>,
     render_counter: &Entity<usize>,
@@ -85,6 +87,23 @@ pub fn drop_image_from_app(cx: &mut App, image: Arc<RenderImage>) {
     }
 }
 
+/// Returns the interface scale set by the user (see
+/// [InterfaceSettings](crate::settings::interface::InterfaceSettings)).
+pub fn ui_scale(cx: &App) -> f32 {
+    cx.global::<SettingsGlobal>()
+        .model
+        .read(cx)
+        .interface
+        .scale()
+}
+
+/// Scales an absolute size by the interface scale. Anything sized in `rems` is already scaled, but
+/// text sizes given in pixels, and the heights of list rows containing text, should go through
+/// this so they stay in proportion with each other.
+pub fn ui_px(value: f32, cx: &App) -> Pixels {
+    px(value * ui_scale(cx))
+}
+
 pub enum MaybeStateful<T> {
     Stateful(Stateful<T>),
     NotStateful(T),
@@ -0,0 +1,254 @@
+use gpui::{
+    App, FontWeight, InteractiveElement, IntoElement, ParentElement, RenderOnce,
+    StatefulInteractiveElement, Styled, Window, div, prelude::FluentBuilder, px,
+};
+use serde::Deserialize;
+use tracing::warn;
+
+use super::{
+    components::{
+        button::{ButtonIntent, button},
+        modal::modal,
+    },
+    models::Models,
+    theme::Theme,
+    util::ui_px,
+};
+
+/// The version of the app that's running, without the build suffix of `VERSION_STRING`.
+pub const CURRENT_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// The changelog, bundled with the app. Each release lists what was added, changed and fixed.
+const CHANGELOG: &str = include_str!("../../CHANGELOG.json");
+
+/// The changes made in one version of the app.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Release {
+    pub version: String,
+    #[serde(default)]
+    pub added: Vec<String>,
+    #[serde(default)]
+    pub changed: Vec<String>,
+    #[serde(default)]
+    pub fixed: Vec<String>,
+}
+
+impl Release {
+    fn sections(&self) -> [(&'static str, &[String]); 3] {
+        [
+            ("Added", &self.added),
+            ("Changed", &self.changed),
+            ("Fixed", &self.fixed),
+        ]
+    }
+}
+
+/// Returns every release in the changelog, newest first. Releases with a version that can't be
+/// read are left out.
+pub fn changelog() -> Vec<Release> {
+    let mut releases: Vec<Release> = serde_json::from_str(CHANGELOG).unwrap_or_else(|err| {
+        warn!(?err, "Couldn't read the changelog: {err}");
+        Vec::new()
+    });
+
+    releases.retain(|release| parse_version(&release.version).is_some());
+    releases.sort_by_key(|release| std::cmp::Reverse(parse_version(&release.version)));
+    releases
+}
+
+/// Parses a version like "0.2.0" into its major, minor and patch numbers. Anything after a `-`
+/// or `+` (pre-release and build information) is ignored, and missing numbers count as 0.
+fn parse_version(version: &str) -> Option<[u64; 3]> {
+    let core = version.trim().split(['-', '+']).next()?;
+    let mut parts = [0; 3];
+
+    for (index, part) in core.split('.').enumerate() {
+        *parts.get_mut(index)? = part.parse().ok()?;
+    }
+
+    Some(parts)
+}
+
+/// Returns the releases made after `last_seen` up to and including `current`, newest first.
+/// Nothing is returned on the first run (when no version has been seen yet), when the version
+/// hasn't changed, or after a downgrade.
+pub fn releases_since(
+    releases: &[Release],
+    last_seen: Option<&str>,
+    current: &str,
+) -> Vec<Release> {
+    let (Some(last_seen), Some(current)) =
+        (last_seen.and_then(parse_version), parse_version(current))
+    else {
+        return Vec::new();
+    };
+
+    if last_seen >= current {
+        return Vec::new();
+    }
+
+    releases
+        .iter()
+        .filter(|release| {
+            parse_version(&release.version)
+                .is_some_and(|version| version > last_seen && version <= current)
+        })
+        .cloned()
+        .collect()
+}
+
+/// Shows the whole changelog, as opposed to only the releases since the last version that ran.
+pub fn show_changelog(cx: &mut App) {
+    let whats_new = cx.global::<Models>().whats_new.clone();
+    whats_new.write(cx, Some(changelog()));
+}
+
+fn close(cx: &mut App) {
+    let whats_new = cx.global::<Models>().whats_new.clone();
+    whats_new.write(cx, None);
+}
+
+/// Lists what was added, changed and fixed in each of the given releases.
+#[derive(IntoElement)]
+pub struct WhatsNewDialog {
+    releases: Vec<Release>,
+}
+
+impl RenderOnce for WhatsNewDialog {
+    fn render(self, _: &mut Window, cx: &mut App) -> impl IntoElement {
+        let theme = cx.global::<Theme>();
+
+        modal().on_exit(|_, cx| close(cx)).child(
+            div()
+                .w(px(500.0))
+                .p(px(20.0))
+                .flex()
+                .flex_col()
+                .gap(px(8.0))
+                .child(
+                    div()
+                        .font_weight(FontWeight::BOLD)
+                        .text_size(ui_px(20.0, cx))
+                        .child(format!("What's new in {CURRENT_VERSION}")),
+                )
+                .child(
+                    div()
+                        .id("whats-new-releases")
+                        .max_h(px(400.0))
+                        .overflow_y_scroll()
+                        .flex()
+                        .flex_col()
+                        .gap(px(12.0))
+                        .children(self.releases.iter().map(|release| {
+                            div()
+                                .flex()
+                                .flex_col()
+                                .gap(px(4.0))
+                                .when(self.releases.len() > 1, |this| {
+                                    this.child(
+                                        div()
+                                            .font_weight(FontWeight::SEMIBOLD)
+                                            .child(format!("Version {}", release.version)),
+                                    )
+                                })
+                                .children(
+                                    release
+                                        .sections()
+                                        .into_iter()
+                                        .filter(|(_, entries)| !entries.is_empty())
+                                        .map(|(heading, entries)| {
+                                            div()
+                                                .flex()
+                                                .flex_col()
+                                                .text_sm()
+                                                .child(
+                                                    div()
+                                                        .text_color(theme.text_secondary)
+                                                        .child(heading),
+                                                )
+                                                .children(entries.iter().map(|entry| {
+                                                    div()
+                                                        .flex()
+                                                        .child(div().w(px(16.0)).child("•"))
+                                                        .child(div().flex_1().child(entry.clone()))
+                                                }))
+                                        }),
+                                )
+                        })),
+                )
+                .child(
+                    div().flex().justify_end().mt(px(8.0)).child(
+                        button()
+                            .id("whats-new-close")
+                            .intent(ButtonIntent::Primary)
+                            .child("Close")
+                            .on_click(|_, _, cx| close(cx)),
+                    ),
+                ),
+        )
+    }
+}
+
+pub fn whats_new_dialog(releases: Vec<Release>) -> WhatsNewDialog {
+    WhatsNewDialog { releases }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn release(version: &str) -> Release {
+        Release {
+            version: version.to_string(),
+            added: Vec::new(),
+            changed: Vec::new(),
+            fixed: Vec::new(),
+        }
+    }
+
+    fn versions(releases: Vec<Release>) -> Vec<String> {
+        releases
+            .into_iter()
+            .map(|release| release.version)
+            .collect()
+    }
+
+    #[test]
+    fn parses_versions() {
+        assert_eq!(parse_version("0.2.0"), Some([0, 2, 0]));
+        assert_eq!(parse_version(" 1.4 "), Some([1, 4, 0]));
+        assert_eq!(parse_version("0.3.0-beta.1+abc"), Some([0, 3, 0]));
+        assert_eq!(parse_version("1.2.3.4"), None);
+        assert_eq!(parse_version("next"), None);
+    }
+
+    #[test]
+    fn lists_the_releases_since_the_last_version_seen() {
+        let releases = [
+            release("0.4.0"),
+            release("0.3.0"),
+            release("0.2.1"),
+            release("0.2.0"),
+        ];
+
+        let since = releases_since(&releases, Some("0.2.0"), "0.3.0");
+
+        assert_eq!(versions(since), ["0.3.0", "0.2.1"]);
+    }
+
+    #[test]
+    fn lists_nothing_on_the_first_run_or_after_a_downgrade() {
+        let releases = [release("0.3.0"), release("0.2.0")];
+
+        assert!(releases_since(&releases, None, "0.3.0").is_empty());
+        assert!(releases_since(&releases, Some("0.3.0"), "0.3.0").is_empty());
+        assert!(releases_since(&releases, Some("0.3.0"), "0.2.0").is_empty());
+    }
+
+    #[test]
+    fn bundles_the_current_version() {
+        let releases = changelog();
+
+        assert_eq!(releases[0].version, CURRENT_VERSION);
+    }
+}
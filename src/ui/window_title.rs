@@ -0,0 +1,186 @@
+use std::{cell::RefCell, rc::Rc};
+
+use gpui::{AnyWindowHandle, App, Window};
+
+use crate::{playback::thread::PlaybackState, settings::SettingsGlobal};
+
+use super::models::{Models, PlaybackInfo};
+
+pub const APP_TITLE: &str = "Hummingbird";
+
+/// The longest the now playing part of the window title can be, in characters. Anything longer is
+/// shortened in the middle, so that both the start of the artist and the end of the title are kept.
+const MAX_NOW_PLAYING_LENGTH: usize = 120;
+
+/// Shortens the string to at most `max` characters by replacing its middle with an ellipsis.
+fn ellipsize_middle(value: &str, max: usize) -> String {
+    let length = value.chars().count();
+
+    if length <= max {
+        return value.to_string();
+    }
+
+    // one character is taken up by the ellipsis
+    let tail = (max - 1) / 2;
+    let head = max - 1 - tail;
+
+    let start: String = value.chars().take(head).collect();
+    let end: String = value.chars().skip(length - tail).collect();
+
+    format!("{}…{}", start.trim_end(), end.trim_start())
+}
+
+/// Formats the window title for the given track and playback state, such as
+/// "Artist – Title — Hummingbird". When nothing is playing, the title is just "Hummingbird".
+pub fn format_window_title(
+    artist: Option<&str>,
+    title: Option<&str>,
+    state: PlaybackState,
+) -> String {
+    if state == PlaybackState::Stopped {
+        return APP_TITLE.to_string();
+    }
+
+    let title = title.map(str::trim).filter(|title| !title.is_empty());
+    let artist = artist.map(str::trim).filter(|artist| !artist.is_empty());
+
+    let now_playing = match (artist, title) {
+        (Some(artist), Some(title)) => format!("{artist} – {title}"),
+        (None, Some(title)) => title.to_string(),
+        (Some(artist), None) => format!("{artist} – Unknown Track"),
+        (None, None) => "Unknown Track".to_string(),
+    };
+
+    let now_playing = ellipsize_middle(&now_playing, MAX_NOW_PLAYING_LENGTH);
+
+    match state {
+        PlaybackState::Paused => format!("⏸ {now_playing} — {APP_TITLE}"),
+        _ => format!("{now_playing} — {APP_TITLE}"),
+    }
+}
+
+fn current_window_title(cx: &App) -> String {
+    let enabled = cx
+        .global::<SettingsGlobal>()
+        .model
+        .read(cx)
+        .interface
+        .now_playing_title;
+    let state = *cx.global::<PlaybackInfo>().playback_state.read(cx);
+
+    if !enabled {
+        return APP_TITLE.to_string();
+    }
+
+    let metadata = cx.global::<Models>().metadata.read(cx);
+
+    format_window_title(metadata.artist.as_deref(), metadata.name.as_deref(), state)
+}
+
+/// Keeps the window title in sync with the track that's currently playing.
+pub fn register_window_title_handlers(cx: &mut App, window: &Window) {
+    let handle = window.window_handle();
+    let last_title = Rc::new(RefCell::new(APP_TITLE.to_string()));
+
+    let update = move |cx: &mut App| {
+        let title = current_window_title(cx);
+
+        // metadata and state updates often arrive together - only change the title if it's
+        // actually different
+        if *last_title.borrow() == title {
+            return;
+        }
+
+        if set_window_title(cx, handle, &title) {
+            *last_title.borrow_mut() = title;
+        }
+    };
+
+    let metadata = cx.global::<Models>().metadata.clone();
+    let playback_state = cx.global::<PlaybackInfo>().playback_state.clone();
+    let settings = cx.global::<SettingsGlobal>().model.clone();
+
+    let update = Rc::new(update);
+
+    let update_clone = update.clone();
+    cx.observe(&metadata, move |_, cx| update_clone(cx))
+        .detach();
+
+    let update_clone = update.clone();
+    cx.observe(&playback_state, move |_, cx| update_clone(cx))
+        .detach();
+
+    let update_clone = update.clone();
+    cx.observe(&settings, move |_, cx| update_clone(cx))
+        .detach();
+
+    // the window is still being opened, so the title can't be set right away
+    cx.defer(move |cx| update(cx));
+}
+
+/// Returns false if the title couldn't be set, which happens if the window is already being
+/// updated.
+fn set_window_title(cx: &mut App, handle: AnyWindowHandle, title: &str) -> bool {
+    handle
+        .update(cx, |_, window, _| window.set_window_title(title))
+        .is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shows_the_app_name_when_stopped() {
+        let title = format_window_title(Some("Artist"), Some("Title"), PlaybackState::Stopped);
+
+        assert_eq!(title, APP_TITLE);
+    }
+
+    #[test]
+    fn falls_back_when_metadata_is_missing() {
+        let title = |artist, name| format_window_title(artist, name, PlaybackState::Playing);
+
+        assert_eq!(
+            title(Some("Artist"), Some("Title")),
+            "Artist – Title — Hummingbird"
+        );
+        assert_eq!(title(None, Some("Title")), "Title — Hummingbird");
+        assert_eq!(
+            title(Some("Artist"), None),
+            "Artist – Unknown Track — Hummingbird"
+        );
+        assert_eq!(title(Some("  "), Some("")), "Unknown Track — Hummingbird");
+        assert_eq!(title(None, None), "Unknown Track — Hummingbird");
+    }
+
+    #[test]
+    fn marks_paused_tracks() {
+        let title = format_window_title(Some("Artist"), Some("Title"), PlaybackState::Paused);
+
+        assert_eq!(title, "⏸ Artist – Title — Hummingbird");
+    }
+
+    #[test]
+    fn limits_the_length_of_the_track() {
+        let long = "a".repeat(MAX_NOW_PLAYING_LENGTH * 2);
+        let title = format_window_title(None, Some(&long), PlaybackState::Playing);
+        let now_playing = title.strip_suffix(" — Hummingbird").unwrap();
+
+        assert_eq!(now_playing.chars().count(), MAX_NOW_PLAYING_LENGTH);
+        assert!(now_playing.contains('…'));
+    }
+
+    #[test]
+    fn keeps_both_ends_when_shortening() {
+        assert_eq!(ellipsize_middle("abcdefghij", 10), "abcdefghij");
+        assert_eq!(ellipsize_middle("abcdefghij", 5), "ab…ij");
+        assert_eq!(ellipsize_middle("ab cdefghi", 6), "ab…hi");
+    }
+
+    #[test]
+    fn cuts_multibyte_text_on_character_boundaries() {
+        assert_eq!(ellipsize_middle("日本語のタイトル", 5), "日本…トル");
+        assert_eq!(ellipsize_middle("🎵🎶🎵🎶🎵🎶", 4), "🎵🎶…🎶");
+    }
+}
@@ -1,4 +1,27 @@
-use image::{Pixel, RgbaImage};
+use std::{
+    cmp::Ordering,
+    io::{BufRead, Seek},
+    iter::Peekable,
+    str::Chars,
+};
+
+use image::{ImageReader, Limits, Pixel, RgbaImage};
+
+/// Embedded album art larger than this (in either dimension) is not decoded.
+const MAX_ART_DIMENSION: u32 = 8192;
+/// The most memory decoding a single piece of embedded album art may use.
+const MAX_ART_ALLOC: u64 = 256 * 1024 * 1024;
+
+/// Applies the decoding limits used for all embedded album art to an image reader, so that a
+/// single oversized image can't use huge amounts of memory.
+pub fn limit_art_decoding<R: BufRead + Seek>(reader: &mut ImageReader<R>) {
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(MAX_ART_DIMENSION);
+    limits.max_image_height = Some(MAX_ART_DIMENSION);
+    limits.max_alloc = Some(MAX_ART_ALLOC);
+
+    reader.limits(limits);
+}
 
 pub fn rgb_to_bgr(image: &mut RgbaImage) {
     image.pixels_mut().for_each(|v| {
@@ -7,6 +30,57 @@ pub fn rgb_to_bgr(image: &mut RgbaImage) {
     });
 }
 
+/// Converts a duration in milliseconds to whole seconds, rounded to the nearest second. Used for
+/// durations that are shown or passed on in seconds, so that a track that's 3:59.9 long is shown
+/// as 4:00 rather than 3:59. Positions within a track are rounded down instead.
+pub fn round_millis(millis: u64) -> u64 {
+    (millis + 500) / 1000
+}
+
+/// Compares two strings the way a person would, treating runs of digits as numbers so that
+/// "2 Song" comes before "10 Song". Letters are compared case-insensitively. Strings that only
+/// differ in case or leading zeros are compared as-is, so that the order is always the same.
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let mut a_chars = a.chars().peekable();
+    let mut b_chars = b.chars().peekable();
+
+    loop {
+        let ordering = match (a_chars.peek().copied(), b_chars.peek().copied()) {
+            (None, None) => return a.cmp(b),
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let x = take_number(&mut a_chars);
+                let y = take_number(&mut b_chars);
+
+                // compared as text, so that numbers of any length work
+                let x = x.trim_start_matches('0');
+                let y = y.trim_start_matches('0');
+                x.len().cmp(&y.len()).then_with(|| x.cmp(y))
+            }
+            (Some(x), Some(y)) => {
+                a_chars.next();
+                b_chars.next();
+                x.to_lowercase().cmp(y.to_lowercase())
+            }
+        };
+
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+}
+
+fn take_number(chars: &mut Peekable<Chars>) -> String {
+    let mut number = String::new();
+
+    while let Some(digit) = chars.next_if(char::is_ascii_digit) {
+        number.push(digit);
+    }
+
+    number
+}
+
 macro_rules! make_unknown_error {
     ($from:ty, $to:ty) => {
         impl From<$from> for $to {
@@ -18,3 +92,49 @@ macro_rules! make_unknown_error {
 }
 
 pub(crate) use make_unknown_error;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compares_numbers_by_value() {
+        assert_eq!(natural_cmp("track2", "track10"), Ordering::Less);
+        assert_eq!(natural_cmp("10 Song", "9 Song"), Ordering::Greater);
+        assert_eq!(natural_cmp("track", "track1"), Ordering::Less);
+    }
+
+    #[test]
+    fn ignores_leading_zeros_until_everything_else_is_equal() {
+        assert_eq!(natural_cmp("track02", "track10"), Ordering::Less);
+        assert_eq!(natural_cmp("track002b", "track2a"), Ordering::Greater);
+        assert_eq!(natural_cmp("track02", "track2"), Ordering::Less);
+    }
+
+    #[test]
+    fn ignores_case_until_everything_else_is_equal() {
+        assert_eq!(natural_cmp("apple", "Banana"), Ordering::Less);
+        assert_eq!(natural_cmp("Song", "song"), Ordering::Less);
+        assert_eq!(natural_cmp("song", "song"), Ordering::Equal);
+    }
+
+    #[test]
+    fn compares_numbers_too_long_for_an_integer() {
+        let small = "track99999999999999999999999999";
+        let large = "track100000000000000000000000000";
+
+        assert_eq!(natural_cmp(small, large), Ordering::Less);
+        assert_eq!(natural_cmp(large, small), Ordering::Greater);
+    }
+
+    #[test]
+    fn sorts_file_names() {
+        let mut names = ["10.flac", "2.flac", "1.flac", "Bonus.flac", "a.flac"];
+        names.sort_by(|a, b| natural_cmp(a, b));
+
+        assert_eq!(
+            names,
+            ["1.flac", "2.flac", "10.flac", "a.flac", "Bonus.flac"]
+        );
+    }
+}